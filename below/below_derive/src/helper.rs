@@ -95,6 +95,54 @@ pub fn to_camelcase(snake: &Ident) -> Ident {
     Ident::new(&res, snake.span())
 }
 
+/// Unit of a field guessed from the suffix of its name. For example,
+/// "usage_pct" => "%" and "rbytes_per_sec" => "bytes/s".
+pub fn unit_from_name(name: &Ident) -> Option<&'static str> {
+    let name = name.to_string();
+    if name.ends_with("_per_sec") {
+        Some(if name.contains("bytes") {
+            "bytes/s"
+        } else {
+            "/s"
+        })
+    } else if name.ends_with("_pct") {
+        Some("%")
+    } else if name.ends_with("_bytes") {
+        Some("bytes")
+    } else if name.ends_with("_usecs") || name.ends_with("_usec") {
+        Some("us")
+    } else if name.ends_with("_ms") {
+        Some("ms")
+    } else if name.ends_with("_secs") || name.ends_with("_sec") {
+        Some("s")
+    } else if name.ends_with("_hz") {
+        Some("Hz")
+    } else {
+        None
+    }
+}
+
+/// Doc comment of an item, with its lines joined by spaces.
+pub fn get_doc(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Str(lit),
+                ..
+            })) => Some(lit.value().trim().to_owned()),
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join(" "))
+    }
+}
+
 /// Simplistic implementation of camel case to snake case conversion for ident.
 /// For example, "ThpFaultAlloc" => "thp_fault_alloc".
 pub fn to_snakecase(camel: &Ident) -> Ident {
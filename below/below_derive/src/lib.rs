@@ -109,6 +109,17 @@ pub fn enum_from_str_derive(input: TokenStream) -> TokenStream {
 ///             C(field_id) => self.c.query(field_id),
 ///         }
 ///     }
+///     fn expand_selector(&self, selector: &str) -> Vec<Self::FieldId> {
+///         let mut field_ids = Vec::new();
+///         if selector == "a" || selector == SELECTOR_WILDCARD {
+///             field_ids.push(Self::FieldId::A);
+///         }
+///         ...
+///         if let Some(rest) = /* selector without "c." or "*." */ {
+///             field_ids.extend(self.c.expand_selector(rest).into_iter().map(Self::FieldId::C));
+///         }
+///         field_ids
+///     }
 /// }
 #[proc_macro_derive(Queriable, attributes(queriable))]
 pub fn queriable_derive(input: TokenStream) -> TokenStream {
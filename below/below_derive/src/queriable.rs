@@ -23,10 +23,13 @@ use syn::Field;
 use syn::Ident;
 use syn::Token;
 
+use crate::helper::get_doc;
 use crate::helper::get_metadata;
 use crate::helper::occurrence_error;
 use crate::helper::parse_option;
 use crate::helper::to_camelcase;
+use crate::helper::to_snakecase;
+use crate::helper::unit_from_name;
 
 mod kw {
     use syn::custom_keyword;
@@ -38,6 +41,7 @@ mod kw {
     custom_keyword!(ignore);
     custom_keyword!(subquery);
     custom_keyword!(preferred_name);
+    custom_keyword!(unit);
 }
 
 pub enum StructMeta {
@@ -73,6 +77,10 @@ pub enum FieldMeta {
         kw: kw::preferred_name,
         value: Ident,
     },
+    Unit {
+        kw: kw::unit,
+        value: syn::LitStr,
+    },
 }
 
 impl Parse for FieldMeta {
@@ -87,6 +95,11 @@ impl Parse for FieldMeta {
             let _: Token![=] = input.parse()?;
             let value = input.parse()?;
             Ok(FieldMeta::PreferredName { kw, value })
+        } else if lookahead.peek(kw::unit) {
+            let kw = input.parse()?;
+            let _: Token![=] = input.parse()?;
+            let value = input.parse()?;
+            Ok(FieldMeta::Unit { kw, value })
         } else {
             Err(lookahead.error())
         }
@@ -99,6 +112,7 @@ impl Spanned for FieldMeta {
             FieldMeta::Ignore(kw) => kw.span,
             FieldMeta::Subquery(kw) => kw.span,
             FieldMeta::PreferredName { kw, .. } => kw.span,
+            FieldMeta::Unit { kw, .. } => kw.span,
         }
     }
 }
@@ -137,6 +151,8 @@ struct QueriableFieldProps {
     pub ident: Ident,
     pub variant_name: Ident,
     pub option_type: Option<syn::Type>,
+    pub doc: Option<String>,
+    pub unit: Option<String>,
 }
 
 fn get_queriable_field_props(field: &Field) -> syn::Result<QueriableFieldProps> {
@@ -146,6 +162,8 @@ fn get_queriable_field_props(field: &Field) -> syn::Result<QueriableFieldProps>
     let mut ignore_kw = None;
     let mut subquery_kw = None;
     let mut preferred_name_kw = None;
+    let mut unit = None;
+    let mut unit_kw = None;
     let option_type = parse_option(&field.ty);
     for meta in get_metadata("queriable", &field.attrs)? {
         match meta {
@@ -175,12 +193,20 @@ fn get_queriable_field_props(field: &Field) -> syn::Result<QueriableFieldProps>
                 preferred_name_kw = Some(kw);
                 preferred_name = Some(value.clone());
             }
+            FieldMeta::Unit { value, kw } => {
+                if let Some(fst_kw) = unit_kw {
+                    return Err(occurrence_error(fst_kw, kw, "unit"));
+                }
+                unit_kw = Some(kw);
+                unit = Some(value.value());
+            }
         }
     }
     let ident = field
         .ident
         .clone()
         .ok_or_else(|| syn::Error::new(field.span(), "This macro only support named fields"))?;
+    let unit = unit.or_else(|| unit_from_name(&ident).map(str::to_owned));
     let preferred_name = preferred_name.unwrap_or_else(|| ident.clone());
     let variant_name = to_camelcase(&preferred_name);
     Ok(QueriableFieldProps {
@@ -189,9 +215,18 @@ fn get_queriable_field_props(field: &Field) -> syn::Result<QueriableFieldProps>
         ident,
         variant_name,
         option_type,
+        doc: get_doc(&field.attrs),
+        unit,
     })
 }
 
+fn option_tokens(s: Option<&str>) -> TokenStream {
+    match s {
+        Some(s) => quote! { ::std::option::Option::Some(#s) },
+        None => quote! { ::std::option::Option::None },
+    }
+}
+
 pub fn queriable_derives_impl(input: TokenStream) -> TokenStream {
     quote! {
         #[derive(
@@ -265,6 +300,60 @@ pub fn queriable_derive_impl(ast: &DeriveInput) -> syn::Result<TokenStream> {
         }
     });
 
+    // Fields match their own name or the wildcard. Subqueries match the
+    // rest of the selector against the sub-Model.
+    let expand_selector_stmts = all_field_props.iter().map(|field_props| {
+        let variant_name = &field_props.variant_name;
+        let field_ident = &field_props.ident;
+        let name = to_snakecase(variant_name).to_string();
+        if field_props.subquery.is_some() {
+            let expand = if field_props.option_type.is_some() {
+                quote! {
+                    self.#field_ident
+                        .as_ref()
+                        .map_or_else(::std::vec::Vec::new, |q| q.expand_selector(rest))
+                }
+            } else {
+                quote! { self.#field_ident.expand_selector(rest) }
+            };
+            quote! {
+                if let ::std::option::Option::Some(rest) = selector
+                    .strip_prefix(#name)
+                    .or_else(|| selector.strip_prefix(SELECTOR_WILDCARD))
+                    .and_then(|rest| rest.strip_prefix('.'))
+                {
+                    field_ids.extend(#expand.into_iter().map(Self::FieldId::#variant_name));
+                }
+            }
+        } else {
+            quote! {
+                if selector == #name || selector == SELECTOR_WILDCARD {
+                    field_ids.push(Self::FieldId::#variant_name);
+                }
+            }
+        }
+    });
+
+    // Subqueries take the doc and unit of the field they end at
+    let doc_match_arms = all_field_props.iter().map(|field_props| {
+        let variant_name = &field_props.variant_name;
+        if field_props.subquery.is_some() {
+            quote! { Self::#variant_name(field_id) => field_id.doc(), }
+        } else {
+            let doc = option_tokens(field_props.doc.as_deref());
+            quote! { Self::#variant_name => #doc, }
+        }
+    });
+    let unit_match_arms = all_field_props.iter().map(|field_props| {
+        let variant_name = &field_props.variant_name;
+        if field_props.subquery.is_some() {
+            quote! { Self::#variant_name(field_id) => field_id.unit(), }
+        } else {
+            let unit = option_tokens(field_props.unit.as_deref());
+            quote! { Self::#variant_name => #unit, }
+        }
+    });
+
     Ok(quote! {
         #[derive(
             Clone,
@@ -280,6 +369,18 @@ pub fn queriable_derive_impl(ast: &DeriveInput) -> syn::Result<TokenStream> {
 
         impl FieldId for #field_id_ident {
             type Queriable = #input_ident;
+            fn doc(&self) -> ::std::option::Option<&'static str> {
+                match self {
+                    #(#doc_match_arms)*
+                    _ => unreachable!(),
+                }
+            }
+            fn unit(&self) -> ::std::option::Option<&'static str> {
+                match self {
+                    #(#unit_match_arms)*
+                    _ => unreachable!(),
+                }
+            }
         }
 
         impl Queriable for #input_ident {
@@ -290,6 +391,14 @@ pub fn queriable_derive_impl(ast: &DeriveInput) -> syn::Result<TokenStream> {
                     _ => unreachable!(),
                 }
             }
+            fn expand_selector(
+                &self,
+                selector: &str,
+            ) -> ::std::vec::Vec<Self::FieldId> {
+                let mut field_ids = ::std::vec::Vec::new();
+                #(#expand_selector_stmts)*
+                field_ids
+            }
         }
     })
 }
@@ -66,6 +66,20 @@ mod ioctl {
         59,
         btrfs_ioctl_logical_ino_args
     );
+    nix::ioctl_readwrite!(
+        scrub_progress,
+        BTRFS_IOCTL_MAGIC,
+        29,
+        btrfs_ioctl_scrub_args
+    );
+    nix::ioctl_readwrite!(dev_info, BTRFS_IOCTL_MAGIC, 30, btrfs_ioctl_dev_info_args);
+    nix::ioctl_read!(fs_info, BTRFS_IOCTL_MAGIC, 31, btrfs_ioctl_fs_info_args);
+    nix::ioctl_readwrite!(
+        get_dev_stats,
+        BTRFS_IOCTL_MAGIC,
+        52,
+        btrfs_ioctl_get_dev_stats
+    );
 }
 
 #[repr(C)]
@@ -269,3 +283,48 @@ pub fn find_root_backref(fd: i32, root_id: u64) -> Result<Option<(String, u64)>>
     )?;
     Ok(res)
 }
+
+pub fn fs_info(fd: i32) -> Result<btrfs_ioctl_fs_info_args> {
+    // SAFETY: all-zero is a valid bit pattern of the plain C struct
+    let mut args: btrfs_ioctl_fs_info_args = unsafe { std::mem::zeroed() };
+    unsafe { ioctl::fs_info(fd, &mut args) }.map_err(Error::SysError)?;
+    Ok(args)
+}
+
+/// Device `devid` of the filesystem, None if there is no such device.
+/// Device ids have gaps after devices were removed.
+pub fn dev_info(fd: i32, devid: u64) -> Result<Option<btrfs_ioctl_dev_info_args>> {
+    // SAFETY: all-zero is a valid bit pattern of the plain C struct
+    let mut args: btrfs_ioctl_dev_info_args = unsafe { std::mem::zeroed() };
+    args.devid = devid;
+    match unsafe { ioctl::dev_info(fd, &mut args) } {
+        Ok(_) => Ok(Some(args)),
+        Err(nix::errno::Errno::ENODEV) => Ok(None),
+        Err(err) => Err(Error::SysError(err)),
+    }
+}
+
+/// Error counters of device `devid`, indexed by `btrfs_dev_stat_values`
+pub fn dev_stats(
+    fd: i32,
+    devid: u64,
+) -> Result<[u64; btrfs_dev_stat_values_BTRFS_DEV_STAT_VALUES_MAX as usize]> {
+    // SAFETY: all-zero is a valid bit pattern of the plain C struct
+    let mut args: btrfs_ioctl_get_dev_stats = unsafe { std::mem::zeroed() };
+    args.devid = devid;
+    args.nr_items = btrfs_dev_stat_values_BTRFS_DEV_STAT_VALUES_MAX as u64;
+    unsafe { ioctl::get_dev_stats(fd, &mut args) }.map_err(Error::SysError)?;
+    Ok(args.values)
+}
+
+/// Progress of the scrub running on device `devid`, None if none is
+pub fn scrub_progress(fd: i32, devid: u64) -> Result<Option<btrfs_scrub_progress>> {
+    // SAFETY: all-zero is a valid bit pattern of the plain C struct
+    let mut args: btrfs_ioctl_scrub_args = unsafe { std::mem::zeroed() };
+    args.devid = devid;
+    match unsafe { ioctl::scrub_progress(fd, &mut args) } {
+        Ok(_) => Ok(Some(args.progress)),
+        Err(nix::errno::Errno::ENOTCONN) => Ok(None),
+        Err(err) => Err(Error::SysError(err)),
+    }
+}
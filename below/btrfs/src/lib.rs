@@ -13,6 +13,8 @@
 // limitations under the License.
 
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ffi::CStr;
 use std::fs::File;
 use std::os::unix::io::AsRawFd;
 use std::path::Path;
@@ -59,6 +61,7 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub const DEFAULT_ROOT: &str = "/";
 pub const DEFAULT_SAMPLES: u64 = 100;
 pub const DEFAULT_MIN_PCT: f64 = 0.0;
+pub const DEFAULT_MOUNTS: &str = "/proc/self/mounts";
 
 // The SampleTree structure stores a hierarchical structure
 // of path names that we have some size estimations for. This
@@ -342,9 +345,141 @@ impl BtrfsReader {
         )
     }
 
+    /// Error counters and scrub progress of the devices of every mounted
+    /// btrfs filesystem. Filesystems that cannot be read are skipped.
+    pub fn sample_devices(&self) -> Result<BtrfsDevMap> {
+        let mounts = std::fs::read_to_string(DEFAULT_MOUNTS)
+            .map_err(|e| Error::IoError(DEFAULT_MOUNTS.into(), e))?;
+        let mut devices = BtrfsDevMap::new();
+        let mut seen_fsids = HashSet::new();
+        for mount_point in parse_btrfs_mounts(&mounts) {
+            // A filesystem is mounted as often as it has mounted subvolumes
+            let f = match File::open(&mount_point) {
+                Ok(f) => f,
+                Err(_) => continue,
+            };
+            if let Err(e) =
+                self.sample_fs_devices(f.as_raw_fd(), &mount_point, &mut seen_fsids, &mut devices)
+            {
+                warn!(
+                    self.logger,
+                    "Failed to read btrfs devices of {}: {}", mount_point, e
+                );
+            }
+        }
+        Ok(devices)
+    }
+
+    fn sample_fs_devices(
+        &self,
+        fd: i32,
+        mount_point: &str,
+        seen_fsids: &mut HashSet<[u8; 16]>,
+        devices: &mut BtrfsDevMap,
+    ) -> Result<()> {
+        let fs_info = btrfs_api::fs_info(fd)?;
+        if !seen_fsids.insert(fs_info.fsid) {
+            return Ok(());
+        }
+        for devid in 1..=fs_info.max_id {
+            let dev_info = match btrfs_api::dev_info(fd, devid)? {
+                Some(dev_info) => dev_info,
+                None => continue,
+            };
+            let path = CStr::from_bytes_until_nul(&dev_info.path)
+                .map_or_else(|_| devid.to_string(), |p| p.to_string_lossy().into_owned());
+            let stats = btrfs_api::dev_stats(fd, devid)?;
+            let scrub = match btrfs_api::scrub_progress(fd, devid) {
+                Ok(scrub) => scrub.map(|p| BtrfsScrubStat {
+                    data_bytes_scrubbed: p.data_bytes_scrubbed,
+                    tree_bytes_scrubbed: p.tree_bytes_scrubbed,
+                    read_errors: p.read_errors,
+                    csum_errors: p.csum_errors,
+                    verify_errors: p.verify_errors,
+                    corrected_errors: p.corrected_errors,
+                    uncorrectable_errors: p.uncorrectable_errors,
+                    last_physical: p.last_physical,
+                }),
+                Err(e) => {
+                    warn!(
+                        self.logger,
+                        "Failed to read scrub progress of {}: {}", path, e
+                    );
+                    None
+                }
+            };
+            let stat = |idx: btrfs_dev_stat_values| Some(stats[idx as usize]);
+            devices.insert(
+                path.clone(),
+                BtrfsDevStat {
+                    path: Some(path),
+                    mount_point: Some(mount_point.to_owned()),
+                    fsid: Some(format_fsid(&fs_info.fsid)),
+                    devid: Some(devid),
+                    total_bytes: Some(dev_info.total_bytes),
+                    write_errs: stat(btrfs_dev_stat_values_BTRFS_DEV_STAT_WRITE_ERRS),
+                    read_errs: stat(btrfs_dev_stat_values_BTRFS_DEV_STAT_READ_ERRS),
+                    flush_errs: stat(btrfs_dev_stat_values_BTRFS_DEV_STAT_FLUSH_ERRS),
+                    corruption_errs: stat(btrfs_dev_stat_values_BTRFS_DEV_STAT_CORRUPTION_ERRS),
+                    generation_errs: stat(btrfs_dev_stat_values_BTRFS_DEV_STAT_GENERATION_ERRS),
+                    scrub,
+                },
+            );
+        }
+        Ok(())
+    }
+
     fn io_error<P: AsRef<Path>>(&self, file_name: P, e: std::io::Error) -> Error {
         let mut p = self.path.clone();
         p.push(file_name);
         Error::IoError(p, e)
     }
 }
+
+/// Mount points of the btrfs filesystems in `mounts`, which is in the format
+/// of /proc/self/mounts.
+fn parse_btrfs_mounts(mounts: &str) -> Vec<String> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let mount_point = fields.nth(1)?;
+            (fields.next()? == "btrfs").then(|| unescape_mount_path(mount_point))
+        })
+        .collect()
+}
+
+/// Undo the octal escaping of spaces, tabs, newlines and backslashes in
+/// mount paths.
+fn unescape_mount_path(path: &str) -> String {
+    let mut unescaped = Vec::with_capacity(path.len());
+    let mut bytes = path.as_bytes();
+    while let Some((&b, rest)) = bytes.split_first() {
+        match rest.get(..3).map(std::str::from_utf8) {
+            Some(Ok(octal)) if b == b'\\' => match u8::from_str_radix(octal, 8) {
+                Ok(c) => {
+                    unescaped.push(c);
+                    bytes = &rest[3..];
+                    continue;
+                }
+                Err(_) => unescaped.push(b),
+            },
+            _ => unescaped.push(b),
+        }
+        bytes = rest;
+    }
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+/// Format a filesystem id like a UUID, as btrfs-progs and sysfs do
+fn format_fsid(fsid: &[u8; 16]) -> String {
+    let hex: Vec<String> = fsid.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        hex[..4].concat(),
+        hex[4..6].concat(),
+        hex[6..8].concat(),
+        hex[8..10].concat(),
+        hex[10..].concat()
+    )
+}
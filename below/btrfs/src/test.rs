@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::format_fsid;
+use crate::parse_btrfs_mounts;
 use crate::SampleTree;
 
 #[test]
@@ -36,3 +38,25 @@ fn sample_tree_add_path_test() {
     assert_eq!(st.children["path"].total, 1);
     assert_eq!(st.children["another"].total, 1);
 }
+
+#[test]
+fn parse_btrfs_mounts_test() {
+    let mounts = "sysfs /sys sysfs rw,nosuid,nodev,noexec,relatime 0 0\n\
+        /dev/vda2 / btrfs rw,relatime,subvolid=256,subvol=/root 0 0\n\
+        tmpfs /tmp tmpfs rw,nosuid,nodev 0 0\n\
+        /dev/vdb /mnt/my\\040data btrfs rw,relatime,subvolid=5,subvol=/ 0 0\n";
+    assert_eq!(
+        parse_btrfs_mounts(mounts),
+        vec!["/".to_owned(), "/mnt/my data".to_owned()]
+    );
+    assert!(parse_btrfs_mounts("").is_empty());
+}
+
+#[test]
+fn format_fsid_test() {
+    let fsid = [
+        0x3c, 0x1b, 0x9a, 0x0e, 0x5d, 0x27, 0x4f, 0x61, 0x8a, 0x4b, 0x02, 0x9c, 0xde, 0x11, 0x70,
+        0xf3,
+    ];
+    assert_eq!(format_fsid(&fsid), "3c1b9a0e-5d27-4f61-8a4b-029cde1170f3");
+}
@@ -25,3 +25,40 @@ pub struct BtrfsStat {
 }
 
 pub type BtrfsMap = BTreeMap<String, BtrfsStat>;
+
+/// A device of a mounted btrfs filesystem
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct BtrfsDevStat {
+    /// Path of the block device
+    pub path: Option<String>,
+    /// Where the filesystem is mounted, the first mount if several
+    pub mount_point: Option<String>,
+    pub fsid: Option<String>,
+    pub devid: Option<u64>,
+    pub total_bytes: Option<u64>,
+    /// Cumulative error counters, kept on disk across mounts
+    pub write_errs: Option<u64>,
+    pub read_errs: Option<u64>,
+    pub flush_errs: Option<u64>,
+    pub corruption_errs: Option<u64>,
+    pub generation_errs: Option<u64>,
+    /// None unless a scrub is running on the device
+    pub scrub: Option<BtrfsScrubStat>,
+}
+
+/// Progress of a running scrub on a device
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct BtrfsScrubStat {
+    pub data_bytes_scrubbed: u64,
+    pub tree_bytes_scrubbed: u64,
+    pub read_errors: u64,
+    pub csum_errors: u64,
+    pub verify_errors: u64,
+    pub corrected_errors: u64,
+    pub uncorrectable_errors: u64,
+    /// Physical offset on the device scrubbed up to
+    pub last_physical: u64,
+}
+
+/// Keyed by device path
+pub type BtrfsDevMap = BTreeMap<String, BtrfsDevStat>;
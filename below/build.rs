@@ -17,20 +17,45 @@ use std::path::PathBuf;
 
 use libbpf_cargo::SkeletonBuilder;
 
-const SRC: &str = "./src/bpf/exitstat.bpf.c";
+const PROGS: &[&str] = &["exitstat", "fileio", "procnet"];
+
+/// Architecture name of `target_arch` as used by the __TARGET_ARCH_*
+/// defines of bpf_tracing.h
+fn bpf_target_arch(target_arch: &str) -> &str {
+    match target_arch {
+        "x86_64" => "x86",
+        "aarch64" => "arm64",
+        "powerpc64" => "powerpc",
+        "s390x" => "s390",
+        "riscv64" => "riscv",
+        "loongarch64" => "loongarch",
+        "sparc64" => "sparc",
+        "mips64" => "mips",
+        arch => arch,
+    }
+}
 
 fn main() {
-    let mut out =
+    let out_dir =
         PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR must be set in build script"));
-    out.push("exitstat.skel.rs");
+    // Of the binary built, which is not the host's when cross compiling
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH")
+        .expect("CARGO_CFG_TARGET_ARCH must be set in build script");
 
-    let mut builder = SkeletonBuilder::new();
-    builder.source(SRC);
-    if let Some(clang) = option_env!("CLANG") {
-        builder.clang(clang);
+    for prog in PROGS {
+        let src = format!("./src/bpf/{}.bpf.c", prog);
+        let mut builder = SkeletonBuilder::new();
+        builder
+            .source(&src)
+            .clang_args([format!("-D__TARGET_ARCH_{}", bpf_target_arch(&target_arch))]);
+        if let Some(clang) = option_env!("CLANG") {
+            builder.clang(clang);
+        }
+        builder
+            .build_and_generate(out_dir.join(format!("{}.skel.rs", prog)))
+            .unwrap();
+        println!("cargo:rerun-if-changed={}", src);
     }
-    builder.build_and_generate(out).unwrap();
-    println!("cargo:rerun-if-changed={}", SRC);
 
     #[cfg(all(feature = "no-vendor", feature = "default"))]
     compile_error!(
@@ -17,14 +17,17 @@ use std::cell::RefCell;
 use std::cell::RefMut;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
+use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
 use std::io::ErrorKind;
+use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
+use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::Mutex;
 
 use nix::sys::statfs::fstatfs;
 use nix::sys::statfs::CGROUP2_SUPER_MAGIC;
@@ -62,7 +65,118 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub struct CgroupReader {
     relative_path: PathBuf,
     dir: Dir,
-    buffer: RefCell<Vec<u8>>,
+    /// Shared with the readers of child cgroups, so that reading a tree
+    /// allocates it once
+    buffer: Rc<RefCell<Vec<u8>>>,
+    limit_cache: Option<LimitCache>,
+}
+
+/// Files that only change when written to, e.g. limits
+const LIMIT_FILES: [&str; 12] = [
+    "cgroup.subtree_control",
+    "cpu.max",
+    "cpu.weight",
+    "cpuset.cpus",
+    "cpuset.mems",
+    "memory.high",
+    "memory.low",
+    "memory.max",
+    "memory.min",
+    "memory.swap.max",
+    "memory.zswap.max",
+    "pids.max",
+];
+
+struct CachedFile {
+    content: String,
+    /// Sample from which the file is read again
+    refresh_at: u64,
+    used: bool,
+}
+
+#[derive(Default)]
+struct LimitCacheState {
+    files: HashMap<PathBuf, CachedFile>,
+    /// Number of samples finished so far
+    sample: u64,
+}
+
+/// Content of limit files by path, to skip reading them on most samples.
+/// kernfs does not update the mtime of cgroup files when they are written,
+/// so a cached file is read again every `refresh_samples` samples instead,
+/// and a changed limit shows up after at most that many samples. Clones
+/// share the cache.
+#[derive(Clone)]
+pub struct LimitCache {
+    state: Arc<Mutex<LimitCacheState>>,
+    refresh_samples: u64,
+}
+
+impl LimitCache {
+    /// Cache reading each limit file once every `refresh_samples` samples,
+    /// at least 1.
+    pub fn new(refresh_samples: u64) -> LimitCache {
+        LimitCache {
+            state: Default::default(),
+            refresh_samples: refresh_samples.max(1),
+        }
+    }
+
+    /// Copy the content of `path` into `buffer` if it is cached and not due
+    /// to be read again
+    fn lookup(&self, path: &Path, buffer: &mut Vec<u8>) -> bool {
+        let mut state = self.state.lock().expect("tried to acquire poisoned lock");
+        let sample = state.sample;
+        match state.files.get_mut(path) {
+            Some(file) if sample < file.refresh_at => {
+                file.used = true;
+                buffer.clear();
+                buffer.extend_from_slice(file.content.as_bytes());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn insert(&self, path: PathBuf, content: &str) {
+        let mut state = self.state.lock().expect("tried to acquire poisoned lock");
+        let refresh_at = if state.files.contains_key(&path) {
+            state.sample + self.refresh_samples
+        } else {
+            // Spread the files over the next samples, so that they are not
+            // all read again on the same one
+            state.sample + 1 + state.files.len() as u64 % self.refresh_samples
+        };
+        state.files.insert(
+            path,
+            CachedFile {
+                content: content.to_owned(),
+                refresh_at,
+                used: true,
+            },
+        );
+    }
+
+    /// Drop the files not read since the previous call, e.g. of removed
+    /// cgroups, and count the sample. Meant to be called after reading each
+    /// tree.
+    pub fn finish_sample(&self) {
+        let mut state = self.state.lock().expect("tried to acquire poisoned lock");
+        state.sample += 1;
+        state.files.retain(|_, file| std::mem::take(&mut file.used));
+    }
+
+    pub fn len(&self) -> usize {
+        self.state
+            .lock()
+            .expect("tried to acquire poisoned lock")
+            .files
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 fn parse_integer_or_max(s: &str) -> std::result::Result<i64, String> {
@@ -227,6 +341,28 @@ macro_rules! parse_and_set_fields {
     )
 }
 
+/// Translate a cgroup path read from /proc/<pid>/cgroup inside a cgroup
+/// namespace, whose root is `ns_root` as returned by `find_cgroup_ns_root`,
+/// into a path from the cgroup root. Paths of processes outside of the
+/// namespace are relative to its root and start with "/..". Returns None if
+/// the path is not absolute or is outside of the cgroup root.
+pub fn resolve_cgroup_ns_path(ns_root: &Path, path: &str) -> Option<String> {
+    let mut parts = ns_root
+        .iter()
+        .map(|part| part.to_str())
+        .collect::<Option<Vec<_>>>()?;
+    for part in path.strip_prefix('/')?.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            _ => parts.push(part),
+        }
+    }
+    Some(format!("/{}", parts.join("/")))
+}
+
 impl CgroupReader {
     pub fn new(root: PathBuf) -> Result<CgroupReader> {
         CgroupReader::new_with_relative_path(root, PathBuf::from(OsStr::new("")))
@@ -236,6 +372,12 @@ impl CgroupReader {
         CgroupReader::new_with_relative_path_inner(root, relative_path, true)
     }
 
+    /// Same as `new`, but does not check that `root` is on cgroup2. For
+    /// reading copies of cgroup trees, e.g. in benchmarks.
+    pub fn new_unchecked(root: PathBuf) -> Result<CgroupReader> {
+        CgroupReader::new_with_relative_path_inner(root, PathBuf::from(OsStr::new("")), false)
+    }
+
     fn new_with_relative_path_inner(
         root: PathBuf,
         relative_path: PathBuf,
@@ -268,10 +410,27 @@ impl CgroupReader {
         Ok(CgroupReader {
             relative_path,
             dir,
-            buffer: RefCell::new(Vec::new()),
+            buffer: Rc::new(RefCell::new(Vec::new())),
+            limit_cache: None,
         })
     }
 
+    /// Read the limits of this cgroup and its descendants through `cache`,
+    /// see `LimitCache`
+    pub fn set_limit_cache(&mut self, cache: LimitCache) {
+        self.limit_cache = Some(cache);
+    }
+
+    /// Reader of the cgroup in `dir`, a child of this one
+    fn child_reader(&self, relative_path: PathBuf, dir: Dir) -> CgroupReader {
+        CgroupReader {
+            relative_path,
+            dir,
+            buffer: Rc::clone(&self.buffer),
+            limit_cache: self.limit_cache.clone(),
+        }
+    }
+
     pub fn root() -> Result<CgroupReader> {
         CgroupReader::new(Path::new(DEFAULT_CG_ROOT).to_path_buf())
     }
@@ -287,10 +446,44 @@ impl CgroupReader {
         file_name: impl AsRef<Path>,
         file: &File,
     ) -> Result<RefMut<'_, str>> {
-        util::read_kern_file_to_internal_buffer(&self.buffer, file)
+        util::pread_kern_file_to_internal_buffer(&self.buffer, file)
             .map_err(|e| self.io_error(file_name.as_ref(), e))
     }
 
+    /// Open and read `file_name`, through the limit cache if set and the
+    /// file is a limit
+    fn read_file(&self, file_name: &str) -> Result<RefMut<'_, str>> {
+        match &self.limit_cache {
+            Some(cache) if LIMIT_FILES.contains(&file_name) => {
+                self.read_limit_file(cache, file_name)
+            }
+            _ => self.open_and_read_file(file_name),
+        }
+    }
+
+    fn open_and_read_file(&self, file_name: &str) -> Result<RefMut<'_, str>> {
+        let file = self
+            .dir
+            .open_file(file_name)
+            .map_err(|e| self.io_error(file_name, e))?;
+        self.read_file_to_str(file_name, &file)
+    }
+
+    fn read_limit_file(&self, cache: &LimitCache, file_name: &str) -> Result<RefMut<'_, str>> {
+        let path = self.relative_path.join(file_name);
+        {
+            let mut buffer = self.buffer.borrow_mut();
+            if cache.lookup(&path, &mut buffer) {
+                return Ok(RefMut::map(buffer, |vec| {
+                    std::str::from_utf8_mut(vec).expect("Cached content is a str")
+                }));
+            }
+        }
+        let content = self.open_and_read_file(file_name)?;
+        cache.insert(path, &content);
+        Ok(content)
+    }
+
     pub fn read_inode_number(&self) -> Result<u64> {
         let meta = self
             .dir
@@ -302,11 +495,7 @@ impl CgroupReader {
     /// Read a value from a file that has a single line. If the file is empty,
     /// the value will be derived from an empty string.
     fn read_empty_or_singleline_file<T: FromStr>(&self, file_name: &str) -> Result<T> {
-        let file = self
-            .dir
-            .open_file(file_name)
-            .map_err(|e| self.io_error(file_name, e))?;
-        let content = self.read_file_to_str(file_name, &file)?;
+        let content = self.read_file(file_name)?;
         let line = content.lines().next().unwrap_or("");
         line.parse::<T>()
             .map_err(move |_| self.unexpected_line(file_name, line.to_string()))
@@ -315,11 +504,7 @@ impl CgroupReader {
     /// Read a value from a file that has a single line. If the file is empty,
     /// InvalidFileFormat is returned.
     fn read_singleline_file<T: FromStr>(&self, file_name: &str) -> Result<T> {
-        let file = self
-            .dir
-            .open_file(file_name)
-            .map_err(|e| self.io_error(file_name, e))?;
-        let content = self.read_file_to_str(file_name, &file)?;
+        let content = self.read_file(file_name)?;
         if let Some(line) = content.lines().next() {
             return line
                 .parse::<T>()
@@ -432,12 +617,53 @@ impl CgroupReader {
         IoStat::read(self, "io.stat")
     }
 
+    /// Read io.latency - returning the latency target of each device that
+    /// has one set
+    pub fn read_io_latency(&self) -> Result<BTreeMap<String, IoLatency>> {
+        IoLatency::read(self, "io.latency")
+    }
+
     /// Read memory.stat - returning assorted memory consumption
     /// statistics
     pub fn read_memory_stat(&self) -> Result<MemoryStat> {
         MemoryStat::read(self)
     }
 
+    /// Read hugetlb.<size>.current and hugetlb.<size>.max of every huge page
+    /// size, keyed by size, e.g. "2MB". Returns a NotFound IoError without
+    /// the hugetlb controller.
+    pub fn read_hugetlb_stat(&self) -> Result<BTreeMap<String, HugetlbStat>> {
+        let mut stats = BTreeMap::new();
+        for entry in self.dir.list_dir(".").map_err(|e| self.io_error("", e))? {
+            let entry = entry.map_err(|e| self.io_error("", e))?;
+            let file_name = entry.file_name().to_string_lossy();
+            // Sizes have no dots, unlike e.g. hugetlb.2MB.rsvd.current
+            let size = match file_name
+                .strip_prefix("hugetlb.")
+                .and_then(|name| name.strip_suffix(".current"))
+            {
+                Some(size) if !size.contains('.') => size.to_owned(),
+                _ => continue,
+            };
+            let stat =
+                HugetlbStat {
+                    current: Some(self.read_singleline_file(&format!("hugetlb.{}.current", size))?),
+                    max: Some(self.read_singleline_integer_or_max_stat_file(&format!(
+                        "hugetlb.{}.max",
+                        size
+                    ))?),
+                };
+            stats.insert(size, stat);
+        }
+        if stats.is_empty() {
+            return Err(self.io_error(
+                "hugetlb.<size>.current",
+                std::io::Error::from(ErrorKind::NotFound),
+            ));
+        }
+        Ok(stats)
+    }
+
     pub fn read_memory_events(&self) -> Result<MemoryEvents> {
         MemoryEvents::read(self)
     }
@@ -450,6 +676,13 @@ impl CgroupReader {
         CgroupStat::read(self)
     }
 
+    /// Read cgroup.pressure, whether PSI accounting is enabled for the
+    /// cgroup. Pressure files of a cgroup with it disabled are not readable.
+    pub fn read_cgroup_pressure(&self) -> Result<bool> {
+        self.read_singleline_file::<u8>("cgroup.pressure")
+            .map(|enabled| enabled != 0)
+    }
+
     /// Read cpu.weight
     pub fn read_cpu_weight(&self) -> Result<u32> {
         self.read_singleline_file::<u32>("cpu.weight")
@@ -501,12 +734,31 @@ impl CgroupReader {
         FullPressureSupported
     );
 
-    /// Read all pressure metrics
+    /// Read irq.pressure, which only has a full line
+    pub fn read_irq_pressure(&self) -> Result<IrqPressure> {
+        let file_name = "irq.pressure";
+        let mut pressure = PressureMetrics::read(self, file_name)?;
+        Ok(IrqPressure {
+            full: pressure
+                .remove("full")
+                .ok_or_else(|| self.invalid_file_format(file_name))?,
+        })
+    }
+
+    /// Read all pressure metrics. IRQ pressure is left out if the kernel
+    /// does not have it.
     pub fn read_pressure(&self) -> Result<Pressure> {
+        let irq = match self.read_irq_pressure() {
+            Ok(irq) => Some(irq),
+            Err(Error::IoError(_, e)) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(Error::PressureNotSupported(_)) => None,
+            Err(e) => return Err(e),
+        };
         Ok(Pressure {
             cpu: self.read_cpu_pressure()?,
             io: self.read_io_pressure()?,
             memory: self.read_memory_pressure()?,
+            irq,
         })
     }
 
@@ -515,11 +767,7 @@ impl CgroupReader {
     pub fn read_memory_numa_stat(&self) -> Result<BTreeMap<u32, MemoryNumaStat>> {
         let mut s: BTreeMap<u32, MemoryNumaStat> = BTreeMap::new();
         let file_name = "memory.numa_stat";
-        let file = self
-            .dir
-            .open_file(file_name)
-            .map_err(|e| self.io_error(file_name, e))?;
-        let content = self.read_file_to_str(file_name, &file)?;
+        let content = self.read_file(file_name)?;
         for line in content.lines() {
             let items = line.split_ascii_whitespace().collect::<Vec<_>>();
             // Need to have at least the field name + at least one N0=val item
@@ -601,16 +849,65 @@ impl CgroupReader {
                     };
                     let mut relative_path = self.relative_path.clone();
                     relative_path.push(entry.file_name());
-                    Some(CgroupReader {
-                        relative_path,
-                        dir,
-                        buffer: RefCell::new(Vec::new()),
-                    })
+                    Some(self.child_reader(relative_path, dir))
                 }
                 _ => None,
             }))
     }
 
+    /// Read cgroup.procs
+    pub fn read_cgroup_procs(&self) -> Result<Vec<u32>> {
+        let file_name = "cgroup.procs";
+        let content = self.read_file(file_name)?;
+        content
+            .lines()
+            .map(|line| {
+                line.parse::<u32>()
+                    .map_err(|_| self.unexpected_line(file_name, line.to_string()))
+            })
+            .collect()
+    }
+
+    fn contains_pid(&self, pid: u32) -> bool {
+        matches!(self.read_cgroup_procs(), Ok(procs) if procs.contains(&pid))
+    }
+
+    fn find_pid_cgroup(&self, pid: u32, suffix: &Path) -> Option<PathBuf> {
+        if self.relative_path.ends_with(suffix) && self.contains_pid(pid) {
+            return Some(self.relative_path.clone());
+        }
+        self.child_cgroup_iter()
+            .ok()?
+            .find_map(|child| child.find_pid_cgroup(pid, suffix))
+    }
+
+    /// Locate the root of a cgroup namespace in the hierarchy under this
+    /// cgroup. `pid` is a process in the namespace and `ns_path` its cgroup
+    /// path as read from /proc/<pid>/cgroup inside the namespace. The
+    /// returned path is relative to the cgroup root like `name()`, and is
+    /// empty if paths from the namespace can be used as-is.
+    pub fn find_cgroup_ns_root(&self, pid: u32, ns_path: &str) -> Option<PathBuf> {
+        let suffix = Path::new(ns_path.trim_start_matches('/'));
+        if !suffix
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return None;
+        }
+        // Fast path: there is no namespace, or its root is what is mounted
+        let direct = self
+            .dir
+            .sub_dir(suffix.join("."))
+            .ok()
+            .map(|dir| self.child_reader(self.relative_path.join(suffix), dir));
+        let found = match direct {
+            Some(reader) if reader.contains_pid(pid) => reader.relative_path,
+            _ => self.find_pid_cgroup(pid, suffix)?,
+        };
+        let depth = found.components().count() - suffix.components().count();
+        Some(found.components().take(depth).collect())
+    }
+
     fn invalid_file_format<P: AsRef<Path>>(&self, file_name: P) -> Error {
         let mut p = self.relative_path.clone();
         p.push(file_name);
@@ -651,17 +948,15 @@ macro_rules! key_values_format {
             fn read(r: &CgroupReader) -> Result<$struct> {
                 let mut s = $struct::default();
                 let file_name = stringify!($file);
-                let file = r.dir.open_file(file_name).map_err(|e| r.io_error(file_name, e))?;
-                let buf_reader = BufReader::new(file);
-                for line in buf_reader.lines() {
-                    let line = line.map_err(|e| r.io_error(file_name, e))?;
+                let content = r.read_file(file_name)?;
+                for line in content.lines() {
                     let mut items = line.split_ascii_whitespace();
-                    let key = items.next().ok_or_else(|| r.unexpected_line(file_name, line.clone()))?;
-                    let val_str = items.next().ok_or_else(|| r.unexpected_line(file_name, line.clone()))?;
+                    let key = items.next().ok_or_else(|| r.unexpected_line(file_name, line.to_string()))?;
+                    let val_str = items.next().ok_or_else(|| r.unexpected_line(file_name, line.to_string()))?;
                     if items.next().is_some() {
-                        return Err(r.unexpected_line(file_name, line));
+                        return Err(r.unexpected_line(file_name, line.to_string()));
                     }
-                    let val = val_str.parse::<_>().map_err(|_| r.unexpected_line(file_name, line.clone()))?;
+                    let val = val_str.parse::<_>().map_err(|_| r.unexpected_line(file_name, line.to_string()))?;
                     match key {
                         $(stringify!($field) => s.$field = Some(val),)*
                         _ => (),
@@ -700,6 +995,8 @@ key_values_format!(MemoryStat; memory.stat; [
     file_dirty,
     file_writeback,
     anon_thp,
+    file_thp,
+    shmem_thp,
     inactive_anon,
     active_anon,
     inactive_file,
@@ -762,20 +1059,19 @@ macro_rules! name_key_equal_value_format {
             fn read<P: AsRef<Path> + AsPath + Clone>(r: &CgroupReader, file_name: P) -> Result<BTreeMap<String, $struct>> {
                 let mut map = BTreeMap::new();
                 let file = r.dir.open_file(file_name.clone()).map_err(|e| r.io_error(file_name.clone(), e))?;
-                let buf_reader = BufReader::new(file);
-                for line in buf_reader.lines() {
-                    let line = line.map_err(|e| {
-                        // Capture a different error if pressure
-                        // metrics aren't supported
-                        if $allows_pressure_eopnotsupp.0 {
-                            if let Some(errno) = e.raw_os_error() {
-                                if errno == /* EOPNOTSUPP */ 95 {
-                                    return r.pressure_not_supported(file_name.clone());
-                                }
+                let content = util::pread_kern_file_to_internal_buffer(&r.buffer, &file).map_err(|e| {
+                    // Capture a different error if pressure
+                    // metrics aren't supported
+                    if $allows_pressure_eopnotsupp.0 {
+                        if let Some(errno) = e.raw_os_error() {
+                            if errno == /* EOPNOTSUPP */ 95 {
+                                return r.pressure_not_supported(file_name.clone());
                             }
                         }
-                        r.io_error(file_name.clone(), e)
-                    })?;
+                    }
+                    r.io_error(file_name.clone(), e)
+                })?;
+                for line in content.lines() {
                     // as an example, io.stat looks like:
                     // 253:0 rbytes=531745786880 wbytes=1623798909952 ...
                     let mut items = line.split_ascii_whitespace();
@@ -790,7 +1086,7 @@ macro_rules! name_key_equal_value_format {
                             parse_and_set_fields!(
                                 s;
                                 key.as_ref();
-                                value.parse().map_err(|_| r.unexpected_line(file_name.clone(), line.clone()))?;
+                                value.parse().map_err(|_| r.unexpected_line(file_name.clone(), line.to_string()))?;
                                 [ $($field,)* ]
                             )
                         }
@@ -821,6 +1117,11 @@ name_key_equal_value_format!(IoStat; AllowsEmpty(true); AllowsPressureEOpNotSupp
     cost_wait,
     cost_indebt,
     cost_indelay,
+    avg_lat,
+]);
+
+name_key_equal_value_format!(IoLatency; AllowsEmpty(true); AllowsPressureEOpNotSupp(false); [
+    target,
 ]);
 
 name_key_equal_value_format!(PressureMetrics; AllowsEmpty(false); AllowsPressureEOpNotSupp(true); [
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::ffi::OsStr;
 use std::fs::File;
@@ -21,13 +22,18 @@ use std::path::Path;
 use std::path::PathBuf;
 use std::str::FromStr;
 
+use below_testutil::CgroupFixture;
+use below_testutil::CgroupfsBuilder;
 use paste::paste;
 use tempfile::TempDir;
 
+use crate::resolve_cgroup_ns_path;
 use crate::CgroupReader;
 use crate::CpuMax;
 use crate::Cpuset;
 use crate::Error;
+use crate::HugetlbStat;
+use crate::LimitCache;
 use crate::MemNodes;
 use crate::MemoryNumaStat;
 
@@ -150,6 +156,9 @@ singleline_integer_or_max_test!(read_memory_zswap_max, "memory.zswap.max");
 test_success!(read_pids_current, "pids.current", b"10000\n", 10000);
 test_success!(read_cpu_weight, "cpu.weight", b"10000\n", 10000);
 test_failure!(read_cpu_weight, "cpu.weight", b"5000000000\n");
+test_success!(read_cgroup_pressure, "cgroup.pressure", b"1\n", true, 1);
+test_success!(read_cgroup_pressure, "cgroup.pressure", b"0\n", false, 2);
+test_failure!(read_cgroup_pressure, "cgroup.pressure", b"on\n");
 test_success!(
     read_cpu_max,
     "cpu.max",
@@ -488,6 +497,23 @@ fn test_io_stat_empty_file() {
     assert!(val.is_empty());
 }
 
+#[test]
+fn test_io_latency_success() {
+    let cgroup = TestCgroup::new();
+    cgroup.create_file_with_content("io.stat", b"253:0 rbytes=531 wbytes=162379 rios=61 wios=81 dbytes=0 dios=0 depth=max avg_lat=3520 win=100\n");
+    cgroup.create_file_with_content("io.latency", b"253:0 target=5000\n");
+
+    let cgroup_reader = cgroup.get_reader();
+    let val = cgroup_reader
+        .read_io_stat()
+        .expect("Failed to read io.stat");
+    assert_eq!(val["253:0"].avg_lat, Some(3520));
+    let val = cgroup_reader
+        .read_io_latency()
+        .expect("Failed to read io.latency");
+    assert_eq!(val["253:0"].target, Some(5000));
+}
+
 #[test]
 fn test_cpu_pressure_success() {
     let cgroup = TestCgroup::new();
@@ -616,6 +642,49 @@ fn test_memory_pressure_empty_file() {
     }
 }
 
+#[test]
+fn test_irq_pressure_success() {
+    let cgroup = TestCgroup::new();
+    cgroup.create_file_with_content(
+        "irq.pressure",
+        b"full avg10=1.50 avg60=0.00 avg300=0.00 total=61917\n",
+    );
+
+    let cgroup_reader = cgroup.get_reader();
+    let val = cgroup_reader
+        .read_irq_pressure()
+        .expect("Failed to read irq.pressure");
+    assert_eq!(val.full.avg10, Some(1.5));
+    assert_eq!(
+        val.full.total.expect("Failed to populate total field"),
+        61917
+    );
+}
+
+#[test]
+fn test_pressure_without_irq() {
+    let cgroup = TestCgroup::new();
+    let content = b"some avg10=0.00 avg60=0.00 avg300=0.00 total=619176290\nfull avg10=0.00 avg60=0.00 avg300=0.00 total=61917\n";
+    cgroup.create_file_with_content("cpu.pressure", content);
+    cgroup.create_file_with_content("io.pressure", content);
+    cgroup.create_file_with_content("memory.pressure", content);
+
+    let cgroup_reader = cgroup.get_reader();
+    let val = cgroup_reader
+        .read_pressure()
+        .expect("Failed to read pressure");
+    assert_eq!(val.irq, None);
+
+    cgroup.create_file_with_content(
+        "irq.pressure",
+        b"full avg10=0.00 avg60=0.00 avg300=0.00 total=42\n",
+    );
+    let val = cgroup_reader
+        .read_pressure()
+        .expect("Failed to read pressure");
+    assert_eq!(val.irq.and_then(|irq| irq.full.total), Some(42));
+}
+
 #[test]
 fn test_child_cgroup_iter() {
     let root = TestCgroup::new();
@@ -677,6 +746,54 @@ fn test_child_cgroup_iter_with_file() {
     assert_eq!(reported_children, children,);
 }
 
+#[test]
+fn test_child_cgroup_iter_fixture() {
+    let cgroupfs = CgroupfsBuilder::synthetic(100)
+        .cgroup(
+            "slice0.slice/unit0.service",
+            CgroupFixture::new().file("memory.max", b"max\n"),
+        )
+        // Removed while being read
+        .cgroup("slice0.slice/unit1.service", CgroupFixture::empty())
+        .build()
+        .expect("Failed to write cgroupfs");
+    let root = CgroupReader::new_unchecked(cgroupfs.path().to_path_buf())
+        .expect("Failed to open cgroup root");
+
+    let slices: Vec<_> = root
+        .child_cgroup_iter()
+        .expect("Failed to enumerate child cgroups")
+        .collect();
+    assert_eq!(slices.len(), 13);
+    let units: Vec<_> = slices
+        .iter()
+        .flat_map(|slice| {
+            slice
+                .child_cgroup_iter()
+                .expect("Failed to enumerate child cgroups")
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    assert_eq!(units.len(), 100);
+
+    let unit = |name: &str| {
+        units
+            .iter()
+            .find(|unit| unit.name() == Path::new(name))
+            .unwrap_or_else(|| panic!("Missing unit {}", name))
+    };
+    let unit0 = unit("slice0.slice/unit0.service");
+    assert_eq!(unit0.read_memory_max().expect("Failed to read"), -1);
+    assert_eq!(
+        unit0.read_memory_current().expect("Failed to read"),
+        64 << 20
+    );
+    assert!(matches!(
+        unit("slice0.slice/unit1.service").read_memory_current(),
+        Err(Error::IoError(_, e)) if e.kind() == std::io::ErrorKind::NotFound
+    ));
+}
+
 #[test]
 fn test_child_cgroup_iter_empty() {
     let root = TestCgroup::new();
@@ -699,6 +816,57 @@ fn test_root_cgroup_name_is_empty() {
     assert_eq!(root.get_reader().name(), OsStr::new(""));
 }
 
+#[test]
+fn test_find_cgroup_ns_root() {
+    let root = TestCgroup::new();
+    root.create_child("system.slice");
+    root.create_child("system.slice/container.scope");
+    root.create_child("system.slice/container.scope/app");
+    root.create_file_with_content("system.slice/container.scope/cgroup.procs", b"");
+    root.create_file_with_content("system.slice/container.scope/app/cgroup.procs", b"1\n42\n");
+    let reader = root.get_reader();
+
+    // No namespace
+    assert_eq!(
+        reader.find_cgroup_ns_root(42, "/system.slice/container.scope/app"),
+        Some(PathBuf::new())
+    );
+    // Namespace rooted at container.scope
+    assert_eq!(
+        reader.find_cgroup_ns_root(42, "/app"),
+        Some(PathBuf::from("system.slice/container.scope"))
+    );
+    assert_eq!(reader.find_cgroup_ns_root(43, "/app"), None);
+    assert_eq!(reader.find_cgroup_ns_root(42, "/other"), None);
+    assert_eq!(reader.find_cgroup_ns_root(42, "/../app"), None);
+}
+
+#[test]
+fn test_resolve_cgroup_ns_path() {
+    let ns_root = Path::new("system.slice/container.scope");
+    assert_eq!(
+        resolve_cgroup_ns_path(ns_root, "/app"),
+        Some("/system.slice/container.scope/app".to_owned())
+    );
+    assert_eq!(
+        resolve_cgroup_ns_path(ns_root, "/"),
+        Some("/system.slice/container.scope".to_owned())
+    );
+    assert_eq!(
+        resolve_cgroup_ns_path(ns_root, "/../../init.scope"),
+        Some("/init.scope".to_owned())
+    );
+    assert_eq!(
+        resolve_cgroup_ns_path(ns_root, "/../../../init.scope"),
+        None
+    );
+    assert_eq!(resolve_cgroup_ns_path(ns_root, "?"), None);
+    assert_eq!(
+        resolve_cgroup_ns_path(Path::new(""), "/init.scope"),
+        Some("/init.scope".to_owned())
+    );
+}
+
 #[test]
 fn test_validate_cgroup2_fs() {
     let root = TestCgroup::new();
@@ -951,3 +1119,100 @@ fn test_memory_numa_stat_invalid_format() {
         _ => panic!("Got unexpected error type: {}", err),
     }
 }
+
+#[test]
+fn test_hugetlb_stat_success() {
+    let cgroup = TestCgroup::new();
+    cgroup.create_file_with_content("hugetlb.2MB.current", b"4194304\n");
+    cgroup.create_file_with_content("hugetlb.2MB.max", b"max\n");
+    cgroup.create_file_with_content("hugetlb.2MB.rsvd.current", b"0\n");
+    cgroup.create_file_with_content("hugetlb.1GB.current", b"0\n");
+    cgroup.create_file_with_content("hugetlb.1GB.max", b"2147483648\n");
+
+    let cgroup_reader = cgroup.get_reader();
+    let val = cgroup_reader
+        .read_hugetlb_stat()
+        .expect("Failed to read hugetlb stat");
+    assert_eq!(
+        val,
+        BTreeMap::from([
+            (
+                "1GB".to_owned(),
+                HugetlbStat {
+                    current: Some(0),
+                    max: Some(2147483648),
+                }
+            ),
+            (
+                "2MB".to_owned(),
+                HugetlbStat {
+                    current: Some(4194304),
+                    max: Some(-1),
+                }
+            ),
+        ])
+    );
+}
+
+#[test]
+fn test_hugetlb_stat_not_enabled() {
+    let cgroup = TestCgroup::new();
+    cgroup.create_file_with_content("memory.current", b"1234\n");
+
+    let cgroup_reader = cgroup.get_reader();
+    let err = cgroup_reader
+        .read_hugetlb_stat()
+        .expect_err("Did not fail to read hugetlb stat");
+    match err {
+        Error::IoError(_, e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        _ => panic!("Got unexpected error type: {}", err),
+    }
+}
+
+#[test]
+fn test_limit_cache() {
+    let cgroup = TestCgroup::new();
+    cgroup.create_child("child");
+    cgroup.create_file_with_content("memory.max", b"1000\n");
+    cgroup.create_file_with_content("memory.min", b"10\n");
+    cgroup.create_file_with_content("memory.current", b"100\n");
+    cgroup.create_file_with_content("child/memory.max", b"max\n");
+
+    let cache = LimitCache::new(3);
+    let mut cgroup_reader = cgroup.get_reader();
+    cgroup_reader.set_limit_cache(cache.clone());
+    let read_child_memory_max = || {
+        cgroup_reader
+            .child_cgroup_iter()
+            .expect("Failed to enumerate child cgroups")
+            .map(|child| child.read_memory_max().expect("Failed to read memory.max"))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(cgroup_reader.read_memory_max().unwrap(), 1000);
+    assert_eq!(cgroup_reader.read_memory_min().unwrap(), 10);
+    assert_eq!(cgroup_reader.read_memory_current().unwrap(), 100);
+    assert_eq!(read_child_memory_max(), vec![-1]);
+    // Not memory.current, which is not a limit
+    assert_eq!(cache.len(), 3);
+    cache.finish_sample();
+
+    // Changed limits are read again once they are due, which is spread
+    // over the next samples
+    cgroup.create_file_with_content("memory.max", b"2000\n");
+    cgroup.create_file_with_content("memory.min", b"20\n");
+    cgroup.create_file_with_content("memory.current", b"200\n");
+    assert_eq!(cgroup_reader.read_memory_max().unwrap(), 2000);
+    assert_eq!(cgroup_reader.read_memory_min().unwrap(), 10);
+    assert_eq!(cgroup_reader.read_memory_current().unwrap(), 200);
+    assert_eq!(read_child_memory_max(), vec![-1]);
+    cache.finish_sample();
+    assert_eq!(cgroup_reader.read_memory_max().unwrap(), 2000);
+    assert_eq!(cgroup_reader.read_memory_min().unwrap(), 20);
+    cache.finish_sample();
+
+    // Files of cgroups not read since, e.g. removed ones, are dropped
+    std::fs::remove_file(cgroup.path().join("child/memory.max")).unwrap();
+    std::fs::remove_dir(cgroup.path().join("child")).unwrap();
+    assert_eq!(read_child_memory_max(), Vec::<i64>::new());
+    assert_eq!(cache.len(), 2);
+}
@@ -46,6 +46,15 @@ pub struct IoStat {
     pub cost_wait: Option<u64>,
     pub cost_indebt: Option<u64>,
     pub cost_indelay: Option<u64>,
+    /// Moving average of IO completion latency in microseconds, only with an
+    /// io.latency target set for the device
+    pub avg_lat: Option<u64>,
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct IoLatency {
+    /// Latency target in microseconds
+    pub target: Option<u64>,
 }
 
 #[derive(Default, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
@@ -63,6 +72,8 @@ pub struct MemoryStat {
     pub file_dirty: Option<u64>,
     pub file_writeback: Option<u64>,
     pub anon_thp: Option<u64>,
+    pub file_thp: Option<u64>,
+    pub shmem_thp: Option<u64>,
     pub inactive_anon: Option<u64>,
     pub active_anon: Option<u64>,
     pub inactive_file: Option<u64>,
@@ -116,11 +127,22 @@ pub struct MemoryPressure {
     pub full: PressureMetrics,
 }
 
+/// IRQ time is never attributable to a task, so only full pressure is
+/// reported.
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct IrqPressure {
+    pub full: PressureMetrics,
+}
+
 #[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Pressure {
     pub cpu: CpuPressure,
     pub io: IoPressure,
     pub memory: MemoryPressure,
+    /// None on kernels without IRQ pressure, i.e. before 6.1 or without
+    /// CONFIG_IRQ_TIME_ACCOUNTING
+    #[serde(default)]
+    pub irq: Option<IrqPressure>,
 }
 
 #[derive(Default, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
@@ -141,6 +163,14 @@ pub struct MemoryEventsLocal {
     pub oom_kill: Option<u64>,
 }
 
+/// Usage and limit of the huge pages of one size, from
+/// hugetlb.<size>.current and hugetlb.<size>.max
+#[derive(Default, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct HugetlbStat {
+    pub current: Option<u64>,
+    pub max: Option<i64>,
+}
+
 #[derive(Default, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct CgroupStat {
     pub nr_descendants: Option<u32>,
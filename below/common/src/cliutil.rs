@@ -21,48 +21,77 @@ use anyhow::bail;
 use anyhow::Result;
 
 use crate::dateutil;
+use crate::tz;
 use crate::util;
 
 const MISSING_SAMPLE_WARN_DURATION_S: u64 = 60;
 
+/// Split a trailing zoneinfo timezone name off a date, e.g.
+/// "2024-03-10 14:00 America/New_York"
+fn split_timezone(date: &str) -> Result<(&str, Option<tz::TimeZone>)> {
+    let date = date.trim();
+    match date.rsplit_once(' ') {
+        Some((date, zone)) if tz::TimeZone::is_zone_name(zone) => {
+            Ok((date.trim_end(), Some(tz::TimeZone::load(zone)?)))
+        }
+        _ => Ok((date, None)),
+    }
+}
+
+/// Parse a date into a `SystemTime` and the timezone it was given in
+fn parse_date(date: &str) -> Result<(SystemTime, Option<tz::TimeZone>)> {
+    let (date_without_zone, timezone) = split_timezone(date)?;
+    let zone = timezone
+        .as_ref()
+        .map_or(dateutil::Zone::Local, dateutil::Zone::Named);
+    let time = dateutil::HgTime::parse_in(date_without_zone, zone).ok_or_else(|| {
+        anyhow!(
+            "Unrecognized timestamp format\n\
+            Input: {}.\n\
+            Examples:\n\t\
+            Keywords: now, today, yesterday\n\t\
+            Days: \"yesterday 14:00\", \"monday 2:30pm\", \"last friday\"\n\t\
+            Relative: \"{{humantime}} ago\", e.g. 2 days 3 hr 15m 10sec ago\n\t\
+            Relative short: Mixed {{time_digit}}{{time_unit_char}}. E.g. 10m, 3d2H, 5h30s, 10m5h\n\t\
+            Absolute: \"Jan 01 23:59\", \"01/01/1970 11:59PM\", \"1970-01-01 23:59:59\"\n\t\
+            ISO8601: \"2024-03-10T14:00:00-05:00\", \"2024-03-10T19:00:00Z\"\n\t\
+            Timezone: append a zoneinfo name, e.g. \"2024-03-10 14:00 America/New_York\"\n\t\
+            Unix Epoch: 1589808367",
+            &date
+        )
+    })?;
+    Ok((UNIX_EPOCH + Duration::from_secs(time.unixtime), timezone))
+}
+
 /// Convert from date to `SystemTime`
 pub fn system_time_from_date(date: &str) -> Result<SystemTime> {
-    Ok(UNIX_EPOCH
-    + Duration::from_secs(
-        dateutil::HgTime::parse(&date)
-            .ok_or_else(|| {
-                anyhow!(
-                    "Unrecognized timestamp format\n\
-                Input: {}.\n\
-                Examples:\n\t\
-                Keywords: now, today, yesterday\n\t\
-                Relative: \"{{humantime}} ago\", e.g. 2 days 3 hr 15m 10sec ago\n\t\
-                Relative short: Mixed {{time_digit}}{{time_unit_char}}. E.g. 10m, 3d2H, 5h30s, 10m5h\n\t\
-                Absolute: \"Jan 01 23:59\", \"01/01/1970 11:59PM\", \"1970-01-01 23:59:59\"\n\t\
-                Unix Epoch: 1589808367",
-                    &date
-                )
-            })?
-            .unixtime,
-    ))
+    parse_date(date).map(|(time, _)| time)
 }
 
-/// Convert from date and an optional days adjuster to `SystemTime`. Days
-/// adjuster is of form y[y...] to `SystemTime`. Each "y" will deduct 1 day
-/// from the resulting time.
+/// Convert from date and an optional days adjuster to a `SystemTime`. Days
+/// adjuster is of form y[y...]. Each "y" will deduct 1 day from the resulting
+/// time, keeping its time of day across DST changes.
 pub fn system_time_from_date_and_adjuster(
     date: &str,
     days_adjuster: Option<&str>,
 ) -> Result<SystemTime> {
-    let mut time = system_time_from_date(date)?;
-    if let Some(days) = days_adjuster {
-        if days.is_empty() || days.find(|c: char| c != 'y').is_some() {
-            bail!("Unrecognized days adjuster format: {}", days);
-        }
-        let time_to_deduct = Duration::from_secs(days.chars().count() as u64 * 86400);
-        time -= time_to_deduct;
+    let (time, timezone) = parse_date(date)?;
+    let days = match days_adjuster {
+        Some(days) => days,
+        None => return Ok(time),
+    };
+    if days.is_empty() || days.find(|c: char| c != 'y').is_some() {
+        bail!("Unrecognized days adjuster format: {}", days);
     }
-    Ok(time)
+    let zone = timezone
+        .as_ref()
+        .map_or(dateutil::Zone::Local, dateutil::Zone::Named);
+    let time = dateutil::HgTime {
+        unixtime: time.duration_since(UNIX_EPOCH)?.as_secs(),
+        offset: 0,
+    };
+    let local = zone.to_local(time) - chrono::Duration::days(days.chars().count() as i64);
+    Ok(UNIX_EPOCH + Duration::from_secs(zone.from_local(local).unixtime))
 }
 
 /// Convert from date range and an optional days adjuster to a start and end
@@ -91,6 +120,15 @@ pub fn system_time_range_from_date_and_adjuster(
     Ok((start, end))
 }
 
+/// Parse a non-zero duration such as "1 min" or "30s".
+pub fn duration_from_str(duration_str: &str) -> Result<Duration> {
+    match duration_str.parse::<humantime::Duration>() {
+        Ok(duration) if !duration.is_zero() => Ok(duration.into()),
+        Ok(_) => bail!("Duration must not be zero: {}", duration_str),
+        Err(e) => bail!("Invalid duration {}: {}", duration_str, e),
+    }
+}
+
 /// Check that initial sample time is within `MISSING_SAMPLE_WARN_DURATION_S`
 /// seconds of the requested start time.
 pub fn check_initial_sample_time_with_requested_time(
@@ -210,6 +248,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_system_time_from_date_with_timezone() {
+        // Skipped when the zoneinfo database is not installed
+        if tz::TimeZone::load("America/New_York").is_err() {
+            return;
+        }
+        // EST in winter, EDT in summer
+        assert_eq!(
+            t("2024-01-15 12:00 America/New_York"),
+            t("2024-01-15 17:00:00 UTC")
+        );
+        assert_eq!(
+            t("2024-07-15 12:00 America/New_York"),
+            t("2024-07-15 16:00:00 UTC")
+        );
+        // Days adjuster across the DST change keeps the time of day
+        assert_eq!(
+            system_time_from_date_and_adjuster("2024-03-11 12:00 America/New_York", Some("y"))
+                .unwrap(),
+            t("2024-03-10 16:00:00 UTC")
+        );
+        assert_eq!(
+            system_time_from_date_and_adjuster("2024-03-10 12:00 America/New_York", Some("y"))
+                .unwrap(),
+            t("2024-03-09 17:00:00 UTC")
+        );
+        assert!(system_time_from_date("2024-01-15 12:00 Nowhere/Zone").is_err());
+    }
+
+    #[test]
+    fn test_system_time_from_iso8601() {
+        assert_eq!(t("2024-03-10T14:00:00-05:00"), t("2024-03-10 19:00:00 UTC"));
+        assert_eq!(t("2024-03-10T19:00:00.250Z"), t("2024-03-10 19:00:00 UTC"));
+    }
+
     /// Convert date to `SystemTime`
     fn t(h: &str) -> SystemTime {
         system_time_from_date(h).unwrap()
@@ -38,6 +38,8 @@ use chrono::NaiveTime;
 use chrono::TimeZone;
 use regex::Regex;
 
+use crate::tz;
+
 /// A simple time structure that matches hg's time representation.
 ///
 /// Internally it's unixtime (in GMT), and offset (GMT -1 = +3600).
@@ -111,6 +113,61 @@ fn today() -> NaiveDate {
     Local::now().date_naive()
 }
 
+/// Timezone of dates given without an explicit offset
+#[derive(Clone, Copy, Debug)]
+pub enum Zone<'a> {
+    Local,
+    Named(&'a tz::TimeZone),
+}
+
+impl Zone<'_> {
+    fn now(&self) -> NaiveDateTime {
+        match self {
+            Zone::Local => Local::now().naive_local(),
+            Zone::Named(_) => self.to_local(HgTime::now()),
+        }
+    }
+
+    fn today(&self) -> NaiveDate {
+        match self {
+            Zone::Local => today(),
+            Zone::Named(_) => self.now().date(),
+        }
+    }
+
+    /// Local time in the timezone at `time`
+    pub fn to_local(&self, time: HgTime) -> NaiveDateTime {
+        let offset = match self {
+            Zone::Local => match DEFAUL_OFFSET.load(Ordering::SeqCst) {
+                offset if is_valid_offset(offset) => -offset,
+                _ => Local
+                    .timestamp_opt(time.unixtime as i64, 0)
+                    .single()
+                    .map_or(0, |local| local.offset().fix().local_minus_utc()),
+            },
+            Zone::Named(tz) => tz.offset_at(time.unixtime as i64),
+        };
+        DateTime::from_timestamp(time.unixtime as i64 + offset as i64, 0)
+            .unwrap_or_default()
+            .naive_utc()
+    }
+
+    /// Time of the local time `time` in the timezone
+    pub fn from_local(&self, time: NaiveDateTime) -> HgTime {
+        match self {
+            Zone::Local => HgTime::from(time).use_default_offset(),
+            Zone::Named(tz) => {
+                let utc = tz.from_local(time);
+                HgTime {
+                    // XXX: This might silently change negative time to 0.
+                    unixtime: utc.max(0) as u64,
+                    offset: -tz.offset_at(utc),
+                }
+            }
+        }
+    }
+}
+
 impl HgTime {
     pub fn now() -> Self {
         let now: HgTime = Local::now().into();
@@ -125,20 +182,19 @@ impl HgTime {
     /// some additional forms like `2 days ago`.
     /// It can also handle future forms like "ten hours from now" and "+10h"
     pub fn parse(date: &str) -> Option<Self> {
+        Self::parse_in(date, Zone::Local)
+    }
+
+    /// Same as `parse`, but dates without an explicit offset are taken in
+    /// `zone`.
+    pub fn parse_in(date: &str, zone: Zone) -> Option<Self> {
+        if let Some(time) = Self::parse_day_and_time(date, zone) {
+            return Some(time);
+        }
         match date {
             "now" => Some(Self::now()),
-            "today" => Some(Self::from(today().and_hms_opt(0, 0, 0).unwrap()).use_default_offset()),
-            "yesterday" => Some(
-                Self::from(today().and_hms_opt(0, 0, 0).unwrap() - Duration::days(1))
-                    .use_default_offset(),
-            ),
-            "tomorrow" => Some(
-                Self::from(today().and_hms_opt(0, 0, 0).unwrap() + Duration::days(1))
-                    .use_default_offset(),
-            ),
             "day after tomorrow" | "the day after tomorrow" | "overmorrow" => Some(
-                Self::from(today().and_hms_opt(0, 0, 0).unwrap() + Duration::days(2))
-                    .use_default_offset(),
+                zone.from_local(zone.today().and_hms_opt(0, 0, 0).unwrap() + Duration::days(2)),
             ),
             // Match all string ends with [dhms] or ago but not ends with pm and am. Case insensitive.
             // We have to use two regex here is because regex crate doesn't support negative lookbehind
@@ -197,10 +253,47 @@ impl HgTime {
                     _ => None,
                 }
             }
-            _ => Self::parse_absolute(date, default_date_lower),
+            _ => Self::parse_absolute(date, default_date_lower, zone),
         }
     }
 
+    /// Parse a day relative to today, optionally followed by a time of day,
+    /// e.g. "yesterday 14:00", "monday 2:30pm" or "last fri". A weekday is
+    /// its most recent occurrence including today, "last" excludes today.
+    fn parse_day_and_time(date: &str, zone: Zone) -> Option<Self> {
+        let date = date.trim().to_ascii_lowercase();
+        let (last, date) = match date.strip_prefix("last ") {
+            Some(date) => (true, date.trim_start()),
+            None => (false, date.as_str()),
+        };
+        let (day, time) = match date.split_once(' ') {
+            Some((day, time)) => (day, Some(time.trim())),
+            None => (date, None),
+        };
+        let today = zone.today();
+        let day = match day {
+            "today" if !last => today,
+            "yesterday" if !last => today - Duration::days(1),
+            "tomorrow" if !last => today + Duration::days(1),
+            day => {
+                let weekday = day.parse::<Weekday>().ok()?;
+                let days_back = match (today.weekday().num_days_from_monday() + 7
+                    - weekday.num_days_from_monday())
+                    % 7
+                {
+                    0 if last => 7,
+                    days_back => days_back,
+                };
+                today - Duration::days(days_back as i64)
+            }
+        };
+        let time = match time {
+            Some(time) => Self::parse_time_of_day(time)?,
+            None => NaiveTime::MIN,
+        };
+        Some(zone.from_local(day.and_time(time)))
+    }
+
     /// Parse a date string as a range.
     ///
     /// For example, `Apr 2000` covers range `Apr 1, 2000` to `Apr 30, 2000`.
@@ -275,11 +368,11 @@ impl HgTime {
                 }
             }
             _ => {
-                let start = Self::parse_absolute(date, default_date_lower);
-                let end = Self::parse_absolute(date, default_date_upper::<N31>)
-                    .or_else(|| Self::parse_absolute(date, default_date_upper::<N30>))
-                    .or_else(|| Self::parse_absolute(date, default_date_upper::<N29>))
-                    .or_else(|| Self::parse_absolute(date, default_date_upper::<N28>));
+                let start = Self::parse_absolute(date, default_date_lower, Zone::Local);
+                let end = Self::parse_absolute(date, default_date_upper::<N31>, Zone::Local)
+                    .or_else(|| Self::parse_absolute(date, default_date_upper::<N30>, Zone::Local))
+                    .or_else(|| Self::parse_absolute(date, default_date_upper::<N29>, Zone::Local))
+                    .or_else(|| Self::parse_absolute(date, default_date_upper::<N28>, Zone::Local));
                 if let (Some(start), Some(end)) = (start, end) {
                     Some(start..end + 1)
                 } else {
@@ -294,10 +387,19 @@ impl HgTime {
     /// Return None if it cannot be parsed.
     ///
     /// `default_date` takes a format char, for example, `H`, and returns a
-    /// default value of it.
-    fn parse_absolute(date: &str, default_date: fn(char) -> &'static str) -> Option<Self> {
+    /// default value of it. Dates without an offset are taken in `zone`.
+    fn parse_absolute(
+        date: &str,
+        default_date: fn(char) -> &'static str,
+        zone: Zone,
+    ) -> Option<Self> {
         let date = date.trim();
 
+        // ISO8601 with fractional seconds, e.g. "2024-03-10T14:00:00.5+01:00"
+        if let Ok(parsed) = DateTime::parse_from_rfc3339(date) {
+            return Some(parsed.into());
+        }
+
         // Hg internal format. "unixtime offset"
         let parts: Vec<_> = date.split(" ").collect();
         if parts.len() == 2 {
@@ -343,7 +445,7 @@ impl HgTime {
                         // For example, if the user only specified "month/day",
                         // then we should use the current "year", instead of
                         // year 0.
-                        let now = now.get_or_insert_with(|| Local::now());
+                        let now = now.get_or_insert_with(|| zone.now());
                         date_with_defaults +=
                             &format!(" @{}", now.format(&format!("%{}", format_char)));
                     } else {
@@ -366,7 +468,7 @@ impl HgTime {
             // Without timezone.
             let format = format!("{}{}", naive_format, default_format);
             if let Ok(parsed) = NaiveDateTime::parse_from_str(&date_with_defaults, &format) {
-                return Some(parsed.into());
+                return Some(zone.from_local(parsed));
             }
         }
 
@@ -485,8 +587,19 @@ impl<Tz: TimeZone> From<DateTime<Tz>> for HgTime {
 impl From<NaiveDateTime> for HgTime {
     fn from(time: NaiveDateTime) -> Self {
         let timestamp = time.timestamp();
-        // Use local offset. (Is there a better way to do this?)
-        let offset = Self::now().offset;
+        // Use the local offset in effect at the time rather than the current
+        // one, which differs across DST changes. A time skipped when clocks
+        // go forward is taken in the offset before the change.
+        let offset = match DEFAUL_OFFSET.load(Ordering::SeqCst) {
+            offset if is_valid_offset(offset) => offset,
+            _ => match Local.from_local_datetime(&time).earliest() {
+                Some(local) => local.offset().fix().utc_minus_local(),
+                None => Local
+                    .offset_from_utc_datetime(&(time - Duration::days(1)))
+                    .fix()
+                    .utc_minus_local(),
+            },
+        };
         // XXX: This might silently change negative time to 0.
         let unixtime = (timestamp + offset as i64).max(0) as u64;
         Self { unixtime, offset }
@@ -696,6 +809,28 @@ mod tests {
         assert_eq!(future_diff_from_now("+10hm", Duration::minutes(10)), -1);
     }
 
+    #[test]
+    fn test_parse_day_and_time() {
+        set_default_offset(7200);
+        let today = HgTime::parse("today").unwrap().unixtime;
+        let weekday = Local::now().weekday().to_string();
+        assert_eq!(
+            t("yesterday 14:00"),
+            format!("{} 7200", today - 86400 + 14 * 3600)
+        );
+        assert_eq!(
+            t("Today 2:30pm"),
+            format!("{} 7200", today + 14 * 3600 + 1800)
+        );
+        assert_eq!(t(&weekday), format!("{} 7200", today));
+        assert_eq!(
+            t(&format!("last {} 08:00", weekday)),
+            format!("{} 7200", today - 7 * 86400 + 8 * 3600)
+        );
+        assert_eq!(t("last today"), "fail");
+        assert_eq!(t("monday 25:00"), "fail");
+    }
+
     #[test]
     fn test_parse_range() {
         set_default_offset(7200);
@@ -16,6 +16,7 @@ pub mod cliutil;
 pub mod dateutil;
 pub mod fileutil;
 pub mod logutil;
+pub mod tz;
 pub mod util;
 
 // Shim between facebook types and open source types.
@@ -12,13 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::fmt;
 use std::io;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::RwLock;
 
+use anyhow::anyhow;
+use anyhow::bail;
 use once_cell::sync::Lazy;
 use slog::Drain;
+use slog::FilterLevel;
 use slog::Level;
 
 #[derive(PartialEq, Copy, Clone)]
@@ -108,6 +113,7 @@ pub fn get_last_log_to_display() -> Option<String> {
 pub struct CompoundDecorator<W: io::Write, T: io::Write> {
     file: Arc<Mutex<W>>,
     term: Arc<Mutex<T>>,
+    color: bool,
 }
 
 impl<W, T> CompoundDecorator<W, T>
@@ -119,8 +125,16 @@ where
         Self {
             file: Arc::new(Mutex::new(file_io)),
             term: Arc::new(Mutex::new(term_io)),
+            color: false,
         }
     }
+
+    /// Color the level of records written to the terminal. The file never
+    /// gets color codes.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
 }
 
 impl<W, T> slog_term::Decorator for CompoundDecorator<W, T>
@@ -130,27 +144,66 @@ where
 {
     fn with_record<F>(
         &self,
-        _record: &slog::Record,
+        record: &slog::Record,
         _logger_values: &slog::OwnedKVList,
         f: F,
     ) -> io::Result<()>
     where
         F: FnOnce(&mut dyn slog_term::RecordDecorator) -> io::Result<()>,
     {
-        f(&mut CompoundRecordDecorator(
-            &*self.file,
-            &*self.term,
-            *LOG_TARGET
+        f(&mut CompoundRecordDecorator {
+            file: &self.file,
+            term: &self.term,
+            target: *LOG_TARGET
                 .read()
                 .expect("Failed to acquire write lock on the LOG_TARGET"),
-        ))
+            color: if self.color {
+                level_color(record.level())
+            } else {
+                None
+            },
+            colored: false,
+        })
     }
 }
 
-pub struct CompoundRecordDecorator<'a, W: 'a, T: 'a>(&'a Mutex<W>, &'a Mutex<T>, TargetLog)
+/// ANSI color code of a level, None to leave it uncolored
+fn level_color(level: Level) -> Option<u8> {
+    match level {
+        Level::Critical | Level::Error => Some(31),
+        Level::Warning => Some(33),
+        Level::Info => Some(32),
+        Level::Debug => Some(36),
+        Level::Trace => None,
+    }
+}
+
+pub struct CompoundRecordDecorator<'a, W: 'a, T: 'a>
+where
+    W: io::Write,
+    T: io::Write,
+{
+    file: &'a Mutex<W>,
+    term: &'a Mutex<T>,
+    target: TargetLog,
+    color: Option<u8>,
+    /// Whether a color code was written to the terminal and not reset yet
+    colored: bool,
+}
+
+impl<'a, W, T> CompoundRecordDecorator<'a, W, T>
 where
     W: io::Write,
-    T: io::Write;
+    T: io::Write,
+{
+    /// Write to the terminal only, e.g. color codes
+    fn write_term(&mut self, buf: &[u8]) -> io::Result<()> {
+        match self.target {
+            TargetLog::All | TargetLog::Term => self.term.lock().unwrap().write_all(buf),
+            TargetLog::File => Ok(()),
+        }
+    }
+}
 
 impl<'a, W, T> io::Write for CompoundRecordDecorator<'a, W, T>
 where
@@ -158,24 +211,24 @@ where
     T: io::Write,
 {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        match self.2 {
+        match self.target {
             TargetLog::All => {
-                let term_res = self.1.lock().unwrap().write(buf);
-                let file_res = self.0.lock().unwrap().write(buf);
+                let term_res = self.term.lock().unwrap().write(buf);
+                let file_res = self.file.lock().unwrap().write(buf);
                 if let Err(e) = term_res {
                     return Err(e);
                 }
 
                 file_res
             }
-            TargetLog::File => self.0.lock().unwrap().write(buf),
-            TargetLog::Term => self.1.lock().unwrap().write(buf),
+            TargetLog::File => self.file.lock().unwrap().write(buf),
+            TargetLog::Term => self.term.lock().unwrap().write(buf),
         }
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        let term_res = self.1.lock().unwrap().flush();
-        let file_res = self.0.lock().unwrap().flush();
+        let term_res = self.term.lock().unwrap().flush();
+        let file_res = self.file.lock().unwrap().flush();
         if let Err(e) = term_res {
             return Err(e);
         }
@@ -190,8 +243,8 @@ where
     T: io::Write,
 {
     fn drop(&mut self) {
-        let _ = self.1.lock().unwrap().flush();
-        let _ = self.0.lock().unwrap().flush();
+        let _ = self.term.lock().unwrap().flush();
+        let _ = self.file.lock().unwrap().flush();
     }
 }
 
@@ -201,6 +254,19 @@ where
     T: io::Write,
 {
     fn reset(&mut self) -> io::Result<()> {
+        if self.colored {
+            self.colored = false;
+            self.write_term(b"\x1b[0m")?;
+        }
+        Ok(())
+    }
+
+    fn start_level(&mut self) -> io::Result<()> {
+        self.reset()?;
+        if let Some(color) = self.color {
+            self.colored = true;
+            self.write_term(format!("\x1b[{}m", color).as_bytes())?;
+        }
         Ok(())
     }
 }
@@ -238,7 +304,312 @@ where
     }
 }
 
+/// Format of log records
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+    /// Human readable text, with colored levels on a terminal
+    Text,
+    /// One JSON object per line, for log ingestion
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => bail!("Invalid log format {}, expected text or json", s),
+        }
+    }
+}
+
+/// How `below` sets up its logger, from the --log-level and --log-format
+/// options
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogOptions {
+    pub level: LogLevelSpec,
+    pub format: LogFormat,
+}
+
+/// Log level of each module, e.g. "info,below_store=debug,below_model=trace"
+/// logs at info level except for the store and model crates. The level of
+/// the longest matching module prefix applies.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogLevelSpec {
+    default: FilterLevel,
+    modules: Vec<(String, FilterLevel)>,
+}
+
+impl LogLevelSpec {
+    pub fn new(default: FilterLevel) -> Self {
+        Self {
+            default,
+            modules: Vec::new(),
+        }
+    }
+
+    /// Level of records logged by `module`, a module path like
+    /// "below_store::cursor"
+    pub fn level_for(&self, module: &str) -> FilterLevel {
+        self.modules
+            .iter()
+            .filter(|(prefix, _)| {
+                module
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+}
+
+fn parse_filter_level(s: &str) -> anyhow::Result<FilterLevel> {
+    FilterLevel::from_str(s).map_err(|_| {
+        anyhow!(
+            "Invalid log level {}, expected one of off, critical, error, warn, info, debug, trace",
+            s
+        )
+    })
+}
+
+impl FromStr for LogLevelSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut spec = Self::new(FilterLevel::Info);
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('=') {
+                Some((module, level)) => spec
+                    .modules
+                    .push((module.trim().to_owned(), parse_filter_level(level.trim())?)),
+                None => spec.default = parse_filter_level(part)?,
+            }
+        }
+        Ok(spec)
+    }
+}
+
+/// Drops records below the level `LogLevelSpec` gives their module
+pub struct LevelSpecDrain<D> {
+    drain: D,
+    spec: LogLevelSpec,
+}
+
+impl<D> LevelSpecDrain<D> {
+    pub fn new(drain: D, spec: LogLevelSpec) -> Self {
+        Self { drain, spec }
+    }
+}
+
+impl<D> Drain for LevelSpecDrain<D>
+where
+    D: Drain,
+{
+    type Ok = Option<D::Ok>;
+    type Err = D::Err;
+
+    fn log(
+        &self,
+        record: &slog::Record,
+        values: &slog::OwnedKVList,
+    ) -> std::result::Result<Self::Ok, Self::Err> {
+        if self.spec.level_for(record.module()).accepts(record.level()) {
+            self.drain.log(record, values).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Collects key value pairs of a record into a JSON object
+struct JsonSerializer<'a>(&'a mut serde_json::Map<String, serde_json::Value>);
+
+/// Keep numbers and bools typed instead of formatting them as strings
+macro_rules! emit_json_value {
+    ($($name:ident: $t:ty),*) => {
+        $(
+            fn $name(&mut self, key: slog::Key, val: $t) -> slog::Result {
+                self.0.insert(key.to_string(), val.into());
+                Ok(())
+            }
+        )*
+    };
+}
+
+impl<'a> slog::Serializer for JsonSerializer<'a> {
+    fn emit_arguments(&mut self, key: slog::Key, val: &fmt::Arguments) -> slog::Result {
+        self.0.insert(key.to_string(), val.to_string().into());
+        Ok(())
+    }
+
+    emit_json_value!(
+        emit_u8: u8,
+        emit_i8: i8,
+        emit_u16: u16,
+        emit_i16: i16,
+        emit_u32: u32,
+        emit_i32: i32,
+        emit_u64: u64,
+        emit_i64: i64,
+        emit_usize: usize,
+        emit_isize: isize,
+        emit_f32: f32,
+        emit_f64: f64,
+        emit_bool: bool
+    );
+}
+
+/// Writes each record as a JSON object on its own line, with "ts", "level",
+/// "module" and "msg" keys along with the key value pairs of the record and
+/// logger. Output goes through a `slog_term::Decorator` so that
+/// `CompoundDecorator` still picks the destination.
+pub struct JsonDrain<D> {
+    decorator: D,
+}
+
+impl<D> JsonDrain<D> {
+    pub fn new(decorator: D) -> Self {
+        Self { decorator }
+    }
+}
+
+impl<D> Drain for JsonDrain<D>
+where
+    D: slog_term::Decorator,
+{
+    type Ok = ();
+    type Err = io::Error;
+
+    fn log(&self, record: &slog::Record, values: &slog::OwnedKVList) -> io::Result<()> {
+        let mut object = serde_json::Map::new();
+        object.insert(
+            "ts".to_owned(),
+            chrono::Local::now()
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, false)
+                .into(),
+        );
+        object.insert("level".to_owned(), record.level().as_str().into());
+        object.insert("module".to_owned(), record.module().into());
+        object.insert("msg".to_owned(), record.msg().to_string().into());
+        {
+            use slog::KV;
+            let mut serializer = JsonSerializer(&mut object);
+            // Record values take precedence over logger values of the same
+            // key, as they are more specific
+            values.serialize(record, &mut serializer)?;
+            record.kv().serialize(record, &mut serializer)?;
+        }
+        self.decorator.with_record(record, values, |w| {
+            serde_json::to_writer(&mut *w, &object)?;
+            writeln!(w)?;
+            w.flush()
+        })
+    }
+}
+
 pub fn get_logger() -> slog::Logger {
     let plain = slog_term::PlainSyncDecorator::new(std::io::stderr());
     slog::Logger::root(slog_term::FullFormat::new(plain).build().fuse(), slog::o!())
 }
+
+#[cfg(test)]
+mod test {
+    use slog::debug;
+    use slog::info;
+    use slog::o;
+    use slog::trace;
+
+    use super::*;
+
+    #[test]
+    fn log_level_spec() {
+        let spec = LogLevelSpec::from_str("warn, below_store=debug,below_store::cursor=trace")
+            .expect("Failed to parse spec");
+        assert_eq!(spec.level_for("below"), FilterLevel::Warning);
+        assert_eq!(spec.level_for("below_store"), FilterLevel::Debug);
+        assert_eq!(spec.level_for("below_store::advance"), FilterLevel::Debug);
+        assert_eq!(spec.level_for("below_store::cursor"), FilterLevel::Trace);
+        // Only whole module names match
+        assert_eq!(spec.level_for("below_store_extra"), FilterLevel::Warning);
+
+        assert_eq!(
+            LogLevelSpec::from_str("below_model=off").unwrap(),
+            LogLevelSpec {
+                default: FilterLevel::Info,
+                modules: vec![("below_model".to_owned(), FilterLevel::Off)],
+            }
+        );
+        assert!(LogLevelSpec::from_str("loud").is_err());
+        assert!(LogLevelSpec::from_str("below_model=loud").is_err());
+    }
+
+    /// Writer whose contents can be read after it is moved into a logger
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuf {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    #[test]
+    fn json_drain() {
+        let file = SharedBuf::default();
+        let term = SharedBuf::default();
+        let drain = JsonDrain::new(CompoundDecorator::new(file.clone(), term.clone()));
+        let drain = LevelSpecDrain::new(drain, LogLevelSpec::new(FilterLevel::Debug));
+        let logger = slog::Logger::root(Mutex::new(drain).fuse(), o!("host" => "host1"));
+        info!(logger, "Collected {} samples", 3; "interval_s" => 5, "compressed" => true);
+        debug!(logger, "debug");
+        trace!(logger, "dropped");
+
+        let lines = file
+            .contents()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("Invalid JSON"))
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["level"], "INFO");
+        assert_eq!(lines[0]["module"], "below_common::logutil::test");
+        assert_eq!(lines[0]["msg"], "Collected 3 samples");
+        assert_eq!(lines[0]["host"], "host1");
+        assert_eq!(lines[0]["interval_s"], 5);
+        assert_eq!(lines[0]["compressed"], true);
+        assert_eq!(lines[1]["level"], "DEBUG");
+    }
+
+    #[test]
+    fn color_only_on_term() {
+        let file = SharedBuf::default();
+        let term = SharedBuf::default();
+        let decorator = CompoundDecorator::new(file.clone(), term.clone()).with_color(true);
+        let drain = slog_term::FullFormat::new(decorator).build();
+        let logger = slog::Logger::root(Mutex::new(drain).fuse(), o!());
+        info!(logger, "hello");
+
+        assert!(
+            file.contents().contains("INFO hello"),
+            "{}",
+            file.contents()
+        );
+        assert!(!file.contents().contains('\x1b'), "{}", file.contents());
+        assert!(
+            term.contents().contains("\x1b[32mINFO\x1b[0m hello"),
+            "{}",
+            term.contents()
+        );
+    }
+}
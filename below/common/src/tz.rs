@@ -0,0 +1,525 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Named timezones, e.g. "America/New_York", read from the system zoneinfo
+//! database so that dates can be given in a timezone other than the local
+//! one.
+//!
+//! Zoneinfo files are in the TZif format of RFC 8536. Times past the last
+//! transition in the file follow the POSIX TZ rule in its footer.
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use chrono::Datelike;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+
+const ZONEINFO_DIR: &str = "/usr/share/zoneinfo";
+const TZIF_MAGIC: &[u8] = b"TZif";
+const TZIF_HEADER_LEN: usize = 44;
+const SECS_PER_DAY: i64 = 86400;
+
+/// Date of a POSIX TZ rule transition within a year
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RuleDate {
+    /// Jn: day n of the year, 1 to 365, not counting February 29
+    Julian1(u32),
+    /// n: day n of the year, 0 to 365, counting February 29
+    Julian0(u32),
+    /// Mm.w.d: day d (0 is Sunday) of week w (5 is the last) of month m
+    MonthWeekDay(u32, u32, u32),
+}
+
+impl RuleDate {
+    fn date(&self, year: i32) -> Option<NaiveDate> {
+        match *self {
+            RuleDate::Julian1(n) => {
+                let leap = NaiveDate::from_ymd_opt(year, 2, 29).is_some();
+                NaiveDate::from_yo_opt(year, if leap && n >= 60 { n + 1 } else { n })
+            }
+            RuleDate::Julian0(n) => NaiveDate::from_yo_opt(year, n + 1),
+            RuleDate::MonthWeekDay(month, week, weekday) => {
+                let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+                let first_weekday = first.weekday().num_days_from_sunday();
+                let mut day = 1 + (weekday + 7 - first_weekday) % 7 + (week - 1) * 7;
+                while NaiveDate::from_ymd_opt(year, month, day).is_none() {
+                    day -= 7;
+                }
+                NaiveDate::from_ymd_opt(year, month, day)
+            }
+        }
+    }
+}
+
+/// Daylight saving time of a POSIX TZ rule
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct DstRule {
+    /// Seconds east of UTC
+    offset: i32,
+    start: (RuleDate, i32),
+    end: (RuleDate, i32),
+}
+
+/// POSIX TZ rule, e.g. "EST5EDT,M3.2.0,M11.1.0"
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PosixRule {
+    /// Seconds east of UTC
+    std_offset: i32,
+    dst: Option<DstRule>,
+}
+
+impl PosixRule {
+    fn parse(rule: &str) -> Result<Self> {
+        let mut rest = rule;
+        skip_name(&mut rest)?;
+        let std_offset = -parse_rule_time(&mut rest)?;
+        if rest.is_empty() {
+            return Ok(Self {
+                std_offset,
+                dst: None,
+            });
+        }
+        skip_name(&mut rest)?;
+        let offset = if rest.starts_with(',') || rest.is_empty() {
+            std_offset + 3600
+        } else {
+            -parse_rule_time(&mut rest)?
+        };
+        let (start, end) = match rest.strip_prefix(',').and_then(|r| r.split_once(',')) {
+            Some((start, end)) => (parse_transition(start)?, parse_transition(end)?),
+            None => bail!("Unsupported TZ rule \"{}\"", rule),
+        };
+        Ok(Self {
+            std_offset,
+            dst: Some(DstRule { offset, start, end }),
+        })
+    }
+
+    fn offset_at(&self, utc: i64) -> i32 {
+        let dst = match self.dst {
+            Some(dst) => dst,
+            None => return self.std_offset,
+        };
+        let year = match chrono::DateTime::from_timestamp(utc + self.std_offset as i64, 0) {
+            Some(time) => time.year(),
+            None => return self.std_offset,
+        };
+        // Transitions are given in the local time in effect before them
+        let transition = |(date, time): (RuleDate, i32), offset: i32| {
+            date.date(year).map(|date| {
+                date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() + time as i64
+                    - offset as i64
+            })
+        };
+        match (
+            transition(dst.start, self.std_offset),
+            transition(dst.end, dst.offset),
+        ) {
+            (Some(start), Some(end)) if start < end && (start..end).contains(&utc) => dst.offset,
+            // Southern hemisphere, daylight saving time spans the new year
+            (Some(start), Some(end)) if start >= end && !(end..start).contains(&utc) => dst.offset,
+            _ => self.std_offset,
+        }
+    }
+}
+
+/// Skip a timezone abbreviation, e.g. "EST" or "<+03>"
+fn skip_name(rest: &mut &str) -> Result<()> {
+    let len = if let Some(quoted) = rest.strip_prefix('<') {
+        match quoted.find('>') {
+            Some(end) => end + 2,
+            None => bail!("Unterminated timezone name in TZ rule"),
+        }
+    } else {
+        rest.find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len())
+    };
+    if len < 3 {
+        bail!("Invalid timezone name in TZ rule");
+    }
+    *rest = &rest[len..];
+    Ok(())
+}
+
+/// Parse a time of the form [+-]hh[:mm[:ss]] into seconds
+fn parse_rule_time(rest: &mut &str) -> Result<i32> {
+    let len = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == ':' || c == '+' || c == '-'))
+        .unwrap_or(rest.len());
+    let (time, remaining) = rest.split_at(len);
+    *rest = remaining;
+    let (sign, time) = match time.strip_prefix('-') {
+        Some(time) => (-1, time),
+        None => (1, time.strip_prefix('+').unwrap_or(time)),
+    };
+    let mut secs = 0;
+    let mut parts = 0;
+    for (part, scale) in time.split(':').zip([3600, 60, 1]) {
+        secs += part
+            .parse::<i32>()
+            .map_err(|_| anyhow!("Invalid time \"{}\" in TZ rule", time))?
+            * scale;
+        parts += 1;
+    }
+    if parts == 0 || time.split(':').count() > 3 {
+        bail!("Invalid time \"{}\" in TZ rule", time);
+    }
+    Ok(sign * secs)
+}
+
+/// Parse a transition of the form date[/time], time defaulting to 02:00
+fn parse_transition(transition: &str) -> Result<(RuleDate, i32)> {
+    let (date, time) = match transition.split_once('/') {
+        Some((date, mut time)) => (date, parse_rule_time(&mut time)?),
+        None => (transition, 2 * 3600),
+    };
+    let invalid = || anyhow!("Invalid transition date \"{}\" in TZ rule", date);
+    let date = if let Some(mwd) = date.strip_prefix('M') {
+        let parts = mwd
+            .split('.')
+            .map(|part| part.parse::<u32>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>>>()?;
+        match parts[..] {
+            [m @ 1..=12, w @ 1..=5, d @ 0..=6] => RuleDate::MonthWeekDay(m, w, d),
+            _ => return Err(invalid()),
+        }
+    } else if let Some(n) = date.strip_prefix('J') {
+        match n.parse() {
+            Ok(n @ 1..=365) => RuleDate::Julian1(n),
+            _ => return Err(invalid()),
+        }
+    } else {
+        match date.parse() {
+            Ok(n @ 0..=365) => RuleDate::Julian0(n),
+            _ => return Err(invalid()),
+        }
+    };
+    Ok((date, time))
+}
+
+fn read_be_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap())
+}
+
+/// A timezone from the zoneinfo database
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeZone {
+    name: String,
+    /// Transition times and the index of the offset taking effect at each
+    transitions: Vec<(i64, usize)>,
+    /// Offsets in seconds east of UTC
+    offsets: Vec<i32>,
+    rule: Option<PosixRule>,
+}
+
+impl TimeZone {
+    /// Whether `name` looks like a zoneinfo timezone name, e.g.
+    /// "Europe/London", rather than part of a date
+    pub fn is_zone_name(name: &str) -> bool {
+        name.contains('/')
+            && name.starts_with(|c: char| c.is_ascii_uppercase())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "/_+-".contains(c))
+            && !name.split('/').any(|part| part.is_empty())
+    }
+
+    /// Load a timezone by name, e.g. "America/New_York"
+    pub fn load(name: &str) -> Result<Self> {
+        if !Self::is_zone_name(name) {
+            bail!("Invalid timezone name \"{}\"", name);
+        }
+        let path = Path::new(ZONEINFO_DIR).join(name);
+        let data =
+            std::fs::read(&path).with_context(|| format!("Unknown timezone \"{}\"", name))?;
+        Self::from_tzif(name, &data)
+            .with_context(|| format!("Failed to read timezone file {}", path.display()))
+    }
+
+    /// Parse a timezone from TZif data
+    pub fn from_tzif(name: &str, data: &[u8]) -> Result<Self> {
+        let (version, mut block) = Self::parse_header(data)?;
+        if version >= b'2' {
+            // Skip the 32-bit data in favor of the 64-bit data that follows
+            let v2 = data
+                .get(block.len_with(4)..)
+                .ok_or_else(|| anyhow!("Truncated TZif file"))?;
+            let (_, v2_block) = Self::parse_header(v2)?;
+            return Self::parse_block(name, v2, v2_block, 8);
+        }
+        // Version 1 files have neither 64-bit data nor a footer
+        block.footer = false;
+        Self::parse_block(name, data, block, 4)
+    }
+
+    fn parse_header(data: &[u8]) -> Result<(u8, BlockLayout)> {
+        if data.len() < TZIF_HEADER_LEN || &data[..4] != TZIF_MAGIC {
+            bail!("Not a TZif file");
+        }
+        let count = |idx: usize| read_be_u32(data, 20 + idx * 4) as usize;
+        Ok((
+            data[4],
+            BlockLayout {
+                isutcnt: count(0),
+                isstdcnt: count(1),
+                leapcnt: count(2),
+                timecnt: count(3),
+                typecnt: count(4),
+                charcnt: count(5),
+                footer: true,
+            },
+        ))
+    }
+
+    fn parse_block(name: &str, data: &[u8], block: BlockLayout, time_size: usize) -> Result<Self> {
+        if data.len() < block.len_with(time_size) || block.typecnt == 0 {
+            bail!("Truncated TZif file");
+        }
+        let mut pos = TZIF_HEADER_LEN;
+        let times = (0..block.timecnt)
+            .map(|i| {
+                let at = pos + i * time_size;
+                if time_size == 8 {
+                    i64::from_be_bytes(data[at..at + 8].try_into().unwrap())
+                } else {
+                    read_be_u32(data, at) as i32 as i64
+                }
+            })
+            .collect::<Vec<_>>();
+        pos += block.timecnt * time_size;
+        let transitions = times
+            .into_iter()
+            .zip(&data[pos..pos + block.timecnt])
+            .map(|(time, idx)| (time, *idx as usize))
+            .collect::<Vec<_>>();
+        pos += block.timecnt;
+        let offsets = (0..block.typecnt)
+            .map(|i| read_be_u32(data, pos + i * 6) as i32)
+            .collect::<Vec<_>>();
+        if transitions.iter().any(|(_, idx)| *idx >= offsets.len()) {
+            bail!("Invalid local time type in TZif file");
+        }
+
+        let rule = if block.footer {
+            let footer = &data[block.len_with(time_size)..];
+            match std::str::from_utf8(footer)
+                .ok()
+                .and_then(|footer| footer.strip_prefix('\n'))
+                .and_then(|footer| footer.split_once('\n'))
+            {
+                Some(("", _)) | None => None,
+                Some((rule, _)) => Some(PosixRule::parse(rule)?),
+            }
+        } else {
+            None
+        };
+
+        Ok(Self {
+            name: name.to_owned(),
+            transitions,
+            offsets,
+            rule,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Offset from UTC in seconds east at the UTC timestamp `utc`
+    pub fn offset_at(&self, utc: i64) -> i32 {
+        match self.transitions.iter().rposition(|(time, _)| *time <= utc) {
+            // Before the first transition, the first local time type applies
+            None => self.offsets[0],
+            Some(idx) if idx + 1 == self.transitions.len() && self.rule.is_some() => {
+                self.rule.unwrap().offset_at(utc)
+            }
+            Some(idx) => self.offsets[self.transitions[idx].1],
+        }
+    }
+
+    /// Local time in this timezone at the UTC timestamp `utc`
+    pub fn to_local(&self, utc: i64) -> Option<NaiveDateTime> {
+        chrono::DateTime::from_timestamp(utc + self.offset_at(utc) as i64, 0)
+            .map(|time| time.naive_utc())
+    }
+
+    /// UTC timestamp of the local time `local` in this timezone. A local
+    /// time repeated when clocks go back resolves to its earlier occurrence.
+    /// A local time skipped when clocks go forward is taken in the offset
+    /// before the change, e.g. 02:30 on the day clocks go forward from 02:00
+    /// to 03:00 is 03:30.
+    pub fn from_local(&self, local: NaiveDateTime) -> i64 {
+        let local = local.and_utc().timestamp();
+        // Offsets in effect shortly before and after cover any transition
+        // around the local time
+        let before = self.offset_at(local - SECS_PER_DAY);
+        let after = self.offset_at(local + SECS_PER_DAY);
+        [before, after]
+            .into_iter()
+            .map(|offset| local - offset as i64)
+            .filter(|utc| self.offset_at(*utc) as i64 == local - utc)
+            .min()
+            .unwrap_or(local - before as i64)
+    }
+}
+
+/// Sizes of a TZif data block, in counts of its fields
+#[derive(Clone, Copy, Debug)]
+struct BlockLayout {
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+    /// Whether a footer with a POSIX TZ rule follows the block
+    footer: bool,
+}
+
+impl BlockLayout {
+    /// Length of the header and data block for times of `time_size` bytes
+    fn len_with(&self, time_size: usize) -> usize {
+        TZIF_HEADER_LEN
+            + self.timecnt * (time_size + 1)
+            + self.typecnt * 6
+            + self.charcnt
+            + self.leapcnt * (time_size + 4)
+            + self.isstdcnt
+            + self.isutcnt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a version 2 TZif file with the given transitions, offsets and
+    /// footer rule
+    fn tzif(transitions: &[(i64, u8)], offsets: &[i32], rule: &str) -> Vec<u8> {
+        let header = |time_size: usize| {
+            let mut header = b"TZif2".to_vec();
+            header.extend([0; 15]);
+            for count in [0, 0, 0, transitions.len(), offsets.len(), 4] {
+                header.extend((count as u32).to_be_bytes());
+            }
+            let mut block = Vec::new();
+            for (time, _) in transitions {
+                if time_size == 8 {
+                    block.extend(time.to_be_bytes());
+                } else {
+                    block.extend((*time as i32).to_be_bytes());
+                }
+            }
+            block.extend(transitions.iter().map(|(_, idx)| *idx));
+            for offset in offsets {
+                block.extend(offset.to_be_bytes());
+                block.extend([0, 0]);
+            }
+            block.extend(b"XXX\0");
+            header.extend(block);
+            header
+        };
+        let mut data = header(4);
+        data.extend(header(8));
+        data.extend(format!("\n{}\n", rule).bytes());
+        data
+    }
+
+    fn utc(date: &str) -> i64 {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+            .and_utc()
+            .timestamp()
+    }
+
+    fn local(date: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_posix_rule() {
+        let rule = PosixRule::parse("EST5EDT,M3.2.0,M11.1.0").unwrap();
+        assert_eq!(rule.std_offset, -5 * 3600);
+        // DST starts 2024-03-10 02:00 EST and ends 2024-11-03 02:00 EDT
+        assert_eq!(rule.offset_at(utc("2024-03-10 06:59:59")), -5 * 3600);
+        assert_eq!(rule.offset_at(utc("2024-03-10 07:00:00")), -4 * 3600);
+        assert_eq!(rule.offset_at(utc("2024-11-03 05:59:59")), -4 * 3600);
+        assert_eq!(rule.offset_at(utc("2024-11-03 06:00:00")), -5 * 3600);
+
+        // Southern hemisphere
+        let rule = PosixRule::parse("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        assert_eq!(rule.offset_at(utc("2024-01-15 00:00:00")), 11 * 3600);
+        assert_eq!(rule.offset_at(utc("2024-06-15 00:00:00")), 10 * 3600);
+
+        let rule = PosixRule::parse("<+0530>-5:30").unwrap();
+        assert_eq!(rule.offset_at(utc("2024-06-15 00:00:00")), 5 * 3600 + 1800);
+
+        assert!(PosixRule::parse("EST5EDT,M13.2.0,M11.1.0").is_err());
+        assert!(PosixRule::parse("E5").is_err());
+    }
+
+    #[test]
+    fn test_timezone() {
+        // New York, with the 2023 transitions in the file and the rule after
+        let data = tzif(
+            &[
+                (utc("2023-03-12 07:00:00"), 1),
+                (utc("2023-11-05 06:00:00"), 0),
+            ],
+            &[-5 * 3600, -4 * 3600],
+            "EST5EDT,M3.2.0,M11.1.0",
+        );
+        let tz = TimeZone::from_tzif("America/New_York", &data).unwrap();
+        assert_eq!(tz.offset_at(utc("2023-01-01 00:00:00")), -5 * 3600);
+        assert_eq!(tz.offset_at(utc("2023-07-01 00:00:00")), -4 * 3600);
+        assert_eq!(tz.offset_at(utc("2024-07-01 00:00:00")), -4 * 3600);
+        assert_eq!(tz.offset_at(utc("2024-12-01 00:00:00")), -5 * 3600);
+
+        assert_eq!(
+            tz.from_local(local("2024-07-01 12:00:00")),
+            utc("2024-07-01 16:00:00")
+        );
+        // Repeated hour when clocks go back resolves to the first one
+        assert_eq!(
+            tz.from_local(local("2024-11-03 01:30:00")),
+            utc("2024-11-03 05:30:00")
+        );
+        // Skipped hour when clocks go forward is taken in standard time
+        assert_eq!(
+            tz.from_local(local("2024-03-10 02:30:00")),
+            utc("2024-03-10 07:30:00")
+        );
+        assert_eq!(
+            tz.to_local(utc("2024-03-10 07:30:00")),
+            Some(local("2024-03-10 03:30:00"))
+        );
+
+        assert!(TimeZone::from_tzif("Invalid/Zone", b"not tzif").is_err());
+    }
+
+    #[test]
+    fn test_is_zone_name() {
+        assert!(TimeZone::is_zone_name("America/New_York"));
+        assert!(TimeZone::is_zone_name("Etc/GMT+5"));
+        assert!(!TimeZone::is_zone_name("UTC"));
+        assert!(!TimeZone::is_zone_name("09/20/2019"));
+        assert!(!TimeZone::is_zone_name("America/../../etc/passwd"));
+        assert!(!TimeZone::is_zone_name("America//New_York"));
+    }
+}
@@ -14,8 +14,10 @@
 
 use std::cell::RefCell;
 use std::cell::RefMut;
+use std::fs::File;
 use std::io;
 use std::io::Read;
+use std::os::unix::fs::FileExt;
 use std::time::Duration;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
@@ -24,6 +26,8 @@ use std::time::UNIX_EPOCH;
 use chrono::prelude::*;
 
 const BELOW_RC: &str = "/.config/below/belowrc";
+const CGROUP_FOLDS: &str = "/.config/below/cgroup_folds";
+const CMD_HISTORY: &str = "/.config/below/cmd_history";
 
 /// Execute an expression every n times. For example
 /// `every_n!(1 + 2, println!("I'm mod 3")` will print on the 1st,
@@ -54,6 +58,10 @@ pub fn get_system_time(timestamp: u64) -> SystemTime {
 }
 
 fn convert(val: f64, base: f64, units: &[&'static str]) -> String {
+    // Changes, e.g. of a size since the last sample, can be negative
+    if val < 0_f64 {
+        return format!("-{}", convert(-val, base, units));
+    }
     if val < 1_f64 {
         return format!("{:.1} {}", val, units[0]);
     }
@@ -88,6 +96,27 @@ pub fn convert_duration(val: u64) -> String {
     convert(val as f64, 1000_f64, UNITS)
 }
 
+/// Render `pct` percent as a horizontal bar of `width` cells, e.g.
+/// "████▌     " for 45% over 10 cells. Values are clamped to [0, 100].
+pub fn render_bar(pct: f64, width: usize) -> String {
+    const PARTIAL: &[char] = &[' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    let eighths = (pct.clamp(0.0, 100.0) / 100.0 * (width * 8) as f64).round() as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    if eighths / 8 < width {
+        bar.push(PARTIAL[eighths % 8]);
+        bar.push_str(&" ".repeat(width - eighths / 8 - 1));
+    }
+    bar
+}
+
+/// Render `pct` percent as a single vertical block, from '▁' to '█'. Values
+/// are clamped to [0, 100] and anything above 0 shows at least '▁'.
+pub fn render_level(pct: f64) -> char {
+    const LEVELS: &[char] = &[' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let level = (pct.clamp(0.0, 100.0) / 100.0 * 8.0).ceil() as usize;
+    LEVELS[level]
+}
+
 pub fn get_prefix(collapsed: bool) -> &'static str {
     if collapsed { "└+ " } else { "└─ " }
 }
@@ -140,14 +169,6 @@ pub fn systemtime_to_datetime(system_time: SystemTime) -> String {
     timestamp_to_datetime(&(get_unix_timestamp(system_time) as i64))
 }
 
-pub fn is_cpu_significant(v: f64) -> Option<cursive::theme::BaseColor> {
-    if v > 100.0 {
-        Some(cursive::theme::BaseColor::Red)
-    } else {
-        None
-    }
-}
-
 /// Get the belowrc filename.
 pub fn get_belowrc_filename() -> String {
     format!(
@@ -157,6 +178,24 @@ pub fn get_belowrc_filename() -> String {
     )
 }
 
+/// Get the filename where the view saves cgroup tree fold state.
+pub fn get_cgroup_folds_filename() -> String {
+    format!(
+        "{}{}",
+        std::env::var("HOME").expect("Fail to obtain HOME env var"),
+        CGROUP_FOLDS
+    )
+}
+
+/// Get the filename where the view saves the command palette history.
+pub fn get_cmd_history_filename() -> String {
+    format!(
+        "{}{}",
+        std::env::var("HOME").expect("Fail to obtain HOME env var"),
+        CMD_HISTORY
+    )
+}
+
 /// The dump section key for belowrc
 pub fn get_belowrc_dump_section_key() -> &'static str {
     "dump"
@@ -206,6 +245,42 @@ pub fn read_kern_file_to_internal_buffer<R: Read>(
     .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid UTF-8 data"))
 }
 
+/// Same as `read_kern_file_to_internal_buffer`, but reads `file` from the
+/// start with pread, so the file offset is neither used nor moved.
+pub fn pread_kern_file_to_internal_buffer<'a>(
+    buffer: &'a RefCell<Vec<u8>>,
+    file: &File,
+) -> io::Result<RefMut<'a, str>> {
+    const BUFFER_CHUNK_SIZE: usize = 1 << 16;
+
+    let mut buffer = buffer.borrow_mut();
+    let mut total_read = 0;
+
+    loop {
+        let buf_len = buffer.len();
+        if buf_len < total_read + BUFFER_CHUNK_SIZE {
+            buffer.resize(buf_len + BUFFER_CHUNK_SIZE, 0);
+        }
+
+        match file.read_at(&mut buffer[total_read..], total_read as u64) {
+            Ok(0) => break,
+            Ok(n) => {
+                total_read += n;
+                if n < BUFFER_CHUNK_SIZE {
+                    break;
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    RefMut::filter_map(buffer, |vec| {
+        std::str::from_utf8_mut(&mut vec[..total_read]).ok()
+    })
+    .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid UTF-8 data"))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -237,6 +312,27 @@ mod test {
         assert_eq!(convert_bytes(1024_f64.powi(3) - 1.0), "1024 MB".to_owned());
         assert_eq!(convert_bytes(1024_f64.powi(3)), "1 GB".to_owned());
         assert_eq!(convert_bytes(1024_f64.powi(4)), "1 TB".to_owned());
+        assert_eq!(convert_bytes(-1_076.0), "-1.1 KB".to_owned());
+    }
+
+    #[test]
+    fn test_render_bar() {
+        assert_eq!(render_bar(0.0, 4), "    ");
+        assert_eq!(render_bar(50.0, 4), "██  ");
+        assert_eq!(render_bar(45.0, 10), "████▌     ");
+        assert_eq!(render_bar(100.0, 4), "████");
+        assert_eq!(render_bar(250.0, 4), "████");
+        assert_eq!(render_bar(-1.0, 2), "  ");
+        assert_eq!(render_bar(f64::NAN, 2), "  ");
+    }
+
+    #[test]
+    fn test_render_level() {
+        assert_eq!(render_level(0.0), ' ');
+        assert_eq!(render_level(0.1), '▁');
+        assert_eq!(render_level(50.0), '▄');
+        assert_eq!(render_level(100.0), '█');
+        assert_eq!(render_level(400.0), '█');
     }
 
     #[test]
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
+use std::ffi::OsString;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
@@ -22,12 +24,17 @@ use anyhow::Result;
 use serde::Deserialize;
 use serde::Serialize;
 
+mod overrides;
 #[cfg(test)]
 mod test;
 
+pub use overrides::parse_override;
+pub use overrides::ENV_PREFIX;
+
 pub const BELOW_DEFAULT_CONF: &str = "/etc/below/below.conf";
 const BELOW_DEFAULT_LOG: &str = "/var/log/below";
 const BELOW_DEFAULT_STORE: &str = "/var/log/below/store";
+const BELOW_DEFAULT_CONTROL_SOCKET: &str = "/run/below/control.sock";
 
 /// Global below config
 pub static BELOW_CONFIG: OnceLock<BelowConfig> = OnceLock::new();
@@ -39,6 +46,7 @@ pub struct BelowConfig {
     pub log_dir: PathBuf,
     pub store_dir: PathBuf,
     pub cgroup_root: PathBuf,
+    pub extra_cgroup_roots: BTreeMap<String, PathBuf>,
     pub cgroup_filter_out: String,
     pub enable_gpu_stats: bool,
     pub use_rgpu_for_gpu_stats: bool,
@@ -49,6 +57,25 @@ pub struct BelowConfig {
     pub enable_ksm_stats: bool,
     pub enable_resctrl_stats: bool,
     pub enable_tc_stats: bool,
+    pub enable_file_io_stats: bool,
+    pub enable_process_net_stats: bool,
+    pub enable_smaps_rollup_stats: bool,
+    pub enable_perf_counter_stats: bool,
+    pub proc_collection_workers: usize,
+    pub proc_batched_reads: bool,
+    pub cgroup_limit_refresh_samples: u64,
+    pub enable_stack_sampling: bool,
+    pub stack_sampling_cpu_threshold_pct: f64,
+    pub stack_sampling_consecutive_samples: u32,
+    pub cmdline_argv0_only: bool,
+    pub cmdline_redact_regex: String,
+    pub psi_trigger_cgroups: Vec<String>,
+    pub psi_trigger_threshold_us: u64,
+    pub psi_trigger_window_us: u64,
+    pub enable_oom_killer_events: bool,
+    pub enable_journal_errors: bool,
+    pub journal_error_units: Vec<String>,
+    pub control_socket: PathBuf,
 }
 
 impl Default for BelowConfig {
@@ -57,6 +84,7 @@ impl Default for BelowConfig {
             log_dir: BELOW_DEFAULT_LOG.into(),
             store_dir: BELOW_DEFAULT_STORE.into(),
             cgroup_root: cgroupfs::DEFAULT_CG_ROOT.into(),
+            extra_cgroup_roots: BTreeMap::new(),
             cgroup_filter_out: String::new(),
             enable_gpu_stats: false,
             use_rgpu_for_gpu_stats: true,
@@ -67,6 +95,25 @@ impl Default for BelowConfig {
             enable_ksm_stats: false,
             enable_resctrl_stats: false,
             enable_tc_stats: false,
+            enable_file_io_stats: false,
+            enable_process_net_stats: false,
+            enable_smaps_rollup_stats: false,
+            enable_perf_counter_stats: false,
+            proc_collection_workers: 1,
+            proc_batched_reads: false,
+            cgroup_limit_refresh_samples: 1,
+            enable_stack_sampling: false,
+            stack_sampling_cpu_threshold_pct: 90.0,
+            stack_sampling_consecutive_samples: 3,
+            cmdline_argv0_only: false,
+            cmdline_redact_regex: String::new(),
+            psi_trigger_cgroups: Vec::new(),
+            psi_trigger_threshold_us: 100_000,
+            psi_trigger_window_us: 1_000_000,
+            enable_oom_killer_events: false,
+            enable_journal_errors: false,
+            journal_error_units: Vec::new(),
+            control_socket: BELOW_DEFAULT_CONTROL_SOCKET.into(),
         }
     }
 }
@@ -81,6 +128,22 @@ impl BelowConfig {
         }
     }
 
+    /// Load the config file at `path`, then apply overrides from `BELOW_*`
+    /// environment variables in `env_vars`, then `cli_overrides`, so later
+    /// layers take precedence.
+    pub fn load_layered<I>(
+        path: &Path,
+        env_vars: I,
+        cli_overrides: &[(String, String)],
+    ) -> Result<Self>
+    where
+        I: IntoIterator<Item = (OsString, OsString)>,
+    {
+        BelowConfig::load(path)?
+            .apply_env_overrides(env_vars)?
+            .apply_overrides(cli_overrides.iter().cloned(), "command line")
+    }
+
     fn load_exists(path: &Path) -> Result<Self> {
         let string_config = match fs::read_to_string(path) {
             Ok(sc) => sc,
@@ -0,0 +1,130 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Overrides of config file values. Each override is a key of the config
+//! file and a string value, which is parsed according to the type of the
+//! value it replaces.
+
+use std::ffi::OsString;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use toml::Value;
+
+use crate::BelowConfig;
+
+/// Prefix of environment variables overriding config values, e.g.
+/// BELOW_STORE_DIR overrides store_dir.
+pub const ENV_PREFIX: &str = "BELOW_";
+
+/// Parse a "key=value" override given on the command line.
+pub fn parse_override(s: &str) -> Result<(String, String)> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        _ => bail!("Expected KEY=VALUE, got: {}", s),
+    }
+}
+
+impl BelowConfig {
+    /// Apply overrides from `BELOW_*` environment variables. Variables that
+    /// do not match a config key are ignored, as the prefix is not reserved
+    /// for config. Matching variables whose value is not valid UTF-8 are
+    /// skipped with a warning.
+    pub fn apply_env_overrides<I>(self, env_vars: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (OsString, OsString)>,
+    {
+        let keys = self.to_table()?;
+        let overrides = env_vars
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let name = name.into_string().ok()?;
+                let key = name.strip_prefix(ENV_PREFIX)?.to_ascii_lowercase();
+                if !keys.contains_key(&key) {
+                    return None;
+                }
+                match value.into_string() {
+                    Ok(value) => Some((key, value)),
+                    Err(_) => {
+                        eprintln!("Ignoring {}: value is not valid UTF-8", name);
+                        None
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        self.apply_overrides(overrides, "environment")
+    }
+
+    /// Apply `(key, value)` overrides. `source` names where the overrides
+    /// come from in errors.
+    pub fn apply_overrides<I>(self, overrides: I, source: &str) -> Result<Self>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut table = self.to_table()?;
+        for (key, value) in overrides {
+            let parsed = match table.get(&key) {
+                Some(Value::String(_)) => Value::String(value),
+                Some(Value::Boolean(_)) => Value::Boolean(parse_value(&key, &value, source)?),
+                Some(Value::Integer(_)) => Value::Integer(parse_value(&key, &value, source)?),
+                Some(Value::Float(_)) => Value::Float(parse_value(&key, &value, source)?),
+                // Lists and tables are written inline as in the config file,
+                // e.g. ["a", "b"] or { label = "/path" }
+                Some(Value::Array(_)) | Some(Value::Table(_)) => {
+                    parse_inline_value(&key, &value, source)?
+                }
+                Some(_) => bail!("Config key {} cannot be overridden from {}", key, source),
+                None => bail!("Unknown config key {} from {}", key, source),
+            };
+            table.insert(key, parsed);
+        }
+        Value::Table(table)
+            .try_into()
+            .with_context(|| format!("Invalid config overrides from {}", source))
+    }
+
+    fn to_table(&self) -> Result<toml::Table> {
+        match Value::try_from(self).context("Failed to serialize config")? {
+            Value::Table(table) => Ok(table),
+            _ => Err(anyhow!("Config did not serialize to a table")),
+        }
+    }
+}
+
+fn parse_value<T>(key: &str, value: &str, source: &str) -> Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    value.parse().with_context(|| {
+        format!(
+            "Invalid value for config key {} from {}: {}",
+            key, source, value
+        )
+    })
+}
+
+fn parse_inline_value(key: &str, value: &str, source: &str) -> Result<Value> {
+    let mut table: toml::Table = format!("value = {}", value).parse().with_context(|| {
+        format!(
+            "Invalid value for config key {} from {}: {}",
+            key, source, value
+        )
+    })?;
+    table
+        .remove("value")
+        .ok_or_else(|| anyhow!("Missing value for config key {} from {}", key, source))
+}
@@ -12,7 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::ffi::OsString;
 use std::io::Write;
+use std::os::unix::ffi::OsStringExt;
 
 use tempfile::TempDir;
 
@@ -33,6 +35,10 @@ fn test_config_default() {
     assert_eq!(below_config.cgroup_filter_out, String::new());
     assert!(!below_config.enable_gpu_stats);
     assert!(!below_config.enable_btrfs_stats);
+    assert_eq!(
+        below_config.control_socket.to_string_lossy(),
+        "/run/below/control.sock"
+    );
 }
 
 #[test]
@@ -77,6 +83,9 @@ fn test_config_load_success() {
         cgroup_filter_out = 'user.slice'
         # I'm a comment
         something_else = "demacia"
+
+        [extra_cgroup_roots]
+        unified = '/sys/fs/cgroup/unified'
     "#;
     file.write_all(config_str.as_bytes())
         .expect("Faild to write temp conf file during testing ignore");
@@ -90,6 +99,10 @@ fn test_config_load_success() {
     assert_eq!(below_config.store_dir.to_string_lossy(), "/var/log/below");
     assert_eq!(below_config.cgroup_root.to_string_lossy(), "/sys/fs/cgroup");
     assert_eq!(below_config.cgroup_filter_out, "user.slice");
+    assert_eq!(
+        below_config.extra_cgroup_roots["unified"].to_string_lossy(),
+        "/sys/fs/cgroup/unified"
+    );
 }
 
 #[test]
@@ -151,3 +164,88 @@ fn test_config_partial_load() {
         "/var/log/below/store"
     );
 }
+
+#[test]
+fn test_config_layered_overrides() {
+    let tempdir = TempDir::with_prefix("below_config_load.").expect("Failed to create temp dir");
+    let path = tempdir.path().join("below.config");
+    std::fs::write(
+        &path,
+        r#"
+        store_dir = '/from/file'
+        log_dir = '/from/file/log'
+        enable_btrfs_stats = true
+    "#,
+    )
+    .expect("Faild to write temp conf file during testing ignore");
+
+    let env_vars = vec![
+        ("BELOW_STORE_DIR".into(), "/from/env".into()),
+        ("BELOW_BTRFS_SAMPLES".into(), "7".into()),
+        (
+            "BELOW_PSI_TRIGGER_CGROUPS".into(),
+            r#"["a.slice", "b.slice"]"#.into(),
+        ),
+        (
+            "BELOW_EXTRA_CGROUP_ROOTS".into(),
+            r#"{ unified = "/sys/fs/cgroup/unified" }"#.into(),
+        ),
+        // Not valid UTF-8
+        (
+            "BELOW_LOG_DIR".into(),
+            OsString::from_vec(b"/\xff".to_vec()),
+        ),
+        (OsString::from_vec(b"BELOW_\xff".to_vec()), "1".into()),
+        // Not a config key
+        ("BELOW_SOMETHING_ELSE".into(), "demacia".into()),
+        ("HOME".into(), "/root".into()),
+    ];
+    let cli_overrides = vec![
+        parse_override("store_dir=/from/cli").expect("Failed to parse override"),
+        parse_override("btrfs_min_pct=2.5").expect("Failed to parse override"),
+    ];
+    let below_config = match BelowConfig::load_layered(&path, env_vars, &cli_overrides) {
+        Ok(b) => b,
+        Err(e) => panic!("{:#}", e),
+    };
+    assert_eq!(below_config.store_dir.to_string_lossy(), "/from/cli");
+    assert_eq!(below_config.log_dir.to_string_lossy(), "/from/file/log");
+    assert!(below_config.enable_btrfs_stats);
+    assert_eq!(below_config.btrfs_samples, 7);
+    assert_eq!(below_config.btrfs_min_pct, 2.5);
+    assert_eq!(below_config.psi_trigger_cgroups, vec!["a.slice", "b.slice"]);
+    assert_eq!(
+        below_config.extra_cgroup_roots,
+        BTreeMap::from([("unified".to_owned(), "/sys/fs/cgroup/unified".into())])
+    );
+}
+
+#[test]
+fn test_config_override_failed() {
+    assert!(parse_override("store_dir").is_err());
+    assert!(parse_override("=/tmp").is_err());
+
+    let err = BelowConfig::default()
+        .apply_overrides(
+            vec![("enable_gpu_stats".to_owned(), "yes".to_owned())],
+            "command line",
+        )
+        .expect_err("Bool override should not parse");
+    assert!(format!("{:#}", err).starts_with("Invalid value for config key enable_gpu_stats"));
+
+    let err = BelowConfig::default()
+        .apply_overrides(
+            vec![("psi_trigger_cgroups".to_owned(), "[a.slice".to_owned())],
+            "command line",
+        )
+        .expect_err("List override should not parse");
+    assert!(format!("{:#}", err).starts_with("Invalid value for config key psi_trigger_cgroups"));
+
+    let err = BelowConfig::default()
+        .apply_overrides(vec![("demacia".to_owned(), "1".to_owned())], "command line")
+        .expect_err("Unknown key should not be overridden");
+    assert_eq!(
+        format!("{}", err),
+        "Unknown config key demacia from command line"
+    );
+}
@@ -0,0 +1,103 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dump throughput of models of synthetic hosts with a configurable number
+//! of processes, see `model::synthetic::bench_sizes`.
+
+use std::time::Duration;
+use std::time::SystemTime;
+
+use below_dump::command::expand_fields;
+use below_dump::command::DEFAULT_CGROUP_FIELDS;
+use below_dump::command::DEFAULT_PROCESS_FIELDS;
+use below_dump::command::GeneralOpt;
+use below_dump::command::OutputFormat;
+use below_dump::tmain::Dumper;
+use below_dump::CommonFieldContext;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::Throughput;
+use model::synthetic;
+use model::Model;
+
+fn output_formats() -> Vec<(&'static str, OutputFormat)> {
+    vec![
+        ("raw", OutputFormat::Raw),
+        ("csv", OutputFormat::Csv),
+        ("json", OutputFormat::Json),
+        ("openmetrics", OutputFormat::OpenMetrics),
+    ]
+}
+
+fn synthetic_model(size: usize) -> Model {
+    let nr_cgroups = std::cmp::max(size / 10, 1);
+    let last = synthetic::synthetic_sample(size, nr_cgroups, 1);
+    let sample = synthetic::synthetic_sample(size, nr_cgroups, 2);
+    Model::new(
+        SystemTime::now(),
+        &sample,
+        Some((&last, Duration::from_secs(5))),
+    )
+}
+
+fn bench_dumper<D: Dumper>(c: &mut Criterion, name: &str, make_dumper: impl Fn(&GeneralOpt) -> D) {
+    let mut group = c.benchmark_group(name);
+    for size in synthetic::bench_sizes() {
+        let model = synthetic_model(size);
+        let ctx = CommonFieldContext {
+            timestamp: 0,
+            hostname: "synthetic".to_owned(),
+            gaps: 0,
+            boot_id: None,
+            kernel_version: None,
+            below_version: None,
+        };
+        for (format_name, format) in output_formats() {
+            let opts = GeneralOpt {
+                output_format: Some(format),
+                ..Default::default()
+            };
+            let dumper = make_dumper(&opts);
+            group.throughput(Throughput::Elements(size as u64));
+            group.bench_function(BenchmarkId::new(format_name, size), |b| {
+                b.iter(|| {
+                    let mut output = Vec::new();
+                    let mut round = 0;
+                    dumper
+                        .dump_model(&ctx, &model, &mut output, &mut round, false)
+                        .expect("Failed to dump model");
+                    output
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_dump_process(c: &mut Criterion) {
+    bench_dumper(c, "dump_process", |opts| {
+        below_dump::process::Process::new(opts, None, expand_fields(DEFAULT_PROCESS_FIELDS, true))
+    });
+}
+
+fn bench_dump_cgroup(c: &mut Criterion) {
+    bench_dumper(c, "dump_cgroup", |opts| {
+        below_dump::cgroup::Cgroup::new(opts, None, expand_fields(DEFAULT_CGROUP_FIELDS, true))
+    });
+}
+
+criterion_group!(benches, bench_dump_process, bench_dump_cgroup);
+criterion_main!(benches);
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use model::BtrfsDeviceModelFieldId;
 use model::BtrfsModelFieldId;
 
 use super::*;
@@ -87,48 +88,26 @@ impl Dumper for Btrfs {
                             Some(OutputFormat::Raw) | None => write!(
                                 output,
                                 "{}",
-                                print::dump_raw(
-                                    &self.fields,
-                                    ctx,
-                                    model,
-                                    *round,
-                                    self.opts.repeat_title,
-                                    self.opts.disable_title,
-                                    self.opts.raw
-                                )
+                                print::dump_raw(&self.fields, ctx, model, *round, &self.opts)
                             )?,
                             Some(OutputFormat::Csv) => write!(
                                 output,
                                 "{}",
-                                print::dump_csv(
-                                    &self.fields,
-                                    ctx,
-                                    model,
-                                    *round,
-                                    self.opts.disable_title,
-                                    self.opts.raw
-                                )
+                                print::dump_csv(&self.fields, ctx, model, *round, &self.opts)
                             )?,
                             Some(OutputFormat::KeyVal) => write!(
                                 output,
                                 "{}",
-                                print::dump_kv(&self.fields, ctx, model, self.opts.raw)
+                                print::dump_kv(&self.fields, ctx, model, &self.opts)
                             )?,
                             Some(OutputFormat::Json) => {
-                                let par = print::dump_json(&self.fields, ctx, model, self.opts.raw);
+                                let par = print::dump_json(&self.fields, ctx, model, &self.opts);
                                 json_output.as_array_mut().unwrap().push(par);
                             }
                             Some(OutputFormat::Tsv) => write!(
                                 output,
                                 "{}",
-                                print::dump_tsv(
-                                    &self.fields,
-                                    ctx,
-                                    model,
-                                    *round,
-                                    self.opts.disable_title,
-                                    self.opts.raw
-                                )
+                                print::dump_tsv(&self.fields, ctx, model, *round, &self.opts)
                             )?,
                             Some(OutputFormat::OpenMetrics) => write!(
                                 output,
@@ -154,3 +133,110 @@ impl Dumper for Btrfs {
         }
     }
 }
+
+pub struct BtrfsDevice {
+    opts: GeneralOpt,
+    select: Option<BtrfsDeviceModelFieldId>,
+    fields: Vec<BtrfsDeviceField>,
+}
+
+impl BtrfsDevice {
+    pub fn new(
+        opts: &GeneralOpt,
+        select: Option<BtrfsDeviceModelFieldId>,
+        fields: Vec<BtrfsDeviceField>,
+    ) -> Self {
+        Self {
+            opts: opts.to_owned(),
+            select,
+            fields,
+        }
+    }
+}
+
+impl Dumper for BtrfsDevice {
+    fn dump_model(
+        &self,
+        ctx: &CommonFieldContext,
+        model: &model::Model,
+        output: &mut dyn Write,
+        round: &mut usize,
+        comma_flag: bool,
+    ) -> Result<IterExecResult> {
+        let mut devices: Vec<_> = match model.system.btrfs_devices.as_ref() {
+            Some(devices) => devices
+                .values()
+                .filter(
+                    |device| match (self.select.as_ref(), self.opts.filter.as_ref()) {
+                        (Some(field_id), Some(filter)) => filter.is_match(
+                            &device
+                                .query(field_id)
+                                .map_or("?".to_owned(), |v| v.to_string()),
+                        ),
+                        _ => true,
+                    },
+                )
+                .collect(),
+            None => return Ok(IterExecResult::Skip),
+        };
+
+        if let Some(field_id) = &self.select {
+            if self.opts.sort {
+                model::sort_queriables(&mut devices, field_id, false);
+            }
+
+            if self.opts.rsort {
+                model::sort_queriables(&mut devices, field_id, true);
+            }
+
+            if (self.opts.sort || self.opts.rsort) && self.opts.top != 0 {
+                devices.truncate(self.opts.top as usize);
+            }
+        }
+        let mut json_output = json!([]);
+
+        for device in devices {
+            match self.opts.output_format {
+                Some(OutputFormat::Raw) | None => write!(
+                    output,
+                    "{}",
+                    print::dump_raw(&self.fields, ctx, device, *round, &self.opts)
+                )?,
+                Some(OutputFormat::Csv) => write!(
+                    output,
+                    "{}",
+                    print::dump_csv(&self.fields, ctx, device, *round, &self.opts)
+                )?,
+                Some(OutputFormat::KeyVal) => write!(
+                    output,
+                    "{}",
+                    print::dump_kv(&self.fields, ctx, device, &self.opts)
+                )?,
+                Some(OutputFormat::Json) => {
+                    let par = print::dump_json(&self.fields, ctx, device, &self.opts);
+                    json_output.as_array_mut().unwrap().push(par);
+                }
+                Some(OutputFormat::Tsv) => write!(
+                    output,
+                    "{}",
+                    print::dump_tsv(&self.fields, ctx, device, *round, &self.opts)
+                )?,
+                Some(OutputFormat::OpenMetrics) => write!(
+                    output,
+                    "{}",
+                    print::dump_openmetrics(&self.fields, ctx, device)
+                )?,
+            }
+            *round += 1;
+        }
+
+        match (self.opts.output_format, comma_flag) {
+            (Some(OutputFormat::Json), true) => write!(output, ",{}", json_output)?,
+            (Some(OutputFormat::Json), false) => write!(output, "{}", json_output)?,
+            (Some(OutputFormat::OpenMetrics), _) => (),
+            _ => writeln!(output)?,
+        };
+
+        Ok(IterExecResult::Success)
+    }
+}
@@ -21,6 +21,10 @@ pub struct Cgroup {
     opts: GeneralOpt,
     select: Option<SingleCgroupModelFieldId>,
     fields: Vec<CgroupField>,
+    /// Path components of the cgroup the dump starts at
+    subtree: Vec<String>,
+    /// Levels below the subtree cgroup to dump
+    max_depth: Option<u32>,
 }
 
 impl Cgroup {
@@ -33,8 +37,23 @@ impl Cgroup {
             opts: opts.to_owned(),
             select,
             fields,
+            subtree: Vec::new(),
+            max_depth: None,
         }
     }
+
+    /// Only dump the cgroup at `subtree`, relative to the root cgroup, and
+    /// its descendants up to `max_depth` levels below it
+    pub fn with_subtree(mut self, subtree: Option<&str>, max_depth: Option<u32>) -> Self {
+        self.subtree = subtree
+            .unwrap_or_default()
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .map(|part| part.to_owned())
+            .collect();
+        self.max_depth = max_depth;
+        self
+    }
 }
 
 impl Dumper for Cgroup {
@@ -52,9 +71,10 @@ impl Dumper for Cgroup {
             model: &model::CgroupModel,
             output: &mut dyn Write,
             round: &mut usize,
-            json: bool,
             jval: &mut Value,
+            depth: u32,
         ) -> Result<()> {
+            let json = handle.opts.output_format == Some(OutputFormat::Json);
             let cgroup = &model.data;
             //filter
             let should_print = match (handle.select.as_ref(), handle.opts.filter.as_ref()) {
@@ -71,47 +91,25 @@ impl Dumper for Cgroup {
                     Some(OutputFormat::Raw) | None => write!(
                         output,
                         "{}",
-                        print::dump_raw_indented(
-                            &handle.fields,
-                            ctx,
-                            cgroup,
-                            *round,
-                            handle.opts.repeat_title,
-                            handle.opts.disable_title,
-                            handle.opts.raw,
-                        )
+                        print::dump_raw_indented(&handle.fields, ctx, cgroup, *round, &handle.opts,)
                     )?,
                     Some(OutputFormat::Csv) => write!(
                         output,
                         "{}",
-                        print::dump_csv(
-                            &handle.fields,
-                            ctx,
-                            cgroup,
-                            *round,
-                            handle.opts.disable_title,
-                            handle.opts.raw,
-                        )
+                        print::dump_csv(&handle.fields, ctx, cgroup, *round, &handle.opts,)
                     )?,
                     Some(OutputFormat::Tsv) => write!(
                         output,
                         "{}",
-                        print::dump_tsv(
-                            &handle.fields,
-                            ctx,
-                            cgroup,
-                            *round,
-                            handle.opts.disable_title,
-                            handle.opts.raw,
-                        )
+                        print::dump_tsv(&handle.fields, ctx, cgroup, *round, &handle.opts,)
                     )?,
                     Some(OutputFormat::KeyVal) => write!(
                         output,
                         "{}",
-                        print::dump_kv(&handle.fields, ctx, cgroup, handle.opts.raw)
+                        print::dump_kv(&handle.fields, ctx, cgroup, &handle.opts)
                     )?,
                     Some(OutputFormat::Json) => {
-                        *jval = print::dump_json(&handle.fields, ctx, cgroup, handle.opts.raw);
+                        *jval = print::dump_json(&handle.fields, ctx, cgroup, &handle.opts);
                         jval["children"] = json!([]);
                     }
                     Some(OutputFormat::OpenMetrics) => write!(
@@ -123,6 +121,11 @@ impl Dumper for Cgroup {
                 *round += 1;
             }
 
+            // Prune the traversal rather than filtering the output
+            if handle.max_depth.is_some_and(|max_depth| depth >= max_depth) {
+                return Ok(());
+            }
+
             let mut children = Vec::from_iter(&model.children);
             //sort
             if let Some(field_id) = &handle.select {
@@ -136,7 +139,7 @@ impl Dumper for Cgroup {
                 }
 
                 if handle.opts.rsort {
-                    model::sort_queriables(&mut children, &field_id, false);
+                    model::sort_queriables(&mut children, &field_id, true);
                 }
 
                 if (handle.opts.sort || handle.opts.rsort) && handle.opts.top != 0 {
@@ -146,11 +149,19 @@ impl Dumper for Cgroup {
 
             for child_cgroup in &children {
                 let mut child = json!({});
-                output_cgroup(handle, ctx, child_cgroup, output, round, json, &mut child)?;
+                output_cgroup(
+                    handle,
+                    ctx,
+                    child_cgroup,
+                    output,
+                    round,
+                    &mut child,
+                    depth + 1,
+                )?;
                 if json && child["children"].is_array() {
                     // Parent does not match, but child does, we should also render parent.
                     if !jval["children"].is_array() {
-                        *jval = print::dump_json(&handle.fields, ctx, cgroup, handle.opts.raw);
+                        *jval = print::dump_json(&handle.fields, ctx, cgroup, &handle.opts);
                         jval["children"] = json!([]);
                     }
                     jval["children"].as_array_mut().unwrap().push(child);
@@ -159,9 +170,16 @@ impl Dumper for Cgroup {
 
             Ok(())
         }
+        let mut root = &model.cgroup;
+        for part in &self.subtree {
+            root = match root.children.get(part.as_str()) {
+                Some(child) => child,
+                None => return Ok(IterExecResult::Skip),
+            };
+        }
         let json = self.opts.output_format == Some(OutputFormat::Json);
         let mut jval = json!({});
-        output_cgroup(&self, ctx, &model.cgroup, output, round, json, &mut jval)?;
+        output_cgroup(self, ctx, root, output, round, &mut jval, 0)?;
         match (json, comma_flag) {
             (true, true) => write!(output, ",{}", jval)?,
             (true, false) => write!(output, "{}", jval)?,
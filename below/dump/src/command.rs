@@ -12,27 +12,33 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::RefCell;
 use std::str::FromStr;
 
 use anyhow::bail;
 use anyhow::Error;
 use anyhow::Result;
 use clap::Parser;
+use model::BtrfsDeviceModelFieldId;
 use model::BtrfsModelFieldId;
 use model::FieldId;
 use model::NetworkModelFieldId;
+use model::ResctrlMonGroupModelFieldId;
 use model::SingleCgroupModelFieldId;
 use model::SingleDiskModelFieldId;
 use model::SingleNetModelFieldId;
 use model::SingleProcessModelFieldId;
 use model::SingleQueueModelFieldId;
+use model::SingleSlabModelFieldId;
 use model::SingleTcModelFieldId;
 use model::SystemModelFieldId;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use crate::tmain::Bucket;
 use crate::CommonField;
 use crate::DumpField;
+use crate::FieldOverrides;
 
 /// Field that represents a group of related FieldIds of a Queriable.
 /// Shorthand for specifying fields to dump.
@@ -71,12 +77,15 @@ impl<F: FieldId + FromStr, A: AggField<F> + FromStr> FromStr for DumpOptionField
     type Err = Error;
 
     /// When parsing command line options into DumpOptionField, priority order
-    /// is CommonField, AggField, and then FieldId.
+    /// is CommonField, AggField, selector, and then FieldId. Selectors go
+    /// before FieldIds as `*` would otherwise parse as e.g. an interface name.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Ok(common) = CommonField::from_str(s) {
             Ok(Self::Unit(DumpField::Common(common)))
         } else if let Ok(agg) = A::from_str(s) {
             Ok(Self::Agg(agg))
+        } else if s.split('.').any(|part| part == model::SELECTOR_WILDCARD) {
+            Ok(Self::Unit(DumpField::Selector(s.to_owned())))
         } else if let Ok(field_id) = F::from_str(s) {
             Ok(Self::Unit(DumpField::FieldId(field_id)))
         } else {
@@ -91,6 +100,7 @@ impl<F: FieldId + ToString, A: AggField<F> + ToString> ToString for DumpOptionFi
         match self {
             Self::Unit(DumpField::Common(common)) => common.to_string(),
             Self::Unit(DumpField::FieldId(field_id)) => field_id.to_string(),
+            Self::Unit(DumpField::Selector(selector)) => selector.clone(),
             Self::Agg(agg) => agg.to_string(),
         }
     }
@@ -139,7 +149,7 @@ macro_rules! make_option {
     }
 }
 
-/// Represents the four sub-model of SystemModel.
+/// Represents the six sub-model of SystemModel.
 #[derive(
     Clone,
     Debug,
@@ -152,13 +162,19 @@ pub enum SystemAggField {
     Mem,
     Vm,
     Stat,
+    Load,
+    Softirq,
+    Sched,
 }
 
 impl AggField<SystemModelFieldId> for SystemAggField {
     fn expand(&self, detail: bool) -> Vec<SystemModelFieldId> {
+        use model::LoadAvgModelFieldId as Load;
         use model::MemoryModelFieldId as Mem;
         use model::ProcStatModelFieldId as Stat;
         use model::SingleCpuModelFieldId as Cpu;
+        use model::SingleSchedModelFieldId as Sched;
+        use model::SingleSoftirqModelFieldId as Softirq;
         use model::SystemModelFieldId as FieldId;
         use model::VmModelFieldId as Vm;
 
@@ -172,6 +188,17 @@ impl AggField<SystemModelFieldId> for SystemAggField {
                 Self::Mem => enum_iterator::all::<Mem>().map(FieldId::Mem).collect(),
                 Self::Vm => enum_iterator::all::<Vm>().map(FieldId::Vm).collect(),
                 Self::Stat => enum_iterator::all::<Stat>().map(FieldId::Stat).collect(),
+                Self::Load => enum_iterator::all::<Load>().map(FieldId::Loadavg).collect(),
+                Self::Softirq => enum_iterator::all::<Softirq>()
+                    // The Idx field is always -1 (we aggregate all CPUs)
+                    .filter(|v| v != &Softirq::Idx)
+                    .map(FieldId::Softirq)
+                    .collect(),
+                Self::Sched => enum_iterator::all::<Sched>()
+                    // The Idx field is always -1 (we aggregate all CPUs)
+                    .filter(|v| v != &Sched::Idx)
+                    .map(FieldId::Sched)
+                    .collect(),
             }
         } else {
             // Default fields for each group
@@ -186,6 +213,25 @@ impl AggField<SystemModelFieldId> for SystemAggField {
                     .collect(),
                 Self::Vm => enum_iterator::all::<Vm>().map(FieldId::Vm).collect(),
                 Self::Stat => enum_iterator::all::<Stat>().map(FieldId::Stat).collect(),
+                Self::Load => vec![Load::Load1, Load::Load5, Load::Load15]
+                    .into_iter()
+                    .map(FieldId::Loadavg)
+                    .collect(),
+                Self::Softirq => vec![
+                    Softirq::TimerPerSec,
+                    Softirq::NetTxPerSec,
+                    Softirq::NetRxPerSec,
+                    Softirq::BlockPerSec,
+                    Softirq::SchedPerSec,
+                    Softirq::RcuPerSec,
+                ]
+                .into_iter()
+                .map(FieldId::Softirq)
+                .collect(),
+                Self::Sched => vec![Sched::RunPct, Sched::WaitPct, Sched::AvgWaitUsec]
+                    .into_iter()
+                    .map(FieldId::Sched)
+                    .collect(),
             }
         }
     }
@@ -200,8 +246,12 @@ pub static DEFAULT_SYSTEM_FIELDS: &[SystemOptionField] = &[
     DumpOptionField::Agg(SystemAggField::Mem),
     DumpOptionField::Agg(SystemAggField::Vm),
     DumpOptionField::Unit(DumpField::FieldId(SystemModelFieldId::KernelVersion)),
+    DumpOptionField::Unit(DumpField::FieldId(SystemModelFieldId::BootId)),
     DumpOptionField::Unit(DumpField::FieldId(SystemModelFieldId::OsRelease)),
+    DumpOptionField::Unit(DumpField::FieldId(SystemModelFieldId::IrqPressureFullPct)),
     DumpOptionField::Agg(SystemAggField::Stat),
+    DumpOptionField::Agg(SystemAggField::Load),
+    DumpOptionField::Unit(DumpField::FieldId(SystemModelFieldId::UptimeSecs)),
     DumpOptionField::Unit(DumpField::Common(CommonField::Timestamp)),
 ];
 
@@ -226,6 +276,12 @@ static SYSTEM_LONG_ABOUT: Lazy<String> = Lazy::new(|| {
 
 * stat: includes [{agg_stat_fields}].
 
+* load: includes [{agg_load_fields}].
+
+* softirq: includes [{agg_softirq_fields}]. Not included by --default.
+
+* sched: includes [{agg_sched_fields}]. Not included by --default.
+
 * --detail: includes [<agg_field>.*] for each given aggregated field.
 
 * --default: includes [{default_fields}].
@@ -244,6 +300,9 @@ $ below dump system -b "08:30:00" -e "08:30:30" -f datetime vm hostname -O csv
         agg_memory_fields = join(SystemAggField::Mem.expand(false)),
         agg_vm_fields = join(SystemAggField::Vm.expand(false)),
         agg_stat_fields = join(SystemAggField::Stat.expand(false)),
+        agg_load_fields = join(SystemAggField::Load.expand(false)),
+        agg_softirq_fields = join(SystemAggField::Softirq.expand(false)),
+        agg_sched_fields = join(SystemAggField::Sched.expand(false)),
         default_fields = join(DEFAULT_SYSTEM_FIELDS.to_owned()),
     )
 });
@@ -432,6 +491,178 @@ $ below dump btrfs -b "08:30:00" -e "08:30:30" -s disk_bytes --rsort --top 5
     )
 });
 
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    below_derive::EnumFromStr,
+    below_derive::EnumToString
+)]
+pub enum BtrfsDeviceAggField {
+    Errors,
+    Scrub,
+}
+
+impl AggField<BtrfsDeviceModelFieldId> for BtrfsDeviceAggField {
+    fn expand(&self, _detail: bool) -> Vec<BtrfsDeviceModelFieldId> {
+        use model::BtrfsDeviceModelFieldId::*;
+
+        match self {
+            Self::Errors => vec![
+                WriteErrs,
+                ReadErrs,
+                FlushErrs,
+                CorruptionErrs,
+                GenerationErrs,
+            ],
+            Self::Scrub => vec![
+                ScrubStatus,
+                ScrubProgressPct,
+                ScrubBytesScrubbed,
+                ScrubCorrectedErrors,
+                ScrubUncorrectableErrors,
+            ],
+        }
+    }
+}
+
+pub type BtrfsDeviceOptionField = DumpOptionField<BtrfsDeviceModelFieldId, BtrfsDeviceAggField>;
+
+pub static DEFAULT_BTRFS_DEVICE_FIELDS: &[BtrfsDeviceOptionField] = &[
+    DumpOptionField::Unit(DumpField::Common(CommonField::Datetime)),
+    DumpOptionField::Unit(DumpField::FieldId(BtrfsDeviceModelFieldId::Path)),
+    DumpOptionField::Unit(DumpField::FieldId(BtrfsDeviceModelFieldId::MountPoint)),
+    DumpOptionField::Agg(BtrfsDeviceAggField::Errors),
+    DumpOptionField::Agg(BtrfsDeviceAggField::Scrub),
+    DumpOptionField::Unit(DumpField::Common(CommonField::Timestamp)),
+];
+
+const BTRFS_DEVICE_ABOUT: &str = "Dump btrfs device error counters and scrub status";
+
+static BTRFS_DEVICE_LONG_ABOUT: Lazy<String> = Lazy::new(|| {
+    format!(
+        r#"{about}
+
+********************** Available fields **********************
+
+{common_fields}, {btrfs_device_fields}
+
+********************** Aggregated fields **********************
+
+* errors: includes [{agg_errors_fields}].
+
+* scrub: includes [{agg_scrub_fields}].
+
+* --detail: no effect.
+
+* --default: includes [{default_fields}].
+
+* --everything: includes everything (equivalent to --default --detail).
+
+********************** Example Commands **********************
+
+Simple example:
+
+$ below dump btrfs-device -b "08:30:00" -e "08:30:30" -f path errors -O csv
+
+Output devices with corrupted blocks for each time slice from 08:30:00 to 08:30:30:
+
+$ below dump btrfs-device -b "08:30:00" -e "08:30:30" -s corruption_errs -F "^[1-9]"
+
+"#,
+        about = BTRFS_DEVICE_ABOUT,
+        common_fields = join(enum_iterator::all::<CommonField>()),
+        btrfs_device_fields = join(enum_iterator::all::<BtrfsDeviceModelFieldId>()),
+        agg_errors_fields = join(BtrfsDeviceAggField::Errors.expand(false)),
+        agg_scrub_fields = join(BtrfsDeviceAggField::Scrub.expand(false)),
+        default_fields = join(DEFAULT_BTRFS_DEVICE_FIELDS.to_owned()),
+    )
+});
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    below_derive::EnumFromStr,
+    below_derive::EnumToString
+)]
+pub enum SlabAggField {
+    Objs,
+    Size,
+    Growth,
+}
+
+impl AggField<SingleSlabModelFieldId> for SlabAggField {
+    fn expand(&self, _detail: bool) -> Vec<SingleSlabModelFieldId> {
+        use model::SingleSlabModelFieldId::*;
+
+        match self {
+            Self::Objs => vec![ActiveObjs, NumObjs, ObjSize, ObjPerSlab, NumSlabs],
+            Self::Size => vec![ActiveSize, TotalSize],
+            Self::Growth => vec![ActiveObjsDelta, TotalSizeDelta, GrowthStreak],
+        }
+    }
+}
+
+pub type SlabOptionField = DumpOptionField<SingleSlabModelFieldId, SlabAggField>;
+
+pub static DEFAULT_SLAB_FIELDS: &[SlabOptionField] = &[
+    DumpOptionField::Unit(DumpField::Common(CommonField::Datetime)),
+    DumpOptionField::Unit(DumpField::FieldId(SingleSlabModelFieldId::Name)),
+    DumpOptionField::Agg(SlabAggField::Size),
+    DumpOptionField::Agg(SlabAggField::Growth),
+    DumpOptionField::Agg(SlabAggField::Objs),
+    DumpOptionField::Unit(DumpField::Common(CommonField::Timestamp)),
+];
+
+const SLAB_ABOUT: &str = "Dump slab cache stats and their growth";
+
+static SLAB_LONG_ABOUT: Lazy<String> = Lazy::new(|| {
+    format!(
+        r#"{about}
+
+The growth streak is the number of consecutive dumped samples a cache grew
+in. Caches that keep growing are suspects for kernel memory leaks.
+
+********************** Available fields **********************
+
+{common_fields}, {slab_fields}
+
+********************** Aggregated fields **********************
+
+* objs: includes [{agg_objs_fields}].
+
+* size: includes [{agg_size_fields}].
+
+* growth: includes [{agg_growth_fields}].
+
+* --detail: no effect.
+
+* --default: includes [{default_fields}].
+
+* --everything: includes everything (equivalent to --default --detail).
+
+********************** Example Commands **********************
+
+Simple example:
+
+$ below dump slab -b "08:30:00" -e "08:30:30" -f name size growth -O csv
+
+Output the 10 fastest growing caches for each time slice from 08:30:00 to 08:30:30:
+
+$ below dump slab -b "08:30:00" -e "08:30:30" -s total_size_delta --rsort --top 10
+
+"#,
+        about = SLAB_ABOUT,
+        common_fields = join(enum_iterator::all::<CommonField>()),
+        slab_fields = join(enum_iterator::all::<SingleSlabModelFieldId>()),
+        agg_objs_fields = join(SlabAggField::Objs.expand(false)),
+        agg_size_fields = join(SlabAggField::Size.expand(false)),
+        agg_growth_fields = join(SlabAggField::Growth.expand(false)),
+        default_fields = join(DEFAULT_SLAB_FIELDS.to_owned()),
+    )
+});
+
 /// Represents the four sub-model of ProcessModel.
 #[derive(
     Clone,
@@ -444,6 +675,7 @@ pub enum ProcessAggField {
     Cpu,
     Mem,
     Io,
+    Net,
 }
 
 impl AggField<SingleProcessModelFieldId> for ProcessAggField {
@@ -451,6 +683,7 @@ impl AggField<SingleProcessModelFieldId> for ProcessAggField {
         use model::ProcessCpuModelFieldId as Cpu;
         use model::ProcessIoModelFieldId as Io;
         use model::ProcessMemoryModelFieldId as Mem;
+        use model::ProcessNetModelFieldId as Net;
         use model::SingleProcessModelFieldId as FieldId;
 
         if detail {
@@ -458,13 +691,28 @@ impl AggField<SingleProcessModelFieldId> for ProcessAggField {
                 Self::Cpu => enum_iterator::all::<Cpu>().map(FieldId::Cpu).collect(),
                 Self::Mem => enum_iterator::all::<Mem>().map(FieldId::Mem).collect(),
                 Self::Io => enum_iterator::all::<Io>().map(FieldId::Io).collect(),
+                Self::Net => enum_iterator::all::<Net>().map(FieldId::Net).collect(),
             }
         } else {
             // Default fields for each group
             match self {
                 Self::Cpu => vec![FieldId::Cpu(Cpu::UsagePct)],
-                Self::Mem => vec![FieldId::Mem(Mem::RssBytes)],
+                // High major fault rates are a key thrashing indicator
+                // and so is swapping out, which the swap growth shows
+                Self::Mem => vec![
+                    FieldId::Mem(Mem::RssBytes),
+                    FieldId::Mem(Mem::MinorfaultsPerSec),
+                    FieldId::Mem(Mem::MajorfaultsPerSec),
+                    FieldId::Mem(Mem::Swap),
+                    FieldId::Mem(Mem::SwapGrowthPerSec),
+                ],
                 Self::Io => vec![FieldId::Io(Io::RbytesPerSec), FieldId::Io(Io::WbytesPerSec)],
+                // Only collected with enable_process_net_stats, so not in the
+                // default fields
+                Self::Net => vec![
+                    FieldId::Net(Net::RxBytesPerSec),
+                    FieldId::Net(Net::TxBytesPerSec),
+                ],
             }
         }
     }
@@ -482,6 +730,9 @@ pub static DEFAULT_PROCESS_FIELDS: &[ProcessOptionField] = &[
     DumpOptionField::Agg(ProcessAggField::Mem),
     DumpOptionField::Agg(ProcessAggField::Io),
     DumpOptionField::Unit(DumpField::FieldId(SingleProcessModelFieldId::UptimeSecs)),
+    DumpOptionField::Unit(DumpField::FieldId(
+        SingleProcessModelFieldId::StartTimeEpochSecs,
+    )),
     DumpOptionField::Unit(DumpField::FieldId(SingleProcessModelFieldId::Cgroup)),
     DumpOptionField::Unit(DumpField::Common(CommonField::Timestamp)),
     DumpOptionField::Unit(DumpField::FieldId(SingleProcessModelFieldId::Cmdline)),
@@ -507,6 +758,8 @@ static PROCESS_LONG_ABOUT: Lazy<String> = Lazy::new(|| {
 
 * io: includes [{agg_io_fields}].
 
+* net: includes [{agg_net_fields}]. Only collected with enable_process_net_stats.
+
 * --detail: includes [<agg_field>.*] for each given aggregated field.
 
 * --default: includes [{default_fields}].
@@ -527,6 +780,14 @@ Output stats for top 5 CPU intense processes for each time slice from 08:30:00 t
 
 $ below dump process -b "08:30:00" -e "08:30:30" -s cpu.usage_pct --rsort --top 5
 
+Output stats for top 5 processes by major page faults, e.g. when the system is thrashing:
+
+$ below dump process -b "08:30:00" -e "08:30:30" -s mem.majorfaults_per_sec --rsort --top 5
+
+Output stats for top 5 processes by bytes sent over the network:
+
+$ below dump process -b "08:30:00" -e "08:30:30" -f comm net -s net.tx_bytes_per_sec --rsort --top 5
+
 "#,
         about = PROCESS_ABOUT,
         common_fields = join(enum_iterator::all::<CommonField>()),
@@ -534,6 +795,7 @@ $ below dump process -b "08:30:00" -e "08:30:30" -s cpu.usage_pct --rsort --top
         agg_cpu_fields = join(ProcessAggField::Cpu.expand(false)),
         agg_memory_fields = join(ProcessAggField::Mem.expand(false)),
         agg_io_fields = join(ProcessAggField::Io.expand(false)),
+        agg_net_fields = join(ProcessAggField::Net.expand(false)),
         default_fields = join(DEFAULT_PROCESS_FIELDS.to_owned()),
     )
 });
@@ -577,7 +839,13 @@ impl AggField<SingleCgroupModelFieldId> for CgroupAggField {
             // Default fields for each group
             match self {
                 Self::Cpu => vec![FieldId::Cpu(Cpu::UsagePct)],
-                Self::Mem => vec![FieldId::Mem(Mem::Total)],
+                Self::Mem => vec![
+                    FieldId::Mem(Mem::Total),
+                    FieldId::Mem(Mem::EventsLowPerSec),
+                    FieldId::Mem(Mem::EventsHighPerSec),
+                    FieldId::Mem(Mem::EventsMaxPerSec),
+                    FieldId::Mem(Mem::EventsOomKillDelta),
+                ],
                 Self::Io => vec![FieldId::Io(Io::RbytesPerSec), FieldId::Io(Io::WbytesPerSec)],
                 Self::Pids => vec![FieldId::Pids(Pid::TidsCurrent)],
                 Self::Pressure => vec![
@@ -646,6 +914,10 @@ from 08:30:00 to 08:30:30 recursively:
 
 $ below dump cgroup -b "08:30:00" -e "08:30:30" -s cpu.usage_pct --rsort --top 5
 
+Output stats for system.slice and its direct children only:
+
+$ below dump cgroup -b "08:30:00" -e "08:30:30" --subtree system.slice --max-depth 1
+
 "#,
         about = CGROUP_ABOUT,
         common_fields = join(enum_iterator::all::<CommonField>()),
@@ -667,6 +939,7 @@ $ below dump cgroup -b "08:30:00" -e "08:30:30" -s cpu.usage_pct --rsort --top 5
     below_derive::EnumToString
 )]
 pub enum IfaceAggField {
+    Link,
     Rate,
     Rx,
     Tx,
@@ -678,6 +951,13 @@ impl AggField<SingleNetModelFieldId> for IfaceAggField {
         use model::SingleNetModelFieldId::*;
 
         match self {
+            Self::Link => vec![
+                Operstate,
+                SpeedMbps,
+                Duplex,
+                CarrierChanges,
+                CarrierChangesDelta,
+            ],
             Self::Rate => vec![
                 RxBytesPerSec,
                 TxBytesPerSec,
@@ -705,6 +985,7 @@ impl AggField<SingleNetModelFieldId> for IfaceAggField {
                 TxCarrierErrors,
                 TxCompressed,
                 TxDropped,
+                QdiscDropsPerSec,
                 TxErrors,
                 TxFifoErrors,
                 TxHeartbeatErrors,
@@ -729,6 +1010,7 @@ pub static DEFAULT_IFACE_FIELDS: &[IfaceOptionField] = &[
     DumpOptionField::Unit(DumpField::FieldId(SingleNetModelFieldId::Collisions)),
     DumpOptionField::Unit(DumpField::FieldId(SingleNetModelFieldId::Multicast)),
     DumpOptionField::Unit(DumpField::FieldId(SingleNetModelFieldId::Interface)),
+    DumpOptionField::Agg(IfaceAggField::Link),
     DumpOptionField::Agg(IfaceAggField::Rate),
     DumpOptionField::Agg(IfaceAggField::Rx),
     DumpOptionField::Agg(IfaceAggField::Tx),
@@ -749,6 +1031,8 @@ static IFACE_LONG_ABOUT: Lazy<String> = Lazy::new(|| {
 
 ********************** Aggregated fields **********************
 
+* link: includes [{agg_link_fields}].
+
 * rate: includes [{agg_rate_fields}].
 
 * rx: includes [{agg_rx_fields}].
@@ -774,10 +1058,15 @@ from 08:30:00 to 08:30:30:
 
 $ below dump iface -b "08:30:00" -e "08:30:30" -s interface -F eth* -O json
 
+Output stats for top 2 interfaces by receive rate for each time slice from 08:30:00 to 08:30:30:
+
+$ below dump iface -b "08:30:00" -e "08:30:30" -s rx_bytes_per_sec --rsort --top 2
+
 "#,
         about = IFACE_ABOUT,
         common_fields = join(enum_iterator::all::<CommonField>()),
         iface_fields = join(enum_iterator::all::<SingleNetModelFieldId>()),
+        agg_link_fields = join(IfaceAggField::Link.expand(false)),
         agg_rate_fields = join(IfaceAggField::Rate.expand(false)),
         agg_rx_fields = join(IfaceAggField::Rx.expand(false)),
         agg_tx_fields = join(IfaceAggField::Tx.expand(false)),
@@ -865,6 +1154,10 @@ Example:
 
 $ below dump network -b "08:30:00" -e "08:30:30" -f ip ip6 -O json
 
+Output the receive rate of every interface, with `*` selecting all of them:
+
+$ below dump network -b "08:30:00" -e "08:30:30" -f datetime "interfaces.*.rx_bytes_per_sec"
+
 "#,
         about = NETWORK_ABOUT,
         common_fields = join(enum_iterator::all::<CommonField>()),
@@ -877,7 +1170,7 @@ $ below dump network -b "08:30:00" -e "08:30:30" -f ip ip6 -O json
     )
 });
 
-/// Represents the tcp and udp sub-models of the network model.
+/// Represents the tcp, udp and sockstat sub-models of the network model.
 #[derive(
     Clone,
     Debug,
@@ -889,6 +1182,8 @@ pub enum TransportAggField {
     Tcp,
     Udp,
     Udp6,
+    Sockstat,
+    Sockstat6,
 }
 
 impl AggField<NetworkModelFieldId> for TransportAggField {
@@ -904,6 +1199,12 @@ impl AggField<NetworkModelFieldId> for TransportAggField {
             Self::Udp6 => enum_iterator::all::<model::Udp6ModelFieldId>()
                 .map(FieldId::Udp6)
                 .collect(),
+            Self::Sockstat => enum_iterator::all::<model::SockStatModelFieldId>()
+                .map(FieldId::Sockstat)
+                .collect(),
+            Self::Sockstat6 => enum_iterator::all::<model::SockStat6ModelFieldId>()
+                .map(FieldId::Sockstat6)
+                .collect(),
         }
     }
 }
@@ -915,10 +1216,12 @@ pub static DEFAULT_TRANSPORT_FIELDS: &[TransportOptionField] = &[
     DumpOptionField::Agg(TransportAggField::Tcp),
     DumpOptionField::Agg(TransportAggField::Udp),
     DumpOptionField::Agg(TransportAggField::Udp6),
+    DumpOptionField::Agg(TransportAggField::Sockstat),
+    DumpOptionField::Agg(TransportAggField::Sockstat6),
     DumpOptionField::Unit(DumpField::Common(CommonField::Timestamp)),
 ];
 
-const TRANSPORT_ABOUT: &str = "Dump the transport layer stats including tcp and udp";
+const TRANSPORT_ABOUT: &str = "Dump the transport layer stats including tcp, udp and socket counts";
 
 /// Generated about message for Transport dump so supported fields are up-to-date.
 static TRANSPORT_LONG_ABOUT: Lazy<String> = Lazy::new(|| {
@@ -937,6 +1240,10 @@ static TRANSPORT_LONG_ABOUT: Lazy<String> = Lazy::new(|| {
 
 * udp6: includes [{agg_udp6_fields}].
 
+* sockstat: includes [{agg_sockstat_fields}].
+
+* sockstat6: includes [{agg_sockstat6_fields}].
+
 * --detail: no effect.
 
 * --default: includes [{default_fields}].
@@ -956,6 +1263,8 @@ $ below dump transport -b "08:30:00" -e "08:30:30" -f tcp udp -O json
         agg_tcp_fields = join(TransportAggField::Tcp.expand(false)),
         agg_udp_fields = join(TransportAggField::Udp.expand(false)),
         agg_udp6_fields = join(TransportAggField::Udp6.expand(false)),
+        agg_sockstat_fields = join(TransportAggField::Sockstat.expand(false)),
+        agg_sockstat6_fields = join(TransportAggField::Sockstat6.expand(false)),
         default_fields = join(DEFAULT_TRANSPORT_FIELDS.to_owned()),
     )
 });
@@ -1029,6 +1338,10 @@ Example:
 
 $ below dump ethtool-queue -b "08:30:00" -e "08:30:30" -O json
 
+Output stats for top 5 queues by transmit rate for each time slice from 08:30:00 to 08:30:30:
+
+$ below dump ethtool-queue -b "08:30:00" -e "08:30:30" -s tx_bytes_per_sec --rsort --top 5
+
 "#,
         about = ETHTOOL_QUEUE_ABOUT,
         common_fields = join(enum_iterator::all::<CommonField>()),
@@ -1059,7 +1372,9 @@ impl AggField<SingleTcModelFieldId> for TcAggField {
             Self::Stats => vec![
                 FieldId::Interface,
                 FieldId::Kind,
+                FieldId::Path,
                 FieldId::Qlen,
+                FieldId::BacklogBytes,
                 FieldId::Bps,
                 FieldId::Pps,
                 FieldId::BytesPerSec,
@@ -1104,6 +1419,8 @@ static TC_LONG_ABOUT: Lazy<String> = Lazy::new(|| {
 ********************** Example Commands **********************
 Example:
 $ below dump tc -b "08:30:00" -e "08:30:30" -O json
+Output stats for top 3 qdiscs by drop rate for each time slice from 08:30:00 to 08:30:30:
+$ below dump tc -b "08:30:00" -e "08:30:30" -s drops_per_sec --rsort --top 3
 "#,
         about = TC_ABOUT,
         common_fields = join(enum_iterator::all::<CommonField>()),
@@ -1112,6 +1429,76 @@ $ below dump tc -b "08:30:00" -e "08:30:30" -O json
     )
 });
 
+/// Represents the fields of a resctrl group.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    below_derive::EnumFromStr,
+    below_derive::EnumToString
+)]
+pub enum ResctrlAggField {
+    Mon,
+}
+
+impl AggField<ResctrlMonGroupModelFieldId> for ResctrlAggField {
+    fn expand(&self, _detail: bool) -> Vec<ResctrlMonGroupModelFieldId> {
+        use model::ResctrlMonGroupModelFieldId as FieldId;
+        use model::ResctrlMonModelFieldId;
+
+        match self {
+            // Per L3 fields are keyed by L3 domain id, so only the totals
+            // are listed
+            Self::Mon => enum_iterator::all::<model::ResctrlL3MonModelFieldId>()
+                .map(|field_id| FieldId::Mon(ResctrlMonModelFieldId::Total(field_id)))
+                .collect::<Vec<_>>(),
+        }
+    }
+}
+
+pub type ResctrlOptionField = DumpOptionField<ResctrlMonGroupModelFieldId, ResctrlAggField>;
+
+pub static DEFAULT_RESCTRL_FIELDS: &[ResctrlOptionField] = &[
+    DumpOptionField::Unit(DumpField::Common(CommonField::Datetime)),
+    DumpOptionField::Unit(DumpField::FieldId(ResctrlMonGroupModelFieldId::FullPath)),
+    DumpOptionField::Agg(ResctrlAggField::Mon),
+    DumpOptionField::Unit(DumpField::Common(CommonField::Timestamp)),
+];
+
+const RESCTRL_ABOUT: &str = "Dump the memory bandwidth and LLC occupancy of resctrl groups";
+
+/// Generated about message for resctrl dump so supported fields are up-to-date.
+static RESCTRL_LONG_ABOUT: Lazy<String> = Lazy::new(|| {
+    format!(
+        r#"{about}
+********************** Available fields **********************
+{common_fields}, {resctrl_fields}.
+********************** Aggregated fields **********************
+* mon: includes [{agg_mon_fields}].
+* --detail: no effect.
+* --default: includes [{default_fields}].
+* --everything: includes everything (equivalent to --default --detail).
+********************** Example Commands **********************
+Example:
+$ below dump resctrl -b "08:30:00" -e "08:30:30" -O json
+Output stats for top 3 groups by memory bandwidth for each time slice from 08:30:00 to 08:30:30:
+$ below dump resctrl -b "08:30:00" -e "08:30:30" -s mon.total.mbm_total_bytes_per_sec --rsort --top 3
+Output the bandwidth of L3 domain 1 for all groups:
+$ below dump resctrl -b "08:30:00" -e "08:30:30" -f full_path mon.per_l3.1.mbm_total_bytes_per_sec
+"#,
+        about = RESCTRL_ABOUT,
+        common_fields = join(enum_iterator::all::<CommonField>()),
+        resctrl_fields = join(enum_iterator::all::<ResctrlMonGroupModelFieldId>()),
+        agg_mon_fields = join(ResctrlAggField::Mon.expand(false)),
+        default_fields = join(DEFAULT_RESCTRL_FIELDS.to_owned()),
+    )
+});
+
+make_option! (WindowOp {
+    "max": Max,
+    "min": Min,
+});
+
 make_option! (OutputFormat {
     "raw": Raw,
     "csv": Csv,
@@ -1133,7 +1520,12 @@ pub struct GeneralOpt {
     #[clap(short, long)]
     pub detail: bool,
     /// Begin time, same format as replay
-    #[clap(long, short)]
+    #[clap(
+        long,
+        short,
+        required_unless_present = "list_fields",
+        default_value = ""
+    )]
     pub begin: String,
     /// End time, same format as replay
     #[clap(long, short, group = "time")]
@@ -1174,9 +1566,65 @@ pub struct GeneralOpt {
     /// Line break symbol between samples
     #[clap(long)]
     pub br: Option<String>,
+    /// Dump one sample per bucket of this duration rather than every
+    /// sample, e.g. "1 min". Shows the first sample of each bucket unless
+    /// --window is given.
+    #[clap(long)]
+    pub bucket: Option<String>,
+    /// Show the max or min of each field across all samples in a bucket,
+    /// so spikes are kept when downsampling. Choose from max, min.
+    #[clap(long, requires = "bucket")]
+    pub window: Option<WindowOp>,
     /// Dump raw data without units or conversion
     #[clap(long)]
     pub raw: bool,
+    /// List the fields with their units and descriptions instead of dumping.
+    /// With -O json, the list is printed as JSON.
+    #[clap(long)]
+    pub list_fields: bool,
+    /// Add hostname, kernel version and below version columns to every
+    /// row, for aggregating dumps of many hosts
+    #[clap(long)]
+    pub with_host_metadata: bool,
+    /// Write a summary of the run to this file at the end, or to stderr
+    /// without a file: rows written, time range covered, missing samples
+    /// and wall time
+    #[clap(long, num_args = 0..=1, default_missing_value = "-")]
+    pub summary: Option<String>,
+    /// Per-field formatting overrides, read from belowrc
+    #[clap(skip)]
+    pub field_overrides: FieldOverrides,
+    /// Fields each selector expanded to for the first row of csv or tsv
+    /// output, so that later rows keep the columns of its title line
+    #[clap(skip)]
+    pub selected_columns: RefCell<Option<Vec<Vec<String>>>>,
+}
+
+impl GeneralOpt {
+    /// Parse --bucket and --window
+    pub fn get_bucket(&self) -> Result<Option<Bucket>> {
+        self.bucket
+            .as_deref()
+            .map(|bucket| {
+                Ok(Bucket {
+                    duration: common::cliutil::duration_from_str(bucket)?,
+                    window: self.window,
+                })
+            })
+            .transpose()
+    }
+
+    /// Prepend the host metadata fields if --with-host-metadata is given
+    pub fn add_host_metadata<F: FieldId>(&self, fields: Vec<DumpField<F>>) -> Vec<DumpField<F>> {
+        if !self.with_host_metadata {
+            return fields;
+        }
+        CommonField::HOST_METADATA
+            .into_iter()
+            .map(DumpField::Common)
+            .chain(fields)
+            .collect()
+    }
 }
 
 #[derive(Debug, Parser, Clone)]
@@ -1220,6 +1668,34 @@ pub enum DumpCommand {
         #[clap(long, short, conflicts_with("fields"))]
         pattern: Option<String>,
     },
+    #[clap(about = BTRFS_DEVICE_ABOUT, long_about = BTRFS_DEVICE_LONG_ABOUT.as_str())]
+    BtrfsDevice {
+        /// Select which fields to display and in what order.
+        #[clap(short, long, num_args = 1..)]
+        fields: Option<Vec<BtrfsDeviceOptionField>>,
+        #[clap(flatten)]
+        opts: GeneralOpt,
+        /// Select field for operation, use with --sort, --rsort, --filter, --top
+        #[clap(long, short)]
+        select: Option<BtrfsDeviceModelFieldId>,
+        /// Saved pattern in the dumprc file under [btrfs_device] section.
+        #[clap(long, short, conflicts_with("fields"))]
+        pattern: Option<String>,
+    },
+    #[clap(about = SLAB_ABOUT, long_about = SLAB_LONG_ABOUT.as_str())]
+    Slab {
+        /// Select which fields to display and in what order.
+        #[clap(short, long, num_args = 1..)]
+        fields: Option<Vec<SlabOptionField>>,
+        #[clap(flatten)]
+        opts: GeneralOpt,
+        /// Select field for operation, use with --sort, --rsort, --filter, --top
+        #[clap(long, short)]
+        select: Option<SingleSlabModelFieldId>,
+        /// Saved pattern in the dumprc file under [slab] section.
+        #[clap(long, short, conflicts_with("fields"))]
+        pattern: Option<String>,
+    },
     #[clap(about = PROCESS_ABOUT, long_about = PROCESS_LONG_ABOUT.as_str())]
     Process {
         /// Select which fields to display and in what order.
@@ -1247,6 +1723,14 @@ pub enum DumpCommand {
         /// Saved pattern in the dumprc file under [cgroup] section.
         #[clap(long, short, conflicts_with("fields"))]
         pattern: Option<String>,
+        /// Only dump the cgroup at this path, e.g. "system.slice/foo.service",
+        /// and its descendants. Time slices without the cgroup are skipped.
+        #[clap(long)]
+        subtree: Option<String>,
+        /// Only dump cgroups up to this many levels below the root of the
+        /// dump, the --subtree cgroup if given. 0 dumps the root only.
+        #[clap(long)]
+        max_depth: Option<u32>,
     },
     #[clap(about = IFACE_ABOUT, long_about = IFACE_LONG_ABOUT.as_str())]
     Iface {
@@ -1255,7 +1739,7 @@ pub enum DumpCommand {
         fields: Option<Vec<IfaceOptionField>>,
         #[clap(flatten)]
         opts: GeneralOpt,
-        /// Select field for operation, use with --filter
+        /// Select field for operation, use with --sort, --rsort, --filter, --top
         #[clap(long, short)]
         select: Option<SingleNetModelFieldId>,
         /// Saved pattern in the dumprc file under [iface] section.
@@ -1291,6 +1775,9 @@ pub enum DumpCommand {
         fields: Option<Vec<EthtoolQueueOptionField>>,
         #[clap(flatten)]
         opts: GeneralOpt,
+        /// Select field for operation, use with --sort, --rsort, --filter, --top
+        #[clap(long, short)]
+        select: Option<SingleQueueModelFieldId>,
         /// Saved pattern in the dumprc file under [ethtool] section.
         #[clap(long, short, conflicts_with("fields"))]
         pattern: Option<String>,
@@ -1302,8 +1789,25 @@ pub enum DumpCommand {
         fields: Option<Vec<TcOptionField>>,
         #[clap(flatten)]
         opts: GeneralOpt,
+        /// Select field for operation, use with --sort, --rsort, --filter, --top
+        #[clap(long, short)]
+        select: Option<SingleTcModelFieldId>,
         /// Saved pattern in the dumprc file under [tc] section.
         #[clap(long, short, conflicts_with("fields"))]
         pattern: Option<String>,
     },
+    #[clap(about = RESCTRL_ABOUT, long_about = RESCTRL_LONG_ABOUT.as_str())]
+    Resctrl {
+        /// Select which fields to display and in what order.
+        #[clap(short, long, num_args = 1..)]
+        fields: Option<Vec<ResctrlOptionField>>,
+        #[clap(flatten)]
+        opts: GeneralOpt,
+        /// Select field for operation, use with --sort, --rsort, --filter, --top
+        #[clap(long, short)]
+        select: Option<ResctrlMonGroupModelFieldId>,
+        /// Saved pattern in the dumprc file under [resctrl] section.
+        #[clap(long, short, conflicts_with("fields"))]
+        pattern: Option<String>,
+    },
 }
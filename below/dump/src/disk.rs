@@ -87,47 +87,25 @@ impl Dumper for Disk {
                     Some(OutputFormat::Raw) | None => write!(
                         output,
                         "{}",
-                        print::dump_raw_indented(
-                            &self.fields,
-                            ctx,
-                            model,
-                            *round,
-                            self.opts.repeat_title,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_raw_indented(&self.fields, ctx, model, *round, &self.opts)
                     )?,
                     Some(OutputFormat::Csv) => write!(
                         output,
                         "{}",
-                        print::dump_csv(
-                            &self.fields,
-                            ctx,
-                            model,
-                            *round,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_csv(&self.fields, ctx, model, *round, &self.opts)
                     )?,
                     Some(OutputFormat::Tsv) => write!(
                         output,
                         "{}",
-                        print::dump_tsv(
-                            &self.fields,
-                            ctx,
-                            model,
-                            *round,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_tsv(&self.fields, ctx, model, *round, &self.opts)
                     )?,
                     Some(OutputFormat::KeyVal) => write!(
                         output,
                         "{}",
-                        print::dump_kv(&self.fields, ctx, model, self.opts.raw)
+                        print::dump_kv(&self.fields, ctx, model, &self.opts)
                     )?,
                     Some(OutputFormat::Json) => {
-                        let par = print::dump_json(&self.fields, ctx, model, self.opts.raw);
+                        let par = print::dump_json(&self.fields, ctx, model, &self.opts);
                         json_output.as_array_mut().unwrap().push(par);
                     }
                     Some(OutputFormat::OpenMetrics) => write!(
@@ -1,14 +1,22 @@
+use model::SingleQueueModelFieldId;
+
 use super::*;
 
 pub struct EthtoolQueue {
     opts: GeneralOpt,
+    select: Option<SingleQueueModelFieldId>,
     fields: Vec<EthtoolQueueField>,
 }
 
 impl EthtoolQueue {
-    pub fn new(opts: &GeneralOpt, fields: Vec<EthtoolQueueField>) -> Self {
+    pub fn new(
+        opts: &GeneralOpt,
+        select: Option<SingleQueueModelFieldId>,
+        fields: Vec<EthtoolQueueField>,
+    ) -> Self {
         Self {
             opts: opts.to_owned(),
+            select,
             fields,
         }
     }
@@ -26,7 +34,17 @@ impl Dumper for EthtoolQueue {
         let mut queues = Vec::new();
         for nic in model.network.interfaces.values() {
             for queue in &nic.queues {
-                queues.push(queue);
+                let should_print = match (self.select.as_ref(), self.opts.filter.as_ref()) {
+                    (Some(field_id), Some(filter)) => filter.is_match(
+                        &queue
+                            .query(field_id)
+                            .map_or("?".to_owned(), |v| v.to_string()),
+                    ),
+                    _ => true,
+                };
+                if should_print {
+                    queues.push(queue);
+                }
             }
         }
 
@@ -35,6 +53,20 @@ impl Dumper for EthtoolQueue {
             return Ok(IterExecResult::Skip);
         }
 
+        if let Some(field_id) = &self.select {
+            if self.opts.sort {
+                model::sort_queriables(&mut queues, field_id, false);
+            }
+
+            if self.opts.rsort {
+                model::sort_queriables(&mut queues, field_id, true);
+            }
+
+            if (self.opts.sort || self.opts.rsort) && self.opts.top != 0 {
+                queues.truncate(self.opts.top as usize);
+            }
+        }
+
         let mut json_output = json!([]);
 
         queues
@@ -44,47 +76,25 @@ impl Dumper for EthtoolQueue {
                     Some(OutputFormat::Raw) | None => write!(
                         output,
                         "{}",
-                        print::dump_raw(
-                            &self.fields,
-                            ctx,
-                            queue,
-                            *round,
-                            self.opts.repeat_title,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_raw(&self.fields, ctx, queue, *round, &self.opts)
                     )?,
                     Some(OutputFormat::Csv) => write!(
                         output,
                         "{}",
-                        print::dump_csv(
-                            &self.fields,
-                            ctx,
-                            queue,
-                            *round,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_csv(&self.fields, ctx, queue, *round, &self.opts)
                     )?,
                     Some(OutputFormat::Tsv) => write!(
                         output,
                         "{}",
-                        print::dump_tsv(
-                            &self.fields,
-                            ctx,
-                            queue,
-                            *round,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_tsv(&self.fields, ctx, queue, *round, &self.opts)
                     )?,
                     Some(OutputFormat::KeyVal) => write!(
                         output,
                         "{}",
-                        print::dump_kv(&self.fields, ctx, queue, self.opts.raw)
+                        print::dump_kv(&self.fields, ctx, queue, &self.opts)
                     )?,
                     Some(OutputFormat::Json) => {
-                        let par = print::dump_json(&self.fields, ctx, queue, self.opts.raw);
+                        let par = print::dump_json(&self.fields, ctx, queue, &self.opts);
                         json_output.as_array_mut().unwrap().push(par);
                     }
                     Some(OutputFormat::OpenMetrics) => write!(
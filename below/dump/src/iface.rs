@@ -45,13 +45,11 @@ impl Dumper for Iface {
         round: &mut usize,
         comma_flag: bool,
     ) -> Result<IterExecResult> {
-        let mut json_output = json!([]);
-
-        model
+        let mut interfaces: Vec<_> = model
             .network
             .interfaces
             .iter()
-            .filter(
+            .filter_map(
                 |(_, model)| match (self.select.as_ref(), self.opts.filter.as_ref()) {
                     (Some(field_id), Some(filter))
                         if !filter.is_match(
@@ -60,57 +58,54 @@ impl Dumper for Iface {
                                 .map_or("?".to_owned(), |v| v.to_string()),
                         ) =>
                     {
-                        false
+                        None
                     }
-                    _ => true,
+                    _ => Some(model),
                 },
             )
-            .map(|(_, model)| {
+            .collect();
+
+        if let Some(field_id) = &self.select {
+            if self.opts.sort {
+                model::sort_queriables(&mut interfaces, &field_id, false);
+            }
+
+            if self.opts.rsort {
+                model::sort_queriables(&mut interfaces, &field_id, true);
+            }
+
+            if (self.opts.sort || self.opts.rsort) && self.opts.top != 0 {
+                interfaces.truncate(self.opts.top as usize);
+            }
+        }
+        let mut json_output = json!([]);
+
+        interfaces
+            .into_iter()
+            .map(|model| {
                 match self.opts.output_format {
                     Some(OutputFormat::Raw) | None => write!(
                         output,
                         "{}",
-                        print::dump_raw(
-                            &self.fields,
-                            ctx,
-                            model,
-                            *round,
-                            self.opts.repeat_title,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_raw(&self.fields, ctx, model, *round, &self.opts)
                     )?,
                     Some(OutputFormat::Csv) => write!(
                         output,
                         "{}",
-                        print::dump_csv(
-                            &self.fields,
-                            ctx,
-                            model,
-                            *round,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_csv(&self.fields, ctx, model, *round, &self.opts)
                     )?,
                     Some(OutputFormat::Tsv) => write!(
                         output,
                         "{}",
-                        print::dump_tsv(
-                            &self.fields,
-                            ctx,
-                            model,
-                            *round,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_tsv(&self.fields, ctx, model, *round, &self.opts)
                     )?,
                     Some(OutputFormat::KeyVal) => write!(
                         output,
                         "{}",
-                        print::dump_kv(&self.fields, ctx, model, self.opts.raw)
+                        print::dump_kv(&self.fields, ctx, model, &self.opts)
                     )?,
                     Some(OutputFormat::Json) => {
-                        let par = print::dump_json(&self.fields, ctx, model, self.opts.raw);
+                        let par = print::dump_json(&self.fields, ctx, model, &self.opts);
                         json_output.as_array_mut().unwrap().push(par);
                     }
                     Some(OutputFormat::OpenMetrics) => write!(
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::fs;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io;
 use std::io::Write;
@@ -21,6 +21,7 @@ use std::str::FromStr;
 use std::sync::mpsc::Receiver;
 use std::time::SystemTime;
 
+use anyhow::anyhow;
 use anyhow::bail;
 use anyhow::Context;
 use anyhow::Error;
@@ -31,15 +32,15 @@ use common::util::get_belowrc_filename;
 use common::util::timestamp_to_datetime;
 use model::Field;
 use model::FieldId;
+use model::ModelParts;
 use model::Queriable;
+use enum_iterator::Sequence;
 use serde_json::json;
 use serde_json::Value;
 use store::advance::new_advance_local;
 use store::advance::new_advance_remote;
 use store::Advance;
 use store::Direction;
-use tar::Archive;
-use tempfile::TempDir;
 use toml::value::Value as TValue;
 
 pub mod btrfs;
@@ -51,6 +52,9 @@ pub mod iface;
 pub mod network;
 pub mod print;
 pub mod process;
+pub mod resctrl;
+pub mod slab;
+pub mod snapshot;
 pub mod system;
 pub mod tc;
 pub mod tmain;
@@ -64,6 +68,8 @@ pub use command::DumpCommand;
 use command::GeneralOpt;
 use command::OutputFormat;
 use render::HasRenderConfigForDump;
+use render::RenderOverride;
+use render::SizeUnit;
 use tmain::dump_timeseries;
 use tmain::Dumper;
 use tmain::IterExecResult;
@@ -81,31 +87,62 @@ use tmain::IterExecResult;
 pub enum CommonField {
     Timestamp,
     Datetime,
+    /// Samples missing right before this one, whose rates are therefore
+    /// averaged over a longer time
+    Gaps,
+    /// Random id of the boot the sample was collected in, which changes on
+    /// reboots
+    BootId,
+    /// Host metadata, added to every row by --with-host-metadata
+    HostName,
+    HostKernelVersion,
+    HostBelowVersion,
+}
+
+impl CommonField {
+    /// Fields added by --with-host-metadata
+    pub const HOST_METADATA: [CommonField; 3] = [
+        Self::HostName,
+        Self::HostKernelVersion,
+        Self::HostBelowVersion,
+    ];
 }
 
 /// Context for initializing CommonFields.
 pub struct CommonFieldContext {
     pub timestamp: i64,
     pub hostname: String,
+    pub gaps: u64,
+    pub boot_id: Option<String>,
+    pub kernel_version: Option<String>,
+    /// Version of below that recorded the sample
+    pub below_version: Option<String>,
 }
 
 impl CommonField {
     pub fn get_field(&self, ctx: &CommonFieldContext) -> Option<Field> {
         match self {
-            Self::Timestamp => Field::from(ctx.timestamp),
-            Self::Datetime => Field::from(timestamp_to_datetime(&ctx.timestamp)),
+            Self::Timestamp => Some(Field::from(ctx.timestamp)),
+            Self::Datetime => Some(Field::from(timestamp_to_datetime(&ctx.timestamp))),
+            Self::Gaps => Some(Field::from(ctx.gaps)),
+            Self::BootId => ctx.boot_id.clone().map(Field::from),
+            Self::HostName => Some(Field::from(ctx.hostname.clone())),
+            Self::HostKernelVersion => ctx.kernel_version.clone().map(Field::from),
+            Self::HostBelowVersion => ctx.below_version.clone().map(Field::from),
         }
-        .into()
     }
 }
 
 /// Generic field for dumping different types of models. It's either a
-/// CommonField or a FieldId that extracts a Field from a given model. It
-/// represents a unified interface for dumpable items.
+/// CommonField, a FieldId that extracts a Field from a given model, or a
+/// selector with wildcards that expands to the FieldIds it matches in each
+/// model (see `Queriable::expand_selector`). It represents a unified
+/// interface for dumpable items.
 #[derive(Clone, Debug, PartialEq)]
 pub enum DumpField<F: FieldId> {
     Common(CommonField),
     FieldId(F),
+    Selector(String),
 }
 
 pub type CgroupField = DumpField<model::SingleCgroupModelFieldId>;
@@ -113,20 +150,27 @@ pub type ProcessField = DumpField<model::SingleProcessModelFieldId>;
 pub type SystemField = DumpField<model::SystemModelFieldId>;
 pub type DiskField = DumpField<model::SingleDiskModelFieldId>;
 pub type BtrfsField = DumpField<model::BtrfsModelFieldId>;
+pub type BtrfsDeviceField = DumpField<model::BtrfsDeviceModelFieldId>;
+pub type SlabField = DumpField<model::SingleSlabModelFieldId>;
 pub type NetworkField = DumpField<model::NetworkModelFieldId>;
 pub type IfaceField = DumpField<model::SingleNetModelFieldId>;
 // Essentially the same as NetworkField
 pub type TransportField = DumpField<model::NetworkModelFieldId>;
 pub type EthtoolQueueField = DumpField<model::SingleQueueModelFieldId>;
 pub type TcField = DumpField<model::SingleTcModelFieldId>;
+pub type ResctrlField = DumpField<model::ResctrlMonGroupModelFieldId>;
+
+/// Formatting overrides keyed by field id string.
+pub type FieldOverrides = BTreeMap<String, RenderOverride>;
 
 fn get_advance(
     logger: slog::Logger,
     dir: PathBuf,
     host: Option<String>,
     port: Option<u16>,
-    snapshot: Option<String>,
+    snapshot_dir: Option<PathBuf>,
     opts: &command::GeneralOpt,
+    parts: ModelParts,
 ) -> Result<(SystemTime, SystemTime, Advance)> {
     let (time_begin, time_end) = cliutil::system_time_range_from_date_and_adjuster(
         opts.begin.as_str(),
@@ -135,25 +179,21 @@ fn get_advance(
         opts.yesterdays.as_deref(),
     )?;
 
-    let mut advance = match (host, snapshot) {
+    let mut advance = match (host, snapshot_dir) {
         (None, None) => new_advance_local(logger.clone(), dir, time_begin),
         (Some(host), None) => new_advance_remote(logger.clone(), host, port, time_begin)?,
-        (None, Some(snapshot)) => {
-            let mut tarball =
-                Archive::new(fs::File::open(&snapshot).context("Failed to open snapshot file")?);
-            let mut snapshot_dir = TempDir::with_prefix("snapshot_replay.")?.into_path();
-            tarball.unpack(&snapshot_dir)?;
-            // Find and append the name of the original snapshot directory
-            for path in fs::read_dir(&snapshot_dir)? {
-                snapshot_dir.push(path.unwrap().file_name());
-            }
-            new_advance_local(logger.clone(), snapshot_dir, time_begin)
-        }
+        (None, Some(snapshot_dir)) => new_advance_local(logger.clone(), snapshot_dir, time_begin),
         (Some(_), Some(_)) => {
             bail!("--host and --snapshot are incompatible options")
         }
     };
 
+    // The system model is always needed since dumps are labeled with its
+    // hostname.
+    advance.set_model_parts(ModelParts {
+        system: true,
+        ..parts
+    });
     advance.initialize();
 
     Ok((time_begin, time_end, advance))
@@ -211,6 +251,224 @@ pub fn parse_pattern<T: FromStr>(
     )
 }
 
+/// Read the [dump.format.<section_key>] table of belowrc, which maps field ids
+/// to formatting overrides, e.g.
+///
+/// [dump.format.process]
+/// "mem.rss_bytes" = { unit = "MiB", precision = 1, width = 12 }
+///
+/// A missing belowrc file or table means no overrides.
+pub fn parse_field_overrides(filename: &str, section_key: &str) -> Result<FieldOverrides> {
+    let belowrc_str = match std::fs::read_to_string(filename) {
+        Ok(belowrc_str) => belowrc_str,
+        Err(_) => return Ok(Default::default()),
+    };
+    let belowrc_val = belowrc_str
+        .parse::<TValue>()
+        .context("Failed to parse belowrc file")?;
+    let table = match belowrc_val
+        .get(get_belowrc_dump_section_key())
+        .and_then(|dump| dump.get("format"))
+        .and_then(|format| format.get(section_key))
+    {
+        Some(table) => table,
+        None => return Ok(Default::default()),
+    };
+    let section = format!(
+        "[{}.format.{}]",
+        get_belowrc_dump_section_key(),
+        section_key
+    );
+    let table = table
+        .as_table()
+        .with_context(|| format!("{} is not a table", section))?;
+
+    let mut overrides = FieldOverrides::new();
+    for (field_id, value) in table {
+        let value = value
+            .as_table()
+            .with_context(|| format!("{} {} is not a table", section, field_id))?;
+        let mut render_override = RenderOverride::default();
+        for (key, option) in value {
+            let usize_option = || -> Result<usize> {
+                option
+                    .as_integer()
+                    .and_then(|v| usize::try_from(v).ok())
+                    .with_context(|| {
+                        format!("{} {}.{} is not a positive integer", section, field_id, key)
+                    })
+            };
+            match key.as_str() {
+                "width" => render_override.width = Some(usize_option()?),
+                "precision" => render_override.precision = Some(usize_option()?),
+                "unit" => {
+                    let unit = option.as_str().with_context(|| {
+                        format!("{} {}.unit is not a string", section, field_id)
+                    })?;
+                    render_override.unit = Some(
+                        unit.parse::<SizeUnit>()
+                            .map_err(Error::msg)
+                            .with_context(|| format!("{} {}.unit", section, field_id))?,
+                    );
+                }
+                _ => bail!(
+                    "{} {}: unknown option {}, expecting width, precision or unit",
+                    section,
+                    field_id,
+                    key
+                ),
+            }
+        }
+        overrides.insert(field_id.to_owned(), render_override);
+    }
+    Ok(overrides)
+}
+
+/// Read the `<section_key>` entry of the [dump.defaults] table of belowrc,
+/// which replaces the default fields of a dump subcommand, e.g.
+///
+/// [dump.defaults]
+/// cgroup = ["datetime", "name", "cpu", "mem.total"]
+///
+/// A missing belowrc file or entry means the built-in defaults.
+pub fn parse_default_fields<T: FromStr>(
+    filename: &str,
+    section_key: &str,
+) -> Result<Option<Vec<T>>> {
+    let belowrc_str = match std::fs::read_to_string(filename) {
+        Ok(belowrc_str) => belowrc_str,
+        Err(_) => return Ok(None),
+    };
+    let belowrc_val = belowrc_str
+        .parse::<TValue>()
+        .context("Failed to parse belowrc file")?;
+    let fields = match belowrc_val
+        .get(get_belowrc_dump_section_key())
+        .and_then(|dump| dump.get("defaults"))
+        .and_then(|defaults| defaults.get(section_key))
+    {
+        Some(fields) => fields,
+        None => return Ok(None),
+    };
+    let key = format!(
+        "{}.defaults.{}",
+        get_belowrc_dump_section_key(),
+        section_key
+    );
+    fields
+        .as_array()
+        .with_context(|| format!("{} is not an array", key))?
+        .iter()
+        .map(|field| {
+            let field_str = field
+                .as_str()
+                .with_context(|| format!("{}: {} is not a string", key, field))?;
+            T::from_str(field_str)
+                .map_err(|_| anyhow!("{}: failed to parse field key: {}", key, field_str))
+        })
+        .collect::<Result<Vec<T>>>()
+        .map(Some)
+}
+
+/// Field id of a model with its unit and description, as listed by
+/// --list-fields
+#[derive(Debug, PartialEq)]
+pub struct FieldDescription {
+    pub field_id: String,
+    pub unit: Option<&'static str>,
+    pub doc: Option<&'static str>,
+}
+
+/// Describe all fields of a model
+pub fn describe_fields<F: FieldId + Sequence + ToString>() -> Vec<FieldDescription> {
+    enum_iterator::all::<F>()
+        .map(|field_id| FieldDescription {
+            field_id: field_id.to_string(),
+            unit: field_id.unit(),
+            doc: field_id.doc(),
+        })
+        .collect()
+}
+
+/// Options of `cmd` and its model fields, described lazily
+fn list_fields_of(cmd: &DumpCommand) -> (&GeneralOpt, fn() -> Vec<FieldDescription>) {
+    match cmd {
+        DumpCommand::System { opts, .. } => (opts, describe_fields::<model::SystemModelFieldId>),
+        DumpCommand::Disk { opts, .. } => (opts, describe_fields::<model::SingleDiskModelFieldId>),
+        DumpCommand::Btrfs { opts, .. } => (opts, describe_fields::<model::BtrfsModelFieldId>),
+        DumpCommand::BtrfsDevice { opts, .. } => {
+            (opts, describe_fields::<model::BtrfsDeviceModelFieldId>)
+        }
+        DumpCommand::Slab { opts, .. } => (opts, describe_fields::<model::SingleSlabModelFieldId>),
+        DumpCommand::Process { opts, .. } => {
+            (opts, describe_fields::<model::SingleProcessModelFieldId>)
+        }
+        DumpCommand::Cgroup { opts, .. } => {
+            (opts, describe_fields::<model::SingleCgroupModelFieldId>)
+        }
+        DumpCommand::Iface { opts, .. } => (opts, describe_fields::<model::SingleNetModelFieldId>),
+        DumpCommand::Network { opts, .. } | DumpCommand::Transport { opts, .. } => {
+            (opts, describe_fields::<model::NetworkModelFieldId>)
+        }
+        DumpCommand::EthtoolQueue { opts, .. } => {
+            (opts, describe_fields::<model::SingleQueueModelFieldId>)
+        }
+        DumpCommand::Tc { opts, .. } => (opts, describe_fields::<model::SingleTcModelFieldId>),
+        DumpCommand::Resctrl { opts, .. } => {
+            (opts, describe_fields::<model::ResctrlMonGroupModelFieldId>)
+        }
+    }
+}
+
+/// Write `fields` as an aligned table, or as JSON with -O json
+pub fn write_field_list(
+    fields: &[FieldDescription],
+    output_format: Option<OutputFormat>,
+    output: &mut dyn Write,
+) -> Result<()> {
+    if output_format == Some(OutputFormat::Json) {
+        let list = fields
+            .iter()
+            .map(|field| {
+                json!({
+                    "field": field.field_id,
+                    "unit": field.unit,
+                    "description": field.doc,
+                })
+            })
+            .collect::<Vec<_>>();
+        writeln!(output, "{}", serde_json::to_string_pretty(&list)?)?;
+        return Ok(());
+    }
+    let id_width = fields
+        .iter()
+        .map(|field| field.field_id.len())
+        .chain(std::iter::once("Field".len()))
+        .max()
+        .unwrap_or_default();
+    let unit_width = fields
+        .iter()
+        .filter_map(|field| field.unit.map(str::len))
+        .chain(std::iter::once("Unit".len()))
+        .max()
+        .unwrap_or_default();
+    writeln!(
+        output,
+        "{:id_width$}  {:unit_width$}  Description",
+        "Field", "Unit"
+    )?;
+    for field in fields {
+        let line = format!(
+            "{:id_width$}  {:unit_width$}  {}",
+            field.field_id,
+            field.unit.unwrap_or("-"),
+            field.doc.unwrap_or("")
+        );
+        writeln!(output, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
 pub fn run(
     logger: slog::Logger,
     errs: Receiver<Error>,
@@ -222,14 +480,46 @@ pub fn run(
 ) -> Result<()> {
     let filename = get_belowrc_filename();
 
-    match cmd {
+    let (opts, describe) = list_fields_of(&cmd);
+    if opts.list_fields {
+        let mut output: Box<dyn Write> = match opts.output.as_ref() {
+            Some(file_path) => Box::new(File::create(file_path)?),
+            None => Box::new(io::stdout()),
+        };
+        return write_field_list(&describe(), opts.output_format, output.as_mut());
+    }
+    let summary_path = opts.summary.clone();
+    if host.is_some() && snapshot.is_some() {
+        bail!("--host and --snapshot are incompatible options")
+    }
+    // Kept unpacked until the dump is done
+    let unpacked = snapshot
+        .map(|snapshot| snapshot::open_snapshot(&logger, &snapshot))
+        .transpose()?;
+    let snapshot = unpacked
+        .as_ref()
+        .map(|unpacked| unpacked.store_dir().to_owned());
+
+    let summary = match cmd {
         DumpCommand::System {
             fields,
-            opts,
+            mut opts,
             pattern,
         } => {
-            let (time_begin, time_end, advance) =
-                get_advance(logger, dir, host, port, snapshot, &opts)?;
+            opts.field_overrides = parse_field_overrides(&filename, "system")?;
+            let default_fields = parse_default_fields(&filename, "system")?;
+            let (time_begin, time_end, advance) = get_advance(
+                logger,
+                dir,
+                host,
+                port,
+                snapshot,
+                &opts,
+                ModelParts {
+                    dstate: true,
+                    ..ModelParts::NONE
+                },
+            )?;
             let default = opts.everything || opts.default;
             let detail = opts.everything || opts.detail;
             let fields = if let Some(pattern_key) = pattern {
@@ -240,10 +530,13 @@ pub fn run(
             let fields = expand_fields(
                 match fields.as_ref() {
                     Some(fields) if !default => fields,
-                    _ => command::DEFAULT_SYSTEM_FIELDS,
+                    _ => default_fields
+                        .as_deref()
+                        .unwrap_or(command::DEFAULT_SYSTEM_FIELDS),
                 },
                 detail,
             );
+            let fields = opts.add_host_metadata(fields);
             let system = system::System::new(&opts, fields);
             let mut output: Box<dyn Write> = match opts.output.as_ref() {
                 Some(file_path) => Box::new(File::create(file_path)?),
@@ -256,18 +549,21 @@ pub fn run(
                 &system,
                 output.as_mut(),
                 opts.output_format,
+                opts.get_bucket()?,
                 opts.br,
                 errs,
             )
         }
         DumpCommand::Disk {
             fields,
-            opts,
+            mut opts,
             select,
             pattern,
         } => {
+            opts.field_overrides = parse_field_overrides(&filename, "disk")?;
+            let default_fields = parse_default_fields(&filename, "disk")?;
             let (time_begin, time_end, advance) =
-                get_advance(logger, dir, host, port, snapshot, &opts)?;
+                get_advance(logger, dir, host, port, snapshot, &opts, ModelParts::NONE)?;
             let default = opts.everything || opts.default;
             let detail = opts.everything || opts.detail;
             let fields = if let Some(pattern_key) = pattern {
@@ -278,10 +574,13 @@ pub fn run(
             let fields = expand_fields(
                 match fields.as_ref() {
                     Some(fields) if !default => fields,
-                    _ => command::DEFAULT_DISK_FIELDS,
+                    _ => default_fields
+                        .as_deref()
+                        .unwrap_or(command::DEFAULT_DISK_FIELDS),
                 },
                 detail,
             );
+            let fields = opts.add_host_metadata(fields);
             let disk = disk::Disk::new(&opts, select, fields);
             let mut output: Box<dyn Write> = match opts.output.as_ref() {
                 Some(file_path) => Box::new(File::create(file_path)?),
@@ -294,18 +593,21 @@ pub fn run(
                 &disk,
                 output.as_mut(),
                 opts.output_format,
+                opts.get_bucket()?,
                 opts.br,
                 errs,
             )
         }
         DumpCommand::Btrfs {
             fields,
-            opts,
+            mut opts,
             select,
             pattern,
         } => {
+            opts.field_overrides = parse_field_overrides(&filename, "btrfs")?;
+            let default_fields = parse_default_fields(&filename, "btrfs")?;
             let (time_begin, time_end, advance) =
-                get_advance(logger, dir, host, port, snapshot, &opts)?;
+                get_advance(logger, dir, host, port, snapshot, &opts, ModelParts::NONE)?;
             let default = opts.everything || opts.default;
             let detail = opts.everything || opts.detail;
             let fields = if let Some(pattern_key) = pattern {
@@ -316,10 +618,13 @@ pub fn run(
             let fields = expand_fields(
                 match fields.as_ref() {
                     Some(fields) if !default => fields,
-                    _ => command::DEFAULT_BTRFS_FIELDS,
+                    _ => default_fields
+                        .as_deref()
+                        .unwrap_or(command::DEFAULT_BTRFS_FIELDS),
                 },
                 detail,
             );
+            let fields = opts.add_host_metadata(fields);
             let btrfs = btrfs::Btrfs::new(&opts, select, fields);
             let mut output: Box<dyn Write> = match opts.output.as_ref() {
                 Some(file_path) => Box::new(File::create(file_path)?),
@@ -332,18 +637,119 @@ pub fn run(
                 &btrfs,
                 output.as_mut(),
                 opts.output_format,
+                opts.get_bucket()?,
                 opts.br,
                 errs,
             )
         }
-        DumpCommand::Process {
+        DumpCommand::BtrfsDevice {
             fields,
-            opts,
+            mut opts,
             select,
             pattern,
         } => {
+            opts.field_overrides = parse_field_overrides(&filename, "btrfs_device")?;
+            let default_fields = parse_default_fields(&filename, "btrfs_device")?;
             let (time_begin, time_end, advance) =
-                get_advance(logger, dir, host, port, snapshot, &opts)?;
+                get_advance(logger, dir, host, port, snapshot, &opts, ModelParts::NONE)?;
+            let default = opts.everything || opts.default;
+            let detail = opts.everything || opts.detail;
+            let fields = if let Some(pattern_key) = pattern {
+                parse_pattern(filename, pattern_key, "btrfs_device")
+            } else {
+                fields
+            };
+            let fields = expand_fields(
+                match fields.as_ref() {
+                    Some(fields) if !default => fields,
+                    _ => default_fields
+                        .as_deref()
+                        .unwrap_or(command::DEFAULT_BTRFS_DEVICE_FIELDS),
+                },
+                detail,
+            );
+            let fields = opts.add_host_metadata(fields);
+            let btrfs_device = btrfs::BtrfsDevice::new(&opts, select, fields);
+            let mut output: Box<dyn Write> = match opts.output.as_ref() {
+                Some(file_path) => Box::new(File::create(file_path)?),
+                None => Box::new(io::stdout()),
+            };
+            dump_timeseries(
+                advance,
+                time_begin,
+                time_end,
+                &btrfs_device,
+                output.as_mut(),
+                opts.output_format,
+                opts.get_bucket()?,
+                opts.br,
+                errs,
+            )
+        }
+        DumpCommand::Slab {
+            fields,
+            mut opts,
+            select,
+            pattern,
+        } => {
+            opts.field_overrides = parse_field_overrides(&filename, "slab")?;
+            let default_fields = parse_default_fields(&filename, "slab")?;
+            let (time_begin, time_end, advance) =
+                get_advance(logger, dir, host, port, snapshot, &opts, ModelParts::NONE)?;
+            let default = opts.everything || opts.default;
+            let detail = opts.everything || opts.detail;
+            let fields = if let Some(pattern_key) = pattern {
+                parse_pattern(filename, pattern_key, "slab")
+            } else {
+                fields
+            };
+            let fields = expand_fields(
+                match fields.as_ref() {
+                    Some(fields) if !default => fields,
+                    _ => default_fields
+                        .as_deref()
+                        .unwrap_or(command::DEFAULT_SLAB_FIELDS),
+                },
+                detail,
+            );
+            let fields = opts.add_host_metadata(fields);
+            let slab = slab::Slab::new(&opts, select, fields);
+            let mut output: Box<dyn Write> = match opts.output.as_ref() {
+                Some(file_path) => Box::new(File::create(file_path)?),
+                None => Box::new(io::stdout()),
+            };
+            dump_timeseries(
+                advance,
+                time_begin,
+                time_end,
+                &slab,
+                output.as_mut(),
+                opts.output_format,
+                opts.get_bucket()?,
+                opts.br,
+                errs,
+            )
+        }
+        DumpCommand::Process {
+            fields,
+            mut opts,
+            select,
+            pattern,
+        } => {
+            opts.field_overrides = parse_field_overrides(&filename, "process")?;
+            let default_fields = parse_default_fields(&filename, "process")?;
+            let (time_begin, time_end, advance) = get_advance(
+                logger,
+                dir,
+                host,
+                port,
+                snapshot,
+                &opts,
+                ModelParts {
+                    process: true,
+                    ..ModelParts::NONE
+                },
+            )?;
             let default = opts.everything || opts.default;
             let detail = opts.everything || opts.detail;
             let fields = if let Some(pattern_key) = pattern {
@@ -354,10 +760,13 @@ pub fn run(
             let fields = expand_fields(
                 match fields.as_ref() {
                     Some(fields) if !default => fields,
-                    _ => command::DEFAULT_PROCESS_FIELDS,
+                    _ => default_fields
+                        .as_deref()
+                        .unwrap_or(command::DEFAULT_PROCESS_FIELDS),
                 },
                 detail,
             );
+            let fields = opts.add_host_metadata(fields);
             let process = process::Process::new(&opts, select, fields);
             let mut output: Box<dyn Write> = match opts.output.as_ref() {
                 Some(file_path) => Box::new(File::create(file_path)?),
@@ -370,18 +779,34 @@ pub fn run(
                 &process,
                 output.as_mut(),
                 opts.output_format,
+                opts.get_bucket()?,
                 opts.br,
                 errs,
             )
         }
         DumpCommand::Cgroup {
             fields,
-            opts,
+            mut opts,
             select,
             pattern,
+            subtree,
+            max_depth,
         } => {
-            let (time_begin, time_end, advance) =
-                get_advance(logger, dir, host, port, snapshot, &opts)?;
+            opts.field_overrides = parse_field_overrides(&filename, "cgroup")?;
+            let default_fields = parse_default_fields(&filename, "cgroup")?;
+            let (time_begin, time_end, advance) = get_advance(
+                logger,
+                dir,
+                host,
+                port,
+                snapshot,
+                &opts,
+                ModelParts {
+                    cgroup: true,
+                    dstate: true,
+                    ..ModelParts::NONE
+                },
+            )?;
             let default = opts.everything || opts.default;
             let detail = opts.everything || opts.detail;
             let fields = if let Some(pattern_key) = pattern {
@@ -392,11 +817,15 @@ pub fn run(
             let fields = expand_fields(
                 match fields.as_ref() {
                     Some(fields) if !default => fields,
-                    _ => command::DEFAULT_CGROUP_FIELDS,
+                    _ => default_fields
+                        .as_deref()
+                        .unwrap_or(command::DEFAULT_CGROUP_FIELDS),
                 },
                 detail,
             );
-            let cgroup = cgroup::Cgroup::new(&opts, select, fields);
+            let fields = opts.add_host_metadata(fields);
+            let cgroup = cgroup::Cgroup::new(&opts, select, fields)
+                .with_subtree(subtree.as_deref(), max_depth);
             let mut output: Box<dyn Write> = match opts.output.as_ref() {
                 Some(file_path) => Box::new(File::create(file_path)?),
                 None => Box::new(io::stdout()),
@@ -408,18 +837,31 @@ pub fn run(
                 &cgroup,
                 output.as_mut(),
                 opts.output_format,
+                opts.get_bucket()?,
                 opts.br,
                 errs,
             )
         }
         DumpCommand::Iface {
             fields,
-            opts,
+            mut opts,
             select,
             pattern,
         } => {
-            let (time_begin, time_end, advance) =
-                get_advance(logger, dir, host, port, snapshot, &opts)?;
+            opts.field_overrides = parse_field_overrides(&filename, "iface")?;
+            let default_fields = parse_default_fields(&filename, "iface")?;
+            let (time_begin, time_end, advance) = get_advance(
+                logger,
+                dir,
+                host,
+                port,
+                snapshot,
+                &opts,
+                ModelParts {
+                    network: true,
+                    ..ModelParts::NONE
+                },
+            )?;
             let default = opts.everything || opts.default;
             let detail = opts.everything || opts.detail;
             let fields = if let Some(pattern_key) = pattern {
@@ -430,10 +872,13 @@ pub fn run(
             let fields = expand_fields(
                 match fields.as_ref() {
                     Some(fields) if !default => fields,
-                    _ => command::DEFAULT_IFACE_FIELDS,
+                    _ => default_fields
+                        .as_deref()
+                        .unwrap_or(command::DEFAULT_IFACE_FIELDS),
                 },
                 detail,
             );
+            let fields = opts.add_host_metadata(fields);
             let iface = iface::Iface::new(&opts, select, fields);
             let mut output: Box<dyn Write> = match opts.output.as_ref() {
                 Some(file_path) => Box::new(File::create(file_path)?),
@@ -446,17 +891,30 @@ pub fn run(
                 &iface,
                 output.as_mut(),
                 opts.output_format,
+                opts.get_bucket()?,
                 opts.br,
                 errs,
             )
         }
         DumpCommand::Network {
             fields,
-            opts,
+            mut opts,
             pattern,
         } => {
-            let (time_begin, time_end, advance) =
-                get_advance(logger, dir, host, port, snapshot, &opts)?;
+            opts.field_overrides = parse_field_overrides(&filename, "network")?;
+            let default_fields = parse_default_fields(&filename, "network")?;
+            let (time_begin, time_end, advance) = get_advance(
+                logger,
+                dir,
+                host,
+                port,
+                snapshot,
+                &opts,
+                ModelParts {
+                    network: true,
+                    ..ModelParts::NONE
+                },
+            )?;
             let default = opts.everything || opts.default;
             let detail = opts.everything || opts.detail;
             let fields = if let Some(pattern_key) = pattern {
@@ -467,10 +925,13 @@ pub fn run(
             let fields = expand_fields(
                 match fields.as_ref() {
                     Some(fields) if !default => fields,
-                    _ => command::DEFAULT_NETWORK_FIELDS,
+                    _ => default_fields
+                        .as_deref()
+                        .unwrap_or(command::DEFAULT_NETWORK_FIELDS),
                 },
                 detail,
             );
+            let fields = opts.add_host_metadata(fields);
             let network = network::Network::new(&opts, fields);
             let mut output: Box<dyn Write> = match opts.output.as_ref() {
                 Some(file_path) => Box::new(File::create(file_path)?),
@@ -483,17 +944,30 @@ pub fn run(
                 &network,
                 output.as_mut(),
                 opts.output_format,
+                opts.get_bucket()?,
                 opts.br,
                 errs,
             )
         }
         DumpCommand::Transport {
             fields,
-            opts,
+            mut opts,
             pattern,
         } => {
-            let (time_begin, time_end, advance) =
-                get_advance(logger, dir, host, port, snapshot, &opts)?;
+            opts.field_overrides = parse_field_overrides(&filename, "transport")?;
+            let default_fields = parse_default_fields(&filename, "transport")?;
+            let (time_begin, time_end, advance) = get_advance(
+                logger,
+                dir,
+                host,
+                port,
+                snapshot,
+                &opts,
+                ModelParts {
+                    network: true,
+                    ..ModelParts::NONE
+                },
+            )?;
             let default = opts.everything || opts.default;
             let detail = opts.everything || opts.detail;
             let fields = if let Some(pattern_key) = pattern {
@@ -504,10 +978,13 @@ pub fn run(
             let fields = expand_fields(
                 match fields.as_ref() {
                     Some(fields) if !default => fields,
-                    _ => command::DEFAULT_TRANSPORT_FIELDS,
+                    _ => default_fields
+                        .as_deref()
+                        .unwrap_or(command::DEFAULT_TRANSPORT_FIELDS),
                 },
                 detail,
             );
+            let fields = opts.add_host_metadata(fields);
             let transport = transport::Transport::new(&opts, fields);
             let mut output: Box<dyn Write> = match opts.output.as_ref() {
                 Some(file_path) => Box::new(File::create(file_path)?),
@@ -520,17 +997,31 @@ pub fn run(
                 &transport,
                 output.as_mut(),
                 opts.output_format,
+                opts.get_bucket()?,
                 opts.br,
                 errs,
             )
         }
         DumpCommand::EthtoolQueue {
             fields,
-            opts,
+            mut opts,
+            select,
             pattern,
         } => {
-            let (time_begin, time_end, advance) =
-                get_advance(logger, dir, host, port, snapshot, &opts)?;
+            opts.field_overrides = parse_field_overrides(&filename, "ethtool_queue")?;
+            let default_fields = parse_default_fields(&filename, "ethtool_queue")?;
+            let (time_begin, time_end, advance) = get_advance(
+                logger,
+                dir,
+                host,
+                port,
+                snapshot,
+                &opts,
+                ModelParts {
+                    network: true,
+                    ..ModelParts::NONE
+                },
+            )?;
             let default = opts.everything || opts.default;
             let detail = opts.everything || opts.detail;
             let fields = if let Some(pattern_key) = pattern {
@@ -541,11 +1032,14 @@ pub fn run(
             let fields = expand_fields(
                 match fields.as_ref() {
                     Some(fields) if !default => fields,
-                    _ => command::DEFAULT_ETHTOOL_QUEUE_FIELDS,
+                    _ => default_fields
+                        .as_deref()
+                        .unwrap_or(command::DEFAULT_ETHTOOL_QUEUE_FIELDS),
                 },
                 detail,
             );
-            let ethtool = ethtool::EthtoolQueue::new(&opts, fields);
+            let fields = opts.add_host_metadata(fields);
+            let ethtool = ethtool::EthtoolQueue::new(&opts, select, fields);
             let mut output: Box<dyn Write> = match opts.output.as_ref() {
                 Some(file_path) => Box::new(File::create(file_path)?),
                 None => Box::new(io::stdout()),
@@ -557,17 +1051,31 @@ pub fn run(
                 &ethtool,
                 output.as_mut(),
                 opts.output_format,
+                opts.get_bucket()?,
                 opts.br,
                 errs,
             )
         }
         DumpCommand::Tc {
             fields,
-            opts,
+            mut opts,
+            select,
             pattern,
         } => {
-            let (time_begin, time_end, advance) =
-                get_advance(logger, dir, host, port, snapshot, &opts)?;
+            opts.field_overrides = parse_field_overrides(&filename, "tc")?;
+            let default_fields = parse_default_fields(&filename, "tc")?;
+            let (time_begin, time_end, advance) = get_advance(
+                logger,
+                dir,
+                host,
+                port,
+                snapshot,
+                &opts,
+                ModelParts {
+                    tc: true,
+                    ..ModelParts::NONE
+                },
+            )?;
             let detail = opts.everything || opts.detail;
             let fields = if let Some(pattern_key) = pattern {
                 parse_pattern(filename, pattern_key, "tc")
@@ -577,11 +1085,14 @@ pub fn run(
             let fields = expand_fields(
                 match fields.as_ref() {
                     Some(fields) => fields,
-                    _ => command::DEFAULT_TC_FIELDS,
+                    _ => default_fields
+                        .as_deref()
+                        .unwrap_or(command::DEFAULT_TC_FIELDS),
                 },
                 detail,
             );
-            let tc = tc::Tc::new(&opts, fields);
+            let fields = opts.add_host_metadata(fields);
+            let tc = tc::Tc::new(&opts, select, fields);
             let mut output: Box<dyn Write> = match opts.output.as_ref() {
                 Some(file_path) => Box::new(File::create(file_path)?),
                 None => Box::new(io::stdout()),
@@ -593,9 +1104,75 @@ pub fn run(
                 &tc,
                 output.as_mut(),
                 opts.output_format,
+                opts.get_bucket()?,
+                opts.br,
+                errs,
+            )
+        }
+        DumpCommand::Resctrl {
+            fields,
+            mut opts,
+            select,
+            pattern,
+        } => {
+            opts.field_overrides = parse_field_overrides(&filename, "resctrl")?;
+            let default_fields = parse_default_fields(&filename, "resctrl")?;
+            let (time_begin, time_end, advance) = get_advance(
+                logger,
+                dir,
+                host,
+                port,
+                snapshot,
+                &opts,
+                ModelParts {
+                    resctrl: true,
+                    ..ModelParts::NONE
+                },
+            )?;
+            let detail = opts.everything || opts.detail;
+            let fields = if let Some(pattern_key) = pattern {
+                parse_pattern(filename, pattern_key, "resctrl")
+            } else {
+                fields
+            };
+            let fields = expand_fields(
+                match fields.as_ref() {
+                    Some(fields) => fields,
+                    _ => default_fields
+                        .as_deref()
+                        .unwrap_or(command::DEFAULT_RESCTRL_FIELDS),
+                },
+                detail,
+            );
+            let fields = opts.add_host_metadata(fields);
+            let resctrl = resctrl::Resctrl::new(&opts, select, fields);
+            let mut output: Box<dyn Write> = match opts.output.as_ref() {
+                Some(file_path) => Box::new(File::create(file_path)?),
+                None => Box::new(io::stdout()),
+            };
+            dump_timeseries(
+                advance,
+                time_begin,
+                time_end,
+                &resctrl,
+                output.as_mut(),
+                opts.output_format,
+                opts.get_bucket()?,
                 opts.br,
                 errs,
             )
         }
+    }?;
+
+    match summary_path.as_deref() {
+        Some("-") => summary.write(&mut io::stderr())?,
+        Some(path) => summary.write(&mut File::create(path)?)?,
+        None => {}
+    }
+    // Let pipelines tell an empty range apart from a successful dump. Filters
+    // matching nothing in a range with samples are not an error.
+    if summary.samples == 0 {
+        bail!("No samples were found in the time range");
     }
+    Ok(())
 }
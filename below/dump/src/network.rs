@@ -41,48 +41,25 @@ impl Dumper for Network {
             Some(OutputFormat::Raw) | None => write!(
                 output,
                 "{}",
-                print::dump_raw(
-                    &self.fields,
-                    ctx,
-                    &model.network,
-                    *round,
-                    self.opts.repeat_title,
-                    self.opts.disable_title,
-                    self.opts.raw
-                )
+                print::dump_raw(&self.fields, ctx, &model.network, *round, &self.opts)
             )?,
             Some(OutputFormat::Csv) => write!(
                 output,
                 "{}",
-                print::dump_csv(
-                    &self.fields,
-                    ctx,
-                    &model.network,
-                    *round,
-                    self.opts.disable_title,
-                    self.opts.raw
-                )
+                print::dump_csv(&self.fields, ctx, &model.network, *round, &self.opts)
             )?,
             Some(OutputFormat::Tsv) => write!(
                 output,
                 "{}",
-                print::dump_tsv(
-                    &self.fields,
-                    ctx,
-                    &model.network,
-                    *round,
-                    self.opts.disable_title,
-                    self.opts.raw
-                )
+                print::dump_tsv(&self.fields, ctx, &model.network, *round, &self.opts)
             )?,
             Some(OutputFormat::KeyVal) => write!(
                 output,
                 "{}",
-                print::dump_kv(&self.fields, ctx, &model.network, self.opts.raw)
+                print::dump_kv(&self.fields, ctx, &model.network, &self.opts)
             )?,
             Some(OutputFormat::Json) => {
-                let json_output =
-                    print::dump_json(&self.fields, ctx, &model.network, self.opts.raw);
+                let json_output = print::dump_json(&self.fields, ctx, &model.network, &self.opts);
                 if comma_flag {
                     write!(output, ",{}", json_output)?;
                 } else {
@@ -12,6 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::borrow::Cow;
+use std::str::FromStr;
+
 use model::Field;
 use model::FieldId;
 use model::Nameable;
@@ -29,6 +32,11 @@ impl CommonField {
         match self {
             Self::Timestamp => rc.title("Timestamp").width(10),
             Self::Datetime => rc.title("Datetime").width(19),
+            Self::Gaps => rc.title("Gaps").width(5),
+            Self::BootId => rc.title("Boot ID").width(37),
+            Self::HostName => rc.title("Host").width(20),
+            Self::HostKernelVersion => rc.title("Kernel Version").width(50),
+            Self::HostBelowVersion => rc.title("Below Version").width(12),
         }
         .get()
     }
@@ -43,10 +51,62 @@ where
         match self {
             Self::Common(common) => common.to_string(),
             Self::FieldId(field_id) => field_id.to_string(),
+            Self::Selector(selector) => selector.clone(),
         }
     }
 }
 
+impl<F> DumpField<F>
+where
+    F: FieldId + ToString,
+    F::Queriable: HasRenderConfigForDump,
+{
+    /// RenderConfig for dumping this field. Raw mode drops the default
+    /// format and suffix, but an explicit unit or precision override wins.
+    pub fn get_overridden_render_config(&self, opts: &GeneralOpt) -> RenderConfig {
+        let mut config = self.get_render_config();
+        let render_override = opts.field_overrides.get(&self.get_field_id_str());
+        if let Some(render_override) = render_override {
+            config.apply_override(render_override);
+        }
+        if opts.raw {
+            if !render_override.is_some_and(|o| o.unit.is_some() || o.precision.is_some()) {
+                config.format = None;
+            }
+            config.suffix = None;
+        }
+        config
+    }
+
+    pub fn dump_field(
+        &self,
+        ctx: &CommonFieldContext,
+        model: &F::Queriable,
+        opts: &GeneralOpt,
+        fixed_width: bool,
+    ) -> String {
+        let config = self.get_overridden_render_config(opts);
+        config.render(self.get_field(ctx, model), fixed_width)
+    }
+}
+
+impl<F> DumpField<F>
+where
+    F: FieldId + ToString,
+    F::Queriable: HasRenderConfigForDump + Recursive,
+{
+    pub fn dump_field_indented(
+        &self,
+        ctx: &CommonFieldContext,
+        model: &F::Queriable,
+        opts: &GeneralOpt,
+        fixed_width: bool,
+    ) -> String {
+        let config = self.get_overridden_render_config(opts);
+        config.render_indented(self.get_field(ctx, model), fixed_width, model.get_depth())
+    }
+}
+
 impl<F> DumpField<F>
 where
     F: FieldId,
@@ -56,6 +116,7 @@ where
         match self {
             Self::Common(common) => common.get_render_config(),
             Self::FieldId(field_id) => F::Queriable::get_render_config_for_dump(field_id),
+            Self::Selector(selector) => render::RenderConfigBuilder::new().title(selector).get(),
         }
     }
 
@@ -65,7 +126,7 @@ where
     ) -> Option<RenderOpenMetricsConfigBuilder> {
         match self {
             // Common fields (eg timestamp) are already encoded into metric
-            Self::Common(_) => None,
+            Self::Common(_) | Self::Selector(_) => None,
             Self::FieldId(field_id) => model.get_openmetrics_config_for_dump(field_id),
         }
     }
@@ -74,24 +135,10 @@ where
         match self {
             Self::Common(common) => common.get_field(ctx),
             Self::FieldId(field_id) => model.query(field_id),
+            Self::Selector(_) => None,
         }
     }
 
-    pub fn dump_field(
-        &self,
-        ctx: &CommonFieldContext,
-        model: &F::Queriable,
-        raw: bool,
-        fixed_width: bool,
-    ) -> String {
-        let mut config = self.get_render_config();
-        if raw {
-            config.format = None;
-            config.suffix = None;
-        }
-        config.render(self.get_field(ctx, model), fixed_width)
-    }
-
     pub fn dump_field_openmetrics(
         &self,
         key: &str,
@@ -109,156 +156,261 @@ where
     }
 }
 
-impl<F> DumpField<F>
+/// `fields` with each selector replaced by the fields it selects in `model`.
+/// Selectors are expanded per model as e.g. the interfaces they select may
+/// come and go.
+fn expand_selectors<'a, T>(
+    fields: &'a [DumpField<T::FieldId>],
+    model: &T,
+) -> Cow<'a, [DumpField<T::FieldId>]>
 where
-    F: FieldId,
-    F::Queriable: HasRenderConfigForDump + Recursive,
+    T: Queriable,
+    T::FieldId: Clone,
 {
-    pub fn dump_field_indented(
-        &self,
-        ctx: &CommonFieldContext,
-        model: &F::Queriable,
-        raw: bool,
-        fixed_width: bool,
-    ) -> String {
-        let mut config = self.get_render_config();
-        if raw {
-            config.format = None;
-            config.suffix = None;
+    if !fields
+        .iter()
+        .any(|field| matches!(field, DumpField::Selector(_)))
+    {
+        return Cow::Borrowed(fields);
+    }
+    let mut expanded = Vec::new();
+    for field in fields {
+        match field {
+            DumpField::Selector(selector) => expanded.extend(
+                model
+                    .expand_selector(selector)
+                    .into_iter()
+                    .map(DumpField::FieldId),
+            ),
+            field => expanded.push(field.clone()),
+        }
+    }
+    Cow::Owned(expanded)
+}
+
+/// `fields` with each selector replaced by the fields it selected in the
+/// first model dumped, and whether each field came from a selector. Rows of
+/// csv and tsv output share the columns of a single title line, so unlike
+/// for other formats selectors are expanded once per run.
+fn expand_selectors_once<T>(
+    fields: &[DumpField<T::FieldId>],
+    model: &T,
+    opts: &GeneralOpt,
+) -> Vec<(DumpField<T::FieldId>, bool)>
+where
+    T: Queriable,
+    T::FieldId: Clone + ToString + FromStr,
+{
+    let mut selected_columns = opts.selected_columns.borrow_mut();
+    let mut selected_columns = selected_columns
+        .get_or_insert_with(|| {
+            fields
+                .iter()
+                .filter_map(|field| match field {
+                    DumpField::Selector(selector) => Some(
+                        model
+                            .expand_selector(selector)
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect(),
+                    ),
+                    _ => None,
+                })
+                .collect()
+        })
+        .iter();
+    let mut expanded = Vec::new();
+    for field in fields {
+        match field {
+            DumpField::Selector(_) => expanded.extend(
+                selected_columns
+                    .next()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|field_id| T::FieldId::from_str(field_id).ok())
+                    .map(|field_id| (DumpField::FieldId(field_id), true)),
+            ),
+            field => expanded.push((field.clone(), false)),
         }
-        config.render_indented(self.get_field(ctx, model), fixed_width, model.get_depth())
     }
+    expanded
 }
 
-pub fn dump_kv<T: HasRenderConfigForDump>(
+/// Rows of csv and tsv output, with `sep` between fields
+fn dump_separated<T>(
     fields: &[DumpField<T::FieldId>],
     ctx: &CommonFieldContext,
     model: &T,
-    raw: bool,
-) -> String {
+    round: usize,
+    opts: &GeneralOpt,
+    sep: &'static str,
+) -> String
+where
+    T: HasRenderConfigForDump,
+    T::FieldId: Clone + ToString + FromStr,
+{
+    let columns = expand_selectors_once(fields, model, opts);
     let mut res = String::new();
-    for field in fields {
-        let config = field.get_render_config();
+    if !opts.disable_title && round == 0 {
+        let fields = columns
+            .iter()
+            .map(|(field, _)| field.clone())
+            .collect::<Vec<_>>();
+        res.push_str(&dump_title_line(&fields, sep, opts, false));
+    }
+    for (field, selected) in columns.iter() {
+        // Items selected in the first model may be gone since
+        if !*selected || field.get_field(ctx, model).is_some() {
+            res.push_str(&field.dump_field(ctx, model, opts, false));
+        }
+        res.push_str(sep);
+    }
+    res.push('\n');
+    res
+}
+
+pub fn dump_kv<T>(
+    fields: &[DumpField<T::FieldId>],
+    ctx: &CommonFieldContext,
+    model: &T,
+    opts: &GeneralOpt,
+) -> String
+where
+    T: HasRenderConfigForDump,
+    T::FieldId: Clone + ToString,
+{
+    let fields = expand_selectors(fields, model);
+    let mut res = String::new();
+    for field in fields.iter() {
+        let config = field.get_overridden_render_config(opts);
         res.push_str(&format!(
             "{}: {}\n",
             config.render_title(false),
-            field.dump_field(ctx, model, raw, false),
+            field.dump_field(ctx, model, opts, false),
         ));
     }
     res.push('\n');
     res
 }
 
-pub fn dump_json<T: HasRenderConfigForDump>(
+pub fn dump_json<T>(
     fields: &[DumpField<T::FieldId>],
     ctx: &CommonFieldContext,
     model: &T,
-    raw: bool,
-) -> Value {
+    opts: &GeneralOpt,
+) -> Value
+where
+    T: HasRenderConfigForDump,
+    T::FieldId: Clone + ToString,
+{
+    let fields = expand_selectors(fields, model);
     let mut res = json!({});
-    for field in fields {
-        let config = field.get_render_config();
-        res[config.render_title(false)] = json!(field.dump_field(ctx, model, raw, false));
+    for field in fields.iter() {
+        let config = field.get_overridden_render_config(opts);
+        res[config.render_title(false)] = json!(field.dump_field(ctx, model, opts, false));
     }
     res
 }
 
-fn dump_title_line<F>(fields: &[DumpField<F>], sep: &'static str, fixed_width: bool) -> String
+fn dump_title_line<F>(
+    fields: &[DumpField<F>],
+    sep: &'static str,
+    opts: &GeneralOpt,
+    fixed_width: bool,
+) -> String
 where
-    F: FieldId,
+    F: FieldId + ToString,
     F::Queriable: HasRenderConfigForDump,
 {
     let mut line = String::new();
-    for field in fields {
-        line.push_str(&field.get_render_config().render_title(fixed_width));
+    for field in fields.iter() {
+        line.push_str(
+            &field
+                .get_overridden_render_config(opts)
+                .render_title(fixed_width),
+        );
         line.push_str(sep);
     }
     line.push('\n');
     line
 }
 
-pub fn dump_raw<T: HasRenderConfigForDump>(
+pub fn dump_raw<T>(
     fields: &[DumpField<T::FieldId>],
     ctx: &CommonFieldContext,
     model: &T,
     round: usize,
-    repeat_title: Option<usize>,
-    disable_title: bool,
-    raw: bool,
-) -> String {
+    opts: &GeneralOpt,
+) -> String
+where
+    T: HasRenderConfigForDump,
+    T::FieldId: Clone + ToString,
+{
+    let fields = expand_selectors(fields, model);
     let mut res = String::new();
-    let repeat = repeat_title.unwrap_or(0);
-    if !disable_title && (round == 0 || (repeat != 0 && round % repeat == 0)) {
-        res.push_str(&dump_title_line(fields, " ", true));
+    let repeat = opts.repeat_title.unwrap_or(0);
+    if !opts.disable_title && (round == 0 || (repeat != 0 && round % repeat == 0)) {
+        res.push_str(&dump_title_line(&fields, " ", opts, true));
     }
-    for field in fields {
-        res.push_str(&field.dump_field(ctx, model, raw, true));
+    for field in fields.iter() {
+        res.push_str(&field.dump_field(ctx, model, opts, true));
         res.push(' ');
     }
     res.push('\n');
     res
 }
 
-pub fn dump_raw_indented<T: HasRenderConfigForDump + Recursive>(
+pub fn dump_raw_indented<T>(
     fields: &[DumpField<T::FieldId>],
     ctx: &CommonFieldContext,
     model: &T,
     round: usize,
-    repeat_title: Option<usize>,
-    disable_title: bool,
-    raw: bool,
-) -> String {
+    opts: &GeneralOpt,
+) -> String
+where
+    T: HasRenderConfigForDump + Recursive,
+    T::FieldId: Clone + ToString,
+{
+    let fields = expand_selectors(fields, model);
     let mut res = String::new();
-    let repeat = repeat_title.unwrap_or(0);
-    if !disable_title && (round == 0 || (repeat != 0 && round % repeat == 0)) {
-        res.push_str(&dump_title_line(fields, " ", true));
+    let repeat = opts.repeat_title.unwrap_or(0);
+    if !opts.disable_title && (round == 0 || (repeat != 0 && round % repeat == 0)) {
+        res.push_str(&dump_title_line(&fields, " ", opts, true));
     }
-    for field in fields {
-        res.push_str(&field.dump_field_indented(ctx, model, raw, true));
+    for field in fields.iter() {
+        res.push_str(&field.dump_field_indented(ctx, model, opts, true));
         res.push(' ');
     }
     res.push('\n');
     res
 }
 
-pub fn dump_csv<T: HasRenderConfigForDump>(
+pub fn dump_csv<T>(
     fields: &[DumpField<T::FieldId>],
     ctx: &CommonFieldContext,
     model: &T,
     round: usize,
-    disable_title: bool,
-    raw: bool,
-) -> String {
-    let mut res = String::new();
-    if !disable_title && round == 0 {
-        res.push_str(&dump_title_line(fields, ",", false));
-    }
-    for field in fields {
-        res.push_str(&field.dump_field(ctx, model, raw, false));
-        res.push(',');
-    }
-    res.push('\n');
-    res
+    opts: &GeneralOpt,
+) -> String
+where
+    T: HasRenderConfigForDump,
+    T::FieldId: Clone + ToString + FromStr,
+{
+    dump_separated(fields, ctx, model, round, opts, ",")
 }
 
-pub fn dump_tsv<T: HasRenderConfigForDump>(
+pub fn dump_tsv<T>(
     fields: &[DumpField<T::FieldId>],
     ctx: &CommonFieldContext,
     model: &T,
     round: usize,
-    disable_title: bool,
-    raw: bool,
-) -> String {
-    let mut res = String::new();
-    if !disable_title && round == 0 {
-        res.push_str(&dump_title_line(fields, "\t", false));
-    }
-    for field in fields {
-        res.push_str(&field.dump_field(ctx, model, raw, false));
-        res.push('\t');
-    }
-    res.push('\n');
-    res
+    opts: &GeneralOpt,
+) -> String
+where
+    T: HasRenderConfigForDump,
+    T::FieldId: Clone + ToString + FromStr,
+{
+    dump_separated(fields, ctx, model, round, opts, "\t")
 }
 
 pub fn dump_openmetrics<T>(
@@ -269,8 +421,9 @@ pub fn dump_openmetrics<T>(
 where
     T: HasRenderConfigForDump,
     T: Nameable,
-    T::FieldId: ToString,
+    T::FieldId: Clone + ToString,
 {
+    let fields = expand_selectors(fields, model);
     fields
         .iter()
         .filter_map(|field| {
@@ -91,47 +91,25 @@ impl Dumper for Process {
                     Some(OutputFormat::Raw) | None => write!(
                         output,
                         "{}",
-                        print::dump_raw(
-                            &self.fields,
-                            ctx,
-                            spm,
-                            *round,
-                            self.opts.repeat_title,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_raw(&self.fields, ctx, spm, *round, &self.opts)
                     )?,
                     Some(OutputFormat::Csv) => write!(
                         output,
                         "{}",
-                        print::dump_csv(
-                            &self.fields,
-                            ctx,
-                            spm,
-                            *round,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_csv(&self.fields, ctx, spm, *round, &self.opts)
                     )?,
                     Some(OutputFormat::Tsv) => write!(
                         output,
                         "{}",
-                        print::dump_tsv(
-                            &self.fields,
-                            ctx,
-                            spm,
-                            *round,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_tsv(&self.fields, ctx, spm, *round, &self.opts)
                     )?,
                     Some(OutputFormat::KeyVal) => write!(
                         output,
                         "{}",
-                        print::dump_kv(&self.fields, ctx, spm, self.opts.raw)
+                        print::dump_kv(&self.fields, ctx, spm, &self.opts)
                     )?,
                     Some(OutputFormat::Json) => {
-                        let par = print::dump_json(&self.fields, ctx, spm, self.opts.raw);
+                        let par = print::dump_json(&self.fields, ctx, spm, &self.opts);
                         json_output.as_array_mut().unwrap().push(par);
                     }
                     Some(OutputFormat::OpenMetrics) => write!(
@@ -0,0 +1,120 @@
+use model::ResctrlMonGroupModel;
+use model::ResctrlMonGroupModelFieldId;
+
+use super::*;
+
+pub struct Resctrl {
+    opts: GeneralOpt,
+    select: Option<ResctrlMonGroupModelFieldId>,
+    fields: Vec<ResctrlField>,
+}
+
+impl Resctrl {
+    pub fn new(
+        opts: &GeneralOpt,
+        select: Option<ResctrlMonGroupModelFieldId>,
+        fields: Vec<ResctrlField>,
+    ) -> Self {
+        Self {
+            opts: opts.to_owned(),
+            select,
+            fields,
+        }
+    }
+}
+
+impl Dumper for Resctrl {
+    fn dump_model(
+        &self,
+        ctx: &CommonFieldContext,
+        model: &model::Model,
+        output: &mut dyn Write,
+        round: &mut usize,
+        comma_flag: bool,
+    ) -> Result<IterExecResult> {
+        let all_groups = match &model.resctrl {
+            Some(resctrl_model) => resctrl_model.all_groups(),
+            None => Vec::new(),
+        };
+        let mut groups: Vec<&ResctrlMonGroupModel> = all_groups
+            .iter()
+            .filter(
+                |group| match (self.select.as_ref(), self.opts.filter.as_ref()) {
+                    (Some(field_id), Some(filter)) => filter.is_match(
+                        &group
+                            .query(field_id)
+                            .map_or("?".to_owned(), |v| v.to_string()),
+                    ),
+                    _ => true,
+                },
+            )
+            .collect();
+        if groups.is_empty() {
+            return Ok(IterExecResult::Skip);
+        }
+
+        if let Some(field_id) = &self.select {
+            if self.opts.sort {
+                model::sort_queriables(&mut groups, field_id, false);
+            }
+
+            if self.opts.rsort {
+                model::sort_queriables(&mut groups, field_id, true);
+            }
+
+            if (self.opts.sort || self.opts.rsort) && self.opts.top != 0 {
+                groups.truncate(self.opts.top as usize);
+            }
+        }
+
+        let mut json_output = json!([]);
+
+        groups
+            .into_iter()
+            .map(|group| {
+                match self.opts.output_format {
+                    Some(OutputFormat::Raw) | None => write!(
+                        output,
+                        "{}",
+                        print::dump_raw(&self.fields, ctx, group, *round, &self.opts)
+                    )?,
+                    Some(OutputFormat::Csv) => write!(
+                        output,
+                        "{}",
+                        print::dump_csv(&self.fields, ctx, group, *round, &self.opts)
+                    )?,
+                    Some(OutputFormat::Tsv) => write!(
+                        output,
+                        "{}",
+                        print::dump_tsv(&self.fields, ctx, group, *round, &self.opts)
+                    )?,
+                    Some(OutputFormat::KeyVal) => write!(
+                        output,
+                        "{}",
+                        print::dump_kv(&self.fields, ctx, group, &self.opts)
+                    )?,
+                    Some(OutputFormat::Json) => {
+                        let par = print::dump_json(&self.fields, ctx, group, &self.opts);
+                        json_output.as_array_mut().unwrap().push(par);
+                    }
+                    Some(OutputFormat::OpenMetrics) => write!(
+                        output,
+                        "{}",
+                        print::dump_openmetrics(&self.fields, ctx, group)
+                    )?,
+                }
+                *round += 1;
+                Ok(())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        match (self.opts.output_format, comma_flag) {
+            (Some(OutputFormat::Json), true) => write!(output, ",{}", json_output)?,
+            (Some(OutputFormat::Json), false) => write!(output, "{}", json_output)?,
+            (Some(OutputFormat::OpenMetrics), _) => (),
+            _ => writeln!(output)?,
+        };
+
+        Ok(IterExecResult::Success)
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use model::SingleSlabModelFieldId;
+
+use super::*;
+
+pub struct Slab {
+    opts: GeneralOpt,
+    select: Option<SingleSlabModelFieldId>,
+    fields: Vec<SlabField>,
+}
+
+impl Slab {
+    pub fn new(
+        opts: &GeneralOpt,
+        select: Option<SingleSlabModelFieldId>,
+        fields: Vec<SlabField>,
+    ) -> Self {
+        Self {
+            opts: opts.to_owned(),
+            select,
+            fields,
+        }
+    }
+}
+
+impl Dumper for Slab {
+    fn dump_model(
+        &self,
+        ctx: &CommonFieldContext,
+        model: &model::Model,
+        output: &mut dyn Write,
+        round: &mut usize,
+        comma_flag: bool,
+    ) -> Result<IterExecResult> {
+        let mut slabs: Vec<_> = model
+            .system
+            .slab
+            .values()
+            .filter(
+                |slab| match (self.select.as_ref(), self.opts.filter.as_ref()) {
+                    (Some(field_id), Some(filter)) => filter.is_match(
+                        &slab
+                            .query(field_id)
+                            .map_or("?".to_owned(), |v| v.to_string()),
+                    ),
+                    _ => true,
+                },
+            )
+            .collect();
+
+        if let Some(field_id) = &self.select {
+            if self.opts.sort {
+                model::sort_queriables(&mut slabs, field_id, false);
+            }
+
+            if self.opts.rsort {
+                model::sort_queriables(&mut slabs, field_id, true);
+            }
+
+            if (self.opts.sort || self.opts.rsort) && self.opts.top != 0 {
+                slabs.truncate(self.opts.top as usize);
+            }
+        }
+        let mut json_output = json!([]);
+
+        for slab in slabs {
+            match self.opts.output_format {
+                Some(OutputFormat::Raw) | None => write!(
+                    output,
+                    "{}",
+                    print::dump_raw(&self.fields, ctx, slab, *round, &self.opts)
+                )?,
+                Some(OutputFormat::Csv) => write!(
+                    output,
+                    "{}",
+                    print::dump_csv(&self.fields, ctx, slab, *round, &self.opts)
+                )?,
+                Some(OutputFormat::KeyVal) => write!(
+                    output,
+                    "{}",
+                    print::dump_kv(&self.fields, ctx, slab, &self.opts)
+                )?,
+                Some(OutputFormat::Json) => {
+                    let par = print::dump_json(&self.fields, ctx, slab, &self.opts);
+                    json_output.as_array_mut().unwrap().push(par);
+                }
+                Some(OutputFormat::Tsv) => write!(
+                    output,
+                    "{}",
+                    print::dump_tsv(&self.fields, ctx, slab, *round, &self.opts)
+                )?,
+                Some(OutputFormat::OpenMetrics) => write!(
+                    output,
+                    "{}",
+                    print::dump_openmetrics(&self.fields, ctx, slab)
+                )?,
+            }
+            *round += 1;
+        }
+
+        match (self.opts.output_format, comma_flag) {
+            (Some(OutputFormat::Json), true) => write!(output, ",{}", json_output)?,
+            (Some(OutputFormat::Json), false) => write!(output, "{}", json_output)?,
+            (Some(OutputFormat::OpenMetrics), _) => (),
+            _ => writeln!(output)?,
+        };
+
+        Ok(IterExecResult::Success)
+    }
+}
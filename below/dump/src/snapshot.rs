@@ -0,0 +1,181 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Opening snapshot files generated by `below snapshot`, either local or
+//! fetched over HTTP(S).
+//!
+//! A downloaded snapshot is verified against the checksum given in the URL
+//! fragment, e.g. `https://host/snapshot.tar.gz#sha256=<hex digest>`, before
+//! it is unpacked. The fragment is never sent to the server.
+
+use std::fs;
+use std::fs::File;
+use std::io::Read;
+use std::io::Seek;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use flate2::read::GzDecoder;
+use indicatif::ProgressBar;
+use indicatif::ProgressStyle;
+use sha2::Digest;
+use sha2::Sha256;
+use slog::warn;
+use tar::Archive;
+use tempfile::TempDir;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Whether the snapshot argument is a URL rather than a local path
+pub fn is_remote_snapshot(snapshot: &str) -> bool {
+    snapshot.starts_with("http://") || snapshot.starts_with("https://")
+}
+
+/// Split the expected sha256 digest off the fragment of a snapshot URL
+pub fn split_checksum(url: &str) -> Result<(&str, Option<String>)> {
+    let (url, fragment) = match url.split_once('#') {
+        Some((url, fragment)) => (url, fragment),
+        None => return Ok((url, None)),
+    };
+    let digest = match fragment.strip_prefix("sha256=") {
+        Some(digest) => digest,
+        None => bail!(
+            "Unsupported snapshot URL fragment \"{}\", expected sha256=<hex digest>",
+            fragment
+        ),
+    };
+    if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("Invalid sha256 digest \"{}\" in snapshot URL", digest);
+    }
+    Ok((url, Some(digest.to_ascii_lowercase())))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Download the snapshot at `url` into a temporary file, showing progress on
+/// stderr, and verify its checksum if the URL has one.
+fn download(logger: &slog::Logger, url: &str) -> Result<File> {
+    let (url, expected) = split_checksum(url)?;
+    if expected.is_none() {
+        warn!(
+            logger,
+            "No sha256 digest in snapshot URL, the download will not be verified. Append #sha256=<hex digest> to verify it."
+        );
+    }
+    let response = ureq::get(url)
+        .call()
+        .with_context(|| format!("Failed to fetch snapshot from {}", url))?;
+    let pb = match response
+        .header("Content-Length")
+        .and_then(|len| len.parse().ok())
+    {
+        Some(len) => {
+            let pb = ProgressBar::new(len);
+            pb.set_style(
+                ProgressStyle::with_template(
+                    "{msg} [{bar:40}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+                )
+                .expect("Invalid progress bar template"),
+            );
+            pb
+        }
+        None => ProgressBar::new_spinner(),
+    };
+    pb.set_message(format!("Downloading {}", url));
+
+    let mut file = tempfile::tempfile().context("Failed to create file for snapshot download")?;
+    let mut reader = pb.wrap_read(response.into_reader());
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 1 << 16];
+    loop {
+        let n = reader
+            .read(&mut buf)
+            .with_context(|| format!("Failed to download snapshot from {}", url))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        file.write_all(&buf[..n])
+            .context("Failed to write downloaded snapshot")?;
+    }
+    pb.finish_and_clear();
+
+    if let Some(expected) = expected {
+        let actual = to_hex(&hasher.finalize());
+        if actual != expected {
+            bail!(
+                "Checksum mismatch for snapshot {}: expected sha256 {}, got {}",
+                url,
+                expected,
+                actual
+            );
+        }
+    }
+    file.rewind()?;
+    Ok(file)
+}
+
+/// Store of a snapshot unpacked into a temporary directory, which is removed
+/// on drop
+#[derive(Debug)]
+pub struct Snapshot {
+    _dir: TempDir,
+    store_dir: PathBuf,
+}
+
+impl Snapshot {
+    pub fn store_dir(&self) -> &Path {
+        &self.store_dir
+    }
+}
+
+/// Unpack a snapshot, optionally gzip compressed, into a temporary directory
+fn unpack(mut file: File) -> Result<Snapshot> {
+    let mut magic = [0; 2];
+    let compressed = file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC;
+    file.rewind()?;
+
+    let dir = TempDir::with_prefix("snapshot_replay.")?;
+    if compressed {
+        Archive::new(GzDecoder::new(file)).unpack(dir.path())?;
+    } else {
+        Archive::new(file).unpack(dir.path())?;
+    }
+    // Find and append the name of the original snapshot directory
+    let mut store_dir = dir.path().to_owned();
+    for path in fs::read_dir(dir.path())? {
+        store_dir.push(path?.file_name());
+    }
+    Ok(Snapshot {
+        _dir: dir,
+        store_dir,
+    })
+}
+
+/// Open the snapshot at a local path or an http(s) URL and unpack it. It is
+/// removed once the returned `Snapshot` is dropped.
+pub fn open_snapshot(logger: &slog::Logger, snapshot: &str) -> Result<Snapshot> {
+    let file = if is_remote_snapshot(snapshot) {
+        download(logger, snapshot)?
+    } else {
+        File::open(snapshot).context("Failed to open snapshot file")?
+    };
+    unpack(file)
+}
@@ -52,53 +52,63 @@ impl Dumper for System {
                     )));
                 }
             }
+            // Likewise add per-cpu softirq and sched fields if any of their
+            // fields is selected.
+            let softirq_selected = self.fields.iter().any(|field| {
+                matches!(
+                    field,
+                    DumpField::FieldId(model::SystemModelFieldId::Softirq(_))
+                )
+            });
+            if softirq_selected {
+                for key in model.system.softirqs.keys() {
+                    for subquery_id in enum_iterator::all::<model::SingleSoftirqModelFieldId>() {
+                        fields.push(DumpField::FieldId(model::SystemModelFieldId::Softirqs(
+                            model::BTreeMapFieldId::new(Some(*key), subquery_id),
+                        )));
+                    }
+                }
+            }
+            let sched_selected = self.fields.iter().any(|field| {
+                matches!(
+                    field,
+                    DumpField::FieldId(model::SystemModelFieldId::Sched(_))
+                )
+            });
+            if sched_selected {
+                for key in model.system.scheds.keys() {
+                    for subquery_id in enum_iterator::all::<model::SingleSchedModelFieldId>() {
+                        fields.push(DumpField::FieldId(model::SystemModelFieldId::Scheds(
+                            model::BTreeMapFieldId::new(Some(*key), subquery_id),
+                        )));
+                    }
+                }
+            }
         }
 
         match self.opts.output_format {
             Some(OutputFormat::Raw) | None => write!(
                 output,
                 "{}",
-                print::dump_raw(
-                    &fields,
-                    ctx,
-                    &model.system,
-                    *round,
-                    self.opts.repeat_title,
-                    self.opts.disable_title,
-                    self.opts.raw
-                )
+                print::dump_raw(&fields, ctx, &model.system, *round, &self.opts)
             )?,
             Some(OutputFormat::Csv) => write!(
                 output,
                 "{}",
-                print::dump_csv(
-                    &fields,
-                    ctx,
-                    &model.system,
-                    *round,
-                    self.opts.disable_title,
-                    self.opts.raw
-                )
+                print::dump_csv(&fields, ctx, &model.system, *round, &self.opts)
             )?,
             Some(OutputFormat::Tsv) => write!(
                 output,
                 "{}",
-                print::dump_tsv(
-                    &fields,
-                    ctx,
-                    &model.system,
-                    *round,
-                    self.opts.disable_title,
-                    self.opts.raw
-                )
+                print::dump_tsv(&fields, ctx, &model.system, *round, &self.opts)
             )?,
             Some(OutputFormat::KeyVal) => write!(
                 output,
                 "{}",
-                print::dump_kv(&fields, ctx, &model.system, self.opts.raw)
+                print::dump_kv(&fields, ctx, &model.system, &self.opts)
             )?,
             Some(OutputFormat::Json) => {
-                let par = print::dump_json(&fields, ctx, &model.system, self.opts.raw);
+                let par = print::dump_json(&fields, ctx, &model.system, &self.opts);
                 if comma_flag {
                     write!(output, ",{}", par.to_string())?;
                 } else {
@@ -1,16 +1,23 @@
 use model::SingleTcModel;
+use model::SingleTcModelFieldId;
 
 use super::*;
 
 pub struct Tc {
     opts: GeneralOpt,
+    select: Option<SingleTcModelFieldId>,
     fields: Vec<TcField>,
 }
 
 impl Tc {
-    pub fn new(opts: &GeneralOpt, fields: Vec<TcField>) -> Self {
+    pub fn new(
+        opts: &GeneralOpt,
+        select: Option<SingleTcModelFieldId>,
+        fields: Vec<TcField>,
+    ) -> Self {
         Self {
             opts: opts.to_owned(),
+            select,
             fields,
         }
     }
@@ -25,14 +32,39 @@ impl Dumper for Tc {
         round: &mut usize,
         comma_flag: bool,
     ) -> Result<IterExecResult> {
-        let tcs: Vec<&SingleTcModel> = match &model.tc {
-            Some(tc_model) => tc_model.tc.iter().collect(),
+        let mut tcs: Vec<&SingleTcModel> = match &model.tc {
+            Some(tc_model) => tc_model
+                .tc
+                .iter()
+                .filter(
+                    |tc| match (self.select.as_ref(), self.opts.filter.as_ref()) {
+                        (Some(field_id), Some(filter)) => filter.is_match(
+                            &tc.query(field_id).map_or("?".to_owned(), |v| v.to_string()),
+                        ),
+                        _ => true,
+                    },
+                )
+                .collect(),
             None => Vec::new(),
         };
         if tcs.is_empty() {
             return Ok(IterExecResult::Skip);
         }
 
+        if let Some(field_id) = &self.select {
+            if self.opts.sort {
+                model::sort_queriables(&mut tcs, field_id, false);
+            }
+
+            if self.opts.rsort {
+                model::sort_queriables(&mut tcs, field_id, true);
+            }
+
+            if (self.opts.sort || self.opts.rsort) && self.opts.top != 0 {
+                tcs.truncate(self.opts.top as usize);
+            }
+        }
+
         let mut json_output = json!([]);
 
         tcs.into_iter()
@@ -41,47 +73,25 @@ impl Dumper for Tc {
                     Some(OutputFormat::Raw) | None => write!(
                         output,
                         "{}",
-                        print::dump_raw(
-                            &self.fields,
-                            ctx,
-                            tc,
-                            *round,
-                            self.opts.repeat_title,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_raw(&self.fields, ctx, tc, *round, &self.opts)
                     )?,
                     Some(OutputFormat::Csv) => write!(
                         output,
                         "{}",
-                        print::dump_csv(
-                            &self.fields,
-                            ctx,
-                            tc,
-                            *round,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_csv(&self.fields, ctx, tc, *round, &self.opts)
                     )?,
                     Some(OutputFormat::Tsv) => write!(
                         output,
                         "{}",
-                        print::dump_tsv(
-                            &self.fields,
-                            ctx,
-                            tc,
-                            *round,
-                            self.opts.disable_title,
-                            self.opts.raw
-                        )
+                        print::dump_tsv(&self.fields, ctx, tc, *round, &self.opts)
                     )?,
                     Some(OutputFormat::KeyVal) => write!(
                         output,
                         "{}",
-                        print::dump_kv(&self.fields, ctx, tc, self.opts.raw)
+                        print::dump_kv(&self.fields, ctx, tc, &self.opts)
                     )?,
                     Some(OutputFormat::Json) => {
-                        let par = print::dump_json(&self.fields, ctx, tc, self.opts.raw);
+                        let par = print::dump_json(&self.fields, ctx, tc, &self.opts);
                         json_output.as_array_mut().unwrap().push(par);
                     }
                     Some(OutputFormat::OpenMetrics) => {
@@ -15,8 +15,13 @@
 use std::collections::BTreeMap;
 use std::time::Duration;
 
+use below_testutil::CgroupFixture;
+use below_testutil::CgroupfsBuilder;
+use below_testutil::ProcessFixture;
+use below_testutil::ProcfsBuilder;
 use command::expand_fields;
 use command::GeneralOpt;
+use command::NetworkOptionField;
 use command::OutputFormat;
 use common::logutil::get_logger;
 use model::Collector;
@@ -56,6 +61,10 @@ fn test_dump_sys_content() {
     let ctx = CommonFieldContext {
         timestamp: 0,
         hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
     };
     system_dumper
         .dump_model(&ctx, &model, &mut system_content, &mut round, false)
@@ -68,7 +77,7 @@ fn test_dump_sys_content() {
 
     for dump_field in fields.iter() {
         match dump_field {
-            DumpField::Common(_) => continue,
+            DumpField::Common(_) | DumpField::Selector(_) => continue,
             DumpField::FieldId(field_id) => {
                 let rc = model::SystemModel::get_render_config_for_dump(field_id);
                 assert_eq!(
@@ -100,7 +109,7 @@ fn test_dump_sys_titles() {
             }),
         )
         .filter_map(|dump_field| match dump_field {
-            DumpField::Common(_) => None,
+            DumpField::Common(_) | DumpField::Selector(_) => None,
             DumpField::FieldId(field_id) => {
                 let rc = model::SystemModel::get_render_config_for_dump(&field_id);
                 Some(rc.render_title(false))
@@ -120,6 +129,11 @@ fn test_dump_sys_titles() {
         "Stolen",
         "Guest",
         "Guest Nice",
+        "Freq",
+        "Max Freq",
+        "Core Throttles",
+        "Core Throttled",
+        "Pkg Throttles",
         "Total",
         "Free",
         "Available",
@@ -167,13 +181,21 @@ fn test_dump_sys_titles() {
         "Pgscan Direct",
         "OOM Kills",
         "Kernel Version",
+        "Boot ID",
         "OS Release",
+        "IRQ Pressure",
         "Total Interrupts",
         "Context Switches",
         "Boot Time Epoch",
         "Total Procs",
         "Running Procs",
         "Blocked Procs",
+        "Load 1m",
+        "Load 5m",
+        "Load 15m",
+        "Runnable Tasks",
+        "Total Tasks",
+        "Uptime(sec)",
         "CPU 31 Idx",
         "CPU 31 Usage",
         "CPU 31 User",
@@ -186,8 +208,89 @@ fn test_dump_sys_titles() {
         "CPU 31 Stolen",
         "CPU 31 Guest",
         "CPU 31 Guest Nice",
+        "CPU 31 Freq",
+        "CPU 31 Max Freq",
+        "CPU 31 Core Throttles",
+        "CPU 31 Core Throttled",
+        "CPU 31 Pkg Throttles",
+    ];
+    assert_eq!(titles, expected_titles);
+}
+
+#[test]
+fn test_dump_sys_softirq_titles() {
+    let titles = expand_fields(
+        &[command::SystemOptionField::Agg(
+            command::SystemAggField::Softirq,
+        )],
+        true,
+    )
+    .into_iter()
+    .filter_map(|dump_field| match dump_field {
+        DumpField::Common(_) | DumpField::Selector(_) => None,
+        DumpField::FieldId(field_id) => {
+            let rc = model::SystemModel::get_render_config_for_dump(&field_id);
+            Some(rc.render_title(false))
+        }
+    })
+    .collect::<Vec<_>>();
+    let expected_titles = vec![
+        "Softirq HI",
+        "Softirq Timer",
+        "Softirq Net TX",
+        "Softirq Net RX",
+        "Softirq Block",
+        "Softirq IRQ Poll",
+        "Softirq Tasklet",
+        "Softirq Sched",
+        "Softirq HRTimer",
+        "Softirq RCU",
+    ];
+    assert_eq!(titles, expected_titles);
+
+    let per_cpu = model::SystemModelFieldId::Softirqs(model::BTreeMapFieldId::new(
+        Some(3),
+        model::SingleSoftirqModelFieldId::NetRxPerSec,
+    ));
+    assert_eq!(
+        model::SystemModel::get_render_config_for_dump(&per_cpu).render_title(false),
+        "CPU 3 Softirq Net RX"
+    );
+}
+
+#[test]
+fn test_dump_sys_sched_titles() {
+    let titles = expand_fields(
+        &[command::SystemOptionField::Agg(
+            command::SystemAggField::Sched,
+        )],
+        true,
+    )
+    .into_iter()
+    .filter_map(|dump_field| match dump_field {
+        DumpField::Common(_) | DumpField::Selector(_) => None,
+        DumpField::FieldId(field_id) => {
+            let rc = model::SystemModel::get_render_config_for_dump(&field_id);
+            Some(rc.render_title(false))
+        }
+    })
+    .collect::<Vec<_>>();
+    let expected_titles = vec![
+        "Sched Run",
+        "Sched Wait",
+        "Sched Timeslices",
+        "Sched Avg Wait",
     ];
     assert_eq!(titles, expected_titles);
+
+    let per_cpu = model::SystemModelFieldId::Scheds(model::BTreeMapFieldId::new(
+        Some(1),
+        model::SingleSchedModelFieldId::AvgWaitUsec,
+    ));
+    assert_eq!(
+        model::SystemModel::get_render_config_for_dump(&per_cpu).render_title(false),
+        "CPU 1 Sched Avg Wait"
+    );
 }
 
 #[test]
@@ -214,6 +317,10 @@ fn test_dump_process_content() {
     let ctx = CommonFieldContext {
         timestamp: 0,
         hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
     };
     process_dumper
         .dump_model(&ctx, &model, &mut process_content, &mut round, false)
@@ -236,7 +343,7 @@ fn test_dump_process_content() {
 
         for dump_field in fields.iter() {
             match dump_field {
-                DumpField::Common(_) => continue,
+                DumpField::Common(_) | DumpField::Selector(_) => continue,
                 DumpField::FieldId(field_id) => {
                     let rc = model::SingleProcessModel::get_render_config_for_dump(field_id);
                     assert_eq!(
@@ -266,7 +373,7 @@ fn test_dump_proc_titles() {
     let titles = expand_fields(command::DEFAULT_PROCESS_FIELDS, true)
         .iter()
         .filter_map(|dump_field| match dump_field {
-            DumpField::Common(_) => None,
+            DumpField::Common(_) | DumpField::Selector(_) => None,
             DumpField::FieldId(field_id) => {
                 let rc = model::SingleProcessModel::get_render_config_for_dump(field_id);
                 Some(rc.render_title(false))
@@ -282,6 +389,7 @@ fn test_dump_proc_titles() {
         "User CPU",
         "Sys CPU",
         "Threads",
+        "CPU Time",
         "Minflt",
         "Majflt",
         "RSS",
@@ -293,11 +401,18 @@ fn test_dump_proc_titles() {
         "Shmem",
         "PTE",
         "Swap",
+        "Swap Growth",
         "Huge TLB",
+        "PSS",
+        "Shared",
+        "Private",
+        "Anon THP",
+        "Swap PSS",
         "Reads",
         "Writes",
         "RW",
         "Uptime(sec)",
+        "Start Time",
         "Cgroup",
         "Cmdline",
         "Exe Path",
@@ -338,6 +453,10 @@ fn test_dump_proc_select() {
     let ctx = CommonFieldContext {
         timestamp: 0,
         hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
     };
     process_dumper
         .dump_model(&ctx, &model, &mut process_content, &mut round, false)
@@ -408,7 +527,7 @@ fn test_dump_proc_select() {
 fn traverse_cgroup_tree(model: &model::CgroupModel, jval: &Value) {
     for dump_field in expand_fields(command::DEFAULT_CGROUP_FIELDS, true) {
         match dump_field {
-            DumpField::Common(_) => continue,
+            DumpField::Common(_) | DumpField::Selector(_) => continue,
             DumpField::FieldId(field_id) => {
                 let rc = model::SingleCgroupModel::get_render_config_for_dump(&field_id);
                 assert_eq!(
@@ -456,6 +575,10 @@ fn test_dump_cgroup_content() {
     let ctx = CommonFieldContext {
         timestamp: 0,
         hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
     };
     cgroup_dumper
         .dump_model(&ctx, &model, &mut cgroup_content, &mut round, false)
@@ -468,12 +591,191 @@ fn test_dump_cgroup_content() {
     traverse_cgroup_tree(&model.cgroup, &jval);
 }
 
+/// Sample of the host described by the fixtures, read as the collector
+/// reads a live host
+fn collect_fixture_sample(procfs: &ProcfsBuilder, cgroupfs: &CgroupfsBuilder) -> model::Sample {
+    let procfs = procfs.build().expect("Failed to write procfs");
+    let cgroupfs = cgroupfs.build().expect("Failed to write cgroupfs");
+    let processes = procfs::ProcReader::new_with_custom_procfs(procfs.path().to_path_buf())
+        .read_all_pids()
+        .expect("Failed to read pids");
+    let reader = cgroupfs::CgroupReader::new_unchecked(cgroupfs.path().to_path_buf())
+        .expect("Failed to open cgroup root");
+    let cgroup = model::collect_cgroup_sample(&reader, true, &get_logger(), &None)
+        .expect("Failed to collect cgroup sample");
+    model::Sample {
+        cgroup,
+        processes,
+        system: model::SystemSample {
+            hostname: "fixture".to_owned(),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+// Test collection of a synthetic host through dump output, without root
+fn test_dump_fixture_content() {
+    let procfs = ProcfsBuilder::synthetic(200, 20);
+    let cgroupfs = CgroupfsBuilder::synthetic(20);
+    let last = collect_fixture_sample(
+        &procfs.clone().process(
+            ProcessFixture::new(300)
+                .comm("a) (b")
+                .cgroup("/slice0.slice/unit0.service"),
+        ),
+        &cgroupfs,
+    );
+    let sample = collect_fixture_sample(
+        &procfs.process(
+            ProcessFixture::new(300)
+                .comm("a) (b")
+                .cgroup("/slice0.slice/unit0.service")
+                .cpu_ticks(250, 50),
+        ),
+        &cgroupfs.cgroup(
+            "slice0.slice/unit0.service",
+            CgroupFixture::new().cpu_usage_usec(1_002_000, 1_001_500, 500),
+        ),
+    );
+    let model = model::Model::new(
+        std::time::SystemTime::now(),
+        &sample,
+        Some((&last, Duration::from_secs(1))),
+    );
+
+    let mut opts: GeneralOpt = Default::default();
+    opts.output_format = Some(OutputFormat::Json);
+    let ctx = CommonFieldContext {
+        timestamp: 0,
+        hostname: "fixture".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
+    };
+
+    let fields = command::expand_fields(command::DEFAULT_PROCESS_FIELDS, true);
+    let process_dumper = process::Process::new(&opts, None, fields);
+    let mut process_content: Vec<u8> = Vec::new();
+    process_dumper
+        .dump_model(&ctx, &model, &mut process_content, &mut 0, false)
+        .expect("Failed to dump process model");
+    let jval: Value =
+        serde_json::from_slice(&process_content).expect("Fail parse json of process dump");
+    let rows = jval.as_array().expect("Process dump is not an array");
+    assert_eq!(rows.len(), 201);
+    let row = rows
+        .iter()
+        .find(|row| row["Pid"] == "300")
+        .expect("Process 300 not dumped");
+    assert_eq!(row["Comm"], "a) (b");
+    assert_eq!(row["CPU"], "100.00%");
+
+    let fields = command::expand_fields(command::DEFAULT_CGROUP_FIELDS, true);
+    let cgroup_dumper = cgroup::Cgroup::new(&opts, None, fields);
+    let mut cgroup_content: Vec<u8> = Vec::new();
+    cgroup_dumper
+        .dump_model(&ctx, &model, &mut cgroup_content, &mut 0, false)
+        .expect("Failed to dump cgroup model");
+    let jval: Value =
+        serde_json::from_slice(&cgroup_content).expect("Fail parse json of cgroup dump");
+    traverse_cgroup_tree(&model.cgroup, &jval);
+}
+
+#[test]
+fn test_dump_cgroup_subtree() {
+    fn cgroup(name: &str, depth: u32, children: Vec<model::CgroupModel>) -> model::CgroupModel {
+        model::CgroupModel {
+            data: model::SingleCgroupModel {
+                name: name.to_owned(),
+                depth,
+                ..Default::default()
+            },
+            count: children.iter().map(|child| child.count).sum::<u32>() + 1,
+            children: children.into_iter().collect(),
+            recreate_flag: false,
+        }
+    }
+    let mut model = model::Model {
+        time_elapsed: Duration::from_secs(5),
+        timestamp: SystemTime::now(),
+        system: model::SystemModel::default(),
+        cgroup: cgroup(
+            "<root>",
+            0,
+            vec![
+                cgroup(
+                    "system.slice",
+                    1,
+                    vec![cgroup(
+                        "foo.service",
+                        2,
+                        vec![cgroup("bar.scope", 3, vec![])],
+                    )],
+                ),
+                cgroup("user.slice", 1, vec![]),
+            ],
+        ),
+        process: model::ProcessModel::default(),
+        network: model::NetworkModel::default(),
+        gpu: None,
+        resctrl: None,
+        tc: None,
+        omitted_sections: Default::default(),
+        gaps: 0,
+        cmdline_redacted: false,
+        discontinuity: false,
+        rebooted: false,
+        unavailable_sources: Default::default(),
+        oom_kills: Vec::new(),
+    };
+
+    let mut opts: GeneralOpt = Default::default();
+    opts.output_format = Some(OutputFormat::Json);
+    let fields = vec![DumpField::FieldId(model::SingleCgroupModelFieldId::Name)];
+    let cgroup_dumper =
+        cgroup::Cgroup::new(&opts, None, fields).with_subtree(Some("/system.slice/"), Some(1));
+    let ctx = CommonFieldContext {
+        timestamp: 0,
+        hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
+    };
+    let mut cgroup_content: Vec<u8> = Vec::new();
+    let mut round = 0;
+    let result = cgroup_dumper
+        .dump_model(&ctx, &model, &mut cgroup_content, &mut round, false)
+        .expect("Failed to dump cgroup model");
+    assert!(result == tmain::IterExecResult::Success);
+    assert_eq!(round, 2);
+    let jval: Value =
+        serde_json::from_slice(&cgroup_content).expect("Fail parse json of cgroup dump");
+    assert_eq!(
+        jval,
+        json!({
+            "Name": "system.slice",
+            "children": [{"Name": "foo.service", "children": []}]
+        })
+    );
+
+    // Time slices without the subtree are skipped
+    model.cgroup = cgroup("<root>", 0, vec![]);
+    let result = cgroup_dumper
+        .dump_model(&ctx, &model, &mut Vec::new(), &mut round, false)
+        .expect("Failed to dump cgroup model");
+    assert!(result == tmain::IterExecResult::Skip);
+}
+
 #[test]
 fn test_dump_cgroup_titles() {
     let titles = expand_fields(command::DEFAULT_CGROUP_FIELDS, true)
         .iter()
         .filter_map(|dump_field| match dump_field {
-            DumpField::Common(_) => None,
+            DumpField::Common(_) | DumpField::Selector(_) => None,
             DumpField::FieldId(field_id) => {
                 let rc = model::SingleCgroupModel::get_render_config_for_dump(field_id);
                 Some(rc.render_title(false))
@@ -503,7 +805,10 @@ fn test_dump_cgroup_titles() {
         "File Mapped",
         "File Dirty",
         "File WB",
+        "Dirty Growth",
         "Anon THP",
+        "File THP",
+        "Shmem THP",
         "Inactive Anon",
         "Active Anon",
         "Inactive File",
@@ -529,6 +834,7 @@ fn test_dump_cgroup_titles() {
         "Pglazyfreed",
         "THP Fault Alloc",
         "THP Collapse Alloc",
+        "HugeTLB",
         "Events Low",
         "Events High",
         "Events Max",
@@ -539,6 +845,10 @@ fn test_dump_cgroup_titles() {
         "Events Local Max",
         "Events Local OOM",
         "Events Local Kill",
+        "Events Low Rate",
+        "Events High Rate",
+        "Events Max Rate",
+        "New OOM Kills",
         "RBytes",
         "WBytes",
         "R I/O",
@@ -550,12 +860,16 @@ fn test_dump_cgroup_titles() {
         "Cost Wait",
         "Cost Indebt",
         "Cost Indelay",
+        "Lat Target",
+        "Lat Avg",
         "CPU Some Pressure",
         "CPU Pressure",
         "I/O Some Pressure",
         "I/O Pressure",
         "Mem Some Pressure",
         "Mem Pressure",
+        "IRQ Pressure",
+        "Mem Trigger Events",
     ];
     assert_eq!(titles, expected_titles);
 }
@@ -584,6 +898,10 @@ fn test_dump_iface_content() {
     let ctx = CommonFieldContext {
         timestamp: 0,
         hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
     };
     iface_dumper
         .dump_model(&ctx, &model, &mut iface_content, &mut round, false)
@@ -606,7 +924,7 @@ fn test_dump_iface_content() {
 
         for dump_field in fields.iter() {
             match dump_field {
-                DumpField::Common(_) => continue,
+                DumpField::Common(_) | DumpField::Selector(_) => continue,
                 DumpField::FieldId(field_id) => {
                     let rc = model::SingleNetModel::get_render_config_for_dump(field_id);
                     assert_eq!(
@@ -631,12 +949,83 @@ fn test_dump_iface_content() {
     }
 }
 
+#[test]
+// Root qdisc drops are joined into the interfaces read from an indexed store
+fn test_dump_iface_qdisc_drops_indexed() {
+    let dir = TempDir::with_prefix("below_dump_test.").expect("tempdir failed");
+    let ts = 1_700_000_000;
+    {
+        let mut writer = store::StoreWriter::new(
+            get_logger(),
+            &dir,
+            store::CompressionMode::Zstd,
+            store::Format::Indexed,
+        )
+        .expect("Failed to create store");
+        for (i, drops) in [0, 50].into_iter().enumerate() {
+            let mut frame = store::DataFrame::default();
+            frame.sample.netstats.interfaces =
+                Some(BTreeMap::from([("eth0".to_owned(), Default::default())]));
+            let mut qdisc = ::tc::TcStat {
+                if_name: "eth0".to_owned(),
+                parent: u32::MAX,
+                ..Default::default()
+            };
+            qdisc.stats.drops = Some(drops);
+            frame.sample.tc = Some(vec![qdisc]);
+            writer
+                .put(
+                    std::time::UNIX_EPOCH + Duration::from_secs(ts + 5 * i as u64),
+                    &frame,
+                )
+                .expect("Failed to store data");
+        }
+    }
+
+    let output = dir.path().join("iface.json");
+    let mut opts: GeneralOpt = Default::default();
+    opts.begin = ts.to_string();
+    opts.end = Some((ts + 5).to_string());
+    opts.everything = true;
+    opts.output_format = Some(OutputFormat::Json);
+    opts.output = Some(output.to_string_lossy().into_owned());
+    let (_errs_tx, errs) = std::sync::mpsc::channel();
+    run(
+        get_logger(),
+        errs,
+        dir.path().to_path_buf(),
+        None,
+        None,
+        None,
+        DumpCommand::Iface {
+            fields: None,
+            opts,
+            select: None,
+            pattern: None,
+        },
+    )
+    .expect("Failed to dump iface");
+
+    let content = std::fs::read(&output).expect("Failed to read dump output");
+    let jval: Value = serde_json::from_slice(&content).expect("Fail parse json of iface dump");
+    // Drops are a rate, so only known from the second sample on
+    let row = jval
+        .as_array()
+        .and_then(|samples| samples.last())
+        .and_then(|rows| rows.as_array())
+        .expect("Iface dump is not an array of samples")
+        .iter()
+        .find(|row| row["Interface"] == "eth0")
+        .expect("eth0 not dumped");
+    assert_eq!(row["Qdisc Drops"], "10/s");
+}
+
 #[test]
 fn test_dump_iface_titles() {
     let titles = expand_fields(command::DEFAULT_IFACE_FIELDS, true)
         .iter()
         .filter_map(|dump_field| match dump_field {
-            DumpField::Common(_) => None,
+            DumpField::Common(_) | DumpField::Selector(_) => None,
             DumpField::FieldId(field_id) => {
                 let rc = model::SingleNetModel::get_render_config_for_dump(field_id);
                 Some(rc.render_title(false))
@@ -647,6 +1036,11 @@ fn test_dump_iface_titles() {
         "Collisions",
         "Multicast",
         "Interface",
+        "State",
+        "Speed",
+        "Duplex",
+        "Carrier Changes",
+        "Link Flaps",
         "RX Bytes/s",
         "TX Bytes/s",
         "I/O Bytes/s",
@@ -669,6 +1063,7 @@ fn test_dump_iface_titles() {
         "TX Carrier Errors",
         "TX Compressed",
         "TX Dropped",
+        "Qdisc Drops",
         "TX Errors",
         "TX Fifo Errors",
         "TX Heartbeat Errors",
@@ -704,6 +1099,10 @@ fn test_dump_network_content() {
     let ctx = CommonFieldContext {
         timestamp: 0,
         hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
     };
     network_dumper
         .dump_model(&ctx, &model, &mut network_content, &mut round, false)
@@ -716,7 +1115,7 @@ fn test_dump_network_content() {
 
     for dump_field in fields.iter() {
         match dump_field {
-            DumpField::Common(_) => continue,
+            DumpField::Common(_) | DumpField::Selector(_) => continue,
             DumpField::FieldId(field_id) => {
                 let rc = model::NetworkModel::get_render_config_for_dump(field_id);
                 assert_eq!(
@@ -736,12 +1135,72 @@ fn test_dump_network_content() {
     }
 }
 
+#[test]
+fn test_dump_network_selector() {
+    let model = model::get_sample_model();
+    let fields = expand_fields(
+        &[
+            NetworkOptionField::from_str("interfaces.*.rx_bytes_per_sec")
+                .expect("Failed to parse selector"),
+        ],
+        false,
+    );
+    assert_eq!(
+        fields,
+        vec![DumpField::Selector(
+            "interfaces.*.rx_bytes_per_sec".to_owned()
+        )]
+    );
+    let ctx = CommonFieldContext {
+        timestamp: 0,
+        hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
+    };
+    let opts: GeneralOpt = Default::default();
+    let csv = print::dump_csv(&fields, &ctx, &model.network, 0, &opts);
+    let expected = ["eth0", "lo"]
+        .iter()
+        .map(|iface| {
+            let field_id = model::NetworkModelFieldId::from_str(&format!(
+                "interfaces.{}.rx_bytes_per_sec",
+                iface
+            ))
+            .unwrap();
+            let rc = model::NetworkModel::get_render_config_for_dump(&field_id);
+            (
+                rc.render_title(false),
+                rc.render(model.network.query(&field_id), false),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        csv,
+        format!(
+            "{},{},\n{},{},\n",
+            expected[0].0, expected[1].0, expected[0].1, expected[1].1
+        )
+    );
+
+    // Later rows keep the columns of the title line, with an empty cell for
+    // the interface that is gone
+    let mut network = model.network.clone();
+    network.interfaces.remove("lo");
+    network
+        .interfaces
+        .insert("eth1".to_owned(), network.interfaces["eth0"].clone());
+    let csv = print::dump_csv(&fields, &ctx, &network, 1, &opts);
+    assert_eq!(csv, format!("{},,\n", expected[0].1));
+}
+
 #[test]
 fn test_dump_network_titles() {
     let titles = expand_fields(command::DEFAULT_NETWORK_FIELDS, true)
         .iter()
         .filter_map(|dump_field| match dump_field {
-            DumpField::Common(_) => None,
+            DumpField::Common(_) | DumpField::Selector(_) => None,
             DumpField::FieldId(field_id) => {
                 let rc = model::NetworkModel::get_render_config_for_dump(field_id);
                 Some(rc.render_title(false))
@@ -825,6 +1284,10 @@ fn test_dump_transport_content() {
     let ctx = CommonFieldContext {
         timestamp: 0,
         hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
     };
     transport_dumper
         .dump_model(&ctx, &model, &mut transport_content, &mut round, false)
@@ -837,7 +1300,7 @@ fn test_dump_transport_content() {
 
     for dump_field in fields.iter() {
         match dump_field {
-            DumpField::Common(_) => continue,
+            DumpField::Common(_) | DumpField::Selector(_) => continue,
             DumpField::FieldId(field_id) => {
                 let rc = model::NetworkModel::get_render_config_for_dump(field_id);
                 assert_eq!(
@@ -862,7 +1325,7 @@ fn test_dump_transport_titles() {
     let titles = expand_fields(command::DEFAULT_TRANSPORT_FIELDS, true)
         .iter()
         .filter_map(|dump_field| match dump_field {
-            DumpField::Common(_) => None,
+            DumpField::Common(_) | DumpField::Selector(_) => None,
             DumpField::FieldId(field_id) => {
                 let rc = model::NetworkModel::get_render_config_for_dump(field_id);
                 Some(rc.render_title(false))
@@ -897,6 +1360,22 @@ fn test_dump_transport_titles() {
         "Udp6SndBufErrs",
         "Udp6InCsumErrs",
         "Udp6IgnoredMulti",
+        "SocketsUsed",
+        "TcpInUse",
+        "TcpOrphan",
+        "TcpTimeWait",
+        "TcpAlloc",
+        "TcpMem",
+        "UdpInUse",
+        "UdpMem",
+        "RawInUse",
+        "FragInUse",
+        "FragMem",
+        "Tcp6InUse",
+        "Udp6InUse",
+        "Raw6InUse",
+        "Frag6InUse",
+        "Frag6Mem",
     ];
     assert_eq!(titles, expected_titles);
 }
@@ -906,7 +1385,7 @@ fn test_queue_titles() {
     let titles = expand_fields(command::DEFAULT_ETHTOOL_QUEUE_FIELDS, true)
         .iter()
         .filter_map(|dump_field| match dump_field {
-            DumpField::Common(_) => None,
+            DumpField::Common(_) | DumpField::Selector(_) => None,
             DumpField::FieldId(field_id) => Some(field_id.to_string()),
         })
         .collect::<Vec<_>>();
@@ -989,19 +1468,30 @@ fn test_dump_queue_content() {
         gpu: None,
         resctrl: None,
         tc: None,
+        omitted_sections: Default::default(),
+        gaps: 0,
+        cmdline_redacted: false,
+        discontinuity: false,
+        rebooted: false,
+        unavailable_sources: Default::default(),
+        oom_kills: Vec::new(),
     };
 
     let mut opts: GeneralOpt = Default::default();
     let fields = command::expand_fields(command::DEFAULT_ETHTOOL_QUEUE_FIELDS, true);
 
     opts.output_format = Some(OutputFormat::Json);
-    let queue_dumper = ethtool::EthtoolQueue::new(&opts, fields.clone());
+    let queue_dumper = ethtool::EthtoolQueue::new(&opts, None, fields.clone());
 
     let mut queue_content: Vec<u8> = Vec::new();
     let mut round = 0;
     let ctx = CommonFieldContext {
         timestamp: 0,
         hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
     };
 
     // we are dumping timestamps assuming they are local time
@@ -1086,6 +1576,10 @@ fn test_dump_disk_content() {
     let ctx = CommonFieldContext {
         timestamp: 0,
         hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
     };
     disk_dumper
         .dump_model(&ctx, &model, &mut disk_content, &mut round, false)
@@ -1107,7 +1601,7 @@ fn test_dump_disk_content() {
 
         for dump_field in fields.iter() {
             match dump_field {
-                DumpField::Common(_) => continue,
+                DumpField::Common(_) | DumpField::Selector(_) => continue,
                 DumpField::FieldId(field_id) => {
                     let rc = model::SingleDiskModel::get_render_config_for_dump(field_id);
                     assert_eq!(
@@ -1137,7 +1631,7 @@ fn test_dump_disk_titles() {
     let titles = expand_fields(command::DEFAULT_DISK_FIELDS, true)
         .iter()
         .filter_map(|dump_field| match dump_field {
-            DumpField::Common(_) => None,
+            DumpField::Common(_) | DumpField::Selector(_) => None,
             DumpField::FieldId(field_id) => {
                 let rc = model::SingleDiskModel::get_render_config_for_dump(field_id);
                 Some(rc.render_title(false))
@@ -1231,12 +1725,174 @@ proc = ["datetime", "mem.anon"]
     );
 }
 
+#[test]
+fn test_parse_field_overrides() {
+    let tempdir = TempDir::with_prefix("below_dump_format.").expect("Failed to create temp dir");
+    let path = tempdir.path().join("belowrc");
+    let belowrc_str = r#"
+[dump.format.process]
+"mem.rss_bytes" = { unit = "MiB", precision = 2, width = 12 }
+
+[dump.format.system]
+"mem.total" = { unit = "bogus" }
+"#;
+    std::fs::write(&path, belowrc_str).expect("Failed to write temp belowrc file");
+    let filename = path.to_string_lossy().to_string();
+
+    let overrides =
+        parse_field_overrides(&filename, "process").expect("Failed to parse process overrides");
+    let field = ProcessField::FieldId(model::SingleProcessModelFieldId::Mem(
+        model::ProcessMemoryModelFieldId::RssBytes,
+    ));
+    let mut opts: GeneralOpt = Default::default();
+    opts.field_overrides = overrides;
+    let config = field.get_overridden_render_config(&opts);
+    assert_eq!(config.width, Some(12));
+    assert_eq!(
+        config.render(Some(model::Field::U64(3 << 20)), false),
+        "3.00 MiB"
+    );
+    // Explicit overrides still apply in raw mode
+    opts.raw = true;
+    let config = field.get_overridden_render_config(&opts);
+    assert_eq!(
+        config.render(Some(model::Field::U64(3 << 20)), false),
+        "3.00 MiB"
+    );
+
+    // Missing sections mean no overrides, bad values are errors
+    assert!(
+        parse_field_overrides(&filename, "cgroup")
+            .expect("Failed to parse cgroup overrides")
+            .is_empty()
+    );
+    assert!(parse_field_overrides(&filename, "system").is_err());
+    assert!(
+        parse_field_overrides(&tempdir.path().join("missing").to_string_lossy(), "process")
+            .expect("Missing belowrc should not be an error")
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_parse_default_fields() {
+    let tempdir = TempDir::with_prefix("below_dump_defaults.").expect("Failed to create temp dir");
+    let path = tempdir.path().join("belowrc");
+    let belowrc_str = r#"
+[dump.defaults]
+cgroup = ["datetime", "name", "mem"]
+process = ["datetime", "bogus"]
+"#;
+    std::fs::write(&path, belowrc_str).expect("Failed to write temp belowrc file");
+    let filename = path.to_string_lossy().to_string();
+
+    let cgroup_res = parse_default_fields::<command::CgroupOptionField>(&filename, "cgroup")
+        .expect("Failed to parse cgroup defaults")
+        .expect("Cgroup defaults missing");
+    assert_eq!(
+        cgroup_res,
+        vec![
+            command::CgroupOptionField::Unit(CgroupField::Common(CommonField::Datetime)),
+            command::CgroupOptionField::Unit(CgroupField::FieldId(
+                model::SingleCgroupModelFieldId::Name
+            )),
+            command::CgroupOptionField::Agg(command::CgroupAggField::Mem),
+        ]
+    );
+
+    // Missing entries mean the built-in defaults, bad fields are errors
+    assert!(
+        parse_default_fields::<command::SystemOptionField>(&filename, "system")
+            .expect("Failed to parse system defaults")
+            .is_none()
+    );
+    assert!(parse_default_fields::<command::ProcessOptionField>(&filename, "process").is_err());
+    assert!(
+        parse_default_fields::<command::CgroupOptionField>(
+            &tempdir.path().join("missing").to_string_lossy(),
+            "cgroup"
+        )
+        .expect("Missing belowrc should not be an error")
+        .is_none()
+    );
+}
+
+#[test]
+fn test_dump_queue_select() {
+    let queues = (0..3)
+        .map(|queue_id| model::SingleQueueModel {
+            interface: "eth0".to_string(),
+            queue_id,
+            tx_bytes_per_sec: Some(10 * (queue_id as u64 % 2 + 1)),
+            ..Default::default()
+        })
+        .collect();
+    let network = model::NetworkModel {
+        interfaces: BTreeMap::from([(
+            "eth0".to_string(),
+            model::SingleNetModel {
+                interface: "eth0".to_string(),
+                queues,
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    let model = model::Model {
+        time_elapsed: Duration::from_secs(60 * 10),
+        timestamp: SystemTime::now(),
+        system: model::SystemModel::default(),
+        cgroup: model::CgroupModel::default(),
+        process: model::ProcessModel::default(),
+        network,
+        gpu: None,
+        resctrl: None,
+        tc: None,
+        omitted_sections: Default::default(),
+        gaps: 0,
+        cmdline_redacted: false,
+        discontinuity: false,
+        rebooted: false,
+        unavailable_sources: Default::default(),
+        oom_kills: Vec::new(),
+    };
+    let ctx = CommonFieldContext {
+        timestamp: 0,
+        hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
+    };
+    let fields = vec![DumpField::FieldId(model::SingleQueueModelFieldId::QueueId)];
+    let mut opts: GeneralOpt = Default::default();
+    opts.output_format = Some(OutputFormat::Json);
+    opts.rsort = true;
+    opts.top = 2;
+    let queue_dumper = ethtool::EthtoolQueue::new(
+        &opts,
+        Some(model::SingleQueueModelFieldId::TxBytesPerSec),
+        fields,
+    );
+
+    let mut queue_content: Vec<u8> = Vec::new();
+    let mut round = 0;
+    queue_dumper
+        .dump_model(&ctx, &model, &mut queue_content, &mut round, false)
+        .expect("Failed to dump queue model");
+    let jval: Value =
+        serde_json::from_slice(&queue_content).expect("Fail parse json of queue dump");
+
+    // Queue 1 has the highest rate, ties keep their original order
+    assert_eq!(jval, json!([{"Queue": "1"}, {"Queue": "0"}]));
+}
+
 #[test]
 fn test_tc_titles() {
     let titles = expand_fields(command::DEFAULT_TC_FIELDS, true)
         .iter()
         .filter_map(|dump_field| match dump_field {
-            DumpField::Common(_) => None,
+            DumpField::Common(_) | DumpField::Selector(_) => None,
             DumpField::FieldId(field_id) => Some(field_id.to_string()),
         })
         .collect::<Vec<_>>();
@@ -1244,7 +1900,9 @@ fn test_tc_titles() {
     let expected_titles = vec![
         "interface",
         "kind",
+        "path",
         "qlen",
+        "backlog_bytes",
         "bps",
         "pps",
         "bytes_per_sec",
@@ -1281,7 +1939,9 @@ fn test_dump_tc_content() {
         model::SingleTcModel {
             interface: "eth0".to_string(),
             kind: "mq".to_string(),
+            path: "eth0/1:".to_string(),
             qlen: Some(42),
+            backlog_bytes: Some(65536),
             bps: Some(420),
             pps: Some(1337),
             bytes_per_sec: Some(299792458),
@@ -1296,7 +1956,9 @@ fn test_dump_tc_content() {
         model::SingleTcModel {
             interface: "eth0".to_string(),
             kind: "fq_codel".to_string(),
+            path: "eth0/1:/1:1/8001:".to_string(),
             qlen: Some(42),
+            backlog_bytes: Some(65536),
             bps: Some(420),
             pps: Some(1337),
             bytes_per_sec: Some(299792458),
@@ -1344,19 +2006,30 @@ fn test_dump_tc_content() {
         gpu: None,
         resctrl: None,
         tc: Some(model::TcModel { tc: tc_models }),
+        omitted_sections: Default::default(),
+        gaps: 0,
+        cmdline_redacted: false,
+        discontinuity: false,
+        rebooted: false,
+        unavailable_sources: Default::default(),
+        oom_kills: Vec::new(),
     };
 
     let mut opts: GeneralOpt = Default::default();
     let fields = command::expand_fields(command::DEFAULT_TC_FIELDS, true);
 
     opts.output_format = Some(OutputFormat::Json);
-    let queue_dumper = tc::Tc::new(&opts, fields.clone());
+    let queue_dumper = tc::Tc::new(&opts, None, fields.clone());
 
     let mut queue_content: Vec<u8> = Vec::new();
     let mut round = 0;
     let ctx = CommonFieldContext {
         timestamp: 0,
         hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
     };
 
     let result = queue_dumper
@@ -1374,7 +2047,9 @@ fn test_dump_tc_content() {
             "Datetime": "1969-12-31 16:00:00",
             "Interface": "eth0",
             "Kind": "mq",
+            "Path": "eth0/1:",
             "Queue Length": "42",
+            "Backlog Bytes": "64 KB",
             "Bps": "420 B/s",
             "Pps": "1337/s",
             "Bytes": "285.9 MB/s",
@@ -1407,7 +2082,9 @@ fn test_dump_tc_content() {
             "Datetime": "1969-12-31 16:00:00",
             "Interface": "eth0",
             "Kind": "fq_codel",
+            "Path": "eth0/1:/1:1/8001:",
             "Queue Length": "42",
+            "Backlog Bytes": "64 KB",
             "Bps": "420 B/s",
             "Pps": "1337/s",
             "Bytes": "285.9 MB/s",
@@ -1439,3 +2116,419 @@ fn test_dump_tc_content() {
     ]);
     assert_eq!(jval, expected_json);
 }
+
+#[test]
+fn test_dump_resctrl_content() {
+    let mon = |llc_occupancy_bytes, llc_occupancy_pct, mbm_total_bytes_per_sec| {
+        let l3 = model::ResctrlL3MonModel {
+            llc_occupancy_bytes: Some(llc_occupancy_bytes),
+            llc_occupancy_pct: Some(llc_occupancy_pct),
+            mbm_total_bytes_per_sec: Some(mbm_total_bytes_per_sec),
+            mbm_local_bytes_per_sec: None,
+        };
+        model::ResctrlMonModel {
+            total: l3.clone(),
+            per_l3: BTreeMap::from([(0, l3)]),
+        }
+    };
+    let model = model::Model {
+        time_elapsed: Duration::from_secs(60 * 10),
+        timestamp: SystemTime::now(),
+        system: model::SystemModel::default(),
+        cgroup: model::CgroupModel::default(),
+        process: model::ProcessModel::default(),
+        network: model::NetworkModel::default(),
+        gpu: None,
+        resctrl: Some(model::ResctrlModel {
+            mon: Some(mon(3 << 20, 75.0, 1 << 30)),
+            ctrl_mon_groups: BTreeMap::from([(
+                "ctrl".to_owned(),
+                model::ResctrlCtrlMonGroupModel {
+                    name: "ctrl".to_owned(),
+                    full_path: "ctrl".to_owned(),
+                    mon: mon(1 << 20, 25.0, 1 << 20),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        }),
+        tc: None,
+        omitted_sections: Default::default(),
+        gaps: 0,
+        cmdline_redacted: false,
+        discontinuity: false,
+        rebooted: false,
+        unavailable_sources: Default::default(),
+        oom_kills: Vec::new(),
+    };
+
+    let mut opts: GeneralOpt = Default::default();
+    // Leave out Datetime as it depends on the local timezone
+    let fields = command::expand_fields(command::DEFAULT_RESCTRL_FIELDS, true)
+        .into_iter()
+        .filter(|field| matches!(field, DumpField::FieldId(_)))
+        .collect();
+    opts.output_format = Some(OutputFormat::Json);
+    let resctrl_dumper = resctrl::Resctrl::new(&opts, None, fields);
+
+    let mut resctrl_content: Vec<u8> = Vec::new();
+    let mut round = 0;
+    let ctx = CommonFieldContext {
+        timestamp: 0,
+        hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
+    };
+    let result = resctrl_dumper
+        .dump_model(&ctx, &model, &mut resctrl_content, &mut round, false)
+        .expect("Failed to dump resctrl model");
+    assert!(result == tmain::IterExecResult::Success);
+
+    let jval: Value =
+        serde_json::from_slice(&resctrl_content).expect("Fail parse json of resctrl dump");
+    let expected_json = json!([
+        {
+            "Full Path": "",
+            "LLC Occupancy": "3 MB",
+            "LLC Share": "75.00%",
+            "MBM Total": "1 GB/s",
+            "MBM Local": "?"
+        },
+        {
+            "Full Path": "ctrl",
+            "LLC Occupancy": "1 MB",
+            "LLC Share": "25.00%",
+            "MBM Total": "1 MB/s",
+            "MBM Local": "?"
+        }
+    ]);
+    assert_eq!(jval, expected_json);
+}
+
+#[test]
+fn test_dump_slab_content() {
+    let slab = |name: &str, total_size, total_size_delta, growth_streak| model::SingleSlabModel {
+        name: Some(name.to_owned()),
+        total_size: Some(total_size),
+        total_size_delta: Some(total_size_delta),
+        growth_streak: Some(growth_streak),
+        ..Default::default()
+    };
+    let mut system = model::SystemModel::default();
+    system
+        .slab
+        .insert("dentry".to_owned(), slab("dentry", 4 << 20, 1 << 20, 5));
+    system.slab.insert(
+        "kmalloc-64".to_owned(),
+        slab("kmalloc-64", 1 << 20, -1024, 0),
+    );
+    let model = model::Model {
+        time_elapsed: Duration::from_secs(5),
+        timestamp: SystemTime::now(),
+        system,
+        cgroup: model::CgroupModel::default(),
+        process: model::ProcessModel::default(),
+        network: model::NetworkModel::default(),
+        gpu: None,
+        resctrl: None,
+        tc: None,
+        omitted_sections: Default::default(),
+        gaps: 0,
+        cmdline_redacted: false,
+        discontinuity: false,
+        rebooted: false,
+        unavailable_sources: Default::default(),
+        oom_kills: Vec::new(),
+    };
+
+    let mut opts: GeneralOpt = Default::default();
+    let fields = command::expand_fields(
+        &[
+            command::DumpOptionField::Unit(DumpField::FieldId(model::SingleSlabModelFieldId::Name)),
+            command::DumpOptionField::Agg(command::SlabAggField::Growth),
+        ],
+        false,
+    );
+    opts.output_format = Some(OutputFormat::Json);
+    opts.rsort = true;
+    let slab_dumper = slab::Slab::new(
+        &opts,
+        Some(model::SingleSlabModelFieldId::TotalSizeDelta),
+        fields,
+    );
+
+    let mut slab_content: Vec<u8> = Vec::new();
+    let mut round = 0;
+    let ctx = CommonFieldContext {
+        timestamp: 0,
+        hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: None,
+        below_version: None,
+    };
+    let result = slab_dumper
+        .dump_model(&ctx, &model, &mut slab_content, &mut round, false)
+        .expect("Failed to dump slab model");
+    assert!(result == tmain::IterExecResult::Success);
+
+    let jval: Value = serde_json::from_slice(&slab_content).expect("Fail parse json of slab dump");
+    let expected_json = json!([
+        {
+            "Name": "dentry",
+            "ActiveObjs Delta": "?",
+            "Size Delta": "1 MB",
+            "Growth Streak": "5"
+        },
+        {
+            "Name": "kmalloc-64",
+            "ActiveObjs Delta": "?",
+            "Size Delta": "-1 KB",
+            "Growth Streak": "0"
+        }
+    ]);
+    assert_eq!(jval, expected_json);
+}
+
+#[test]
+fn test_dump_summary() {
+    use tmain::DumpSummary;
+
+    let sample = model::Sample::default();
+    let begin = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+    let model_at = |secs, gaps| {
+        let mut model = model::Model::new(begin + Duration::from_secs(secs), &sample, None);
+        model.gaps = gaps;
+        model
+    };
+
+    let mut summary = DumpSummary::default();
+    // Gaps before the first sample dumped are outside of the range
+    summary.add_sample(&model_at(0, 3), 2);
+    // Samples with all rows filtered out are still part of the range
+    summary.add_sample(&model_at(5, 0), 0);
+    let mut rebooted = model_at(15, 1);
+    rebooted.rebooted = true;
+    summary.add_sample(&rebooted, 2);
+    assert_eq!(summary.rows, 4);
+    assert_eq!(summary.samples, 3);
+    assert_eq!(summary.first_sample, Some(begin));
+    assert_eq!(summary.last_sample, Some(begin + Duration::from_secs(15)));
+    assert_eq!(summary.gaps, 1);
+    assert_eq!(summary.reboots, 1);
+
+    let mut trailer = Vec::new();
+    summary
+        .write(&mut trailer)
+        .expect("Failed to write summary");
+    let trailer = String::from_utf8(trailer).expect("Summary is not UTF-8");
+    assert!(trailer.starts_with("Rows: 4\nSamples: 3\n"));
+    assert!(trailer.contains("Missing samples: 1\nReboots: 1\n"));
+
+    let mut trailer = Vec::new();
+    DumpSummary::default()
+        .write(&mut trailer)
+        .expect("Failed to write summary");
+    assert!(String::from_utf8_lossy(&trailer).contains("First sample: -\n"));
+}
+
+#[test]
+fn test_window_aggregator() {
+    use command::WindowOp;
+    use tmain::WindowAggregator;
+
+    let sample = model::Sample::default();
+    let begin = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+    let models = [
+        (Some(10.0), Some(5)),
+        (Some(90.0), None),
+        (Some(30.0), Some(1)),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, (usage_pct, oom_kill))| {
+        let mut model = model::Model::new(begin + Duration::from_secs(5 * i as u64), &sample, None);
+        model.system.total_cpu.usage_pct = usage_pct;
+        model.system.vm.oom_kill = oom_kill;
+        model.system.hostname = format!("host{}", i);
+        model
+    })
+    .collect::<Vec<_>>();
+
+    let aggregate = |op| {
+        let mut window = WindowAggregator::new(op, &models[0]).expect("Failed to aggregate");
+        for model in &models[1..] {
+            window.add(model).expect("Failed to aggregate");
+        }
+        window.finish().expect("Failed to aggregate")
+    };
+
+    let max = aggregate(WindowOp::Max);
+    assert_eq!(max.timestamp, begin);
+    assert_eq!(max.system.total_cpu.usage_pct, Some(90.0));
+    assert_eq!(max.system.vm.oom_kill, Some(5));
+    // Non-numeric values come from the last sample
+    assert_eq!(max.system.hostname, "host2");
+
+    let min = aggregate(WindowOp::Min);
+    assert_eq!(min.system.total_cpu.usage_pct, Some(10.0));
+    assert_eq!(min.system.vm.oom_kill, Some(1));
+}
+
+#[test]
+fn test_snapshot_url_checksum() {
+    use snapshot::split_checksum;
+
+    let digest = "ab".repeat(32);
+    assert_eq!(
+        split_checksum("https://host/snapshot.tar.gz").unwrap(),
+        ("https://host/snapshot.tar.gz", None)
+    );
+    assert_eq!(
+        split_checksum(&format!(
+            "https://host/snapshot.tar.gz#sha256={}",
+            digest.to_uppercase()
+        ))
+        .unwrap(),
+        ("https://host/snapshot.tar.gz", Some(digest))
+    );
+    assert!(split_checksum("https://host/snapshot.tar.gz#md5=abc").is_err());
+    assert!(split_checksum("https://host/snapshot.tar.gz#sha256=abc").is_err());
+}
+
+#[test]
+fn test_open_remote_snapshot() {
+    use std::io::BufRead;
+    use std::io::BufReader;
+    use std::net::TcpListener;
+
+    use flate2::write::GzEncoder;
+    use sha2::Digest;
+
+    // A gzipped tarball of a snapshot directory with a single file
+    let mut tarball = tar::Builder::new(GzEncoder::new(Vec::new(), Default::default()));
+    let data = b"below";
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    tarball
+        .append_data(&mut header, "snapshot_dir/data", &data[..])
+        .expect("Failed to build tarball");
+    let body = tarball
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .expect("Failed to build tarball");
+    let digest = sha2::Sha256::digest(&body)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+
+    // Serve it for each request
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+    let url = format!("http://{}/snapshot.tar.gz", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.expect("Failed to accept");
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(&body).unwrap();
+        }
+    });
+
+    let logger = get_logger();
+    let snapshot = snapshot::open_snapshot(&logger, &format!("{}#sha256={}", url, digest))
+        .expect("Failed to open snapshot");
+    let snapshot_dir = snapshot.store_dir().to_owned();
+    assert!(snapshot_dir.ends_with("snapshot_dir"));
+    assert_eq!(
+        std::fs::read(snapshot_dir.join("data")).expect("Failed to read unpacked file"),
+        data
+    );
+    // Removed once dropped
+    drop(snapshot);
+    assert!(!snapshot_dir.exists());
+
+    let err = snapshot::open_snapshot(&logger, &format!("{}#sha256={}", url, "0".repeat(64)))
+        .expect_err("Checksum mismatch not detected");
+    assert!(err.to_string().contains("Checksum mismatch"));
+}
+
+#[test]
+fn test_list_fields() {
+    let fields = describe_fields::<model::SingleCgroupModelFieldId>();
+    let usage = fields
+        .iter()
+        .find(|field| field.field_id == "cpu.usage_pct")
+        .expect("cpu.usage_pct not listed");
+    assert_eq!(usage.unit, Some("%"));
+    assert!(usage.doc.is_some());
+
+    let mut table = Vec::new();
+    write_field_list(&fields, None, &mut table).expect("Failed to list fields");
+    let table = String::from_utf8(table).expect("Invalid utf8");
+    let mut lines = table.lines();
+    assert!(lines.next().unwrap().starts_with("Field"));
+    assert_eq!(lines.count(), fields.len());
+    assert!(
+        table
+            .lines()
+            .any(|line| line.starts_with("cpu.usage_pct ") && line.contains(" % "))
+    );
+
+    let mut json = Vec::new();
+    write_field_list(&fields, Some(OutputFormat::Json), &mut json).expect("Failed to list fields");
+    let json: Value = serde_json::from_slice(&json).expect("Invalid json");
+    let list = json.as_array().expect("Not a list");
+    assert_eq!(list.len(), fields.len());
+    assert!(
+        list.iter()
+            .any(|field| field["field"] == "cpu.usage_pct" && field["unit"] == "%")
+    );
+}
+
+#[test]
+fn test_host_metadata() {
+    let fields = expand_fields(command::DEFAULT_TRANSPORT_FIELDS, false);
+    let opts: GeneralOpt = Default::default();
+    assert_eq!(opts.add_host_metadata(fields.clone()), fields);
+
+    let opts = GeneralOpt {
+        with_host_metadata: true,
+        ..Default::default()
+    };
+    let with_metadata = opts.add_host_metadata(fields.clone());
+    assert_eq!(
+        with_metadata[..3],
+        CommonField::HOST_METADATA.map(DumpField::<model::NetworkModelFieldId>::Common)
+    );
+    assert_eq!(with_metadata[3..], fields);
+
+    let ctx = CommonFieldContext {
+        timestamp: 0,
+        hostname: "h".to_string(),
+        gaps: 0,
+        boot_id: None,
+        kernel_version: Some("6.9.0".to_string()),
+        below_version: None,
+    };
+    let values = CommonField::HOST_METADATA
+        .iter()
+        .map(|field| field.get_field(&ctx).map(|f| f.to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        vec![Some("h".to_string()), Some("6.9.0".to_string()), None]
+    );
+}
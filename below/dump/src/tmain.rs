@@ -12,7 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::time::Duration;
+
 use super::*;
+use crate::command::WindowOp;
 
 #[derive(PartialEq)]
 pub enum IterExecResult {
@@ -35,6 +38,182 @@ pub trait Dumper {
     ) -> Result<IterExecResult>;
 }
 
+/// Groups consecutive samples into buckets of `duration`, each dumped as a
+/// single sample.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bucket {
+    pub duration: Duration,
+    /// How to combine the samples of a bucket. If None, the first sample is
+    /// dumped.
+    pub window: Option<WindowOp>,
+}
+
+/// Combines the samples of a bucket field by field, keeping the max or min
+/// of each numeric value. Values that are missing in some samples are taken
+/// from the samples that have them. Entities (processes, cgroups, etc.) are
+/// those of the last sample, and non-numeric values are taken from it too.
+///
+/// This works on the serialized Model so that new fields are covered without
+/// any extra code.
+pub struct WindowAggregator {
+    op: WindowOp,
+    timestamp: SystemTime,
+    value: Value,
+}
+
+impl WindowAggregator {
+    pub fn new(op: WindowOp, first: &model::Model) -> Result<Self> {
+        Ok(Self {
+            op,
+            timestamp: first.timestamp,
+            value: serde_json::to_value(first).context("Failed to serialize model")?,
+        })
+    }
+
+    pub fn add(&mut self, model: &model::Model) -> Result<()> {
+        let value = serde_json::to_value(model).context("Failed to serialize model")?;
+        self.value = merge_values(std::mem::take(&mut self.value), value, self.op);
+        Ok(())
+    }
+
+    /// The combined model, labeled with the timestamp of the first sample.
+    pub fn finish(self) -> Result<model::Model> {
+        let mut model: model::Model =
+            serde_json::from_value(self.value).context("Failed to deserialize model")?;
+        model.timestamp = self.timestamp;
+        Ok(model)
+    }
+}
+
+fn merge_values(old: Value, new: Value, op: WindowOp) -> Value {
+    match (old, new) {
+        (Value::Number(old), Value::Number(new)) => {
+            let keep_old = match (old.as_f64(), new.as_f64()) {
+                (Some(o), Some(n)) => match op {
+                    WindowOp::Max => o > n,
+                    WindowOp::Min => o < n,
+                },
+                _ => false,
+            };
+            Value::Number(if keep_old { old } else { new })
+        }
+        (Value::Object(mut old), Value::Object(new)) => {
+            // Objects at the same place in a sorted collection may still be
+            // different entities, e.g. when a cgroup was removed.
+            if old.get("name") != new.get("name") {
+                return Value::Object(new);
+            }
+            Value::Object(
+                new.into_iter()
+                    .map(|(key, new)| {
+                        let merged = match old.remove(&key) {
+                            Some(old) => merge_values(old, new, op),
+                            None => new,
+                        };
+                        (key, merged)
+                    })
+                    .collect(),
+            )
+        }
+        (Value::Array(old), Value::Array(new)) if old.len() == new.len() => Value::Array(
+            old.into_iter()
+                .zip(new)
+                .map(|(old, new)| merge_values(old, new, op))
+                .collect(),
+        ),
+        (old, Value::Null) => old,
+        (_, new) => new,
+    }
+}
+
+/// Consume the samples following `first` that fall into its bucket. Returns
+/// the model to dump for the bucket and the first model of the next bucket,
+/// if there is one within `time_end`.
+fn collect_bucket(
+    advance: &mut Advance,
+    first: model::Model,
+    bucket: &Bucket,
+    time_end: SystemTime,
+) -> Result<(model::Model, Option<model::Model>)> {
+    let bucket_end = first.timestamp + bucket.duration;
+    let mut gaps = first.gaps;
+    let mut rebooted = first.rebooted;
+    let mut window = bucket
+        .window
+        .map(|op| WindowAggregator::new(op, &first))
+        .transpose()?;
+    let next = loop {
+        if advance.get_next_ts() > time_end {
+            break None;
+        }
+        match advance.advance(Direction::Forward) {
+            Some(model) if model.timestamp < bucket_end => {
+                gaps += model.gaps;
+                rebooted |= model.rebooted;
+                if let Some(window) = window.as_mut() {
+                    window.add(&model)?;
+                }
+            }
+            Some(model) if model.timestamp <= time_end => break Some(model),
+            _ => break None,
+        }
+    };
+    let mut model = match window {
+        Some(window) => window.finish()?,
+        None => first,
+    };
+    // Gaps and reboots anywhere in the bucket affect its aggregated values
+    model.gaps = gaps;
+    model.rebooted = rebooted;
+    Ok((model, next))
+}
+
+/// What a dump run covered, for the trailer written with --summary
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DumpSummary {
+    /// Rows written, e.g. one per process and sample for process dumps
+    pub rows: usize,
+    /// Samples in the time range, with or without rows written, e.g. when
+    /// filters matched nothing
+    pub samples: usize,
+    /// Timestamps of the first and last samples in the time range
+    pub first_sample: Option<SystemTime>,
+    pub last_sample: Option<SystemTime>,
+    /// Samples missing between the samples in the time range
+    pub gaps: u64,
+    /// Reboots between the samples in the time range
+    pub reboots: u64,
+    pub wall_time: Duration,
+}
+
+impl DumpSummary {
+    pub(crate) fn add_sample(&mut self, model: &model::Model, rows: usize) {
+        if self.first_sample.is_some() {
+            self.gaps += model.gaps;
+            self.reboots += model.rebooted as u64;
+        } else {
+            self.first_sample = Some(model.timestamp);
+        }
+        self.last_sample = Some(model.timestamp);
+        self.rows += rows;
+        self.samples += 1;
+    }
+
+    pub fn write(&self, output: &mut dyn Write) -> Result<()> {
+        let time = |time: Option<SystemTime>| {
+            time.map_or("-".to_owned(), common::util::systemtime_to_datetime)
+        };
+        writeln!(output, "Rows: {}", self.rows)?;
+        writeln!(output, "Samples: {}", self.samples)?;
+        writeln!(output, "First sample: {}", time(self.first_sample))?;
+        writeln!(output, "Last sample: {}", time(self.last_sample))?;
+        writeln!(output, "Missing samples: {}", self.gaps)?;
+        writeln!(output, "Reboots: {}", self.reboots)?;
+        writeln!(output, "Wall time: {:.3}s", self.wall_time.as_secs_f64())?;
+        Ok(())
+    }
+}
+
 /// Called by dump commands to dump Models in continuous time steps. The actual
 /// dump logic for different Models in each time step is handled by specific
 /// Dumper implementations. This function is responsible for retrieving Models
@@ -46,9 +225,12 @@ pub fn dump_timeseries(
     dumper: &dyn Dumper,
     output: &mut dyn Write,
     output_format: Option<OutputFormat>,
+    bucket: Option<Bucket>,
     br: Option<String>,
     errs: Receiver<Error>,
-) -> Result<()> {
+) -> Result<DumpSummary> {
+    let started = std::time::Instant::now();
+    let mut summary = DumpSummary::default();
     let mut model = match advance.jump_sample_to(time_begin) {
         Some(m) => m,
         None => bail!(
@@ -76,15 +258,24 @@ pub fn dump_timeseries(
         if let Ok(e) = errs.try_recv() {
             bail!(e);
         }
+        let mut next = None;
+        if let Some(bucket) = &bucket {
+            (model, next) = collect_bucket(&mut advance, model, bucket, time_end)?;
+        }
         let ctx = CommonFieldContext {
             timestamp: model
                 .timestamp
                 .duration_since(SystemTime::UNIX_EPOCH)?
                 .as_secs() as i64,
             hostname: model.system.hostname.clone(),
+            gaps: model.gaps,
+            boot_id: model.system.boot_id.clone(),
+            kernel_version: model.system.kernel_version.clone(),
+            below_version: model.system.below_version.clone(),
         };
         // Base on the exec result, we will determine if we need to generate the line breaker, etc
         let comma_flag = round != 0;
+        let rows_before = round;
         let res = match dumper.dump_model(&ctx, &model, output, &mut round, comma_flag) {
             Ok(res) => res,
             Err(e) => {
@@ -93,20 +284,31 @@ pub fn dump_timeseries(
                 if e.downcast_ref::<std::io::Error>()
                     .map_or(false, |e| e.kind() == std::io::ErrorKind::BrokenPipe)
                 {
-                    return Ok(());
+                    summary.wall_time = started.elapsed();
+                    return Ok(summary);
                 } else {
                     return Err(e);
                 }
             }
         };
+        summary.add_sample(&model, round - rows_before);
 
-        if advance.get_next_ts() > time_end {
-            break;
-        }
-
-        model = match advance.advance(Direction::Forward) {
-            Some(m) => m,
-            None => break,
+        model = if bucket.is_some() {
+            match next {
+                Some(m) => m,
+                None => break,
+            }
+        } else {
+            if advance.get_next_ts() > time_end {
+                break;
+            }
+            match advance.advance(Direction::Forward) {
+                Some(mut m) => {
+                    m.system.track_slab_growth(&model.system);
+                    m
+                }
+                None => break,
+            }
         };
 
         if res == IterExecResult::Skip {
@@ -128,5 +330,6 @@ pub fn dump_timeseries(
 
     cliutil::check_final_sample_time_with_requested_time(model.timestamp, time_end);
 
-    Ok(())
+    summary.wall_time = started.elapsed();
+    Ok(summary)
 }
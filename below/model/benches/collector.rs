@@ -0,0 +1,171 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Collection of synthetic hosts with a configurable number of processes,
+//! see `model::synthetic::bench_sizes`. Hosts have a cgroup for every ten
+//! processes.
+
+use std::path::Path;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use below_model::synthetic;
+use below_testutil::CgroupfsBuilder;
+use below_testutil::ProcfsBuilder;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::Throughput;
+
+fn nr_cgroups(nr_processes: usize) -> usize {
+    std::cmp::max(nr_processes / 10, 1)
+}
+
+fn bench_read_all_pids(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_all_pids");
+    for size in synthetic::bench_sizes() {
+        let dir = ProcfsBuilder::synthetic(size, nr_cgroups(size))
+            .build()
+            .expect("Failed to write procfs");
+        let reader = procfs::ProcReader::new_with_custom_procfs(dir.path().to_owned());
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| reader.read_all_pids().expect("Failed to read pids"))
+        });
+    }
+    group.finish();
+}
+
+/// As above, but opening the files of each pid directory together
+fn bench_read_all_pids_batched(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_all_pids_batched");
+    for size in synthetic::bench_sizes() {
+        let dir = ProcfsBuilder::synthetic(size, nr_cgroups(size))
+            .build()
+            .expect("Failed to write procfs");
+        let mut reader = procfs::ProcReader::new_with_custom_procfs(dir.path().to_owned());
+        reader.set_batched_reads(true);
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| reader.read_all_pids().expect("Failed to read pids"))
+        });
+    }
+    group.finish();
+}
+
+fn bench_collect_cgroup_sample(c: &mut Criterion) {
+    let logger = slog::Logger::root(slog::Discard, slog::o!());
+    let mut group = c.benchmark_group("collect_cgroup_sample");
+    for size in synthetic::bench_sizes() {
+        let dir = CgroupfsBuilder::synthetic(nr_cgroups(size))
+            .build()
+            .expect("Failed to write cgroupfs");
+        let reader = cgroupfs::CgroupReader::new_unchecked(dir.path().to_owned())
+            .expect("Failed to open cgroup root");
+        group.throughput(Throughput::Elements(nr_cgroups(size) as u64));
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| {
+                below_model::collect_cgroup_sample(&reader, true, &logger, &None)
+                    .expect("Failed to collect cgroup sample")
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Write limits to every cgroup under `path`, as synthetic cgroups have none
+fn write_limits(path: &Path) {
+    for (file_name, contents) in [
+        ("cgroup.subtree_control", "cpu memory pids\n"),
+        ("cpu.max", "max 100000\n"),
+        ("cpuset.cpus", "\n"),
+        ("cpuset.mems", "\n"),
+        ("memory.high", "max\n"),
+        ("memory.low", "0\n"),
+        ("memory.max", "max\n"),
+        ("memory.min", "0\n"),
+        ("memory.swap.max", "max\n"),
+        ("memory.zswap.max", "max\n"),
+        ("pids.max", "max\n"),
+    ] {
+        std::fs::write(path.join(file_name), contents).expect("Failed to write limit");
+    }
+    for entry in std::fs::read_dir(path).expect("Failed to list cgroup") {
+        let entry = entry.expect("Failed to list cgroup");
+        if entry.file_type().map_or(false, |t| t.is_dir()) {
+            write_limits(&entry.path());
+        }
+    }
+}
+
+fn bench_collect_cgroup_sample_with_limits(c: &mut Criterion) {
+    let logger = slog::Logger::root(slog::Discard, slog::o!());
+    let mut group = c.benchmark_group("collect_cgroup_sample_with_limits");
+    for size in synthetic::bench_sizes() {
+        let dir = CgroupfsBuilder::synthetic(nr_cgroups(size))
+            .build()
+            .expect("Failed to write cgroupfs");
+        write_limits(dir.path());
+        group.throughput(Throughput::Elements(nr_cgroups(size) as u64));
+        // Limits read every sample, and every tenth sample
+        for refresh_samples in [1, 10] {
+            let mut reader = cgroupfs::CgroupReader::new_unchecked(dir.path().to_owned())
+                .expect("Failed to open cgroup root");
+            let cache = cgroupfs::LimitCache::new(refresh_samples);
+            if refresh_samples > 1 {
+                reader.set_limit_cache(cache.clone());
+            }
+            let id = format!("refresh_{}", refresh_samples);
+            group.bench_function(BenchmarkId::new(id, size), |b| {
+                b.iter(|| {
+                    let sample = below_model::collect_cgroup_sample(&reader, true, &logger, &None)
+                        .expect("Failed to collect cgroup sample");
+                    cache.finish_sample();
+                    sample
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_model_new(c: &mut Criterion) {
+    let mut group = c.benchmark_group("model_new");
+    for size in synthetic::bench_sizes() {
+        let last = synthetic::synthetic_sample(size, nr_cgroups(size), 1);
+        let sample = synthetic::synthetic_sample(size, nr_cgroups(size), 2);
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| {
+                below_model::Model::new(
+                    SystemTime::now(),
+                    &sample,
+                    Some((&last, Duration::from_secs(5))),
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_read_all_pids,
+    bench_read_all_pids_batched,
+    bench_collect_cgroup_sample,
+    bench_collect_cgroup_sample_with_limits,
+    bench_model_new
+);
+criterion_main!(benches);
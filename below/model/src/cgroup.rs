@@ -44,9 +44,31 @@ pub struct SingleCgroupModel {
     pub pressure: Option<CgroupPressureModel>,
     #[queriable(subquery)]
     pub cgroup_stat: Option<CgroupStatModel>,
+    /// Not set without a previous sample or across a recreated cgroup
+    #[queriable(subquery)]
+    pub churn: Option<CgroupChurnModel>,
     #[queriable(subquery)]
     #[queriable(preferred_name = mem_numa)]
     pub memory_numa_stat: Option<BTreeMap<u32, CgroupMemoryNumaModel>>,
+    /// Keyed by huge page size, e.g. 2MB
+    #[queriable(subquery)]
+    #[queriable(preferred_name = hugetlb_details)]
+    pub hugetlb: Option<BTreeMap<String, CgroupHugetlbModel>>,
+    #[queriable(subquery)]
+    pub file_io: Option<CgroupFileIoModel>,
+    /// Only set for top level cgroups if perf counters are enabled
+    #[queriable(subquery)]
+    pub perf: Option<PerfCounterModel>,
+    /// Not set for the root
+    #[queriable(subquery)]
+    pub share: Option<CgroupShareModel>,
+    /// Processes in D state in the cgroup and its descendants. Filled in
+    /// from the processes by the Model, None without them.
+    #[queriable(subquery)]
+    pub dstate: Option<DStateModel>,
+    /// Error-level journal messages logged by the cgroup and its descendants,
+    /// only set if journal errors are counted
+    pub journal_errors_per_sec: Option<f64>,
 }
 
 /// A model that represents a cgroup subtree. Each instance is a node that uses
@@ -114,6 +136,20 @@ impl QueriableContainer for CgroupModel {
         }
         Some(&model.data)
     }
+    /// Paths of all cgroups of the tree, parents before their children
+    fn indices(&self) -> Vec<CgroupPath> {
+        let mut paths = Vec::new();
+        let mut stack = vec![(self, Vec::new())];
+        while let Some((model, path)) = stack.pop() {
+            for child in model.children.iter().rev() {
+                let mut child_path = path.clone();
+                child_path.push(child.data.name.clone());
+                stack.push((child, child_path));
+            }
+            paths.push(CgroupPath { path });
+        }
+        paths
+    }
 }
 
 impl core::borrow::Borrow<str> for CgroupModel {
@@ -175,7 +211,15 @@ impl CgroupModel {
                             begin.get(device_name).map(|begin_io_stat| {
                                 (
                                     device_name.clone(),
-                                    CgroupIoModel::new(&begin_io_stat, &end_io_stat, delta),
+                                    CgroupIoModel::new(
+                                        &begin_io_stat,
+                                        &end_io_stat,
+                                        sample
+                                            .io_latency
+                                            .as_ref()
+                                            .and_then(|latency| latency.get(device_name)),
+                                        delta,
+                                    ),
                                 )
                             })
                         })
@@ -202,10 +246,13 @@ impl CgroupModel {
         let pressure = sample
             .pressure
             .as_ref()
-            .map(|p| CgroupPressureModel::new(p));
+            .map(|p| CgroupPressureModel::new(p, sample.memory_pressure_triggers.as_deref()));
 
         let cgroup_stat = sample.cgroup_stat.as_ref().map(CgroupStatModel::new);
 
+        let churn =
+            last_if_inode_matches.map(|(last, delta)| CgroupChurnModel::new(sample, last, delta));
+
         let memory_numa_stat = {
             sample.memory_numa_stat.as_ref().map(|end_numa_nodes| {
                 let begin_numa_nodes = last_if_inode_matches.and_then(|(s, d)| {
@@ -224,6 +271,29 @@ impl CgroupModel {
             })
         };
 
+        let hugetlb = sample.hugetlb_stat.as_ref().map(|stats| {
+            stats
+                .iter()
+                .map(|(size, stat)| (size.clone(), CgroupHugetlbModel::new(stat)))
+                .collect()
+        });
+
+        let file_io = sample.file_io.as_ref().map(CgroupFileIoModel::new);
+
+        let perf = sample.perf_counters.as_ref().map(|perf_counters| {
+            PerfCounterModel::new(
+                perf_counters,
+                last_if_inode_matches.and_then(|(last, delta)| {
+                    last.perf_counters.as_ref().map(|last| (last, delta))
+                }),
+            )
+        });
+
+        // Counts are keyed by path, so they carry over a recreated cgroup
+        let journal_errors_per_sec = last.and_then(|(last, delta)| {
+            count_per_sec!(last.journal_errors, sample.journal_errors, delta)
+        });
+
         // recursively calculate view of children
         // `children` is optional, but we treat it the same as an empty map
         let empty = BTreeMap::new();
@@ -263,7 +333,15 @@ impl CgroupModel {
                 pressure,
                 depth,
                 cgroup_stat,
+                churn,
                 memory_numa_stat,
+                hugetlb,
+                file_io,
+                perf,
+                journal_errors_per_sec,
+                // Filled in by fill_shares once the whole tree is built
+                share: None,
+                dstate: None,
             },
             children,
             count: nr_descendants + 1,
@@ -277,6 +355,77 @@ impl CgroupModel {
         });
         self
     }
+
+    /// Set the share of the CPU and memory usage of the parent and of this
+    /// cgroup, as the root, for all descendants. Call after
+    /// `aggr_top_level_val` so that the root has memory usage.
+    pub fn fill_shares(mut self) -> Self {
+        let root = CgroupUsage::of(&self.data);
+        self.children = std::mem::take(&mut self.children)
+            .into_iter()
+            .map(|child| child.fill_shares_under(&root, &root))
+            .collect();
+        self
+    }
+
+    fn fill_shares_under(mut self, parent: &CgroupUsage, root: &CgroupUsage) -> Self {
+        let usage = CgroupUsage::of(&self.data);
+        self.data.share = Some(CgroupShareModel {
+            cpu_pct_of_parent: share_pct(usage.cpu, parent.cpu),
+            cpu_pct_of_root: share_pct(usage.cpu, root.cpu),
+            mem_pct_of_parent: share_pct(usage.mem, parent.mem),
+            mem_pct_of_root: share_pct(usage.mem, root.mem),
+        });
+        self.children = std::mem::take(&mut self.children)
+            .into_iter()
+            .map(|child| child.fill_shares_under(&usage, root))
+            .collect();
+        self
+    }
+
+    /// Set the processes in D state of this cgroup and all descendants from
+    /// `by_cgroup`, as returned by `DStateModel::by_cgroup`
+    pub fn fill_dstate(mut self, by_cgroup: &BTreeMap<String, DStateModel>) -> Self {
+        self.data.dstate = Some(
+            by_cgroup
+                .get(&self.data.full_path)
+                .cloned()
+                .unwrap_or_else(DStateModel::empty),
+        );
+        self.children = std::mem::take(&mut self.children)
+            .into_iter()
+            .map(|child| child.fill_dstate(by_cgroup))
+            .collect();
+        self
+    }
+}
+
+/// CPU and memory usage a share is computed of
+struct CgroupUsage {
+    cpu: Option<f64>,
+    mem: Option<f64>,
+}
+
+impl CgroupUsage {
+    fn of(model: &SingleCgroupModel) -> Self {
+        Self {
+            cpu: model.cpu.as_ref().and_then(|cpu| cpu.usage_pct),
+            mem: model
+                .memory
+                .as_ref()
+                .and_then(|mem| mem.total)
+                .map(|total| total as f64),
+        }
+    }
+}
+
+/// `value` in percent of `total`, None if either is unknown or the total is
+/// zero
+fn share_pct(value: Option<f64>, total: Option<f64>) -> Option<f64> {
+    match (value, total) {
+        (Some(value), Some(total)) if total > 0.0 => Some(value * 100.0 / total),
+        _ => None,
+    }
 }
 
 impl Nameable for CgroupModel {
@@ -299,11 +448,17 @@ impl Nameable for SingleCgroupModel {
 
 #[::below_derive::queriable_derives]
 pub struct CgroupCpuModel {
+    /// CPU time used, in percent of one CPU
     pub usage_pct: Option<f64>,
+    /// CPU time used in user mode, in percent of one CPU
     pub user_pct: Option<f64>,
+    /// CPU time used in kernel mode, in percent of one CPU
     pub system_pct: Option<f64>,
+    /// Enforcement periods of cpu.max elapsed
     pub nr_periods_per_sec: Option<f64>,
+    /// Enforcement periods of cpu.max in which the cgroup was throttled
     pub nr_throttled_per_sec: Option<f64>,
+    /// Time the cgroup was throttled by cpu.max, in percent of wall time
     pub throttled_pct: Option<f64>,
 }
 
@@ -339,23 +494,83 @@ impl CgroupStatModel {
     }
 }
 
+/// Child cgroups that appeared or disappeared since the previous sample.
+/// Children both created and removed between two samples are missed, and
+/// in lite mode, children moving in and out of the top cgroups of the root
+/// count as churn of the root.
+#[::below_derive::queriable_derives]
+pub struct CgroupChurnModel {
+    /// Children created, including recreated ones
+    pub created_per_sec: Option<f64>,
+    /// Children removed, including recreated ones
+    pub removed_per_sec: Option<f64>,
+}
+
+impl CgroupChurnModel {
+    pub fn new(sample: &CgroupSample, last: &CgroupSample, delta: Duration) -> CgroupChurnModel {
+        let empty = BTreeMap::new();
+        let children = sample.children.as_ref().unwrap_or(&empty);
+        let last_children = last.children.as_ref().unwrap_or(&empty);
+        // A child with a new inode number was removed and created again
+        let is_same = |child: &CgroupSample, other: Option<&CgroupSample>| {
+            other.is_some_and(|other| other.inode_number == child.inode_number)
+        };
+        let created = children
+            .iter()
+            .filter(|(name, child)| !is_same(child, last_children.get(*name)))
+            .count();
+        let removed = last_children
+            .iter()
+            .filter(|(name, child)| !is_same(child, children.get(*name)))
+            .count();
+        CgroupChurnModel {
+            created_per_sec: Some(created as f64 / delta.as_secs_f64()),
+            removed_per_sec: Some(removed as f64 / delta.as_secs_f64()),
+        }
+    }
+}
+
 #[::below_derive::queriable_derives]
 pub struct CgroupIoModel {
+    /// Bytes read
     pub rbytes_per_sec: Option<f64>,
+    /// Bytes written
     pub wbytes_per_sec: Option<f64>,
+    /// Read operations
     pub rios_per_sec: Option<f64>,
+    /// Write operations
     pub wios_per_sec: Option<f64>,
+    /// Bytes discarded
     pub dbytes_per_sec: Option<f64>,
+    /// Discard operations
     pub dios_per_sec: Option<f64>,
+    /// Bytes read and written
     pub rwbytes_per_sec: Option<f64>,
+    /// Device time used as accounted by the iocost controller, in percent
+    /// of wall time
     pub cost_usage_pct: Option<f64>,
+    /// Time IOs waited for iocost budget, in percent of wall time
     pub cost_wait_pct: Option<f64>,
+    /// Time the cgroup spent in iocost debt, having issued IOs that cannot
+    /// be throttled beyond its budget, e.g. swap, in percent of wall time
     pub cost_indebt_pct: Option<f64>,
+    /// Time the cgroup was delayed on returning to userspace to pay back its
+    /// iocost debt, in percent of wall time
     pub cost_indelay_pct: Option<f64>,
+    /// Latency target set with io.latency, in microseconds
+    pub latency_target_usec: Option<u64>,
+    /// Moving average of IO completion latency the io.latency controller
+    /// holds against the target, in microseconds
+    pub latency_avg_usec: Option<u64>,
 }
 
 impl CgroupIoModel {
-    pub fn new(begin: &cgroupfs::IoStat, end: &cgroupfs::IoStat, delta: Duration) -> CgroupIoModel {
+    pub fn new(
+        begin: &cgroupfs::IoStat,
+        end: &cgroupfs::IoStat,
+        latency: Option<&cgroupfs::IoLatency>,
+        delta: Duration,
+    ) -> CgroupIoModel {
         let rbytes_per_sec = count_per_sec!(begin.rbytes, end.rbytes, delta);
         let wbytes_per_sec = count_per_sec!(begin.wbytes, end.wbytes, delta);
         let rwbytes_per_sec = opt_add(rbytes_per_sec.clone(), wbytes_per_sec.clone());
@@ -371,6 +586,8 @@ impl CgroupIoModel {
             cost_wait_pct: usec_pct!(begin.cost_wait, end.cost_wait, delta),
             cost_indebt_pct: usec_pct!(begin.cost_indebt, end.cost_indebt, delta),
             cost_indelay_pct: usec_pct!(begin.cost_indelay, end.cost_indelay, delta),
+            latency_target_usec: latency.and_then(|latency| latency.target),
+            latency_avg_usec: end.avg_lat,
         }
     }
 
@@ -389,6 +606,8 @@ impl CgroupIoModel {
             cost_wait_pct: Some(0.0),
             cost_indebt_pct: Some(0.0),
             cost_indelay_pct: Some(0.0),
+            latency_target_usec: None,
+            latency_avg_usec: None,
         }
     }
 }
@@ -409,6 +628,10 @@ impl std::ops::Add<&CgroupIoModel> for CgroupIoModel {
             cost_wait_pct: opt_add(self.cost_wait_pct, other.cost_wait_pct),
             cost_indebt_pct: opt_add(self.cost_indebt_pct, other.cost_indebt_pct),
             cost_indelay_pct: opt_add(self.cost_indelay_pct, other.cost_indelay_pct),
+            // Latencies don't add up, the total has those of the device
+            // with the highest ones
+            latency_target_usec: self.latency_target_usec.max(other.latency_target_usec),
+            latency_avg_usec: self.latency_avg_usec.max(other.latency_avg_usec),
         }
     }
 }
@@ -429,7 +652,13 @@ pub struct CgroupMemoryModel {
     pub file_mapped: Option<u64>,
     pub file_dirty: Option<u64>,
     pub file_writeback: Option<u64>,
+    /// Growth of file_dirty in bytes per second. Negative while writeback
+    /// catches up, persistently positive while dirtying outpaces it, until
+    /// writers are throttled in balance_dirty_pages.
+    pub file_dirty_growth_per_sec: Option<f64>,
     pub anon_thp: Option<u64>,
+    pub file_thp: Option<u64>,
+    pub shmem_thp: Option<u64>,
     pub inactive_anon: Option<u64>,
     pub active_anon: Option<u64>,
     pub inactive_file: Option<u64>,
@@ -455,6 +684,8 @@ pub struct CgroupMemoryModel {
     pub pglazyfreed: Option<u64>,
     pub thp_fault_alloc: Option<u64>,
     pub thp_collapse_alloc: Option<u64>,
+    /// Bytes of huge pages of all sizes in use, not part of total
+    pub hugetlb: Option<u64>,
     pub events_low: Option<u64>,
     pub events_high: Option<u64>,
     pub events_max: Option<u64>,
@@ -465,6 +696,10 @@ pub struct CgroupMemoryModel {
     pub events_local_max: Option<u64>,
     pub events_local_oom: Option<u64>,
     pub events_local_oom_kill: Option<u64>,
+    pub events_low_per_sec: Option<f64>,
+    pub events_high_per_sec: Option<f64>,
+    pub events_max_per_sec: Option<f64>,
+    pub events_oom_kill_delta: Option<u64>,
 }
 
 impl std::ops::Add for CgroupMemoryModel {
@@ -486,7 +721,13 @@ impl std::ops::Add for CgroupMemoryModel {
             file_mapped: opt_add(self.file_mapped, other.file_mapped),
             file_dirty: opt_add(self.file_dirty, other.file_dirty),
             file_writeback: opt_add(self.file_writeback, other.file_writeback),
+            file_dirty_growth_per_sec: opt_add(
+                self.file_dirty_growth_per_sec,
+                other.file_dirty_growth_per_sec,
+            ),
             anon_thp: opt_add(self.anon_thp, other.anon_thp),
+            file_thp: opt_add(self.file_thp, other.file_thp),
+            shmem_thp: opt_add(self.shmem_thp, other.shmem_thp),
             inactive_anon: opt_add(self.inactive_anon, other.inactive_anon),
             active_anon: opt_add(self.active_anon, other.active_anon),
             inactive_file: opt_add(self.inactive_file, other.inactive_file),
@@ -533,6 +774,7 @@ impl std::ops::Add for CgroupMemoryModel {
             pglazyfreed: opt_add(self.pglazyfreed, other.pglazyfreed),
             thp_fault_alloc: opt_add(self.thp_fault_alloc, other.thp_fault_alloc),
             thp_collapse_alloc: opt_add(self.thp_collapse_alloc, other.thp_collapse_alloc),
+            hugetlb: opt_add(self.hugetlb, other.hugetlb),
             events_low: opt_add(self.events_low, other.events_low),
             events_high: opt_add(self.events_high, other.events_high),
             events_max: opt_add(self.events_max, other.events_max),
@@ -543,6 +785,10 @@ impl std::ops::Add for CgroupMemoryModel {
             events_local_max: opt_add(self.events_local_max, other.events_local_max),
             events_local_oom: opt_add(self.events_local_oom, other.events_local_oom),
             events_local_oom_kill: opt_add(self.events_local_oom_kill, other.events_local_oom_kill),
+            events_low_per_sec: opt_add(self.events_low_per_sec, other.events_low_per_sec),
+            events_high_per_sec: opt_add(self.events_high_per_sec, other.events_high_per_sec),
+            events_max_per_sec: opt_add(self.events_max_per_sec, other.events_max_per_sec),
+            events_oom_kill_delta: opt_add(self.events_oom_kill_delta, other.events_oom_kill_delta),
         }
     }
 }
@@ -564,7 +810,32 @@ impl CgroupMemoryModel {
             model.events_max = events.max;
             model.events_oom = events.oom;
             model.events_oom_kill = events.oom_kill;
+
+            if let Some((
+                CgroupSample {
+                    memory_events: Some(last_events),
+                    ..
+                },
+                delta,
+            )) = last
+            {
+                model.events_low_per_sec = count_per_sec!(last_events.low, events.low, delta);
+                model.events_high_per_sec = count_per_sec!(last_events.high, events.high, delta);
+                model.events_max_per_sec = count_per_sec!(last_events.max, events.max, delta);
+                // Counters reset when the cgroup is recreated, so only report
+                // kills when the counter did not go backwards.
+                model.events_oom_kill_delta = match (last_events.oom_kill, events.oom_kill) {
+                    (Some(begin), Some(end)) if begin <= end => Some(end - begin),
+                    _ => None,
+                };
+            }
         }
+        model.hugetlb = sample.hugetlb_stat.as_ref().map(|stats| {
+            stats
+                .values()
+                .filter_map(|stat| stat.current)
+                .fold(0, u64::saturating_add)
+        });
         if let Some(events_local) = &sample.memory_events_local {
             model.events_local_low = events_local.low;
             model.events_local_high = events_local.high;
@@ -589,6 +860,8 @@ impl CgroupMemoryModel {
             model.file_dirty = stat.file_dirty;
             model.file_writeback = stat.file_writeback;
             model.anon_thp = stat.anon_thp;
+            model.file_thp = stat.file_thp;
+            model.shmem_thp = stat.shmem_thp;
             model.inactive_anon = stat.inactive_anon;
             model.active_anon = stat.active_anon;
             model.inactive_file = stat.inactive_file;
@@ -605,6 +878,12 @@ impl CgroupMemoryModel {
                 delta,
             )) = last
             {
+                model.file_dirty_growth_per_sec = match (last_stat.file_dirty, stat.file_dirty) {
+                    (Some(begin), Some(end)) if !delta.is_zero() => {
+                        Some((end as f64 - begin as f64) / delta.as_secs_f64())
+                    }
+                    _ => None,
+                };
                 model.pgfault = count_per_sec!(last_stat.pgfault, stat.pgfault, delta, u64);
                 model.pgmajfault =
                     count_per_sec!(last_stat.pgmajfault, stat.pgmajfault, delta, u64);
@@ -676,6 +955,75 @@ impl CgroupMemoryModel {
     }
 }
 
+/// IO to the files a cgroup used the most, as traced by BPF. Totals only
+/// cover the tracked files rather than all IO of the cgroup.
+#[::below_derive::queriable_derives]
+pub struct CgroupFileIoModel {
+    pub read_bytes_per_sec: Option<f64>,
+    pub write_bytes_per_sec: Option<f64>,
+    pub top_file: Option<String>,
+    pub top_file_rw_bytes_per_sec: Option<f64>,
+    /// Tracked files, busiest first
+    #[queriable(ignore)]
+    pub files: Vec<FileIoModel>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileIoModel {
+    pub path: String,
+    pub read_bytes_per_sec: f64,
+    pub write_bytes_per_sec: f64,
+}
+
+impl FileIoModel {
+    pub fn rw_bytes_per_sec(&self) -> f64 {
+        self.read_bytes_per_sec + self.write_bytes_per_sec
+    }
+}
+
+impl CgroupFileIoModel {
+    pub fn new(sample: &FileIoSample) -> CgroupFileIoModel {
+        if sample.interval_ms == 0 {
+            return Default::default();
+        }
+        let secs = sample.interval_ms as f64 / 1000.0;
+        let mut files = sample
+            .files
+            .iter()
+            .map(|file| FileIoModel {
+                path: file.path.clone(),
+                read_bytes_per_sec: file.read_bytes as f64 / secs,
+                write_bytes_per_sec: file.write_bytes as f64 / secs,
+            })
+            .collect::<Vec<_>>();
+        files.sort_by(|a, b| {
+            b.rw_bytes_per_sec()
+                .partial_cmp(&a.rw_bytes_per_sec())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        CgroupFileIoModel {
+            read_bytes_per_sec: Some(files.iter().map(|f| f.read_bytes_per_sec).sum()),
+            write_bytes_per_sec: Some(files.iter().map(|f| f.write_bytes_per_sec).sum()),
+            top_file: files.first().map(|f| f.path.clone()),
+            top_file_rw_bytes_per_sec: files.first().map(FileIoModel::rw_bytes_per_sec),
+            files,
+        }
+    }
+}
+
+/// Share of a cgroup in the usage of its parent and of the root
+#[::below_derive::queriable_derives]
+pub struct CgroupShareModel {
+    /// CPU usage in percent of the CPU usage of the parent
+    pub cpu_pct_of_parent: Option<f64>,
+    /// CPU usage in percent of the CPU usage of the root
+    pub cpu_pct_of_root: Option<f64>,
+    /// Memory usage in percent of the memory usage of the parent
+    pub mem_pct_of_parent: Option<f64>,
+    /// Memory usage in percent of the memory usage of the root
+    pub mem_pct_of_root: Option<f64>,
+}
+
 #[::below_derive::queriable_derives]
 pub struct CgroupPidsModel {
     pub tids_current: Option<u64>,
@@ -700,16 +1048,27 @@ impl CgroupPidsModel {
 
 #[::below_derive::queriable_derives]
 pub struct CgroupPressureModel {
+    /// Time some tasks stalled on CPU, 10s average
     pub cpu_some_pct: Option<f64>,
+    /// Time all non-idle tasks stalled on CPU, 10s average
     pub cpu_full_pct: Option<f64>,
+    /// Time some tasks stalled on IO, 10s average
     pub io_some_pct: Option<f64>,
+    /// Time all non-idle tasks stalled on IO, 10s average
     pub io_full_pct: Option<f64>,
+    /// Time some tasks stalled on memory, 10s average
     pub memory_some_pct: Option<f64>,
+    /// Time all non-idle tasks stalled on memory, 10s average
     pub memory_full_pct: Option<f64>,
+    /// Time the CPUs of the cgroup spent handling IRQs, 10s average
+    pub irq_full_pct: Option<f64>,
+    /// Firings of the memory PSI trigger since the last sample, which catch
+    /// stalls too short to move the averages. Only set for watched cgroups.
+    pub memory_trigger_events: Option<u64>,
 }
 
 impl CgroupPressureModel {
-    fn new(pressure: &cgroupfs::Pressure) -> CgroupPressureModel {
+    fn new(pressure: &cgroupfs::Pressure, memory_triggers: Option<&[u64]>) -> CgroupPressureModel {
         // Use avg10 instead of calculating pressure with the total metric. If
         // elapsed time between reading pressure total and recording time is too
         // long, pressure could exceed 100%.
@@ -720,9 +1079,27 @@ impl CgroupPressureModel {
             io_full_pct: pressure.io.full.avg10,
             memory_some_pct: pressure.memory.some.avg10,
             memory_full_pct: pressure.memory.full.avg10,
+            irq_full_pct: pressure.irq.as_ref().and_then(|irq| irq.full.avg10),
+            memory_trigger_events: memory_triggers.map(|triggers| triggers.len() as u64),
+        }
+    }
+}
+#[::below_derive::queriable_derives]
+pub struct CgroupHugetlbModel {
+    pub current: Option<u64>,
+    /// -1 if unlimited
+    pub max: Option<i64>,
+}
+
+impl CgroupHugetlbModel {
+    pub fn new(stat: &cgroupfs::HugetlbStat) -> CgroupHugetlbModel {
+        CgroupHugetlbModel {
+            current: stat.current,
+            max: stat.max,
         }
     }
 }
+
 #[::below_derive::queriable_derives]
 pub struct CgroupMemoryNumaModel {
     pub total: Option<u64>,
@@ -853,6 +1230,8 @@ pub struct CgroupProperties {
     pub cpuset_cpus_effective: Option<cgroupfs::Cpuset>,
     pub cpuset_mems: Option<cgroupfs::MemNodes>,
     pub cpuset_mems_effective: Option<cgroupfs::MemNodes>,
+    /// Number of CPUs in the effective cpuset, the CPUs the cgroup can run on
+    pub cpus_allowed_count: Option<u64>,
 }
 
 impl CgroupProperties {
@@ -874,6 +1253,10 @@ impl CgroupProperties {
             cpuset_cpus_effective: sample.cpuset_cpus_effective.clone(),
             cpuset_mems: sample.cpuset_mems.clone(),
             cpuset_mems_effective: sample.cpuset_mems_effective.clone(),
+            cpus_allowed_count: sample
+                .cpuset_cpus_effective
+                .as_ref()
+                .map(|cpuset| cpuset.cpus.len() as u64),
         }
     }
 }
@@ -963,4 +1346,400 @@ mod tests {
             Some(Field::F64(42.0))
         );
     }
+
+    #[test]
+    fn fill_shares() {
+        let model_json = r#"
+        {
+            "data": { "name": "<root>", "full_path": "", "depth": 0, "cpu": { "usage_pct": 200.0 } },
+            "count": 4,
+            "recreate_flag": false,
+            "children": [
+                {
+                    "data": {
+                        "name": "system.slice", "full_path": "/system.slice", "depth": 1,
+                        "cpu": { "usage_pct": 100.0 }, "memory": { "total": 3000 }
+                    },
+                    "count": 2,
+                    "recreate_flag": false,
+                    "children": [
+                        {
+                            "data": {
+                                "name": "foo.service", "full_path": "/system.slice/foo.service", "depth": 2,
+                                "cpu": { "usage_pct": 25.0 }, "memory": { "total": 1500 }
+                            },
+                            "count": 1,
+                            "recreate_flag": false,
+                            "children": []
+                        }
+                    ]
+                },
+                {
+                    "data": {
+                        "name": "user.slice", "full_path": "/user.slice", "depth": 1,
+                        "cpu": { "usage_pct": 0.0 }, "memory": { "total": 1000 }
+                    },
+                    "count": 1,
+                    "recreate_flag": false,
+                    "children": []
+                }
+            ]
+        }
+        "#;
+        let model: CgroupModel =
+            serde_json::from_str(model_json).expect("Failed to deserialize cgroup model JSON");
+        let model = model.aggr_top_level_val().fill_shares();
+        assert_eq!(model.data.share, None);
+        let query = |field_id: &str| {
+            model.query(&CgroupModelFieldId::from_str(field_id).expect("Bad field id"))
+        };
+        for (field_id, expected) in [
+            ("path:/system.slice/.share.cpu_pct_of_parent", Some(50.0)),
+            ("path:/system.slice/.share.mem_pct_of_root", Some(75.0)),
+            (
+                "path:/system.slice/foo.service/.share.cpu_pct_of_parent",
+                Some(25.0),
+            ),
+            (
+                "path:/system.slice/foo.service/.share.cpu_pct_of_root",
+                Some(12.5),
+            ),
+            (
+                "path:/system.slice/foo.service/.share.mem_pct_of_parent",
+                Some(50.0),
+            ),
+            (
+                "path:/system.slice/foo.service/.share.mem_pct_of_root",
+                Some(37.5),
+            ),
+            ("path:/user.slice/.share.cpu_pct_of_root", Some(0.0)),
+            ("path:/user.slice/.share.mem_pct_of_parent", Some(25.0)),
+        ] {
+            assert_eq!(query(field_id), expected.map(Field::F64), "{}", field_id);
+        }
+    }
+
+    #[test]
+    fn perf_counter_model() {
+        let sample = |inode_number, llc_references, llc_misses| CgroupSample {
+            inode_number: Some(inode_number),
+            perf_counters: Some(PerfCounterSample {
+                llc_references,
+                llc_misses,
+            }),
+            ..Default::default()
+        };
+        let last = sample(1, 1000, 100);
+        let model = CgroupModel::new(
+            "<root>".into(),
+            String::new(),
+            0,
+            &sample(1, 3000, 600),
+            Some((&last, Duration::from_secs(2))),
+        );
+        let perf = model.data.perf.as_ref().expect("perf not set");
+        assert_eq!(perf.llc_references_per_sec, Some(1000.0));
+        assert_eq!(perf.llc_misses_per_sec, Some(250.0));
+        assert_eq!(perf.llc_miss_pct, Some(25.0));
+        assert_eq!(
+            model
+                .data
+                .query(&SingleCgroupModelFieldId::from_str("perf.mem_bw_bytes_per_sec").unwrap()),
+            Some(Field::F64(250.0 * CACHE_LINE_SIZE as f64))
+        );
+
+        // No rates across a recreated cgroup
+        let recreated = CgroupModel::new(
+            "<root>".into(),
+            String::new(),
+            0,
+            &sample(2, 3000, 600),
+            Some((&last, Duration::from_secs(2))),
+        );
+        let perf = recreated.data.perf.as_ref().expect("perf not set");
+        assert_eq!(perf.llc_misses_per_sec, None);
+    }
+
+    #[test]
+    fn churn() {
+        let cgroup = |inode_number, children: &[(&str, i64)]| CgroupSample {
+            inode_number: Some(inode_number),
+            children: Some(
+                children
+                    .iter()
+                    .map(|(name, inode_number)| {
+                        (
+                            name.to_string(),
+                            CgroupSample {
+                                inode_number: Some(*inode_number),
+                                ..Default::default()
+                            },
+                        )
+                    })
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        let last = cgroup(1, &[("kept", 10), ("removed", 11), ("recreated", 12)]);
+        let model = CgroupModel::new(
+            "<root>".into(),
+            String::new(),
+            0,
+            &cgroup(1, &[("kept", 10), ("recreated", 13), ("a", 14), ("b", 15)]),
+            Some((&last, Duration::from_secs(2))),
+        );
+        let churn = model.data.churn.as_ref().expect("churn not set");
+        assert_eq!(churn.created_per_sec, Some(1.5));
+        assert_eq!(churn.removed_per_sec, Some(1.0));
+        assert_eq!(
+            model
+                .data
+                .query(&SingleCgroupModelFieldId::from_str("churn.created_per_sec").unwrap()),
+            Some(Field::F64(1.5))
+        );
+        // Leaves have no churn
+        let kept = model.children.get("kept").expect("kept not found");
+        assert_eq!(
+            kept.data
+                .churn
+                .as_ref()
+                .and_then(|churn| churn.removed_per_sec),
+            Some(0.0)
+        );
+
+        // Nothing to compare with across a recreated cgroup
+        let recreated = CgroupModel::new(
+            "<root>".into(),
+            String::new(),
+            0,
+            &cgroup(2, &[("kept", 10)]),
+            Some((&last, Duration::from_secs(2))),
+        );
+        assert!(recreated.data.churn.is_none());
+    }
+
+    #[test]
+    fn journal_errors_per_sec() {
+        let sample = |inode_number, journal_errors| CgroupSample {
+            inode_number: Some(inode_number),
+            journal_errors,
+            ..Default::default()
+        };
+        let last = sample(1, Some(10));
+        let model = CgroupModel::new(
+            "<root>".into(),
+            String::new(),
+            0,
+            &sample(1, Some(16)),
+            Some((&last, Duration::from_secs(2))),
+        );
+        assert_eq!(model.data.journal_errors_per_sec, Some(3.0));
+        assert_eq!(
+            model
+                .data
+                .query(&SingleCgroupModelFieldId::from_str("journal_errors_per_sec").unwrap()),
+            Some(Field::F64(3.0))
+        );
+
+        // Counts are by path, so a recreated cgroup keeps its rate
+        let recreated = CgroupModel::new(
+            "<root>".into(),
+            String::new(),
+            0,
+            &sample(2, Some(16)),
+            Some((&last, Duration::from_secs(2))),
+        );
+        assert_eq!(recreated.data.journal_errors_per_sec, Some(3.0));
+
+        // Not counted
+        let model = CgroupModel::new(
+            "<root>".into(),
+            String::new(),
+            0,
+            &sample(1, None),
+            Some((&sample(1, None), Duration::from_secs(2))),
+        );
+        assert_eq!(model.data.journal_errors_per_sec, None);
+    }
+
+    #[test]
+    fn file_io_model() {
+        let sample = CgroupSample {
+            file_io: Some(FileIoSample {
+                interval_ms: 2000,
+                files: vec![
+                    FileIoStat {
+                        path: "log/small".into(),
+                        read_bytes: 100,
+                        write_bytes: 0,
+                    },
+                    FileIoStat {
+                        path: "data/big".into(),
+                        read_bytes: 1000,
+                        write_bytes: 3000,
+                    },
+                ],
+            }),
+            ..Default::default()
+        };
+        let model = CgroupModel::new("<root>".into(), String::new(), 0, &sample, None);
+        let file_io = model.data.file_io.as_ref().expect("file_io not set");
+        assert_eq!(file_io.read_bytes_per_sec, Some(550.0));
+        assert_eq!(file_io.write_bytes_per_sec, Some(1500.0));
+        assert_eq!(file_io.top_file_rw_bytes_per_sec, Some(2000.0));
+        assert_eq!(file_io.files[1].path, "log/small");
+        assert_eq!(
+            model
+                .data
+                .query(&SingleCgroupModelFieldId::from_str("file_io.top_file").unwrap()),
+            Some(Field::Str("data/big".into()))
+        );
+
+        let empty = CgroupModel::new("<root>".into(), String::new(), 0, &Default::default(), None);
+        assert!(empty.data.file_io.is_none());
+    }
+
+    #[test]
+    fn hugetlb() {
+        let sample = CgroupSample {
+            hugetlb_stat: Some(BTreeMap::from([
+                (
+                    "1GB".to_owned(),
+                    cgroupfs::HugetlbStat {
+                        current: Some(1 << 30),
+                        max: Some(-1),
+                    },
+                ),
+                (
+                    "2MB".to_owned(),
+                    cgroupfs::HugetlbStat {
+                        current: Some(4 << 20),
+                        max: Some(8 << 20),
+                    },
+                ),
+            ])),
+            ..Default::default()
+        };
+        let model = CgroupModel::new("<root>".into(), String::new(), 0, &sample, None);
+        assert_eq!(
+            model
+                .data
+                .query(&SingleCgroupModelFieldId::from_str("mem.hugetlb").unwrap()),
+            Some(Field::U64((1 << 30) + (4 << 20)))
+        );
+        assert_eq!(
+            model
+                .data
+                .query(&SingleCgroupModelFieldId::from_str("hugetlb_details.2MB.max").unwrap()),
+            Some(Field::I64(8 << 20))
+        );
+
+        // Without the controller
+        let empty = CgroupModel::new("<root>".into(), String::new(), 0, &Default::default(), None);
+        assert!(empty.data.hugetlb.is_none());
+        assert!(empty.data.memory.unwrap().hugetlb.is_none());
+    }
+
+    #[test]
+    fn file_dirty_growth() {
+        let sample = |file_dirty| CgroupSample {
+            memory_stat: Some(cgroupfs::MemoryStat {
+                file_dirty: Some(file_dirty),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let growth = |begin, end| {
+            CgroupMemoryModel::new(&sample(end), Some((&sample(begin), Duration::from_secs(2))))
+                .file_dirty_growth_per_sec
+        };
+        assert_eq!(growth(1 << 20, 5 << 20), Some(2097152.0));
+        // Writeback catching up
+        assert_eq!(growth(5 << 20, 1 << 20), Some(-2097152.0));
+        assert_eq!(
+            CgroupMemoryModel::new(&sample(1 << 20), None).file_dirty_growth_per_sec,
+            None
+        );
+    }
+
+    #[test]
+    fn io_latency() {
+        let io_stat = |avg_lat| cgroupfs::IoStat {
+            rbytes: Some(0),
+            avg_lat,
+            ..Default::default()
+        };
+        let latency = cgroupfs::IoLatency { target: Some(5000) };
+        let sda = CgroupIoModel::new(
+            &io_stat(Some(3000)),
+            &io_stat(Some(6000)),
+            Some(&latency),
+            Duration::from_secs(1),
+        );
+        assert_eq!(sda.latency_target_usec, Some(5000));
+        assert_eq!(sda.latency_avg_usec, Some(6000));
+        let sdb = CgroupIoModel::new(&io_stat(None), &io_stat(None), None, Duration::from_secs(1));
+        assert_eq!(sdb.latency_target_usec, None);
+        assert_eq!(sdb.latency_avg_usec, None);
+
+        let total = CgroupIoModel::empty() + &sda + &sdb;
+        assert_eq!(total.latency_target_usec, Some(5000));
+        assert_eq!(total.latency_avg_usec, Some(6000));
+    }
+
+    #[test]
+    fn memory_trigger_events() {
+        let watched = |triggers| CgroupSample {
+            pressure: Some(Default::default()),
+            memory_pressure_triggers: triggers,
+            ..Default::default()
+        };
+        let model = CgroupModel::new(
+            "<root>".into(),
+            String::new(),
+            0,
+            &watched(Some(vec![1000, 1500, 2250])),
+            None,
+        );
+        assert_eq!(
+            model.data.query(
+                &SingleCgroupModelFieldId::from_str("pressure.memory_trigger_events").unwrap()
+            ),
+            Some(Field::U64(3))
+        );
+        // Watched without firings is not the same as not watched
+        let quiet = CgroupModel::new(
+            "<root>".into(),
+            String::new(),
+            0,
+            &watched(Some(vec![])),
+            None,
+        );
+        assert_eq!(quiet.data.pressure.unwrap().memory_trigger_events, Some(0));
+        let unwatched = CgroupModel::new("<root>".into(), String::new(), 0, &watched(None), None);
+        assert_eq!(unwatched.data.pressure.unwrap().memory_trigger_events, None);
+    }
+
+    #[test]
+    fn cpuset_properties() {
+        let sample = CgroupSample {
+            cpuset_cpus: Some(cgroupfs::Cpuset::from_str("0-7,16-23").unwrap()),
+            cpuset_cpus_effective: Some(cgroupfs::Cpuset::from_str("0-7,16-23").unwrap()),
+            cpuset_mems_effective: Some(cgroupfs::MemNodes::from_str("0").unwrap()),
+            ..Default::default()
+        };
+        let model = CgroupModel::new("<root>".into(), String::new(), 0, &sample, None);
+        let query = |field_id: &str| {
+            model
+                .data
+                .query(&SingleCgroupModelFieldId::from_str(field_id).unwrap())
+                .map(|field| field.to_string())
+        };
+        assert_eq!(query("props.cpuset_cpus").as_deref(), Some("0-7,16-23"));
+        assert_eq!(query("props.cpus_allowed_count").as_deref(), Some("16"));
+        assert_eq!(query("props.cpuset_mems_effective").as_deref(), Some("0"));
+        // Without the cpuset controller the files do not exist
+        let model = CgroupModel::new("<root>".into(), String::new(), 0, &Default::default(), None);
+        assert_eq!(model.data.properties.unwrap().cpus_allowed_count, None);
+    }
 }
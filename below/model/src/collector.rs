@@ -12,20 +12,41 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
+use std::ops::ControlFlow;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::sync::Mutex;
 
 use regex::Regex;
 use slog::error;
+use slog::info;
+use slog::warn;
 
 use super::*;
 use crate::collector_plugin;
 
 pub struct CollectorOptions {
     pub cgroup_root: PathBuf,
+    /// Cgroup roots collected besides `cgroup_root`, by label
+    pub extra_cgroup_roots: BTreeMap<String, PathBuf>,
     pub exit_data: Arc<Mutex<procfs::PidMap>>,
+    /// Latest file IO samples from the BPF file IO tracer, if enabled
+    pub file_io_data: Option<Arc<Mutex<CgroupFileIoMap>>>,
+    /// Firings of PSI triggers registered on watched cgroups
+    pub psi_trigger_data: Option<Arc<Mutex<CgroupPsiTriggerMap>>>,
+    /// Kills by userspace OOM killers, if their events are ingested
+    pub oom_kill_data: Option<Arc<Mutex<Vec<OomKillEvent>>>>,
+    /// Error-level journal message counts, if they are followed
+    pub journal_error_data: Option<Arc<Mutex<CgroupJournalErrorMap>>>,
+    /// Latest socket traffic counters from the BPF socket tracer, if enabled
+    pub process_net_data: Option<Arc<Mutex<ProcessNetMap>>>,
+    /// Latest cache counters from the perf counter collector, if enabled
+    pub perf_counter_data: Option<Arc<Mutex<PerfCounters>>>,
+    /// Health counters updated by the BPF collectors
+    pub bpf_stats: Option<Arc<Mutex<BpfProgramStats>>>,
     pub collect_io_stat: bool,
     pub disable_disk_stat: bool,
     pub enable_btrfs_stats: bool,
@@ -33,6 +54,15 @@ pub struct CollectorOptions {
     pub enable_ksm_stats: bool,
     pub enable_resctrl_stats: bool,
     pub enable_tc_stats: bool,
+    /// Read /proc/[pid]/smaps_rollup of every process, which is expensive
+    pub enable_smaps_rollup_stats: bool,
+    /// Threads reading /proc/[pid] directories, 1 to read them serially
+    pub proc_collection_workers: usize,
+    /// Open the files of each /proc/[pid] directory relative to it
+    pub proc_batched_reads: bool,
+    /// Read cgroup limits only every this many samples rather than every
+    /// sample, see `cgroupfs::LimitCache`. 0 and 1 read them every sample.
+    pub cgroup_limit_refresh_samples: u64,
     pub btrfs_samples: u64,
     pub btrfs_min_pct: f64,
     pub cgroup_re: Option<Regex>,
@@ -40,13 +70,91 @@ pub struct CollectorOptions {
         Option<collector_plugin::Consumer<crate::gpu_stats_collector_plugin::SampleType>>,
     pub tc_stats_receiver:
         Option<collector_plugin::Consumer<crate::tc_collector_plugin::SampleType>>,
+    /// Sample sections to drop after collection
+    pub omit_sections: BTreeSet<SampleSection>,
+    /// Collection interval, recorded in every sample for gap detection
+    pub interval: Option<Duration>,
+    pub stack_sampling: Option<StackSamplingOptions>,
+    /// Redaction of process command lines and exe paths
+    pub cmdline_redaction: Option<CmdlineRedaction>,
+    /// Collect a reduced set of data to lower the collection overhead
+    pub lite: Option<LiteOptions>,
+}
+
+/// Metric ranking the children of the cgroup root in lite mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Sequence)]
+pub enum LiteCgroupMetric {
+    /// CPU time used since the previous sample
+    Cpu,
+    /// memory.current
+    Memory,
+    /// pids.current
+    Pids,
+}
+
+impl fmt::Display for LiteCgroupMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cpu => "cpu",
+            Self::Memory => "memory",
+            Self::Pids => "pids",
+        })
+    }
+}
+
+impl FromStr for LiteCgroupMetric {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        enum_iterator::all::<LiteCgroupMetric>()
+            .find(|metric| metric.to_string() == s)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown cgroup metric {:?}, expected one of: {}",
+                    s,
+                    enum_iterator::all::<LiteCgroupMetric>()
+                        .map(|metric| metric.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+/// Options of lite mode, which collects the system sample and only the
+/// `top_cgroups` children of the cgroup root ranking highest by `metric`,
+/// without their descendants. Processes and extra cgroup roots are left
+/// out.
+pub struct LiteOptions {
+    pub top_cgroups: usize,
+    pub metric: LiteCgroupMetric,
+}
+
+/// Options of stack sampling, which is done outside of the collector as it
+/// takes a while.
+pub struct StackSamplingOptions {
+    /// CPU usage above which a process counts as hot
+    pub cpu_threshold_pct: f64,
+    /// Number of samples in a row a process has to be hot to be sampled
+    pub consecutive_samples: u32,
+    /// Pids of processes to sample are sent here
+    pub requests: Sender<i32>,
+    /// Stacks sampled since the last collection
+    pub data: Arc<Mutex<StackSampleMap>>,
 }
 
 impl Default for CollectorOptions {
     fn default() -> Self {
         Self {
             cgroup_root: Path::new(cgroupfs::DEFAULT_CG_ROOT).to_path_buf(),
+            extra_cgroup_roots: BTreeMap::new(),
             exit_data: Default::default(),
+            file_io_data: None,
+            psi_trigger_data: None,
+            oom_kill_data: None,
+            journal_error_data: None,
+            process_net_data: None,
+            perf_counter_data: None,
+            bpf_stats: None,
             collect_io_stat: true,
             disable_disk_stat: false,
             enable_btrfs_stats: false,
@@ -54,11 +162,20 @@ impl Default for CollectorOptions {
             enable_ksm_stats: false,
             enable_resctrl_stats: false,
             enable_tc_stats: false,
+            enable_smaps_rollup_stats: false,
+            proc_collection_workers: 1,
+            proc_batched_reads: false,
+            cgroup_limit_refresh_samples: 1,
             btrfs_samples: btrfs::DEFAULT_SAMPLES,
             btrfs_min_pct: btrfs::DEFAULT_MIN_PCT,
             cgroup_re: None,
             gpu_stats_receiver: None,
             tc_stats_receiver: None,
+            omit_sections: BTreeSet::new(),
+            interval: None,
+            stack_sampling: None,
+            cmdline_redaction: None,
+            lite: None,
         }
     }
 }
@@ -69,20 +186,95 @@ pub struct Collector {
     proc_reader: procfs::ProcReader,
     prev_sample: Option<(Sample, Instant)>,
     collector_options: CollectorOptions,
+    /// Root of our cgroup namespace under the cgroup root, if process cgroup
+    /// paths need to be translated to match the cgroup hierarchy.
+    cgroup_ns_root: Option<PathBuf>,
+    hot_processes: HotProcesses,
+    /// CPU usage of the children of the cgroup root at the previous sample,
+    /// to rank them in lite mode
+    lite_cpu_usage: BTreeMap<String, u64>,
+    /// Limits of the cgroups under the cgroup root, kept across samples
+    limit_cache: Option<cgroupfs::LimitCache>,
+    /// Data sources unavailable at the previous sample, to only log changes
+    unavailable_sources: BTreeMap<DataSource, String>,
 }
 
 impl Collector {
     pub fn new(logger: slog::Logger, collector_options: CollectorOptions) -> Self {
+        let mut proc_reader = procfs::ProcReader::new();
+        proc_reader.set_workers(collector_options.proc_collection_workers);
+        proc_reader.set_batched_reads(collector_options.proc_batched_reads);
+        let cgroup_ns_root =
+            find_cgroup_ns_root(&logger, &proc_reader, &collector_options.cgroup_root);
+        let refresh_samples = collector_options.cgroup_limit_refresh_samples;
+        let limit_cache = (refresh_samples > 1).then(|| cgroupfs::LimitCache::new(refresh_samples));
         Self {
             logger,
-            proc_reader: procfs::ProcReader::new(),
+            proc_reader,
             prev_sample: None,
             collector_options,
+            cgroup_ns_root,
+            hot_processes: Default::default(),
+            lite_cpu_usage: BTreeMap::new(),
+            limit_cache,
+            unavailable_sources: BTreeMap::new(),
         }
     }
 
     pub fn collect_sample(&mut self) -> Result<Sample> {
-        collect_sample(&self.logger, &mut self.proc_reader, &self.collector_options)
+        let mut sample = collect_sample(
+            &self.logger,
+            &mut self.proc_reader,
+            &self.collector_options,
+            &mut self.lite_cpu_usage,
+            self.limit_cache.as_ref(),
+        )?;
+        if let Some(ns_root) = &self.cgroup_ns_root {
+            for pidinfo in sample.processes.values_mut() {
+                if let Some(path) = cgroupfs::resolve_cgroup_ns_path(ns_root, &pidinfo.cgroup) {
+                    pidinfo.cgroup = path;
+                }
+            }
+        }
+        for (source, reason) in &sample.unavailable_sources {
+            if !self.unavailable_sources.contains_key(source) {
+                warn!(self.logger, "No {} data, {}", source, reason);
+            }
+        }
+        for source in self.unavailable_sources.keys() {
+            if !sample.unavailable_sources.contains_key(source) {
+                info!(self.logger, "{} data available again", source);
+            }
+        }
+        self.unavailable_sources = sample.unavailable_sources.clone();
+        if let Some(redaction) = &self.collector_options.cmdline_redaction {
+            sample.redact_cmdlines(redaction);
+        }
+        sample.omit_sections(&self.collector_options.omit_sections);
+        if let Some(stack_sampling) = &self.collector_options.stack_sampling {
+            sample.stacks = std::mem::take(
+                &mut *stack_sampling
+                    .data
+                    .lock()
+                    .expect("tried to acquire poisoned lock"),
+            );
+            let hot = self.hot_processes.update(
+                &sample.processes,
+                Instant::now(),
+                stack_sampling.cpu_threshold_pct,
+                stack_sampling.consecutive_samples,
+            );
+            for pid in hot {
+                // The sampler only goes away on failure, which it reports
+                let _ = stack_sampling.requests.send(pid);
+            }
+        }
+        Ok(sample)
+    }
+
+    /// Change the collection interval recorded in samples
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.collector_options.interval = Some(interval);
     }
 
     /// Collect a new `Sample`, returning an updated Model
@@ -99,6 +291,114 @@ impl Collector {
         self.prev_sample = Some((sample, now));
         Ok(model)
     }
+
+    /// Collect a `Model` every `interval` and pass it to `f` until `f`
+    /// breaks, returning the break value. This is the collection loop of
+    /// `below record` for embedders consuming models continuously: exit data
+    /// of processes is merged in as configured in `CollectorOptions`, failed
+    /// collections are logged and retried with exponential backoff, and only
+    /// unrecoverable errors, i.e. cgroupfs not being cgroup2, are returned.
+    pub fn stream<B>(
+        &mut self,
+        interval: Duration,
+        mut f: impl FnMut(Model) -> ControlFlow<B>,
+    ) -> Result<B> {
+        self.set_interval(interval);
+        let mut failures = 0;
+        loop {
+            let collect_instant = Instant::now();
+            let wait = match self.collect_and_update_model() {
+                Ok(model) => {
+                    failures = 0;
+                    if let ControlFlow::Break(b) = f(model) {
+                        return Ok(b);
+                    }
+                    interval.saturating_sub(collect_instant.elapsed())
+                }
+                Err(e) => {
+                    // Unrecoverable error -- below only supports cgroup2
+                    if let Some(cgroupfs::Error::NotCgroup2(_)) = e.downcast_ref() {
+                        return Err(e);
+                    }
+                    failures += 1;
+                    let backoff = error_backoff(interval, failures);
+                    error!(
+                        self.logger,
+                        "{:#}, retrying in {} ms",
+                        e,
+                        backoff.as_millis()
+                    );
+                    backoff
+                }
+            };
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+/// Upper bound of the wait after failed collections, unless the interval is
+/// longer.
+const MAX_ERROR_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Wait before the next collection after `failures` failed ones in a row.
+/// Doubles with every failure, starting at `interval`.
+fn error_backoff(interval: Duration, failures: u32) -> Duration {
+    let backoff = interval.saturating_mul(1 << failures.saturating_sub(1).min(16));
+    backoff.min(MAX_ERROR_BACKOFF.max(interval))
+}
+
+/// Tracks how many samples in a row each process used more CPU than a
+/// threshold.
+#[derive(Default)]
+pub(crate) struct HotProcesses {
+    /// Time of the last update
+    last_update: Option<Instant>,
+    /// CPU time in usecs and number of hot samples in a row by pid
+    last: HashMap<i32, (u64, u32)>,
+}
+
+impl HotProcesses {
+    /// Update with the processes of a sample taken at `now` and return the
+    /// pids of processes above `threshold_pct` for `consecutive` samples in
+    /// a row. The count restarts once a process is returned, so one that
+    /// stays hot is returned again every `consecutive` samples.
+    pub(crate) fn update(
+        &mut self,
+        processes: &procfs::PidMap,
+        now: Instant,
+        threshold_pct: f64,
+        consecutive: u32,
+    ) -> Vec<i32> {
+        let elapsed_usecs = self
+            .last_update
+            .map_or(0, |last_update| now.duration_since(last_update).as_micros());
+        self.last_update = Some(now);
+
+        let mut hot = Vec::new();
+        let mut current = HashMap::with_capacity(processes.len());
+        for (pid, pidinfo) in processes {
+            let cpu_usecs = match (pidinfo.stat.user_usecs, pidinfo.stat.system_usecs) {
+                (Some(user), Some(system)) => user + system,
+                _ => continue,
+            };
+            let mut count = 0;
+            if let Some((last_cpu_usecs, last_count)) = self.last.get(pid) {
+                if elapsed_usecs > 0 && cpu_usecs >= *last_cpu_usecs {
+                    let pct = (cpu_usecs - last_cpu_usecs) as f64 * 100.0 / elapsed_usecs as f64;
+                    if pct >= threshold_pct {
+                        count = last_count + 1;
+                    }
+                }
+            }
+            if count > 0 && count >= consecutive {
+                hot.push(*pid);
+                count = 0;
+            }
+            current.insert(*pid, (cpu_usecs, count));
+        }
+        self.last = current;
+        hot
+    }
 }
 
 pub fn opt_add<T: std::ops::Add<T, Output = T>>(a: Option<T>, b: Option<T>) -> Option<T> {
@@ -145,6 +445,49 @@ pub fn get_os_release() -> Result<String> {
     ))
 }
 
+/// When running in a cgroup namespace, /proc/<pid>/cgroup paths are relative
+/// to the namespace root, which may not be what is mounted at the cgroup root
+/// (e.g. the host hierarchy is bind mounted into a container). Locate the
+/// namespace root in the mounted hierarchy using our own cgroup.
+fn find_cgroup_ns_root(
+    logger: &slog::Logger,
+    proc_reader: &procfs::ProcReader,
+    cgroup_root: &Path,
+) -> Option<PathBuf> {
+    let pid = std::process::id();
+    let self_path = proc_reader.read_pid_cgroup(pid).ok()?;
+    let cgroup_reader = cgroupfs::CgroupReader::new(cgroup_root.to_owned()).ok()?;
+    match cgroup_reader.find_cgroup_ns_root(pid, &self_path) {
+        Some(ns_root) if ns_root.as_os_str().is_empty() => None,
+        Some(ns_root) => {
+            info!(
+                logger,
+                "Resolving process cgroups relative to cgroup namespace root /{}",
+                ns_root.display()
+            );
+            Some(ns_root)
+        }
+        None => {
+            warn!(
+                logger,
+                "Could not find own cgroup {} under {}, process cgroups may not match",
+                self_path,
+                cgroup_root.display()
+            );
+            None
+        }
+    }
+}
+
+/// Fill in smaps_rollup of each process. Errors are swallowed as with the
+/// other optional per-pid files: the process may have exited or its
+/// smaps_rollup may not be readable by us.
+fn read_smaps_rollups(reader: &procfs::ProcReader, processes: &mut procfs::PidMap) {
+    for (pid, pidinfo) in processes.iter_mut() {
+        pidinfo.smaps_rollup = reader.read_pid_smaps_rollup(*pid as u32).ok();
+    }
+}
+
 fn merge_procfs_and_exit_data(
     mut procfs_data: procfs::PidMap,
     exit_data: procfs::PidMap,
@@ -180,96 +523,197 @@ fn is_all_zero_disk_stats(disk_stats: &procfs::DiskStat) -> bool {
         && disk_stats.time_spend_discard_ms == Some(0)
 }
 
+/// Run `f`, adding the time it took to `usecs`
+fn timed<T>(usecs: &mut u64, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let ret = f();
+    *usecs += start.elapsed().as_micros() as u64;
+    ret
+}
+
 fn collect_sample(
     logger: &slog::Logger,
     reader: &mut procfs::ProcReader,
     options: &CollectorOptions,
+    lite_cpu_usage: &mut BTreeMap<String, u64>,
+    limit_cache: Option<&cgroupfs::LimitCache>,
 ) -> Result<Sample> {
-    let btrfs_reader =
-        btrfs::BtrfsReader::new(options.btrfs_samples, options.btrfs_min_pct, logger.clone());
+    let start = Instant::now();
+    let mut timing = CollectionTiming::default();
+    let mut unavailable_sources = BTreeMap::new();
     let ethtool_reader = ethtool::EthtoolReader::new();
-    let ksm_reader = procfs::KsmReader::new();
 
     // Take mutex, then take all values out of shared map and replace with default map
     //
     // NB: unconditionally drain the exit buffer otherwise we can leak the entries
-    let exit_pidmap = std::mem::take(
-        &mut *options
-            .exit_data
+    let exit_pidmap = timed(&mut timing.bpf_usecs, || {
+        std::mem::take(
+            &mut *options
+                .exit_data
+                .lock()
+                .expect("tried to acquire poisoned lock"),
+        )
+    });
+
+    let mut cgroup = timed(&mut timing.cgroup_usecs, || {
+        let mut cgroup_reader = match cgroupfs::CgroupReader::new(options.cgroup_root.to_owned()) {
+            Ok(cgroup_reader) => cgroup_reader,
+            // No cgroup hierarchy is mounted, but one that is not cgroup2 is
+            // still an error
+            Err(cgroupfs::Error::IoError(path, e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                unavailable_sources.insert(DataSource::Cgroup, not_found(&path));
+                return Ok(Default::default());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        // Not for extra roots, whose paths would clash in the cache as they
+        // are relative to each root
+        if let Some(cache) = limit_cache {
+            cgroup_reader.set_limit_cache(cache.clone());
+        }
+        let sample = match &options.lite {
+            Some(lite) => collect_lite_cgroup_sample(
+                &cgroup_reader,
+                options.collect_io_stat,
+                logger,
+                &options.cgroup_re,
+                lite,
+                lite_cpu_usage,
+            ),
+            None => collect_cgroup_sample(
+                &cgroup_reader,
+                options.collect_io_stat,
+                logger,
+                &options.cgroup_re,
+            ),
+        };
+        // Drop the files of cgroups removed since the previous sample
+        if let Some(cache) = limit_cache {
+            cache.finish_sample();
+        }
+        sample
+    })?;
+    // An extra root failing, e.g. as it was unmounted, should not fail the
+    // whole sample
+    let extra_cgroups = timed(&mut timing.cgroup_usecs, || {
+        if options.lite.is_some() {
+            return BTreeMap::new();
+        }
+        options
+            .extra_cgroup_roots
+            .iter()
+            .filter_map(|(label, root)| {
+                match cgroupfs::CgroupReader::new(root.to_owned())
+                    .map_err(anyhow::Error::from)
+                    .and_then(|reader| {
+                        collect_cgroup_sample(
+                            &reader,
+                            options.collect_io_stat,
+                            logger,
+                            &options.cgroup_re,
+                        )
+                    }) {
+                    Ok(sample) => Some((label.clone(), sample)),
+                    Err(e) => {
+                        error!(
+                            logger,
+                            "Failed to collect cgroup root {} at {}: {:#}",
+                            label,
+                            root.display(),
+                            e
+                        );
+                        None
+                    }
+                }
+            })
+            .collect()
+    });
+    if let Some(file_io_data) = &options.file_io_data {
+        // Take the samples so that an interval is never reported twice
+        let mut file_io = timed(&mut timing.bpf_usecs, || {
+            std::mem::take(&mut *file_io_data.lock().expect("tried to acquire poisoned lock"))
+        });
+        attach_file_io(&mut cgroup, &mut file_io);
+    }
+    if let Some(psi_trigger_data) = &options.psi_trigger_data {
+        attach_psi_triggers(
+            &mut cgroup,
+            &mut psi_trigger_data
+                .lock()
+                .expect("tried to acquire poisoned lock"),
+        );
+    }
+    if let Some(journal_error_data) = &options.journal_error_data {
+        let mut journal_errors = journal_error_data
             .lock()
-            .expect("tried to acquire poisoned lock"),
-    );
+            .expect("tried to acquire poisoned lock");
+        prune_journal_errors(&mut journal_errors, &options.cgroup_root);
+        attach_journal_errors(&mut cgroup, "", &journal_errors);
+    }
 
-    Ok(Sample {
-        cgroup: collect_cgroup_sample(
-            &cgroupfs::CgroupReader::new(options.cgroup_root.to_owned())?,
-            options.collect_io_stat,
-            logger,
-            &options.cgroup_re,
-        )?,
-        processes: merge_procfs_and_exit_data(reader.read_all_pids()?, exit_pidmap),
-        netstats: match procfs::NetReader::new(logger.clone()).and_then(|v| v.read_netstat()) {
-            Ok(ns) => ns,
-            Err(e) => {
-                error!(logger, "{:#}", e);
+    let processes = timed(&mut timing.process_usecs, || -> Result<_> {
+        if options.lite.is_some() {
+            return Ok(Default::default());
+        }
+        let mut processes = match reader.read_all_pids() {
+            Ok(processes) => processes,
+            Err(procfs::Error::IoError(path, e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                unavailable_sources.insert(DataSource::Processes, not_found(&path));
                 Default::default()
             }
-        },
-        system: SystemSample {
-            stat: reader.read_stat()?,
-            meminfo: reader.read_meminfo()?,
-            vmstat: reader.read_vmstat()?,
-            slabinfo: reader.read_slabinfo().unwrap_or_default(),
-            ksm: if !options.enable_ksm_stats {
-                None
-            } else {
-                Some(ksm_reader.read_ksm())
-            },
-            hostname: get_hostname()?,
-            kernel_version: match reader.read_kernel_version() {
-                Ok(k) => Some(k),
-                Err(e) => {
-                    error!(logger, "{:#}", e);
-                    None
+            Err(e) => return Err(e.into()),
+        };
+        if options.enable_smaps_rollup_stats {
+            read_smaps_rollups(reader, &mut processes);
+        }
+        Ok(merge_procfs_and_exit_data(processes, exit_pidmap))
+    })?;
+    // Counters are cumulative, so they are copied rather than taken
+    let process_net = match &options.process_net_data {
+        Some(process_net_data) => timed(&mut timing.bpf_usecs, || {
+            process_net_data
+                .lock()
+                .expect("tried to acquire poisoned lock")
+                .clone()
+        }),
+        None => Default::default(),
+    };
+    let perf_counters = options.perf_counter_data.as_ref().map(|perf_counter_data| {
+        perf_counter_data
+            .lock()
+            .expect("tried to acquire poisoned lock")
+            .clone()
+    });
+    if let Some(perf_counters) = &perf_counters {
+        attach_perf_counters(&mut cgroup, &perf_counters.cgroups);
+    }
+
+    Ok(Sample {
+        cgroup,
+        processes,
+        netstats: timed(
+            &mut timing.network_usecs,
+            || match procfs::NetReader::new(logger.clone()).and_then(|net_reader| {
+                if let Some(path) = net_reader.missing_interface_dir() {
+                    unavailable_sources.insert(DataSource::Interfaces, not_found(path));
                 }
-            },
-            os_release: match get_os_release() {
-                Ok(o) => Some(o),
+                if let Some(path) = net_reader.missing_proc_net_dir() {
+                    unavailable_sources.insert(DataSource::Protocols, not_found(path));
+                }
+                net_reader.read_netstat()
+            }) {
+                Ok(ns) => ns,
                 Err(e) => {
                     error!(logger, "{:#}", e);
-                    None
-                }
-            },
-            disks: if options.disable_disk_stat {
-                Default::default()
-            } else {
-                match reader.read_disk_stats_and_fsinfo() {
-                    Ok(disks) => disks
-                        .into_iter()
-                        .filter(|(disk_name, disk_stat)| {
-                            if disk_name.starts_with("ram") || disk_name.starts_with("loop") {
-                                return false;
-                            }
-                            !is_all_zero_disk_stats(disk_stat)
-                        })
-                        .collect(),
-                    Err(e) => {
-                        error!(logger, "{:#}", e);
-                        Default::default()
-                    }
-                }
-            },
-            btrfs: if !options.enable_btrfs_stats {
-                Default::default()
-            } else {
-                match btrfs_reader.sample() {
-                    Ok(btrfs) => Some(btrfs),
-                    Err(e) => {
-                        error!(logger, "{:#}", e);
-                        Default::default()
-                    }
+                    Default::default()
                 }
             },
+        ),
+        system: SystemSample {
+            perf_counters: perf_counters.and_then(|perf_counters| perf_counters.system),
+            ..timed(&mut timing.system_usecs, || {
+                collect_system_sample(logger, reader, options, &mut unavailable_sources)
+            })?
         },
         gpus: {
             if let Some(gpu_stats_receiver) = &options.gpu_stats_receiver {
@@ -293,13 +737,15 @@ fn collect_sample(
         ethtool: if !options.enable_ethtool_stats {
             Default::default()
         } else {
-            match ethtool_reader.read_stats::<ethtool::Ethtool>() {
-                Ok(ethtool_stats) => Some(ethtool_stats),
-                Err(e) => {
-                    error!(logger, "{:#}", e);
-                    Default::default()
+            timed(&mut timing.ethtool_usecs, || {
+                match ethtool_reader.read_stats::<ethtool::Ethtool>() {
+                    Ok(ethtool_stats) => Some(ethtool_stats),
+                    Err(e) => {
+                        error!(logger, "{:#}", e);
+                        Default::default()
+                    }
                 }
-            }
+            })
         },
         resctrl: if !options.enable_resctrl_stats {
             None
@@ -329,6 +775,153 @@ fn collect_sample(
         } else {
             None
         },
+        omitted_sections: BTreeSet::new(),
+        interval_s: options.interval.map(|interval| interval.as_secs()),
+        // Filled in by the Collector
+        stacks: Default::default(),
+        process_net,
+        extra_cgroups,
+        cmdline_redacted: false,
+        // Filled in by the recorder
+        discontinuity: false,
+        collection_timing: Some(CollectionTiming {
+            total_usecs: start.elapsed().as_micros() as u64,
+            ..timing
+        }),
+        // The counters are cumulative, so they are copied rather than taken
+        bpf_stats: options
+            .bpf_stats
+            .as_ref()
+            .map(|bpf_stats| {
+                bpf_stats
+                    .lock()
+                    .expect("tried to acquire poisoned lock")
+                    .clone()
+            })
+            .unwrap_or_default(),
+        unavailable_sources,
+        // Taken so that a kill is only recorded once
+        oom_kills: options
+            .oom_kill_data
+            .as_ref()
+            .map(|oom_kill_data| {
+                std::mem::take(
+                    &mut *oom_kill_data
+                        .lock()
+                        .expect("tried to acquire poisoned lock"),
+                )
+            })
+            .unwrap_or_default(),
+    })
+}
+
+/// Reason a data source is unavailable when `path` is missing
+fn not_found(path: &Path) -> String {
+    format!("{} not found", path.display())
+}
+
+fn collect_system_sample(
+    logger: &slog::Logger,
+    reader: &procfs::ProcReader,
+    options: &CollectorOptions,
+    unavailable_sources: &mut BTreeMap<DataSource, String>,
+) -> Result<SystemSample> {
+    let btrfs_reader =
+        btrfs::BtrfsReader::new(options.btrfs_samples, options.btrfs_min_pct, logger.clone());
+    let ksm_reader = procfs::KsmReader::new();
+
+    Ok(SystemSample {
+        stat: reader.read_stat()?,
+        meminfo: reader.read_meminfo()?,
+        vmstat: reader.read_vmstat()?,
+        slabinfo: reader.read_slabinfo().unwrap_or_default(),
+        softirqs: reader.read_softirqs().unwrap_or_default(),
+        schedstat: reader.read_schedstat().unwrap_or_default(),
+        cpufreq: procfs::CpuFreqReader::new()
+            .read_cpufreq()
+            .unwrap_or_default(),
+        irq_pressure: reader.read_irq_pressure().ok(),
+        hung_task_detect_count: reader.read_hung_task_detect_count().ok(),
+        loadavg: reader.read_loadavg().ok(),
+        uptime_secs: reader.read_uptime().ok(),
+        entropy_avail: reader.read_entropy_avail().ok(),
+        // Filled in by collect_sample
+        perf_counters: None,
+        ksm: if !options.enable_ksm_stats {
+            None
+        } else {
+            Some(ksm_reader.read_ksm())
+        },
+        hostname: get_hostname()?,
+        kernel_version: match reader.read_kernel_version() {
+            Ok(k) => Some(k),
+            Err(e) => {
+                error!(logger, "{:#}", e);
+                None
+            }
+        },
+        boot_id: match reader.read_boot_id() {
+            Ok(b) => Some(b),
+            Err(e) => {
+                error!(logger, "{:#}", e);
+                None
+            }
+        },
+        below_version: Some(env!("CARGO_PKG_VERSION").to_owned()),
+        os_release: match get_os_release() {
+            Ok(o) => Some(o),
+            Err(e) => {
+                error!(logger, "{:#}", e);
+                None
+            }
+        },
+        disks: if options.disable_disk_stat {
+            Default::default()
+        } else {
+            match reader.read_disk_stats_and_fsinfo() {
+                Ok(disks) => disks
+                    .into_iter()
+                    .filter(|(disk_name, disk_stat)| {
+                        if disk_name.starts_with("ram") || disk_name.starts_with("loop") {
+                            return false;
+                        }
+                        !is_all_zero_disk_stats(disk_stat)
+                    })
+                    .collect(),
+                Err(procfs::Error::IoError(path, e))
+                    if e.kind() == std::io::ErrorKind::NotFound =>
+                {
+                    unavailable_sources.insert(DataSource::Disks, not_found(&path));
+                    Default::default()
+                }
+                Err(e) => {
+                    error!(logger, "{:#}", e);
+                    Default::default()
+                }
+            }
+        },
+        btrfs: if !options.enable_btrfs_stats {
+            Default::default()
+        } else {
+            match btrfs_reader.sample() {
+                Ok(btrfs) => Some(btrfs),
+                Err(e) => {
+                    error!(logger, "{:#}", e);
+                    Default::default()
+                }
+            }
+        },
+        btrfs_devices: if !options.enable_btrfs_stats {
+            None
+        } else {
+            match btrfs_reader.sample_devices() {
+                Ok(devices) => Some(devices),
+                Err(e) => {
+                    error!(logger, "{:#}", e);
+                    None
+                }
+            }
+        },
     })
 }
 
@@ -384,16 +977,68 @@ fn pressure_wrap<S: Sized>(
     }
 }
 
-fn collect_cgroup_sample(
+/// Collect the sample of the cgroup of `reader` and, recursively, of its
+/// children not matching `cgroup_re`
+pub fn collect_cgroup_sample(
     reader: &cgroupfs::CgroupReader,
     collect_io_stat: bool,
     logger: &slog::Logger,
     cgroup_re: &Option<Regex>,
 ) -> Result<CgroupSample> {
-    let io_stat = if collect_io_stat {
-        io_stat_wrap(reader.read_io_stat())?
+    // We transpose at the end here to convert the
+    // Option<Result<BTreeMap... into Result<Option<BTreeMap and
+    // then bail any errors with `?` - leaving us with the
+    // Option<BTreeMap...
+    //
+    // The only case this can be None is if the cgroup no longer
+    // exists - this is consistent with the other members
+    let children = wrap(reader.child_cgroup_iter())
+        .context("Failed to get iterator over cgroup children")?
+        .map(|child_iter| {
+            child_iter
+                .filter(|child| {
+                    if let Some(cgroup_re) = cgroup_re.as_ref() {
+                        !cgroup_re.is_match(&child.name().to_string_lossy())
+                    } else {
+                        true
+                    }
+                })
+                .map(|child| {
+                    collect_cgroup_sample(&child, collect_io_stat, logger, cgroup_re).map(
+                        |child_sample| {
+                            (
+                                child
+                                    .name()
+                                    .file_name()
+                                    .expect("Unexpected .. in cgroup path")
+                                    .to_string_lossy()
+                                    .to_string(),
+                                child_sample,
+                            )
+                        },
+                    )
+                })
+                .collect::<Result<BTreeMap<String, CgroupSample>>>()
+        })
+        .transpose()?;
+    collect_cgroup_sample_with_children(reader, collect_io_stat, logger, children)
+}
+
+/// Collect the sample of the cgroup of `reader` with the given samples of
+/// its children
+fn collect_cgroup_sample_with_children(
+    reader: &cgroupfs::CgroupReader,
+    collect_io_stat: bool,
+    logger: &slog::Logger,
+    children: Option<BTreeMap<String, CgroupSample>>,
+) -> Result<CgroupSample> {
+    let (io_stat, io_latency) = if collect_io_stat {
+        (
+            io_stat_wrap(reader.read_io_stat())?,
+            wrap(reader.read_io_latency())?,
+        )
     } else {
-        None
+        (None, None)
     };
     Ok(CgroupSample {
         cpu_stat: wrap(reader.read_cpu_stat())?.map(Into::into),
@@ -403,42 +1048,7 @@ fn collect_cgroup_sample(
         memory_current: wrap(reader.read_memory_current().map(|v| v as i64))?,
         memory_stat: wrap(reader.read_memory_stat())?.map(Into::into),
         pressure: pressure_wrap(reader.read_pressure())?.map(Into::into),
-        // We transpose at the end here to convert the
-        // Option<Result<BTreeMap... into Result<Option<BTreeMap and
-        // then bail any errors with `?` - leaving us with the
-        // Option<BTreeMap...
-        //
-        // The only case this can be None is if the cgroup no longer
-        // exists - this is consistent with the above members
-        children: wrap(reader.child_cgroup_iter())
-            .context("Failed to get iterator over cgroup children")?
-            .map(|child_iter| {
-                child_iter
-                    .filter(|child| {
-                        if let Some(cgroup_re) = cgroup_re.as_ref() {
-                            !cgroup_re.is_match(&child.name().to_string_lossy())
-                        } else {
-                            true
-                        }
-                    })
-                    .map(|child| {
-                        collect_cgroup_sample(&child, collect_io_stat, logger, cgroup_re).map(
-                            |child_sample| {
-                                (
-                                    child
-                                        .name()
-                                        .file_name()
-                                        .expect("Unexpected .. in cgroup path")
-                                        .to_string_lossy()
-                                        .to_string(),
-                                    child_sample,
-                                )
-                            },
-                        )
-                    })
-                    .collect::<Result<BTreeMap<String, CgroupSample>>>()
-            })
-            .transpose()?,
+        children,
         memory_swap_current: wrap(reader.read_memory_swap_current().map(|v| v as i64))?,
         memory_zswap_current: None, // Use the one from memory.stat
         memory_min: wrap(reader.read_memory_min())?,
@@ -466,9 +1076,141 @@ fn collect_cgroup_sample(
         cpu_max: wrap(reader.read_cpu_max())?,
         cgroup_controllers: wrap(reader.read_cgroup_controllers())?,
         cgroup_subtree_control: wrap(reader.read_cgroup_subtree_control())?,
+        file_io: None,
+        memory_pressure_triggers: None,
+        perf_counters: None,
+        hugetlb_stat: wrap(reader.read_hugetlb_stat())?,
+        io_latency,
+        journal_errors: None,
     })
 }
 
+/// Collect the sample of the cgroup of `reader` and of only its
+/// `lite.top_cgroups` children not matching `cgroup_re` that rank highest by
+/// `lite.metric`, without their descendants. `prev_cpu_usage` holds the CPU
+/// usage of the children at the previous sample and is updated.
+fn collect_lite_cgroup_sample(
+    reader: &cgroupfs::CgroupReader,
+    collect_io_stat: bool,
+    logger: &slog::Logger,
+    cgroup_re: &Option<Regex>,
+    lite: &LiteOptions,
+    prev_cpu_usage: &mut BTreeMap<String, u64>,
+) -> Result<CgroupSample> {
+    let child_iter = match wrap(reader.child_cgroup_iter())
+        .context("Failed to get iterator over cgroup children")?
+    {
+        Some(child_iter) => child_iter,
+        None => return collect_cgroup_sample_with_children(reader, collect_io_stat, logger, None),
+    };
+    let mut cpu_usage = BTreeMap::new();
+    let mut ranked = Vec::new();
+    for child in child_iter {
+        if let Some(cgroup_re) = cgroup_re.as_ref() {
+            if cgroup_re.is_match(&child.name().to_string_lossy()) {
+                continue;
+            }
+        }
+        let name = child
+            .name()
+            .file_name()
+            .expect("Unexpected .. in cgroup path")
+            .to_string_lossy()
+            .to_string();
+        // Cgroups removed meanwhile rank last
+        let rank = match lite.metric {
+            LiteCgroupMetric::Cpu => match child.read_cpu_stat().ok().and_then(|s| s.usage_usec) {
+                Some(usage) => {
+                    let prev = prev_cpu_usage.get(&name).copied().unwrap_or(0);
+                    cpu_usage.insert(name.clone(), usage);
+                    usage.saturating_sub(prev)
+                }
+                None => 0,
+            },
+            LiteCgroupMetric::Memory => child.read_memory_current().unwrap_or(0),
+            LiteCgroupMetric::Pids => child.read_pids_current().unwrap_or(0),
+        };
+        ranked.push((rank, name, child));
+    }
+    *prev_cpu_usage = cpu_usage;
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+    let mut children = BTreeMap::new();
+    for (_, name, child) in ranked {
+        if children.len() >= lite.top_cgroups {
+            break;
+        }
+        let child_sample = collect_cgroup_sample_with_children(
+            &child,
+            collect_io_stat,
+            logger,
+            Some(BTreeMap::new()),
+        )?;
+        children.insert(name, child_sample);
+    }
+    collect_cgroup_sample_with_children(reader, collect_io_stat, logger, Some(children))
+}
+
+/// Move file IO samples from `file_io` to the cgroups with matching inode
+/// numbers. Samples of cgroups that were filtered out or no longer exist
+/// are left in `file_io`.
+fn attach_file_io(cgroup: &mut CgroupSample, file_io: &mut CgroupFileIoMap) {
+    if let Some(inode_number) = cgroup.inode_number {
+        cgroup.file_io = file_io.remove(&(inode_number as u64));
+    }
+    for child in cgroup.children.iter_mut().flat_map(|c| c.values_mut()) {
+        attach_file_io(child, file_io);
+    }
+}
+
+/// Copy the perf counters of the cgroups with matching inode numbers from
+/// `perf_counters`. Counters are cumulative, so they are copied rather than
+/// taken.
+fn attach_perf_counters(cgroup: &mut CgroupSample, perf_counters: &CgroupPerfCounterMap) {
+    if let Some(inode_number) = cgroup.inode_number {
+        cgroup.perf_counters = perf_counters.get(&(inode_number as u64)).cloned();
+    }
+    for child in cgroup.children.iter_mut().flat_map(|c| c.values_mut()) {
+        attach_perf_counters(child, perf_counters);
+    }
+}
+
+/// Move PSI trigger firings from `triggers` to the cgroups with matching
+/// inode numbers. Every watched cgroup has an entry, so watched cgroups
+/// without firings get an empty list and can be told apart from unwatched
+/// ones.
+fn attach_psi_triggers(cgroup: &mut CgroupSample, triggers: &mut CgroupPsiTriggerMap) {
+    if let Some(inode_number) = cgroup.inode_number {
+        cgroup.memory_pressure_triggers =
+            triggers.get_mut(&(inode_number as u64)).map(std::mem::take);
+    }
+    for child in cgroup.children.iter_mut().flat_map(|c| c.values_mut()) {
+        attach_psi_triggers(child, triggers);
+    }
+}
+
+/// Copy the journal error counts of the cgroups at `path` and below from
+/// `journal_errors`. Counts are keyed by path rather than inode number, as
+/// that is what the journal records, and are cumulative, so cgroups without
+/// any errors logged get a count of zero.
+fn attach_journal_errors(
+    cgroup: &mut CgroupSample,
+    path: &str,
+    journal_errors: &CgroupJournalErrorMap,
+) {
+    cgroup.journal_errors = Some(journal_errors.get(path).copied().unwrap_or(0));
+    for (name, child) in cgroup.children.iter_mut().flat_map(|c| c.iter_mut()) {
+        attach_journal_errors(child, &format!("{}/{}", path, name), journal_errors);
+    }
+}
+
+/// Drop the journal error counts of cgroups that no longer exist under
+/// `cgroup_root`, so that the counts of every transient unit that ever
+/// logged an error do not pile up
+fn prune_journal_errors(journal_errors: &mut CgroupJournalErrorMap, cgroup_root: &Path) {
+    journal_errors.retain(|path, _| cgroup_root.join(path.trim_start_matches('/')).exists());
+}
+
 macro_rules! usec_pct {
     ($a_opt:expr, $b_opt:expr, $delta:expr) => {{
         let mut ret = None;
@@ -520,3 +1262,50 @@ macro_rules! get_option_rate {
             .map(|s| s as u64)
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune_journal_errors() {
+        let cgroupfs = below_testutil::CgroupfsBuilder::synthetic(1)
+            .build()
+            .expect("Failed to write cgroupfs");
+        let mut journal_errors = CgroupJournalErrorMap::from([
+            ("".to_owned(), 3),
+            ("/slice0.slice".to_owned(), 3),
+            ("/slice0.slice/unit0.service".to_owned(), 2),
+            ("/slice0.slice/gone.service".to_owned(), 1),
+        ]);
+        prune_journal_errors(&mut journal_errors, cgroupfs.path());
+        assert_eq!(
+            journal_errors.keys().collect::<Vec<_>>(),
+            vec!["", "/slice0.slice", "/slice0.slice/unit0.service"]
+        );
+    }
+
+    #[test]
+    fn test_error_backoff() {
+        let interval = Duration::from_secs(5);
+        assert_eq!(error_backoff(interval, 1), Duration::from_secs(5));
+        assert_eq!(error_backoff(interval, 2), Duration::from_secs(10));
+        assert_eq!(error_backoff(interval, 4), Duration::from_secs(40));
+        assert_eq!(error_backoff(interval, 5), MAX_ERROR_BACKOFF);
+        assert_eq!(error_backoff(interval, u32::MAX), MAX_ERROR_BACKOFF);
+        // Never shorter than the interval
+        let interval = Duration::from_secs(120);
+        assert_eq!(error_backoff(interval, 3), interval);
+    }
+
+    #[test]
+    fn test_lite_cgroup_metric() {
+        for metric in enum_iterator::all::<LiteCgroupMetric>() {
+            assert_eq!(
+                metric.to_string().parse::<LiteCgroupMetric>().unwrap(),
+                metric
+            );
+        }
+        assert!("io".parse::<LiteCgroupMetric>().is_err());
+    }
+}
@@ -23,16 +23,30 @@
 ///
 /// This list also servers as documentation for available field ids that could
 /// be used in other below crates. A test ensures that this list is up-to-date.
-pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
+pub const COMMON_MODEL_FIELD_IDS: [&str; 645] = [
     "system.hostname",
     "system.kernel_version",
+    "system.boot_id",
     "system.os_release",
+    "system.below_version",
+    "system.irq_pressure_full_pct",
+    "system.hung_task_detect_count",
+    "system.hung_task_detect_delta",
+    "system.uptime_secs",
+    "system.entropy_avail",
+    "system.loadavg.load1",
+    "system.loadavg.load5",
+    "system.loadavg.load15",
+    "system.loadavg.runnable_tasks",
+    "system.loadavg.total_tasks",
     "system.stat.total_interrupt_ct",
     "system.stat.context_switches",
     "system.stat.boot_time_epoch_secs",
     "system.stat.total_processes",
     "system.stat.running_processes",
     "system.stat.blocked_processes",
+    "system.dstate.count",
+    "system.dstate.max_uptime_secs",
     "system.cpu.idx",
     "system.cpu.usage_pct",
     "system.cpu.user_pct",
@@ -45,6 +59,11 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "system.cpu.stolen_pct",
     "system.cpu.guest_pct",
     "system.cpu.guest_nice_pct",
+    "system.cpu.freq_hz",
+    "system.cpu.max_freq_hz",
+    "system.cpu.core_throttles",
+    "system.cpu.core_throttle_usec",
+    "system.cpu.package_throttles",
     "system.cpus.<key>.idx",
     "system.cpus.<key>.usage_pct",
     "system.cpus.<key>.user_pct",
@@ -57,6 +76,43 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "system.cpus.<key>.stolen_pct",
     "system.cpus.<key>.guest_pct",
     "system.cpus.<key>.guest_nice_pct",
+    "system.cpus.<key>.freq_hz",
+    "system.cpus.<key>.max_freq_hz",
+    "system.cpus.<key>.core_throttles",
+    "system.cpus.<key>.core_throttle_usec",
+    "system.cpus.<key>.package_throttles",
+    "system.softirq.idx",
+    "system.softirq.hi_per_sec",
+    "system.softirq.timer_per_sec",
+    "system.softirq.net_tx_per_sec",
+    "system.softirq.net_rx_per_sec",
+    "system.softirq.block_per_sec",
+    "system.softirq.irq_poll_per_sec",
+    "system.softirq.tasklet_per_sec",
+    "system.softirq.sched_per_sec",
+    "system.softirq.hrtimer_per_sec",
+    "system.softirq.rcu_per_sec",
+    "system.softirqs.<key>.idx",
+    "system.softirqs.<key>.hi_per_sec",
+    "system.softirqs.<key>.timer_per_sec",
+    "system.softirqs.<key>.net_tx_per_sec",
+    "system.softirqs.<key>.net_rx_per_sec",
+    "system.softirqs.<key>.block_per_sec",
+    "system.softirqs.<key>.irq_poll_per_sec",
+    "system.softirqs.<key>.tasklet_per_sec",
+    "system.softirqs.<key>.sched_per_sec",
+    "system.softirqs.<key>.hrtimer_per_sec",
+    "system.softirqs.<key>.rcu_per_sec",
+    "system.sched.idx",
+    "system.sched.run_pct",
+    "system.sched.wait_pct",
+    "system.sched.timeslices_per_sec",
+    "system.sched.avg_wait_usec",
+    "system.scheds.<key>.idx",
+    "system.scheds.<key>.run_pct",
+    "system.scheds.<key>.wait_pct",
+    "system.scheds.<key>.timeslices_per_sec",
+    "system.scheds.<key>.avg_wait_usec",
     "system.mem.total",
     "system.mem.free",
     "system.mem.available",
@@ -113,6 +169,9 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "system.slab.<key>.num_caches",
     "system.slab.<key>.active_size",
     "system.slab.<key>.total_size",
+    "system.slab.<key>.active_objs_delta",
+    "system.slab.<key>.total_size_delta",
+    "system.slab.<key>.growth_streak",
     "system.ksm.advisor_max_cpu",
     "system.ksm.advisor_max_pages_to_scan",
     "system.ksm.advisor_min_pages_to_scan",
@@ -137,6 +196,10 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "system.ksm.stable_node_chains_prune_millisecs",
     "system.ksm.stable_node_dups",
     "system.ksm.use_zero_pages",
+    "system.perf.llc_references_per_sec",
+    "system.perf.llc_misses_per_sec",
+    "system.perf.llc_miss_pct",
+    "system.perf.mem_bw_bytes_per_sec",
     "system.disks.<key>.name",
     "system.disks.<key>.disk_usage",
     "system.disks.<key>.partition_size",
@@ -162,6 +225,31 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "system.btrfs.<key>.name",
     "system.btrfs.<key>.disk_fraction",
     "system.btrfs.<key>.disk_bytes",
+    "system.btrfs_devices.<key>.path",
+    "system.btrfs_devices.<key>.mount_point",
+    "system.btrfs_devices.<key>.fsid",
+    "system.btrfs_devices.<key>.devid",
+    "system.btrfs_devices.<key>.write_errs",
+    "system.btrfs_devices.<key>.read_errs",
+    "system.btrfs_devices.<key>.flush_errs",
+    "system.btrfs_devices.<key>.corruption_errs",
+    "system.btrfs_devices.<key>.generation_errs",
+    "system.btrfs_devices.<key>.scrub_status",
+    "system.btrfs_devices.<key>.scrub_progress_pct",
+    "system.btrfs_devices.<key>.scrub_bytes_scrubbed",
+    "system.btrfs_devices.<key>.scrub_corrected_errors",
+    "system.btrfs_devices.<key>.scrub_uncorrectable_errors",
+    "system.collection.bpf_usecs",
+    "system.collection.cgroup_usecs",
+    "system.collection.process_usecs",
+    "system.collection.system_usecs",
+    "system.collection.network_usecs",
+    "system.collection.ethtool_usecs",
+    "system.collection.total_usecs",
+    "system.collection.bpf_lost_events",
+    "system.collection.bpf_attach_failures",
+    "system.collection.bpf_prog_usecs",
+    "system.collection.exitstat_source",
     "cgroup.[path:/<cgroup_path>/.]name",
     "cgroup.[path:/<cgroup_path>/.]full_path",
     "cgroup.[path:/<cgroup_path>/.]inode_number",
@@ -174,6 +262,7 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "cgroup.[path:/<cgroup_path>/.]props.cpuset_cpus_effective",
     "cgroup.[path:/<cgroup_path>/.]props.cpuset_mems",
     "cgroup.[path:/<cgroup_path>/.]props.cpuset_mems_effective",
+    "cgroup.[path:/<cgroup_path>/.]props.cpus_allowed_count",
     "cgroup.[path:/<cgroup_path>/.]props.tids_max",
     "cgroup.[path:/<cgroup_path>/.]props.memory_high",
     "cgroup.[path:/<cgroup_path>/.]props.memory_low",
@@ -202,7 +291,10 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "cgroup.[path:/<cgroup_path>/.]mem.file_mapped",
     "cgroup.[path:/<cgroup_path>/.]mem.file_dirty",
     "cgroup.[path:/<cgroup_path>/.]mem.file_writeback",
+    "cgroup.[path:/<cgroup_path>/.]mem.file_dirty_growth_per_sec",
     "cgroup.[path:/<cgroup_path>/.]mem.anon_thp",
+    "cgroup.[path:/<cgroup_path>/.]mem.file_thp",
+    "cgroup.[path:/<cgroup_path>/.]mem.shmem_thp",
     "cgroup.[path:/<cgroup_path>/.]mem.inactive_anon",
     "cgroup.[path:/<cgroup_path>/.]mem.active_anon",
     "cgroup.[path:/<cgroup_path>/.]mem.inactive_file",
@@ -228,6 +320,7 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "cgroup.[path:/<cgroup_path>/.]mem.pglazyfreed",
     "cgroup.[path:/<cgroup_path>/.]mem.thp_fault_alloc",
     "cgroup.[path:/<cgroup_path>/.]mem.thp_collapse_alloc",
+    "cgroup.[path:/<cgroup_path>/.]mem.hugetlb",
     "cgroup.[path:/<cgroup_path>/.]mem.events_low",
     "cgroup.[path:/<cgroup_path>/.]mem.events_high",
     "cgroup.[path:/<cgroup_path>/.]mem.events_max",
@@ -238,6 +331,10 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "cgroup.[path:/<cgroup_path>/.]mem.events_local_max",
     "cgroup.[path:/<cgroup_path>/.]mem.events_local_oom",
     "cgroup.[path:/<cgroup_path>/.]mem.events_local_oom_kill",
+    "cgroup.[path:/<cgroup_path>/.]mem.events_low_per_sec",
+    "cgroup.[path:/<cgroup_path>/.]mem.events_high_per_sec",
+    "cgroup.[path:/<cgroup_path>/.]mem.events_max_per_sec",
+    "cgroup.[path:/<cgroup_path>/.]mem.events_oom_kill_delta",
     "cgroup.[path:/<cgroup_path>/.]io_details.<key>.rbytes_per_sec",
     "cgroup.[path:/<cgroup_path>/.]io_details.<key>.wbytes_per_sec",
     "cgroup.[path:/<cgroup_path>/.]io_details.<key>.rios_per_sec",
@@ -249,6 +346,8 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "cgroup.[path:/<cgroup_path>/.]io_details.<key>.cost_wait_pct",
     "cgroup.[path:/<cgroup_path>/.]io_details.<key>.cost_indebt_pct",
     "cgroup.[path:/<cgroup_path>/.]io_details.<key>.cost_indelay_pct",
+    "cgroup.[path:/<cgroup_path>/.]io_details.<key>.latency_target_usec",
+    "cgroup.[path:/<cgroup_path>/.]io_details.<key>.latency_avg_usec",
     "cgroup.[path:/<cgroup_path>/.]io.rbytes_per_sec",
     "cgroup.[path:/<cgroup_path>/.]io.wbytes_per_sec",
     "cgroup.[path:/<cgroup_path>/.]io.rios_per_sec",
@@ -260,14 +359,20 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "cgroup.[path:/<cgroup_path>/.]io.cost_wait_pct",
     "cgroup.[path:/<cgroup_path>/.]io.cost_indebt_pct",
     "cgroup.[path:/<cgroup_path>/.]io.cost_indelay_pct",
+    "cgroup.[path:/<cgroup_path>/.]io.latency_target_usec",
+    "cgroup.[path:/<cgroup_path>/.]io.latency_avg_usec",
     "cgroup.[path:/<cgroup_path>/.]pressure.cpu_some_pct",
     "cgroup.[path:/<cgroup_path>/.]pressure.cpu_full_pct",
     "cgroup.[path:/<cgroup_path>/.]pressure.io_some_pct",
     "cgroup.[path:/<cgroup_path>/.]pressure.io_full_pct",
     "cgroup.[path:/<cgroup_path>/.]pressure.memory_some_pct",
     "cgroup.[path:/<cgroup_path>/.]pressure.memory_full_pct",
+    "cgroup.[path:/<cgroup_path>/.]pressure.irq_full_pct",
+    "cgroup.[path:/<cgroup_path>/.]pressure.memory_trigger_events",
     "cgroup.[path:/<cgroup_path>/.]cgroup_stat.nr_descendants",
     "cgroup.[path:/<cgroup_path>/.]cgroup_stat.nr_dying_descendants",
+    "cgroup.[path:/<cgroup_path>/.]churn.created_per_sec",
+    "cgroup.[path:/<cgroup_path>/.]churn.removed_per_sec",
     "cgroup.[path:/<cgroup_path>/.]mem_numa.<key>.total",
     "cgroup.[path:/<cgroup_path>/.]mem_numa.<key>.anon",
     "cgroup.[path:/<cgroup_path>/.]mem_numa.<key>.file",
@@ -295,37 +400,62 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "cgroup.[path:/<cgroup_path>/.]mem_numa.<key>.workingset_restore_anon",
     "cgroup.[path:/<cgroup_path>/.]mem_numa.<key>.workingset_restore_file",
     "cgroup.[path:/<cgroup_path>/.]mem_numa.<key>.workingset_nodereclaim",
+    "cgroup.[path:/<cgroup_path>/.]hugetlb_details.<key>.current",
+    "cgroup.[path:/<cgroup_path>/.]hugetlb_details.<key>.max",
+    "cgroup.[path:/<cgroup_path>/.]file_io.read_bytes_per_sec",
+    "cgroup.[path:/<cgroup_path>/.]file_io.write_bytes_per_sec",
+    "cgroup.[path:/<cgroup_path>/.]file_io.top_file",
+    "cgroup.[path:/<cgroup_path>/.]file_io.top_file_rw_bytes_per_sec",
+    "cgroup.[path:/<cgroup_path>/.]perf.llc_references_per_sec",
+    "cgroup.[path:/<cgroup_path>/.]perf.llc_misses_per_sec",
+    "cgroup.[path:/<cgroup_path>/.]perf.llc_miss_pct",
+    "cgroup.[path:/<cgroup_path>/.]perf.mem_bw_bytes_per_sec",
+    "cgroup.[path:/<cgroup_path>/.]share.cpu_pct_of_parent",
+    "cgroup.[path:/<cgroup_path>/.]share.cpu_pct_of_root",
+    "cgroup.[path:/<cgroup_path>/.]share.mem_pct_of_parent",
+    "cgroup.[path:/<cgroup_path>/.]share.mem_pct_of_root",
+    "cgroup.[path:/<cgroup_path>/.]dstate.count",
+    "cgroup.[path:/<cgroup_path>/.]dstate.max_uptime_secs",
+    "cgroup.[path:/<cgroup_path>/.]journal_errors_per_sec",
     "resctrl.cpuset",
     "resctrl.ctrl_mon_groups.<key>.cpuset",
     "resctrl.ctrl_mon_groups.<key>.full_path",
     "resctrl.ctrl_mon_groups.<key>.mode",
     "resctrl.ctrl_mon_groups.<key>.mon.per_l3.<key>.llc_occupancy_bytes",
+    "resctrl.ctrl_mon_groups.<key>.mon.per_l3.<key>.llc_occupancy_pct",
     "resctrl.ctrl_mon_groups.<key>.mon.per_l3.<key>.mbm_local_bytes_per_sec",
     "resctrl.ctrl_mon_groups.<key>.mon.per_l3.<key>.mbm_total_bytes_per_sec",
     "resctrl.ctrl_mon_groups.<key>.mon.total.llc_occupancy_bytes",
+    "resctrl.ctrl_mon_groups.<key>.mon.total.llc_occupancy_pct",
     "resctrl.ctrl_mon_groups.<key>.mon.total.mbm_local_bytes_per_sec",
     "resctrl.ctrl_mon_groups.<key>.mon.total.mbm_total_bytes_per_sec",
     "resctrl.ctrl_mon_groups.<key>.mon_groups.<key>.full_path",
     "resctrl.ctrl_mon_groups.<key>.mon_groups.<key>.mon.per_l3.<key>.llc_occupancy_bytes",
+    "resctrl.ctrl_mon_groups.<key>.mon_groups.<key>.mon.per_l3.<key>.llc_occupancy_pct",
     "resctrl.ctrl_mon_groups.<key>.mon_groups.<key>.mon.per_l3.<key>.mbm_local_bytes_per_sec",
     "resctrl.ctrl_mon_groups.<key>.mon_groups.<key>.mon.per_l3.<key>.mbm_total_bytes_per_sec",
     "resctrl.ctrl_mon_groups.<key>.mon_groups.<key>.mon.total.llc_occupancy_bytes",
+    "resctrl.ctrl_mon_groups.<key>.mon_groups.<key>.mon.total.llc_occupancy_pct",
     "resctrl.ctrl_mon_groups.<key>.mon_groups.<key>.mon.total.mbm_local_bytes_per_sec",
     "resctrl.ctrl_mon_groups.<key>.mon_groups.<key>.mon.total.mbm_total_bytes_per_sec",
     "resctrl.ctrl_mon_groups.<key>.mon_groups.<key>.name",
     "resctrl.ctrl_mon_groups.<key>.name",
     "resctrl.mode",
     "resctrl.mon.per_l3.<key>.llc_occupancy_bytes",
+    "resctrl.mon.per_l3.<key>.llc_occupancy_pct",
     "resctrl.mon.per_l3.<key>.mbm_local_bytes_per_sec",
     "resctrl.mon.per_l3.<key>.mbm_total_bytes_per_sec",
     "resctrl.mon.total.llc_occupancy_bytes",
+    "resctrl.mon.total.llc_occupancy_pct",
     "resctrl.mon.total.mbm_local_bytes_per_sec",
     "resctrl.mon.total.mbm_total_bytes_per_sec",
     "resctrl.mon_groups.<key>.full_path",
     "resctrl.mon_groups.<key>.mon.per_l3.<key>.llc_occupancy_bytes",
+    "resctrl.mon_groups.<key>.mon.per_l3.<key>.llc_occupancy_pct",
     "resctrl.mon_groups.<key>.mon.per_l3.<key>.mbm_local_bytes_per_sec",
     "resctrl.mon_groups.<key>.mon.per_l3.<key>.mbm_total_bytes_per_sec",
     "resctrl.mon_groups.<key>.mon.total.llc_occupancy_bytes",
+    "resctrl.mon_groups.<key>.mon.total.llc_occupancy_pct",
     "resctrl.mon_groups.<key>.mon.total.mbm_local_bytes_per_sec",
     "resctrl.mon_groups.<key>.mon.total.mbm_total_bytes_per_sec",
     "resctrl.mon_groups.<key>.name",
@@ -335,6 +465,7 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "process.processes.<key>.comm",
     "process.processes.<key>.state",
     "process.processes.<key>.uptime_secs",
+    "process.processes.<key>.start_time_epoch_secs",
     "process.processes.<key>.cgroup",
     "process.processes.<key>.io.rbytes_per_sec",
     "process.processes.<key>.io.wbytes_per_sec",
@@ -350,14 +481,31 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "process.processes.<key>.mem.shmem",
     "process.processes.<key>.mem.pte",
     "process.processes.<key>.mem.swap",
+    "process.processes.<key>.mem.swap_growth_per_sec",
     "process.processes.<key>.mem.huge_tlb",
+    "process.processes.<key>.mem.pss",
+    "process.processes.<key>.mem.shared",
+    "process.processes.<key>.mem.private",
+    "process.processes.<key>.mem.anon_thp",
+    "process.processes.<key>.mem.swap_pss",
     "process.processes.<key>.cpu.usage_pct",
     "process.processes.<key>.cpu.user_pct",
     "process.processes.<key>.cpu.system_pct",
     "process.processes.<key>.cpu.num_threads",
+    "process.processes.<key>.cpu.total_usecs",
     "process.processes.<key>.cmdline",
     "process.processes.<key>.exe_path",
+    "process.processes.<key>.stack.samples",
+    "process.processes.<key>.stack.top_frame",
+    "process.processes.<key>.stack.top_frame_pct",
+    "process.processes.<key>.net.rx_bytes_per_sec",
+    "process.processes.<key>.net.tx_bytes_per_sec",
     "network.interfaces.<key>.interface",
+    "network.interfaces.<key>.operstate",
+    "network.interfaces.<key>.speed_mbps",
+    "network.interfaces.<key>.duplex",
+    "network.interfaces.<key>.carrier_changes",
+    "network.interfaces.<key>.carrier_changes_delta",
     "network.interfaces.<key>.rx_bytes_per_sec",
     "network.interfaces.<key>.tx_bytes_per_sec",
     "network.interfaces.<key>.throughput_per_sec",
@@ -388,6 +536,7 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "network.interfaces.<key>.tx_packets",
     "network.interfaces.<key>.tx_window_errors",
     "network.interfaces.<key>.tx_timeout_per_sec",
+    "network.interfaces.<key>.qdisc_drops_per_sec",
     "network.interfaces.<key>.raw_stats",
     "network.interfaces.<key>.queues.<idx>.interface",
     "network.interfaces.<key>.queues.<idx>.queue_id",
@@ -473,6 +622,23 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "network.udp6.sndbuf_errors",
     "network.udp6.in_csum_errors",
     "network.udp6.ignored_multi",
+    "network.sockstat.sockets_used",
+    "network.sockstat.tcp_inuse",
+    "network.sockstat.tcp_orphan",
+    "network.sockstat.tcp_tw",
+    "network.sockstat.tcp_alloc",
+    "network.sockstat.tcp_mem",
+    "network.sockstat.udp_inuse",
+    "network.sockstat.udp_mem",
+    "network.sockstat.raw_inuse",
+    "network.sockstat.frag_inuse",
+    "network.sockstat.frag_memory_bytes",
+    "network.sockstat6.tcp_inuse",
+    "network.sockstat6.udp_inuse",
+    "network.sockstat6.raw_inuse",
+    "network.sockstat6.frag_inuse",
+    "network.sockstat6.frag_memory_bytes",
+    "tc.tc.<idx>.backlog_bytes",
     "tc.tc.<idx>.backlog_per_sec",
     "tc.tc.<idx>.bps",
     "tc.tc.<idx>.bytes_per_sec",
@@ -481,6 +647,7 @@ pub const COMMON_MODEL_FIELD_IDS: [&str; 478] = [
     "tc.tc.<idx>.kind",
     "tc.tc.<idx>.overlimits_per_sec",
     "tc.tc.<idx>.packets_per_sec",
+    "tc.tc.<idx>.path",
     "tc.tc.<idx>.pps",
     "tc.tc.<idx>.qdisc.fq_codel.ce_threshold",
     "tc.tc.<idx>.qdisc.fq_codel.drop_batch_size",
@@ -26,6 +26,7 @@ use anyhow::Context;
 use anyhow::Result;
 use common::open_source_shim;
 use enum_iterator::Sequence;
+use regex::Regex;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -40,6 +41,8 @@ pub mod process;
 pub mod resctrl;
 pub mod sample;
 mod sample_model;
+#[cfg(any(test, feature = "synthetic"))]
+pub mod synthetic;
 pub mod system;
 pub mod tc_collector_plugin;
 pub mod tc_model;
@@ -126,6 +129,22 @@ impl From<Field> for String {
     }
 }
 
+/// Numbers and strings as such, other types as their display string
+impl From<Field> for serde_json::Value {
+    fn from(field: Field) -> serde_json::Value {
+        match field {
+            Field::U32(v) => v.into(),
+            Field::U64(v) => v.into(),
+            Field::I32(v) => v.into(),
+            Field::I64(v) => v.into(),
+            Field::F32(v) => v.into(),
+            Field::F64(v) => v.into(),
+            Field::Str(v) => v.into(),
+            field => field.to_string().into(),
+        }
+    }
+}
+
 impl From<u32> for Field {
     fn from(v: u32) -> Self {
         Field::U32(v)
@@ -316,11 +335,32 @@ impl fmt::Display for Field {
 pub trait Queriable {
     type FieldId: FieldId<Queriable = Self>;
     fn query(&self, field_id: &Self::FieldId) -> Option<Field>;
+
+    /// FieldIds of the fields of this Model matching `selector`, a FieldId
+    /// string in which `*` selects every item of a container or every field
+    /// of a Model, e.g. `interfaces.*.rx_bytes_per_sec`. Selectors without
+    /// `*` match the FieldId they parse to, if the Model has the field.
+    fn expand_selector(&self, selector: &str) -> Vec<Self::FieldId>;
 }
 
-/// Marker trait to bind FieldId back to Queriable for type inference.
+/// Selector matching every item of a container or every field of a Model
+pub const SELECTOR_WILDCARD: &str = "*";
+
+/// Binds FieldId back to Queriable for type inference, and describes the
+/// field it identifies.
 pub trait FieldId: Sized {
     type Queriable: Queriable<FieldId = Self> + ?Sized;
+
+    /// Description of the field, from the doc comment of the model field
+    fn doc(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Unit of the field value, e.g. "bytes/s". Set with
+    /// #[queriable(unit = "...")] or guessed from the field name.
+    fn unit(&self) -> Option<&'static str> {
+        None
+    }
 }
 
 pub fn sort_queriables<T: Queriable>(queriables: &mut [&T], field_id: &T::FieldId, reverse: bool) {
@@ -348,13 +388,15 @@ pub trait Nameable {
 /// Type that contains sub-queriables of the same type, individually retrieveable
 /// by some index. It is itself a Queriable.
 pub trait QueriableContainer {
-    type Idx;
+    type Idx: Clone + ToString;
     type SubqueryId: FieldId;
     const IDX_PLACEHOLDER: &'static str = "<idx>.";
     fn split(s: &str) -> Option<(&str, &str)> {
         s.split_once('.')
     }
     fn get_item(&self, idx: &Self::Idx) -> Option<&<Self::SubqueryId as FieldId>::Queriable>;
+    /// Indices of all items, in order
+    fn indices(&self) -> Vec<Self::Idx>;
 }
 
 impl<C: QueriableContainer> Queriable for C {
@@ -363,6 +405,33 @@ impl<C: QueriableContainer> Queriable for C {
         self.get_item(field_id.idx.as_ref()?)
             .and_then(|sub| sub.query(&field_id.subquery_id.0))
     }
+
+    fn expand_selector(&self, selector: &str) -> Vec<Self::FieldId> {
+        let (idx_str, subquery_selector) = match selector
+            .strip_prefix(SELECTOR_WILDCARD)
+            .and_then(|rest| rest.strip_prefix('.'))
+        {
+            Some(rest) => (SELECTOR_WILDCARD, rest),
+            None => match C::split(selector) {
+                Some(split) => split,
+                None => return Vec::new(),
+            },
+        };
+        let mut field_ids = Vec::new();
+        for idx in self.indices() {
+            if idx_str != SELECTOR_WILDCARD && idx.to_string() != idx_str {
+                continue;
+            }
+            if let Some(item) = self.get_item(&idx) {
+                field_ids.extend(
+                    item.expand_selector(subquery_selector)
+                        .into_iter()
+                        .map(|subquery_id| Self::FieldId::new(Some(idx.clone()), subquery_id)),
+                );
+            }
+        }
+        field_ids
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -377,6 +446,14 @@ pub struct QueriableContainerFieldId<C: QueriableContainer> {
 
 impl<C: QueriableContainer> FieldId for QueriableContainerFieldId<C> {
     type Queriable = C;
+
+    fn doc(&self) -> Option<&'static str> {
+        self.subquery_id.0.doc()
+    }
+
+    fn unit(&self) -> Option<&'static str> {
+        self.subquery_id.0.unit()
+    }
 }
 
 impl<C: QueriableContainer> QueriableContainerFieldId<C> {
@@ -450,17 +527,23 @@ impl<Q: Queriable> QueriableContainer for Vec<Q> {
     fn get_item(&self, idx: &usize) -> Option<&Q> {
         self.get(*idx)
     }
+    fn indices(&self) -> Vec<usize> {
+        (0..self.len()).collect()
+    }
 }
 
 pub type VecFieldId<Q> = QueriableContainerFieldId<Vec<Q>>;
 
-impl<K: Ord, Q: Queriable> QueriableContainer for BTreeMap<K, Q> {
+impl<K: Ord + Clone + ToString, Q: Queriable> QueriableContainer for BTreeMap<K, Q> {
     type Idx = K;
     type SubqueryId = Q::FieldId;
     const IDX_PLACEHOLDER: &'static str = "<key>.";
     fn get_item(&self, idx: &K) -> Option<&Q> {
         self.get(idx)
     }
+    fn indices(&self) -> Vec<K> {
+        self.keys().cloned().collect()
+    }
 }
 
 pub type BTreeMapFieldId<K, Q> = QueriableContainerFieldId<BTreeMap<K, Q>>;
@@ -490,6 +573,167 @@ pub struct Model {
     pub resctrl: Option<ResctrlModel>,
     #[queriable(subquery)]
     pub tc: Option<TcModel>,
+    /// Sample sections that were not recorded
+    #[queriable(ignore)]
+    #[serde(default)]
+    pub omitted_sections: BTreeSet<SampleSection>,
+    /// Number of samples missing between the previous sample and this one,
+    /// over which the rates of this model are stretched
+    #[queriable(ignore)]
+    #[serde(default)]
+    pub gaps: u64,
+    /// Whether process command lines and exe paths were redacted
+    #[queriable(ignore)]
+    #[serde(default)]
+    pub cmdline_redacted: bool,
+    /// Whether the wall clock stepped right before this sample, in which
+    /// case no rates are computed for it
+    #[queriable(ignore)]
+    #[serde(default)]
+    pub discontinuity: bool,
+    /// Whether the host rebooted between the previous sample and this one,
+    /// in which case no rates are computed for it as counters restarted
+    #[queriable(ignore)]
+    #[serde(default)]
+    pub rebooted: bool,
+    /// Data sources missing on the host, with the reason, by source
+    #[queriable(ignore)]
+    #[serde(default)]
+    pub unavailable_sources: BTreeMap<DataSource, String>,
+    /// Kills by userspace OOM killers since the previous sample
+    #[queriable(ignore)]
+    #[serde(default)]
+    pub oom_kills: Vec<OomKillEvent>,
+}
+
+/// A sample is taken to follow a gap once this many collection intervals
+/// passed since the previous one. Collection itself takes time on top of
+/// the interval, so some slack is needed.
+const GAP_INTERVALS: f64 = 1.5;
+
+/// Number of samples missing between two samples taken `elapsed` apart
+/// with a collection interval of `interval_s`. No gaps are reported if the
+/// interval is unknown.
+pub fn count_gaps(elapsed: Duration, interval_s: Option<u64>) -> u64 {
+    let interval = match interval_s {
+        Some(interval) if interval > 0 => interval as f64,
+        _ => return 0,
+    };
+    let intervals = elapsed.as_secs_f64() / interval;
+    if intervals < GAP_INTERVALS {
+        0
+    } else {
+        std::cmp::max(intervals.round() as u64 - 1, 1)
+    }
+}
+
+/// Whether `sample` was collected in another boot than `last`. Samples
+/// recorded before boot ids were collected can only tell by the kernel
+/// version.
+pub fn rebooted_between(last: &Sample, sample: &Sample) -> bool {
+    match (&last.system.boot_id, &sample.system.boot_id) {
+        (Some(last_id), Some(id)) => last_id != id,
+        _ => matches!(
+            (&last.system.kernel_version, &sample.system.kernel_version),
+            (Some(last_version), Some(version)) if last_version != version
+        ),
+    }
+}
+
+/// A difference between the wall clock and the monotonic clock above this
+/// is taken as a step of the wall clock rather than drift.
+const CLOCK_STEP_THRESHOLD: Duration = Duration::from_secs(2);
+
+/// Step of the wall clock between two samples
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockStep {
+    Forward(Duration),
+    Backward(Duration),
+}
+
+impl fmt::Display for ClockStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Forward(step) => write!(f, "forward by {}s", step.as_secs()),
+            Self::Backward(step) => write!(f, "backward by {}s", step.as_secs()),
+        }
+    }
+}
+
+/// Detects steps of the wall clock between samples, e.g. by NTP, by
+/// comparing how far it moved with how far the monotonic clock did.
+#[derive(Default)]
+pub struct ClockStepDetector {
+    last: Option<(SystemTime, Instant)>,
+}
+
+impl ClockStepDetector {
+    /// Record the wall and monotonic time of a new sample, returning the
+    /// step of the wall clock since the previous one if any.
+    pub fn check(&mut self, wall: SystemTime, monotonic: Instant) -> Option<ClockStep> {
+        let (last_wall, last_monotonic) = self.last.replace((wall, monotonic))?;
+        let elapsed = monotonic.saturating_duration_since(last_monotonic);
+        let step = match wall.duration_since(last_wall) {
+            Ok(wall_elapsed) if wall_elapsed >= elapsed => {
+                ClockStep::Forward(wall_elapsed - elapsed)
+            }
+            Ok(wall_elapsed) => ClockStep::Backward(elapsed - wall_elapsed),
+            Err(e) => ClockStep::Backward(elapsed + e.duration()),
+        };
+        match step {
+            ClockStep::Forward(d) | ClockStep::Backward(d) if d > CLOCK_STEP_THRESHOLD => {
+                Some(step)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Selects which sub-models of a `Model` get materialized. Sub-models that
+/// are not selected are left at their default value, which saves building
+/// them when only part of the model is looked at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ModelParts {
+    pub system: bool,
+    pub cgroup: bool,
+    pub process: bool,
+    pub network: bool,
+    pub gpu: bool,
+    pub resctrl: bool,
+    pub tc: bool,
+    /// D-state counts of the system and cgroup models, which are built from
+    /// the processes of the sample
+    pub dstate: bool,
+}
+
+impl ModelParts {
+    pub const ALL: ModelParts = ModelParts {
+        system: true,
+        cgroup: true,
+        process: true,
+        network: true,
+        gpu: true,
+        resctrl: true,
+        tc: true,
+        dstate: true,
+    };
+
+    pub const NONE: ModelParts = ModelParts {
+        system: false,
+        cgroup: false,
+        process: false,
+        network: false,
+        gpu: false,
+        resctrl: false,
+        tc: false,
+        dstate: false,
+    };
+}
+
+impl Default for ModelParts {
+    fn default() -> Self {
+        Self::ALL
+    }
 }
 
 impl Model {
@@ -497,68 +741,221 @@ impl Model {
     /// `CumulativeSample` as well as the `Duration` since it was
     /// collected.
     pub fn new(timestamp: SystemTime, sample: &Sample, last: Option<(&Sample, Duration)>) -> Self {
+        Self::new_with_parts(timestamp, sample, last, ModelParts::ALL)
+    }
+
+    /// Same as `new`, but only materializes the sub-models selected by
+    /// `parts`.
+    pub fn new_with_parts(
+        timestamp: SystemTime,
+        sample: &Sample,
+        last: Option<(&Sample, Duration)>,
+        parts: ModelParts,
+    ) -> Self {
+        let rebooted = last.is_some_and(|(last, _)| rebooted_between(last, sample));
+        // The time elapsed since the last sample is unknown
+        let last = last.filter(|_| !sample.discontinuity && !rebooted);
         Model {
             time_elapsed: last.map(|(_, d)| d).unwrap_or_default(),
             timestamp,
-            system: SystemModel::new(&sample.system, last.map(|(s, d)| (&s.system, d))),
-            cgroup: CgroupModel::new(
-                "<root>".to_string(),
-                String::new(),
-                0,
-                &sample.cgroup,
-                last.map(|(s, d)| (&s.cgroup, d)),
+            system: if parts.system {
+                Self::build_system(sample, last)
+            } else {
+                Default::default()
+            },
+            cgroup: if parts.cgroup {
+                Self::build_cgroup(sample, last)
+            } else {
+                Default::default()
+            },
+            process: if parts.process {
+                Self::build_process(sample, last)
+            } else {
+                Default::default()
+            },
+            network: if parts.network {
+                Self::build_network(sample, last)
+            } else {
+                Default::default()
+            },
+            gpu: if parts.gpu {
+                Self::build_gpu(sample, last)
+            } else {
+                None
+            },
+            resctrl: if parts.resctrl {
+                Self::build_resctrl(sample, last)
+            } else {
+                None
+            },
+            tc: if parts.tc {
+                Self::build_tc(sample, last)
+            } else {
+                None
+            },
+            omitted_sections: sample.omitted_sections.clone(),
+            cmdline_redacted: sample.cmdline_redacted,
+            discontinuity: sample.discontinuity,
+            rebooted,
+            unavailable_sources: sample.unavailable_sources.clone(),
+            oom_kills: sample.oom_kills.clone(),
+            gaps: last.map_or(0, |(last, elapsed)| {
+                // The interval may have changed between the two samples
+                count_gaps(elapsed, std::cmp::max(sample.interval_s, last.interval_s))
+            }),
+        }
+    }
+
+    fn build_system(sample: &Sample, last: Option<(&Sample, Duration)>) -> SystemModel {
+        let mut model = SystemModel::new(&sample.system, last.map(|(s, d)| (&s.system, d)));
+        model.collection = sample.collection_timing.as_ref().map(|timing| {
+            CollectionModel::new(
+                timing,
+                &sample.bpf_stats,
+                last.map(|(last, _)| &last.bpf_stats),
             )
-            .aggr_top_level_val(),
-            process: ProcessModel::new(&sample.processes, last.map(|(s, d)| (&s.processes, d))),
-            network: {
-                let sample = NetworkStats {
-                    net: &sample.netstats,
-                    ethtool: &sample.ethtool,
+        });
+        if !sample.processes.is_empty() {
+            model.dstate = Some(DStateModel::new(&sample.processes));
+        }
+        model
+    }
+
+    fn build_cgroup(sample: &Sample, last: Option<(&Sample, Duration)>) -> CgroupModel {
+        let mut model = CgroupModel::new(
+            "<root>".to_string(),
+            String::new(),
+            0,
+            &sample.cgroup,
+            last.map(|(s, d)| (&s.cgroup, d)),
+        )
+        .aggr_top_level_val()
+        .fill_shares();
+        if !sample.processes.is_empty() {
+            model = model.fill_dstate(&DStateModel::by_cgroup(&sample.processes));
+        }
+        // Extra roots are shown as top level cgroups named "<label>". They
+        // are left out of the root totals as they may overlap with the main
+        // hierarchy, e.g. a nested container root.
+        for (label, extra) in &sample.extra_cgroups {
+            let name = format!("<{}>", label);
+            let child = CgroupModel::new(
+                name.clone(),
+                format!("/{}", name),
+                1,
+                extra,
+                last.and_then(|(s, d)| s.extra_cgroups.get(label).map(|l| (l, d))),
+            );
+            model.count += child.count;
+            model.children.insert(child);
+        }
+        model
+    }
+
+    fn build_process(sample: &Sample, last: Option<(&Sample, Duration)>) -> ProcessModel {
+        let mut model = ProcessModel::new(
+            &sample.processes,
+            last.map(|(s, d)| (&s.processes, d)),
+            sample.system.stat.boot_time_epoch_secs,
+        );
+        if sample.omitted_sections.contains(&SampleSection::Cmdline) {
+            for spm in model.processes.values_mut() {
+                spm.cmdline = Some(NOT_COLLECTED.to_owned());
+            }
+        }
+        for (pid, stack) in &sample.stacks {
+            if let Some(spm) = model.processes.get_mut(pid) {
+                spm.stack = Some(ProcessStackModel::new(stack));
+            }
+        }
+        if let Some((last, d)) = last {
+            for (pid, net) in &sample.process_net {
+                // Skip pids reused since the last sample
+                let reused = match (sample.processes.get(pid), last.processes.get(pid)) {
+                    (Some(pidinfo), Some(last_pidinfo)) => {
+                        !process::is_same_process(last_pidinfo, pidinfo)
+                    }
+                    _ => false,
                 };
-                let network_stats: NetworkStats;
-
-                let last = if let Some((s, d)) = last {
-                    network_stats = NetworkStats {
-                        net: &s.netstats,
-                        ethtool: &s.ethtool,
-                    };
-                    Some((&network_stats, d))
+                if reused {
+                    continue;
+                }
+                if let (Some(spm), Some(last_net)) =
+                    (model.processes.get_mut(pid), last.process_net.get(pid))
+                {
+                    spm.net = Some(ProcessNetModel::new(last_net, net, d));
+                }
+            }
+        }
+        model
+    }
+
+    fn build_network(sample: &Sample, last: Option<(&Sample, Duration)>) -> NetworkModel {
+        let network_sample = NetworkStats {
+            net: &sample.netstats,
+            ethtool: &sample.ethtool,
+        };
+        let network_stats: NetworkStats;
+
+        let network_last = if let Some((s, d)) = last {
+            network_stats = NetworkStats {
+                net: &s.netstats,
+                ethtool: &s.ethtool,
+            };
+            Some((&network_stats, d))
+        } else {
+            None
+        };
+
+        let mut model = NetworkModel::new(&network_sample, network_last);
+        // Join qdisc drops into the interfaces they were dropped on
+        if let Some(tc) = sample.tc.as_ref() {
+            let last = last.and_then(|(s, d)| s.tc.as_ref().map(|tc| (tc, d)));
+            for (interface, drops) in root_qdisc_drops_per_sec(tc, last) {
+                if let Some(iface) = model.interfaces.get_mut(&interface) {
+                    iface.qdisc_drops_per_sec = Some(drops);
+                }
+            }
+        }
+        model
+    }
+
+    fn build_gpu(sample: &Sample, last: Option<(&Sample, Duration)>) -> Option<GpuModel> {
+        sample.gpus.as_ref().map(|gpus| {
+            GpuModel::new(&gpus.gpu_map, {
+                if let Some((s, d)) = last {
+                    s.gpus.as_ref().map(|g| (&g.gpu_map, d))
                 } else {
                     None
-                };
+                }
+            })
+        })
+    }
 
-                NetworkModel::new(&sample, last)
-            },
-            gpu: sample.gpus.as_ref().map(|gpus| {
-                GpuModel::new(&gpus.gpu_map, {
-                    if let Some((s, d)) = last {
-                        s.gpus.as_ref().map(|g| (&g.gpu_map, d))
-                    } else {
-                        None
-                    }
-                })
-            }),
-            resctrl: sample.resctrl.as_ref().map(|r| {
-                ResctrlModel::new(
-                    r,
-                    if let Some((s, d)) = last {
-                        s.resctrl.as_ref().map(|r| (r, d))
-                    } else {
-                        None
-                    },
-                )
-            }),
-            tc: sample.tc.as_ref().map(|tc| {
-                TcModel::new(
-                    tc,
-                    if let Some((s, d)) = last {
-                        s.tc.as_ref().map(|tc| (tc, d))
-                    } else {
-                        None
-                    },
-                )
-            }),
-        }
+    fn build_resctrl(sample: &Sample, last: Option<(&Sample, Duration)>) -> Option<ResctrlModel> {
+        sample.resctrl.as_ref().map(|r| {
+            ResctrlModel::new(
+                r,
+                if let Some((s, d)) = last {
+                    s.resctrl.as_ref().map(|r| (r, d))
+                } else {
+                    None
+                },
+            )
+        })
+    }
+
+    fn build_tc(sample: &Sample, last: Option<(&Sample, Duration)>) -> Option<TcModel> {
+        sample.tc.as_ref().map(|tc| {
+            TcModel::new(
+                tc,
+                if let Some((s, d)) = last {
+                    s.tc.as_ref().map(|tc| (tc, d))
+                } else {
+                    None
+                },
+            )
+        })
     }
 }
 
@@ -602,6 +999,273 @@ mod tests {
         get_sample_model();
     }
 
+    #[test]
+    fn test_model_parts() {
+        let mut sample = Sample::default();
+        sample.system.hostname = "host".to_string();
+        sample.processes.insert(1, Default::default());
+        let timestamp = SystemTime::now();
+
+        let full = Model::new(timestamp, &sample, None);
+        assert_eq!(full.system.hostname, "host");
+        assert_eq!(full.process.processes.len(), 1);
+        assert_eq!(full.cgroup.data.name, "<root>");
+
+        let parts = ModelParts {
+            cgroup: true,
+            ..ModelParts::NONE
+        };
+        let partial = Model::new_with_parts(timestamp, &sample, None, parts);
+        assert_eq!(partial.system.hostname, "");
+        assert!(partial.process.processes.is_empty());
+        assert_eq!(partial.cgroup.data.name, "<root>");
+    }
+
+    #[test]
+    fn test_omit_sections() {
+        let mut sample = Sample::default();
+        let mut pidinfo = procfs::PidInfo::default();
+        pidinfo.cmdline_vec = Some(vec!["below".to_string(), "record".to_string()]);
+        sample.processes.insert(1, pidinfo);
+        sample
+            .system
+            .slabinfo
+            .insert("dentry".into(), Default::default());
+        sample.tc = Some(Default::default());
+
+        let sections = BTreeSet::from([SampleSection::Cmdline, SampleSection::Tc]);
+        sample.omit_sections(&sections);
+        assert_eq!(sample.processes[&1].cmdline_vec, None);
+        assert_eq!(sample.tc, None);
+        assert_eq!(sample.system.slabinfo.len(), 1);
+        assert_eq!(sample.omitted_sections, sections);
+
+        let model = Model::new(SystemTime::now(), &sample, None);
+        assert_eq!(
+            model.process.processes[&1].cmdline.as_deref(),
+            Some(NOT_COLLECTED)
+        );
+        assert_eq!(model.omitted_sections, sections);
+
+        assert_eq!(
+            "slabinfo".parse::<SampleSection>().expect("bad section"),
+            SampleSection::Slabinfo
+        );
+        assert!("cmdlines".parse::<SampleSection>().is_err());
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let mut sample = Sample::default();
+        sample.unavailable_sources.insert(
+            DataSource::Interfaces,
+            "/sys/class/net not found".to_owned(),
+        );
+        let capabilities = sample.capabilities();
+        assert_eq!(
+            capabilities.len(),
+            enum_iterator::cardinality::<DataSource>()
+        );
+        assert_eq!(capabilities[&DataSource::Cgroup], None);
+        assert_eq!(
+            capabilities[&DataSource::Interfaces].as_deref(),
+            Some("/sys/class/net not found")
+        );
+        assert_eq!(
+            serde_json::to_value(&capabilities).expect("Failed to serialize")["interfaces"],
+            "/sys/class/net not found"
+        );
+
+        let model = Model::new(SystemTime::now(), &sample, None);
+        assert_eq!(model.unavailable_sources, sample.unavailable_sources);
+    }
+
+    #[test]
+    fn test_redact_cmdlines() {
+        let mut sample = Sample::default();
+        let mut pidinfo = procfs::PidInfo::default();
+        pidinfo.cmdline_vec = Some(vec![
+            "mysql".to_string(),
+            "--user=root".to_string(),
+            "--password=hunter2".to_string(),
+        ]);
+        pidinfo.exe_path = Some("/usr/bin/mysql".to_string());
+        sample.processes.insert(1, pidinfo);
+
+        let mut scrubbed = sample.clone();
+        scrubbed.redact_cmdlines(&CmdlineRedaction {
+            argv0_only: false,
+            regex: Some(Regex::new("password=.*").expect("bad regex")),
+        });
+        assert_eq!(
+            scrubbed.processes[&1].cmdline_vec,
+            Some(vec![
+                "mysql".to_string(),
+                "--user=root".to_string(),
+                "--<redacted>".to_string(),
+            ])
+        );
+        assert_eq!(
+            scrubbed.processes[&1].exe_path.as_deref(),
+            Some("/usr/bin/mysql")
+        );
+        assert!(scrubbed.cmdline_redacted);
+
+        sample.redact_cmdlines(&CmdlineRedaction {
+            argv0_only: true,
+            regex: Some(Regex::new("^/usr/bin/").expect("bad regex")),
+        });
+        assert_eq!(
+            sample.processes[&1].cmdline_vec,
+            Some(vec!["mysql".to_string()])
+        );
+        assert_eq!(
+            sample.processes[&1].exe_path.as_deref(),
+            Some("<redacted>mysql")
+        );
+
+        let model = Model::new(SystemTime::now(), &sample, None);
+        assert!(model.cmdline_redacted);
+        assert_eq!(
+            model.process.processes[&1].cmdline.as_deref(),
+            Some("mysql")
+        );
+    }
+
+    #[test]
+    fn test_count_gaps() {
+        assert_eq!(count_gaps(Duration::from_secs(5), Some(5)), 0);
+        assert_eq!(count_gaps(Duration::from_secs(7), Some(5)), 0);
+        assert_eq!(count_gaps(Duration::from_millis(7500), Some(5)), 1);
+        assert_eq!(count_gaps(Duration::from_secs(10), Some(5)), 1);
+        assert_eq!(count_gaps(Duration::from_secs(31), Some(5)), 5);
+        assert_eq!(count_gaps(Duration::from_secs(60), None), 0);
+
+        let mut last = Sample {
+            interval_s: Some(5),
+            ..Default::default()
+        };
+        let sample = last.clone();
+        let model = Model::new(
+            SystemTime::now(),
+            &sample,
+            Some((&last, Duration::from_secs(20))),
+        );
+        assert_eq!(model.gaps, 3);
+        // Recorder switched from a 10s to a 5s interval
+        last.interval_s = Some(10);
+        let model = Model::new(
+            SystemTime::now(),
+            &sample,
+            Some((&last, Duration::from_secs(10))),
+        );
+        assert_eq!(model.gaps, 0);
+    }
+
+    #[test]
+    fn test_clock_step() {
+        let mut detector = ClockStepDetector::default();
+        let wall = SystemTime::now();
+        let monotonic = Instant::now();
+        assert_eq!(detector.check(wall, monotonic), None);
+        let interval = Duration::from_secs(5);
+        // Drift within the threshold
+        assert_eq!(
+            detector.check(
+                wall + interval + Duration::from_secs(1),
+                monotonic + interval
+            ),
+            None
+        );
+        assert_eq!(
+            detector.check(wall + Duration::from_secs(3600), monotonic + interval * 2),
+            Some(ClockStep::Forward(Duration::from_secs(3589)))
+        );
+        assert_eq!(
+            detector.check(wall, monotonic + interval * 3),
+            Some(ClockStep::Backward(Duration::from_secs(3605)))
+        );
+
+        let mut last = Sample::default();
+        last.system.stat.total_cpu = Some(Default::default());
+        let mut sample = last.clone();
+        sample.discontinuity = true;
+        let model = Model::new(
+            SystemTime::now(),
+            &sample,
+            Some((&last, Duration::from_secs(3600))),
+        );
+        assert!(model.discontinuity);
+        assert_eq!(model.time_elapsed, Duration::from_secs(0));
+        assert_eq!(model.gaps, 0);
+        assert_eq!(model.system.total_cpu.usage_pct, None);
+    }
+
+    #[test]
+    fn test_oom_kill_targets() {
+        let kill = OomKillEvent {
+            cgroup: "/system.slice/foo.service".into(),
+            ..Default::default()
+        };
+        assert!(kill.targets("/system.slice/foo.service"));
+        assert!(kill.targets("/system.slice"));
+        // The root
+        assert!(kill.targets(""));
+        assert!(!kill.targets("/system.slice/foo"));
+        assert!(!kill.targets("/system.slice/foo.service/child"));
+    }
+
+    #[test]
+    fn test_reboot() {
+        let cpu = |usec| procfs::CpuStat {
+            user_usec: Some(usec),
+            nice_usec: Some(usec),
+            system_usec: Some(usec),
+            idle_usec: Some(usec),
+            iowait_usec: Some(usec),
+            irq_usec: Some(usec),
+            softirq_usec: Some(usec),
+            stolen_usec: Some(usec),
+            guest_usec: Some(usec),
+            guest_nice_usec: Some(usec),
+        };
+        let mut last = Sample::default();
+        last.system.stat.total_cpu = Some(cpu(1_000_000));
+        last.system.boot_id = Some("a".to_owned());
+        last.system.kernel_version = Some("6.1.0".to_owned());
+        let mut sample = last.clone();
+        sample.system.stat.total_cpu = Some(cpu(2_000_000));
+        let build = |sample: &Sample, last: &Sample| {
+            Model::new(
+                SystemTime::now(),
+                sample,
+                Some((last, Duration::from_secs(5))),
+            )
+        };
+
+        let model = build(&sample, &last);
+        assert!(!model.rebooted);
+        assert!(model.system.total_cpu.usage_pct.is_some());
+
+        // Counters restart from zero after a reboot
+        let mut rebooted = sample.clone();
+        rebooted.system.stat.total_cpu = Some(cpu(1_000));
+        rebooted.system.boot_id = Some("b".to_owned());
+        let model = build(&rebooted, &last);
+        assert!(model.rebooted);
+        assert_eq!(model.time_elapsed, Duration::from_secs(0));
+        assert_eq!(model.system.total_cpu.usage_pct, None);
+        assert_eq!(model.system.boot_id, Some("b".to_owned()));
+
+        // Without boot ids, a new kernel version tells of a reboot
+        last.system.boot_id = None;
+        sample.system.boot_id = None;
+        assert!(!build(&sample, &last).rebooted);
+        rebooted.system.boot_id = None;
+        rebooted.system.kernel_version = Some("6.2.0".to_owned());
+        assert!(build(&rebooted, &last).rebooted);
+    }
+
     #[::below_derive::queriable_derives]
     pub struct TestModel {
         pub msg: String,
@@ -698,4 +1362,318 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_expand_selector() {
+        let model = get_sample_model();
+        let expand = |selector: &str| {
+            model
+                .expand_selector(selector)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            expand("network.interfaces.*.rx_bytes_per_sec"),
+            vec![
+                "network.interfaces.eth0.rx_bytes_per_sec",
+                "network.interfaces.lo.rx_bytes_per_sec",
+            ]
+        );
+        assert_eq!(
+            expand("network.interfaces.lo.rx_bytes_per_sec"),
+            vec!["network.interfaces.lo.rx_bytes_per_sec"]
+        );
+        // `*` does not descend into sub-Models
+        assert_eq!(
+            expand("network.interfaces.lo.*"),
+            enum_iterator::all::<SingleNetModelFieldId>()
+                .map(|field_id| format!("network.interfaces.lo.{}", field_id.to_string()))
+                .filter(|field_id| !field_id.contains(".queues."))
+                .collect::<Vec<_>>()
+        );
+        // Parents come before their children
+        let cgroups = expand("cgroup.*.name");
+        assert_eq!(cgroups[0], "cgroup.path://.name");
+        assert_eq!(cgroups[1], "cgroup.path:/child_a.slice/.name");
+        for field_id in &cgroups {
+            assert!(
+                model
+                    .query(&ModelFieldId::from_str(field_id).unwrap())
+                    .is_some(),
+                "{} not found",
+                field_id
+            );
+        }
+        assert!(expand("network.interfaces.*.no_such_field").is_empty());
+        assert!(expand("network.interfaces.no_such_iface.rx_bytes_per_sec").is_empty());
+    }
+
+    #[test]
+    fn test_hot_processes() {
+        let processes = |cpu_usecs: &[(i32, u64)]| -> procfs::PidMap {
+            cpu_usecs
+                .iter()
+                .map(|(pid, usecs)| {
+                    let mut pidinfo = procfs::PidInfo::default();
+                    pidinfo.stat.user_usecs = Some(*usecs);
+                    pidinfo.stat.system_usecs = Some(0);
+                    (*pid, pidinfo)
+                })
+                .collect()
+        };
+        let mut hot = HotProcesses::default();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        // Nothing is hot without a previous sample
+        assert!(
+            hot.update(&processes(&[(1, 0), (2, 0)]), at(0), 90.0, 2)
+                .is_empty()
+        );
+        // Pid 1 at 100%, pid 2 at 50%
+        assert!(
+            hot.update(&processes(&[(1, 1_000_000), (2, 500_000)]), at(1), 90.0, 2)
+                .is_empty()
+        );
+        assert_eq!(
+            hot.update(
+                &processes(&[(1, 2_000_000), (2, 1_000_000)]),
+                at(2),
+                90.0,
+                2
+            ),
+            vec![1]
+        );
+        // The count restarts after a process was returned
+        assert!(
+            hot.update(
+                &processes(&[(1, 3_000_000), (2, 1_500_000)]),
+                at(3),
+                90.0,
+                2
+            )
+            .is_empty()
+        );
+        assert_eq!(
+            hot.update(
+                &processes(&[(1, 4_000_000), (2, 2_000_000)]),
+                at(4),
+                90.0,
+                2
+            ),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn test_process_stack_model() {
+        let stack = |frames: &[&str], count| StackCount {
+            frames: frames.iter().map(|f| f.to_string()).collect(),
+            count,
+        };
+        let sample = StackSample {
+            duration_ms: 1000,
+            samples: 100,
+            stacks: vec![
+                stack(&["memcpy", "encode", "main"], 40),
+                stack(&["compress", "encode", "main"], 30),
+                stack(&["memcpy", "decode", "main"], 20),
+            ],
+        };
+        let model = ProcessStackModel::new(&sample);
+        assert_eq!(model.samples, Some(100));
+        assert_eq!(model.top_frame.as_deref(), Some("memcpy"));
+        assert_eq!(model.top_frame_pct, Some(60.0));
+        assert_eq!(model.stacks.len(), 3);
+
+        let model = ProcessStackModel::new(&Default::default());
+        assert_eq!(model.top_frame, None);
+    }
+
+    #[test]
+    fn test_collection_timing() {
+        let mut sample = Sample::default();
+        let model = Model::new(SystemTime::now(), &sample, None);
+        assert!(model.system.collection.is_none());
+
+        sample.collection_timing = Some(CollectionTiming {
+            cgroup_usecs: 1500,
+            process_usecs: 30000,
+            total_usecs: 40000,
+            ..Default::default()
+        });
+        let model = Model::new(SystemTime::now(), &sample, None);
+        assert_eq!(
+            model.query(
+                &ModelFieldId::from_str("system.collection.process_usecs")
+                    .expect("Failed to parse field id")
+            ),
+            Some(Field::U64(30000))
+        );
+        assert_eq!(
+            model.system.collection.and_then(|c| c.total_usecs),
+            Some(40000)
+        );
+    }
+
+    #[test]
+    fn test_bpf_stats() {
+        let sample = |lost_events, run_time_ns| {
+            let mut sample = Sample {
+                collection_timing: Some(Default::default()),
+                ..Default::default()
+            };
+            sample.bpf_stats.insert(
+                "exitstat".to_owned(),
+                BpfProgramStat {
+                    lost_events,
+                    attach_failures: 0,
+                    run_time_ns,
+                    fallback: None,
+                },
+            );
+            sample.bpf_stats.insert(
+                "fileio".to_owned(),
+                BpfProgramStat {
+                    attach_failures: 1,
+                    ..Default::default()
+                },
+            );
+            sample
+        };
+
+        let model = Model::new(SystemTime::now(), &sample(10, Some(1_000_000)), None);
+        let collection = model.system.collection.expect("No collection model");
+        assert_eq!(collection.bpf_lost_events, None);
+        assert_eq!(collection.bpf_attach_failures, Some(1));
+        assert_eq!(collection.bpf_prog_usecs, None);
+        assert_eq!(collection.exitstat_source.as_deref(), Some("bpf"));
+
+        let mut fell_back = sample(10, None);
+        fell_back.bpf_stats.get_mut("exitstat").unwrap().fallback = Some("taskstats".to_owned());
+        let model = Model::new(SystemTime::now(), &fell_back, None);
+        let collection = model.system.collection.expect("No collection model");
+        assert_eq!(collection.exitstat_source.as_deref(), Some("taskstats"));
+
+        let last = sample(10, Some(1_000_000));
+        let model = Model::new(
+            SystemTime::now(),
+            &sample(25, Some(3_500_000)),
+            Some((&last, Duration::from_secs(5))),
+        );
+        let collection = model.system.collection.expect("No collection model");
+        assert_eq!(collection.bpf_lost_events, Some(15));
+        assert_eq!(collection.bpf_prog_usecs, Some(2_500));
+
+        // Run time is unknown if bpf_stats_enabled was not set throughout
+        let model = Model::new(
+            SystemTime::now(),
+            &sample(25, None),
+            Some((&last, Duration::from_secs(5))),
+        );
+        let collection = model.system.collection.expect("No collection model");
+        assert_eq!(collection.bpf_prog_usecs, None);
+
+        let model = Model::new(SystemTime::now(), &Sample::default(), None);
+        assert!(model.system.collection.is_none());
+    }
+
+    #[test]
+    fn test_process_net_model() {
+        let sample = |rx_bytes, tx_bytes| {
+            let mut sample = Sample::default();
+            sample.processes.insert(1, Default::default());
+            sample.processes.insert(2, Default::default());
+            sample.process_net.insert(
+                1,
+                ProcessNetStat {
+                    rx_bytes,
+                    tx_bytes,
+                    interfaces: Default::default(),
+                },
+            );
+            sample
+        };
+        let last = sample(1000, 500);
+        let model = Model::new(
+            SystemTime::now(),
+            &sample(3000, 1500),
+            Some((&last, Duration::from_secs(2))),
+        );
+        let net = model.process.processes[&1].net.as_ref().unwrap();
+        assert_eq!(net.rx_bytes_per_sec, Some(1000.0));
+        assert_eq!(net.tx_bytes_per_sec, Some(500.0));
+        assert_eq!(model.process.processes[&2].net, None);
+
+        // No rates without a previous sample
+        let model = Model::new(SystemTime::now(), &last, None);
+        assert_eq!(model.process.processes[&1].net, None);
+
+        // No rates if the pid was reused by a new process in between
+        let mut reused = sample(3000, 1500);
+        reused
+            .processes
+            .get_mut(&1)
+            .unwrap()
+            .stat
+            .start_secs_since_boot = Some(100);
+        let mut last = last;
+        last.processes
+            .get_mut(&1)
+            .unwrap()
+            .stat
+            .start_secs_since_boot = Some(50);
+        let model = Model::new(
+            SystemTime::now(),
+            &reused,
+            Some((&last, Duration::from_secs(2))),
+        );
+        assert_eq!(model.process.processes[&1].net, None);
+    }
+
+    #[test]
+    fn test_field_doc_and_unit() {
+        let field_id = SingleCgroupModelFieldId::from_str("io.cost_indebt_pct").unwrap();
+        assert!(field_id.doc().unwrap().contains("iocost debt"));
+        assert_eq!(field_id.unit(), Some("%"));
+        // Nested subqueries and containers end at the same field
+        let field_id =
+            ModelFieldId::from_str("cgroup.path:/system.slice/.io_details.sda.rbytes_per_sec")
+                .unwrap();
+        assert_eq!(field_id.doc(), Some("Bytes read"));
+        assert_eq!(field_id.unit(), Some("bytes/s"));
+        let field_id = SingleCgroupModelFieldId::from_str("name").unwrap();
+        assert_eq!(field_id.doc(), None);
+        assert_eq!(field_id.unit(), None);
+    }
+
+    #[test]
+    fn test_extra_cgroup_roots() {
+        let cgroup = |memory_current| CgroupSample {
+            memory_current: Some(memory_current),
+            ..Default::default()
+        };
+        let mut sample = Sample::default();
+        sample.cgroup.children = Some(BTreeMap::from([("system.slice".to_owned(), cgroup(100))]));
+        let mut extra = cgroup(40);
+        extra.children = Some(BTreeMap::from([("sandbox.scope".to_owned(), cgroup(40))]));
+        sample.extra_cgroups.insert("nested".to_owned(), extra);
+
+        let model = Model::new(SystemTime::now(), &sample, None);
+        assert_eq!(model.cgroup.count, 4);
+        let names = model
+            .cgroup
+            .children
+            .iter()
+            .map(|child| child.data.full_path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["/<nested>", "/system.slice"]);
+        // The extra root does not add to the root totals
+        assert_eq!(model.cgroup.data.memory.as_ref().unwrap().total, Some(100));
+        let query = |id: &str| model.query(&ModelFieldId::from_str(id).unwrap());
+        assert_eq!(
+            query("cgroup.path:/<nested>/sandbox.scope/.mem.total"),
+            Some(Field::U64(40))
+        );
+    }
 }
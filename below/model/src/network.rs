@@ -32,6 +32,12 @@ pub struct NetworkModel {
     pub udp: UdpModel,
     #[queriable(subquery)]
     pub udp6: Udp6Model,
+    #[queriable(subquery)]
+    #[serde(default)]
+    pub sockstat: SockStatModel,
+    #[queriable(subquery)]
+    #[serde(default)]
+    pub sockstat6: SockStat6Model,
 }
 
 impl NetworkModel {
@@ -145,6 +151,12 @@ impl NetworkModel {
                     n.udp6.as_ref().map(|n| (n, d))
                 }),
             ),
+            sockstat: SockStatModel::new(
+                sample.net.sockstat.as_ref().unwrap_or(&Default::default()),
+            ),
+            sockstat6: SockStat6Model::new(
+                sample.net.sockstat6.as_ref().unwrap_or(&Default::default()),
+            ),
         }
     }
 }
@@ -400,9 +412,74 @@ impl Udp6Model {
     }
 }
 
+/// Sockets in use per protocol, from /proc/net/sockstat. Growing counts
+/// point at socket leaks the packet counters do not show.
+#[::below_derive::queriable_derives]
+pub struct SockStatModel {
+    pub sockets_used: Option<u64>,
+    pub tcp_inuse: Option<u64>,
+    pub tcp_orphan: Option<u64>,
+    pub tcp_tw: Option<u64>,
+    pub tcp_alloc: Option<u64>,
+    /// In pages
+    pub tcp_mem: Option<u64>,
+    pub udp_inuse: Option<u64>,
+    /// In pages
+    pub udp_mem: Option<u64>,
+    pub raw_inuse: Option<u64>,
+    pub frag_inuse: Option<u64>,
+    pub frag_memory_bytes: Option<u64>,
+}
+
+impl SockStatModel {
+    pub fn new(sample: &procfs::SockStat) -> SockStatModel {
+        SockStatModel {
+            sockets_used: sample.sockets_used,
+            tcp_inuse: sample.tcp_inuse,
+            tcp_orphan: sample.tcp_orphan,
+            tcp_tw: sample.tcp_tw,
+            tcp_alloc: sample.tcp_alloc,
+            tcp_mem: sample.tcp_mem,
+            udp_inuse: sample.udp_inuse,
+            udp_mem: sample.udp_mem,
+            raw_inuse: sample.raw_inuse,
+            frag_inuse: sample.frag_inuse,
+            frag_memory_bytes: sample.frag_memory,
+        }
+    }
+}
+
+/// IPv6 sockets in use per protocol, from /proc/net/sockstat6
+#[::below_derive::queriable_derives]
+pub struct SockStat6Model {
+    pub tcp_inuse: Option<u64>,
+    pub udp_inuse: Option<u64>,
+    pub raw_inuse: Option<u64>,
+    pub frag_inuse: Option<u64>,
+    pub frag_memory_bytes: Option<u64>,
+}
+
+impl SockStat6Model {
+    pub fn new(sample: &procfs::SockStat6) -> SockStat6Model {
+        SockStat6Model {
+            tcp_inuse: sample.tcp_inuse,
+            udp_inuse: sample.udp_inuse,
+            raw_inuse: sample.raw_inuse,
+            frag_inuse: sample.frag_inuse,
+            frag_memory_bytes: sample.frag_memory,
+        }
+    }
+}
+
 #[::below_derive::queriable_derives]
 pub struct SingleNetModel {
     pub interface: String,
+    pub operstate: Option<String>,
+    pub speed_mbps: Option<u64>,
+    pub duplex: Option<String>,
+    pub carrier_changes: Option<u64>,
+    /// Carrier changes since the previous sample, i.e. link flaps
+    pub carrier_changes_delta: Option<u64>,
     pub rx_bytes_per_sec: Option<f64>,
     pub tx_bytes_per_sec: Option<f64>,
     pub throughput_per_sec: Option<f64>,
@@ -433,6 +510,8 @@ pub struct SingleNetModel {
     pub tx_packets: Option<u64>,
     pub tx_window_errors: Option<u64>,
     pub tx_timeout_per_sec: Option<u64>,
+    /// Packets dropped per second by the root egress qdisc of the interface
+    pub qdisc_drops_per_sec: Option<u64>,
     pub raw_stats: BTreeMap<String, u64>,
 
     #[queriable(subquery)]
@@ -506,6 +585,15 @@ impl SingleNetModel {
         net_model.tx_heartbeat_errors = sample.tx_heartbeat_errors;
         net_model.tx_packets = sample.tx_packets;
         net_model.tx_window_errors = sample.tx_window_errors;
+        net_model.operstate = sample.operstate.clone();
+        net_model.speed_mbps = sample.speed;
+        net_model.duplex = sample.duplex.clone();
+        net_model.carrier_changes = sample.carrier_changes;
+        net_model.carrier_changes_delta =
+            last.and_then(|(l, _)| match (l.carrier_changes, sample.carrier_changes) {
+                (Some(begin), Some(end)) if begin <= end => Some(end - begin),
+                _ => None,
+            });
     }
 
     fn add_ethtool_stats(
@@ -804,4 +892,43 @@ mod test {
         let queue_raw_stat = queue_model.raw_stats.get("stat3").unwrap();
         assert_eq!(*queue_raw_stat, 13);
     }
+
+    #[test]
+    fn test_link_state() {
+        let iface = |carrier_changes| procfs::InterfaceStat {
+            operstate: Some("up".to_owned()),
+            speed: Some(10000),
+            duplex: Some("full".to_owned()),
+            carrier_changes: Some(carrier_changes),
+            ..Default::default()
+        };
+        let l_net_stats = procfs::NetStat {
+            interfaces: Some(BTreeMap::from([("eth0".to_owned(), iface(3))])),
+            ..Default::default()
+        };
+        let s_net_stats = procfs::NetStat {
+            interfaces: Some(BTreeMap::from([("eth0".to_owned(), iface(5))])),
+            ..Default::default()
+        };
+        let prev_sample = NetworkStats {
+            net: &l_net_stats,
+            ethtool: &None,
+        };
+        let sample = NetworkStats {
+            net: &s_net_stats,
+            ethtool: &None,
+        };
+
+        let model = NetworkModel::new(&sample, None);
+        let iface_model = model.interfaces.get("eth0").unwrap();
+        assert_eq!(iface_model.operstate.as_deref(), Some("up"));
+        assert_eq!(iface_model.speed_mbps, Some(10000));
+        assert_eq!(iface_model.duplex.as_deref(), Some("full"));
+        assert_eq!(iface_model.carrier_changes, Some(5));
+        assert_eq!(iface_model.carrier_changes_delta, None);
+
+        let model = NetworkModel::new(&sample, Some((&prev_sample, Duration::from_secs(1))));
+        let iface_model = model.interfaces.get("eth0").unwrap();
+        assert_eq!(iface_model.carrier_changes_delta, Some(2));
+    }
 }
@@ -37,7 +37,11 @@ pub struct ProcessModel {
 }
 
 impl ProcessModel {
-    pub fn new(sample: &procfs::PidMap, last: Option<(&procfs::PidMap, Duration)>) -> ProcessModel {
+    pub fn new(
+        sample: &procfs::PidMap,
+        last: Option<(&procfs::PidMap, Duration)>,
+        boot_time_epoch_secs: Option<u64>,
+    ) -> ProcessModel {
         let mut processes: BTreeMap<i32, SingleProcessModel> = BTreeMap::new();
 
         for (pid, pidinfo) in sample.iter() {
@@ -45,7 +49,12 @@ impl ProcessModel {
                 *pid,
                 SingleProcessModel::new(
                     &pidinfo,
-                    last.and_then(|(p, d)| p.get(pid).map(|p| (p, d))),
+                    last.and_then(|(p, d)| {
+                        p.get(pid)
+                            .filter(|p| is_same_process(p, pidinfo))
+                            .map(|p| (p, d))
+                    }),
+                    boot_time_epoch_secs,
                 ),
             );
         }
@@ -54,6 +63,20 @@ impl ProcessModel {
     }
 }
 
+/// Whether `last` and `sample` of the same pid are the same process, rather
+/// than the pid being reused in between. Processes are told apart by their
+/// start time. Without it, e.g. in older samples, they are assumed to be the
+/// same.
+pub(crate) fn is_same_process(last: &procfs::PidInfo, sample: &procfs::PidInfo) -> bool {
+    match (
+        last.stat.start_secs_since_boot,
+        sample.stat.start_secs_since_boot,
+    ) {
+        (Some(last_start), Some(start)) => last_start == start,
+        _ => true,
+    }
+}
+
 impl Nameable for ProcessModel {
     fn name() -> &'static str {
         "process"
@@ -68,6 +91,7 @@ pub struct SingleProcessModel {
     pub comm: Option<String>,
     pub state: Option<procfs::PidState>,
     pub uptime_secs: Option<u64>,
+    pub start_time_epoch_secs: Option<u64>,
     pub cgroup: Option<String>,
     #[queriable(subquery)]
     pub io: Option<ProcessIoModel>,
@@ -77,12 +101,19 @@ pub struct SingleProcessModel {
     pub cpu: Option<ProcessCpuModel>,
     pub cmdline: Option<String>,
     pub exe_path: Option<String>,
+    /// Only set in samples the process was stack sampled in
+    #[queriable(subquery)]
+    pub stack: Option<ProcessStackModel>,
+    /// Only set with enable_process_net_stats
+    #[queriable(subquery)]
+    pub net: Option<ProcessNetModel>,
 }
 
 impl SingleProcessModel {
     fn new(
         sample: &procfs::PidInfo,
         last: Option<(&procfs::PidInfo, Duration)>,
+        boot_time_epoch_secs: Option<u64>,
     ) -> SingleProcessModel {
         SingleProcessModel {
             pid: sample.stat.pid,
@@ -96,6 +127,9 @@ impl SingleProcessModel {
             comm: sample.stat.comm.clone(),
             state: sample.stat.state.clone(),
             uptime_secs: sample.stat.running_secs.map(|s| s as u64),
+            start_time_epoch_secs: boot_time_epoch_secs
+                .zip(sample.stat.start_secs_since_boot)
+                .map(|(boot, start)| boot + start),
             cgroup: Some(sample.cgroup.clone()),
             io: last.map(|(l, d)| ProcessIoModel::new(&l.io, &sample.io, d)),
             mem: last.map(|(l, d)| ProcessMemoryModel::new(&l, &sample, d)),
@@ -106,6 +140,8 @@ impl SingleProcessModel {
                 Some("?".into())
             },
             exe_path: sample.exe_path.clone(),
+            stack: None,
+            net: None,
         }
     }
 
@@ -120,12 +156,15 @@ impl SingleProcessModel {
             state: None,
             // 80% sure it should be None here. Don't know what someone can infer from summed uptime
             uptime_secs: None,
+            start_time_epoch_secs: None,
             cgroup: None,
             io: fold_optionals!(&left.io, &right.io, ProcessIoModel::fold),
             mem: fold_optionals!(&left.mem, &right.mem, ProcessMemoryModel::fold),
             cpu: fold_optionals!(&left.cpu, &right.cpu, ProcessCpuModel::fold),
             cmdline: None,
             exe_path: None,
+            stack: None,
+            net: fold_optionals!(&left.net, &right.net, ProcessNetModel::fold),
         }
     }
 }
@@ -167,12 +206,68 @@ impl ProcessIoModel {
     }
 }
 
+/// Traffic over IP sockets of a process, as traced with BPF. Includes
+/// loopback traffic.
+#[::below_derive::queriable_derives]
+pub struct ProcessNetModel {
+    pub rx_bytes_per_sec: Option<f64>,
+    pub tx_bytes_per_sec: Option<f64>,
+    /// Bytes received and sent per second by interface name, for the
+    /// traffic attributed to an interface
+    #[queriable(ignore)]
+    pub interface_bytes_per_sec: BTreeMap<String, f64>,
+}
+
+impl ProcessNetModel {
+    pub fn new(begin: &ProcessNetStat, end: &ProcessNetStat, delta: Duration) -> ProcessNetModel {
+        ProcessNetModel {
+            rx_bytes_per_sec: count_per_sec!(Some(begin.rx_bytes), Some(end.rx_bytes), delta),
+            tx_bytes_per_sec: count_per_sec!(Some(begin.tx_bytes), Some(end.tx_bytes), delta),
+            interface_bytes_per_sec: end
+                .interfaces
+                .iter()
+                .filter_map(|(name, end)| {
+                    let begin = begin.interfaces.get(name).cloned().unwrap_or_default();
+                    count_per_sec!(
+                        Some(begin.rx_bytes + begin.tx_bytes),
+                        Some(end.rx_bytes + end.tx_bytes),
+                        delta
+                    )
+                    .map(|rate| (name.clone(), rate))
+                })
+                .collect(),
+        }
+    }
+
+    /// See `SingleProcessModel::fold`
+    pub fn fold(left: &ProcessNetModel, right: &ProcessNetModel) -> ProcessNetModel {
+        let mut interface_bytes_per_sec = left.interface_bytes_per_sec.clone();
+        for (name, rate) in &right.interface_bytes_per_sec {
+            *interface_bytes_per_sec.entry(name.clone()).or_default() += rate;
+        }
+        ProcessNetModel {
+            rx_bytes_per_sec: fold_optionals!(left.rx_bytes_per_sec, right.rx_bytes_per_sec),
+            tx_bytes_per_sec: fold_optionals!(left.tx_bytes_per_sec, right.tx_bytes_per_sec),
+            interface_bytes_per_sec,
+        }
+    }
+
+    /// Whether the process had traffic on the interface `name`
+    pub fn uses_interface(&self, name: &str) -> bool {
+        self.interface_bytes_per_sec
+            .get(name)
+            .is_some_and(|rate| *rate > 0.0)
+    }
+}
+
 #[::below_derive::queriable_derives]
 pub struct ProcessCpuModel {
     pub usage_pct: Option<f64>,
     pub user_pct: Option<f64>,
     pub system_pct: Option<f64>,
     pub num_threads: Option<u64>,
+    /// User and system CPU time since the process started
+    pub total_usecs: Option<u64>,
 }
 
 impl ProcessCpuModel {
@@ -185,6 +280,7 @@ impl ProcessCpuModel {
             user_pct,
             system_pct,
             num_threads: end.num_threads.map(|t| t as u64),
+            total_usecs: collector::opt_add(end.user_usecs, end.system_usecs),
         }
     }
 
@@ -195,6 +291,7 @@ impl ProcessCpuModel {
             user_pct: fold_optionals!(left.user_pct, right.user_pct),
             system_pct: fold_optionals!(left.system_pct, right.system_pct),
             num_threads: fold_optionals!(left.num_threads, right.num_threads),
+            total_usecs: fold_optionals!(left.total_usecs, right.total_usecs),
         }
     }
 }
@@ -212,7 +309,16 @@ pub struct ProcessMemoryModel {
     pub shmem: Option<u64>,
     pub pte: Option<u64>,
     pub swap: Option<u64>,
+    /// Change rate of swap usage in bytes/s, positive while the process is
+    /// being swapped out and negative while it is swapped back in
+    pub swap_growth_per_sec: Option<f64>,
     pub huge_tlb: Option<u64>,
+    // Below are only collected with smaps_rollup stats enabled
+    pub pss: Option<u64>,
+    pub shared: Option<u64>,
+    pub private: Option<u64>,
+    pub anon_thp: Option<u64>,
+    pub swap_pss: Option<u64>,
 }
 
 impl ProcessMemoryModel {
@@ -229,7 +335,24 @@ impl ProcessMemoryModel {
             shmem: end.status.shmem,
             pte: end.status.pte,
             swap: end.status.swap,
+            swap_growth_per_sec: match (begin.status.swap, end.status.swap) {
+                (Some(begin), Some(end)) if !delta.is_zero() => {
+                    Some((end as f64 - begin as f64) / delta.as_secs_f64())
+                }
+                _ => None,
+            },
             huge_tlb: end.status.huge_tlb,
+            pss: end.smaps_rollup.as_ref().and_then(|r| r.pss),
+            shared: end
+                .smaps_rollup
+                .as_ref()
+                .and_then(|r| collector::opt_add(r.shared_clean, r.shared_dirty)),
+            private: end
+                .smaps_rollup
+                .as_ref()
+                .and_then(|r| collector::opt_add(r.private_clean, r.private_dirty)),
+            anon_thp: end.smaps_rollup.as_ref().and_then(|r| r.anon_huge_pages),
+            swap_pss: end.smaps_rollup.as_ref().and_then(|r| r.swap_pss),
         }
     }
 
@@ -253,7 +376,16 @@ impl ProcessMemoryModel {
             shmem: fold_optionals!(left.shmem, right.shmem),
             pte: fold_optionals!(left.pte, right.pte),
             swap: fold_optionals!(left.swap, right.swap),
+            swap_growth_per_sec: fold_optionals!(
+                left.swap_growth_per_sec,
+                right.swap_growth_per_sec
+            ),
             huge_tlb: fold_optionals!(left.huge_tlb, right.huge_tlb),
+            pss: fold_optionals!(left.pss, right.pss),
+            shared: fold_optionals!(left.shared, right.shared),
+            private: fold_optionals!(left.private, right.private),
+            anon_thp: fold_optionals!(left.anon_thp, right.anon_thp),
+            swap_pss: fold_optionals!(left.swap_pss, right.swap_pss),
         }
     }
 }
@@ -280,4 +412,289 @@ mod test {
             Some(Field::Str("systemd".to_owned()))
         );
     }
+
+    #[test]
+    fn memory_fault_rates() {
+        let mut begin = procfs::PidInfo::default();
+        begin.stat.minflt = Some(1000);
+        begin.stat.majflt = Some(10);
+        let mut end = begin.clone();
+        end.stat.minflt = Some(1500);
+        end.stat.majflt = Some(30);
+
+        let model = ProcessMemoryModel::new(&begin, &end, Duration::from_secs(5));
+        assert_eq!(model.minorfaults_per_sec, Some(100.0));
+        assert_eq!(model.majorfaults_per_sec, Some(4.0));
+
+        // Counters going backwards, e.g. after pid reuse, give no rate
+        let model = ProcessMemoryModel::new(&end, &begin, Duration::from_secs(5));
+        assert_eq!(model.minorfaults_per_sec, None);
+        assert_eq!(model.majorfaults_per_sec, None);
+    }
+
+    #[test]
+    fn swap_growth() {
+        let mut begin = procfs::PidInfo::default();
+        begin.status.swap = Some(1 << 20);
+        let mut end = begin.clone();
+        end.status.swap = Some(11 << 20);
+
+        let model = ProcessMemoryModel::new(&begin, &end, Duration::from_secs(5));
+        assert_eq!(model.swap, Some(11 << 20));
+        assert_eq!(model.swap_growth_per_sec, Some(2097152.0));
+        // Swapped back in
+        let model = ProcessMemoryModel::new(&end, &begin, Duration::from_secs(5));
+        assert_eq!(model.swap_growth_per_sec, Some(-2097152.0));
+    }
+
+    #[test]
+    fn memory_smaps_rollup() {
+        let begin = procfs::PidInfo::default();
+        let model = ProcessMemoryModel::new(&begin, &begin, Duration::from_secs(5));
+        assert_eq!(model.pss, None);
+        assert_eq!(model.shared, None);
+
+        let mut end = begin.clone();
+        end.smaps_rollup = Some(procfs::PidSmapsRollup {
+            pss: Some(300),
+            shared_clean: Some(100),
+            shared_dirty: Some(20),
+            private_clean: Some(30),
+            anon_huge_pages: Some(2048),
+            swap_pss: Some(40),
+            ..Default::default()
+        });
+        let model = ProcessMemoryModel::new(&begin, &end, Duration::from_secs(5));
+        assert_eq!(model.pss, Some(300));
+        assert_eq!(model.shared, Some(120));
+        assert_eq!(model.private, Some(30));
+        assert_eq!(model.anon_thp, Some(2048));
+        assert_eq!(model.swap_pss, Some(40));
+    }
+
+    #[test]
+    fn start_time_and_cpu_time() {
+        let mut begin = procfs::PidInfo::default();
+        begin.stat.start_secs_since_boot = Some(100);
+        begin.stat.user_usecs = Some(3_000_000);
+        begin.stat.system_usecs = Some(1_000_000);
+        let mut end = begin.clone();
+        end.stat.user_usecs = Some(4_000_000);
+
+        let model = SingleProcessModel::new(
+            &end,
+            Some((&begin, Duration::from_secs(5))),
+            Some(1_700_000_000),
+        );
+        assert_eq!(model.start_time_epoch_secs, Some(1_700_000_100));
+        assert_eq!(model.cpu.and_then(|cpu| cpu.total_usecs), Some(5_000_000));
+
+        // No boot time, e.g. in samples from before it was collected
+        let model = SingleProcessModel::new(&end, None, None);
+        assert_eq!(model.start_time_epoch_secs, None);
+    }
+
+    #[test]
+    fn pid_reuse() {
+        let process = |start_secs_since_boot, user_usecs| {
+            let mut pidinfo = procfs::PidInfo::default();
+            pidinfo.stat.start_secs_since_boot = start_secs_since_boot;
+            pidinfo.stat.user_usecs = Some(user_usecs);
+            pidinfo
+        };
+        let last: procfs::PidMap = [
+            (1, process(Some(10), 1_000_000)),
+            (2, process(Some(20), 1_000_000)),
+            (3, process(None, 1_000_000)),
+        ]
+        .into_iter()
+        .collect();
+        // Pid 2 exited and was reused by a process that started later
+        let sample: procfs::PidMap = [
+            (1, process(Some(10), 2_000_000)),
+            (2, process(Some(90), 500_000)),
+            (3, process(Some(30), 2_000_000)),
+        ]
+        .into_iter()
+        .collect();
+
+        let model = ProcessModel::new(&sample, Some((&last, Duration::from_secs(1))), None);
+        let user_pct = |pid| {
+            model.processes[&pid]
+                .cpu
+                .as_ref()
+                .and_then(|cpu| cpu.user_pct)
+        };
+        assert_eq!(user_pct(1), Some(100.0));
+        // Not diffed against the old process
+        assert_eq!(model.processes[&2].cpu, None);
+        assert_eq!(model.processes[&2].io, None);
+        // No start time to tell them apart
+        assert_eq!(user_pct(3), Some(100.0));
+    }
+
+    #[test]
+    fn dstate_by_cgroup() {
+        let process = |state, running_secs, cgroup: &str| {
+            let mut pidinfo = procfs::PidInfo::default();
+            pidinfo.stat.state = Some(state);
+            pidinfo.stat.running_secs = Some(running_secs);
+            pidinfo.cgroup = cgroup.to_owned();
+            pidinfo
+        };
+        let processes: procfs::PidMap = [
+            (1, process(procfs::PidState::Sleeping, 900, "/init.scope")),
+            (
+                2,
+                process(procfs::PidState::UninterruptibleSleep, 30, "/a/b"),
+            ),
+            (3, process(procfs::PidState::UninterruptibleSleep, 60, "/a")),
+            (
+                4,
+                process(procfs::PidState::UninterruptibleSleep, 10, "/c/"),
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        let total = DStateModel::new(&processes);
+        assert_eq!(total.count, Some(3));
+        assert_eq!(total.max_uptime_secs, Some(60));
+
+        let by_cgroup = DStateModel::by_cgroup(&processes);
+        assert_eq!(
+            by_cgroup.keys().collect::<Vec<_>>(),
+            vec!["", "/a", "/a/b", "/c"]
+        );
+        assert_eq!(by_cgroup[""], total);
+        assert_eq!(by_cgroup["/a"].count, Some(2));
+        assert_eq!(by_cgroup["/a"].max_uptime_secs, Some(60));
+        assert_eq!(by_cgroup["/a/b"].count, Some(1));
+        assert_eq!(by_cgroup["/a/b"].max_uptime_secs, Some(30));
+        assert_eq!(by_cgroup["/c"].count, Some(1));
+    }
+
+    #[test]
+    fn net_interface_rates() {
+        let stat = |interfaces: &[(&str, u64, u64)]| ProcessNetStat {
+            rx_bytes: 0,
+            tx_bytes: 0,
+            interfaces: interfaces
+                .iter()
+                .map(|(name, rx_bytes, tx_bytes)| {
+                    (
+                        name.to_string(),
+                        ProcessNetIfaceStat {
+                            rx_bytes: *rx_bytes,
+                            tx_bytes: *tx_bytes,
+                        },
+                    )
+                })
+                .collect(),
+        };
+        let begin = stat(&[("eth0", 100, 100), ("eth1", 50, 0)]);
+        let end = stat(&[("eth0", 300, 200), ("eth1", 50, 0), ("lo", 10, 10)]);
+        let model = ProcessNetModel::new(&begin, &end, Duration::from_secs(2));
+        assert_eq!(model.interface_bytes_per_sec["eth0"], 150.0);
+        assert_eq!(model.interface_bytes_per_sec["eth1"], 0.0);
+        // First seen on the interface since the previous sample
+        assert_eq!(model.interface_bytes_per_sec["lo"], 10.0);
+        assert!(model.uses_interface("eth0"));
+        assert!(!model.uses_interface("eth1"));
+        assert!(!model.uses_interface("eth2"));
+
+        let folded = ProcessNetModel::fold(&model, &model);
+        assert_eq!(folded.interface_bytes_per_sec["eth0"], 300.0);
+        assert_eq!(folded.interface_bytes_per_sec["lo"], 20.0);
+    }
+}
+
+/// Stacks sampled from a process that stayed hot for several samples
+#[::below_derive::queriable_derives]
+pub struct ProcessStackModel {
+    pub samples: Option<u64>,
+    /// Innermost frame seen the most across the kept stacks, i.e. where the
+    /// process spent most of its time
+    pub top_frame: Option<String>,
+    pub top_frame_pct: Option<f64>,
+    /// Most sampled stacks, most sampled first
+    #[queriable(ignore)]
+    pub stacks: Vec<StackCount>,
+}
+
+impl ProcessStackModel {
+    pub fn new(sample: &StackSample) -> ProcessStackModel {
+        let mut frame_counts: BTreeMap<&str, u64> = BTreeMap::new();
+        for stack in &sample.stacks {
+            if let Some(frame) = stack.frames.first() {
+                *frame_counts.entry(frame).or_default() += stack.count;
+            }
+        }
+        let top_frame = frame_counts
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .filter(|_| sample.samples > 0);
+        ProcessStackModel {
+            samples: Some(sample.samples),
+            top_frame: top_frame.map(|(frame, _)| frame.to_owned()),
+            top_frame_pct: top_frame.map(|(_, count)| count as f64 * 100.0 / sample.samples as f64),
+            stacks: sample.stacks.clone(),
+        }
+    }
+}
+
+/// Processes in uninterruptible sleep (D state). A pileup of them is a
+/// common sign of stuck I/O or lock contention.
+#[::below_derive::queriable_derives]
+pub struct DStateModel {
+    pub count: Option<u64>,
+    /// Uptime of the longest running process in D state. When a process
+    /// entered the state is not exposed, so this is an upper bound on how
+    /// long it has been blocked.
+    pub max_uptime_secs: Option<u64>,
+}
+
+impl DStateModel {
+    /// Processes in D state among `processes`
+    pub fn new(processes: &procfs::PidMap) -> DStateModel {
+        processes
+            .values()
+            .filter(|pidinfo| is_dstate(pidinfo))
+            .fold(Self::empty(), Self::add)
+    }
+
+    /// Processes in D state in each cgroup and its descendants, keyed by
+    /// cgroup path with "" for the root. Cgroups without any are left out.
+    pub fn by_cgroup(processes: &procfs::PidMap) -> BTreeMap<String, DStateModel> {
+        let mut by_cgroup: BTreeMap<String, DStateModel> = BTreeMap::new();
+        for pidinfo in processes.values().filter(|pidinfo| is_dstate(pidinfo)) {
+            let path = pidinfo.cgroup.trim_end_matches('/');
+            let ancestors = std::iter::successors(Some(path), |path| {
+                path.rsplit_once('/').map(|(parent, _)| parent)
+            });
+            for cgroup in ancestors {
+                let model = by_cgroup.remove(cgroup).unwrap_or_else(Self::empty);
+                by_cgroup.insert(cgroup.to_owned(), model.add(pidinfo));
+            }
+        }
+        by_cgroup
+    }
+
+    pub fn empty() -> DStateModel {
+        DStateModel {
+            count: Some(0),
+            max_uptime_secs: None,
+        }
+    }
+
+    fn add(self, pidinfo: &procfs::PidInfo) -> DStateModel {
+        DStateModel {
+            count: self.count.map(|count| count + 1),
+            max_uptime_secs: std::cmp::max(self.max_uptime_secs, pidinfo.stat.running_secs),
+        }
+    }
+}
+
+fn is_dstate(pidinfo: &procfs::PidInfo) -> bool {
+    pidinfo.stat.state == Some(procfs::PidState::UninterruptibleSleep)
 }
@@ -17,6 +17,8 @@ use super::*;
 #[::below_derive::queriable_derives]
 pub struct ResctrlL3MonModel {
     pub llc_occupancy_bytes: Option<u64>,
+    /// Share of the LLC occupancy of all groups on the same L3 domain(s)
+    pub llc_occupancy_pct: Option<f64>,
     pub mbm_total_bytes_per_sec: Option<u64>,
     pub mbm_local_bytes_per_sec: Option<u64>,
 }
@@ -39,6 +41,12 @@ pub struct ResctrlMonGroupModel {
     pub mon: ResctrlMonModel,
 }
 
+impl Nameable for ResctrlMonGroupModel {
+    fn name() -> &'static str {
+        "resctrl"
+    }
+}
+
 /// Collection of all data about a single CTRL_MON group and descendents
 #[::below_derive::queriable_derives]
 pub struct ResctrlCtrlMonGroupModel {
@@ -79,6 +87,8 @@ impl std::ops::Add<&ResctrlL3MonModel> for ResctrlL3MonModel {
     fn add(self, other: &Self) -> Self {
         Self {
             llc_occupancy_bytes: opt_add(self.llc_occupancy_bytes, other.llc_occupancy_bytes),
+            // Depends on all groups, see `ResctrlModel::fill_llc_occupancy_pct`
+            llc_occupancy_pct: None,
             mbm_total_bytes_per_sec: opt_add(
                 self.mbm_total_bytes_per_sec,
                 other.mbm_total_bytes_per_sec,
@@ -96,7 +106,7 @@ impl ResctrlModel {
         sample: &resctrlfs::ResctrlSample,
         last: Option<(&resctrlfs::ResctrlSample, Duration)>,
     ) -> ResctrlModel {
-        ResctrlModel {
+        let mut model = ResctrlModel {
             cpuset: sample.cpuset.clone(),
             mode: sample.mode.clone(),
             mon: sample.mon_stat.as_ref().map(|mon_stat| {
@@ -149,7 +159,74 @@ impl ResctrlModel {
                     )
                 })
                 .collect(),
+        };
+        model.fill_llc_occupancy_pct();
+        model
+    }
+
+    /// The mon data of the root and CTRL_MON groups includes their MON
+    /// groups, so together they account for all monitored occupancy.
+    fn fill_llc_occupancy_pct(&mut self) {
+        let mut totals = ResctrlMonModel::default();
+        for mon in self
+            .mon
+            .iter()
+            .chain(self.ctrl_mon_groups.values().map(|group| &group.mon))
+        {
+            totals.total.llc_occupancy_bytes = opt_add(
+                totals.total.llc_occupancy_bytes,
+                mon.total.llc_occupancy_bytes,
+            );
+            for (l3, l3_mon) in &mon.per_l3 {
+                let total = totals.per_l3.entry(*l3).or_default();
+                total.llc_occupancy_bytes =
+                    opt_add(total.llc_occupancy_bytes, l3_mon.llc_occupancy_bytes);
+            }
+        }
+
+        let fill = |mon: &mut ResctrlMonModel| {
+            mon.total.fill_llc_occupancy_pct(&totals.total);
+            for (l3, l3_mon) in mon.per_l3.iter_mut() {
+                if let Some(total) = totals.per_l3.get(l3) {
+                    l3_mon.fill_llc_occupancy_pct(total);
+                }
+            }
+        };
+        if let Some(mon) = self.mon.as_mut() {
+            fill(mon);
+        }
+        for group in self.mon_groups.values_mut() {
+            fill(&mut group.mon);
+        }
+        for group in self.ctrl_mon_groups.values_mut() {
+            fill(&mut group.mon);
+            for child in group.mon_groups.values_mut() {
+                fill(&mut child.mon);
+            }
+        }
+    }
+
+    /// All groups with their monitoring data as a flat list, the root group
+    /// first and each CTRL_MON group followed by its MON groups.
+    pub fn all_groups(&self) -> Vec<ResctrlMonGroupModel> {
+        let mut groups = Vec::new();
+        if let Some(mon) = self.mon.as_ref() {
+            groups.push(ResctrlMonGroupModel {
+                name: "<root>".to_owned(),
+                full_path: String::new(),
+                mon: mon.clone(),
+            });
         }
+        groups.extend(self.mon_groups.values().cloned());
+        for group in self.ctrl_mon_groups.values() {
+            groups.push(ResctrlMonGroupModel {
+                name: group.name.clone(),
+                full_path: group.full_path.clone(),
+                mon: group.mon.clone(),
+            });
+            groups.extend(group.mon_groups.values().cloned());
+        }
+        groups
     }
 }
 
@@ -292,6 +369,7 @@ impl ResctrlL3MonModel {
         if let Some((begin, delta)) = last {
             ResctrlL3MonModel {
                 llc_occupancy_bytes: rmid_bytes_to_opt(&sample.llc_occupancy_bytes),
+                llc_occupancy_pct: None,
                 mbm_total_bytes_per_sec: count_per_sec!(
                     rmid_bytes_to_opt(&begin.mbm_total_bytes),
                     rmid_bytes_to_opt(&sample.mbm_total_bytes),
@@ -308,9 +386,93 @@ impl ResctrlL3MonModel {
         } else {
             ResctrlL3MonModel {
                 llc_occupancy_bytes: rmid_bytes_to_opt(&sample.llc_occupancy_bytes),
+                llc_occupancy_pct: None,
                 mbm_total_bytes_per_sec: None,
                 mbm_local_bytes_per_sec: None,
             }
         }
     }
+
+    fn fill_llc_occupancy_pct(&mut self, total: &ResctrlL3MonModel) {
+        self.llc_occupancy_pct = match (self.llc_occupancy_bytes, total.llc_occupancy_bytes) {
+            (Some(bytes), Some(total)) if total > 0 => Some(bytes as f64 * 100.0 / total as f64),
+            _ => None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use resctrlfs::L3MonStat;
+    use resctrlfs::MonStat;
+    use resctrlfs::RmidBytes;
+
+    use super::*;
+
+    fn mon_stat(llc_occupancy: u64, mbm_total: u64) -> Option<MonStat> {
+        Some(MonStat {
+            l3_mon_stat: Some(BTreeMap::from([(
+                0,
+                L3MonStat {
+                    llc_occupancy_bytes: Some(RmidBytes::Bytes(llc_occupancy)),
+                    mbm_total_bytes: Some(RmidBytes::Bytes(mbm_total)),
+                    mbm_local_bytes: Some(RmidBytes::Unavailable),
+                },
+            )])),
+        })
+    }
+
+    fn sample(mbm_total: u64) -> resctrlfs::ResctrlSample {
+        resctrlfs::ResctrlSample {
+            mon_stat: mon_stat(300, mbm_total),
+            ctrl_mon_groups: Some(BTreeMap::from([(
+                "ctrl".to_owned(),
+                resctrlfs::CtrlMonGroupStat {
+                    inode_number: Some(1),
+                    mon_stat: mon_stat(100, 2 * mbm_total),
+                    mon_groups: Some(BTreeMap::from([(
+                        "mon".to_owned(),
+                        resctrlfs::MonGroupStat {
+                            inode_number: Some(2),
+                            mon_stat: mon_stat(50, mbm_total),
+                            ..Default::default()
+                        },
+                    )])),
+                    ..Default::default()
+                },
+            )])),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn mbm_rates_and_llc_share() {
+        let last = sample(1000);
+        let model = ResctrlModel::new(&sample(3000), Some((&last, Duration::from_secs(2))));
+
+        let groups = model.all_groups();
+        let paths: Vec<&str> = groups.iter().map(|g| g.full_path.as_str()).collect();
+        assert_eq!(paths, vec!["", "ctrl", "ctrl/mon"]);
+
+        let rates: Vec<Option<u64>> = groups
+            .iter()
+            .map(|g| g.mon.total.mbm_total_bytes_per_sec)
+            .collect();
+        assert_eq!(rates, vec![Some(1000), Some(2000), Some(1000)]);
+        assert_eq!(groups[0].mon.total.mbm_local_bytes_per_sec, None);
+
+        // Root and CTRL_MON groups add up to 400 bytes
+        let shares: Vec<Option<f64>> = groups
+            .iter()
+            .map(|g| g.mon.per_l3[&0].llc_occupancy_pct)
+            .collect();
+        assert_eq!(shares, vec![Some(75.0), Some(25.0), Some(12.5)]);
+        assert_eq!(groups[2].mon.total.llc_occupancy_pct, Some(12.5));
+
+        // Rates need a previous sample, occupancy does not
+        let model = ResctrlModel::new(&sample(3000), None);
+        let root = &model.all_groups()[0];
+        assert_eq!(root.mon.total.mbm_total_bytes_per_sec, None);
+        assert_eq!(root.mon.total.llc_occupancy_pct, Some(75.0));
+    }
 }
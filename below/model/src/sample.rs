@@ -24,6 +24,213 @@ pub struct Sample {
     pub ethtool: Option<ethtool::EthtoolStats>,
     pub resctrl: Option<resctrlfs::ResctrlSample>,
     pub tc: Option<tc::TcStats>,
+    /// Sections left out when the sample was recorded. Their data is
+    /// missing rather than empty.
+    #[serde(default)]
+    pub omitted_sections: BTreeSet<SampleSection>,
+    /// Collection interval the sample was taken with, used to tell gaps
+    /// between samples apart. None in samples of older recorders.
+    #[serde(default)]
+    pub interval_s: Option<u64>,
+    /// Stacks of processes that used a lot of CPU, only collected if stack
+    /// sampling is enabled
+    #[serde(default)]
+    pub stacks: StackSampleMap,
+    /// Socket traffic of processes, only collected if process network
+    /// tracing is enabled
+    #[serde(default)]
+    pub process_net: ProcessNetMap,
+    /// Cgroup trees under the extra cgroup roots, by root label
+    #[serde(default)]
+    pub extra_cgroups: BTreeMap<String, CgroupSample>,
+    /// Whether process command lines and exe paths were redacted before
+    /// the sample was recorded
+    #[serde(default)]
+    pub cmdline_redacted: bool,
+    /// Set on the first sample recorded after the wall clock stepped, e.g.
+    /// by NTP. Its timestamp is not comparable with the one before it, so
+    /// no rates are computed between the two.
+    #[serde(default)]
+    pub discontinuity: bool,
+    /// Time each part of the collection took. None in samples of older
+    /// recorders.
+    #[serde(default)]
+    pub collection_timing: Option<CollectionTiming>,
+    /// Health of the BPF collectors. Empty in samples of older recorders
+    /// and if no BPF collector runs.
+    #[serde(default)]
+    pub bpf_stats: BpfProgramStats,
+    /// Data sources missing on the host, with the reason, by source. Their
+    /// data is None or empty rather than failing the whole sample.
+    #[serde(default)]
+    pub unavailable_sources: BTreeMap<DataSource, String>,
+    /// Kills by userspace OOM killers since the last sample, only collected
+    /// if OOM killer events are ingested
+    #[serde(default)]
+    pub oom_kills: Vec<OomKillEvent>,
+}
+
+/// Replaces the parts of command lines and exe paths matched by a
+/// `CmdlineRedaction`
+pub const REDACTED: &str = "<redacted>";
+
+/// How process command lines are redacted before samples are recorded, as
+/// stores may be shared with others.
+#[derive(Clone, Debug, Default)]
+pub struct CmdlineRedaction {
+    /// Keep only argv[0] of each command line
+    pub argv0_only: bool,
+    /// Matches within each argument and exe path are replaced by REDACTED
+    pub regex: Option<Regex>,
+}
+
+/// Shown in place of values from omitted sample sections.
+pub const NOT_COLLECTED: &str = "not collected";
+
+/// Heavy sections of a `Sample` that `below record` can be told not to
+/// store, to shrink the store in constrained environments.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    Sequence
+)]
+#[serde(rename_all = "lowercase")]
+pub enum SampleSection {
+    /// Per-process command lines
+    Cmdline,
+    /// Per-interface and per-queue ethtool stats
+    Ethtool,
+    /// Slab cache stats
+    Slabinfo,
+    /// Traffic control stats
+    Tc,
+}
+
+impl fmt::Display for SampleSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cmdline => "cmdline",
+            Self::Ethtool => "ethtool",
+            Self::Slabinfo => "slabinfo",
+            Self::Tc => "tc",
+        })
+    }
+}
+
+impl FromStr for SampleSection {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        enum_iterator::all::<SampleSection>()
+            .find(|section| section.to_string() == s)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown sample section {:?}, expected one of: {}",
+                    s,
+                    enum_iterator::all::<SampleSection>()
+                        .map(|section| section.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+/// Parts of the host a `Sample` is collected from that may be missing, e.g.
+/// in a chroot or in an initramfs early during boot.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    Sequence
+)]
+#[serde(rename_all = "lowercase")]
+pub enum DataSource {
+    /// The cgroup2 hierarchy at the cgroup root
+    Cgroup,
+    /// /proc/[pid] of all processes
+    Processes,
+    /// /sys/class/net, for interface stats
+    Interfaces,
+    /// /proc/net, for protocol stats
+    Protocols,
+    /// /proc/diskstats
+    Disks,
+}
+
+impl fmt::Display for DataSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cgroup => "cgroup",
+            Self::Processes => "processes",
+            Self::Interfaces => "interfaces",
+            Self::Protocols => "protocols",
+            Self::Disks => "disks",
+        })
+    }
+}
+
+impl Sample {
+    /// Every data source, with the reason it was unavailable or None if it
+    /// was available
+    pub fn capabilities(&self) -> BTreeMap<DataSource, Option<String>> {
+        enum_iterator::all::<DataSource>()
+            .map(|source| (source, self.unavailable_sources.get(&source).cloned()))
+            .collect()
+    }
+
+    /// Drop the data of `sections` and record them as omitted.
+    pub fn omit_sections(&mut self, sections: &BTreeSet<SampleSection>) {
+        for section in sections {
+            match section {
+                SampleSection::Cmdline => {
+                    for pidinfo in self.processes.values_mut() {
+                        pidinfo.cmdline_vec = None;
+                    }
+                }
+                SampleSection::Ethtool => self.ethtool = None,
+                SampleSection::Slabinfo => self.system.slabinfo.clear(),
+                SampleSection::Tc => self.tc = None,
+            }
+        }
+        self.omitted_sections.extend(sections);
+    }
+
+    /// Redact process command lines and exe paths and mark the sample as
+    /// redacted.
+    pub fn redact_cmdlines(&mut self, redaction: &CmdlineRedaction) {
+        let scrub = |s: &mut String| {
+            if let Some(regex) = &redaction.regex {
+                if let std::borrow::Cow::Owned(scrubbed) = regex.replace_all(s, REDACTED) {
+                    *s = scrubbed;
+                }
+            }
+        };
+        for pidinfo in self.processes.values_mut() {
+            if let Some(cmdline) = pidinfo.cmdline_vec.as_mut() {
+                if redaction.argv0_only {
+                    cmdline.truncate(1);
+                }
+                cmdline.iter_mut().for_each(scrub);
+            }
+            if let Some(exe_path) = pidinfo.exe_path.as_mut() {
+                scrub(exe_path);
+            }
+        }
+        self.cmdline_redacted = true;
+    }
 }
 
 #[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -57,6 +264,203 @@ pub struct CgroupSample {
     pub cpu_max: Option<cgroupfs::CpuMax>,
     pub cgroup_controllers: Option<BTreeSet<String>>,
     pub cgroup_subtree_control: Option<BTreeSet<String>>,
+    /// Top files by IO, only collected if file IO tracing is enabled
+    #[serde(default)]
+    pub file_io: Option<FileIoSample>,
+    /// Times of memory PSI trigger firings since the last sample, in
+    /// milliseconds since the epoch. Only collected for cgroups watched with
+    /// PSI triggers.
+    #[serde(default)]
+    pub memory_pressure_triggers: Option<Vec<u64>>,
+    /// Only collected for top level cgroups if perf counters are enabled
+    #[serde(default)]
+    pub perf_counters: Option<PerfCounterSample>,
+    /// Keyed by huge page size, None without the hugetlb controller
+    #[serde(default)]
+    pub hugetlb_stat: Option<BTreeMap<String, cgroupfs::HugetlbStat>>,
+    /// Keyed by device, only devices with an io.latency target are listed
+    #[serde(default)]
+    pub io_latency: Option<BTreeMap<String, cgroupfs::IoLatency>>,
+    /// Error-level journal messages logged by the cgroup and its descendants
+    /// since below started following the journal. Only collected if journal
+    /// errors are counted.
+    #[serde(default)]
+    pub journal_errors: Option<u64>,
+}
+
+/// Files a cgroup did the most IO to over the last file IO collection
+/// interval. Unlike most samples these are deltas rather than cumulative
+/// counters, as the set of files changes from one interval to the next.
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FileIoSample {
+    pub interval_ms: u64,
+    pub files: Vec<FileIoStat>,
+}
+
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FileIoStat {
+    /// Trailing components of the file path, as much as could be resolved
+    /// when the file was first seen
+    pub path: String,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+/// File IO samples keyed by cgroup id, i.e. the inode number of the cgroup
+/// directory.
+pub type CgroupFileIoMap = BTreeMap<u64, FileIoSample>;
+
+/// Hardware cache events counted by perf since the counters were opened,
+/// scaled up for the time the counters were multiplexed out
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PerfCounterSample {
+    /// Last level cache accesses
+    pub llc_references: u64,
+    /// Last level cache misses, each of which reads a cache line from memory
+    pub llc_misses: u64,
+}
+
+/// Perf counters keyed by cgroup id, i.e. the inode number of the cgroup
+/// directory.
+pub type CgroupPerfCounterMap = BTreeMap<u64, PerfCounterSample>;
+
+/// Latest counts of the perf counter collector
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct PerfCounters {
+    /// None if the system wide counters could not be opened
+    pub system: Option<PerfCounterSample>,
+    pub cgroups: CgroupPerfCounterMap,
+}
+
+/// PSI trigger firing times keyed by cgroup id, like `CgroupFileIoMap`
+pub type CgroupPsiTriggerMap = BTreeMap<u64, Vec<u64>>;
+
+/// Error-level journal message counts keyed by cgroup path relative to the
+/// cgroup2 mount, e.g. "/system.slice/foo.service", the root cgroup being
+/// keyed by the empty path
+pub type CgroupJournalErrorMap = BTreeMap<String, u64>;
+
+/// A cgroup killed by a userspace OOM killer, e.g. oomd or systemd-oomd, as
+/// logged by the killer
+#[derive(Default, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct OomKillEvent {
+    /// Seconds since the epoch
+    pub timestamp: u64,
+    /// Syslog identifier of the killer, e.g. "systemd-oomd"
+    pub killer: String,
+    /// Path of the killed cgroup, e.g. "/system.slice/foo.service"
+    pub cgroup: String,
+    /// Message logged by the killer, with the reason of the kill
+    pub message: String,
+}
+
+impl OomKillEvent {
+    /// Whether the kill targeted the cgroup at `path`, as in
+    /// `SingleCgroupModel::full_path`, or one of its descendants
+    pub fn targets(&self, path: &str) -> bool {
+        let path = path.trim_end_matches('/');
+        self.cgroup
+            .strip_prefix(path)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+/// Stacks of a process sampled with perf for a short while, taken after it
+/// stayed above a CPU usage threshold for several samples.
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct StackSample {
+    pub duration_ms: u64,
+    /// Number of stacks sampled, including the ones not kept
+    pub samples: u64,
+    /// Most sampled stacks, most sampled first
+    pub stacks: Vec<StackCount>,
+}
+
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct StackCount {
+    /// Symbolized frames, innermost first
+    pub frames: Vec<String>,
+    pub count: u64,
+}
+
+/// Stack samples keyed by pid
+pub type StackSampleMap = BTreeMap<i32, StackSample>;
+
+/// Bytes a process sent and received over IP sockets since the BPF socket
+/// tracer first saw it
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ProcessNetStat {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    /// Part of the traffic by interface name. Only traffic of sockets with
+    /// a route or bound to a device, e.g. connected TCP sockets, is
+    /// attributed to an interface.
+    #[serde(default)]
+    pub interfaces: BTreeMap<String, ProcessNetIfaceStat>,
+}
+
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ProcessNetIfaceStat {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+/// Socket traffic counters keyed by pid
+pub type ProcessNetMap = BTreeMap<i32, ProcessNetStat>;
+
+/// Health of a BPF collector, to tell when its data is incomplete. Counters
+/// are cumulative since the collector started.
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct BpfProgramStat {
+    /// Events dropped because the buffer shared with userspace was full
+    pub lost_events: u64,
+    /// Failures to attach the programs of the collector
+    pub attach_failures: u64,
+    /// Time the programs of the collector ran for. None unless the
+    /// kernel.bpf_stats_enabled sysctl is set, as the kernel does not
+    /// account it otherwise.
+    pub run_time_ns: Option<u64>,
+    /// Non-BPF source the collector fell back to because its programs could
+    /// not run on this kernel or architecture, e.g. "taskstats"
+    #[serde(default)]
+    pub fallback: Option<String>,
+}
+
+/// Health of the BPF collectors by collector name, e.g. "exitstat"
+pub type BpfProgramStats = BTreeMap<String, BpfProgramStat>;
+
+/// Time spent collecting each part of a sample, to tell which one is
+/// responsible for slow collections
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CollectionTiming {
+    /// Taking exited processes and file IO from the BPF collectors, which
+    /// run on their own threads
+    pub bpf_usecs: u64,
+    /// Walking the cgroup hierarchy
+    pub cgroup_usecs: u64,
+    /// Reading /proc/<pid> of all processes
+    pub process_usecs: u64,
+    /// Reading system-wide stats, including disks and btrfs
+    pub system_usecs: u64,
+    pub network_usecs: u64,
+    pub ethtool_usecs: u64,
+    /// Whole collection, including the parts not broken out
+    pub total_usecs: u64,
+}
+
+impl fmt::Display for CollectionTiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bpf {} ms, cgroup {} ms, process {} ms, system {} ms, network {} ms, ethtool {} ms",
+            self.bpf_usecs / 1000,
+            self.cgroup_usecs / 1000,
+            self.process_usecs / 1000,
+            self.system_usecs / 1000,
+            self.network_usecs / 1000,
+            self.ethtool_usecs / 1000,
+        )
+    }
 }
 
 #[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -66,10 +470,42 @@ pub struct SystemSample {
     pub vmstat: procfs::VmStat,
     #[serde(default)]
     pub slabinfo: procfs::SlabInfoMap,
+    #[serde(default)]
+    pub softirqs: procfs::SoftirqMap,
+    #[serde(default)]
+    pub schedstat: procfs::SchedStatMap,
+    /// Frequency and thermal throttling per CPU, from sysfs
+    #[serde(default)]
+    pub cpufreq: procfs::CpuFreqMap,
     pub ksm: Option<procfs::Ksm>,
     pub hostname: String,
     pub disks: procfs::DiskMap,
     pub btrfs: Option<btrfs::BtrfsMap>,
+    /// Error counters and scrub progress of btrfs devices
+    #[serde(default)]
+    pub btrfs_devices: Option<btrfs::BtrfsDevMap>,
     pub kernel_version: Option<String>,
+    /// Random id of the boot the sample was collected in
+    #[serde(default)]
+    pub boot_id: Option<String>,
     pub os_release: Option<String>,
+    /// Version of below that collected the sample
+    #[serde(default)]
+    pub below_version: Option<String>,
+    /// None on kernels without IRQ pressure
+    #[serde(default)]
+    pub irq_pressure: Option<procfs::PressureMetrics>,
+    #[serde(default)]
+    pub loadavg: Option<procfs::LoadAvg>,
+    /// Seconds since boot, from /proc/uptime
+    #[serde(default)]
+    pub uptime_secs: Option<f64>,
+    #[serde(default)]
+    pub entropy_avail: Option<u64>,
+    /// None on kernels without the hung_task_detect_count sysctl
+    #[serde(default)]
+    pub hung_task_detect_count: Option<u64>,
+    /// Only collected if perf counters are enabled
+    #[serde(default)]
+    pub perf_counters: Option<PerfCounterSample>,
 }
@@ -81,6 +81,70 @@ pub const SAMPLE_MODEL_JSON: &str = r#"
                 "guest_nice_pct": 0.0
             }
         },
+        "total_softirq": {
+            "idx": -1,
+            "hi_per_sec": 0.0,
+            "timer_per_sec": 2000.0,
+            "net_tx_per_sec": 20.0,
+            "net_rx_per_sec": 1000.0,
+            "block_per_sec": 40.0,
+            "irq_poll_per_sec": 0.0,
+            "tasklet_per_sec": 2.0,
+            "sched_per_sec": 1600.0,
+            "hrtimer_per_sec": 0.0,
+            "rcu_per_sec": 1800.0
+        },
+        "softirqs": {
+            "0": {
+                "idx": 0,
+                "hi_per_sec": 0.0,
+                "timer_per_sec": 1000.0,
+                "net_tx_per_sec": 10.0,
+                "net_rx_per_sec": 500.0,
+                "block_per_sec": 20.0,
+                "irq_poll_per_sec": 0.0,
+                "tasklet_per_sec": 1.0,
+                "sched_per_sec": 800.0,
+                "hrtimer_per_sec": 0.0,
+                "rcu_per_sec": 900.0
+            },
+            "1": {
+                "idx": 1,
+                "hi_per_sec": 0.0,
+                "timer_per_sec": 1000.0,
+                "net_tx_per_sec": 10.0,
+                "net_rx_per_sec": 500.0,
+                "block_per_sec": 20.0,
+                "irq_poll_per_sec": 0.0,
+                "tasklet_per_sec": 1.0,
+                "sched_per_sec": 800.0,
+                "hrtimer_per_sec": 0.0,
+                "rcu_per_sec": 900.0
+            }
+        },
+        "total_sched": {
+            "idx": -1,
+            "run_pct": 40.0,
+            "wait_pct": 5.0,
+            "timeslices_per_sec": 2000.0,
+            "avg_wait_usec": 50.0
+        },
+        "scheds": {
+            "0": {
+                "idx": 0,
+                "run_pct": 40.0,
+                "wait_pct": 5.0,
+                "timeslices_per_sec": 1000.0,
+                "avg_wait_usec": 50.0
+            },
+            "1": {
+                "idx": 1,
+                "run_pct": 40.0,
+                "wait_pct": 5.0,
+                "timeslices_per_sec": 1000.0,
+                "avg_wait_usec": 50.0
+            }
+        },
         "mem": {
             "total": 8000000000,
             "free": 4000000000,
@@ -732,6 +796,11 @@ pub const SAMPLE_MODEL_JSON: &str = r#"
         "interfaces": {
             "eth0": {
                 "interface": "eth0",
+                "operstate": "up",
+                "speed_mbps": 25000,
+                "duplex": "full",
+                "carrier_changes": 4,
+                "carrier_changes_delta": 1,
                 "rx_bytes_per_sec": 200000.5,
                 "tx_bytes_per_sec": 50000.5,
                 "throughput_per_sec": 200000.5,
@@ -798,6 +867,11 @@ pub const SAMPLE_MODEL_JSON: &str = r#"
             },
             "lo": {
                 "interface": "lo",
+                "operstate": "unknown",
+                "speed_mbps": null,
+                "duplex": null,
+                "carrier_changes": 0,
+                "carrier_changes_delta": 0,
                 "rx_bytes_per_sec": 10000000.5,
                 "tx_bytes_per_sec": 10000000.5,
                 "throughput_per_sec": 30000000.5,
@@ -958,7 +1032,9 @@ pub const SAMPLE_MODEL_JSON: &str = r#"
             {
                 "interface": "eth0",
                 "kind": "fq_codel",
+                "path": "eth0/1:/1:1/8001:",
                 "qlen": 42,
+                "backlog_bytes": 65536,
                 "bps": 420,
                 "pps": 1337,
                 "bytes_per_sec": 299792458,
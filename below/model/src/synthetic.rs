@@ -0,0 +1,312 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Synthetic hosts of configurable size, for benchmarks and for tests that
+//! check how below scales with the number of processes and cgroups.
+//!
+//! Hosts are described by a number of processes and cgroups. Cgroups are
+//! grouped into slices of up to `UNITS_PER_SLICE` units under the root, and
+//! processes are spread evenly over the units. The same hosts are written
+//! to disk by `below_testutil::ProcfsBuilder::synthetic` and
+//! `below_testutil::CgroupfsBuilder::synthetic`.
+
+use super::*;
+
+/// Number of units in each slice of a synthetic cgroup tree
+const UNITS_PER_SLICE: usize = 8;
+
+/// Sizes the benchmarks run at, as numbers of processes. Set the
+/// BELOW_BENCH_SIZES environment variable to a comma separated list, e.g.
+/// "100,10000", to override.
+pub fn bench_sizes() -> Vec<usize> {
+    match std::env::var("BELOW_BENCH_SIZES") {
+        Ok(sizes) => sizes
+            .split(',')
+            .map(|size| {
+                size.trim()
+                    .parse()
+                    .unwrap_or_else(|_| panic!("Invalid size in BELOW_BENCH_SIZES: {}", size))
+            })
+            .collect(),
+        Err(_) => vec![100, 1000],
+    }
+}
+
+/// Paths of the units of a synthetic cgroup tree of `nr_cgroups` cgroups,
+/// relative to the cgroup root and without leading slash.
+fn synthetic_units(nr_cgroups: usize) -> Vec<String> {
+    (0..nr_cgroups.max(1))
+        .map(|i| {
+            format!(
+                "slice{}.slice/unit{}.service",
+                i / UNITS_PER_SLICE,
+                i % UNITS_PER_SLICE
+            )
+        })
+        .collect()
+}
+
+fn synthetic_pressure(seq: u64) -> cgroupfs::Pressure {
+    let metrics = cgroupfs::PressureMetrics {
+        avg10: Some(1.0),
+        avg60: Some(0.5),
+        avg300: Some(0.25),
+        total: Some(seq * 1000),
+    };
+    cgroupfs::Pressure {
+        cpu: cgroupfs::CpuPressure {
+            some: metrics.clone(),
+            full: Some(metrics.clone()),
+        },
+        io: cgroupfs::IoPressure {
+            some: metrics.clone(),
+            full: metrics.clone(),
+        },
+        memory: cgroupfs::MemoryPressure {
+            some: metrics.clone(),
+            full: metrics,
+        },
+        irq: None,
+    }
+}
+
+fn synthetic_cgroup(seq: u64, children: Option<BTreeMap<String, CgroupSample>>) -> CgroupSample {
+    CgroupSample {
+        cpu_stat: Some(cgroupfs::CpuStat {
+            usage_usec: Some(seq * 2000),
+            user_usec: Some(seq * 1500),
+            system_usec: Some(seq * 500),
+            nr_periods: Some(seq),
+            nr_throttled: Some(0),
+            throttled_usec: Some(0),
+        }),
+        io_stat: Some(BTreeMap::from([(
+            "8:0".to_owned(),
+            cgroupfs::IoStat {
+                rbytes: Some(seq * 4096),
+                wbytes: Some(seq * 8192),
+                rios: Some(seq),
+                wios: Some(seq * 2),
+                dbytes: Some(0),
+                dios: Some(0),
+                ..Default::default()
+            },
+        )])),
+        tids_current: Some(4),
+        memory_current: Some(64 << 20),
+        memory_stat: Some(cgroupfs::MemoryStat {
+            anon: Some(48 << 20),
+            file: Some(16 << 20),
+            ..Default::default()
+        }),
+        pressure: Some(synthetic_pressure(seq)),
+        children,
+        inode_number: Some(seq as i64),
+        ..Default::default()
+    }
+}
+
+/// Sample of a host with `nr_processes` processes in `nr_cgroups` cgroups.
+/// Cumulative counters grow with `seq`, so a model of two samples with
+/// consecutive `seq` has non-zero rates.
+pub fn synthetic_sample(nr_processes: usize, nr_cgroups: usize, seq: u64) -> Sample {
+    let units = synthetic_units(nr_cgroups);
+    let mut slices: BTreeMap<String, BTreeMap<String, CgroupSample>> = BTreeMap::new();
+    for unit in &units {
+        let (slice, unit) = unit.split_once('/').expect("bug: unit without slice");
+        slices
+            .entry(slice.to_owned())
+            .or_default()
+            .insert(unit.to_owned(), synthetic_cgroup(seq, None));
+    }
+    let cgroup = synthetic_cgroup(
+        seq,
+        Some(
+            slices
+                .into_iter()
+                .map(|(slice, units)| (slice, synthetic_cgroup(seq, Some(units))))
+                .collect(),
+        ),
+    );
+
+    let processes = (0..nr_processes)
+        .map(|i| {
+            let pid = i as i32 + 1;
+            let pidinfo = procfs::PidInfo {
+                stat: procfs::PidStat {
+                    pid: Some(pid),
+                    comm: Some(format!("proc{}", pid)),
+                    state: Some(procfs::PidState::Running),
+                    ppid: Some(1),
+                    pgrp: Some(pid),
+                    session: Some(pid),
+                    minflt: Some(seq * 10),
+                    majflt: Some(seq),
+                    user_usecs: Some(seq * 1500),
+                    system_usecs: Some(seq * 500),
+                    num_threads: Some(4),
+                    running_secs: Some(seq),
+                    start_secs_since_boot: Some(1000),
+                    rss_bytes: Some(16 << 20),
+                    processor: Some(i as i32 % 8),
+                },
+                io: procfs::PidIo {
+                    rbytes: Some(seq * 4096),
+                    wbytes: Some(seq * 8192),
+                },
+                cgroup: format!("/{}", units[i % units.len()]),
+                cmdline_vec: Some(vec![format!("/usr/bin/proc{}", pid), "--flag".to_owned()]),
+                exe_path: Some(format!("/usr/bin/proc{}", pid)),
+                status: procfs::PidStatus {
+                    vm_size: Some(256 << 20),
+                    anon: Some(12 << 20),
+                    file: Some(4 << 20),
+                    ..Default::default()
+                },
+                smaps_rollup: None,
+            };
+            (pid, pidinfo)
+        })
+        .collect();
+
+    Sample {
+        cgroup,
+        processes,
+        system: SystemSample {
+            hostname: "synthetic".to_owned(),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::GlobalAlloc;
+    use std::alloc::Layout;
+    use std::alloc::System;
+    use std::cell::Cell;
+
+    use super::*;
+
+    /// Counts allocations made by the current thread, so that tests running
+    /// in parallel do not disturb each other.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn count_allocation() {
+        // Fails while the thread is torn down
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count_allocation();
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count_allocation();
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// Number of allocations made by `f`
+    fn allocations<T>(f: impl FnOnce() -> T) -> usize {
+        let before = ALLOCATIONS.with(Cell::get);
+        let res = f();
+        let after = ALLOCATIONS.with(Cell::get);
+        drop(res);
+        after - before
+    }
+
+    #[test]
+    fn test_synthetic_trees() {
+        let procfs = below_testutil::ProcfsBuilder::synthetic(20, 10)
+            .build()
+            .expect("Failed to write procfs");
+        let cgroupfs = below_testutil::CgroupfsBuilder::synthetic(10)
+            .build()
+            .expect("Failed to write cgroupfs");
+
+        let pids = procfs::ProcReader::new_with_custom_procfs(procfs.path().to_owned())
+            .read_all_pids()
+            .expect("Failed to read pids");
+        assert_eq!(pids.len(), 20);
+        assert_eq!(pids[&20].cgroup, "/slice1.slice/unit1.service");
+        assert_eq!(pids[&20].io.wbytes, Some(8192));
+
+        let reader = cgroupfs::CgroupReader::new_unchecked(cgroupfs.path().to_owned())
+            .expect("Failed to open cgroup root");
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let cgroup = collect_cgroup_sample(&reader, true, &logger, &None)
+            .expect("Failed to collect cgroup sample");
+        let slices = cgroup.children.as_ref().expect("No slices");
+        assert_eq!(slices.len(), 2);
+        assert_eq!(
+            slices["slice1.slice"].children.as_ref().map(BTreeMap::len),
+            Some(2)
+        );
+        assert!(cgroup.pressure.is_some());
+
+        // Same shape as the sample built in memory
+        let sample = synthetic_sample(20, 10, 1);
+        assert_eq!(sample.processes[&20].cgroup, pids[&20].cgroup);
+        assert_eq!(
+            sample
+                .cgroup
+                .children
+                .as_ref()
+                .map(|c| c.keys().collect::<Vec<_>>()),
+            Some(slices.keys().collect())
+        );
+    }
+
+    /// Allocations must grow linearly with the size of the host, and stay
+    /// within a fixed budget per process and cgroup.
+    #[test]
+    fn test_model_allocations() {
+        let model_allocations = |nr_processes, nr_cgroups| {
+            let last = synthetic_sample(nr_processes, nr_cgroups, 1);
+            let sample = synthetic_sample(nr_processes, nr_cgroups, 2);
+            allocations(|| {
+                Model::new(
+                    SystemTime::now(),
+                    &sample,
+                    Some((&last, Duration::from_secs(5))),
+                )
+            })
+        };
+        let small = model_allocations(100, 10);
+        let large = model_allocations(1000, 100);
+        assert!(
+            large <= small * 12,
+            "{} allocations for 10x the host, {} before",
+            large,
+            small
+        );
+        // About 5 per process at the time of writing
+        assert!(large <= 10 * (1000 + 100), "{} allocations", large);
+    }
+}
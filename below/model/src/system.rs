@@ -18,15 +18,44 @@ use super::*;
 pub struct SystemModel {
     pub hostname: String,
     pub kernel_version: Option<String>,
+    /// Random id the kernel generates on every boot
+    pub boot_id: Option<String>,
     pub os_release: Option<String>,
+    /// Version of below that collected the sample
+    pub below_version: Option<String>,
+    /// Share of time all non-idle tasks were stalled by IRQ handling, from
+    /// /proc/pressure/irq
+    pub irq_pressure_full_pct: Option<f64>,
+    /// Hung tasks the kernel detected since boot
+    pub hung_task_detect_count: Option<u64>,
+    /// Hung tasks the kernel detected since the last sample
+    pub hung_task_detect_delta: Option<u64>,
+    pub uptime_secs: Option<u64>,
+    /// Bits of entropy available to the kernel random number generator
+    pub entropy_avail: Option<u64>,
+    #[queriable(subquery)]
+    pub loadavg: Option<LoadAvgModel>,
     #[queriable(subquery)]
     pub stat: ProcStatModel,
+    /// Filled in from the processes by the Model, None without them
+    #[queriable(subquery)]
+    pub dstate: Option<DStateModel>,
     #[queriable(subquery)]
     #[queriable(preferred_name = cpu)]
     pub total_cpu: SingleCpuModel,
     #[queriable(subquery)]
     pub cpus: BTreeMap<u32, SingleCpuModel>,
     #[queriable(subquery)]
+    #[queriable(preferred_name = softirq)]
+    pub total_softirq: SingleSoftirqModel,
+    #[queriable(subquery)]
+    pub softirqs: BTreeMap<u32, SingleSoftirqModel>,
+    #[queriable(subquery)]
+    #[queriable(preferred_name = sched)]
+    pub total_sched: SingleSchedModel,
+    #[queriable(subquery)]
+    pub scheds: BTreeMap<u32, SingleSchedModel>,
+    #[queriable(subquery)]
     pub mem: MemoryModel,
     #[queriable(subquery)]
     pub vm: VmModel,
@@ -34,16 +63,25 @@ pub struct SystemModel {
     pub slab: BTreeMap<String, SingleSlabModel>,
     #[queriable(subquery)]
     pub ksm: Option<KsmModel>,
+    /// Only set if perf counters are enabled
+    #[queriable(subquery)]
+    pub perf: Option<PerfCounterModel>,
     #[queriable(subquery)]
     pub disks: BTreeMap<String, SingleDiskModel>,
     #[queriable(subquery)]
     pub btrfs: Option<BTreeMap<String, BtrfsModel>>,
+    /// Keyed by device path
+    #[queriable(subquery)]
+    pub btrfs_devices: Option<BTreeMap<String, BtrfsDeviceModel>>,
+    /// Time below itself took to collect the sample
+    #[queriable(subquery)]
+    pub collection: Option<CollectionModel>,
 }
 
 impl SystemModel {
     pub fn new(sample: &SystemSample, last: Option<(&SystemSample, Duration)>) -> SystemModel {
         let stat = ProcStatModel::new(&sample.stat);
-        let total_cpu = match (
+        let mut total_cpu = match (
             last.and_then(|(last, _)| last.stat.total_cpu.as_ref()),
             sample.stat.total_cpu.as_ref(),
         ) {
@@ -51,7 +89,7 @@ impl SystemModel {
             _ => Default::default(),
         };
 
-        let cpus: BTreeMap<u32, SingleCpuModel> = match (
+        let mut cpus: BTreeMap<u32, SingleCpuModel> = match (
             last.and_then(|(last, _)| last.stat.cpus_map.as_ref()),
             sample.stat.cpus_map.as_ref(),
         ) {
@@ -70,6 +108,124 @@ impl SystemModel {
             _ => Default::default(),
         };
 
+        for (idx, cpufreq) in &sample.cpufreq {
+            if let Some(cpu) = cpus.get_mut(idx) {
+                cpu.set_cpufreq(last.and_then(|(last, _)| last.cpufreq.get(idx)), cpufreq);
+            }
+        }
+        let freqs = cpus
+            .values()
+            .filter_map(|cpu| cpu.freq_hz)
+            .collect::<Vec<_>>();
+        if !freqs.is_empty() {
+            total_cpu.freq_hz = Some(freqs.iter().sum::<u64>() / freqs.len() as u64);
+        }
+        total_cpu.core_throttles = cpus
+            .values()
+            .fold(None, |acc, cpu| opt_add(acc, cpu.core_throttles));
+
+        let (total_softirq, softirqs) = match last {
+            Some((last, duration)) => {
+                let softirqs = sample
+                    .softirqs
+                    .iter()
+                    .map(|(idx, curr)| {
+                        (
+                            *idx,
+                            last.softirqs.get(idx).map_or_else(
+                                || SingleSoftirqModel {
+                                    idx: *idx as i32,
+                                    ..Default::default()
+                                },
+                                |prev| SingleSoftirqModel::new(*idx as i32, prev, curr, duration),
+                            ),
+                        )
+                    })
+                    .collect();
+                let total_softirq = SingleSoftirqModel::new(
+                    -1,
+                    &sum_softirqs(&last.softirqs, &sample.softirqs),
+                    &sum_softirqs(&sample.softirqs, &last.softirqs),
+                    duration,
+                );
+                (total_softirq, softirqs)
+            }
+            None => (
+                SingleSoftirqModel {
+                    idx: -1,
+                    ..Default::default()
+                },
+                sample
+                    .softirqs
+                    .keys()
+                    .map(|idx| {
+                        (
+                            *idx,
+                            SingleSoftirqModel {
+                                idx: *idx as i32,
+                                ..Default::default()
+                            },
+                        )
+                    })
+                    .collect(),
+            ),
+        };
+
+        let (total_sched, scheds) = match last {
+            Some((last, duration)) => {
+                let scheds = sample
+                    .schedstat
+                    .iter()
+                    .map(|(idx, curr)| {
+                        (
+                            *idx,
+                            last.schedstat.get(idx).map_or_else(
+                                || SingleSchedModel {
+                                    idx: *idx as i32,
+                                    ..Default::default()
+                                },
+                                |prev| SingleSchedModel::new(*idx as i32, prev, curr, duration),
+                            ),
+                        )
+                    })
+                    .collect();
+                let mut total_sched = SingleSchedModel::new(
+                    -1,
+                    &sum_schedstat(&last.schedstat, &sample.schedstat),
+                    &sum_schedstat(&sample.schedstat, &last.schedstat),
+                    duration,
+                );
+                // Time shares are averaged over all CPUs, like total CPU usage
+                let nr_cpus = sample
+                    .schedstat
+                    .keys()
+                    .filter(|idx| last.schedstat.contains_key(idx))
+                    .count() as f64;
+                total_sched.run_pct = total_sched.run_pct.map(|pct| pct / nr_cpus);
+                total_sched.wait_pct = total_sched.wait_pct.map(|pct| pct / nr_cpus);
+                (total_sched, scheds)
+            }
+            None => (
+                SingleSchedModel {
+                    idx: -1,
+                    ..Default::default()
+                },
+                sample
+                    .schedstat
+                    .keys()
+                    .map(|idx| {
+                        (
+                            *idx,
+                            SingleSchedModel {
+                                idx: *idx as i32,
+                                ..Default::default()
+                            },
+                        )
+                    })
+                    .collect(),
+            ),
+        };
+
         let mem = MemoryModel::new(&sample.meminfo);
         let vm = last
             .map(|(last, duration)| VmModel::new(&last.vmstat, &sample.vmstat, duration))
@@ -78,7 +234,15 @@ impl SystemModel {
         let mut slab = sample
             .slabinfo
             .iter()
-            .map(|(name, slab_info)| (name.to_owned(), SingleSlabModel::new(slab_info)))
+            .map(|(name, slab_info)| {
+                (
+                    name.to_owned(),
+                    SingleSlabModel::new(
+                        slab_info,
+                        last.and_then(|(last, _)| last.slabinfo.get(name)),
+                    ),
+                )
+            })
             .collect::<BTreeMap<String, SingleSlabModel>>();
 
         let slab_total = slab.iter().fold(
@@ -94,13 +258,30 @@ impl SystemModel {
                 acc.num_caches = opt_add(acc.num_caches, slabinfo.num_caches);
                 acc.active_size = opt_add(acc.active_size, slabinfo.active_size);
                 acc.total_size = opt_add(acc.total_size, slabinfo.total_size);
+                acc.active_objs_delta = opt_add(acc.active_objs_delta, slabinfo.active_objs_delta);
+                acc.total_size_delta = opt_add(acc.total_size_delta, slabinfo.total_size_delta);
                 acc
             },
         );
-        slab.insert(String::from("TOTAL"), slab_total);
+        slab.insert(
+            String::from("TOTAL"),
+            SingleSlabModel {
+                growth_streak: slab_total.total_size_delta.map(|delta| (delta > 0) as u64),
+                ..slab_total
+            },
+        );
 
         let ksm = sample.ksm.as_ref().map(KsmModel::new);
 
+        let perf = sample.perf_counters.as_ref().map(|perf_counters| {
+            PerfCounterModel::new(
+                perf_counters,
+                last.and_then(|(last, duration)| {
+                    last.perf_counters.as_ref().map(|last| (last, duration))
+                }),
+            )
+        });
+
         let mut disks: BTreeMap<String, SingleDiskModel> = BTreeMap::new();
         sample.disks.iter().for_each(|(disk_name, end_disk_stat)| {
             disks.insert(
@@ -135,19 +316,67 @@ impl SystemModel {
             None => {}
         }
 
+        let btrfs_devices = sample.btrfs_devices.as_ref().map(|devices| {
+            devices
+                .iter()
+                .map(|(path, stat)| (path.clone(), BtrfsDeviceModel::new(stat)))
+                .collect()
+        });
+
         SystemModel {
             hostname: sample.hostname.clone(),
             kernel_version: sample.kernel_version.clone(),
+            boot_id: sample.boot_id.clone(),
             os_release: sample.os_release.clone(),
+            below_version: sample.below_version.clone(),
+            irq_pressure_full_pct: sample.irq_pressure.as_ref().and_then(|p| p.avg10),
+            hung_task_detect_count: sample.hung_task_detect_count,
+            hung_task_detect_delta: last.and_then(|(last, _)| {
+                match (last.hung_task_detect_count, sample.hung_task_detect_count) {
+                    (Some(begin), Some(end)) if begin <= end => Some(end - begin),
+                    _ => None,
+                }
+            }),
+            uptime_secs: sample.uptime_secs.map(|s| s as u64),
+            entropy_avail: sample.entropy_avail,
+            loadavg: sample.loadavg.as_ref().map(LoadAvgModel::new),
             stat,
+            dstate: None,
             total_cpu,
             cpus,
+            total_softirq,
+            softirqs,
+            total_sched,
+            scheds,
             mem,
             vm,
             slab,
             ksm,
+            perf,
             disks,
             btrfs,
+            btrfs_devices,
+            // Filled in from the Sample by the Model
+            collection: None,
+        }
+    }
+}
+
+impl SystemModel {
+    /// Continue the growth streaks of the slab caches from `last`, the model
+    /// of the previous sample. Models only see two samples, so readers going
+    /// through samples in order call this to track growth over more samples.
+    pub fn track_slab_growth(&mut self, last: &SystemModel) {
+        for (name, slab) in self.slab.iter_mut() {
+            if slab.growth_streak.unwrap_or(0) > 0 {
+                slab.growth_streak = Some(
+                    last.slab
+                        .get(name)
+                        .and_then(|last| last.growth_streak)
+                        .unwrap_or(0)
+                        + 1,
+                );
+            }
         }
     }
 }
@@ -158,6 +387,28 @@ impl Nameable for SystemModel {
     }
 }
 
+#[::below_derive::queriable_derives]
+pub struct LoadAvgModel {
+    pub load1: Option<f64>,
+    pub load5: Option<f64>,
+    pub load15: Option<f64>,
+    pub runnable_tasks: Option<u64>,
+    /// Threads that currently exist
+    pub total_tasks: Option<u64>,
+}
+
+impl LoadAvgModel {
+    pub fn new(loadavg: &procfs::LoadAvg) -> Self {
+        LoadAvgModel {
+            load1: loadavg.load1,
+            load5: loadavg.load5,
+            load15: loadavg.load15,
+            runnable_tasks: loadavg.runnable_tasks,
+            total_tasks: loadavg.total_tasks,
+        }
+    }
+}
+
 #[::below_derive::queriable_derives]
 pub struct ProcStatModel {
     pub total_interrupt_ct: Option<u64>,
@@ -195,6 +446,17 @@ pub struct SingleCpuModel {
     pub stolen_pct: Option<f64>,
     pub guest_pct: Option<f64>,
     pub guest_nice_pct: Option<f64>,
+    /// Average frequency over the interval where cpufreq stats are
+    /// available, the current frequency otherwise
+    pub freq_hz: Option<u64>,
+    pub max_freq_hz: Option<u64>,
+    /// Thermal throttling events of the core since the previous sample
+    pub core_throttles: Option<u64>,
+    /// Time the core was throttled since the previous sample
+    pub core_throttle_usec: Option<u64>,
+    /// Thermal throttling events of the CPU's package since the previous
+    /// sample
+    pub package_throttles: Option<u64>,
 }
 
 impl SingleCpuModel {
@@ -254,6 +516,7 @@ impl SingleCpuModel {
                     stolen_pct: Some(stolen_usec as f64 * 100.0 / total_usec as f64),
                     guest_pct: Some(guest_usec as f64 * 100.0 / total_usec as f64),
                     guest_nice_pct: Some(guest_nice_usec as f64 * 100.0 / total_usec as f64),
+                    ..Default::default()
                 }
             }
             _ => SingleCpuModel {
@@ -262,6 +525,150 @@ impl SingleCpuModel {
             },
         }
     }
+
+    /// Fill in frequency and throttling from the cpufreq and thermal_throttle
+    /// counters at the start and end of the interval.
+    pub fn set_cpufreq(&mut self, begin: Option<&procfs::CpuFreqStat>, end: &procfs::CpuFreqStat) {
+        let delta = |counter: fn(&procfs::CpuFreqStat) -> Option<u64>| match (
+            begin.and_then(counter),
+            counter(end),
+        ) {
+            (Some(begin), Some(end)) if begin <= end => Some(end - begin),
+            _ => None,
+        };
+        // Weigh each frequency by the time spent at it during the interval
+        let avg_freq_khz = begin
+            .and_then(|begin| Some((begin.time_in_state.as_ref()?, end.time_in_state.as_ref()?)))
+            .and_then(|(begin, end)| {
+                let (weighted, total) =
+                    end.iter().fold((0, 0), |(weighted, total), (freq, time)| {
+                        let time = time.saturating_sub(begin.get(freq).copied().unwrap_or(0));
+                        (weighted + freq * time, total + time)
+                    });
+                weighted.checked_div(total)
+            });
+        self.freq_hz = avg_freq_khz.or(end.cur_freq_khz).map(|khz| khz * 1000);
+        self.max_freq_hz = end.max_freq_khz.map(|khz| khz * 1000);
+        self.core_throttles = delta(|stat| stat.core_throttle_count);
+        self.core_throttle_usec =
+            delta(|stat| stat.core_throttle_total_time_ms).map(|ms| ms * 1000);
+        self.package_throttles = delta(|stat| stat.package_throttle_count);
+    }
+}
+
+/// Sum over the CPUs of `softirqs` that are in `other` as well, so that a CPU
+/// going on- or offline between samples does not count as softirqs handled
+fn sum_softirqs(softirqs: &procfs::SoftirqMap, other: &procfs::SoftirqMap) -> procfs::SoftirqStat {
+    softirqs
+        .iter()
+        .filter(|(idx, _)| other.contains_key(idx))
+        .map(|(_, stat)| stat)
+        .fold(Default::default(), |acc, stat| procfs::SoftirqStat {
+            hi: opt_add(acc.hi, stat.hi),
+            timer: opt_add(acc.timer, stat.timer),
+            net_tx: opt_add(acc.net_tx, stat.net_tx),
+            net_rx: opt_add(acc.net_rx, stat.net_rx),
+            block: opt_add(acc.block, stat.block),
+            irq_poll: opt_add(acc.irq_poll, stat.irq_poll),
+            tasklet: opt_add(acc.tasklet, stat.tasklet),
+            sched: opt_add(acc.sched, stat.sched),
+            hrtimer: opt_add(acc.hrtimer, stat.hrtimer),
+            rcu: opt_add(acc.rcu, stat.rcu),
+        })
+}
+
+/// Softirqs handled per second by type, for a single CPU or all CPUs (idx -1)
+#[::below_derive::queriable_derives]
+pub struct SingleSoftirqModel {
+    pub idx: i32,
+    pub hi_per_sec: Option<f64>,
+    pub timer_per_sec: Option<f64>,
+    pub net_tx_per_sec: Option<f64>,
+    pub net_rx_per_sec: Option<f64>,
+    pub block_per_sec: Option<f64>,
+    pub irq_poll_per_sec: Option<f64>,
+    pub tasklet_per_sec: Option<f64>,
+    pub sched_per_sec: Option<f64>,
+    pub hrtimer_per_sec: Option<f64>,
+    pub rcu_per_sec: Option<f64>,
+}
+
+impl SingleSoftirqModel {
+    pub fn new(
+        idx: i32,
+        begin: &procfs::SoftirqStat,
+        end: &procfs::SoftirqStat,
+        duration: Duration,
+    ) -> SingleSoftirqModel {
+        SingleSoftirqModel {
+            idx,
+            hi_per_sec: count_per_sec!(begin.hi, end.hi, duration),
+            timer_per_sec: count_per_sec!(begin.timer, end.timer, duration),
+            net_tx_per_sec: count_per_sec!(begin.net_tx, end.net_tx, duration),
+            net_rx_per_sec: count_per_sec!(begin.net_rx, end.net_rx, duration),
+            block_per_sec: count_per_sec!(begin.block, end.block, duration),
+            irq_poll_per_sec: count_per_sec!(begin.irq_poll, end.irq_poll, duration),
+            tasklet_per_sec: count_per_sec!(begin.tasklet, end.tasklet, duration),
+            sched_per_sec: count_per_sec!(begin.sched, end.sched, duration),
+            hrtimer_per_sec: count_per_sec!(begin.hrtimer, end.hrtimer, duration),
+            rcu_per_sec: count_per_sec!(begin.rcu, end.rcu, duration),
+        }
+    }
+}
+
+/// Sum over the CPUs of `schedstat` that are in `other` as well, like
+/// `sum_softirqs`
+fn sum_schedstat(
+    schedstat: &procfs::SchedStatMap,
+    other: &procfs::SchedStatMap,
+) -> procfs::CpuSchedStat {
+    schedstat
+        .iter()
+        .filter(|(idx, _)| other.contains_key(idx))
+        .map(|(_, stat)| stat)
+        .fold(Default::default(), |acc, stat| procfs::CpuSchedStat {
+            running_ns: opt_add(acc.running_ns, stat.running_ns),
+            waiting_ns: opt_add(acc.waiting_ns, stat.waiting_ns),
+            timeslices: opt_add(acc.timeslices, stat.timeslices),
+        })
+}
+
+/// Runqueue statistics for a single CPU or all CPUs (idx -1)
+#[::below_derive::queriable_derives]
+pub struct SingleSchedModel {
+    pub idx: i32,
+    /// Share of time spent running tasks
+    pub run_pct: Option<f64>,
+    /// Time tasks spent waiting on the runqueue, as share of the interval.
+    /// Over 100% when several tasks wait at once.
+    pub wait_pct: Option<f64>,
+    pub timeslices_per_sec: Option<f64>,
+    /// Average time a task waited on the runqueue before running
+    pub avg_wait_usec: Option<f64>,
+}
+
+impl SingleSchedModel {
+    pub fn new(
+        idx: i32,
+        begin: &procfs::CpuSchedStat,
+        end: &procfs::CpuSchedStat,
+        duration: Duration,
+    ) -> SingleSchedModel {
+        let ns_pct = |ns_per_sec: f64| ns_per_sec / 10_000_000.0;
+        let waiting_ns_per_sec = count_per_sec!(begin.waiting_ns, end.waiting_ns, duration);
+        let timeslices_per_sec = count_per_sec!(begin.timeslices, end.timeslices, duration);
+        let avg_wait_usec = match (waiting_ns_per_sec, timeslices_per_sec) {
+            (Some(wait), Some(slices)) if slices > 0.0 => Some(wait / slices / 1000.0),
+            _ => None,
+        };
+        SingleSchedModel {
+            idx,
+            run_pct: count_per_sec!(begin.running_ns, end.running_ns, duration).map(ns_pct),
+            wait_pct: waiting_ns_per_sec.map(ns_pct),
+            timeslices_per_sec,
+            avg_wait_usec,
+        }
+    }
 }
 
 #[::below_derive::queriable_derives]
@@ -390,10 +797,21 @@ pub struct SingleSlabModel {
     pub num_caches: Option<u64>,
     pub active_size: Option<u64>,
     pub total_size: Option<u64>,
+    /// Change of the active objects since the last sample
+    pub active_objs_delta: Option<i64>,
+    /// Change of the total size since the last sample, in bytes
+    pub total_size_delta: Option<i64>,
+    /// Number of consecutive samples the total size grew in. Only counts
+    /// the last sample unless continued with `SystemModel::track_slab_growth`.
+    pub growth_streak: Option<u64>,
 }
 
 impl SingleSlabModel {
-    fn new(slabinfo: &procfs::SlabInfo) -> SingleSlabModel {
+    fn new(slabinfo: &procfs::SlabInfo, last: Option<&procfs::SlabInfo>) -> SingleSlabModel {
+        let total_size = opt_multiply(slabinfo.obj_size, slabinfo.num_objs);
+        let total_size_delta = last.and_then(|last| {
+            Some(total_size? as i64 - opt_multiply(last.obj_size, last.num_objs)? as i64)
+        });
         SingleSlabModel {
             name: slabinfo.name.clone(),
             active_objs: slabinfo.active_objs,
@@ -408,7 +826,11 @@ impl SingleSlabModel {
             ),
             num_caches: Some(1),
             active_size: opt_multiply(slabinfo.obj_size, slabinfo.active_objs),
-            total_size: opt_multiply(slabinfo.obj_size, slabinfo.num_objs),
+            total_size,
+            active_objs_delta: last
+                .and_then(|last| Some(slabinfo.active_objs? as i64 - last.active_objs? as i64)),
+            total_size_delta,
+            growth_streak: total_size_delta.map(|delta| (delta > 0) as u64),
         }
     }
 }
@@ -472,6 +894,139 @@ impl KsmModel {
     }
 }
 
+/// Bytes read from memory for each last level cache miss
+pub const CACHE_LINE_SIZE: u64 = 64;
+
+/// Last level cache activity counted with perf, of the whole system or of a
+/// cgroup. Memory bandwidth is estimated from the misses, which works without
+/// uncore or RDT counters but leaves out writebacks and prefetches.
+#[::below_derive::queriable_derives]
+pub struct PerfCounterModel {
+    /// Last level cache accesses
+    pub llc_references_per_sec: Option<f64>,
+    /// Last level cache accesses that had to go to memory
+    pub llc_misses_per_sec: Option<f64>,
+    /// Share of last level cache accesses that missed
+    pub llc_miss_pct: Option<f64>,
+    /// Memory read bandwidth, estimated as a cache line per last level cache
+    /// miss
+    pub mem_bw_bytes_per_sec: Option<f64>,
+}
+
+impl PerfCounterModel {
+    pub fn new(
+        sample: &PerfCounterSample,
+        last: Option<(&PerfCounterSample, Duration)>,
+    ) -> PerfCounterModel {
+        let (last, duration) = match last {
+            Some(last) => last,
+            None => return Default::default(),
+        };
+        let llc_references_per_sec = count_per_sec!(
+            Some(last.llc_references),
+            Some(sample.llc_references),
+            duration
+        );
+        let llc_misses_per_sec =
+            count_per_sec!(Some(last.llc_misses), Some(sample.llc_misses), duration);
+        PerfCounterModel {
+            llc_references_per_sec,
+            llc_misses_per_sec,
+            llc_miss_pct: match (llc_references_per_sec, llc_misses_per_sec) {
+                (Some(references), Some(misses)) if references > 0.0 => {
+                    Some(misses * 100.0 / references)
+                }
+                _ => None,
+            },
+            mem_bw_bytes_per_sec: llc_misses_per_sec.map(|misses| misses * CACHE_LINE_SIZE as f64),
+        }
+    }
+}
+
+/// Time spent collecting each part of a sample. Parts that were not
+/// collected, e.g. ethtool when disabled, are 0.
+#[::below_derive::queriable_derives]
+pub struct CollectionModel {
+    pub bpf_usecs: Option<u64>,
+    pub cgroup_usecs: Option<u64>,
+    pub process_usecs: Option<u64>,
+    pub system_usecs: Option<u64>,
+    pub network_usecs: Option<u64>,
+    pub ethtool_usecs: Option<u64>,
+    pub total_usecs: Option<u64>,
+    /// Events the BPF collectors lost since the last sample. Exited
+    /// processes are missing if any were lost.
+    pub bpf_lost_events: Option<u64>,
+    /// Failures to attach BPF programs since the recorder started
+    pub bpf_attach_failures: Option<u64>,
+    /// CPU time the BPF programs used since the last sample
+    pub bpf_prog_usecs: Option<u64>,
+    /// Where exited processes come from, "bpf" or the source the exitstat
+    /// collector fell back to where BPF is unsupported. None if they are
+    /// not collected.
+    pub exitstat_source: Option<String>,
+}
+
+impl CollectionModel {
+    /// BPF fields are None if no BPF collector ran, and the per sample ones
+    /// also without the `last` health of the BPF collectors.
+    pub fn new(
+        timing: &CollectionTiming,
+        bpf_stats: &BpfProgramStats,
+        last: Option<&BpfProgramStats>,
+    ) -> CollectionModel {
+        let (bpf_lost_events, bpf_attach_failures, bpf_prog_usecs) = if bpf_stats.is_empty() {
+            (None, None, None)
+        } else {
+            (
+                last.map(|last| {
+                    bpf_stats
+                        .iter()
+                        .map(|(name, stat)| {
+                            let last_lost = last.get(name).map_or(0, |last| last.lost_events);
+                            // Counters restart with the collector
+                            stat.lost_events
+                                .checked_sub(last_lost)
+                                .unwrap_or(stat.lost_events)
+                        })
+                        .sum()
+                }),
+                Some(bpf_stats.values().map(|stat| stat.attach_failures).sum()),
+                last.and_then(|last| {
+                    bpf_stats
+                        .iter()
+                        .filter_map(|(name, stat)| {
+                            let run_time_ns = stat.run_time_ns?;
+                            let last_run_time_ns = last.get(name)?.run_time_ns?;
+                            Some(
+                                run_time_ns
+                                    .checked_sub(last_run_time_ns)
+                                    .unwrap_or(run_time_ns)
+                                    / 1000,
+                            )
+                        })
+                        .reduce(|a, b| a + b)
+                }),
+            )
+        };
+        CollectionModel {
+            bpf_usecs: Some(timing.bpf_usecs),
+            cgroup_usecs: Some(timing.cgroup_usecs),
+            process_usecs: Some(timing.process_usecs),
+            system_usecs: Some(timing.system_usecs),
+            network_usecs: Some(timing.network_usecs),
+            ethtool_usecs: Some(timing.ethtool_usecs),
+            total_usecs: Some(timing.total_usecs),
+            bpf_lost_events,
+            bpf_attach_failures,
+            bpf_prog_usecs,
+            exitstat_source: bpf_stats
+                .get("exitstat")
+                .map(|stat| stat.fallback.clone().unwrap_or_else(|| "bpf".to_owned())),
+        }
+    }
+}
+
 #[::below_derive::queriable_derives]
 pub struct SingleDiskModel {
     pub name: Option<String>,
@@ -546,6 +1101,12 @@ impl SingleDiskModel {
     }
 }
 
+impl Nameable for SingleSlabModel {
+    fn name() -> &'static str {
+        "slab"
+    }
+}
+
 impl Nameable for SingleDiskModel {
     fn name() -> &'static str {
         "disk"
@@ -575,6 +1136,64 @@ impl Nameable for BtrfsModel {
     }
 }
 
+/// A device of a mounted btrfs filesystem. Error counters are cumulative and
+/// persist across mounts, any non-zero one hints at a failing device.
+#[::below_derive::queriable_derives]
+pub struct BtrfsDeviceModel {
+    pub path: Option<String>,
+    pub mount_point: Option<String>,
+    pub fsid: Option<String>,
+    pub devid: Option<u64>,
+    pub write_errs: Option<u64>,
+    pub read_errs: Option<u64>,
+    pub flush_errs: Option<u64>,
+    /// Checksum mismatches, i.e. silently corrupted data or metadata
+    pub corruption_errs: Option<u64>,
+    /// Blocks written in a different transaction than expected, e.g. lost
+    /// writes
+    pub generation_errs: Option<u64>,
+    /// "running" or "idle"
+    pub scrub_status: Option<String>,
+    /// How far the running scrub got through the device
+    pub scrub_progress_pct: Option<f64>,
+    pub scrub_bytes_scrubbed: Option<u64>,
+    pub scrub_corrected_errors: Option<u64>,
+    pub scrub_uncorrectable_errors: Option<u64>,
+}
+
+impl BtrfsDeviceModel {
+    fn new(stat: &btrfs::BtrfsDevStat) -> BtrfsDeviceModel {
+        let scrub = stat.scrub.as_ref();
+        BtrfsDeviceModel {
+            path: stat.path.clone(),
+            mount_point: stat.mount_point.clone(),
+            fsid: stat.fsid.clone(),
+            devid: stat.devid,
+            write_errs: stat.write_errs,
+            read_errs: stat.read_errs,
+            flush_errs: stat.flush_errs,
+            corruption_errs: stat.corruption_errs,
+            generation_errs: stat.generation_errs,
+            scrub_status: Some(if scrub.is_some() { "running" } else { "idle" }.to_owned()),
+            scrub_progress_pct: scrub.and_then(|scrub| {
+                stat.total_bytes
+                    .filter(|total| *total > 0)
+                    .map(|total| (scrub.last_physical as f64 * 100.0 / total as f64).min(100.0))
+            }),
+            scrub_bytes_scrubbed: scrub
+                .map(|scrub| scrub.data_bytes_scrubbed + scrub.tree_bytes_scrubbed),
+            scrub_corrected_errors: scrub.map(|scrub| scrub.corrected_errors),
+            scrub_uncorrectable_errors: scrub.map(|scrub| scrub.uncorrectable_errors),
+        }
+    }
+}
+
+impl Nameable for BtrfsDeviceModel {
+    fn name() -> &'static str {
+        "btrfs_device"
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -589,6 +1208,14 @@ mod test {
                 "idx": -1
             },
             "cpus": {},
+            "total_softirq": {
+                "idx": -1
+            },
+            "softirqs": {},
+            "total_sched": {
+                "idx": -1
+            },
+            "scheds": {},
             "mem": {},
             "vm": {},
             "slab": {},
@@ -608,4 +1235,197 @@ mod test {
             Some(Field::F64(42.0))
         );
     }
+
+    #[test]
+    fn softirq_rates() {
+        let stat = |net_rx, timer| procfs::SoftirqStat {
+            net_rx: Some(net_rx),
+            timer: Some(timer),
+            ..Default::default()
+        };
+        let mut last = SystemSample::default();
+        last.softirqs.insert(0, stat(100, 10));
+        last.softirqs.insert(1, stat(200, 20));
+        let mut sample = SystemSample::default();
+        sample.softirqs.insert(0, stat(300, 20));
+        sample.softirqs.insert(1, stat(600, 30));
+        // A CPU that came online in between has no rates yet
+        sample.softirqs.insert(2, stat(5, 5));
+
+        let model = SystemModel::new(&sample, Some((&last, Duration::from_secs(2))));
+        assert_eq!(model.softirqs[&0].net_rx_per_sec, Some(100.0));
+        assert_eq!(model.softirqs[&1].timer_per_sec, Some(5.0));
+        assert_eq!(model.softirqs[&2].idx, 2);
+        assert_eq!(model.softirqs[&2].net_rx_per_sec, None);
+        assert_eq!(model.softirqs[&0].hi_per_sec, None);
+        assert_eq!(model.total_softirq.idx, -1);
+        assert_eq!(
+            model.query(&SystemModelFieldId::from_str("softirq.net_rx_per_sec").unwrap()),
+            // The new CPU is left out of the total
+            Some(Field::F64(300.0))
+        );
+    }
+
+    #[test]
+    fn sched_rates() {
+        let stat = |running_ns, waiting_ns, timeslices| procfs::CpuSchedStat {
+            running_ns: Some(running_ns),
+            waiting_ns: Some(waiting_ns),
+            timeslices: Some(timeslices),
+        };
+        let mut last = SystemSample::default();
+        last.schedstat.insert(0, stat(0, 0, 0));
+        last.schedstat.insert(1, stat(0, 0, 0));
+        let mut sample = SystemSample::default();
+        sample
+            .schedstat
+            .insert(0, stat(1_000_000_000, 3_000_000_000, 1000));
+        sample.schedstat.insert(1, stat(500_000_000, 0, 0));
+        // A CPU that came online in between is left out of the total
+        sample
+            .schedstat
+            .insert(2, stat(2_000_000_000, 2_000_000_000, 100));
+
+        let model = SystemModel::new(&sample, Some((&last, Duration::from_secs(2))));
+        assert_eq!(model.scheds[&0].run_pct, Some(50.0));
+        assert_eq!(model.scheds[&0].wait_pct, Some(150.0));
+        assert_eq!(model.scheds[&0].timeslices_per_sec, Some(500.0));
+        assert_eq!(model.scheds[&0].avg_wait_usec, Some(3000.0));
+        // No timeslices, no average wait
+        assert_eq!(model.scheds[&1].avg_wait_usec, None);
+        assert_eq!(model.total_sched.idx, -1);
+        assert_eq!(model.total_sched.run_pct, Some(37.5));
+        assert_eq!(
+            model.query(&SystemModelFieldId::from_str("sched.avg_wait_usec").unwrap()),
+            Some(Field::F64(3000.0))
+        );
+    }
+
+    #[test]
+    fn cpufreq_throttles() {
+        let stat = |time_in_state: [(u64, u64); 2], core_throttle_count| procfs::CpuFreqStat {
+            cur_freq_khz: Some(1_000_000),
+            max_freq_khz: Some(3_000_000),
+            time_in_state: Some(BTreeMap::from(time_in_state)),
+            core_throttle_count: Some(core_throttle_count),
+            core_throttle_total_time_ms: Some(core_throttle_count * 10),
+            package_throttle_count: None,
+            package_throttle_total_time_ms: None,
+        };
+        let cpus = Some(BTreeMap::from([
+            (0, procfs::CpuStat::default()),
+            (1, procfs::CpuStat::default()),
+        ]));
+        let mut last = SystemSample::default();
+        last.stat.cpus_map = cpus.clone();
+        last.cpufreq
+            .insert(0, stat([(1_000_000, 100), (3_000_000, 100)], 5));
+        last.cpufreq
+            .insert(1, stat([(1_000_000, 100), (3_000_000, 100)], 5));
+        let mut sample = SystemSample::default();
+        sample.stat.cpus_map = cpus;
+        // A quarter of the interval at 1 GHz, the rest at 3 GHz
+        sample
+            .cpufreq
+            .insert(0, stat([(1_000_000, 150), (3_000_000, 250)], 5));
+        sample
+            .cpufreq
+            .insert(1, stat([(1_000_000, 300), (3_000_000, 100)], 8));
+
+        let model = SystemModel::new(&sample, Some((&last, Duration::from_secs(2))));
+        assert_eq!(model.cpus[&0].freq_hz, Some(2_500_000_000));
+        assert_eq!(model.cpus[&0].max_freq_hz, Some(3_000_000_000));
+        assert_eq!(model.cpus[&0].core_throttles, Some(0));
+        assert_eq!(model.cpus[&1].freq_hz, Some(1_000_000_000));
+        assert_eq!(model.cpus[&1].core_throttles, Some(3));
+        assert_eq!(model.cpus[&1].core_throttle_usec, Some(30_000));
+        assert_eq!(model.cpus[&1].package_throttles, None);
+        assert_eq!(model.total_cpu.freq_hz, Some(1_750_000_000));
+        assert_eq!(model.total_cpu.core_throttles, Some(3));
+
+        // Without a previous sample the current frequency is shown
+        let model = SystemModel::new(&sample, None);
+        assert_eq!(model.cpus[&0].freq_hz, Some(1_000_000_000));
+        assert_eq!(model.cpus[&0].core_throttles, None);
+    }
+
+    #[test]
+    fn btrfs_device_scrub() {
+        let idle = btrfs::BtrfsDevStat {
+            path: Some("/dev/vdb".to_owned()),
+            total_bytes: Some(1000),
+            corruption_errs: Some(2),
+            ..Default::default()
+        };
+        let model = BtrfsDeviceModel::new(&idle);
+        assert_eq!(model.corruption_errs, Some(2));
+        assert_eq!(model.scrub_status.as_deref(), Some("idle"));
+        assert_eq!(model.scrub_progress_pct, None);
+
+        let scrubbing = btrfs::BtrfsDevStat {
+            scrub: Some(btrfs::BtrfsScrubStat {
+                data_bytes_scrubbed: 200,
+                tree_bytes_scrubbed: 50,
+                uncorrectable_errors: 1,
+                last_physical: 250,
+                ..Default::default()
+            }),
+            ..idle
+        };
+        let model = BtrfsDeviceModel::new(&scrubbing);
+        assert_eq!(model.scrub_status.as_deref(), Some("running"));
+        assert_eq!(model.scrub_progress_pct, Some(25.0));
+        assert_eq!(model.scrub_bytes_scrubbed, Some(250));
+        assert_eq!(model.scrub_uncorrectable_errors, Some(1));
+    }
+
+    #[test]
+    fn slab_growth() {
+        let sample_with = |dentry_objs, inode_objs| {
+            let slab = |name: &str, num_objs| procfs::SlabInfo {
+                name: Some(name.to_owned()),
+                active_objs: Some(num_objs),
+                num_objs: Some(num_objs),
+                obj_size: Some(192),
+                ..Default::default()
+            };
+            let mut sample = SystemSample::default();
+            sample
+                .slabinfo
+                .insert("dentry".to_owned(), slab("dentry", dentry_objs));
+            sample
+                .slabinfo
+                .insert("inode_cache".to_owned(), slab("inode_cache", inode_objs));
+            sample
+        };
+        let samples = [
+            sample_with(100, 100),
+            sample_with(200, 150),
+            sample_with(300, 120),
+            sample_with(400, 130),
+        ];
+        let duration = Duration::from_secs(5);
+
+        let first = SystemModel::new(&samples[0], None);
+        assert_eq!(first.slab["dentry"].total_size_delta, None);
+        assert_eq!(first.slab["dentry"].growth_streak, None);
+
+        let mut last = SystemModel::new(&samples[1], Some((&samples[0], duration)));
+        assert_eq!(last.slab["dentry"].active_objs_delta, Some(100));
+        assert_eq!(last.slab["dentry"].total_size_delta, Some(19200));
+        assert_eq!(last.slab["TOTAL"].total_size_delta, Some(28800));
+        last.track_slab_growth(&first);
+        assert_eq!(last.slab["dentry"].growth_streak, Some(1));
+
+        for pair in samples[1..].windows(2) {
+            let mut model = SystemModel::new(&pair[1], Some((&pair[0], duration)));
+            model.track_slab_growth(&last);
+            last = model;
+        }
+        // dentry grew in every sample, inode_cache shrank in between
+        assert_eq!(last.slab["dentry"].growth_streak, Some(3));
+        assert_eq!(last.slab["inode_cache"].growth_streak, Some(1));
+        assert_eq!(last.slab["inode_cache"].total_size_delta, Some(1920));
+        assert_eq!(last.slab["TOTAL"].growth_streak, Some(3));
+    }
 }
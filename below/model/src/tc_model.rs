@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::time::Duration;
 
 use serde::Deserialize;
@@ -11,6 +12,7 @@ use crate::Field;
 use crate::FieldId;
 use crate::Nameable;
 use crate::Queriable;
+use crate::SELECTOR_WILDCARD;
 
 /// rate! macro calculates the rate of a field for given sample and last objects.
 /// It basically calls count_per_sec! macro after extracting the field from the objects.
@@ -37,7 +39,11 @@ impl TcModel {
             Some((last_tcs, d)) if last_tcs.len() == sample.len() => sample
                 .iter()
                 .zip(last_tcs.iter())
-                .map(|(sample, last)| SingleTcModel::new(sample, Some((last, d))))
+                .map(|(tc, last)| {
+                    let mut tc_model = SingleTcModel::new(tc, Some((last, d)));
+                    tc_model.path = qdisc_path(tc, sample);
+                    tc_model
+                })
                 .collect::<Vec<_>>(),
             _ => Vec::new(),
         };
@@ -46,14 +52,45 @@ impl TcModel {
     }
 }
 
+/// Drops per second of the root egress qdisc of each interface, keyed by
+/// interface name. Classful qdiscs count the drops of their children, so the
+/// root accounts for all packets dropped in the egress hierarchy.
+pub fn root_qdisc_drops_per_sec(
+    sample: &TcStats,
+    last: Option<(&TcStats, Duration)>,
+) -> BTreeMap<String, u64> {
+    let (last, d) = match last {
+        Some(last) => last,
+        None => return BTreeMap::new(),
+    };
+    sample
+        .iter()
+        .filter(|tc| tc.parent == TC_H_ROOT)
+        .filter_map(|tc| {
+            let l = last
+                .iter()
+                .find(|l| l.if_index == tc.if_index && l.handle == tc.handle)?;
+            let drops = count_per_sec!(l.stats.drops, tc.stats.drops, d, u64)?;
+            Some((tc.if_name.clone(), drops))
+        })
+        .collect()
+}
+
 #[below_derive::queriable_derives]
 pub struct SingleTcModel {
     /// Name of the interface
     pub interface: String,
     /// Name of the qdisc
     pub kind: String,
+    /// Position of the qdisc in the hierarchy of its interface, e.g.
+    /// "eth0/1:/1:10/8001:" for a qdisc attached to class 1:10 of the root
+    /// qdisc 1:
+    pub path: String,
 
+    /// Packets queued
     pub qlen: Option<u32>,
+    /// Bytes queued
+    pub backlog_bytes: Option<u32>,
     pub bps: Option<u32>,
     pub pps: Option<u32>,
 
@@ -87,6 +124,7 @@ impl SingleTcModel {
 
         let stats = &sample.stats;
         tc_model.qlen = stats.qlen;
+        tc_model.backlog_bytes = stats.backlog;
         tc_model.bps = stats.bps;
         tc_model.pps = stats.pps;
 
@@ -116,6 +154,55 @@ impl SingleTcModel {
     }
 }
 
+const TC_H_ROOT: u32 = 0xFFFFFFFF;
+const TC_H_INGRESS: u32 = 0xFFFFFFF1;
+const TC_H_MAJ_MASK: u32 = 0xFFFF0000;
+/// Bounds the walk up the hierarchy in case of a loop in the handles
+const MAX_QDISC_DEPTH: usize = 16;
+
+/// Format a handle like tc does, e.g. "1:" for a qdisc and "1:10" for a class
+fn format_handle(handle: u32) -> String {
+    match handle & !TC_H_MAJ_MASK {
+        0 => format!("{:x}:", handle >> 16),
+        minor => format!("{:x}:{:x}", handle >> 16, minor),
+    }
+}
+
+/// Path of `tc` from its interface through the qdiscs and classes it hangs
+/// off of, looked up among the qdiscs of `all`.
+fn qdisc_path(tc: &TcStat, all: &TcStats) -> String {
+    let mut segments = vec![format_handle(tc.handle)];
+    let mut current = tc;
+    for _ in 0..MAX_QDISC_DEPTH {
+        match current.parent {
+            TC_H_ROOT | 0 => break,
+            TC_H_INGRESS => {
+                segments.push("ingress".to_owned());
+                break;
+            }
+            parent => {
+                if parent & !TC_H_MAJ_MASK != 0 {
+                    segments.push(format_handle(parent));
+                }
+                match all.iter().find(|qdisc| {
+                    qdisc.if_index == current.if_index
+                        && qdisc.handle == parent & TC_H_MAJ_MASK
+                        && !std::ptr::eq(*qdisc, current)
+                }) {
+                    Some(qdisc) => {
+                        segments.push(format_handle(qdisc.handle));
+                        current = qdisc;
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+    segments.push(tc.if_name.clone());
+    segments.reverse();
+    segments.join("/")
+}
+
 #[below_derive::queriable_derives]
 pub struct QDiscModel {
     #[queriable(subquery)]
@@ -240,3 +327,66 @@ impl FqCodelXStatsModel {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn qdisc(if_index: u32, handle: u32, parent: u32) -> TcStat {
+        TcStat {
+            if_index,
+            if_name: format!("eth{}", if_index),
+            handle,
+            parent,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_qdisc_path() {
+        let tcs = vec![
+            qdisc(0, 0x10000, TC_H_ROOT),
+            qdisc(0, 0x80010000, 0x10010),
+            qdisc(0, 0xFFFF0000, TC_H_INGRESS),
+            // Same handle on another interface
+            qdisc(1, 0x10000, TC_H_ROOT),
+            // Parent not in the sample
+            qdisc(1, 0x80020000, 0x20001),
+        ];
+        let paths: Vec<_> = tcs.iter().map(|tc| qdisc_path(tc, &tcs)).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "eth0/1:",
+                "eth0/1:/1:10/8001:",
+                "eth0/ingress/ffff:",
+                "eth1/1:",
+                "eth1/2:1/8002:",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_root_qdisc_drops_per_sec() {
+        let with_drops = |mut tc: TcStat, drops| {
+            tc.stats.drops = Some(drops);
+            tc
+        };
+        let last = vec![
+            with_drops(qdisc(0, 0x10000, TC_H_ROOT), 10),
+            with_drops(qdisc(0, 0x80010000, 0x10010), 10),
+            with_drops(qdisc(0, 0xFFFF0000, TC_H_INGRESS), 10),
+            with_drops(qdisc(1, 0x10000, TC_H_ROOT), 5),
+        ];
+        let sample = vec![
+            with_drops(qdisc(0, 0x10000, TC_H_ROOT), 30),
+            with_drops(qdisc(0, 0x80010000, 0x10010), 30),
+            with_drops(qdisc(0, 0xFFFF0000, TC_H_INGRESS), 100),
+            // Root qdisc replaced since the last sample
+            with_drops(qdisc(1, 0x20000, TC_H_ROOT), 5),
+        ];
+        let drops = root_qdisc_drops_per_sec(&sample, Some((&last, Duration::from_secs(2))));
+        assert_eq!(drops, BTreeMap::from([("eth0".to_owned(), 10)]));
+        assert!(root_qdisc_drops_per_sec(&sample, None).is_empty());
+    }
+}
@@ -17,7 +17,7 @@ use std::cell::RefCell;
 use std::cell::RefMut;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
-use std::fs::File;
+use std::ffi::OsString;
 use std::io::BufRead;
 use std::io::BufReader;
 use std::io::ErrorKind;
@@ -41,7 +41,9 @@ use slog::debug;
 use thiserror::Error;
 use threadpool::ThreadPool;
 
+mod source;
 mod types;
+pub use source::*;
 pub use types::*;
 
 #[cfg(test)]
@@ -50,6 +52,7 @@ mod test;
 use common::util;
 
 pub const KSM_SYSFS: &str = "/sys/kernel/mm/ksm";
+pub const CPU_SYSFS: &str = "/sys/devices/system/cpu";
 pub const NET_SYSFS: &str = "/sys/class/net/";
 pub const NET_PROCFS: &str = "/proc/net";
 
@@ -158,17 +161,38 @@ macro_rules! parse_kb {
 
 pub struct ProcReader {
     path: PathBuf,
+    source: Arc<dyn SourceProvider>,
     threadpool: ThreadPool,
     buffer: RefCell<Vec<u8>>,
+    /// Number of threads reading pid directories in `read_all_pids`
+    workers: usize,
+    /// Whether `read_all_pids` opens the files of a pid directory together
+    batched_reads: bool,
 }
 
+/// Files of a pid directory opened together with batched reads, in the
+/// order `read_pids` reads them
+const BATCHED_PID_FILES: [&str; 4] = ["stat", "status", "io", "cgroup"];
+
+/// Pid directories below which `read_all_pids` does not bother spreading
+/// the work over threads
+const MIN_PIDS_PER_WORKER: usize = 64;
+
+/// Threads of the cmdline reader threadpool per `read_all_pids` worker
+const CMDLINE_THREADS_PER_WORKER: usize = 5;
+
 impl ProcReader {
     pub fn new() -> ProcReader {
         ProcReader {
             path: Path::new("/proc").to_path_buf(),
-            // 5 threads max
-            threadpool: ThreadPool::with_name("procreader_worker".to_string(), 5),
+            source: Arc::new(FsSourceProvider),
+            threadpool: ThreadPool::with_name(
+                "procreader_worker".to_string(),
+                CMDLINE_THREADS_PER_WORKER,
+            ),
             buffer: RefCell::new(Vec::new()),
+            workers: 1,
+            batched_reads: false,
         }
     }
 
@@ -178,8 +202,56 @@ impl ProcReader {
         reader
     }
 
+    /// Read procfs content rooted at `path` through `source` instead of
+    /// directly from the filesystem.
+    pub fn new_with_source_provider(path: PathBuf, source: Arc<dyn SourceProvider>) -> ProcReader {
+        let mut reader = ProcReader::new_with_custom_procfs(path);
+        reader.source = source;
+        reader
+    }
+
+    /// Read pid directories in `read_all_pids` with up to `workers` threads.
+    /// 0 or 1 reads them on the calling thread.
+    pub fn set_workers(&mut self, workers: usize) {
+        self.workers = workers.max(1);
+        // Workers wait on cmdline reads concurrently, so the pool grows with
+        // them to keep the reads from timing out in the queue
+        self.threadpool
+            .set_num_threads(CMDLINE_THREADS_PER_WORKER * self.workers);
+    }
+
+    /// Open the files of each pid directory in `read_all_pids` together
+    /// through `SourceProvider::open_in_dir`. For the filesystem this
+    /// resolves the pid directory once rather than once per file.
+    pub fn set_batched_reads(&mut self, batched_reads: bool) {
+        self.batched_reads = batched_reads;
+    }
+
+    /// Reader for a `read_all_pids` worker thread. It has its own buffer and
+    /// shares the source and cmdline threadpool.
+    fn worker_reader(&self) -> ProcReader {
+        ProcReader {
+            path: self.path.clone(),
+            source: Arc::clone(&self.source),
+            threadpool: self.threadpool.clone(),
+            buffer: RefCell::new(Vec::new()),
+            workers: 1,
+            batched_reads: self.batched_reads,
+        }
+    }
+
     fn read_file_to_str(&self, path: &Path) -> Result<RefMut<'_, str>> {
-        File::open(path)
+        self.read_opened_file_to_str(path, None)
+    }
+
+    /// As above, but reads `opened` instead of opening `path` if set
+    fn read_opened_file_to_str(
+        &self,
+        path: &Path,
+        opened: Option<std::io::Result<Box<dyn Read>>>,
+    ) -> Result<RefMut<'_, str>> {
+        opened
+            .unwrap_or_else(|| self.source.open(path))
             .and_then(|file| util::read_kern_file_to_internal_buffer(&self.buffer, file))
             .map_err(|e| Error::IoError(path.to_path_buf(), e))
     }
@@ -223,6 +295,69 @@ impl ProcReader {
         Ok(content.trim_matches('\n').trim().into())
     }
 
+    /// Read the random id the kernel generates on every boot
+    pub fn read_boot_id(&self) -> Result<String> {
+        let path = self.path.join("sys/kernel/random/boot_id");
+        let content = self.read_file_to_str(&path)?;
+        Ok(content.trim().into())
+    }
+
+    /// Read the number of hung tasks the kernel detected since boot. The
+    /// sysctl only exists on kernels with hung task detection that count
+    /// the detections.
+    pub fn read_hung_task_detect_count(&self) -> Result<u64> {
+        let path = self.path.join("sys/kernel/hung_task_detect_count");
+        let content = self.read_file_to_str(&path)?;
+        let count = content.trim();
+        parse_item!(path, Some(count), u64, count)?.ok_or(Error::InvalidFileFormat(path))
+    }
+
+    /// Read the number of bits of entropy available to the kernel random
+    /// number generator
+    pub fn read_entropy_avail(&self) -> Result<u64> {
+        let path = self.path.join("sys/kernel/random/entropy_avail");
+        let content = self.read_file_to_str(&path)?;
+        let entropy = content.trim();
+        parse_item!(path, Some(entropy), u64, entropy)?.ok_or(Error::InvalidFileFormat(path))
+    }
+
+    /// Read the seconds since boot from /proc/uptime
+    pub fn read_uptime(&self) -> Result<f64> {
+        let path = self.path.join("uptime");
+        let content = self.read_file_to_str(&path)?;
+        // Format is like "350735.47 234388.90", the second item being the
+        // idle time summed over all CPUs
+        let line = content.trim();
+        parse_item!(path, line.split_ascii_whitespace().next(), f64, line)?
+            .ok_or(Error::InvalidFileFormat(path))
+    }
+
+    pub fn read_loadavg(&self) -> Result<LoadAvg> {
+        let path = self.path.join("loadavg");
+        let content = self.read_file_to_str(&path)?;
+        // Format is like "0.52 0.58 0.59 2/1234 56789", the last item being
+        // the most recently created pid
+        let line = content.trim();
+        let mut items = line.split_ascii_whitespace();
+        let mut loadavg = LoadAvg {
+            load1: parse_item!(&path, items.next(), f64, line)?,
+            load5: parse_item!(&path, items.next(), f64, line)?,
+            load15: parse_item!(&path, items.next(), f64, line)?,
+            ..Default::default()
+        };
+        if let Some(tasks) = items.next() {
+            let (runnable, total) = tasks
+                .split_once('/')
+                .ok_or_else(|| Error::InvalidFileFormat(path.clone()))?;
+            loadavg.runnable_tasks = parse_item!(&path, Some(runnable), u64, line)?;
+            loadavg.total_tasks = parse_item!(&path, Some(total), u64, line)?;
+        }
+        if loadavg.load15.is_none() {
+            return Err(Error::InvalidFileFormat(path));
+        }
+        Ok(loadavg)
+    }
+
     pub fn read_stat(&self) -> Result<Stat> {
         let path = self.path.join("stat");
         let content = self.read_file_to_str(&path)?;
@@ -424,6 +559,135 @@ impl ProcReader {
         Ok(slab_info_map)
     }
 
+    pub fn read_softirqs(&self) -> Result<SoftirqMap> {
+        let path = self.path.join("softirqs");
+        let content = self.read_file_to_str(&path)?;
+        let mut lines = content.lines();
+
+        // The first line names the online CPUs, e.g. "    CPU0       CPU2",
+        // followed by one line of per-CPU counts for each softirq type:
+        //
+        //           HI:          1          0
+        //        TIMER:    4513245    4286117
+        //
+        let header = match lines.next() {
+            Some(header) => header,
+            None => return Err(Error::InvalidFileFormat(path)),
+        };
+        let cpus = header
+            .split_ascii_whitespace()
+            .map(|cpu| match cpu.strip_prefix("CPU") {
+                Some(idx) => parse_item!(&path, Some(idx), u32, header).map(Option::unwrap),
+                None => Err(Error::UnexpectedLine(path.clone(), header.to_string())),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut softirq_map: SoftirqMap =
+            cpus.iter().map(|cpu| (*cpu, Default::default())).collect();
+
+        for line in lines {
+            let mut items = line.split_ascii_whitespace();
+            let name = match items.next() {
+                Some(name) => name,
+                None => continue,
+            };
+            for cpu in &cpus {
+                let count = parse_item!(&path, items.next(), u64, line)?;
+                let stat = softirq_map.get_mut(cpu).expect("bug: missing cpu");
+                match name {
+                    "HI:" => stat.hi = count,
+                    "TIMER:" => stat.timer = count,
+                    "NET_TX:" => stat.net_tx = count,
+                    "NET_RX:" => stat.net_rx = count,
+                    "BLOCK:" => stat.block = count,
+                    "IRQ_POLL:" => stat.irq_poll = count,
+                    "TASKLET:" => stat.tasklet = count,
+                    "SCHED:" => stat.sched = count,
+                    "HRTIMER:" => stat.hrtimer = count,
+                    "RCU:" => stat.rcu = count,
+                    _ => {}
+                }
+            }
+        }
+
+        if softirq_map.is_empty() {
+            Err(Error::InvalidFileFormat(path))
+        } else {
+            Ok(softirq_map)
+        }
+    }
+
+    pub fn read_schedstat(&self) -> Result<SchedStatMap> {
+        let path = self.path.join("schedstat");
+        let content = self.read_file_to_str(&path)?;
+        let mut schedstat_map = SchedStatMap::new();
+
+        // Besides the "version" and "timestamp" lines, there is a line per
+        // online CPU followed by lines of its sched domains:
+        //
+        //   cpu0 0 0 0 0 0 0 2482049853 317265402 1102384
+        //   domain0 00000003 1 0 0 ...
+        //
+        // The last three fields of a CPU line are the time spent running
+        // and waiting on the runqueue in ns and the number of timeslices.
+        for line in content.lines() {
+            let mut items = line.split_ascii_whitespace();
+            let item = match items.next() {
+                Some(item) => item,
+                None => continue,
+            };
+            if item == "version" {
+                // Older versions order the CPU fields differently
+                let version = parse_item!(&path, items.next(), u32, line)?;
+                if version.unwrap_or(0) < 15 {
+                    return Err(Error::UnexpectedLine(path, line.to_string()));
+                }
+            } else if let Some(cpu_suffix) = item.strip_prefix("cpu") {
+                let cpu_id = parse_item!(&path, Some(cpu_suffix), u32, line)?.unwrap();
+                let stat = CpuSchedStat {
+                    running_ns: parse_item!(&path, items.nth(6), u64, line)?,
+                    waiting_ns: parse_item!(&path, items.next(), u64, line)?,
+                    timeslices: parse_item!(&path, items.next(), u64, line)?,
+                };
+                if schedstat_map.insert(cpu_id, stat).is_some() {
+                    return Err(Error::UnexpectedLine(path, line.to_string()));
+                }
+            }
+        }
+
+        if schedstat_map.is_empty() {
+            Err(Error::InvalidFileFormat(path))
+        } else {
+            Ok(schedstat_map)
+        }
+    }
+
+    /// Read the full line of /proc/pressure/irq. The file only exists on
+    /// kernels with IRQ time accounting, 6.1 and up.
+    pub fn read_irq_pressure(&self) -> Result<PressureMetrics> {
+        let path = self.path.join("pressure/irq");
+        let content = self.read_file_to_str(&path)?;
+
+        // full avg10=0.00 avg60=0.00 avg300=0.00 total=7153
+        for line in content.lines() {
+            let mut items = line.split_ascii_whitespace();
+            if items.next() != Some("full") {
+                continue;
+            }
+            let mut pressure: PressureMetrics = Default::default();
+            for item in items {
+                match item.split_once('=') {
+                    Some(("avg10", v)) => pressure.avg10 = parse_item!(path, Some(v), f64, line)?,
+                    Some(("avg60", v)) => pressure.avg60 = parse_item!(path, Some(v), f64, line)?,
+                    Some(("avg300", v)) => pressure.avg300 = parse_item!(path, Some(v), f64, line)?,
+                    Some(("total", v)) => pressure.total = parse_item!(path, Some(v), u64, line)?,
+                    _ => {}
+                }
+            }
+            return Ok(pressure);
+        }
+        Err(Error::InvalidFileFormat(path))
+    }
+
     fn read_disk_fsinfo(&self, mount_info: &MountInfo) -> Option<(f32, u64)> {
         if let Some(mount_point) = &mount_info.mount_point {
             if let Ok(stat) = sys::statvfs::statvfs(Path::new(&mount_point)) {
@@ -537,9 +801,14 @@ impl ProcReader {
         }
     }
 
-    fn read_pid_stat_from_path<P: AsRef<Path>>(&self, path: P) -> Result<PidStat> {
+    /// `opened` is the stat file if it was already opened
+    fn read_pid_stat_from_path<P: AsRef<Path>>(
+        &self,
+        path: P,
+        opened: Option<std::io::Result<Box<dyn Read>>>,
+    ) -> Result<PidStat> {
         let path = path.as_ref().join("stat");
-        let content = self.read_file_to_str(&path)?;
+        let content = self.read_opened_file_to_str(&path, opened)?;
         let mut pidstat: PidStat = Default::default();
 
         let mut line = content.to_string();
@@ -574,7 +843,9 @@ impl ProcReader {
                 18 => pidstat.num_threads = parse_item!(path, Some(item), u64, line)?,
                 20 => {
                     let uptime = self.read_uptime_secs()?;
-                    pidstat.running_secs = parse_sec!(path, Some(item), line)?
+                    pidstat.start_secs_since_boot = parse_sec!(path, Some(item), line)?;
+                    pidstat.running_secs = pidstat
+                        .start_secs_since_boot
                         .map(|running_secs_since_boot| (uptime - running_secs_since_boot) as u64);
                 }
                 22 => {
@@ -594,19 +865,24 @@ impl ProcReader {
     }
 
     pub fn read_pid_stat(&self, pid: u32) -> Result<PidStat> {
-        self.read_pid_stat_from_path(self.path.join(pid.to_string()))
+        self.read_pid_stat_from_path(self.path.join(pid.to_string()), None)
     }
 
     pub fn read_tid_stat(&self, tid: u32) -> Result<PidStat> {
         let mut p = self.path.join(tid.to_string());
         p.push("task");
         p.push(tid.to_string());
-        self.read_pid_stat_from_path(p)
+        self.read_pid_stat_from_path(p, None)
     }
 
-    fn read_pid_status_from_path<P: AsRef<Path>>(&self, path: P) -> Result<PidStatus> {
+    /// `opened` is the status file if it was already opened
+    fn read_pid_status_from_path<P: AsRef<Path>>(
+        &self,
+        path: P,
+        opened: Option<std::io::Result<Box<dyn Read>>>,
+    ) -> Result<PidStatus> {
         let path = path.as_ref().join("status");
-        let content = self.read_file_to_str(&path)?;
+        let content = self.read_opened_file_to_str(&path, opened)?;
         let mut pidstatus: PidStatus = Default::default();
 
         for line in content.lines() {
@@ -635,12 +911,66 @@ impl ProcReader {
     }
 
     pub fn read_pid_mem(&self, pid: u32) -> Result<PidStatus> {
-        self.read_pid_status_from_path(self.path.join(pid.to_string()))
+        self.read_pid_status_from_path(self.path.join(pid.to_string()), None)
     }
 
-    fn read_pid_io_from_path<P: AsRef<Path>>(&self, path: P) -> Result<PidIo> {
-        let path = path.as_ref().join("io");
+    fn read_pid_smaps_rollup_from_path<P: AsRef<Path>>(&self, path: P) -> Result<PidSmapsRollup> {
+        let path = path.as_ref().join("smaps_rollup");
         let content = self.read_file_to_str(&path)?;
+        let mut rollup: PidSmapsRollup = Default::default();
+
+        for line in content.lines() {
+            let mut items = line.split(':');
+            if let Some(item) = items.next() {
+                let mut values = items.flat_map(|s| s.split_ascii_whitespace());
+                match item {
+                    "Rss" => rollup.rss = parse_kb!(path, values.next(), line)?,
+                    "Pss" => rollup.pss = parse_kb!(path, values.next(), line)?,
+                    "Pss_Anon" => rollup.pss_anon = parse_kb!(path, values.next(), line)?,
+                    "Pss_File" => rollup.pss_file = parse_kb!(path, values.next(), line)?,
+                    "Pss_Shmem" => rollup.pss_shmem = parse_kb!(path, values.next(), line)?,
+                    "Shared_Clean" => rollup.shared_clean = parse_kb!(path, values.next(), line)?,
+                    "Shared_Dirty" => rollup.shared_dirty = parse_kb!(path, values.next(), line)?,
+                    "Private_Clean" => rollup.private_clean = parse_kb!(path, values.next(), line)?,
+                    "Private_Dirty" => rollup.private_dirty = parse_kb!(path, values.next(), line)?,
+                    "AnonHugePages" => {
+                        rollup.anon_huge_pages = parse_kb!(path, values.next(), line)?
+                    }
+                    "ShmemPmdMapped" => {
+                        rollup.shmem_pmd_mapped = parse_kb!(path, values.next(), line)?
+                    }
+                    "FilePmdMapped" => {
+                        rollup.file_pmd_mapped = parse_kb!(path, values.next(), line)?
+                    }
+                    "Swap" => rollup.swap = parse_kb!(path, values.next(), line)?,
+                    "SwapPss" => rollup.swap_pss = parse_kb!(path, values.next(), line)?,
+                    _ => {}
+                }
+            }
+        }
+
+        if rollup == Default::default() {
+            Err(Error::InvalidFileFormat(path))
+        } else {
+            Ok(rollup)
+        }
+    }
+
+    /// Reading smaps_rollup takes the mmap lock of the process and walks
+    /// all of its mappings, so it is much more expensive than the other
+    /// per-pid files and not part of `read_all_pids`.
+    pub fn read_pid_smaps_rollup(&self, pid: u32) -> Result<PidSmapsRollup> {
+        self.read_pid_smaps_rollup_from_path(self.path.join(pid.to_string()))
+    }
+
+    /// `opened` is the io file if it was already opened
+    fn read_pid_io_from_path<P: AsRef<Path>>(
+        &self,
+        path: P,
+        opened: Option<std::io::Result<Box<dyn Read>>>,
+    ) -> Result<PidIo> {
+        let path = path.as_ref().join("io");
+        let content = self.read_opened_file_to_str(&path, opened)?;
         let mut pidio: PidIo = Default::default();
 
         for line in content.lines() {
@@ -662,12 +992,17 @@ impl ProcReader {
     }
 
     pub fn read_pid_io(&self, pid: u32) -> Result<PidIo> {
-        self.read_pid_io_from_path(self.path.join(pid.to_string()))
+        self.read_pid_io_from_path(self.path.join(pid.to_string()), None)
     }
 
-    fn read_pid_cgroup_from_path<P: AsRef<Path>>(&self, path: P) -> Result<String> {
+    /// `opened` is the cgroup file if it was already opened
+    fn read_pid_cgroup_from_path<P: AsRef<Path>>(
+        &self,
+        path: P,
+        opened: Option<std::io::Result<Box<dyn Read>>>,
+    ) -> Result<String> {
         let path = path.as_ref().join("cgroup");
-        let content = self.read_file_to_str(&path)?;
+        let content = self.read_opened_file_to_str(&path, opened)?;
 
         let mut cgroup_path = None;
         for line in content.lines() {
@@ -692,16 +1027,21 @@ impl ProcReader {
     }
 
     pub fn read_pid_cgroup(&self, pid: u32) -> Result<String> {
-        self.read_pid_cgroup_from_path(self.path.join(pid.to_string()))
+        self.read_pid_cgroup_from_path(self.path.join(pid.to_string()), None)
     }
 
     pub fn read_pid_cmdline(&self, pid: u32) -> Result<Option<Vec<String>>> {
         self.read_pid_cmdline_from_path(self.path.join(pid.to_string()))
     }
 
-    fn read_pid_cmdline_from_path_blocking<P: AsRef<Path>>(path: P) -> Result<Option<Vec<String>>> {
+    fn read_pid_cmdline_from_path_blocking<P: AsRef<Path>>(
+        source: &dyn SourceProvider,
+        path: P,
+    ) -> Result<Option<Vec<String>>> {
         let path = path.as_ref().join("cmdline");
-        let mut file = File::open(&path).map_err(|e| Error::IoError(path.clone(), e))?;
+        let mut file = source
+            .open(&path)
+            .map_err(|e| Error::IoError(path.clone(), e))?;
         let mut buf = [0; 4096];
         let bytes_read = file
             .read(&mut buf)
@@ -731,11 +1071,12 @@ impl ProcReader {
     /// run from a high priority binary).
     fn read_pid_cmdline_from_path<P: AsRef<Path>>(&self, path: P) -> Result<Option<Vec<String>>> {
         let path = path.as_ref().to_owned();
+        let source = Arc::clone(&self.source);
         let cmdline_data = Arc::new((Mutex::new(None), Condvar::new()));
         let cmdline_data_clone = Arc::clone(&cmdline_data);
 
         self.threadpool.execute(move || {
-            let result = Self::read_pid_cmdline_from_path_blocking(path);
+            let result = Self::read_pid_cmdline_from_path_blocking(source.as_ref(), path);
             let (mutex, cvar) = &*cmdline_data_clone;
             *mutex.lock() = Some(result);
             cvar.notify_one();
@@ -752,7 +1093,8 @@ impl ProcReader {
 
     fn read_pid_exe_path_from_path<P: AsRef<Path>>(&self, path: P) -> Result<String> {
         let path = path.as_ref().join("exe");
-        std::fs::read_link(path.clone())
+        self.source
+            .read_link(&path)
             .map_err(|e| Error::IoError(path, e))
             .map(|p| p.to_string_lossy().into_owned())
     }
@@ -775,36 +1117,64 @@ impl ProcReader {
     }
 
     pub fn read_all_pids(&self) -> Result<PidMap> {
-        let mut pidmap: PidMap = Default::default();
-        for entry in
-            std::fs::read_dir(&self.path).map_err(|e| Error::IoError(self.path.clone(), e))?
-        {
-            let entry = match entry {
-                Err(ref e)
-                    if e.raw_os_error()
-                        .map_or(false, |ec| ec == 2 || ec == 3 /* ENOENT or ESRCH */) =>
-                {
-                    continue;
+        let names = self
+            .source
+            .list_dirs(&self.path)
+            .map_err(|e| Error::IoError(self.path.clone(), e))?;
+        let workers = self.workers.min(names.len() / MIN_PIDS_PER_WORKER).max(1);
+        let pidmap = if workers == 1 {
+            self.read_pids(&names)?
+        } else {
+            let chunk_size = names.len().div_ceil(workers);
+            std::thread::scope(|scope| {
+                let handles = names
+                    .chunks(chunk_size)
+                    .map(|chunk| {
+                        let reader = self.worker_reader();
+                        std::thread::Builder::new()
+                            .name("procreader_pids".to_string())
+                            .spawn_scoped(scope, move || reader.read_pids(chunk))
+                            .expect("Failed to spawn pid reader thread")
+                    })
+                    .collect::<Vec<_>>();
+                let mut pidmap = PidMap::new();
+                for handle in handles {
+                    let pids = handle.join().expect("Pid reader thread panicked")?;
+                    pidmap.extend(pids);
                 }
-                ent => ent.map_err(|e| Error::IoError(self.path.clone(), e))?,
-            };
+                Ok::<_, Error>(pidmap)
+            })?
+        };
 
-            if !entry
-                .file_type()
-                .map_err(|e| Error::IoError(self.path.clone(), e))?
-                .is_dir()
-            {
-                continue;
-            }
+        if pidmap == Default::default() {
+            Err(Error::InvalidFileFormat(self.path.clone()))
+        } else {
+            Ok(pidmap)
+        }
+    }
 
-            let pid = match Self::ascii_digits_to_i32(entry.file_name().as_bytes()) {
+    /// Read the pid directories among `names`. Processes that exit while
+    /// being read are skipped.
+    fn read_pids(&self, names: &[OsString]) -> Result<PidMap> {
+        let mut pidmap: PidMap = Default::default();
+        for name in names {
+            let pid = match Self::ascii_digits_to_i32(name.as_bytes()) {
                 Some(pid) => pid,
                 None => continue,
             };
+            let pid_path = self.path.join(name);
+            // Opened in BATCHED_PID_FILES order, or each file is opened as
+            // it is read
+            let mut opened = if self.batched_reads {
+                self.source.open_in_dir(&pid_path, &BATCHED_PID_FILES)
+            } else {
+                Vec::new()
+            }
+            .into_iter();
 
             let mut pidinfo: PidInfo = Default::default();
 
-            match self.read_pid_stat_from_path(entry.path()) {
+            match self.read_pid_stat_from_path(&pid_path, opened.next()) {
                 Err(Error::IoError(_, ref e))
                     if e.raw_os_error()
                         .map_or(false, |ec| ec == 2 || ec == 3 /* ENOENT or ESRCH */) =>
@@ -814,7 +1184,7 @@ impl ProcReader {
                 res => pidinfo.stat = res?,
             }
 
-            match self.read_pid_status_from_path(entry.path()) {
+            match self.read_pid_status_from_path(&pid_path, opened.next()) {
                 Err(Error::IoError(_, ref e))
                     if e.raw_os_error()
                         .map_or(false, |ec| ec == 2 || ec == 3 /* ENOENT or ESRCH */) =>
@@ -824,7 +1194,7 @@ impl ProcReader {
                 res => pidinfo.status = res?,
             }
 
-            match self.read_pid_io_from_path(entry.path()) {
+            match self.read_pid_io_from_path(&pid_path, opened.next()) {
                 Err(Error::IoError(_, ref e))
                     if e.raw_os_error().map_or(false, |ec| {
                         ec == 2 || ec == 3 /* ENOENT or ESRCH */
@@ -842,7 +1212,7 @@ impl ProcReader {
                 res => pidinfo.io = res?,
             }
 
-            match self.read_pid_cgroup_from_path(entry.path()) {
+            match self.read_pid_cgroup_from_path(&pid_path, opened.next()) {
                 Err(Error::IoError(_, ref e))
                     if e.raw_os_error()
                         .map_or(false, |ec| ec == 2 || ec == 3 /* ENOENT or ESRCH */) =>
@@ -852,7 +1222,7 @@ impl ProcReader {
                 res => pidinfo.cgroup = res?,
             }
 
-            match self.read_pid_cmdline_from_path(entry.path()) {
+            match self.read_pid_cmdline_from_path(&pid_path) {
                 Err(Error::IoError(_, ref e))
                     if e.raw_os_error()
                         .map_or(false, |ec| ec == 2 || ec == 3 /* ENOENT or ESRCH */) =>
@@ -865,18 +1235,13 @@ impl ProcReader {
             // Swallow the error since reading the /proc/pid/exe
             // 1. will need root permission to trace link
             // 2. Even with root permission, some exe will have broken link, kworker for example.
-            if let Ok(s) = self.read_pid_exe_path_from_path(entry.path()) {
+            if let Ok(s) = self.read_pid_exe_path_from_path(&pid_path) {
                 pidinfo.exe_path = Some(s);
             }
 
             pidmap.insert(pid, pidinfo);
         }
-
-        if pidmap == Default::default() {
-            Err(Error::InvalidFileFormat(self.path.clone()))
-        } else {
-            Ok(pidmap)
-        }
+        Ok(pidmap)
     }
 }
 
@@ -930,10 +1295,15 @@ macro_rules! get_val_from_stats_map {
     }
 }
 
+/// Reads interface stats from /sys/class/net and protocol stats from
+/// /proc/net. Either may be missing, e.g. in a chroot or an initramfs, in
+/// which case their stats are None.
 pub struct NetReader {
     logger: slog::Logger,
-    interface_dir: Dir,
-    proc_net_dir: Dir,
+    interface_path: PathBuf,
+    interface_dir: Option<Dir>,
+    proc_net_path: PathBuf,
+    proc_net_dir: Option<Dir>,
 }
 
 impl NetReader {
@@ -946,18 +1316,51 @@ impl NetReader {
         interface_path: PathBuf,
         proc_net_path: PathBuf,
     ) -> Result<NetReader> {
-        let interface_dir =
-            Dir::open(&interface_path).map_err(|e| Error::IoError(interface_path, e))?;
-        let proc_net_dir =
-            Dir::open(&proc_net_path).map_err(|e| Error::IoError(proc_net_path, e))?;
+        let open = |path: &PathBuf| match Dir::open(path) {
+            Ok(dir) => Ok(Some(dir)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Error::IoError(path.clone(), e)),
+        };
+        let interface_dir = open(&interface_path)?;
+        let proc_net_dir = open(&proc_net_path)?;
 
         Ok(NetReader {
             logger,
+            interface_path,
             interface_dir,
+            proc_net_path,
             proc_net_dir,
         })
     }
 
+    /// Path of the interface directory if it is missing
+    pub fn missing_interface_dir(&self) -> Option<&Path> {
+        match self.interface_dir {
+            Some(_) => None,
+            None => Some(&self.interface_path),
+        }
+    }
+
+    /// Path of the /proc/net directory if it is missing
+    pub fn missing_proc_net_dir(&self) -> Option<&Path> {
+        match self.proc_net_dir {
+            Some(_) => None,
+            None => Some(&self.proc_net_path),
+        }
+    }
+
+    fn interface_dir(&self) -> Result<&Dir> {
+        self.interface_dir
+            .as_ref()
+            .ok_or_else(|| Error::IoError(self.interface_path.clone(), ErrorKind::NotFound.into()))
+    }
+
+    fn proc_net_dir(&self) -> Result<&Dir> {
+        self.proc_net_dir
+            .as_ref()
+            .ok_or_else(|| Error::IoError(self.proc_net_path.clone(), ErrorKind::NotFound.into()))
+    }
+
     fn read_iface_stat(
         interface_dir: &Dir,
         cur_path: &PathBuf,
@@ -985,19 +1388,54 @@ impl NetReader {
         }
     }
 
+    /// Read a single line attribute of the interface. Some attributes (e.g.
+    /// speed and duplex) fail with EINVAL when they do not apply, which is
+    /// treated the same as a missing file.
+    fn read_iface_attr<T: FromStr>(
+        link_dir: &Dir,
+        cur_path: &Path,
+        attr: &str,
+    ) -> Result<Option<T>> {
+        let mut content = String::new();
+        match link_dir
+            .open_file(attr)
+            .and_then(|mut file| file.read_to_string(&mut content))
+        {
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound || e.raw_os_error() == Some(22) => {
+                return Ok(None);
+            }
+            Err(e) => return Err(Error::IoError(cur_path.join(attr), e)),
+        }
+        let line = content.lines().next().unwrap_or_default();
+        line.parse::<T>()
+            .map(Some)
+            .map_err(|_| Error::UnexpectedLine(cur_path.join(attr), line.to_owned()))
+    }
+
     fn read_all_iface_stats(&self, interface: &str, cur_path: &PathBuf) -> Result<InterfaceStat> {
         let interface_dir = self
-            .interface_dir
+            .interface_dir()?
             .read_link(interface)
             .map_err(|e| Error::IoError(cur_path.clone(), e))?;
-        let stats_dir = self
-            .interface_dir
+        let link_dir = self
+            .interface_dir()?
             .sub_dir(interface_dir.as_path())
-            .map_err(|e| Error::IoError(interface_dir, e))?
+            .map_err(|e| Error::IoError(interface_dir, e))?;
+        let stats_dir = link_dir
             .sub_dir("statistics")
             .map_err(|e| Error::IoError(cur_path.clone(), e))?;
-        let cur_path = cur_path.join(interface).join("statistics");
-        let mut net_stat: InterfaceStat = Default::default();
+        let link_path = cur_path.join(interface);
+        let cur_path = link_path.join("statistics");
+        let mut net_stat = InterfaceStat {
+            operstate: Self::read_iface_attr(&link_dir, &link_path, "operstate")?,
+            // Reported as -1 when the speed is unknown
+            speed: Self::read_iface_attr::<i64>(&link_dir, &link_path, "speed")?
+                .and_then(|speed| u64::try_from(speed).ok()),
+            duplex: Self::read_iface_attr(&link_dir, &link_path, "duplex")?,
+            carrier_changes: Self::read_iface_attr(&link_dir, &link_path, "carrier_changes")?,
+            ..Default::default()
+        };
         parse_interface_stats!(
             net_stat,
             stats_dir,
@@ -1033,12 +1471,12 @@ impl NetReader {
     fn read_net_map(&self) -> Result<NetMap> {
         let mut netmap: NetMap = Default::default();
         let cur_path = self
-            .interface_dir
+            .interface_dir()?
             .recover_path()
             .unwrap_or_else(|_| NET_SYSFS.into());
 
         for entry in self
-            .interface_dir
+            .interface_dir()?
             .list_dir(".")
             .map_err(|e| Error::IoError(cur_path.clone(), e))?
             .filter_map(|entry| match entry {
@@ -1061,12 +1499,12 @@ impl NetReader {
     // format like /proc/net/netstat. Key will be in "{title}_{field}" format
     fn read_kv_diff_line(&self, stats_filename: &str) -> Result<BTreeMap<String, u64>> {
         let cur_path = self
-            .proc_net_dir
+            .proc_net_dir()?
             .recover_path()
             .unwrap_or_else(|_| NET_PROCFS.into())
             .join(stats_filename);
         let stats_file = self
-            .proc_net_dir
+            .proc_net_dir()?
             .open_file(stats_filename)
             .map_err(|e| Error::IoError(cur_path.clone(), e))?;
 
@@ -1120,12 +1558,12 @@ impl NetReader {
 
     fn read_kv_same_line(&self, stats_filename: &str) -> Result<BTreeMap<String, u64>> {
         let cur_path = self
-            .proc_net_dir
+            .proc_net_dir()?
             .recover_path()
             .unwrap_or_else(|_| NET_PROCFS.into())
             .join(stats_filename);
         let stats_file = self
-            .proc_net_dir
+            .proc_net_dir()?
             .open_file(stats_filename)
             .map_err(|e| Error::IoError(cur_path.clone(), e))?;
         let buf_reader = BufReader::new(stats_file);
@@ -1156,6 +1594,83 @@ impl NetReader {
         Ok(res)
     }
 
+    // format like /proc/net/sockstat, i.e. "{title}: {field} {value} ..."
+    // on each line. Key will be in "{title}_{field}" format
+    fn read_kv_pairs_line(&self, stats_filename: &str) -> Result<BTreeMap<String, u64>> {
+        let cur_path = self
+            .proc_net_dir()?
+            .recover_path()
+            .unwrap_or_else(|_| NET_PROCFS.into())
+            .join(stats_filename);
+        let stats_file = self
+            .proc_net_dir()?
+            .open_file(stats_filename)
+            .map_err(|e| Error::IoError(cur_path.clone(), e))?;
+        let buf_reader = BufReader::new(stats_file);
+
+        let mut res = BTreeMap::new();
+        for line in buf_reader.lines() {
+            let line = match line {
+                Ok(l) => l,
+                _ => continue,
+            };
+
+            let (key_header, kvs) = line
+                .split_once(':')
+                .ok_or_else(|| Error::InvalidFileFormat(cur_path.clone()))?;
+            let kvs = kvs.split_ascii_whitespace().collect::<Vec<&str>>();
+            if kvs.len() % 2 != 0 {
+                return Err(Error::InvalidFileFormat(cur_path));
+            }
+
+            for kv in kvs.chunks(2) {
+                res.insert(
+                    format!("{}_{}", key_header, kv[0]),
+                    kv[1].parse::<u64>().map_err(|_| Error::ParseError {
+                        line: line.clone(),
+                        item: kv[1].into(),
+                        type_name: "u64".into(),
+                        path: cur_path.clone(),
+                    })?,
+                );
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn read_sockstat(sockstat_map: &BTreeMap<String, u64>) -> SockStat {
+        get_val_from_stats_map!(
+            sockstat_map,
+            SockStat {
+                sockets_used: "sockets_used",
+                tcp_inuse: "TCP_inuse",
+                tcp_orphan: "TCP_orphan",
+                tcp_tw: "TCP_tw",
+                tcp_alloc: "TCP_alloc",
+                tcp_mem: "TCP_mem",
+                udp_inuse: "UDP_inuse",
+                udp_mem: "UDP_mem",
+                raw_inuse: "RAW_inuse",
+                frag_inuse: "FRAG_inuse",
+                frag_memory: "FRAG_memory",
+            }
+        )
+    }
+
+    fn read_sockstat6(sockstat6_map: &BTreeMap<String, u64>) -> SockStat6 {
+        get_val_from_stats_map!(
+            sockstat6_map,
+            SockStat6 {
+                tcp_inuse: "TCP6_inuse",
+                udp_inuse: "UDP6_inuse",
+                raw_inuse: "RAW6_inuse",
+                frag_inuse: "FRAG6_inuse",
+                frag_memory: "FRAG6_memory",
+            }
+        )
+    }
+
     fn read_tcp_stat(snmp_map: &BTreeMap<String, u64>) -> TcpStat {
         get_val_from_stats_map!(
             snmp_map,
@@ -1340,6 +1855,8 @@ impl NetReader {
         let snmp_map = handle_enoent(&self.logger, self.read_kv_diff_line("snmp"))?;
         let snmp6_map = handle_enoent(&self.logger, self.read_kv_same_line("snmp6"))?;
         let iface_map = handle_enoent(&self.logger, self.read_net_map())?;
+        let sockstat_map = handle_enoent(&self.logger, self.read_kv_pairs_line("sockstat"))?;
+        let sockstat6_map = handle_enoent(&self.logger, self.read_kv_pairs_line("sockstat6"))?;
 
         Ok(NetStat {
             interfaces: iface_map,
@@ -1352,6 +1869,8 @@ impl NetReader {
             icmp6: snmp6_map.as_ref().map(Self::read_icmp6_stat),
             udp: snmp_map.as_ref().map(Self::read_udp_stat),
             udp6: snmp6_map.as_ref().map(Self::read_udp6_stat),
+            sockstat: sockstat_map.as_ref().map(Self::read_sockstat),
+            sockstat6: sockstat6_map.as_ref().map(Self::read_sockstat6),
         })
     }
 }
@@ -1430,6 +1949,89 @@ impl KsmReader {
     }
 }
 
+pub struct CpuFreqReader {
+    path: PathBuf,
+}
+
+impl Default for CpuFreqReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CpuFreqReader {
+    pub fn new() -> CpuFreqReader {
+        CpuFreqReader {
+            path: Path::new(CPU_SYSFS).to_path_buf(),
+        }
+    }
+
+    pub fn new_with_custom_path(path: PathBuf) -> CpuFreqReader {
+        CpuFreqReader { path }
+    }
+
+    /// Read frequency and throttle counters of all CPUs that have any
+    pub fn read_cpufreq(&self) -> Result<CpuFreqMap> {
+        let mut cpufreq_map = CpuFreqMap::new();
+        let entries =
+            std::fs::read_dir(&self.path).map_err(|e| Error::IoError(self.path.clone(), e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| Error::IoError(self.path.clone(), e))?;
+            let cpu_id = match entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("cpu"))
+                .and_then(|suffix| suffix.parse::<u32>().ok())
+            {
+                Some(cpu_id) => cpu_id,
+                // Not a CPU, e.g. cpufreq or cpuidle
+                None => continue,
+            };
+            let cpu_path = entry.path();
+            let read = |name: &str| -> Option<u64> {
+                std::fs::read_to_string(cpu_path.join(name))
+                    .ok()?
+                    .trim()
+                    .parse()
+                    .ok()
+            };
+            let stat = CpuFreqStat {
+                cur_freq_khz: read("cpufreq/scaling_cur_freq"),
+                max_freq_khz: read("cpufreq/cpuinfo_max_freq"),
+                time_in_state: self.read_time_in_state(&cpu_path),
+                core_throttle_count: read("thermal_throttle/core_throttle_count"),
+                core_throttle_total_time_ms: read("thermal_throttle/core_throttle_total_time_ms"),
+                package_throttle_count: read("thermal_throttle/package_throttle_count"),
+                package_throttle_total_time_ms: read(
+                    "thermal_throttle/package_throttle_total_time_ms",
+                ),
+            };
+            if stat != CpuFreqStat::default() {
+                cpufreq_map.insert(cpu_id, stat);
+            }
+        }
+        Ok(cpufreq_map)
+    }
+
+    /// Parse "<frequency> <time>" lines of cpufreq/stats/time_in_state,
+    /// only present with CONFIG_CPU_FREQ_STAT
+    fn read_time_in_state(&self, cpu_path: &Path) -> Option<BTreeMap<u64, u64>> {
+        let content = std::fs::read_to_string(cpu_path.join("cpufreq/stats/time_in_state")).ok()?;
+        let time_in_state = content
+            .lines()
+            .map(|line| {
+                let (freq, time) = line.split_once(' ')?;
+                Some((freq.trim().parse().ok()?, time.trim().parse().ok()?))
+            })
+            .collect::<Option<BTreeMap<_, _>>>()?;
+        if time_in_state.is_empty() {
+            None
+        } else {
+            Some(time_in_state)
+        }
+    }
+}
+
 /// Wraps the result into an `Option` if the result is not an error.
 /// If the error is of type `ENOENT`, it is returned as `Ok(None)`.
 /// Else, the error itself is returned.
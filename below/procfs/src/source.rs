@@ -0,0 +1,163 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::Cursor;
+use std::io::Read;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::FromRawFd;
+use std::path::Path;
+use std::path::PathBuf;
+
+use nix::fcntl::OFlag;
+use nix::sys::stat::Mode;
+
+/// Where `ProcReader` gets raw procfs content from.
+///
+/// The default implementation, `FsSourceProvider`, reads the real
+/// filesystem. Alternative implementations can serve synthetic trees, fuzz
+/// inputs or platforms with a different procfs layout without staging files
+/// on disk. Paths passed in are always rooted at the reader's procfs path.
+pub trait SourceProvider: Send + Sync {
+    /// Open the file at `path` for reading.
+    fn open(&self, path: &Path) -> std::io::Result<Box<dyn Read>>;
+
+    /// Resolve the symlink at `path`.
+    fn read_link(&self, path: &Path) -> std::io::Result<PathBuf>;
+
+    /// List the names of the directories directly under `path`. Entries that
+    /// disappear while listing are skipped.
+    fn list_dirs(&self, path: &Path) -> std::io::Result<Vec<OsString>>;
+
+    /// Open the files `names` in the directory at `path` for reading, e.g.
+    /// the files of a pid directory, returning a result per name. Opens
+    /// them one by one by default.
+    fn open_in_dir(&self, path: &Path, names: &[&str]) -> Vec<std::io::Result<Box<dyn Read>>> {
+        names
+            .iter()
+            .map(|name| self.open(&path.join(name)))
+            .collect()
+    }
+}
+
+/// Reads procfs content straight from the filesystem.
+#[derive(Default)]
+pub struct FsSourceProvider;
+
+impl SourceProvider for FsSourceProvider {
+    fn open(&self, path: &Path) -> std::io::Result<Box<dyn Read>> {
+        Ok(Box::new(File::open(path)?))
+    }
+
+    fn read_link(&self, path: &Path) -> std::io::Result<PathBuf> {
+        std::fs::read_link(path)
+    }
+
+    fn list_dirs(&self, path: &Path) -> std::io::Result<Vec<OsString>> {
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let entry = match entry {
+                Err(ref e)
+                    if e.raw_os_error()
+                        .map_or(false, |ec| ec == 2 || ec == 3 /* ENOENT or ESRCH */) =>
+                {
+                    continue;
+                }
+                ent => ent?,
+            };
+
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name());
+            }
+        }
+        Ok(names)
+    }
+
+    /// Resolves `path` once and opens the files relative to it, which saves
+    /// walking the whole path, including the pid lookup in /proc, per file.
+    fn open_in_dir(&self, path: &Path, names: &[&str]) -> Vec<std::io::Result<Box<dyn Read>>> {
+        let dir = match File::options()
+            .read(true)
+            .custom_flags(libc::O_PATH | libc::O_DIRECTORY)
+            .open(path)
+        {
+            Ok(dir) => dir,
+            // Leave reporting the error to opening each file
+            Err(_) => {
+                return names
+                    .iter()
+                    .map(|name| self.open(&path.join(name)))
+                    .collect();
+            }
+        };
+        names
+            .iter()
+            .map(|name| {
+                let fd = nix::fcntl::openat(
+                    dir.as_raw_fd(),
+                    *name,
+                    OFlag::O_RDONLY | OFlag::O_CLOEXEC,
+                    Mode::empty(),
+                )
+                .map_err(|errno| std::io::Error::from_raw_os_error(errno as i32))?;
+                // Nothing else owns the fd just opened
+                Ok(Box::new(unsafe { File::from_raw_fd(fd) }) as Box<dyn Read>)
+            })
+            .collect()
+    }
+}
+
+/// Wraps another provider and snapshots the raw text of every file read into
+/// `record_dir`, mirroring the original absolute path. The snapshot can later
+/// be replayed with `ProcReader::new_with_custom_procfs` to debug parser
+/// issues offline. Symlink targets are not recorded.
+pub struct RecordingSourceProvider<P: SourceProvider> {
+    inner: P,
+    record_dir: PathBuf,
+}
+
+impl<P: SourceProvider> RecordingSourceProvider<P> {
+    pub fn new(inner: P, record_dir: PathBuf) -> RecordingSourceProvider<P> {
+        RecordingSourceProvider { inner, record_dir }
+    }
+
+    fn record_path(&self, path: &Path) -> PathBuf {
+        self.record_dir.join(path.strip_prefix("/").unwrap_or(path))
+    }
+}
+
+impl<P: SourceProvider> SourceProvider for RecordingSourceProvider<P> {
+    fn open(&self, path: &Path) -> std::io::Result<Box<dyn Read>> {
+        let mut content = Vec::new();
+        self.inner.open(path)?.read_to_end(&mut content)?;
+
+        let record_path = self.record_path(path);
+        if let Some(parent) = record_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&record_path, &content)?;
+
+        Ok(Box::new(Cursor::new(content)))
+    }
+
+    fn read_link(&self, path: &Path) -> std::io::Result<PathBuf> {
+        self.inner.read_link(path)
+    }
+
+    fn list_dirs(&self, path: &Path) -> std::io::Result<Vec<OsString>> {
+        self.inner.list_dirs(path)
+    }
+}
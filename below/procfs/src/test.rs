@@ -12,18 +12,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::ffi::OsString;
 use std::fs::File;
+use std::io::Cursor;
+use std::io::Read;
 use std::io::Write;
 use std::os::unix::fs::symlink;
 use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
 
+use below_testutil::ProcessFixture;
+use below_testutil::ProcfsBuilder;
 use slog::Drain;
 use tempfile::TempDir;
 
 use crate::types::*;
+use crate::CpuFreqReader;
+use crate::FsSourceProvider;
 use crate::KsmReader;
 use crate::NetReader;
 use crate::ProcReader;
+use crate::RecordingSourceProvider;
+use crate::SourceProvider;
 use crate::PAGE_SIZE;
 
 struct TestProcfs {
@@ -127,6 +140,18 @@ impl TestProcfs {
         let path = interface_dir.join(p);
         self.create_file_with_content_full_path(path, content.to_string().as_bytes());
     }
+
+    /// Create a link attribute file, e.g. operstate, for an interface
+    /// already created by `create_net_stat_file_with_content`.
+    fn create_net_link_file_with_content<P: AsRef<Path>>(
+        &self,
+        interface: &str,
+        p: P,
+        content: &str,
+    ) {
+        let path = self.path().join(interface).join(p);
+        self.create_file_with_content_full_path(path, content.as_bytes());
+    }
 }
 
 fn get_logger() -> slog::Logger {
@@ -147,6 +172,78 @@ fn test_kernel_version() {
     assert_eq!(kernel_version, "1.2.3");
 }
 
+#[test]
+fn test_boot_id() {
+    let procfs = TestProcfs::new();
+    procfs.create_dir("sys/kernel/random");
+    procfs.create_file_with_content(
+        "sys/kernel/random/boot_id",
+        b"0f3c7e4e-5a5b-4d7c-9f1e-2b8c6d1a9e30\n",
+    );
+    let reader = procfs.get_reader();
+    let boot_id = reader.read_boot_id().expect("Fail to read boot id");
+    assert_eq!(boot_id, "0f3c7e4e-5a5b-4d7c-9f1e-2b8c6d1a9e30");
+}
+
+#[test]
+fn test_read_hung_task_detect_count() {
+    let procfs = TestProcfs::new();
+    let reader = procfs.get_reader();
+    // Kernels without the sysctl
+    assert!(reader.read_hung_task_detect_count().is_err());
+
+    procfs.create_dir("sys/kernel");
+    procfs.create_file_with_content("sys/kernel/hung_task_detect_count", b"3\n");
+    assert_eq!(
+        reader
+            .read_hung_task_detect_count()
+            .expect("Failed to read hung task detect count"),
+        3
+    );
+
+    procfs.create_file_with_content("sys/kernel/hung_task_detect_count", b"x\n");
+    assert!(reader.read_hung_task_detect_count().is_err());
+}
+
+#[test]
+fn test_read_entropy_avail() {
+    let procfs = TestProcfs::new();
+    procfs.create_dir("sys/kernel/random");
+    procfs.create_file_with_content("sys/kernel/random/entropy_avail", b"256\n");
+    let reader = procfs.get_reader();
+    assert_eq!(
+        reader
+            .read_entropy_avail()
+            .expect("Failed to read entropy_avail"),
+        256
+    );
+}
+
+#[test]
+fn test_read_uptime() {
+    let procfs = TestProcfs::new();
+    procfs.create_file_with_content("uptime", b"350735.47 234388.90\n");
+    let reader = procfs.get_reader();
+    let uptime = reader.read_uptime().expect("Failed to read uptime");
+    assert!((uptime - 350735.47).abs() < 1e-6);
+}
+
+#[test]
+fn test_read_loadavg() {
+    let procfs = TestProcfs::new();
+    procfs.create_file_with_content("loadavg", b"0.52 0.58 1.59 2/1234 56789\n");
+    let reader = procfs.get_reader();
+    let loadavg = reader.read_loadavg().expect("Failed to read loadavg");
+    assert_eq!(loadavg.load1, Some(0.52));
+    assert_eq!(loadavg.load5, Some(0.58));
+    assert_eq!(loadavg.load15, Some(1.59));
+    assert_eq!(loadavg.runnable_tasks, Some(2));
+    assert_eq!(loadavg.total_tasks, Some(1234));
+
+    procfs.create_file_with_content("loadavg", b"0.52 0.58\n");
+    assert!(reader.read_loadavg().is_err());
+}
+
 #[test]
 fn test_stat_success() {
     let stat = b"cpu  152068189 10802578 74452328 5513630980 5288390 0 1767719 0 0 0
@@ -647,6 +744,46 @@ fn test_ksm() {
     assert_eq!(ksm.use_zero_pages, Some(0));
 }
 
+#[test]
+fn test_read_cpufreq() {
+    let procfs = TestProcfs::new();
+    procfs.create_dir("cpu0/cpufreq/stats");
+    procfs.create_dir("cpu0/thermal_throttle");
+    procfs.create_file_with_content("cpu0/cpufreq/scaling_cur_freq", b"2100000\n");
+    procfs.create_file_with_content("cpu0/cpufreq/cpuinfo_max_freq", b"3500000\n");
+    procfs.create_file_with_content(
+        "cpu0/cpufreq/stats/time_in_state",
+        b"3500000 120\n2100000 4500\n",
+    );
+    procfs.create_file_with_content("cpu0/thermal_throttle/core_throttle_count", b"7\n");
+    procfs.create_file_with_content(
+        "cpu0/thermal_throttle/core_throttle_total_time_ms",
+        b"350\n",
+    );
+    procfs.create_file_with_content("cpu0/thermal_throttle/package_throttle_count", b"2\n");
+    // CPU without cpufreq or thermal_throttle, e.g. in a VM
+    procfs.create_dir("cpu1");
+    // Not CPUs
+    procfs.create_dir("cpufreq");
+    procfs.create_file_with_content("online", b"0-1\n");
+
+    let reader = CpuFreqReader::new_with_custom_path(procfs.path().to_path_buf());
+    let cpufreq = reader.read_cpufreq().expect("Failed to read cpufreq");
+    assert_eq!(cpufreq.keys().copied().collect::<Vec<_>>(), vec![0]);
+    assert_eq!(
+        cpufreq[&0],
+        CpuFreqStat {
+            cur_freq_khz: Some(2100000),
+            max_freq_khz: Some(3500000),
+            time_in_state: Some(BTreeMap::from([(2100000, 4500), (3500000, 120)])),
+            core_throttle_count: Some(7),
+            core_throttle_total_time_ms: Some(350),
+            package_throttle_count: Some(2),
+            package_throttle_total_time_ms: None,
+        }
+    );
+}
+
 #[test]
 fn test_disk_stat() {
     let diskstats = b"   1       0 ram0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0
@@ -991,6 +1128,56 @@ cancelled_write_bytes: 5431947264
     assert_eq!(pidio.wbytes, Some(284070445056));
 }
 
+#[test]
+fn test_pid_smaps_rollup() {
+    let smaps_rollup =
+        b"55e4bd3d0000-7ffd4f9e9000 ---p 00000000 00:00 0                          [rollup]
+Rss:              124404 kB
+Pss:               70210 kB
+Pss_Dirty:         60004 kB
+Pss_Anon:          58920 kB
+Pss_File:          11278 kB
+Pss_Shmem:            12 kB
+Shared_Clean:      50120 kB
+Shared_Dirty:       4096 kB
+Private_Clean:     13004 kB
+Private_Dirty:     57184 kB
+Referenced:       120000 kB
+Anonymous:         99284 kB
+LazyFree:              0 kB
+AnonHugePages:     40960 kB
+ShmemPmdMapped:        0 kB
+FilePmdMapped:      2048 kB
+Shared_Hugetlb:        0 kB
+Private_Hugetlb:       0 kB
+Swap:               8812 kB
+SwapPss:            4406 kB
+Locked:                4 kB
+";
+
+    let procfs = TestProcfs::new();
+    procfs.create_pid_file_with_content(1024, "smaps_rollup", smaps_rollup);
+    let reader = procfs.get_reader();
+    let rollup = reader
+        .read_pid_smaps_rollup(1024)
+        .expect("Failed to read pid smaps_rollup file");
+
+    assert_eq!(rollup.rss, Some(124404 * 1024));
+    assert_eq!(rollup.pss, Some(70210 * 1024));
+    assert_eq!(rollup.pss_anon, Some(58920 * 1024));
+    assert_eq!(rollup.pss_file, Some(11278 * 1024));
+    assert_eq!(rollup.pss_shmem, Some(12 * 1024));
+    assert_eq!(rollup.shared_clean, Some(50120 * 1024));
+    assert_eq!(rollup.shared_dirty, Some(4096 * 1024));
+    assert_eq!(rollup.private_clean, Some(13004 * 1024));
+    assert_eq!(rollup.private_dirty, Some(57184 * 1024));
+    assert_eq!(rollup.anon_huge_pages, Some(40960 * 1024));
+    assert_eq!(rollup.shmem_pmd_mapped, Some(0));
+    assert_eq!(rollup.file_pmd_mapped, Some(2048 * 1024));
+    assert_eq!(rollup.swap, Some(8812 * 1024));
+    assert_eq!(rollup.swap_pss, Some(4406 * 1024));
+}
+
 #[test]
 fn test_pid_cgroupv2() {
     let cgroup = b"0::/user.slice/user:with:colon.slice/session-3.scope
@@ -1198,6 +1385,103 @@ nonvoluntary_ctxt_switches:	37733";
     );
 }
 
+#[test]
+fn test_read_all_pids_parallel() {
+    let procfs = TestProcfs::new();
+    for pid in 1..=500 {
+        let stat = format!(
+            "{pid} (proc{pid}) S 1 {pid} {pid} 0 -1 4194304 100 0 10 0 150 50 0 0 20 0 1 0 0 \
+            268435456 4096 18446744073709551615 1 1 0 0 0 0 0 0 0 0 0 0 17 {cpu} 0 0 0 0 0",
+            pid = pid,
+            cpu = pid % 8,
+        );
+        procfs.create_pid_file_with_content(pid, "stat", stat.as_bytes());
+        procfs.create_pid_file_with_content(pid, "status", b"VmSize:\t  262144 kB\n");
+        procfs.create_pid_file_with_content(pid, "io", b"read_bytes: 4096\nwrite_bytes: 8192\n");
+        procfs.create_pid_file_with_content(
+            pid,
+            "cgroup",
+            format!("0::/unit{}\n", pid % 7).as_bytes(),
+        );
+        procfs.create_pid_file_with_content(pid, "cmdline", b"proc\0");
+    }
+    // Exited while being read
+    procfs.create_pid_file_with_content(501, "cgroup", b"0::/\n");
+
+    let serial = procfs
+        .get_reader()
+        .read_all_pids()
+        .expect("Failed to get all pids");
+    let mut reader = procfs.get_reader();
+    reader.set_workers(4);
+    let parallel = reader.read_all_pids().expect("Failed to get all pids");
+
+    assert_eq!(serial.len(), 500);
+    assert_eq!(parallel[&500].cgroup, "/unit3");
+    // Cmdlines are read with a timeout, so they may be missing under load,
+    // and running_secs is relative to the uptime at the time of the read
+    let comparable = |mut pidmap: PidMap| {
+        for pidinfo in pidmap.values_mut() {
+            pidinfo.cmdline_vec = None;
+            pidinfo.stat.running_secs = None;
+        }
+        pidmap
+    };
+    assert_eq!(comparable(parallel), comparable(serial));
+}
+
+#[test]
+fn test_read_all_pids_fixture() {
+    let procfs = ProcfsBuilder::synthetic(2_000, 100)
+        .process(ProcessFixture::new(30_000).comm("a) (b").state('D'))
+        .process(ProcessFixture::kernel_thread(30_001))
+        // Exited between listing /proc and reading its files
+        .process(ProcessFixture::new(30_002).without("status"))
+        .build()
+        .expect("Failed to write procfs");
+    let mut reader = ProcReader::new_with_custom_procfs(procfs.path().to_path_buf());
+    reader.set_workers(4);
+    let pidmap = reader.read_all_pids().expect("Failed to get all pids");
+
+    assert_eq!(pidmap.len(), 2_002);
+    assert_eq!(pidmap[&2_000].cgroup, "/slice12.slice/unit3.service");
+    assert_eq!(pidmap[&30_000].stat.comm.as_deref(), Some("a) (b"));
+    assert_eq!(
+        pidmap[&30_000].stat.state,
+        Some(PidState::UninterruptibleSleep)
+    );
+    assert_eq!(pidmap[&30_001].stat.state, Some(PidState::Idle));
+    assert_eq!(pidmap[&30_001].exe_path, None);
+    assert!(!pidmap.contains_key(&30_002));
+}
+
+#[test]
+fn test_read_all_pids_batched() {
+    let procfs = ProcfsBuilder::synthetic(200, 10)
+        .process(ProcessFixture::new(30_000).without("io"))
+        // Exited between listing /proc and reading its files
+        .process(ProcessFixture::new(30_002).without("status"))
+        .build()
+        .expect("Failed to write procfs");
+    let serial = ProcReader::new_with_custom_procfs(procfs.path().to_path_buf())
+        .read_all_pids()
+        .expect("Failed to get all pids");
+    let mut reader = ProcReader::new_with_custom_procfs(procfs.path().to_path_buf());
+    reader.set_batched_reads(true);
+    let batched = reader.read_all_pids().expect("Failed to get all pids");
+
+    assert!(!batched.contains_key(&30_000));
+    assert!(!batched.contains_key(&30_002));
+    // Cmdlines are read with a timeout, so they may be missing under load
+    let without_cmdlines = |mut pidmap: PidMap| {
+        for pidinfo in pidmap.values_mut() {
+            pidinfo.cmdline_vec = None;
+        }
+        pidmap
+    };
+    assert_eq!(without_cmdlines(batched), without_cmdlines(serial));
+}
+
 fn write_net_map(netsysfs: &TestProcfs) {
     for interface in &["enp1s0", "enp2s0"] {
         netsysfs.create_net_stat_file_with_content(interface, "collisions", 1);
@@ -1225,6 +1509,12 @@ fn write_net_map(netsysfs: &TestProcfs) {
         netsysfs.create_net_stat_file_with_content(interface, "tx_packets", 23);
         netsysfs.create_net_stat_file_with_content(interface, "tx_window_errors", 24);
     }
+    netsysfs.create_net_link_file_with_content("enp1s0", "operstate", "up\n");
+    netsysfs.create_net_link_file_with_content("enp1s0", "speed", "25000\n");
+    netsysfs.create_net_link_file_with_content("enp1s0", "duplex", "full\n");
+    netsysfs.create_net_link_file_with_content("enp1s0", "carrier_changes", "3\n");
+    netsysfs.create_net_link_file_with_content("enp2s0", "operstate", "down\n");
+    netsysfs.create_net_link_file_with_content("enp2s0", "speed", "-1\n");
 }
 
 fn write_net_snmp(netsysfs: &TestProcfs) {
@@ -1354,12 +1644,31 @@ IpExt: 0 0 72982 72982 26227 6841 3021953584043 3021942373821 11953543 11953543
     netsysfs.create_file_with_content("netstat", netstat);
 }
 
+fn write_net_sockstat(netsysfs: &TestProcfs) {
+    let sockstat = b"sockets: used 1043
+TCP: inuse 44 orphan 2 tw 17 alloc 321 mem 12
+UDP: inuse 9 mem 4
+UDPLITE: inuse 0
+RAW: inuse 1
+FRAG: inuse 0 memory 0
+";
+    let sockstat6 = b"TCP6: inuse 38
+UDP6: inuse 7
+UDPLITE6: inuse 0
+RAW6: inuse 2
+FRAG6: inuse 1 memory 1024
+";
+    netsysfs.create_file_with_content("sockstat", sockstat);
+    netsysfs.create_file_with_content("sockstat6", sockstat6);
+}
+
 #[test]
 fn test_read_net_stat() {
     let netsysfs = TestProcfs::new();
     write_net_snmp(&netsysfs);
     write_net_snmp6(&netsysfs);
     write_net_netstat(&netsysfs);
+    write_net_sockstat(&netsysfs);
     write_net_map(&netsysfs);
     let netstat = netsysfs
         .get_net_reader()
@@ -1374,6 +1683,8 @@ fn test_read_net_stat() {
     verify_icmp6(&netstat);
     verify_udp(&netstat);
     verify_udp6(&netstat);
+    verify_sockstat(&netstat);
+    verify_sockstat6(&netstat);
     verify_interfaces(&netstat);
 }
 
@@ -1396,6 +1707,35 @@ fn test_read_enoent() {
     assert_eq!(netstat.icmp6, None);
     assert_eq!(netstat.udp, None);
     assert_eq!(netstat.udp6, None);
+    assert_eq!(netstat.sockstat, None);
+    assert_eq!(netstat.sockstat6, None);
+}
+
+#[test]
+fn test_read_missing_net_dirs() {
+    let netsysfs = TestProcfs::new();
+    write_net_snmp(&netsysfs);
+    let iface_dir = netsysfs.path().join("iface");
+
+    // No /sys/class/net, e.g. in a chroot
+    let reader =
+        NetReader::new_with_custom_path(get_logger(), iface_dir.clone(), netsysfs.path().into())
+            .expect("Fail to construct Net Reader");
+    assert_eq!(reader.missing_interface_dir(), Some(iface_dir.as_path()));
+    assert_eq!(reader.missing_proc_net_dir(), None);
+    let netstat = reader.read_netstat().expect("Fail to get NetStat");
+    assert_eq!(netstat.interfaces, None);
+    verify_tcp(&netstat);
+
+    // Neither, e.g. in an initramfs without /proc/net
+    let reader =
+        NetReader::new_with_custom_path(get_logger(), iface_dir, netsysfs.path().join("net"))
+            .expect("Fail to construct Net Reader");
+    assert!(reader.missing_proc_net_dir().is_some());
+    assert_eq!(
+        reader.read_netstat().expect("Fail to get NetStat"),
+        Default::default()
+    );
 }
 
 #[test]
@@ -1552,6 +1892,33 @@ fn verify_udp6(netstat: &NetStat) {
     assert_eq!(udp6.ignored_multi, Some(0));
 }
 
+fn verify_sockstat(netstat: &NetStat) {
+    let sockstat = netstat.sockstat.as_ref().expect("Fail to collect sockstat");
+    assert_eq!(sockstat.sockets_used, Some(1043));
+    assert_eq!(sockstat.tcp_inuse, Some(44));
+    assert_eq!(sockstat.tcp_orphan, Some(2));
+    assert_eq!(sockstat.tcp_tw, Some(17));
+    assert_eq!(sockstat.tcp_alloc, Some(321));
+    assert_eq!(sockstat.tcp_mem, Some(12));
+    assert_eq!(sockstat.udp_inuse, Some(9));
+    assert_eq!(sockstat.udp_mem, Some(4));
+    assert_eq!(sockstat.raw_inuse, Some(1));
+    assert_eq!(sockstat.frag_inuse, Some(0));
+    assert_eq!(sockstat.frag_memory, Some(0));
+}
+
+fn verify_sockstat6(netstat: &NetStat) {
+    let sockstat6 = netstat
+        .sockstat6
+        .as_ref()
+        .expect("Fail to collect sockstat6");
+    assert_eq!(sockstat6.tcp_inuse, Some(38));
+    assert_eq!(sockstat6.udp_inuse, Some(7));
+    assert_eq!(sockstat6.raw_inuse, Some(2));
+    assert_eq!(sockstat6.frag_inuse, Some(1));
+    assert_eq!(sockstat6.frag_memory, Some(1024));
+}
+
 fn verify_interfaces(netstat: &NetStat) {
     let netmap = netstat
         .interfaces
@@ -1584,6 +1951,16 @@ fn verify_interfaces(netstat: &NetStat) {
         assert_eq!(netstat.tx_packets, Some(23));
         assert_eq!(netstat.tx_window_errors, Some(24));
     }
+    let enp1s0 = netmap.get("enp1s0").expect("Fail to find interface");
+    assert_eq!(enp1s0.operstate.as_deref(), Some("up"));
+    assert_eq!(enp1s0.speed, Some(25000));
+    assert_eq!(enp1s0.duplex.as_deref(), Some("full"));
+    assert_eq!(enp1s0.carrier_changes, Some(3));
+    let enp2s0 = netmap.get("enp2s0").expect("Fail to find interface");
+    assert_eq!(enp2s0.operstate.as_deref(), Some("down"));
+    assert_eq!(enp2s0.speed, None);
+    assert_eq!(enp2s0.duplex, None);
+    assert_eq!(enp2s0.carrier_changes, None);
 }
 
 #[test]
@@ -1758,3 +2135,222 @@ fn test_read_mountinfo() {
         "/dev/vda1"
     );
 }
+
+struct InMemorySource {
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl SourceProvider for InMemorySource {
+    fn open(&self, path: &Path) -> std::io::Result<Box<dyn Read>> {
+        self.files
+            .get(path)
+            .map(|content| Box::new(Cursor::new(content.clone())) as Box<dyn Read>)
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))
+    }
+
+    fn read_link(&self, _path: &Path) -> std::io::Result<PathBuf> {
+        Err(std::io::Error::from(std::io::ErrorKind::NotFound))
+    }
+
+    fn list_dirs(&self, path: &Path) -> std::io::Result<Vec<OsString>> {
+        let mut names: Vec<OsString> = self
+            .files
+            .keys()
+            .filter_map(|p| p.parent()?.strip_prefix(path).ok())
+            .filter(|p| p.components().count() == 1)
+            .map(|p| p.as_os_str().to_owned())
+            .collect();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+}
+
+#[test]
+fn test_source_provider() {
+    let mut files = HashMap::new();
+    files.insert(
+        PathBuf::from("/fake/sys/kernel/osrelease"),
+        b"4.5.6".to_vec(),
+    );
+    files.insert(
+        PathBuf::from("/fake/42/stat"),
+        b"42 (foo) S 1 42 42 0 -1 4194560 0 0 0 0 0 0 0 0 20 0 1 0 0 0 0".to_vec(),
+    );
+    files.insert(
+        PathBuf::from("/fake/42/status"),
+        b"VmSize:\t4 kB\n".to_vec(),
+    );
+    files.insert(PathBuf::from("/fake/42/io"), b"read_bytes: 7\n".to_vec());
+    files.insert(PathBuf::from("/fake/42/cgroup"), b"0::/foo\n".to_vec());
+    files.insert(PathBuf::from("/fake/42/cmdline"), b"foo\0bar\0".to_vec());
+    let reader = ProcReader::new_with_source_provider(
+        PathBuf::from("/fake"),
+        Arc::new(InMemorySource { files }),
+    );
+
+    assert_eq!(
+        reader
+            .read_kernel_version()
+            .expect("Fail to read kernel version"),
+        "4.5.6"
+    );
+
+    let pidmap = reader.read_all_pids().expect("Failed to read all pids");
+    let pidinfo = &pidmap[&42];
+    assert_eq!(pidinfo.stat.comm, Some("foo".to_string()));
+    assert_eq!(pidinfo.status.vm_size, Some(4 * 1024));
+    assert_eq!(pidinfo.io.rbytes, Some(7));
+    assert_eq!(pidinfo.cgroup, "/foo");
+    assert_eq!(
+        pidinfo.cmdline_vec,
+        Some(vec!["foo".to_string(), "bar".to_string()])
+    );
+    assert_eq!(pidinfo.exe_path, None);
+}
+
+#[test]
+fn test_recording_source_provider() {
+    let procfs = TestProcfs::new();
+    procfs.create_file_with_content("meminfo", b"MemTotal:       1 kB\n");
+    let record_dir = TempDir::new().expect("Failed to create tempdir");
+    let reader = ProcReader::new_with_source_provider(
+        procfs.path().to_path_buf(),
+        Arc::new(RecordingSourceProvider::new(
+            FsSourceProvider,
+            record_dir.path().to_path_buf(),
+        )),
+    );
+    let meminfo = reader.read_meminfo().expect("Failed to read meminfo");
+    assert_eq!(meminfo.total, Some(1024));
+
+    // Replay the snapshot
+    let replay_path = record_dir
+        .path()
+        .join(procfs.path().strip_prefix("/").unwrap());
+    let replay = ProcReader::new_with_custom_procfs(replay_path);
+    assert_eq!(
+        replay.read_meminfo().expect("Failed to replay meminfo"),
+        meminfo
+    );
+}
+
+#[test]
+fn test_read_softirqs() {
+    let softirqs = b"                    CPU0       CPU2
+          HI:          1          0
+       TIMER:    4513245    4286117
+      NET_TX:        412        391
+      NET_RX:    1538294    1320155
+       BLOCK:      91234      88012
+    IRQ_POLL:          0          0
+     TASKLET:        127         96
+       SCHED:    2871233    2712098
+     HRTIMER:         84         61
+         RCU:    3123456    3011234
+";
+    let procfs = TestProcfs::new();
+    procfs.create_file_with_content("softirqs", softirqs);
+    let reader = procfs.get_reader();
+    let softirqs = reader
+        .read_softirqs()
+        .expect("Failed to read softirqs file");
+    assert_eq!(softirqs.keys().copied().collect::<Vec<_>>(), vec![0, 2]);
+    assert_eq!(
+        softirqs[&2],
+        SoftirqStat {
+            hi: Some(0),
+            timer: Some(4286117),
+            net_tx: Some(391),
+            net_rx: Some(1320155),
+            block: Some(88012),
+            irq_poll: Some(0),
+            tasklet: Some(96),
+            sched: Some(2712098),
+            hrtimer: Some(61),
+            rcu: Some(3011234),
+        }
+    );
+    assert_eq!(softirqs[&0].net_rx, Some(1538294));
+}
+
+#[test]
+fn test_read_irq_pressure() {
+    let procfs = TestProcfs::new();
+    let reader = procfs.get_reader();
+    // Kernels without IRQ pressure
+    assert!(reader.read_irq_pressure().is_err());
+
+    std::fs::create_dir(procfs.path().join("pressure")).expect("Failed to create pressure dir");
+    procfs.create_file_with_content(
+        "pressure/irq",
+        b"full avg10=1.25 avg60=0.50 avg300=0.00 total=7153\n",
+    );
+    let pressure = reader
+        .read_irq_pressure()
+        .expect("Failed to read irq pressure");
+    assert_eq!(
+        pressure,
+        PressureMetrics {
+            avg10: Some(1.25),
+            avg60: Some(0.5),
+            avg300: Some(0.0),
+            total: Some(7153),
+        }
+    );
+
+    procfs.create_file_with_content("pressure/irq", b"");
+    assert!(reader.read_irq_pressure().is_err());
+}
+
+#[test]
+fn test_read_softirqs_truncated() {
+    let softirqs = b"                    CPU0       CPU1
+          HI:          1
+";
+    let procfs = TestProcfs::new();
+    procfs.create_file_with_content("softirqs", softirqs);
+    let reader = procfs.get_reader();
+    // A missing column is a missing value, not an error
+    let softirqs = reader
+        .read_softirqs()
+        .expect("Failed to read softirqs file");
+    assert_eq!(softirqs[&0].hi, Some(1));
+    assert_eq!(softirqs[&1].hi, None);
+
+    procfs.create_file_with_content("softirqs", b"");
+    assert!(reader.read_softirqs().is_err());
+}
+
+#[test]
+fn test_read_schedstat() {
+    let schedstat = b"version 15
+timestamp 4300958853
+cpu0 0 0 0 0 0 0 2482049853 317265402 1102384
+domain0 00000003 1 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0
+cpu2 0 0 0 0 0 0 1913827410 98123456 874512
+domain0 0000000c 2 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0
+";
+    let procfs = TestProcfs::new();
+    procfs.create_file_with_content("schedstat", schedstat);
+    let reader = procfs.get_reader();
+    let schedstat = reader
+        .read_schedstat()
+        .expect("Failed to read schedstat file");
+    assert_eq!(schedstat.keys().copied().collect::<Vec<_>>(), vec![0, 2]);
+    assert_eq!(
+        schedstat[&2],
+        CpuSchedStat {
+            running_ns: Some(1913827410),
+            waiting_ns: Some(98123456),
+            timeslices: Some(874512),
+        }
+    );
+
+    // Versions before 15 have a different layout
+    procfs.create_file_with_content(
+        "schedstat",
+        b"version 14\ncpu0 0 0 0 0 0 0 0 0 0 0 0 1 2 3\n",
+    );
+    assert!(reader.read_schedstat().is_err());
+}
@@ -44,6 +44,18 @@ pub struct Stat {
     pub blocked_processes: Option<u32>,
 }
 
+/// Load averages and task counts from /proc/loadavg
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LoadAvg {
+    pub load1: Option<f64>,
+    pub load5: Option<f64>,
+    pub load15: Option<f64>,
+    /// Tasks currently runnable
+    pub runnable_tasks: Option<u64>,
+    /// Tasks, i.e. threads, that currently exist
+    pub total_tasks: Option<u64>,
+}
+
 // In kilobytes unless specified otherwise
 #[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct MemInfo {
@@ -119,6 +131,12 @@ pub struct InterfaceStat {
     pub tx_heartbeat_errors: Option<u64>,
     pub tx_packets: Option<u64>,
     pub tx_window_errors: Option<u64>,
+    // Link attributes from the interface directory rather than statistics/
+    pub operstate: Option<String>,
+    /// Link speed in Mbit/s, None if unknown (e.g. link down)
+    pub speed: Option<u64>,
+    pub duplex: Option<String>,
+    pub carrier_changes: Option<u64>,
 }
 
 #[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -259,6 +277,33 @@ pub struct Udp6Stat {
     pub ignored_multi: Option<u64>,
 }
 
+/// Socket counts from /proc/net/sockstat. Memory is in pages, except for
+/// IP fragments which is in bytes.
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SockStat {
+    pub sockets_used: Option<u64>,
+    pub tcp_inuse: Option<u64>,
+    pub tcp_orphan: Option<u64>,
+    pub tcp_tw: Option<u64>,
+    pub tcp_alloc: Option<u64>,
+    pub tcp_mem: Option<u64>,
+    pub udp_inuse: Option<u64>,
+    pub udp_mem: Option<u64>,
+    pub raw_inuse: Option<u64>,
+    pub frag_inuse: Option<u64>,
+    pub frag_memory: Option<u64>,
+}
+
+/// Socket counts from /proc/net/sockstat6
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SockStat6 {
+    pub tcp_inuse: Option<u64>,
+    pub udp_inuse: Option<u64>,
+    pub raw_inuse: Option<u64>,
+    pub frag_inuse: Option<u64>,
+    pub frag_memory: Option<u64>,
+}
+
 #[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct VmStat {
     pub pgpgin: Option<u64>,
@@ -284,6 +329,61 @@ pub struct SlabInfo {
     pub num_slabs: Option<u64>,
 }
 
+/// Per-type softirq counts of a single CPU, from /proc/softirqs
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SoftirqStat {
+    pub hi: Option<u64>,
+    pub timer: Option<u64>,
+    pub net_tx: Option<u64>,
+    pub net_rx: Option<u64>,
+    pub block: Option<u64>,
+    pub irq_poll: Option<u64>,
+    pub tasklet: Option<u64>,
+    pub sched: Option<u64>,
+    pub hrtimer: Option<u64>,
+    pub rcu: Option<u64>,
+}
+
+/// Scheduler statistics of a single CPU, from /proc/schedstat
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CpuSchedStat {
+    /// Time spent running tasks
+    pub running_ns: Option<u64>,
+    /// Time tasks spent runnable on the runqueue, waiting for the CPU
+    pub waiting_ns: Option<u64>,
+    /// Number of timeslices run
+    pub timeslices: Option<u64>,
+}
+
+/// Frequency and thermal throttling of a single CPU, from
+/// /sys/devices/system/cpu/cpu<N>. Fields are None where the cpufreq driver
+/// or the thermal_throttle interface (x86 only) do not provide them.
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CpuFreqStat {
+    /// cpufreq/scaling_cur_freq
+    pub cur_freq_khz: Option<u64>,
+    /// cpufreq/cpuinfo_max_freq
+    pub max_freq_khz: Option<u64>,
+    /// Time spent at each frequency in kHz in units of 10ms, from
+    /// cpufreq/stats/time_in_state
+    pub time_in_state: Option<BTreeMap<u64, u64>>,
+    pub core_throttle_count: Option<u64>,
+    pub core_throttle_total_time_ms: Option<u64>,
+    /// Events of the package the CPU belongs to, the same for all its CPUs
+    pub package_throttle_count: Option<u64>,
+    pub package_throttle_total_time_ms: Option<u64>,
+}
+
+/// One line of a /proc/pressure file, e.g. the full line of
+/// /proc/pressure/irq
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PressureMetrics {
+    pub avg10: Option<f64>,
+    pub avg60: Option<f64>,
+    pub avg300: Option<f64>,
+    pub total: Option<u64>,
+}
+
 #[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Ksm {
     pub advisor_max_cpu: Option<u64>,
@@ -373,6 +473,8 @@ pub struct PidStat {
     pub system_usecs: Option<u64>,
     pub num_threads: Option<u64>,
     pub running_secs: Option<u64>,
+    /// Start time of the process in seconds since boot
+    pub start_secs_since_boot: Option<u64>,
     pub rss_bytes: Option<u64>,
     pub processor: Option<i32>,
 }
@@ -391,6 +493,26 @@ pub struct PidStatus {
     pub huge_tlb: Option<u64>,
 }
 
+/// Memory of all mappings of a process summed up, from
+/// /proc/[pid]/smaps_rollup
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PidSmapsRollup {
+    pub rss: Option<u64>,
+    pub pss: Option<u64>,
+    pub pss_anon: Option<u64>,
+    pub pss_file: Option<u64>,
+    pub pss_shmem: Option<u64>,
+    pub shared_clean: Option<u64>,
+    pub shared_dirty: Option<u64>,
+    pub private_clean: Option<u64>,
+    pub private_dirty: Option<u64>,
+    pub anon_huge_pages: Option<u64>,
+    pub shmem_pmd_mapped: Option<u64>,
+    pub file_pmd_mapped: Option<u64>,
+    pub swap: Option<u64>,
+    pub swap_pss: Option<u64>,
+}
+
 #[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct PidIo {
     pub rbytes: Option<u64>,
@@ -409,12 +531,21 @@ pub struct PidInfo {
     // This field was previously called "mem"
     #[serde(alias = "mem")]
     pub status: PidStatus,
+    // Optional b/c reading smaps_rollup walks all mappings and is only done
+    // if enabled
+    pub smaps_rollup: Option<PidSmapsRollup>,
 }
 
 pub type PidMap = BTreeMap<i32, PidInfo>;
 pub type NetMap = BTreeMap<String, InterfaceStat>;
 pub type DiskMap = BTreeMap<String, DiskStat>;
 pub type SlabInfoMap = BTreeMap<String, SlabInfo>;
+/// Softirq counts keyed by CPU index
+pub type SoftirqMap = BTreeMap<u32, SoftirqStat>;
+/// Scheduler statistics keyed by CPU index
+pub type SchedStatMap = BTreeMap<u32, CpuSchedStat>;
+/// Frequency and throttling statistics keyed by CPU index
+pub type CpuFreqMap = BTreeMap<u32, CpuFreqStat>;
 
 #[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct NetStat {
@@ -428,6 +559,10 @@ pub struct NetStat {
     pub icmp6: Option<Icmp6Stat>,
     pub udp: Option<UdpStat>,
     pub udp6: Option<Udp6Stat>,
+    #[serde(default)]
+    pub sockstat: Option<SockStat>,
+    #[serde(default)]
+    pub sockstat6: Option<SockStat6>,
 }
 
 impl fmt::Display for PidState {
@@ -15,9 +15,11 @@
 use model::ProcessModelFieldId;
 use model::SingleCgroupModelFieldId;
 use model::SingleProcessModelFieldId;
+use RenderFormat::Datetime;
 use RenderFormat::Duration;
 use RenderFormat::MaxOrDuration;
 use RenderFormat::MaxOrReadableSize;
+use RenderFormat::ReadableFrequency;
 use RenderFormat::PageReadableSize;
 use RenderFormat::Precision;
 use RenderFormat::ReadableSize;
@@ -61,11 +63,20 @@ impl HasRenderConfig for model::SingleCgroupModel {
             Mem(field_id) => model::CgroupMemoryModel::get_render_config_builder(field_id),
             Pressure(field_id) => model::CgroupPressureModel::get_render_config_builder(field_id),
             CgroupStat(field_id) => model::CgroupStatModel::get_render_config_builder(field_id),
+            Churn(field_id) => model::CgroupChurnModel::get_render_config_builder(field_id),
             MemNuma(field_id) => {
                 model::CgroupMemoryNumaModel::get_render_config_builder(&field_id.subquery_id.0)
             }
+            HugetlbDetails(field_id) => {
+                model::CgroupHugetlbModel::get_render_config_builder(&field_id.subquery_id.0)
+            }
             Props(field_id) => model::CgroupProperties::get_render_config_builder(field_id),
             Pids(field_id) => model::CgroupPidsModel::get_render_config_builder(field_id),
+            FileIo(field_id) => model::CgroupFileIoModel::get_render_config_builder(field_id),
+            Perf(field_id) => model::PerfCounterModel::get_render_config_builder(field_id),
+            Share(field_id) => model::CgroupShareModel::get_render_config_builder(field_id),
+            Dstate(field_id) => model::DStateModel::get_render_config_builder(field_id),
+            JournalErrorsPerSec => rc.title("Errors").suffix("/s").format(Precision(1)),
         }
     }
 }
@@ -80,12 +91,17 @@ impl HasRenderConfigForDump for model::SingleCgroupModel {
         use model::CgroupIoModelFieldId::CostWaitPct;
         use model::CgroupIoModelFieldId::DbytesPerSec;
         use model::CgroupIoModelFieldId::DiosPerSec;
+        use model::CgroupIoModelFieldId::LatencyAvgUsec;
+        use model::CgroupIoModelFieldId::LatencyTargetUsec;
         use model::CgroupIoModelFieldId::RbytesPerSec;
         use model::CgroupIoModelFieldId::RiosPerSec;
         use model::CgroupIoModelFieldId::RwbytesPerSec;
         use model::CgroupIoModelFieldId::WbytesPerSec;
         use model::CgroupIoModelFieldId::WiosPerSec;
         use model::CgroupMemoryModelFieldId::Anon;
+        use model::CgroupMemoryModelFieldId::EventsHighPerSec;
+        use model::CgroupMemoryModelFieldId::EventsLowPerSec;
+        use model::CgroupMemoryModelFieldId::EventsMaxPerSec;
         use model::CgroupMemoryModelFieldId::File;
         use model::CgroupMemoryModelFieldId::Pgactivate;
         use model::CgroupMemoryModelFieldId::Pgdeactivate;
@@ -135,6 +151,8 @@ impl HasRenderConfigForDump for model::SingleCgroupModel {
             Io(CostWaitPct) => rc.title("Cost Wait"),
             Io(CostIndebtPct) => rc.title("Cost Indebt"),
             Io(CostIndelayPct) => rc.title("Cost Indelay"),
+            Io(LatencyTargetUsec) => rc.title("Lat Target"),
+            Io(LatencyAvgUsec) => rc.title("Lat Avg"),
             Mem(Total) => rc.title("Mem Total"),
             Mem(Swap) => rc.title("Mem Swap"),
             Mem(Anon) => rc.title("Mem Anon"),
@@ -162,6 +180,9 @@ impl HasRenderConfigForDump for model::SingleCgroupModel {
             Mem(Pglazyfreed) => rc.title("Pglazyfreed"),
             Mem(ThpFaultAlloc) => rc.title("THP Fault Alloc"),
             Mem(ThpCollapseAlloc) => rc.title("THP Collapse Alloc"),
+            Mem(EventsLowPerSec) => rc.title("Events Low Rate"),
+            Mem(EventsHighPerSec) => rc.title("Events High Rate"),
+            Mem(EventsMaxPerSec) => rc.title("Events Max Rate"),
             Pressure(MemorySomePct) => rc.title("Mem Some Pressure"),
             Pressure(MemoryFullPct) => rc.title("Mem Pressure"),
             _ => rc,
@@ -173,11 +194,13 @@ impl HasRenderConfigForDump for model::SingleCgroupModel {
         &self,
         field_id: &Self::FieldId,
     ) -> Option<RenderOpenMetricsConfigBuilder> {
+        use model::CgroupChurnModelFieldId::*;
         use model::CgroupCpuModelFieldId::*;
         use model::CgroupIoModelFieldId::*;
         use model::CgroupMemoryModelFieldId::*;
         use model::CgroupPidsModelFieldId::*;
         use model::CgroupPressureModelFieldId::*;
+        use model::CgroupFileIoModelFieldId::*;
         use model::CgroupStatModelFieldId::*;
         use model::SingleCgroupModelFieldId::*;
 
@@ -213,6 +236,8 @@ impl HasRenderConfigForDump for model::SingleCgroupModel {
                 CostWaitPct => Some(gauge.unit("percent")),
                 CostIndebtPct => Some(gauge.unit("percent")),
                 CostIndelayPct => Some(gauge.unit("percent")),
+                LatencyTargetUsec => Some(gauge.unit("microseconds")),
+                LatencyAvgUsec => Some(gauge.unit("microseconds")),
             },
             Mem(field_id) => match field_id {
                 Total => Some(counter.unit("bytes")),
@@ -229,6 +254,10 @@ impl HasRenderConfigForDump for model::SingleCgroupModel {
                 EventsLocalMax => None,
                 EventsLocalOom => Some(counter),
                 EventsLocalOomKill => Some(counter),
+                EventsLowPerSec => Some(gauge.help("Memory low boundary breaches per second")),
+                EventsHighPerSec => Some(gauge.help("Memory high boundary breaches per second")),
+                EventsMaxPerSec => Some(gauge.help("Memory max boundary breaches per second")),
+                EventsOomKillDelta => Some(gauge.help("OOM kills since the previous sample")),
                 Anon => Some(gauge.unit("bytes")),
                 File => Some(gauge.unit("bytes")),
                 Kernel => Some(gauge.unit("bytes")),
@@ -241,7 +270,10 @@ impl HasRenderConfigForDump for model::SingleCgroupModel {
                 FileMapped => Some(gauge.unit("bytes")),
                 FileDirty => Some(gauge.unit("bytes")),
                 FileWriteback => Some(gauge.unit("bytes")),
+                FileDirtyGrowthPerSec => Some(gauge.unit("bytes_per_second")),
                 AnonThp => Some(gauge.unit("bytes")),
+                FileThp => Some(gauge.unit("bytes")),
+                ShmemThp => Some(gauge.unit("bytes")),
                 InactiveAnon => Some(gauge.unit("bytes")),
                 ActiveAnon => Some(gauge.unit("bytes")),
                 InactiveFile => Some(gauge.unit("bytes")),
@@ -267,6 +299,7 @@ impl HasRenderConfigForDump for model::SingleCgroupModel {
                 Pglazyfreed => Some(gauge.help("Pglazyfreed per second")),
                 ThpFaultAlloc => Some(gauge.help("THP Fault Alloc per second")),
                 ThpCollapseAlloc => Some(gauge.help("THP Collapse Alloc per second")),
+                Hugetlb => Some(gauge.unit("bytes")),
             },
             Pressure(field_id) => match field_id {
                 CpuSomePct => Some(gauge.unit("percent")),
@@ -275,19 +308,46 @@ impl HasRenderConfigForDump for model::SingleCgroupModel {
                 IoFullPct => Some(gauge.unit("percent")),
                 MemorySomePct => Some(gauge.unit("percent")),
                 MemoryFullPct => Some(gauge.unit("percent")),
+                IrqFullPct => Some(gauge.unit("percent")),
+                MemoryTriggerEvents => Some(gauge),
             },
             CgroupStat(field_id) => match field_id {
                 NrDescendants => Some(counter),
                 NrDyingDescendants => Some(counter),
             },
+            Churn(field_id) => match field_id {
+                CreatedPerSec => Some(gauge.help("Child cgroups created per second")),
+                RemovedPerSec => Some(gauge.help("Child cgroups removed per second")),
+            },
             // Unclear how to represent numa nodes. Doesn't seem super useful so leave out for now.
             MemNuma(_) => None,
+            // Same as above, the total is reported with mem
+            HugetlbDetails(_) => None,
             // These are all settings rather than counters/gauges, so not sure how to represent
             // these. Leave out for now.
             Props(_) => None,
             // Looks like these represent child IO data. Not sure it's necessary to report this
             // as dump does not even pretend to form a hierarchy.
             IoDetails(_) => None,
+            FileIo(field_id) => match field_id {
+                ReadBytesPerSec => Some(gauge.unit("bytes_per_second")),
+                WriteBytesPerSec => Some(gauge.unit("bytes_per_second")),
+                // Only the busiest file of the interval, so not useful as a
+                // time series
+                TopFile => None,
+                TopFileRwBytesPerSec => None,
+            },
+            Perf(field_id) => self
+                .perf
+                .as_ref()
+                .and_then(|perf| perf.get_openmetrics_config_for_dump(field_id))
+                .map(|config| config.label("cgroup", &self.full_path)),
+            Share(_) => Some(gauge.unit("percent")),
+            Dstate(field_id) => match field_id {
+                model::DStateModelFieldId::Count => Some(gauge),
+                model::DStateModelFieldId::MaxUptimeSecs => Some(gauge.unit("seconds")),
+            },
+            JournalErrorsPerSec => Some(gauge.help("Error-level journal messages per second")),
         }
     }
 }
@@ -307,6 +367,19 @@ impl HasRenderConfig for model::CgroupCpuModel {
     }
 }
 
+impl HasRenderConfig for model::CgroupFileIoModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::CgroupFileIoModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            ReadBytesPerSec => rc.title("File Reads").suffix("/s").format(ReadableSize),
+            WriteBytesPerSec => rc.title("File Writes").suffix("/s").format(ReadableSize),
+            TopFile => rc.title("Top File").width(50),
+            TopFileRwBytesPerSec => rc.title("Top File RW").suffix("/s").format(ReadableSize),
+        }
+    }
+}
+
 impl HasRenderConfig for model::CgroupPidsModel {
     fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
         use model::CgroupPidsModelFieldId::*;
@@ -317,6 +390,19 @@ impl HasRenderConfig for model::CgroupPidsModel {
     }
 }
 
+impl HasRenderConfig for model::CgroupShareModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::CgroupShareModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            CpuPctOfParent => rc.title("CPU Of Parent").suffix("%").format(Precision(2)),
+            CpuPctOfRoot => rc.title("CPU Of Root").suffix("%").format(Precision(2)),
+            MemPctOfParent => rc.title("Mem Of Parent").suffix("%").format(Precision(2)),
+            MemPctOfRoot => rc.title("Mem Of Root").suffix("%").format(Precision(2)),
+        }
+    }
+}
+
 impl HasRenderConfig for model::CgroupIoModel {
     fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
         use model::CgroupIoModelFieldId::*;
@@ -333,6 +419,8 @@ impl HasRenderConfig for model::CgroupIoModel {
             CostWaitPct => rc.title("Cost Wait").suffix("%").format(Precision(2)),
             CostIndebtPct => rc.title("Cost Indebt").suffix("%").format(Precision(2)),
             CostIndelayPct => rc.title("Cost Indelay").suffix("%").format(Precision(2)),
+            LatencyTargetUsec => rc.title("Latency Target").format(Duration),
+            LatencyAvgUsec => rc.title("Latency Avg").format(Duration),
         }
     }
 }
@@ -354,6 +442,10 @@ impl HasRenderConfig for model::CgroupMemoryModel {
             EventsLocalMax => rc.title("Events Local Max"),
             EventsLocalOom => rc.title("Events Local OOM"),
             EventsLocalOomKill => rc.title("Events Local Kill"),
+            EventsLowPerSec => rc.title("Events Low/s").format(Precision(2)),
+            EventsHighPerSec => rc.title("Events High/s").format(Precision(2)),
+            EventsMaxPerSec => rc.title("Events Max/s").format(Precision(2)),
+            EventsOomKillDelta => rc.title("New OOM Kills"),
             Anon => rc.title("Anon").format(ReadableSize),
             File => rc.title("File").format(ReadableSize),
             Kernel => rc.title("Kernel").format(ReadableSize),
@@ -366,7 +458,10 @@ impl HasRenderConfig for model::CgroupMemoryModel {
             FileMapped => rc.title("File Mapped").format(ReadableSize),
             FileDirty => rc.title("File Dirty").format(ReadableSize),
             FileWriteback => rc.title("File WB").format(ReadableSize),
+            FileDirtyGrowthPerSec => rc.title("Dirty Growth").suffix("/s").format(ReadableSize),
             AnonThp => rc.title("Anon THP").format(ReadableSize),
+            FileThp => rc.title("File THP").format(ReadableSize),
+            ShmemThp => rc.title("Shmem THP").format(ReadableSize),
             InactiveAnon => rc.title("Inactive Anon").format(ReadableSize),
             ActiveAnon => rc.title("Active Anon").format(ReadableSize),
             InactiveFile => rc.title("Inactive File").format(ReadableSize),
@@ -392,6 +487,18 @@ impl HasRenderConfig for model::CgroupMemoryModel {
             Pglazyfreed => rc.title("Pglazyfreed/s"),
             ThpFaultAlloc => rc.title("THP Fault Alloc/s"),
             ThpCollapseAlloc => rc.title("THP Collapse Alloc/s"),
+            Hugetlb => rc.title("HugeTLB").format(ReadableSize),
+        }
+    }
+}
+
+impl HasRenderConfig for model::CgroupHugetlbModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::CgroupHugetlbModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            Current => rc.title("HugeTLB").format(ReadableSize),
+            Max => rc.title("HugeTLB Max").format(MaxOrReadableSize),
         }
     }
 }
@@ -416,6 +523,8 @@ impl HasRenderConfig for model::CgroupPressureModel {
                 .suffix("%")
                 .format(Precision(2)),
             MemoryFullPct => rc.title("Mem Pressure").suffix("%").format(Precision(2)),
+            IrqFullPct => rc.title("IRQ Pressure").suffix("%").format(Precision(2)),
+            MemoryTriggerEvents => rc.title("Mem Trigger Events"),
         }
     }
 }
@@ -434,6 +543,8 @@ impl HasRenderConfig for model::NetworkModel {
             Icmp6(field_id) => model::Icmp6Model::get_render_config_builder(field_id),
             Udp(field_id) => model::UdpModel::get_render_config_builder(field_id),
             Udp6(field_id) => model::Udp6Model::get_render_config_builder(field_id),
+            Sockstat(field_id) => model::SockStatModel::get_render_config_builder(field_id),
+            Sockstat6(field_id) => model::SockStat6Model::get_render_config_builder(field_id),
         }
     }
 }
@@ -456,6 +567,8 @@ impl HasRenderConfigForDump for model::NetworkModel {
             Icmp6(field_id) => self.icmp6.get_openmetrics_config_for_dump(field_id),
             Udp(field_id) => self.udp.get_openmetrics_config_for_dump(field_id),
             Udp6(field_id) => self.udp6.get_openmetrics_config_for_dump(field_id),
+            Sockstat(field_id) => self.sockstat.get_openmetrics_config_for_dump(field_id),
+            Sockstat6(field_id) => self.sockstat6.get_openmetrics_config_for_dump(field_id),
         }
     }
 }
@@ -751,12 +864,89 @@ impl HasRenderConfigForDump for model::Udp6Model {
     }
 }
 
+impl HasRenderConfig for model::SockStatModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::SockStatModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            SocketsUsed => rc.title("SocketsUsed"),
+            TcpInuse => rc.title("TcpInUse"),
+            TcpOrphan => rc.title("TcpOrphan"),
+            TcpTw => rc.title("TcpTimeWait"),
+            TcpAlloc => rc.title("TcpAlloc"),
+            TcpMem => rc.title("TcpMem").format(PageReadableSize),
+            UdpInuse => rc.title("UdpInUse"),
+            UdpMem => rc.title("UdpMem").format(PageReadableSize),
+            RawInuse => rc.title("RawInUse"),
+            FragInuse => rc.title("FragInUse"),
+            FragMemoryBytes => rc.title("FragMem").format(ReadableSize),
+        }
+    }
+}
+
+impl HasRenderConfigForDump for model::SockStatModel {
+    fn get_openmetrics_config_for_dump(
+        &self,
+        field_id: &Self::FieldId,
+    ) -> Option<RenderOpenMetricsConfigBuilder> {
+        use model::SockStatModelFieldId::*;
+        match field_id {
+            SocketsUsed => Some(gauge()),
+            TcpInuse => Some(gauge()),
+            TcpOrphan => Some(gauge()),
+            TcpTw => Some(gauge()),
+            TcpAlloc => Some(gauge()),
+            TcpMem => Some(gauge()),
+            UdpInuse => Some(gauge()),
+            UdpMem => Some(gauge()),
+            RawInuse => Some(gauge()),
+            FragInuse => Some(gauge()),
+            FragMemoryBytes => Some(gauge().unit("bytes")),
+        }
+    }
+}
+
+impl HasRenderConfig for model::SockStat6Model {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::SockStat6ModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            TcpInuse => rc.title("Tcp6InUse"),
+            UdpInuse => rc.title("Udp6InUse"),
+            RawInuse => rc.title("Raw6InUse"),
+            FragInuse => rc.title("Frag6InUse"),
+            FragMemoryBytes => rc.title("Frag6Mem").format(ReadableSize),
+        }
+    }
+}
+
+impl HasRenderConfigForDump for model::SockStat6Model {
+    fn get_openmetrics_config_for_dump(
+        &self,
+        field_id: &Self::FieldId,
+    ) -> Option<RenderOpenMetricsConfigBuilder> {
+        use model::SockStat6ModelFieldId::*;
+        match field_id {
+            TcpInuse => Some(gauge()),
+            UdpInuse => Some(gauge()),
+            RawInuse => Some(gauge()),
+            FragInuse => Some(gauge()),
+            FragMemoryBytes => Some(gauge().unit("bytes")),
+        }
+    }
+}
+
 impl HasRenderConfig for model::SingleNetModel {
     fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
         use model::SingleNetModelFieldId::*;
         let rc = RenderConfigBuilder::new();
         match field_id {
             Interface => rc.title("Interface"),
+            Operstate => rc.title("State"),
+            SpeedMbps => rc.title("Speed").suffix(" Mb/s"),
+            Duplex => rc.title("Duplex"),
+            CarrierChanges => rc.title("Carrier Changes"),
+            CarrierChangesDelta => rc.title("Link Flaps"),
             RxBytesPerSec => rc.title("RX Bytes/s").format(ReadableSize),
             TxBytesPerSec => rc.title("TX Bytes/s").format(ReadableSize),
             ThroughputPerSec => rc.title("I/O Bytes/s").format(ReadableSize),
@@ -787,6 +977,7 @@ impl HasRenderConfig for model::SingleNetModel {
             TxPackets => rc.title("TX Packets"),
             TxWindowErrors => rc.title("TX Window Errors"),
             TxTimeoutPerSec => rc.title("TX Timeout").suffix("/s"),
+            QdiscDropsPerSec => rc.title("Qdisc Drops").suffix("/s"),
             RawStats => rc.title("Raw Stats"),
             Queues(field_id) => Vec::<model::SingleQueueModel>::get_render_config_builder(field_id),
         }
@@ -804,6 +995,11 @@ impl HasRenderConfigForDump for model::SingleNetModel {
         match field_id {
             // We label all the other metrics with the interface name
             Interface => None,
+            Operstate => None,
+            SpeedMbps => Some(gauge),
+            Duplex => None,
+            CarrierChanges => Some(counter),
+            CarrierChangesDelta => Some(gauge),
             RxBytesPerSec => Some(gauge),
             TxBytesPerSec => Some(gauge),
             ThroughputPerSec => Some(gauge),
@@ -834,6 +1030,7 @@ impl HasRenderConfigForDump for model::SingleNetModel {
             TxPackets => Some(counter),
             TxWindowErrors => Some(counter),
             TxTimeoutPerSec => Some(gauge),
+            QdiscDropsPerSec => Some(gauge),
             RawStats => Some(counter),
             Queues(field_id) => self.queues.get_openmetrics_config_for_dump(field_id),
         }
@@ -918,12 +1115,15 @@ impl HasRenderConfig for model::SingleProcessModel {
             Comm => rc.title("Comm").width(30),
             State => rc.title("State"),
             UptimeSecs => rc.title("Uptime(sec)"),
+            StartTimeEpochSecs => rc.title("Start Time").width(19).format(Datetime),
             Cgroup => rc.title("Cgroup").width(50).fold(FoldOption::Name),
             Io(field_id) => model::ProcessIoModel::get_render_config_builder(field_id),
             Mem(field_id) => model::ProcessMemoryModel::get_render_config_builder(field_id),
             Cpu(field_id) => model::ProcessCpuModel::get_render_config_builder(field_id),
             Cmdline => rc.title("Cmdline").width(50),
             ExePath => rc.title("Exe Path"),
+            Stack(field_id) => model::ProcessStackModel::get_render_config_builder(field_id),
+            Net(field_id) => model::ProcessNetModel::get_render_config_builder(field_id),
         }
     }
 }
@@ -953,6 +1153,8 @@ impl HasRenderConfigForDump for model::SingleProcessModel {
         use model::ProcessCpuModelFieldId::*;
         use model::ProcessIoModelFieldId::*;
         use model::ProcessMemoryModelFieldId::*;
+        use model::ProcessNetModelFieldId::*;
+        use model::ProcessStackModelFieldId::*;
         use model::SingleProcessModelFieldId::*;
         let mut counter = counter();
         let mut gauge = gauge();
@@ -976,6 +1178,7 @@ impl HasRenderConfigForDump for model::SingleProcessModel {
             // OpenMetrics does not support strings
             State => None,
             UptimeSecs => Some(counter),
+            StartTimeEpochSecs => Some(counter),
             // OpenMetrics does not support strings
             Cgroup => None,
             Io(field_id) => match field_id {
@@ -995,18 +1198,35 @@ impl HasRenderConfigForDump for model::SingleProcessModel {
                 Shmem => Some(gauge.unit("bytes")),
                 Pte => Some(gauge.unit("bytes")),
                 Swap => Some(gauge.unit("bytes")),
+                SwapGrowthPerSec => Some(gauge.unit("bytes_per_second")),
                 HugeTlb => Some(gauge.unit("bytes")),
+                Pss => Some(gauge.unit("bytes")),
+                Shared => Some(gauge.unit("bytes")),
+                Private => Some(gauge.unit("bytes")),
+                AnonThp => Some(gauge.unit("bytes")),
+                SwapPss => Some(gauge.unit("bytes")),
             },
             Cpu(field_id) => match field_id {
                 UsagePct => Some(gauge.unit("percent")),
                 UserPct => Some(gauge.unit("percent")),
                 SystemPct => Some(gauge.unit("percent")),
                 NumThreads => Some(counter),
+                TotalUsecs => Some(counter),
             },
             // OpenMetrics does not support strings
             Cmdline => None,
             // OpenMetrics does not support strings
             ExePath => None,
+            Stack(field_id) => match field_id {
+                Samples => Some(gauge),
+                // OpenMetrics does not support strings
+                TopFrame => None,
+                TopFramePct => Some(gauge.unit("percent")),
+            },
+            Net(field_id) => match field_id {
+                RxBytesPerSec => Some(gauge),
+                TxBytesPerSec => Some(gauge),
+            },
         }
     }
 }
@@ -1039,7 +1259,13 @@ impl HasRenderConfig for model::ProcessMemoryModel {
             Shmem => rc.title("Shmem").format(ReadableSize),
             Pte => rc.title("PTE").format(ReadableSize),
             Swap => rc.title("Swap").format(ReadableSize),
+            SwapGrowthPerSec => rc.title("Swap Growth").format(ReadableSize).suffix("/s"),
             HugeTlb => rc.title("Huge TLB").format(ReadableSize),
+            Pss => rc.title("PSS").format(ReadableSize),
+            Shared => rc.title("Shared").format(ReadableSize),
+            Private => rc.title("Private").format(ReadableSize),
+            AnonThp => rc.title("Anon THP").format(ReadableSize),
+            SwapPss => rc.title("Swap PSS").format(ReadableSize),
         }
     }
 }
@@ -1053,6 +1279,84 @@ impl HasRenderConfig for model::ProcessCpuModel {
             UserPct => rc.title("CPU User").format(Precision(2)).suffix("%"),
             SystemPct => rc.title("CPU System").format(Precision(2)).suffix("%"),
             NumThreads => rc.title("Threads"),
+            TotalUsecs => rc.title("CPU Time").format(Duration),
+        }
+    }
+}
+
+impl HasRenderConfig for model::ProcessNetModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::ProcessNetModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            RxBytesPerSec => rc.title("Net RX").suffix("/s").format(ReadableSize),
+            TxBytesPerSec => rc.title("Net TX").suffix("/s").format(ReadableSize),
+        }
+    }
+}
+
+impl HasRenderConfig for model::ProcessStackModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::ProcessStackModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            Samples => rc.title("Stack Samples"),
+            TopFrame => rc.title("Top Frame").width(40),
+            TopFramePct => rc.title("Top Frame Share").format(Precision(2)).suffix("%"),
+        }
+    }
+}
+
+impl HasRenderConfig for model::DStateModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::DStateModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            Count => rc.title("D State Procs"),
+            MaxUptimeSecs => rc.title("D Max Uptime(sec)"),
+        }
+    }
+}
+
+impl HasRenderConfigForDump for model::DStateModel {
+    fn get_openmetrics_config_for_dump(
+        &self,
+        field_id: &Self::FieldId,
+    ) -> Option<RenderOpenMetricsConfigBuilder> {
+        use model::DStateModelFieldId::*;
+        match field_id {
+            Count => Some(gauge()),
+            MaxUptimeSecs => Some(gauge().unit("seconds")),
+        }
+    }
+}
+
+impl HasRenderConfig for model::LoadAvgModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::LoadAvgModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            Load1 => rc.title("Load 1m").format(Precision(2)),
+            Load5 => rc.title("Load 5m").format(Precision(2)),
+            Load15 => rc.title("Load 15m").format(Precision(2)),
+            RunnableTasks => rc.title("Runnable Tasks"),
+            TotalTasks => rc.title("Total Tasks"),
+        }
+    }
+}
+
+impl HasRenderConfigForDump for model::LoadAvgModel {
+    fn get_openmetrics_config_for_dump(
+        &self,
+        field_id: &Self::FieldId,
+    ) -> Option<RenderOpenMetricsConfigBuilder> {
+        use model::LoadAvgModelFieldId::*;
+        match field_id {
+            Load1 => Some(gauge()),
+            Load5 => Some(gauge()),
+            Load15 => Some(gauge()),
+            RunnableTasks => Some(gauge()),
+            TotalTasks => Some(gauge()),
         }
     }
 }
@@ -1064,24 +1368,46 @@ impl HasRenderConfig for model::SystemModel {
         match field_id {
             Hostname => rc.title("Hostname").width(20),
             KernelVersion => rc.title("Kernel Version").width(50),
+            BootId => rc.title("Boot ID").width(37),
             OsRelease => rc.title("OS Release").width(50),
+            BelowVersion => rc.title("Below Version").width(12),
+            IrqPressureFullPct => rc.title("IRQ Pressure").suffix("%").format(Precision(2)),
+            HungTaskDetectCount => rc.title("Hung Tasks"),
+            HungTaskDetectDelta => rc.title("New Hung Tasks"),
+            UptimeSecs => rc.title("Uptime(sec)"),
+            EntropyAvail => rc.title("Entropy Avail"),
+            Loadavg(field_id) => model::LoadAvgModel::get_render_config_builder(field_id),
             Stat(field_id) => model::ProcStatModel::get_render_config_builder(field_id),
+            Dstate(field_id) => model::DStateModel::get_render_config_builder(field_id),
             Cpu(field_id) => model::SingleCpuModel::get_render_config_builder(field_id),
             Cpus(field_id) => {
                 BTreeMap::<u32, model::SingleCpuModel>::get_render_config_builder(field_id)
             }
+            Softirq(field_id) => model::SingleSoftirqModel::get_render_config_builder(field_id),
+            Softirqs(field_id) => {
+                BTreeMap::<u32, model::SingleSoftirqModel>::get_render_config_builder(field_id)
+            }
+            Sched(field_id) => model::SingleSchedModel::get_render_config_builder(field_id),
+            Scheds(field_id) => {
+                BTreeMap::<u32, model::SingleSchedModel>::get_render_config_builder(field_id)
+            }
             Mem(field_id) => model::MemoryModel::get_render_config_builder(field_id),
             Vm(field_id) => model::VmModel::get_render_config_builder(field_id),
             Slab(field_id) => {
                 model::SingleSlabModel::get_render_config_builder(&field_id.subquery_id.0)
             }
             Ksm(field_id) => model::KsmModel::get_render_config_builder(field_id),
+            Perf(field_id) => model::PerfCounterModel::get_render_config_builder(field_id),
             Disks(field_id) => {
                 model::SingleDiskModel::get_render_config_builder(&field_id.subquery_id.0)
             }
             Btrfs(field_id) => {
                 model::BtrfsModel::get_render_config_builder(&field_id.subquery_id.0)
             }
+            BtrfsDevices(field_id) => {
+                model::BtrfsDeviceModel::get_render_config_builder(&field_id.subquery_id.0)
+            }
+            Collection(field_id) => model::CollectionModel::get_render_config_builder(field_id),
         }
     }
 }
@@ -1098,18 +1424,51 @@ impl HasRenderConfigForDump for model::SystemModel {
             // OpenMetrics does not support strings
             KernelVersion => None,
             // OpenMetrics does not support strings
+            BootId => None,
+            // OpenMetrics does not support strings
             OsRelease => None,
+            // OpenMetrics does not support strings
+            BelowVersion => None,
+            IrqPressureFullPct => Some(gauge().unit("percent")),
+            HungTaskDetectCount => Some(counter()),
+            HungTaskDetectDelta => {
+                Some(gauge().help("Hung tasks detected since the previous sample"))
+            }
+            UptimeSecs => Some(counter().unit("seconds")),
+            EntropyAvail => Some(gauge()),
+            Loadavg(field_id) => self
+                .loadavg
+                .as_ref()
+                .and_then(|loadavg| loadavg.get_openmetrics_config_for_dump(field_id)),
             Stat(field_id) => self.stat.get_openmetrics_config_for_dump(field_id),
+            Dstate(field_id) => self
+                .dstate
+                .as_ref()
+                .and_then(|dstate| dstate.get_openmetrics_config_for_dump(field_id)),
             Cpu(field_id) => self.total_cpu.get_openmetrics_config_for_dump(field_id),
             Cpus(field_id) => self.cpus.get_openmetrics_config_for_dump(field_id),
+            Softirq(field_id) => self.total_softirq.get_openmetrics_config_for_dump(field_id),
+            Softirqs(field_id) => self.softirqs.get_openmetrics_config_for_dump(field_id),
+            Sched(field_id) => self.total_sched.get_openmetrics_config_for_dump(field_id),
+            Scheds(field_id) => self.scheds.get_openmetrics_config_for_dump(field_id),
             Mem(field_id) => self.mem.get_openmetrics_config_for_dump(field_id),
             Vm(field_id) => self.vm.get_openmetrics_config_for_dump(field_id),
             Slab(_) => None,
             Ksm(_) => None,
+            Perf(field_id) => self
+                .perf
+                .as_ref()
+                .and_then(|perf| perf.get_openmetrics_config_for_dump(field_id)),
             // Same as with NetworkModel, we leave disk dumping to `disk` category
             Disks(_) => None,
             // Same as with above, we leave btrfs dumping to `btrfs` category
             Btrfs(_) => None,
+            // Left to the `btrfs-device` category
+            BtrfsDevices(_) => None,
+            Collection(field_id) => self
+                .collection
+                .as_ref()
+                .and_then(|collection| collection.get_openmetrics_config_for_dump(field_id)),
         }
     }
 }
@@ -1163,6 +1522,11 @@ impl HasRenderConfig for model::SingleCpuModel {
             StolenPct => rc.title("Stolen").suffix("%").format(Precision(2)),
             GuestPct => rc.title("Guest").suffix("%").format(Precision(2)),
             GuestNicePct => rc.title("Guest Nice").suffix("%").format(Precision(2)),
+            FreqHz => rc.title("Freq").format(ReadableFrequency),
+            MaxFreqHz => rc.title("Max Freq").format(ReadableFrequency),
+            CoreThrottles => rc.title("Core Throttles"),
+            CoreThrottleUsec => rc.title("Core Throttled").format(Duration),
+            PackageThrottles => rc.title("Pkg Throttles"),
         }
     }
 }
@@ -1188,6 +1552,11 @@ impl HasRenderConfigForDump for model::SingleCpuModel {
             StolenPct => Some(gauge),
             GuestPct => Some(gauge),
             GuestNicePct => Some(gauge),
+            FreqHz => Some(gauge.unit("hertz")),
+            MaxFreqHz => Some(gauge.unit("hertz")),
+            CoreThrottles => Some(gauge),
+            CoreThrottleUsec => Some(gauge.unit("microseconds")),
+            PackageThrottles => Some(gauge),
         }
     }
 }
@@ -1222,6 +1591,157 @@ impl HasRenderConfigForDump for BTreeMap<u32, model::SingleCpuModel> {
     }
 }
 
+impl HasRenderConfig for model::SingleSoftirqModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::SingleSoftirqModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            Idx => rc.title("Idx"),
+            HiPerSec => rc.title("Softirq HI").suffix("/s").format(Precision(1)),
+            TimerPerSec => rc.title("Softirq Timer").suffix("/s").format(Precision(1)),
+            NetTxPerSec => rc.title("Softirq Net TX").suffix("/s").format(Precision(1)),
+            NetRxPerSec => rc.title("Softirq Net RX").suffix("/s").format(Precision(1)),
+            BlockPerSec => rc.title("Softirq Block").suffix("/s").format(Precision(1)),
+            IrqPollPerSec => rc
+                .title("Softirq IRQ Poll")
+                .suffix("/s")
+                .format(Precision(1)),
+            TaskletPerSec => rc
+                .title("Softirq Tasklet")
+                .suffix("/s")
+                .format(Precision(1)),
+            SchedPerSec => rc.title("Softirq Sched").suffix("/s").format(Precision(1)),
+            HrtimerPerSec => rc
+                .title("Softirq HRTimer")
+                .suffix("/s")
+                .format(Precision(1)),
+            RcuPerSec => rc.title("Softirq RCU").suffix("/s").format(Precision(1)),
+        }
+    }
+}
+
+impl HasRenderConfigForDump for model::SingleSoftirqModel {
+    fn get_openmetrics_config_for_dump(
+        &self,
+        field_id: &Self::FieldId,
+    ) -> Option<RenderOpenMetricsConfigBuilder> {
+        use model::SingleSoftirqModelFieldId::*;
+        let gauge = gauge().label("cpu", &self.idx.to_string());
+        match field_id {
+            // We label each metric with the CPU index
+            Idx => None,
+            HiPerSec => Some(gauge),
+            TimerPerSec => Some(gauge),
+            NetTxPerSec => Some(gauge),
+            NetRxPerSec => Some(gauge),
+            BlockPerSec => Some(gauge),
+            IrqPollPerSec => Some(gauge),
+            TaskletPerSec => Some(gauge),
+            SchedPerSec => Some(gauge),
+            HrtimerPerSec => Some(gauge),
+            RcuPerSec => Some(gauge),
+        }
+    }
+}
+
+impl HasRenderConfig for BTreeMap<u32, model::SingleSoftirqModel> {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        let mut rc =
+            model::SingleSoftirqModel::get_render_config_builder(&field_id.subquery_id.0).get();
+        rc.title = rc.title.map(|title| {
+            format!(
+                "CPU {} {}",
+                field_id
+                    .idx
+                    .expect("BTreeMapFieldId without key should not have render config"),
+                title
+            )
+        });
+        rc.into()
+    }
+}
+
+impl HasRenderConfigForDump for BTreeMap<u32, model::SingleSoftirqModel> {
+    fn get_openmetrics_config_for_dump(
+        &self,
+        field_id: &Self::FieldId,
+    ) -> Option<RenderOpenMetricsConfigBuilder> {
+        let key = field_id
+            .idx
+            .expect("BTreeMapFieldId without key should not have render config");
+        self.get(&key)
+            .map(|softirq| softirq.get_openmetrics_config_for_dump(&field_id.subquery_id.0))?
+    }
+}
+
+impl HasRenderConfig for model::SingleSchedModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::SingleSchedModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            Idx => rc.title("Idx"),
+            RunPct => rc.title("Sched Run").suffix("%").format(Precision(2)),
+            WaitPct => rc.title("Sched Wait").suffix("%").format(Precision(2)),
+            TimeslicesPerSec => rc
+                .title("Sched Timeslices")
+                .suffix("/s")
+                .format(Precision(1)),
+            AvgWaitUsec => rc
+                .title("Sched Avg Wait")
+                .suffix(" us")
+                .format(Precision(1)),
+        }
+    }
+}
+
+impl HasRenderConfigForDump for model::SingleSchedModel {
+    fn get_openmetrics_config_for_dump(
+        &self,
+        field_id: &Self::FieldId,
+    ) -> Option<RenderOpenMetricsConfigBuilder> {
+        use model::SingleSchedModelFieldId::*;
+        let gauge = gauge().label("cpu", &self.idx.to_string());
+        match field_id {
+            // We label each metric with the CPU index
+            Idx => None,
+            RunPct => Some(gauge.unit("percent")),
+            WaitPct => Some(gauge.unit("percent")),
+            TimeslicesPerSec => Some(gauge),
+            AvgWaitUsec => Some(gauge.unit("microseconds")),
+        }
+    }
+}
+
+impl HasRenderConfig for BTreeMap<u32, model::SingleSchedModel> {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        let mut rc =
+            model::SingleSchedModel::get_render_config_builder(&field_id.subquery_id.0).get();
+        rc.title = rc.title.map(|title| {
+            format!(
+                "CPU {} {}",
+                field_id
+                    .idx
+                    .expect("BTreeMapFieldId without key should not have render config"),
+                title
+            )
+        });
+        rc.into()
+    }
+}
+
+impl HasRenderConfigForDump for BTreeMap<u32, model::SingleSchedModel> {
+    fn get_openmetrics_config_for_dump(
+        &self,
+        field_id: &Self::FieldId,
+    ) -> Option<RenderOpenMetricsConfigBuilder> {
+        let key = field_id
+            .idx
+            .expect("BTreeMapFieldId without key should not have render config");
+        self.get(&key)
+            .map(|sched| sched.get_openmetrics_config_for_dump(&field_id.subquery_id.0))?
+    }
+}
+
 impl HasRenderConfig for model::MemoryModel {
     fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
         use model::MemoryModelFieldId::*;
@@ -1369,6 +1889,9 @@ impl HasRenderConfig for model::SingleSlabModel {
             NumCaches => rc.title("TotalCaches"),
             ActiveSize => rc.title("ActiveSize").format(ReadableSize),
             TotalSize => rc.title("TotalSize").format(ReadableSize),
+            ActiveObjsDelta => rc.title("ActiveObjs Delta"),
+            TotalSizeDelta => rc.title("Size Delta").format(ReadableSize),
+            GrowthStreak => rc.title("Growth Streak"),
         }
     }
 }
@@ -1379,17 +1902,31 @@ impl HasRenderConfigForDump for model::SingleSlabModel {
         field_id: &Self::FieldId,
     ) -> Option<RenderOpenMetricsConfigBuilder> {
         use model::SingleSlabModelFieldId::*;
+        let counter = if let Some(name) = &self.name {
+            counter().label("slab", name)
+        } else {
+            counter()
+        };
+        let gauge = if let Some(name) = &self.name {
+            gauge().label("slab", name)
+        } else {
+            gauge()
+        };
         match field_id {
+            // We label the other metrics with the cache name
             Name => None,
-            ActiveObjs => Some(counter()),
-            NumObjs => Some(counter()),
-            ObjSize => Some(counter()),
-            ObjPerSlab => Some(counter()),
-            NumSlabs => Some(counter()),
-            ActiveCaches => Some(counter()),
-            NumCaches => Some(counter()),
-            ActiveSize => Some(counter()),
-            TotalSize => Some(counter()),
+            ActiveObjs => Some(counter),
+            NumObjs => Some(counter),
+            ObjSize => Some(counter),
+            ObjPerSlab => Some(counter),
+            NumSlabs => Some(counter),
+            ActiveCaches => Some(counter),
+            NumCaches => Some(counter),
+            ActiveSize => Some(counter),
+            TotalSize => Some(counter),
+            ActiveObjsDelta => Some(gauge),
+            TotalSizeDelta => Some(gauge.unit("bytes")),
+            GrowthStreak => Some(gauge.help("Consecutive samples the cache grew in")),
         }
     }
 }
@@ -1462,6 +1999,69 @@ impl HasRenderConfigForDump for model::KsmModel {
     }
 }
 
+impl HasRenderConfig for model::PerfCounterModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::PerfCounterModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            LlcReferencesPerSec => rc.title("LLC Refs").suffix("/s").format(Precision(1)),
+            LlcMissesPerSec => rc.title("LLC Misses").suffix("/s").format(Precision(1)),
+            LlcMissPct => rc.title("LLC Miss").suffix("%").format(Precision(2)),
+            MemBwBytesPerSec => rc.title("Mem BW").suffix("/s").format(ReadableSize),
+        }
+    }
+}
+
+impl HasRenderConfigForDump for model::PerfCounterModel {
+    fn get_openmetrics_config_for_dump(
+        &self,
+        field_id: &Self::FieldId,
+    ) -> Option<RenderOpenMetricsConfigBuilder> {
+        use model::PerfCounterModelFieldId::*;
+        match field_id {
+            LlcReferencesPerSec => Some(gauge()),
+            LlcMissesPerSec => Some(gauge()),
+            LlcMissPct => Some(gauge().unit("percent")),
+            MemBwBytesPerSec => Some(gauge().unit("bytes_per_second")),
+        }
+    }
+}
+
+impl HasRenderConfig for model::CollectionModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::CollectionModelFieldId::*;
+        let rc = RenderConfigBuilder::new().format(Duration);
+        match field_id {
+            BpfUsecs => rc.title("BPF"),
+            CgroupUsecs => rc.title("Cgroup"),
+            ProcessUsecs => rc.title("Process"),
+            SystemUsecs => rc.title("System"),
+            NetworkUsecs => rc.title("Network"),
+            EthtoolUsecs => rc.title("Ethtool"),
+            TotalUsecs => rc.title("Total"),
+            BpfLostEvents => RenderConfigBuilder::new().title("BPF Lost Events"),
+            BpfAttachFailures => RenderConfigBuilder::new().title("BPF Attach Failures"),
+            BpfProgUsecs => rc.title("BPF Programs"),
+            ExitstatSource => RenderConfigBuilder::new().title("Exitstat Source"),
+        }
+    }
+}
+
+impl HasRenderConfigForDump for model::CollectionModel {
+    fn get_openmetrics_config_for_dump(
+        &self,
+        field_id: &Self::FieldId,
+    ) -> Option<RenderOpenMetricsConfigBuilder> {
+        use model::CollectionModelFieldId::*;
+        match field_id {
+            BpfLostEvents => Some(gauge()),
+            BpfAttachFailures => Some(counter()),
+            ExitstatSource => None,
+            _ => Some(gauge().unit("microseconds")),
+        }
+    }
+}
+
 impl HasRenderConfig for model::SingleDiskModel {
     fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
         use model::SingleDiskModelFieldId::*;
@@ -1563,6 +2163,66 @@ impl HasRenderConfigForDump for model::BtrfsModel {
     }
 }
 
+impl HasRenderConfig for model::BtrfsDeviceModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::BtrfsDeviceModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            Path => rc.title("Device").width(20).fold(FoldOption::Path),
+            MountPoint => rc.title("Mount Point").width(20).fold(FoldOption::Path),
+            Fsid => rc.title("Fsid").width(38),
+            Devid => rc.title("Devid").width(7),
+            WriteErrs => rc.title("Write Errs"),
+            ReadErrs => rc.title("Read Errs"),
+            FlushErrs => rc.title("Flush Errs"),
+            CorruptionErrs => rc.title("Corruption Errs"),
+            GenerationErrs => rc.title("Generation Errs"),
+            ScrubStatus => rc.title("Scrub"),
+            ScrubProgressPct => rc.title("Scrub Progress").format(Precision(1)).suffix("%"),
+            ScrubBytesScrubbed => rc.title("Scrubbed").format(ReadableSize),
+            ScrubCorrectedErrors => rc.title("Scrub Corrected"),
+            ScrubUncorrectableErrors => rc.title("Scrub Uncorrectable"),
+        }
+    }
+}
+
+impl HasRenderConfigForDump for model::BtrfsDeviceModel {
+    fn get_openmetrics_config_for_dump(
+        &self,
+        field_id: &Self::FieldId,
+    ) -> Option<RenderOpenMetricsConfigBuilder> {
+        use model::BtrfsDeviceModelFieldId::*;
+        let counter = if let Some(path) = &self.path {
+            counter().label("device", path)
+        } else {
+            counter()
+        };
+        let gauge = if let Some(path) = &self.path {
+            gauge().label("device", path)
+        } else {
+            gauge()
+        };
+        match field_id {
+            // We label the other metrics with the device path
+            Path => None,
+            // OpenMetrics does not support strings
+            MountPoint => None,
+            Fsid => None,
+            ScrubStatus => None,
+            Devid => None,
+            WriteErrs => Some(counter),
+            ReadErrs => Some(counter),
+            FlushErrs => Some(counter),
+            CorruptionErrs => Some(counter),
+            GenerationErrs => Some(counter),
+            ScrubProgressPct => Some(gauge.unit("percent")),
+            ScrubBytesScrubbed => Some(gauge.unit("bytes")),
+            ScrubCorrectedErrors => Some(gauge),
+            ScrubUncorrectableErrors => Some(gauge),
+        }
+    }
+}
+
 impl HasRenderConfig for model::CgroupStatModel {
     fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
         use model::CgroupStatModelFieldId::*;
@@ -1574,6 +2234,17 @@ impl HasRenderConfig for model::CgroupStatModel {
     }
 }
 
+impl HasRenderConfig for model::CgroupChurnModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::CgroupChurnModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            CreatedPerSec => rc.title("Created").suffix("/s").format(Precision(1)),
+            RemovedPerSec => rc.title("Removed").suffix("/s").format(Precision(1)),
+        }
+    }
+}
+
 impl HasRenderConfig for model::CgroupMemoryNumaModel {
     fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
         use model::CgroupMemoryNumaModelFieldId::*;
@@ -1647,10 +2318,12 @@ impl HasRenderConfig for model::CgroupProperties {
             MemorySwapMax => rc.title("Swap Max").format(MaxOrReadableSize),
             MemoryZswapMax => rc.title("Zswap Max").format(MaxOrReadableSize),
             CpuWeight => rc.title("CPU Weight"),
-            CpusetCpus => rc.title("Allowed CPUs"),
-            CpusetCpusEffective => rc.title("Effective CPUs"),
+            // Rendered as ranges, e.g. "0-7,16-23"
+            CpusetCpus => rc.title("Allowed CPUs").width(20),
+            CpusetCpusEffective => rc.title("Effective CPUs").width(20),
             CpusetMems => rc.title("Allowed Mem Nodes"),
             CpusetMemsEffective => rc.title("Effective Mem Nodes"),
+            CpusAllowedCount => rc.title("CPUs Allowed"),
             CpuMaxUsec => rc.title("CPU Max").format(MaxOrDuration),
             CpuMaxPeriodUsec => rc.title("CPU Max Period").format(Duration),
         }
@@ -1664,7 +2337,9 @@ impl HasRenderConfig for model::SingleTcModel {
         match field_id {
             Interface => rc.title("Interface"),
             Kind => rc.title("Kind"),
+            Path => rc.title("Path").width(30),
             Qlen => rc.title("Queue Length"),
+            BacklogBytes => rc.title("Backlog Bytes").format(ReadableSize),
             Bps => rc.title("Bps").format(ReadableSize).suffix("/s"),
             Pps => rc.title("Pps").suffix("/s"),
             BytesPerSec => rc.title("Bytes").format(ReadableSize).suffix("/s"),
@@ -1691,7 +2366,9 @@ impl HasRenderConfigForDump for model::SingleTcModel {
         match field_id {
             Interface => None,
             Kind => None,
+            Path => None,
             Qlen => Some(gauge),
+            BacklogBytes => Some(gauge.unit("bytes")),
             Bps => Some(gauge.unit("bytes_per_second")),
             Pps => Some(gauge.unit("packets_per_second")),
             BytesPerSec => Some(gauge.unit("bytes_per_second")),
@@ -1836,3 +2513,93 @@ impl HasRenderConfigForDump for model::FqCodelXStatsModel {
         }
     }
 }
+
+impl HasRenderConfig for model::ResctrlMonGroupModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::ResctrlMonGroupModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            Name => rc.title("Name").width(30),
+            FullPath => rc.title("Full Path").width(50),
+            Mon(field_id) => model::ResctrlMonModel::get_render_config_builder(field_id),
+        }
+    }
+}
+
+impl HasRenderConfigForDump for model::ResctrlMonGroupModel {
+    fn get_openmetrics_config_for_dump(
+        &self,
+        field_id: &Self::FieldId,
+    ) -> Option<RenderOpenMetricsConfigBuilder> {
+        use model::ResctrlMonGroupModelFieldId::*;
+        match field_id {
+            // We label each metric with the full path
+            Name => None,
+            FullPath => None,
+            Mon(field_id) => self
+                .mon
+                .get_openmetrics_config_for_dump(field_id)
+                .map(|config| config.label("group", &self.full_path)),
+        }
+    }
+}
+
+impl HasRenderConfig for model::ResctrlMonModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::ResctrlMonModelFieldId::*;
+        match field_id {
+            Total(field_id) => model::ResctrlL3MonModel::get_render_config_builder(field_id),
+            PerL3(field_id) => {
+                model::ResctrlL3MonModel::get_render_config_builder(&field_id.subquery_id.0)
+            }
+        }
+    }
+}
+
+impl HasRenderConfigForDump for model::ResctrlMonModel {
+    fn get_openmetrics_config_for_dump(
+        &self,
+        field_id: &Self::FieldId,
+    ) -> Option<RenderOpenMetricsConfigBuilder> {
+        use model::ResctrlMonModelFieldId::*;
+        match field_id {
+            Total(field_id) => self.total.get_openmetrics_config_for_dump(field_id),
+            PerL3(field_id) => {
+                let l3 = field_id.idx?;
+                self.per_l3
+                    .get(&l3)?
+                    .get_openmetrics_config_for_dump(&field_id.subquery_id.0)
+                    .map(|config| config.label("l3", &l3.to_string()))
+            }
+        }
+    }
+}
+
+impl HasRenderConfig for model::ResctrlL3MonModel {
+    fn get_render_config_builder(field_id: &Self::FieldId) -> RenderConfigBuilder {
+        use model::ResctrlL3MonModelFieldId::*;
+        let rc = RenderConfigBuilder::new();
+        match field_id {
+            LlcOccupancyBytes => rc.title("LLC Occupancy").format(ReadableSize),
+            LlcOccupancyPct => rc.title("LLC Share").suffix("%").format(Precision(2)),
+            MbmTotalBytesPerSec => rc.title("MBM Total").format(ReadableSize).suffix("/s"),
+            MbmLocalBytesPerSec => rc.title("MBM Local").format(ReadableSize).suffix("/s"),
+        }
+    }
+}
+
+impl HasRenderConfigForDump for model::ResctrlL3MonModel {
+    fn get_openmetrics_config_for_dump(
+        &self,
+        field_id: &Self::FieldId,
+    ) -> Option<RenderOpenMetricsConfigBuilder> {
+        use model::ResctrlL3MonModelFieldId::*;
+        let gauge = gauge();
+        match field_id {
+            LlcOccupancyBytes => Some(gauge.unit("bytes")),
+            LlcOccupancyPct => Some(gauge.unit("percent")),
+            MbmTotalBytesPerSec => Some(gauge.unit("bytes_per_second")),
+            MbmLocalBytesPerSec => Some(gauge.unit("bytes_per_second")),
+        }
+    }
+}
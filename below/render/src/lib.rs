@@ -16,6 +16,7 @@ use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Write;
+use std::str::FromStr;
 
 mod default_configs;
 
@@ -24,6 +25,7 @@ use common::util::convert_bytes;
 use common::util::convert_duration;
 use common::util::convert_freq;
 use common::util::fold_string;
+use common::util::timestamp_to_datetime;
 use model::Field;
 use model::Queriable;
 
@@ -54,6 +56,102 @@ pub enum RenderFormat {
     /// Only works on int Fields. -1 displays "max" else displays duration with
     /// human readable suffixes (us, ms, s, etc.)
     MaxOrDuration,
+    /// Only works on int Fields. Displays seconds since the epoch as local
+    /// date and time.
+    Datetime,
+    /// Only works on numeric Fields. Format as size in a fixed unit with the
+    /// given precision. `scale` is the number of bytes each unit of the
+    /// Field stands for, e.g. 4096 for pages. If `max` is set, -1 displays
+    /// "max".
+    FixedSize {
+        scale: u64,
+        unit: SizeUnit,
+        precision: usize,
+        max: bool,
+    },
+}
+
+/// Unit for sizes rendered with `RenderFormat::FixedSize`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SizeUnit {
+    Bytes,
+    Kilo,
+    Mega,
+    Giga,
+    Tera,
+    Kibi,
+    Mebi,
+    Gibi,
+    Tebi,
+}
+
+impl SizeUnit {
+    fn bytes(&self) -> f64 {
+        match self {
+            SizeUnit::Bytes => 1.0,
+            SizeUnit::Kilo => 1e3,
+            SizeUnit::Mega => 1e6,
+            SizeUnit::Giga => 1e9,
+            SizeUnit::Tera => 1e12,
+            SizeUnit::Kibi => 1024.0,
+            SizeUnit::Mebi => 1024.0 * 1024.0,
+            SizeUnit::Gibi => 1024.0 * 1024.0 * 1024.0,
+            SizeUnit::Tebi => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        }
+    }
+}
+
+impl Display for SizeUnit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let unit = match self {
+            SizeUnit::Bytes => "B",
+            SizeUnit::Kilo => "KB",
+            SizeUnit::Mega => "MB",
+            SizeUnit::Giga => "GB",
+            SizeUnit::Tera => "TB",
+            SizeUnit::Kibi => "KiB",
+            SizeUnit::Mebi => "MiB",
+            SizeUnit::Gibi => "GiB",
+            SizeUnit::Tebi => "TiB",
+        };
+        write!(f, "{}", unit)
+    }
+}
+
+impl FromStr for SizeUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "B" | "bytes" => Ok(SizeUnit::Bytes),
+            "KB" => Ok(SizeUnit::Kilo),
+            "MB" => Ok(SizeUnit::Mega),
+            "GB" => Ok(SizeUnit::Giga),
+            "TB" => Ok(SizeUnit::Tera),
+            "KiB" => Ok(SizeUnit::Kibi),
+            "MiB" => Ok(SizeUnit::Mebi),
+            "GiB" => Ok(SizeUnit::Gibi),
+            "TiB" => Ok(SizeUnit::Tebi),
+            _ => Err(format!(
+                "Unknown size unit {}, expecting one of B, KB, MB, GB, TB, KiB, MiB, GiB, TiB",
+                s
+            )),
+        }
+    }
+}
+
+/// User overrides applied on top of the default RenderConfig of a Field,
+/// e.g. from the dump section of belowrc.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct RenderOverride {
+    /// Fixed width of the rendered Field.
+    pub width: Option<usize>,
+    /// Digits after the decimal point. Applies to plain numbers and to sizes
+    /// rendered with an explicit unit.
+    pub precision: Option<usize>,
+    /// Render sizes in this unit instead of a human-readable one. Ignored
+    /// for Fields that are not rendered as sizes.
+    pub unit: Option<SizeUnit>,
 }
 
 /// Specifies how a long string is folded to fit into a shorter width.
@@ -360,6 +458,25 @@ impl RenderConfig {
                         convert_duration(field as u64)
                     }
                 }
+                Datetime => timestamp_to_datetime(&(u64::from(field) as i64)),
+                FixedSize {
+                    scale,
+                    unit,
+                    precision,
+                    max,
+                } => {
+                    if *max && matches!(field, Field::I32(-1) | Field::I64(-1)) {
+                        "max".to_owned()
+                    } else {
+                        let bytes = *scale as f64 * f64::from(field);
+                        format!(
+                            "{:.precision$} {}",
+                            bytes / unit.bytes(),
+                            unit,
+                            precision = precision
+                        )
+                    }
+                }
             },
             None => field.to_string(),
         }
@@ -424,6 +541,45 @@ impl RenderConfig {
     pub fn render(&self, field: Option<Field>, fixed_width: bool) -> String {
         self.render_indented(field, fixed_width, 0)
     }
+
+    /// Applies user overrides on top of this config.
+    pub fn apply_override(&mut self, render_override: &RenderOverride) {
+        use RenderFormat::*;
+        if let Some(width) = render_override.width {
+            self.width = Some(width);
+        }
+        // Bytes per unit of the Field and whether -1 means max, if the Field
+        // is rendered as a size.
+        let size = match &self.format {
+            Some(ReadableSize) => Some((1, false)),
+            Some(PageReadableSize) => Some((4096, false)),
+            Some(SectorReadableSize) => Some((512, false)),
+            Some(MaxOrReadableSize) => Some((1, true)),
+            Some(FixedSize { scale, max, .. }) => Some((*scale, *max)),
+            _ => None,
+        };
+        match (size, render_override.unit, render_override.precision) {
+            (Some((scale, max)), Some(unit), precision) => {
+                self.format = Some(FixedSize {
+                    scale,
+                    unit,
+                    precision: precision.unwrap_or(1),
+                    max,
+                });
+            }
+            (Some(_), None, Some(precision)) => {
+                if let Some(FixedSize { precision: p, .. }) = &mut self.format {
+                    *p = precision;
+                }
+            }
+            (None, _, Some(precision)) => {
+                if matches!(self.format, None | Some(Precision(_))) {
+                    self.format = Some(Precision(precision));
+                }
+            }
+            (_, _, None) => {}
+        }
+    }
 }
 
 /// Provide default RenderConfig for each Field in a Model
@@ -588,3 +744,62 @@ my_key{label1="value1",label2="value2",label3="zzz"} 1.23 1234
 "#;
     assert_eq!(text, expected);
 }
+
+#[test]
+fn test_render_override_size_unit() {
+    let mut config = RenderConfigBuilder::new()
+        .format(RenderFormat::PageReadableSize)
+        .get();
+    config.apply_override(&RenderOverride {
+        unit: Some(SizeUnit::Mebi),
+        precision: Some(2),
+        ..Default::default()
+    });
+    assert_eq!(config.render(Some(Field::U64(384)), false), "1.50 MiB");
+
+    config.apply_override(&RenderOverride {
+        unit: Some(SizeUnit::Bytes),
+        precision: Some(0),
+        ..Default::default()
+    });
+    assert_eq!(config.render(Some(Field::U64(1)), false), "4096 B");
+}
+
+#[test]
+fn test_render_override_max_size() {
+    let mut config = RenderConfigBuilder::new()
+        .format(RenderFormat::MaxOrReadableSize)
+        .get();
+    config.apply_override(&RenderOverride {
+        unit: Some(SizeUnit::Kilo),
+        ..Default::default()
+    });
+    assert_eq!(config.render(Some(Field::I64(-1)), false), "max");
+    assert_eq!(config.render(Some(Field::I64(1500)), false), "1.5 KB");
+}
+
+#[test]
+fn test_render_override_precision_and_width() {
+    let mut config = RenderConfigBuilder::new()
+        .title("Title")
+        .format(RenderFormat::Precision(2))
+        .get();
+    config.apply_override(&RenderOverride {
+        width: Some(12),
+        precision: Some(0),
+        ..Default::default()
+    });
+    assert_eq!(config.render(Some(Field::F64(1.6)), true), "2           ");
+    assert_eq!(config.render_title(true), "Title       ");
+
+    // Units and precision do not apply to non-size formats
+    let mut config = RenderConfigBuilder::new()
+        .format(RenderFormat::Duration)
+        .get();
+    config.apply_override(&RenderOverride {
+        unit: Some(SizeUnit::Mebi),
+        precision: Some(3),
+        ..Default::default()
+    });
+    assert_eq!(config.render(Some(Field::U64(1500)), false), "1.5 ms");
+}
@@ -0,0 +1,465 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rule-based summary of notable events in a time range.
+//!
+//! Every model in the range is fed to an `Analyzer`, which keeps per-cgroup
+//! peaks and counters and tracks windows where a metric stayed above a fixed
+//! threshold. The report lists:
+//!   * cgroups with the highest peak cpu/io/memory pressure
+//!   * OOM kills, system wide and per cgroup
+//!   * cgroups whose memory usage grew the most
+//!   * periods where a cgroup was CPU throttled
+//!   * periods where the system was stalled on IO, with the busiest disk
+//!   * optionally, the average, min, max and sum of given fields
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Write;
+use std::time::SystemTime;
+
+use common::util::convert_bytes;
+use common::util::systemtime_to_datetime;
+use model::CgroupModel;
+use model::Model;
+use model::ModelFieldId;
+use model::SingleDiskModel;
+use store::advance::FieldAggregate;
+
+/// Throttled time, as a percentage of wall time, that starts a throttling
+/// period for a cgroup.
+pub const THROTTLED_PCT_THRESHOLD: f64 = 10.0;
+/// System wide IO full pressure that starts a disk saturation window.
+pub const IO_FULL_PCT_THRESHOLD: f64 = 10.0;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Peak {
+    value: f64,
+    timestamp: SystemTime,
+}
+
+fn update_peak(peak: &mut Option<Peak>, value: Option<f64>, timestamp: SystemTime) {
+    match (value, peak.as_ref()) {
+        (Some(value), Some(p)) if value <= p.value => {}
+        (Some(value), _) => *peak = Some(Peak { value, timestamp }),
+        (None, _) => {}
+    }
+}
+
+/// Contiguous run of samples where a metric stayed at or above a threshold.
+#[derive(Clone, Debug, PartialEq)]
+struct Window {
+    begin: SystemTime,
+    end: SystemTime,
+    peak: f64,
+    /// What was going on at the peak sample, e.g. the busiest disk.
+    detail: Option<String>,
+}
+
+impl Window {
+    fn duration_secs(&self) -> u64 {
+        self.end
+            .duration_since(self.begin)
+            .map_or(0, |d| d.as_secs())
+    }
+}
+
+#[derive(Default)]
+struct WindowTracker {
+    open: Option<Window>,
+    closed: Vec<Window>,
+}
+
+impl WindowTracker {
+    fn observe<F>(&mut self, timestamp: SystemTime, value: Option<f64>, threshold: f64, detail: F)
+    where
+        F: FnOnce() -> Option<String>,
+    {
+        match (value.filter(|v| *v >= threshold), self.open.as_mut()) {
+            (Some(value), Some(window)) => {
+                window.end = timestamp;
+                if value > window.peak {
+                    window.peak = value;
+                    window.detail = detail();
+                }
+            }
+            (Some(value), None) => {
+                self.open = Some(Window {
+                    begin: timestamp,
+                    end: timestamp,
+                    peak: value,
+                    detail: detail(),
+                })
+            }
+            (None, _) => self.closed.extend(self.open.take()),
+        }
+    }
+
+    fn finish(mut self) -> Vec<Window> {
+        self.closed.extend(self.open.take());
+        self.closed
+    }
+}
+
+#[derive(Default)]
+struct CgroupStats {
+    cpu_pressure: Option<Peak>,
+    io_pressure: Option<Peak>,
+    memory_pressure: Option<Peak>,
+    first_memory: Option<u64>,
+    last_memory: Option<u64>,
+    last_local_oom_kill: Option<u64>,
+    oom_kills: Vec<(SystemTime, u64)>,
+    throttling: WindowTracker,
+}
+
+impl CgroupStats {
+    fn memory_growth(&self) -> Option<i64> {
+        Some(self.last_memory? as i64 - self.first_memory? as i64)
+    }
+}
+
+/// Accumulates models over a time range and prints a summary of notable
+/// events.
+#[derive(Default)]
+pub struct Analyzer {
+    samples: usize,
+    first_timestamp: Option<SystemTime>,
+    last_timestamp: Option<SystemTime>,
+    hostname: String,
+    first_oom_kill: Option<u64>,
+    last_oom_kill: Option<u64>,
+    cgroups: BTreeMap<String, CgroupStats>,
+    io_saturation: WindowTracker,
+}
+
+/// Rough share of wall time the disk spent serving requests. May exceed 100%
+/// when requests are queued.
+fn disk_busy_pct(begin: &SingleDiskModel, end: &SingleDiskModel, elapsed_ms: f64) -> Option<f64> {
+    let busy_ms = |disk: &SingleDiskModel| {
+        Some(
+            disk.time_spend_read_ms?
+                + disk.time_spend_write_ms?
+                + disk.time_spend_discard_ms.unwrap_or_default(),
+        )
+    };
+    let (begin, end) = (busy_ms(begin)?, busy_ms(end)?);
+    if end < begin || elapsed_ms <= 0.0 {
+        return None;
+    }
+    Some((end - begin) as f64 * 100.0 / elapsed_ms)
+}
+
+impl Analyzer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Feed the next model in the range. `last` is the previous model fed,
+    /// used to derive rates from cumulative counters.
+    pub fn observe(&mut self, model: &Model, last: Option<&Model>) {
+        let timestamp = model.timestamp;
+        self.samples += 1;
+        self.first_timestamp.get_or_insert(timestamp);
+        self.last_timestamp = Some(timestamp);
+        self.hostname.clone_from(&model.system.hostname);
+
+        if let Some(oom_kill) = model.system.vm.oom_kill {
+            self.first_oom_kill.get_or_insert(oom_kill);
+            self.last_oom_kill = Some(oom_kill);
+        }
+
+        let io_full_pct = model
+            .cgroup
+            .data
+            .pressure
+            .as_ref()
+            .and_then(|p| p.io_full_pct);
+        self.io_saturation
+            .observe(timestamp, io_full_pct, IO_FULL_PCT_THRESHOLD, || {
+                Self::busiest_disk(model, last)
+            });
+
+        // The root cgroup mirrors system wide pressure and usage, so it would
+        // top every list. Only look at its descendants.
+        for child in &model.cgroup.children {
+            self.observe_cgroup(child, timestamp);
+        }
+    }
+
+    fn busiest_disk(model: &Model, last: Option<&Model>) -> Option<String> {
+        let last = last?;
+        let elapsed_ms = model
+            .timestamp
+            .duration_since(last.timestamp)
+            .ok()?
+            .as_millis() as f64;
+        model
+            .system
+            .disks
+            .iter()
+            .filter(|(_, disk)| disk.minor == Some(0))
+            .filter_map(|(name, disk)| {
+                let busy_pct = disk_busy_pct(last.system.disks.get(name)?, disk, elapsed_ms)?;
+                Some((name, busy_pct))
+            })
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            .map(|(name, busy_pct)| format!("busiest disk {} at {:.1}% busy", name, busy_pct))
+    }
+
+    fn observe_cgroup(&mut self, cgroup: &CgroupModel, timestamp: SystemTime) {
+        let data = &cgroup.data;
+        let stats = self.cgroups.entry(data.full_path.clone()).or_default();
+
+        if let Some(pressure) = &data.pressure {
+            update_peak(&mut stats.cpu_pressure, pressure.cpu_some_pct, timestamp);
+            update_peak(&mut stats.io_pressure, pressure.io_some_pct, timestamp);
+            update_peak(
+                &mut stats.memory_pressure,
+                pressure.memory_some_pct,
+                timestamp,
+            );
+        }
+
+        if let Some(memory) = &data.memory {
+            if let Some(total) = memory.total {
+                stats.first_memory.get_or_insert(total);
+                stats.last_memory = Some(total);
+            }
+            // Use the local counter so that a kill is attributed to the cgroup
+            // it happened in rather than to all of its ancestors. Counters
+            // reset when the cgroup is recreated.
+            if let Some(oom_kill) = memory.events_local_oom_kill {
+                match stats.last_local_oom_kill {
+                    Some(last) if oom_kill > last => {
+                        stats.oom_kills.push((timestamp, oom_kill - last))
+                    }
+                    _ => {}
+                }
+                stats.last_local_oom_kill = Some(oom_kill);
+            }
+        }
+
+        let throttled_pct = data.cpu.as_ref().and_then(|cpu| cpu.throttled_pct);
+        stats
+            .throttling
+            .observe(timestamp, throttled_pct, THROTTLED_PCT_THRESHOLD, || None);
+
+        for child in &cgroup.children {
+            self.observe_cgroup(child, timestamp);
+        }
+    }
+
+    /// Write the report, listing at most `top` entries per section.
+    pub fn report<W: Write>(self, out: &mut W, top: usize) -> io::Result<()> {
+        let (first, last) = match (self.first_timestamp, self.last_timestamp) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return writeln!(out, "No samples found in the requested range"),
+        };
+        writeln!(
+            out,
+            "Analysis of {} from {} to {} ({} samples)",
+            if self.hostname.is_empty() {
+                "<unknown host>"
+            } else {
+                &self.hostname
+            },
+            systemtime_to_datetime(first),
+            systemtime_to_datetime(last),
+            self.samples,
+        )?;
+
+        let mut cgroups = self.cgroups.into_iter().collect::<Vec<_>>();
+
+        write_top_pressure(out, &cgroups, "cpu", top, |s| s.cpu_pressure.as_ref())?;
+        write_top_pressure(out, &cgroups, "io", top, |s| s.io_pressure.as_ref())?;
+        write_top_pressure(out, &cgroups, "memory", top, |s| s.memory_pressure.as_ref())?;
+
+        writeln!(out, "\nOOM kills:")?;
+        match (self.first_oom_kill, self.last_oom_kill) {
+            (Some(first), Some(last)) if last >= first => {
+                writeln!(out, "  {} system wide", last - first)?
+            }
+            _ => writeln!(out, "  system wide count unavailable")?,
+        }
+        let mut kills = cgroups
+            .iter()
+            .flat_map(|(path, stats)| {
+                stats
+                    .oom_kills
+                    .iter()
+                    .map(move |(timestamp, count)| (*timestamp, *count, path))
+            })
+            .collect::<Vec<_>>();
+        kills.sort();
+        for (timestamp, count, path) in kills {
+            writeln!(
+                out,
+                "  {} killed at {}  {}",
+                count,
+                systemtime_to_datetime(timestamp),
+                path
+            )?;
+        }
+
+        writeln!(out, "\nTop cgroups by memory growth:")?;
+        let mut growth = cgroups
+            .iter()
+            .filter_map(|(path, stats)| Some((path, stats.memory_growth()?, stats)))
+            .filter(|(_, growth, _)| *growth > 0)
+            .collect::<Vec<_>>();
+        growth.sort_by_key(|(_, growth, _)| std::cmp::Reverse(*growth));
+        if growth.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for (path, growth, stats) in growth.into_iter().take(top) {
+            writeln!(
+                out,
+                "  +{:<10} ({} -> {})  {}",
+                convert_bytes(growth as f64),
+                convert_bytes(stats.first_memory.unwrap_or_default() as f64),
+                convert_bytes(stats.last_memory.unwrap_or_default() as f64),
+                path
+            )?;
+        }
+
+        writeln!(
+            out,
+            "\nCPU throttling periods (throttled >= {}%):",
+            THROTTLED_PCT_THRESHOLD
+        )?;
+        let mut throttling = cgroups
+            .iter_mut()
+            .flat_map(|(path, stats)| {
+                let path = &*path;
+                std::mem::take(&mut stats.throttling)
+                    .finish()
+                    .into_iter()
+                    .map(move |window| (path, window))
+            })
+            .collect::<Vec<_>>();
+        sort_windows(&mut throttling, |(_, window)| window);
+        if throttling.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for (path, window) in throttling.into_iter().take(top) {
+            write_window(out, &window, "throttled")?;
+            writeln!(out, "  {}", path)?;
+        }
+
+        writeln!(
+            out,
+            "\nDisk saturation windows (io full pressure >= {}%):",
+            IO_FULL_PCT_THRESHOLD
+        )?;
+        let mut saturation = self.io_saturation.finish();
+        sort_windows(&mut saturation, |window| window);
+        if saturation.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for window in saturation.into_iter().take(top) {
+            write_window(out, &window, "pressure")?;
+            match &window.detail {
+                Some(detail) => writeln!(out, "  {}", detail)?,
+                None => writeln!(out)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn write_top_pressure<W, F>(
+    out: &mut W,
+    cgroups: &[(String, CgroupStats)],
+    resource: &str,
+    top: usize,
+    get: F,
+) -> io::Result<()>
+where
+    W: Write,
+    F: Fn(&CgroupStats) -> Option<&Peak>,
+{
+    writeln!(out, "\nTop cgroups by peak {} pressure:", resource)?;
+    let mut peaks = cgroups
+        .iter()
+        .filter_map(|(path, stats)| Some((path, get(stats)?)))
+        .filter(|(_, peak)| peak.value > 0.0)
+        .collect::<Vec<_>>();
+    peaks.sort_by(|a, b| b.1.value.partial_cmp(&a.1.value).unwrap_or(Ordering::Equal));
+    if peaks.is_empty() {
+        writeln!(out, "  none")?;
+    }
+    for (path, peak) in peaks.into_iter().take(top) {
+        writeln!(
+            out,
+            "  {:>6.2}%  at {}  {}",
+            peak.value,
+            systemtime_to_datetime(peak.timestamp),
+            path
+        )?;
+    }
+    Ok(())
+}
+
+/// Longest windows first, then the most severe.
+fn sort_windows<T, F>(windows: &mut [T], get: F)
+where
+    F: Fn(&T) -> &Window,
+{
+    windows.sort_by(|a, b| {
+        let (a, b) = (get(a), get(b));
+        b.duration_secs()
+            .cmp(&a.duration_secs())
+            .then_with(|| b.peak.partial_cmp(&a.peak).unwrap_or(Ordering::Equal))
+    });
+}
+
+fn write_window<W: Write>(out: &mut W, window: &Window, what: &str) -> io::Result<()> {
+    write!(
+        out,
+        "  {} - {} ({}s), peak {} {:.2}%",
+        systemtime_to_datetime(window.begin),
+        systemtime_to_datetime(window.end),
+        window.duration_secs(),
+        what,
+        window.peak
+    )
+}
+
+/// Aggregates of `field_ids` over the analyzed range, in the same order
+pub fn write_aggregates<W: Write>(
+    out: &mut W,
+    field_ids: &[ModelFieldId],
+    aggregates: &[FieldAggregate],
+) -> io::Result<()> {
+    writeln!(out, "\nAggregates:")?;
+    for (field_id, aggregate) in field_ids.iter().zip(aggregates) {
+        match (aggregate.avg(), aggregate.min, aggregate.max) {
+            (Some(avg), Some(min), Some(max)) => writeln!(
+                out,
+                "  avg {:.2}  min {:.2}  max {:.2}  sum {:.2}  ({} samples)  {}",
+                avg,
+                min,
+                max,
+                aggregate.sum,
+                aggregate.samples,
+                field_id.to_string()
+            )?,
+            _ => writeln!(out, "  no values  {}", field_id.to_string())?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,119 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#ifdef FBCODE_BUILD
+#include <bpf/vmlinux/vmlinux.h>
+#else
+#include "../open_source/vmlinux/vmlinux.h"
+#endif // FBCODE_BUILD
+
+#include <bpf/bpf_core_read.h>
+#include <bpf/bpf_helpers.h>
+#include <bpf/bpf_tracing.h>
+
+#define FILE_NAME_LEN 64
+// Bounds the number of (cgroup, file) pairs tracked at once. The least
+// recently used pair is evicted when full.
+#define MAX_FILES 16384
+
+#define S_IFMT 00170000
+#define S_IFREG 0100000
+
+struct file_key {
+  u64 cgroup_id; // cgroup2 id, i.e. inode number of the cgroup directory
+  u64 ino;
+  u32 dev;
+  u32 pad;
+};
+
+struct file_stats {
+  u64 read_bytes;
+  u64 write_bytes;
+  char parent[FILE_NAME_LEN]; // name of the parent directory
+  char name[FILE_NAME_LEN];
+};
+
+struct {
+  __uint(type, BPF_MAP_TYPE_LRU_HASH);
+  __uint(max_entries, MAX_FILES);
+  __type(key, struct file_key);
+  __type(value, struct file_stats);
+} file_io SEC(".maps");
+
+// Add `bytes` to the cumulative read or write counter of `file` in the
+// current cgroup. Names are only resolved the first time a file is seen so
+// that the hot path is a single lookup.
+static __always_inline void account(struct file* file, ssize_t bytes, bool write) {
+  if (bytes <= 0) {
+    return;
+  }
+  struct inode* inode = BPF_CORE_READ(file, f_inode);
+  if ((BPF_CORE_READ(inode, i_mode) & S_IFMT) != S_IFREG) {
+    return;
+  }
+
+  struct file_key key = {};
+  key.cgroup_id = bpf_get_current_cgroup_id();
+  key.ino = BPF_CORE_READ(inode, i_ino);
+  key.dev = BPF_CORE_READ(inode, i_sb, s_dev);
+
+  struct file_stats* stats = bpf_map_lookup_elem(&file_io, &key);
+  if (!stats) {
+    struct file_stats init = {};
+    struct dentry* dentry = BPF_CORE_READ(file, f_path.dentry);
+    bpf_probe_read_kernel_str(
+        init.name, sizeof(init.name), BPF_CORE_READ(dentry, d_name.name));
+    bpf_probe_read_kernel_str(
+        init.parent,
+        sizeof(init.parent),
+        BPF_CORE_READ(dentry, d_parent, d_name.name));
+    bpf_map_update_elem(&file_io, &key, &init, BPF_NOEXIST);
+    stats = bpf_map_lookup_elem(&file_io, &key);
+    if (!stats) {
+      return;
+    }
+  }
+
+  if (write) {
+    __sync_fetch_and_add(&stats->write_bytes, bytes);
+  } else {
+    __sync_fetch_and_add(&stats->read_bytes, bytes);
+  }
+}
+
+SEC("fexit/vfs_read")
+int BPF_PROG(
+    vfs_read_exit,
+    struct file* file,
+    char* buf,
+    size_t count,
+    loff_t* pos,
+    ssize_t ret) {
+  account(file, ret, false);
+  return 0;
+}
+
+SEC("fexit/vfs_write")
+int BPF_PROG(
+    vfs_write_exit,
+    struct file* file,
+    const char* buf,
+    size_t count,
+    loff_t* pos,
+    ssize_t ret) {
+  account(file, ret, true);
+  return 0;
+}
+
+char _license[] SEC("license") = "GPL";
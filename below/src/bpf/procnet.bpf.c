@@ -0,0 +1,122 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#ifdef FBCODE_BUILD
+#include <bpf/vmlinux/vmlinux.h>
+#else
+#include "../open_source/vmlinux/vmlinux.h"
+#endif // FBCODE_BUILD
+
+#include <bpf/bpf_core_read.h>
+#include <bpf/bpf_helpers.h>
+#include <bpf/bpf_tracing.h>
+
+// Bounds the number of process and interface pairs tracked at once. The
+// least recently used pair is evicted when full.
+#define MAX_ENTRIES 32768
+
+#define AF_INET 2
+#define AF_INET6 10
+
+struct net_stats {
+  u64 rx_bytes;
+  u64 tx_bytes;
+};
+
+struct proc_net_key {
+  // pid of the process in the root pid namespace
+  u32 tgid;
+  // Interface the socket is bound to or routed through, 0 if unknown, e.g.
+  // for unconnected UDP sockets
+  u32 ifindex;
+};
+
+struct {
+  __uint(type, BPF_MAP_TYPE_LRU_HASH);
+  __uint(max_entries, MAX_ENTRIES);
+  __type(key, struct proc_net_key);
+  __type(value, struct net_stats);
+} proc_net SEC(".maps");
+
+static __always_inline u32 sock_ifindex(struct sock* sk) {
+  int ifindex = BPF_CORE_READ(sk, __sk_common.skc_bound_dev_if);
+  if (ifindex > 0) {
+    return ifindex;
+  }
+  // Same for all the traffic of connected sockets. Reading it without
+  // holding the socket lock may race with a route change, which at worst
+  // attributes a few bytes to the previous interface.
+  ifindex = BPF_CORE_READ(sk, sk_dst_cache, dev, ifindex);
+  return ifindex > 0 ? ifindex : 0;
+}
+
+// Add `bytes` to the cumulative rx or tx counter of the current process on
+// the interface of `sock` if it is an IP socket. Unix domain sockets are left out as their traffic
+// never reaches a NIC.
+static __always_inline void account(struct socket* sock, int bytes, bool tx) {
+  if (bytes <= 0) {
+    return;
+  }
+  struct sock* sk = BPF_CORE_READ(sock, sk);
+  if (!sk) {
+    return;
+  }
+  u16 family = BPF_CORE_READ(sk, __sk_common.skc_family);
+  if (family != AF_INET && family != AF_INET6) {
+    return;
+  }
+
+  struct proc_net_key key = {
+      .tgid = bpf_get_current_pid_tgid() >> 32,
+      .ifindex = sock_ifindex(sk),
+  };
+  struct net_stats* stats = bpf_map_lookup_elem(&proc_net, &key);
+  if (!stats) {
+    struct net_stats init = {};
+    bpf_map_update_elem(&proc_net, &key, &init, BPF_NOEXIST);
+    stats = bpf_map_lookup_elem(&proc_net, &key);
+    if (!stats) {
+      return;
+    }
+  }
+
+  if (tx) {
+    __sync_fetch_and_add(&stats->tx_bytes, bytes);
+  } else {
+    __sync_fetch_and_add(&stats->rx_bytes, bytes);
+  }
+}
+
+SEC("fexit/sock_sendmsg")
+int BPF_PROG(
+    sock_sendmsg_exit,
+    struct socket* sock,
+    struct msghdr* msg,
+    int ret) {
+  account(sock, ret, true);
+  return 0;
+}
+
+SEC("fexit/sock_recvmsg")
+int BPF_PROG(
+    sock_recvmsg_exit,
+    struct socket* sock,
+    struct msghdr* msg,
+    int flags,
+    int ret) {
+  account(sock, ret, false);
+  return 0;
+}
+
+char _license[] SEC("license") = "GPL";
@@ -0,0 +1,85 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::os::fd::AsFd;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use libbpf_rs::query::ProgInfoQueryOptions;
+use libbpf_rs::query::ProgramInfo;
+use libbpf_rs::Object;
+use model::BpfProgramStat;
+use model::BpfProgramStats;
+
+const BPF_STATS_ENABLED_PATH: &str = "/proc/sys/kernel/bpf_stats_enabled";
+
+/// Health counters of one BPF collector, kept in the map shared with the
+/// model collector so they end up in every sample.
+#[derive(Clone)]
+pub struct BpfHealth {
+    name: &'static str,
+    stats: Arc<Mutex<BpfProgramStats>>,
+}
+
+impl BpfHealth {
+    pub fn new(name: &'static str, stats: Arc<Mutex<BpfProgramStats>>) -> Self {
+        // A collector that runs is reported even before anything went wrong
+        stats.lock().unwrap().entry(name.to_owned()).or_default();
+        Self { name, stats }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut BpfProgramStat)) {
+        f(self
+            .stats
+            .lock()
+            .unwrap()
+            .entry(self.name.to_owned())
+            .or_default())
+    }
+
+    pub fn attach_failed(&self) {
+        self.update(|stat| stat.attach_failures += 1)
+    }
+
+    pub fn lost_events(&self, count: u64) {
+        self.update(|stat| stat.lost_events += count)
+    }
+
+    /// Record that the collector runs on `source` instead of BPF
+    pub fn fell_back(&self, source: &str) {
+        self.update(|stat| stat.fallback = Some(source.to_owned()))
+    }
+
+    /// Update the run time with the total of the programs of `object`. It
+    /// is only known if the kernel accounts it, which it does while the
+    /// kernel.bpf_stats_enabled sysctl is set.
+    pub fn update_run_time(&self, object: &Object) {
+        let enabled = fs::read_to_string(BPF_STATS_ENABLED_PATH)
+            .map_or(false, |enabled| enabled.trim() == "1");
+        let run_time_ns = if enabled {
+            object
+                .progs_iter()
+                .map(|prog| {
+                    ProgramInfo::load_from_fd(prog.as_fd(), &ProgInfoQueryOptions::default())
+                        .map(|info| info.run_time_ns)
+                })
+                .sum::<libbpf_rs::Result<u64>>()
+                .ok()
+        } else {
+            None
+        };
+        self.update(|stat| stat.run_time_ns = run_time_ns)
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Control socket of `below record`, used by `below ctl` to manage a
+//! running recorder without restarting it.
+//!
+//! Requests and responses are JSON objects, one per line, e.g.
+//! `{"method":"set_interval","params":{"interval_s":10}}` answered by
+//! `{"result":{"interval_s":10}}` or `{"error":"..."}`.
+
+use std::fs;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixListener;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use slog::error;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum Request {
+    /// Sync the samples written to the active shard to disk
+    Flush,
+    /// Close and reopen the files of the active shard
+    Rotate,
+    /// Change the collection interval
+    SetInterval { interval_s: u64 },
+    /// Report statistics of the recorder itself
+    Stats,
+    /// Create a snapshot file from the store, same as `below snapshot`
+    Snapshot {
+        begin: String,
+        end: Option<String>,
+        duration: Option<String>,
+        output: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Response {
+    Result(Value),
+    Error(String),
+}
+
+impl From<Result<Value>> for Response {
+    fn from(res: Result<Value>) -> Self {
+        match res {
+            Ok(value) => Response::Result(value),
+            Err(e) => Response::Error(format!("{:#}", e)),
+        }
+    }
+}
+
+/// A request along with the channel to send its response on
+pub type Call = (Request, Sender<Response>);
+
+/// Listen for requests on the unix socket at `path`. Requests are passed to
+/// the returned receiver and each connection waits for the response before
+/// reading its next request.
+pub fn listen(logger: slog::Logger, path: &Path) -> Result<Receiver<Call>> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            bail!("Another recorder is listening on {}", path.display());
+        }
+        // Left behind by a recorder that did not exit cleanly
+        fs::remove_file(path)
+            .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let listener = UnixListener::bind(path)
+        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+    // Only the user running the recorder may control it
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+        .with_context(|| format!("Failed to set permissions on {}", path.display()))?;
+
+    let (call_send, call_recv) = channel();
+    thread::Builder::new()
+        .name("control_listener".to_owned())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        error!(logger, "Failed to accept control connection: {:#}", e);
+                        continue;
+                    }
+                };
+                let logger = logger.clone();
+                let call_send = call_send.clone();
+                thread::Builder::new()
+                    .name("control_conn".to_owned())
+                    .spawn(move || {
+                        if let Err(e) = serve(stream, &call_send) {
+                            error!(logger, "Control connection failed: {:#}", e);
+                        }
+                    })
+                    .expect("Failed to spawn thread");
+            }
+        })
+        .expect("Failed to spawn thread");
+    Ok(call_recv)
+}
+
+fn serve(stream: UnixStream, call_send: &Sender<Call>) -> Result<()> {
+    let mut writer = stream
+        .try_clone()
+        .context("Failed to clone control connection")?;
+    for line in BufReader::new(stream).lines() {
+        let line = line.context("Failed to read request")?;
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                let (response_send, response_recv) = channel();
+                call_send
+                    .send((request, response_send))
+                    .context("Recorder stopped")?;
+                response_recv
+                    .recv()
+                    .context("Recorder dropped the request")?
+            }
+            Err(e) => Response::Error(format!("Invalid request: {}", e)),
+        };
+        serde_json::to_writer(&mut writer, &response).context("Failed to write response")?;
+        writer
+            .write_all(b"\n")
+            .context("Failed to write response")?;
+    }
+    Ok(())
+}
+
+/// Send `request` to the recorder listening on `path` and wait for its
+/// response
+pub fn call(path: &Path, request: &Request) -> Result<Response> {
+    let mut stream = UnixStream::connect(path).with_context(|| {
+        format!(
+            "Failed to connect to {}. Is below record running?",
+            path.display()
+        )
+    })?;
+    serde_json::to_writer(&mut stream, request).context("Failed to send request")?;
+    stream.write_all(b"\n").context("Failed to send request")?;
+    let mut line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut line)
+        .context("Failed to read response")?;
+    serde_json::from_str(&line).with_context(|| format!("Invalid response: {}", line))
+}
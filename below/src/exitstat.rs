@@ -16,6 +16,7 @@ use core::time::Duration;
 use std::ffi::CStr;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Instant;
 
 use anyhow::Context;
 use anyhow::Result;
@@ -26,8 +27,14 @@ use once_cell::sync::Lazy;
 use plain::Plain;
 use slog::warn;
 
+use crate::bpfstats::BpfHealth;
+use crate::taskstats::TaskstatsDriver;
 use crate::ExitstatSkelBuilder;
 
+/// How often the run time of the BPF program is updated, which takes a few
+/// syscalls
+const RUN_TIME_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
 static PAGE_SIZE: Lazy<u64> = Lazy::new(page_size);
 
 #[repr(C)]
@@ -76,14 +83,16 @@ pub struct ExitstatDriver {
     logger: slog::Logger,
     debug: bool,
     buffer: Arc<Mutex<procfs::PidMap>>,
+    health: BpfHealth,
 }
 
 impl ExitstatDriver {
-    pub fn new(logger: slog::Logger, debug: bool) -> Self {
+    pub fn new(logger: slog::Logger, debug: bool, health: BpfHealth) -> Self {
         Self {
             logger,
             debug,
             buffer: Arc::new(Mutex::new(procfs::PidMap::default())),
+            health,
         }
     }
 
@@ -123,6 +132,7 @@ impl ExitstatDriver {
                 system_usecs: Some(event.stats.stime_us),
                 num_threads: Some(event.stats.nr_threads),
                 running_secs: Some(event.stats.etime_us / 1000000),
+                start_secs_since_boot: None,
                 rss_bytes: Some(event.stats.active_rss_pages * *PAGE_SIZE),
                 processor: Some(event.meta.cpu),
             },
@@ -144,33 +154,70 @@ impl ExitstatDriver {
         handle.lock().unwrap().insert(event.meta.tid, pidinfo);
     }
 
-    fn handle_lost_events(logger: &slog::Logger, cpu: i32, count: u64) {
+    fn handle_lost_events(logger: &slog::Logger, health: &BpfHealth, cpu: i32, count: u64) {
         warn!(logger, "Lost {} events on CPU {}", count, cpu);
+        health.lost_events(count);
     }
 
-    /// Loops forever unless an error is hit
+    /// Loops forever unless an error is hit. Falls back to taskstats if the
+    /// BPF program cannot run here, e.g. without BTF or on an architecture
+    /// it is not supported on.
     pub fn drive(&mut self) -> Result<()> {
-        let mut skel_builder = ExitstatSkelBuilder::default();
-        skel_builder.obj_builder.debug(self.debug);
-        let mut skel = skel_builder
-            .open()
-            .context("Failed to open BPF program")?
-            .load()
-            .context("Failed to load BPF program")?;
-        skel.attach().context("Failed to attach BPF program?")?;
+        let loaded = (|| {
+            let mut skel_builder = ExitstatSkelBuilder::default();
+            skel_builder.obj_builder.debug(self.debug);
+            let mut skel = skel_builder
+                .open()
+                .context("Failed to open BPF program")?
+                .load()
+                .context("Failed to load BPF program")?;
+            skel.attach()
+                .map_err(|e| {
+                    self.health.attach_failed();
+                    e
+                })
+                .context("Failed to attach BPF program?")?;
+            Ok::<_, anyhow::Error>(skel)
+        })();
+        let skel = match loaded {
+            Ok(skel) => skel,
+            Err(bpf_err) => {
+                warn!(
+                    self.logger,
+                    "{:#}, falling back to taskstats for exited processes", bpf_err
+                );
+                self.health.fell_back("taskstats");
+                return TaskstatsDriver::new(
+                    self.logger.clone(),
+                    self.buffer.clone(),
+                    self.health.clone(),
+                )
+                .drive()
+                .with_context(|| format!("BPF program unavailable: {:#}", bpf_err));
+            }
+        };
 
         // Set up perf ring buffer
         let buffer = self.get_buffer();
         let logger_clone = self.logger.clone();
+        let health_clone = self.health.clone();
         let perf = libbpf_rs::PerfBufferBuilder::new(skel.maps().events())
             .sample_cb(move |_, data: &[u8]| Self::handle_event(&buffer, data))
-            .lost_cb(move |cpu, count| Self::handle_lost_events(&logger_clone, cpu, count))
+            .lost_cb(move |cpu, count| {
+                Self::handle_lost_events(&logger_clone, &health_clone, cpu, count)
+            })
             .build()?;
 
         // Poll events
+        let mut last_run_time_update: Option<Instant> = None;
         loop {
             perf.poll(Duration::from_millis(100))
                 .context("Error polling perf buffer")?;
+            if last_run_time_update.map_or(true, |last| last.elapsed() >= RUN_TIME_UPDATE_INTERVAL)
+            {
+                self.health.update_run_time(skel.object());
+                last_run_time_update = Some(Instant::now());
+            }
         }
     }
 }
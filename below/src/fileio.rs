@@ -0,0 +1,199 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Context;
+use anyhow::Result;
+use libbpf_rs::skel::OpenSkel as _;
+use libbpf_rs::skel::Skel as _;
+use libbpf_rs::skel::SkelBuilder as _;
+use libbpf_rs::MapFlags;
+use model::CgroupFileIoMap;
+use model::FileIoSample;
+use model::FileIoStat;
+use plain::Plain;
+
+use crate::bpfstats::BpfHealth;
+use crate::FileioSkelBuilder;
+
+/// Number of files kept per cgroup for each interval
+const TOP_FILES_PER_CGROUP: usize = 10;
+const FILE_NAME_LEN: usize = 64;
+
+// See bpf prog for comments on what each field is
+#[repr(C)]
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash)]
+struct FileKey {
+    cgroup_id: u64,
+    ino: u64,
+    dev: u32,
+    pad: u32,
+}
+
+unsafe impl Plain for FileKey {}
+
+#[repr(C)]
+struct FileStats {
+    read_bytes: u64,
+    write_bytes: u64,
+    parent: [u8; FILE_NAME_LEN],
+    name: [u8; FILE_NAME_LEN],
+}
+
+impl Default for FileStats {
+    fn default() -> Self {
+        Self {
+            read_bytes: 0,
+            write_bytes: 0,
+            parent: [0; FILE_NAME_LEN],
+            name: [0; FILE_NAME_LEN],
+        }
+    }
+}
+
+unsafe impl Plain for FileStats {}
+
+/// Convert a nul padded buffer filled by the bpf prog to a String
+fn buf_to_string(buf: &[u8]) -> String {
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+impl FileStats {
+    /// The bpf prog only resolves the file name and its parent directory,
+    /// as walking the full dentry chain is too expensive on the IO path.
+    fn path(&self) -> String {
+        let parent = buf_to_string(&self.parent);
+        let name = buf_to_string(&self.name);
+        match parent.as_str() {
+            "" => name,
+            "/" => format!("/{}", name),
+            _ => format!("{}/{}", parent, name),
+        }
+    }
+}
+
+/// Traces reads and writes to regular files with BPF and periodically
+/// publishes the busiest files of each cgroup over the last interval.
+pub struct FileIoDriver {
+    debug: bool,
+    interval: Duration,
+    buffer: Arc<Mutex<CgroupFileIoMap>>,
+    health: BpfHealth,
+}
+
+impl FileIoDriver {
+    pub fn new(debug: bool, interval: Duration, health: BpfHealth) -> Self {
+        Self {
+            debug,
+            interval,
+            buffer: Arc::new(Mutex::new(CgroupFileIoMap::default())),
+            health,
+        }
+    }
+
+    pub fn get_buffer(&self) -> Arc<Mutex<CgroupFileIoMap>> {
+        self.buffer.clone()
+    }
+
+    /// Loops forever unless an error is hit
+    pub fn drive(&mut self) -> Result<()> {
+        let mut skel_builder = FileioSkelBuilder::default();
+        skel_builder.obj_builder.debug(self.debug);
+        let mut skel = skel_builder
+            .open()
+            .context("Failed to open BPF program")?
+            .load()
+            .context("Failed to load BPF program")?;
+        skel.attach()
+            .map_err(|e| {
+                self.health.attach_failed();
+                e
+            })
+            .context("Failed to attach BPF program?")?;
+
+        // The bpf prog keeps cumulative counters, so remember the last
+        // values to report per interval deltas
+        let mut last_counters: HashMap<FileKey, (u64, u64)> = HashMap::new();
+        let mut last_instant = Instant::now();
+        loop {
+            std::thread::sleep(self.interval);
+            self.health.update_run_time(skel.object());
+            let now = Instant::now();
+            let interval_ms = now.duration_since(last_instant).as_millis() as u64;
+            last_instant = now;
+
+            let maps = skel.maps();
+            let file_io = maps.file_io();
+            let mut counters = HashMap::with_capacity(last_counters.len());
+            let mut cgroups: BTreeMap<u64, Vec<FileIoStat>> = BTreeMap::new();
+            for key_bytes in file_io.keys() {
+                // The entry may have been evicted since we got its key
+                let value_bytes = match file_io
+                    .lookup(&key_bytes, MapFlags::ANY)
+                    .context("Failed to look up file IO entry")?
+                {
+                    Some(value_bytes) => value_bytes,
+                    None => continue,
+                };
+                let mut key = FileKey::default();
+                plain::copy_from_bytes(&mut key, &key_bytes).expect("Key buffer was too short");
+                let mut stats = FileStats::default();
+                plain::copy_from_bytes(&mut stats, &value_bytes)
+                    .expect("Value buffer was too short");
+
+                // Counters restart from zero if the entry was evicted and
+                // the file was seen again
+                let (last_read, last_write) = last_counters.get(&key).copied().unwrap_or_default();
+                let read_bytes = stats
+                    .read_bytes
+                    .checked_sub(last_read)
+                    .unwrap_or(stats.read_bytes);
+                let write_bytes = stats
+                    .write_bytes
+                    .checked_sub(last_write)
+                    .unwrap_or(stats.write_bytes);
+                counters.insert(key, (stats.read_bytes, stats.write_bytes));
+
+                if read_bytes > 0 || write_bytes > 0 {
+                    cgroups.entry(key.cgroup_id).or_default().push(FileIoStat {
+                        path: stats.path(),
+                        read_bytes,
+                        write_bytes,
+                    });
+                }
+            }
+            last_counters = counters;
+
+            let samples = cgroups
+                .into_iter()
+                .map(|(cgroup_id, mut files)| {
+                    files.sort_by_key(|file| Reverse(file.read_bytes + file.write_bytes));
+                    files.truncate(TOP_FILES_PER_CGROUP);
+                    (cgroup_id, FileIoSample { interval_ms, files })
+                })
+                .collect();
+            // Replace rather than merge, so a sample is never more than one
+            // interval old
+            *self.buffer.lock().unwrap() = samples;
+        }
+    }
+}
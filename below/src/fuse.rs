@@ -0,0 +1,355 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Read-only FUSE filesystem exposing the store for ad-hoc exploration.
+//!
+//! Layout:
+//!   /<timestamp>/system.json
+//!   /<timestamp>/network.json
+//!   /<timestamp>/{gpu,resctrl,tc}.json (when collected)
+//!   /<timestamp>/process/<pid>.json
+//!   /<timestamp>/cgroup/cgroup.json
+//!   /<timestamp>/cgroup/<child>/.../cgroup.json
+//!
+//! Models are computed on demand from the sample and the one before it, the
+//! same way replay does.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use anyhow::Result;
+use fuser::FileAttr;
+use fuser::FileType;
+use fuser::Filesystem;
+use fuser::MountOption;
+use fuser::ReplyAttr;
+use fuser::ReplyData;
+use fuser::ReplyDirectory;
+use fuser::ReplyEntry;
+use fuser::Request;
+use model::CgroupModel;
+use model::Model;
+use store::advance::Advance;
+use store::DataFrame;
+use store::Direction;
+use store::LocalStore;
+use store::Store;
+
+const TTL: Duration = Duration::from_secs(60);
+const ROOT_INO: u64 = 1;
+const CGROUP_FILE: &str = "cgroup.json";
+
+enum Node {
+    Dir(Vec<(String, FileType)>),
+    File(Vec<u8>),
+}
+
+macro_rules! json {
+    ($value:expr) => {
+        serde_json::to_vec_pretty($value).ok().map(Node::File)
+    };
+}
+
+fn resolve_cgroup(cgroup: &CgroupModel, path: &[&str]) -> Option<Node> {
+    match path {
+        [] => {
+            let mut entries = vec![(CGROUP_FILE.to_owned(), FileType::RegularFile)];
+            entries.extend(
+                cgroup
+                    .children
+                    .iter()
+                    .map(|child| (child.data.name.clone(), FileType::Directory)),
+            );
+            Some(Node::Dir(entries))
+        }
+        [name] if *name == CGROUP_FILE => json!(&cgroup.data),
+        [name, rest @ ..] => cgroup
+            .children
+            .iter()
+            .find(|child| child.data.name == *name)
+            .and_then(|child| resolve_cgroup(child, rest)),
+    }
+}
+
+fn resolve_model(model: &Model, path: &[&str]) -> Option<Node> {
+    match path {
+        [] => {
+            let mut entries = vec![
+                ("system.json".to_owned(), FileType::RegularFile),
+                ("network.json".to_owned(), FileType::RegularFile),
+                ("process".to_owned(), FileType::Directory),
+                ("cgroup".to_owned(), FileType::Directory),
+            ];
+            if model.gpu.is_some() {
+                entries.push(("gpu.json".to_owned(), FileType::RegularFile));
+            }
+            if model.resctrl.is_some() {
+                entries.push(("resctrl.json".to_owned(), FileType::RegularFile));
+            }
+            if model.tc.is_some() {
+                entries.push(("tc.json".to_owned(), FileType::RegularFile));
+            }
+            Some(Node::Dir(entries))
+        }
+        ["system.json"] => json!(&model.system),
+        ["network.json"] => json!(&model.network),
+        ["gpu.json"] => json!(model.gpu.as_ref()?),
+        ["resctrl.json"] => json!(model.resctrl.as_ref()?),
+        ["tc.json"] => json!(model.tc.as_ref()?),
+        ["process"] => Some(Node::Dir(
+            model
+                .process
+                .processes
+                .keys()
+                .map(|pid| (format!("{}.json", pid), FileType::RegularFile))
+                .collect(),
+        )),
+        ["process", file] => {
+            let pid = file.strip_suffix(".json")?.parse::<i32>().ok()?;
+            json!(model.process.processes.get(&pid)?)
+        }
+        ["cgroup", rest @ ..] => resolve_cgroup(&model.cgroup, rest),
+        _ => None,
+    }
+}
+
+pub struct StoreFs {
+    /// Unix timestamps of all samples in the mounted range, ascending
+    timestamps: Vec<u64>,
+    advance: Advance<DataFrame, Model>,
+    /// Last computed model. Tools usually walk one timestamp at a time.
+    cached_model: Option<(u64, Model)>,
+    /// Path components for each inode, indexed by ino - 1
+    inodes: Vec<Vec<String>>,
+    inode_map: HashMap<Vec<String>, u64>,
+    uid: u32,
+    gid: u32,
+}
+
+impl StoreFs {
+    pub fn new(
+        logger: slog::Logger,
+        store_dir: PathBuf,
+        time_begin: SystemTime,
+        time_end: SystemTime,
+    ) -> Result<Self> {
+        let mut store = LocalStore::new(logger.clone(), store_dir.clone());
+        let mut timestamps = Vec::new();
+        let mut cur_time = time_begin;
+        while cur_time < time_end {
+            match store.get_sample_at_timestamp(cur_time, Direction::Forward)? {
+                Some((frame_time, _)) if frame_time < time_end => {
+                    timestamps.push(common::util::get_unix_timestamp(frame_time));
+                    cur_time = frame_time + Duration::from_secs(1);
+                }
+                _ => break,
+            }
+        }
+
+        Ok(Self {
+            timestamps,
+            advance: store::advance::new_advance_local(logger, store_dir, time_begin),
+            cached_model: None,
+            inodes: vec![vec![]],
+            inode_map: HashMap::from([(vec![], ROOT_INO)]),
+            uid: unsafe { libc::getuid() },
+            gid: unsafe { libc::getgid() },
+        })
+    }
+
+    fn model(&mut self, timestamp: u64) -> Option<&Model> {
+        if self.cached_model.as_ref().map(|(ts, _)| *ts) != Some(timestamp) {
+            let model = self
+                .advance
+                .jump_sample_to(UNIX_EPOCH + Duration::from_secs(timestamp))?;
+            self.cached_model = Some((timestamp, model));
+        }
+        self.cached_model.as_ref().map(|(_, model)| model)
+    }
+
+    fn resolve(&mut self, path: &[String]) -> Option<Node> {
+        let path = path.iter().map(String::as_str).collect::<Vec<&str>>();
+        match path.as_slice() {
+            [] => Some(Node::Dir(
+                self.timestamps
+                    .iter()
+                    .map(|ts| (ts.to_string(), FileType::Directory))
+                    .collect(),
+            )),
+            [ts, rest @ ..] => {
+                let ts = ts.parse::<u64>().ok()?;
+                self.timestamps.binary_search(&ts).ok()?;
+                resolve_model(self.model(ts)?, rest)
+            }
+        }
+    }
+
+    fn ino(&mut self, path: Vec<String>) -> u64 {
+        if let Some(ino) = self.inode_map.get(&path) {
+            return *ino;
+        }
+        self.inodes.push(path.clone());
+        let ino = self.inodes.len() as u64;
+        self.inode_map.insert(path, ino);
+        ino
+    }
+
+    fn path(&self, ino: u64) -> Option<Vec<String>> {
+        self.inodes.get(ino.checked_sub(1)? as usize).cloned()
+    }
+
+    fn attr(&self, ino: u64, path: &[String], node: &Node) -> FileAttr {
+        // Files carry the time of the sample they belong to
+        let time = path
+            .first()
+            .and_then(|ts| ts.parse::<u64>().ok())
+            .or_else(|| self.timestamps.last().copied())
+            .map_or(UNIX_EPOCH, |ts| UNIX_EPOCH + Duration::from_secs(ts));
+        let (kind, size, perm, nlink) = match node {
+            Node::Dir(_) => (FileType::Directory, 0, 0o555, 2),
+            Node::File(content) => (FileType::RegularFile, content.len() as u64, 0o444, 1),
+        };
+        FileAttr {
+            ino,
+            size,
+            blocks: size.div_ceil(512),
+            atime: time,
+            mtime: time,
+            ctime: time,
+            crtime: time,
+            kind,
+            perm,
+            nlink,
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            blksize: 4096,
+            flags: 0,
+        }
+    }
+}
+
+impl Filesystem for StoreFs {
+    fn lookup(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        let mut path = match self.path(parent) {
+            Some(path) => path,
+            None => return reply.error(libc::ENOENT),
+        };
+        path.push(name.to_string_lossy().into_owned());
+        match self.resolve(&path) {
+            Some(node) => {
+                let ino = self.ino(path.clone());
+                reply.entry(&TTL, &self.attr(ino, &path, &node), 0);
+            }
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request, ino: u64, reply: ReplyAttr) {
+        let path = match self.path(ino) {
+            Some(path) => path,
+            None => return reply.error(libc::ENOENT),
+        };
+        match self.resolve(&path) {
+            Some(node) => reply.attr(&TTL, &self.attr(ino, &path, &node)),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        let path = match self.path(ino) {
+            Some(path) => path,
+            None => return reply.error(libc::ENOENT),
+        };
+        match self.resolve(&path) {
+            Some(Node::File(content)) => {
+                let begin = (offset.max(0) as usize).min(content.len());
+                let end = (begin + size as usize).min(content.len());
+                reply.data(&content[begin..end]);
+            }
+            Some(Node::Dir(_)) => reply.error(libc::EISDIR),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        let path = match self.path(ino) {
+            Some(path) => path,
+            None => return reply.error(libc::ENOENT),
+        };
+        let entries = match self.resolve(&path) {
+            Some(Node::Dir(entries)) => entries,
+            Some(Node::File(_)) => return reply.error(libc::ENOTDIR),
+            None => return reply.error(libc::ENOENT),
+        };
+
+        let parent_ino = match path.split_last() {
+            Some((_, parent)) => self.ino(parent.to_vec()),
+            None => ROOT_INO,
+        };
+        let mut all_entries = vec![
+            (ino, FileType::Directory, ".".to_owned()),
+            (parent_ino, FileType::Directory, "..".to_owned()),
+        ];
+        for (name, kind) in entries {
+            let mut child = path.clone();
+            child.push(name.clone());
+            all_entries.push((self.ino(child), kind, name));
+        }
+
+        for (i, (ino, kind, name)) in all_entries.into_iter().enumerate().skip(offset as usize) {
+            // Offset of the next entry. Stop once the reply buffer is full.
+            if reply.add(ino, (i + 1) as i64, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+}
+
+/// Mount the store read-only at `mountpoint`. Blocks until unmounted.
+pub fn mount(fs: StoreFs, mountpoint: &Path) -> Result<()> {
+    fuser::mount2(
+        fs,
+        mountpoint,
+        &[
+            MountOption::RO,
+            MountOption::FSName("below".to_owned()),
+            MountOption::Subtype("below".to_owned()),
+        ],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,108 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Error-level journal messages by cgroup.
+//!
+//! The journal records the cgroup of the process that logged each message,
+//! so counting messages of priority err and above by cgroup shows which
+//! workloads log errors alongside their resource usage. Like the OOM killer
+//! events, the journal is followed with journalctl.
+
+use std::io::BufRead;
+use std::io::BufReader;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use model::CgroupJournalErrorMap;
+use serde_json::Value;
+
+/// Cgroup of a journal entry as printed by `journalctl --output=json`, e.g.
+/// "/system.slice/foo.service", None if the entry has no cgroup, e.g.
+/// kernel messages
+pub fn parse_journal_entry(line: &str) -> Option<String> {
+    let entry = serde_json::from_str::<Value>(line).ok()?;
+    let cgroup = entry.get("_SYSTEMD_CGROUP")?.as_str()?;
+    cgroup.starts_with('/').then(|| cgroup.to_owned())
+}
+
+/// Count a message logged in `cgroup` for the cgroup and all its ancestors,
+/// the root cgroup being keyed by the empty path
+pub fn count_message(counts: &mut CgroupJournalErrorMap, cgroup: &str) {
+    let mut path = cgroup.trim_end_matches('/');
+    loop {
+        *counts.entry(path.to_owned()).or_default() += 1;
+        match path.rfind('/') {
+            Some(pos) => path = &path[..pos],
+            None => break,
+        }
+    }
+}
+
+pub struct JournalErrorWatcher {
+    units: Vec<String>,
+    data: Arc<Mutex<CgroupJournalErrorMap>>,
+}
+
+impl JournalErrorWatcher {
+    /// Watch messages of `units`, or of all units if empty
+    pub fn new(units: Vec<String>) -> Self {
+        Self {
+            units,
+            data: Arc::new(Mutex::new(CgroupJournalErrorMap::new())),
+        }
+    }
+
+    pub fn get_buffer(&self) -> Arc<Mutex<CgroupJournalErrorMap>> {
+        self.data.clone()
+    }
+
+    /// Follow the journal and count error-level messages from now on. Only
+    /// returns on failure.
+    pub fn drive(&mut self) -> Result<()> {
+        let mut journalctl = Command::new("journalctl");
+        journalctl.args([
+            "--follow",
+            "--lines=0",
+            "--output=json",
+            "--output-fields=_SYSTEMD_CGROUP",
+            "--priority=err",
+        ]);
+        for unit in &self.units {
+            journalctl.arg(format!("--unit={}", unit));
+        }
+        let mut child = journalctl
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to run journalctl")?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        for line in BufReader::new(stdout).lines() {
+            let line = line.context("Failed to read journalctl output")?;
+            if let Some(cgroup) = parse_journal_entry(&line) {
+                count_message(
+                    &mut self.data.lock().expect("tried to acquire poisoned lock"),
+                    &cgroup,
+                );
+            }
+        }
+        let status = child.wait().context("Failed to wait for journalctl")?;
+        bail!("journalctl exited with {}", status);
+    }
+}
@@ -14,16 +14,26 @@
 
 #![recursion_limit = "256"]
 
+#[cfg(feature = "view")]
 use std::cell::RefCell;
+use std::collections::BTreeSet;
 use std::fs;
 use std::io;
+use std::io::Write;
 use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
 use std::path::PathBuf;
 use std::process::exit;
+#[cfg(feature = "view")]
 use std::rc::Rc;
+#[cfg(feature = "view")]
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::mpsc::channel;
 use std::sync::mpsc::Receiver;
 use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::Sender;
 use std::sync::mpsc::TryRecvError;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -41,28 +51,46 @@ use clap::CommandFactory;
 use clap::Parser;
 use clap_complete::generate;
 use clap_complete::Shell;
+#[cfg(feature = "view")]
 use cursive::Cursive;
 use indicatif::ProgressBar;
 use regex::Regex;
 use signal_hook::iterator::Signals;
 use slog::debug;
 use slog::error;
+use slog::info;
 use slog::warn;
-use tar::Archive;
 use tar::Builder as TarBuilder;
 use tempfile::TempDir;
 use tokio::runtime::Builder as TB;
 use uzers::get_current_uid;
 use uzers::get_user_by_uid;
 
+mod analyze;
+mod bpfstats;
+mod control;
 mod exitstat;
+mod fileio;
+#[cfg(feature = "fuse")]
+mod fuse;
+mod journal_errors;
+mod oomd;
+mod perf;
+mod perfcounters;
+mod procnet;
+mod profile;
+mod psi_triggers;
+mod stacks;
+mod taskstats;
 #[cfg(test)]
 mod test;
 
 use common::cliutil;
 use common::logutil;
 use common::open_source_shim;
+use common::util::timestamp_to_datetime;
 use config::BelowConfig;
+#[cfg(feature = "dump")]
 use dump::DumpCommand;
 use store::advance::new_advance_local;
 use store::advance::new_advance_remote;
@@ -70,18 +98,51 @@ use store::ChunkSizePo2;
 use store::CompressionMode;
 use store::DataFrame;
 use store::Store;
+#[cfg(feature = "view")]
 use view::ViewState;
 
 open_source_shim!();
 
+#[cfg(feature = "view")]
 static LIVE_REMOTE_MAX_LATENCY_SEC: u64 = 10;
+/// Seconds between samples in live mode without an interval option or view
+/// profile setting it
+static DEFAULT_LIVE_INTERVAL_S: u64 = 5;
+/// Top level cgroups collected in lite live mode without --lite-top-cgroups
+static DEFAULT_LITE_TOP_CGROUPS: usize = 10;
 
 #[derive(Debug, Parser)]
 struct Opt {
-    #[clap(long, value_parser, default_value = config::BELOW_DEFAULT_CONF)]
+    #[clap(
+        long,
+        value_parser,
+        default_value = config::BELOW_DEFAULT_CONF,
+        env = "BELOW_CONFIG"
+    )]
     config: PathBuf,
+    /// Override a config file value, e.g. --set store_dir=/data/below. May
+    /// be repeated. Config values can also be overridden by BELOW_<KEY>
+    /// environment variables, e.g. BELOW_STORE_DIR. Values given here take
+    /// precedence over the environment, which takes precedence over the
+    /// config file.
+    #[clap(
+        long = "set",
+        value_name = "KEY=VALUE",
+        value_parser = config::parse_override,
+        global = true
+    )]
+    config_overrides: Vec<(String, String)>,
     #[clap(short, long)]
     debug: bool,
+    /// Log level, optionally per module, e.g. "debug" or
+    /// "info,below_store=debug,below_model=trace". Levels are off, critical,
+    /// error, warn, info, debug and trace. Defaults to debug with --debug,
+    /// info otherwise.
+    #[clap(long, value_name = "SPEC", env = "BELOW_LOG_LEVEL", global = true)]
+    log_level: Option<logutil::LogLevelSpec>,
+    /// Log format: "text", or "json" for one JSON object per line
+    #[clap(long, default_value = "text", env = "BELOW_LOG_FORMAT", global = true)]
+    log_format: logutil::LogFormat,
     #[clap(subcommand)]
     cmd: Option<Command>,
 }
@@ -96,7 +157,7 @@ struct CompressOpts {
     #[clap(long)]
     compress: bool,
     /// Only valid when used with --compress. Must be at least 2, a
-    /// power of 2, and at most 32768.
+    /// power of 2, and at most 32768, or "auto".
     ///
     /// If specified, zstd dictionary compression is used in aligned
     /// chunks of size --dict-compress-chunk-size. The first frame of
@@ -104,19 +165,37 @@ struct CompressOpts {
     /// of the chunk.
     ///
     /// With --dict-compress-chunk-size 16, you can expect around
-    /// 20-30x smaller data files.
+    /// 20-30x smaller data files. With "auto", the chunk size of each
+    /// shard is picked from the size of the frames, shorter chunks for
+    /// larger frames.
     #[clap(long, requires("compress"), value_parser = parse_chunk_size)]
-    dict_compress_chunk_size: Option<u32>,
+    dict_compress_chunk_size: Option<DictChunkSize>,
+    /// Store each sample field separately so that replay and dump can skip
+    /// decoding the fields they do not show, e.g. the process map when
+    /// dumping cgroups.
+    ///
+    /// Stores written this way cannot be read by older versions of below.
+    #[clap(long)]
+    indexed_format: bool,
+    /// Only valid when used with --dict-compress-chunk-size and
+    /// --indexed-format.
+    ///
+    /// Store the process map of each frame as a delta against the first
+    /// frame of its dictionary compression chunk. Significantly reduces
+    /// store size on hosts with many long-lived processes.
+    #[clap(long, requires("dict_compress_chunk_size"), requires("indexed_format"))]
+    process_delta: bool,
 }
 
 impl CompressOpts {
     fn to_compression_mode(&self) -> Result<CompressionMode> {
         Ok(match (self.compress, self.dict_compress_chunk_size) {
-            (true, Some(chunk_size)) => {
+            (true, Some(DictChunkSize::Fixed(chunk_size))) => {
                 assert_eq!(chunk_size.count_ones(), 1, "chunk size not a power of 2");
                 let chunk_size_po2 = chunk_size.trailing_zeros();
                 CompressionMode::ZstdDictionary(ChunkSizePo2(chunk_size_po2))
             }
+            (true, Some(DictChunkSize::Auto)) => CompressionMode::ZstdDictionaryAuto,
             (true, None) => CompressionMode::Zstd,
             (false, Some(_)) => {
                 bail!("bug: --dict-compress-chunk-size can only be used with --compress");
@@ -124,9 +203,47 @@ impl CompressOpts {
             (false, None) => CompressionMode::None,
         })
     }
+
+    fn to_format(&self) -> store::Format {
+        if self.indexed_format {
+            store::Format::Indexed
+        } else {
+            store::Format::Cbor
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+struct ForwardOpts {
+    /// Also send each sample to a collector at this address ("host:port"),
+    /// i.e. another below instance running `below collect`.
+    #[clap(long, requires("forward_token_file"))]
+    forward_to: Option<String>,
+    /// Only valid when used with --forward-to.
+    ///
+    /// File holding the token the collector was started with, see
+    /// `below collect --token-file`.
+    #[clap(long, requires("forward_to"))]
+    forward_token_file: Option<PathBuf>,
+    /// Only valid when used with --forward-to.
+    ///
+    /// Do not write the local store, so samples only exist on the collector.
+    #[clap(long, requires("forward_to"))]
+    no_local_store: bool,
 }
 
-fn parse_chunk_size(s: &str) -> Result<u32> {
+/// Value of --dict-compress-chunk-size
+#[derive(Clone, Copy, Debug)]
+enum DictChunkSize {
+    Fixed(u32),
+    /// Picked for each shard from the size of the frames
+    Auto,
+}
+
+fn parse_chunk_size(s: &str) -> Result<DictChunkSize> {
+    if s == "auto" {
+        return Ok(DictChunkSize::Auto);
+    }
     let x = s
         .parse::<u32>()
         .with_context(|| format!("{} cannot be parsed as a u32", s))?;
@@ -143,46 +260,104 @@ fn parse_chunk_size(s: &str) -> Result<u32> {
             store::MAX_CHUNK_COMPRESS_SIZE
         );
     }
-    Ok(x)
+    Ok(DictChunkSize::Fixed(x))
 }
 
 #[derive(Debug, Parser)]
 enum Command {
     #[clap(flatten)]
     External(commands::Command),
-    /// Display live system data (interactive) (default)
+    /// Display live system data (interactive) (default). Builds without the
+    /// view only support --headless.
     Live {
-        #[clap(short, long, default_value = "5")]
-        interval_s: u64,
+        /// Defaults to the refresh_interval_s of the view profile, or 5
+        #[clap(short, long)]
+        interval_s: Option<u64>,
         /// Supply hostname to activate remote viewing
         #[clap(short = 's', long)]
         host: Option<String>,
         /// Override default port to connect remote viewing to
         #[clap(long, requires("host"))]
         port: Option<u16>,
+        /// View profile from the [view.profile.<name>] section of belowrc
+        #[clap(long)]
+        profile: Option<String>,
+        /// Low overhead mode for heavily loaded or latency sensitive hosts.
+        /// Only the system and the top level cgroups ranking highest by
+        /// --lite-metric are collected. Processes and the optional
+        /// collectors, e.g. ethtool and btrfs, are skipped.
+        #[clap(long, conflicts_with("host"))]
+        lite: bool,
+        /// Number of top level cgroups collected with --lite, 10 by default
+        #[clap(long, requires("lite"))]
+        lite_top_cgroups: Option<usize>,
+        /// Metric ranking the top level cgroups with --lite, one of cpu,
+        /// memory and pids. Defaults to cpu.
+        #[clap(long, requires("lite"))]
+        lite_metric: Option<model::LiteCgroupMetric>,
+        /// Record the keys pressed and the screens shown to this file, to
+        /// attach to bug reports. Play it back with `below debug view-replay`.
+        #[clap(long)]
+        record_session: Option<PathBuf>,
+        /// Print samples to stdout as they are collected instead of showing
+        /// the interactive view, e.g. to ship them elsewhere. Nothing is
+        /// stored.
+        #[clap(long, conflicts_with_all(["host", "record_session"]))]
+        headless: bool,
+        /// Output format of --headless. jsonl prints a JSON object per line
+        /// and sample.
+        #[clap(long, requires("headless"), default_value = "jsonl")]
+        format: HeadlessFormat,
+        /// Comma separated field ids to print with --headless, e.g.
+        /// system.cpu.usage_pct. Whole samples are printed by default.
+        #[clap(long, requires("headless"), value_delimiter = ',')]
+        fields: Vec<String>,
     },
     /// Record local system data (daemon mode)
     Record {
         #[clap(short, long, default_value = "5")]
         interval_s: u64,
-        /// Store retention in seconds. Data is stored in 24 hour shards.
-        /// Whever an entire shard of data is outside the retention period it
-        /// is discarded. That is, any data older than retention + 24 hours
-        /// is guaranteed to be discarded.
+        /// Store retention in seconds. Data is stored in shards of
+        /// --shard-duration-s, 24 hours by default. Whever an entire shard of
+        /// data is outside the retention period it is discarded. That is, any
+        /// data older than retention + shard duration is guaranteed to be
+        /// discarded.
         ///
         /// N.B. If --store-size-limit is set, data may be discarded earlier
         ///      than the specified retention.
         #[clap(long)]
         retain_for_s: Option<u64>,
-        /// Store size limit in bytes. Data is stored in 24 hour shards.
-        /// Shards before the active shard are deleted, oldest first,
-        /// according to the size limit. Enforcement is only triggered on new
-        /// shard creation.
+        /// Store size limit in bytes. Data is stored in shards of
+        /// --shard-duration-s, 24 hours by default. Shards before the active
+        /// shard are deleted, oldest first, according to the size limit.
+        /// Enforcement is only triggered on new shard creation.
         ///
         /// N.B. Since the active shard cannot be deleted, the size limit may
         ///      be exceeded by a single active shard.
         #[clap(long)]
         store_size_limit: Option<u64>,
+        /// Retention in seconds of downsampled data. If set, an aggregate of
+        /// every --downsample-interval-s is also written to the "downsampled"
+        /// subdirectory of the store, and kept for this long, e.g. to keep a
+        /// coarser history after --retain-for-s discarded the full samples.
+        /// Aggregates hold the last sample of the interval along with the
+        /// min, max and average of the main gauges and the main counters
+        /// summed over the interval. Viewing and dumping fall back to the
+        /// downsampled data where the full samples are gone.
+        ///
+        /// N.B. The downsampled data counts towards --store-size-limit.
+        #[clap(long)]
+        downsample_retain_for_s: Option<u64>,
+        /// Seconds aggregated by each downsampled frame
+        #[clap(long, default_value = "60")]
+        downsample_interval_s: u64,
+        /// Duration of a store shard in seconds, e.g. 3600 for hourly shards,
+        /// which lets retention and the size limit discard data in smaller
+        /// steps. Must evenly divide a day. Only applied when the store is
+        /// created, recording to an existing store with a different shard
+        /// duration fails. Defaults to 24 hours.
+        #[clap(long)]
+        shard_duration_s: Option<u64>,
         /// Whether or not to collect io.stat for cgroups which could
         /// be expensive
         #[clap(long)]
@@ -199,17 +374,74 @@ enum Command {
         /// Flag to disable eBPF-based exitstats
         #[clap(long)]
         disable_exitstats: bool,
+        /// Comma separated sample sections to leave out of the store, to
+        /// save space. Views show their values as "not collected".
+        /// Sections: cmdline, ethtool, slabinfo, tc
+        #[clap(long, value_delimiter = ',')]
+        omit: Vec<model::SampleSection>,
+        /// Options for compression
+        #[clap(flatten)]
+        compress_opts: CompressOpts,
+        /// Options for forwarding samples to a collector
+        #[clap(flatten)]
+        forward_opts: ForwardOpts,
+    },
+    /// Collect samples forwarded by `below record --forward-to` from other
+    /// hosts (daemon mode). Samples from each host are written to their own
+    /// store in a subdirectory named after the host.
+    Collect {
+        /// Address to listen on, e.g. "[::]:8999"
+        #[clap(long)]
+        listen: String,
+        /// File holding the token forwarders authenticate with, which must
+        /// not be accessible by group or others. The token is sent in the
+        /// clear, so only forward over a trusted network or a tunnel.
+        #[clap(long)]
+        token_file: PathBuf,
+        /// Maximum number of open connections, including ones that did not
+        /// authenticate yet. Further connections are dropped.
+        #[clap(long, default_value = "1024")]
+        max_connections: usize,
+        /// Directory holding the per-host stores. Defaults to "hosts" in the
+        /// configured store directory.
+        #[clap(long)]
+        store_dir: Option<PathBuf>,
+        /// Store retention in seconds, applied to each host's store the same
+        /// way as for `below record`.
+        #[clap(long)]
+        retain_for_s: Option<u64>,
+        /// Store size limit in bytes, applied to each host's store the same
+        /// way as for `below record`.
+        #[clap(long)]
+        store_size_limit: Option<u64>,
         /// Options for compression
         #[clap(flatten)]
         compress_opts: CompressOpts,
     },
+    /// Inspect a store
+    Store {
+        #[clap(subcommand)]
+        cmd: StoreCommand,
+    },
+    /// Manage a running `below record` through its control socket
+    Ctl {
+        /// Control socket of the recorder, defaults to the configured one
+        #[clap(long)]
+        socket: Option<PathBuf>,
+        #[clap(subcommand)]
+        cmd: CtlCommand,
+    },
     /// Replay historical data (interactive)
+    #[cfg(feature = "view")]
     Replay {
         /// Time string specifying the replay starting point, e.g. "1 day ago"{n}
         /// Keywords: now, today, yesterday{n}
+        /// Days: "yesterday 14:00", "monday 2:30pm", "last friday"{n}
         /// Relative: {humantime} ago, e.g. "2 days 3 hr 15m 10sec ago"{n}
         /// Relative short: Mixed {time_digit}{time_unit_char} E.g. 10m, 3d2h, 5h30s. Case insensitive.{n}
         /// Absolute: "Jan 01 23:59", "01/01/1970 11:59PM", "1970-01-01 23:59:59"{n}
+        /// ISO8601: "2024-03-10T14:00:00-05:00", "2024-03-10T19:00:00Z"{n}
+        /// Timezone: append a zoneinfo name, e.g. "2024-03-10 14:00 America/New_York"{n}
         /// Unix Epoch: 1589808367
         /// _
         #[clap(short, long, verbatim_doc_comment)]
@@ -228,9 +460,28 @@ enum Command {
         #[clap(short = 'r', verbatim_doc_comment)]
         yesterdays: Option<String>,
         /// Replay from a snapshot file generated by the snapshot
-        /// command instead of from the store directory.
+        /// command instead of from the store directory. Can be an http(s)
+        /// URL, optionally with the expected checksum appended as
+        /// "#sha256=<hex digest>".
         #[clap(long, conflicts_with("host"))]
         snapshot: Option<String>,
+        /// Show the values at the given offset earlier next to the current
+        /// ones, e.g. "1d" to compare with the same time yesterday. Can be
+        /// changed with the compare command while replaying.
+        #[clap(long, value_parser = cliutil::duration_from_str)]
+        compare: Option<Duration>,
+        /// View profile from the [view.profile.<name>] section of belowrc
+        #[clap(long)]
+        profile: Option<String>,
+        /// Play the samples back automatically at this multiple of the
+        /// recorded speed, e.g. "60x" to play an hour in a minute. Can be
+        /// paused with space and changed with the playback command.
+        #[clap(long, value_parser = view::playback::parse_speed)]
+        speed: Option<f64>,
+        /// Record the keys pressed and the screens shown to this file, to
+        /// attach to bug reports. Play it back with `below debug view-replay`.
+        #[clap(long)]
+        record_session: Option<PathBuf>,
     },
     /// Debugging facilities (for development use)
     Debug {
@@ -238,6 +489,7 @@ enum Command {
         cmd: DebugCommand,
     },
     /// Dump historical data into parseable text format
+    #[cfg(feature = "dump")]
     Dump {
         /// Supply hostname to activate remote dumping
         #[clap(short = 's', long)]
@@ -246,7 +498,8 @@ enum Command {
         #[clap(long, requires("host"))]
         port: Option<u16>,
         /// Dump from a snapshot file generated by the snapshot
-        /// command instead of from the store directory.
+        /// command instead of from the store directory. Can be an http(s)
+        /// URL, same as for replay.
         #[clap(long, conflicts_with("host"))]
         snapshot: Option<String>,
         #[clap(subcommand)]
@@ -277,6 +530,75 @@ enum Command {
         #[clap(long, requires("host"))]
         port: Option<u16>,
     },
+    /// Run a command in a cgroup of its own while recording it, then print
+    /// a summary of its resource usage and write a snapshot of the run for
+    /// replay, e.g. `below profile -- make -j8`
+    Profile {
+        /// Seconds between samples
+        #[clap(short, long, default_value = "1")]
+        interval_s: u64,
+        /// Snapshot file path.
+        /// Randomized name in current directory if unspecified.
+        #[clap(short, long, value_parser)]
+        output: Option<PathBuf>,
+        /// Command to run and its arguments
+        #[clap(last = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Scan a time range and summarize notable events: cgroups under the
+    /// most pressure, OOM kills, memory growth, CPU throttling and disk
+    /// saturation
+    Analyze {
+        /// Begin time, same format as replay
+        #[clap(short, long, verbatim_doc_comment)]
+        begin: String,
+        /// End time, same format as replay
+        #[clap(short, long, verbatim_doc_comment, group = "time")]
+        end: Option<String>,
+        /// Time string specifying the duration to analyze, e.g. "10 min"{n}
+        /// Keywords: days min, h, sec{n}
+        /// Relative: {humantime}, e.g. "2 days 3 hr 15m 10sec"{n}
+        /// _
+        #[clap(long, verbatim_doc_comment, group = "time")]
+        duration: Option<String>,
+        /// Number of entries to list per section
+        #[clap(long, default_value_t = 5)]
+        top: usize,
+        /// Comma separated field ids to also report the average, min, max
+        /// and sum of over the range, e.g. system.cpu.usage_pct
+        #[clap(long, value_delimiter = ',')]
+        aggregate: Vec<String>,
+        /// Supply hostname to analyze data from remote
+        #[clap(short = 's', long)]
+        host: Option<String>,
+        /// Override default port to connect to remote
+        #[clap(long, requires("host"))]
+        port: Option<u16>,
+    },
+    /// Mount the store as a read-only filesystem for ad-hoc exploration.
+    /// Each sample is a directory named by its unix timestamp, holding a
+    /// JSON file per model section.
+    #[cfg(feature = "fuse")]
+    Fuse {
+        /// Directory to mount on
+        #[clap(value_parser)]
+        mountpoint: PathBuf,
+        /// Begin time, same format as replay
+        #[clap(short, long, verbatim_doc_comment)]
+        begin: String,
+        /// End time, same format as replay
+        #[clap(short, long, verbatim_doc_comment, group = "time")]
+        end: Option<String>,
+        /// Time string specifying the duration to expose, e.g. "10 min"{n}
+        /// Keywords: days min, h, sec{n}
+        /// Relative: {humantime}, e.g. "2 days 3 hr 15m 10sec"{n}
+        /// _
+        #[clap(long, verbatim_doc_comment, group = "time")]
+        duration: Option<String>,
+        /// Store directory to mount instead of the configured one
+        #[clap(long)]
+        store_dir: Option<PathBuf>,
+    },
     /// Generate a shell completions file
     #[clap(hide = true)]
     GenerateCompletions {
@@ -320,6 +642,68 @@ enum DebugCommand {
         #[clap(flatten)]
         compress_opts: CompressOpts,
     },
+    /// Collect a sample and print which data sources are available, e.g. to
+    /// see what can be collected in a chroot or an initramfs. Unavailable
+    /// sources come with the reason.
+    Capabilities,
+    /// Play back a session recorded with --record-session, printing the
+    /// screens shown after each key. The session is replayed from the store
+    /// starting at the sample the recording started at.
+    #[cfg(feature = "view")]
+    ViewReplay {
+        /// Session file to play back
+        #[clap(long)]
+        session: PathBuf,
+        /// Replay from a snapshot file generated by the snapshot command
+        /// instead of from the store directory
+        #[clap(long)]
+        snapshot: Option<String>,
+    },
+}
+
+#[derive(Debug, Parser)]
+enum StoreCommand {
+    /// Verify the integrity of a store, e.g. after copying it between
+    /// hosts. Closed shards are checked against their manifest.
+    Verify {
+        /// Store to verify, defaults to the configured store
+        #[clap(long)]
+        store_dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Parser)]
+enum CtlCommand {
+    /// Sync the samples written to the active shard to disk
+    Flush,
+    /// Close and reopen the files of the active shard, e.g. after they were
+    /// moved or removed by an external tool
+    Rotate,
+    /// Change the collection interval. Background collectors, e.g. for GPU
+    /// and tc stats, keep the interval the recorder started with.
+    SetInterval {
+        #[clap(value_parser)]
+        interval_s: u64,
+    },
+    /// Print statistics of the recorder itself
+    Stats,
+    /// Have the recorder create a snapshot file, same as `below snapshot`
+    Snapshot {
+        /// Begin time, same format as replay
+        #[clap(short, long, verbatim_doc_comment)]
+        begin: String,
+        /// End time, same format as replay
+        #[clap(short, long, verbatim_doc_comment, group = "time")]
+        end: Option<String>,
+        /// Time string specifying the snapshot duration, same format as
+        /// snapshot
+        #[clap(long, verbatim_doc_comment, group = "time")]
+        duration: Option<String>,
+        /// Output file path. Written by the recorder, so it must be able to
+        /// write there.
+        #[clap(short, long, value_parser)]
+        output: PathBuf,
+    },
 }
 
 // Whether or not to start a service to respond to network request
@@ -396,8 +780,13 @@ fn create_log_dir(path: &PathBuf) -> Result<()> {
 fn start_exitstat(
     logger: slog::Logger,
     debug: bool,
+    bpf_stats: Arc<Mutex<model::BpfProgramStats>>,
 ) -> (Arc<Mutex<procfs::PidMap>>, Option<Receiver<Error>>) {
-    let mut exit_driver = exitstat::ExitstatDriver::new(logger, debug);
+    let mut exit_driver = exitstat::ExitstatDriver::new(
+        logger,
+        debug,
+        bpfstats::BpfHealth::new("exitstat", bpf_stats),
+    );
     let exit_buffer = exit_driver.get_buffer();
     let (bpf_err_send, bpf_err_recv) = channel();
     thread::Builder::new()
@@ -413,6 +802,164 @@ fn start_exitstat(
     (exit_buffer, Some(bpf_err_recv))
 }
 
+// File IO tracing runs a bpf program that hooks into reads and writes of
+// regular files to attribute IO to files per cgroup. Failures are logged and
+// leave the file IO data empty rather than stopping the collection.
+fn start_file_io(
+    logger: slog::Logger,
+    debug: bool,
+    interval: Duration,
+    bpf_stats: Arc<Mutex<model::BpfProgramStats>>,
+) -> Arc<Mutex<model::CgroupFileIoMap>> {
+    let mut file_io_driver = fileio::FileIoDriver::new(
+        debug,
+        interval,
+        bpfstats::BpfHealth::new("fileio", bpf_stats),
+    );
+    let file_io_buffer = file_io_driver.get_buffer();
+    thread::Builder::new()
+        .name("file_io_driver".to_owned())
+        .spawn(move || {
+            if let Err(e) = file_io_driver.drive() {
+                error!(logger, "File IO tracing stopped: {:#}", e);
+            }
+        })
+        .expect("Failed to spawn thread");
+    file_io_buffer
+}
+
+// Process network tracing runs a bpf program that hooks into socket sends
+// and receives to attribute IP traffic to processes. Like file IO tracing,
+// failures are logged and leave the data empty.
+fn start_process_net(
+    logger: slog::Logger,
+    debug: bool,
+    interval: Duration,
+    bpf_stats: Arc<Mutex<model::BpfProgramStats>>,
+) -> Arc<Mutex<model::ProcessNetMap>> {
+    let mut proc_net_driver = procnet::ProcNetDriver::new(
+        debug,
+        interval,
+        bpfstats::BpfHealth::new("procnet", bpf_stats),
+    );
+    let proc_net_buffer = proc_net_driver.get_buffer();
+    thread::Builder::new()
+        .name("proc_net_driver".to_owned())
+        .spawn(move || {
+            if let Err(e) = proc_net_driver.drive() {
+                error!(logger, "Process network tracing stopped: {:#}", e);
+            }
+        })
+        .expect("Failed to spawn thread");
+    proc_net_buffer
+}
+
+// Perf counters are read on a thread of their own like the BPF collectors,
+// which keeps opening events for every CPU and cgroup out of the collection.
+// Failures are logged and leave the perf counter data empty.
+fn start_perf_counters(
+    logger: slog::Logger,
+    cgroup_root: PathBuf,
+    interval: Duration,
+) -> Arc<Mutex<model::PerfCounters>> {
+    let mut perf_counter_driver =
+        perfcounters::PerfCounterDriver::new(logger.clone(), cgroup_root, interval);
+    let perf_counter_buffer = perf_counter_driver.get_buffer();
+    thread::Builder::new()
+        .name("perf_counter_driver".to_owned())
+        .spawn(move || {
+            if let Err(e) = perf_counter_driver.drive() {
+                error!(logger, "Perf counters stopped: {:#}", e);
+            }
+        })
+        .expect("Failed to spawn thread");
+    perf_counter_buffer
+}
+
+// PSI triggers are watched on a thread of their own as firings have to be
+// timestamped when they happen rather than at the next collection.
+fn start_psi_triggers(
+    logger: slog::Logger,
+    below_config: &BelowConfig,
+) -> Arc<Mutex<model::CgroupPsiTriggerMap>> {
+    let mut watcher = psi_triggers::PsiTriggerWatcher::new(
+        logger.clone(),
+        below_config.cgroup_root.clone(),
+        below_config.psi_trigger_cgroups.clone(),
+        Duration::from_micros(below_config.psi_trigger_threshold_us),
+        Duration::from_micros(below_config.psi_trigger_window_us),
+    );
+    let data = watcher.get_buffer();
+    thread::Builder::new()
+        .name("psi_triggers".to_owned())
+        .spawn(move || {
+            if let Err(e) = watcher.drive() {
+                error!(logger, "PSI trigger watching stopped: {:#}", e);
+            }
+        })
+        .expect("Failed to spawn thread");
+    data
+}
+
+// Kills are ingested on a thread of their own, following the journal as
+// they are logged
+fn start_oom_kill_events(logger: slog::Logger) -> Arc<Mutex<Vec<model::OomKillEvent>>> {
+    let mut watcher = oomd::OomKillWatcher::new();
+    let data = watcher.get_buffer();
+    thread::Builder::new()
+        .name("oom_kill_events".to_owned())
+        .spawn(move || {
+            if let Err(e) = watcher.drive() {
+                error!(logger, "OOM killer event ingestion stopped: {:#}", e);
+            }
+        })
+        .expect("Failed to spawn thread");
+    data
+}
+
+// Errors are counted on a thread of their own, following the journal as
+// they are logged
+fn start_journal_errors(
+    logger: slog::Logger,
+    below_config: &BelowConfig,
+) -> Arc<Mutex<model::CgroupJournalErrorMap>> {
+    let mut watcher =
+        journal_errors::JournalErrorWatcher::new(below_config.journal_error_units.clone());
+    let data = watcher.get_buffer();
+    thread::Builder::new()
+        .name("journal_errors".to_owned())
+        .spawn(move || {
+            if let Err(e) = watcher.drive() {
+                error!(logger, "Journal error counting stopped: {:#}", e);
+            }
+        })
+        .expect("Failed to spawn thread");
+    data
+}
+
+fn start_stack_sampling(
+    logger: slog::Logger,
+    below_config: &BelowConfig,
+) -> model::StackSamplingOptions {
+    let (request_send, request_recv) = channel();
+    let mut stack_sampler = stacks::StackSampler::new(request_recv);
+    let data = stack_sampler.get_buffer();
+    thread::Builder::new()
+        .name("stack_sampler".to_owned())
+        .spawn(move || {
+            if let Err(e) = stack_sampler.drive() {
+                error!(logger, "Stack sampling stopped: {:#}", e);
+            }
+        })
+        .expect("Failed to spawn thread");
+    model::StackSamplingOptions {
+        cpu_threshold_pct: below_config.stack_sampling_cpu_threshold_pct,
+        consecutive_samples: below_config.stack_sampling_consecutive_samples,
+        requests: request_send,
+        data,
+    }
+}
+
 pub fn start_gpu_stats_thread_and_get_stats_receiver(
     init: init::InitToken,
     logger: slog::Logger,
@@ -555,6 +1102,50 @@ fn check_for_exitstat_errors(logger: &slog::Logger, receiver: &Receiver<Error>)
     false
 }
 
+/// Start a thread writing the downsampled samples of the store at `store_dir`
+/// every `interval`, and discarding those older than `retention`.
+fn start_downsampling(
+    logger: slog::Logger,
+    store_dir: PathBuf,
+    interval: Duration,
+    retention: Duration,
+    compress_opts: &CompressOpts,
+) -> Result<()> {
+    if interval.is_zero() {
+        bail!("--downsample-interval-s must be at least 1");
+    }
+    let compression_mode = compress_opts.to_compression_mode()?;
+    let format = compress_opts.to_format();
+    thread::Builder::new()
+        .name("downsampler".to_owned())
+        .spawn(move || {
+            let mut downsampler = match store::downsample::Downsampler::new(
+                logger.clone(),
+                &store_dir,
+                interval,
+                compression_mode,
+                format,
+            ) {
+                Ok(downsampler) => downsampler,
+                Err(e) => {
+                    error!(logger, "Downsampling disabled: {:#}", e);
+                    return;
+                }
+            };
+            loop {
+                if let Err(e) = downsampler.run() {
+                    error!(logger, "Failed to downsample: {:#}", e);
+                }
+                if let Err(e) = downsampler.discard_earlier(SystemTime::now() - retention) {
+                    error!(logger, "Failed to discard downsampled data: {:#}", e);
+                }
+                thread::sleep(interval);
+            }
+        })
+        .context("Failed to spawn downsampler thread")?;
+    Ok(())
+}
+
 /// Discard old data shards in store according to store size limit and retention
 fn cleanup_store(
     store: &store::StoreWriter,
@@ -600,7 +1191,7 @@ impl std::fmt::Display for StopSignal {
 
 pub fn run<F>(
     init: init::InitToken,
-    debug: bool,
+    log_options: &logutil::LogOptions,
     below_config: &BelowConfig,
     _service: Service,
     redirect: RedirectLogOnFail,
@@ -620,8 +1211,10 @@ where
         return 1;
     }
 
-    let logger = logging::setup(init, log_dir, debug, redirect);
+    let logger = logging::setup(init, log_dir, log_options, redirect);
     setup_log_on_panic(logger.clone());
+    #[cfg(feature = "view")]
+    view::panic_report::set_panic_report_dir(below_config.log_dir.clone());
 
     match Signals::new(&[signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM]) {
         Ok(mut signals) => {
@@ -696,37 +1289,86 @@ fn main() {
 fn real_main(init: init::InitToken) {
     let opts = Opt::parse();
     let debug = opts.debug;
+    let log_options = logutil::LogOptions {
+        level: opts.log_level.clone().unwrap_or_else(|| {
+            logutil::LogLevelSpec::new(if debug {
+                slog::FilterLevel::Debug
+            } else {
+                slog::FilterLevel::Info
+            })
+        }),
+        format: opts.log_format,
+    };
     config::BELOW_CONFIG
-        .set(match BelowConfig::load(&opts.config) {
-            Ok(c) => c,
-            Err(e) => {
-                eprintln!("{:#}", e);
-                exit(1);
-            }
-        })
+        .set(
+            match BelowConfig::load_layered(
+                &opts.config,
+                std::env::vars_os(),
+                &opts.config_overrides,
+            ) {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("{:#}", e);
+                    exit(1);
+                }
+            },
+        )
         .expect("BELOW_CONFIG singleton set twice");
     let below_config = config::BELOW_CONFIG
         .get()
         .expect("BELOW_CONFIG empty after set");
 
     // Use live mode as default
+    #[cfg(feature = "view")]
     let cmd = opts.cmd.as_ref().unwrap_or(&Command::Live {
-        interval_s: 5,
+        interval_s: None,
         host: None,
         port: None,
+        profile: None,
+        lite: false,
+        lite_top_cgroups: None,
+        lite_metric: None,
+        record_session: None,
+        headless: false,
+        format: HeadlessFormat::Jsonl,
+        fields: Vec::new(),
     });
+    // Without the view there is no sensible default
+    #[cfg(not(feature = "view"))]
+    let cmd = match opts.cmd.as_ref() {
+        Some(cmd) => cmd,
+        None => {
+            Opt::command().print_help().expect("Failed to print help");
+            exit(2);
+        }
+    };
     let rc = match cmd {
         Command::External(command) => commands::run_command(init, debug, below_config, command),
         Command::Live {
             ref interval_s,
             ref host,
             ref port,
+            ref profile,
+            ref lite,
+            ref lite_top_cgroups,
+            ref lite_metric,
+            ref record_session,
+            ref headless,
+            ref format,
+            ref fields,
         } => {
             let host = host.clone();
             let port = port.clone();
+            let profile = profile.clone();
+            let record_session = record_session.clone();
+            let headless = headless.then(|| (*format, fields.clone()));
+            let lite = lite.then(|| model::LiteOptions {
+                top_cgroups: lite_top_cgroups.unwrap_or(DEFAULT_LITE_TOP_CGROUPS),
+                metric: lite_metric.unwrap_or(model::LiteCgroupMetric::Cpu),
+            });
             run(
                 init,
-                debug,
+                &log_options,
                 below_config,
                 Service::Off,
                 RedirectLogOnFail::On,
@@ -735,11 +1377,15 @@ fn real_main(init: init::InitToken) {
                         init,
                         logger,
                         errs,
-                        Duration::from_secs(*interval_s as u64),
+                        *interval_s,
                         debug,
                         below_config,
                         host,
                         port,
+                        profile,
+                        lite,
+                        record_session,
+                        headless,
                     )
                 },
             )
@@ -748,17 +1394,22 @@ fn real_main(init: init::InitToken) {
             ref interval_s,
             ref retain_for_s,
             ref store_size_limit,
+            ref downsample_retain_for_s,
+            ref downsample_interval_s,
+            ref shard_duration_s,
             ref collect_io_stat,
             ref port,
             ref skew_detection_threshold_ms,
             ref disable_disk_stat,
             ref disable_exitstats,
+            ref omit,
             ref compress_opts,
+            ref forward_opts,
         } => {
             logutil::set_current_log_target(logutil::TargetLog::Term);
             run(
                 init,
-                debug,
+                &log_options,
                 below_config,
                 Service::On(*port),
                 RedirectLogOnFail::Off,
@@ -771,31 +1422,112 @@ fn real_main(init: init::InitToken) {
                         below_config,
                         retain_for_s.map(|r| Duration::from_secs(r as u64)),
                         *store_size_limit,
+                        downsample_retain_for_s.map(|r| {
+                            (
+                                Duration::from_secs(*downsample_interval_s),
+                                Duration::from_secs(r),
+                            )
+                        }),
+                        *shard_duration_s,
                         *collect_io_stat,
                         Duration::from_millis(*skew_detection_threshold_ms),
                         debug,
                         *disable_disk_stat,
                         *disable_exitstats,
+                        omit.iter().copied().collect(),
+                        compress_opts,
+                        forward_opts,
+                    )
+                },
+            )
+        }
+        Command::Collect {
+            ref listen,
+            ref token_file,
+            ref max_connections,
+            ref store_dir,
+            ref retain_for_s,
+            ref store_size_limit,
+            ref compress_opts,
+        } => {
+            logutil::set_current_log_target(logutil::TargetLog::Term);
+            let store_dir = store_dir
+                .clone()
+                .unwrap_or_else(|| below_config.store_dir.join("hosts"));
+            run(
+                init,
+                &log_options,
+                below_config,
+                Service::Off,
+                RedirectLogOnFail::Off,
+                |_, _below_config, logger, errs| {
+                    collect(
+                        logger,
+                        errs,
+                        listen,
+                        token_file,
+                        *max_connections,
+                        store_dir,
+                        retain_for_s.map(|r| Duration::from_secs(r as u64)),
+                        *store_size_limit,
                         compress_opts,
                     )
                 },
             )
         }
+        Command::Store { ref cmd } => match cmd {
+            StoreCommand::Verify { ref store_dir } => {
+                let store_dir = store_dir.clone();
+                run(
+                    init,
+                    &log_options,
+                    below_config,
+                    Service::Off,
+                    RedirectLogOnFail::Off,
+                    |_, below_config, _logger, _errs| verify_store(below_config, store_dir),
+                )
+            }
+        },
+        Command::Ctl {
+            ref socket,
+            ref cmd,
+        } => {
+            let socket = socket
+                .clone()
+                .unwrap_or_else(|| below_config.control_socket.clone());
+            run(
+                init,
+                &log_options,
+                below_config,
+                Service::Off,
+                RedirectLogOnFail::Off,
+                |_, _below_config, _logger, _errs| ctl(socket, cmd),
+            )
+        }
+        #[cfg(feature = "view")]
         Command::Replay {
             ref time,
             ref host,
             ref port,
             ref yesterdays,
             ref snapshot,
+            ref compare,
+            ref profile,
+            ref speed,
+            ref record_session,
         } => {
             let time = time.clone();
             let host = host.clone();
             let port = port.clone();
             let days_adjuster = yesterdays.clone();
             let snapshot = snapshot.clone();
+            let compare = *compare;
+            let profile = profile.clone();
+            let speed = *speed;
+            let record_session = record_session.clone();
             run(
                 init,
-                debug,
+                &log_options,
                 below_config,
                 Service::Off,
                 RedirectLogOnFail::Off,
@@ -809,6 +1541,10 @@ fn real_main(init: init::InitToken) {
                         port,
                         days_adjuster,
                         snapshot,
+                        compare,
+                        profile,
+                        speed,
+                        record_session,
                     )
                 },
             )
@@ -829,7 +1565,7 @@ fn real_main(init: init::InitToken) {
             let port = port.clone();
             run(
                 init,
-                debug,
+                &log_options,
                 below_config,
                 Service::Off,
                 RedirectLogOnFail::Off,
@@ -847,23 +1583,111 @@ fn real_main(init: init::InitToken) {
                 },
             )
         }
-        Command::Debug { ref cmd } => match cmd {
-            DebugCommand::DumpStore { ref time, ref json } => {
-                let time = time.clone();
-                let json = json.clone();
-                run(
-                    init,
-                    debug,
-                    below_config,
-                    Service::Off,
-                    RedirectLogOnFail::Off,
-                    |_, below_config, logger, _errs| dump_store(logger, time, below_config, json),
-                )
-            }
-            DebugCommand::ConvertStore {
-                ref begin,
-                ref end,
-                ref duration,
+        Command::Profile {
+            ref interval_s,
+            ref output,
+            ref command,
+        } => {
+            let interval = Duration::from_secs(*interval_s);
+            let output = output.clone();
+            let command = command.clone();
+            run(
+                init,
+                &log_options,
+                below_config,
+                Service::Off,
+                RedirectLogOnFail::Off,
+                |_, below_config, logger, _errs| {
+                    profile(logger, below_config, interval, output, command)
+                },
+            )
+        }
+        Command::Analyze {
+            ref begin,
+            ref end,
+            ref duration,
+            ref top,
+            ref aggregate,
+            ref host,
+            ref port,
+        } => {
+            let begin = begin.clone();
+            let end = end.clone();
+            let duration = duration.clone();
+            let top = *top;
+            let aggregate = aggregate.clone();
+            let host = host.clone();
+            let port = port.clone();
+            run(
+                init,
+                &log_options,
+                below_config,
+                Service::Off,
+                RedirectLogOnFail::Off,
+                |_, below_config, logger, _errs| {
+                    analyze(
+                        logger,
+                        below_config,
+                        begin,
+                        end,
+                        duration,
+                        top,
+                        aggregate,
+                        host,
+                        port,
+                    )
+                },
+            )
+        }
+        #[cfg(feature = "fuse")]
+        Command::Fuse {
+            ref mountpoint,
+            ref begin,
+            ref end,
+            ref duration,
+            ref store_dir,
+        } => {
+            let mountpoint = mountpoint.clone();
+            let begin = begin.clone();
+            let end = end.clone();
+            let duration = duration.clone();
+            let store_dir = store_dir.clone();
+            run(
+                init,
+                &log_options,
+                below_config,
+                Service::Off,
+                RedirectLogOnFail::Off,
+                |_, below_config, logger, _errs| {
+                    fuse_mount(
+                        logger,
+                        below_config,
+                        mountpoint,
+                        begin,
+                        end,
+                        duration,
+                        store_dir,
+                    )
+                },
+            )
+        }
+        Command::Debug { ref cmd } => match cmd {
+            DebugCommand::DumpStore { ref time, ref json } => {
+                let time = time.clone();
+                let json = json.clone();
+                run(
+                    init,
+                    &log_options,
+                    below_config,
+                    Service::Off,
+                    RedirectLogOnFail::Off,
+                    |_, below_config, logger, _errs| dump_store(logger, time, below_config, json),
+                )
+            }
+            DebugCommand::ConvertStore {
+                ref begin,
+                ref end,
+                ref duration,
                 ref from_store_dir,
                 ref to_store_dir,
                 ref host,
@@ -879,7 +1703,7 @@ fn real_main(init: init::InitToken) {
                 let port = port.clone();
                 run(
                     init,
-                    debug,
+                    &log_options,
                     below_config,
                     Service::Off,
                     RedirectLogOnFail::Off,
@@ -895,11 +1719,39 @@ fn real_main(init: init::InitToken) {
                             host,
                             port,
                             compress_opts,
+                            ProgressBar::no_length(),
                         )
                     },
                 )
             }
+            DebugCommand::Capabilities => run(
+                init,
+                &log_options,
+                below_config,
+                Service::Off,
+                RedirectLogOnFail::Off,
+                |_, below_config, logger, _errs| capabilities(logger, below_config),
+            ),
+            #[cfg(feature = "view")]
+            DebugCommand::ViewReplay {
+                ref session,
+                ref snapshot,
+            } => {
+                let session = session.clone();
+                let snapshot = snapshot.clone();
+                run(
+                    init,
+                    &log_options,
+                    below_config,
+                    Service::Off,
+                    RedirectLogOnFail::Off,
+                    |_, below_config, logger, _errs| {
+                        view_replay(logger, below_config, session, snapshot)
+                    },
+                )
+            }
         },
+        #[cfg(feature = "dump")]
         Command::Dump {
             ref host,
             ref port,
@@ -913,7 +1765,7 @@ fn real_main(init: init::InitToken) {
             let cmd = cmd.clone();
             run(
                 init,
-                debug,
+                &log_options,
                 below_config,
                 Service::Off,
                 RedirectLogOnFail::Off,
@@ -934,6 +1786,7 @@ fn real_main(init: init::InitToken) {
     exit(rc);
 }
 
+#[cfg(feature = "view")]
 fn replay(
     logger: slog::Logger,
     errs: Receiver<Error>,
@@ -943,30 +1796,41 @@ fn replay(
     port: Option<u16>,
     days_adjuster: Option<String>,
     snapshot: Option<String>,
+    compare: Option<Duration>,
+    profile: Option<String>,
+    speed: Option<f64>,
+    record_session: Option<PathBuf>,
 ) -> Result<()> {
+    let (viewrc, viewrc_error) = load_viewrc(profile.as_deref())?;
     let timestamp =
         cliutil::system_time_from_date_and_adjuster(time.as_str(), days_adjuster.as_deref())?;
 
-    let mut advance = match (host, snapshot) {
-        (None, None) => {
-            new_advance_local(logger.clone(), below_config.store_dir.clone(), timestamp)
-        }
-        (Some(host), None) => new_advance_remote(logger.clone(), host, port, timestamp)?,
-        (None, Some(snapshot)) => {
-            let mut tarball =
-                Archive::new(fs::File::open(&snapshot).context("Failed to open snapshot file")?);
-            let mut snapshot_dir = TempDir::with_prefix("snapshot_replay.")?.into_path();
-            tarball.unpack(&snapshot_dir)?;
-            // Find and append the name of the original snapshot directory
-            for path in fs::read_dir(&snapshot_dir)? {
-                snapshot_dir.push(path.unwrap().file_name());
-            }
-            new_advance_local(logger.clone(), snapshot_dir, timestamp)
-        }
+    // Local store to replay from, None for a remote host. A snapshot is kept
+    // unpacked until the view exits.
+    let mut unpacked = None;
+    let store_dir = match (&host, snapshot) {
+        (None, None) => Some(below_config.store_dir.clone()),
+        (Some(_), None) => None,
+        (None, Some(snapshot)) => Some(
+            unpacked
+                .insert(dump::snapshot::open_snapshot(&logger, &snapshot)?)
+                .store_dir()
+                .to_owned(),
+        ),
         (Some(_), Some(_)) => {
             bail!("--host and --snapshot are incompatible options")
         }
     };
+    let new_advance = |timestamp| match (&store_dir, &host) {
+        (Some(store_dir), _) => Ok(new_advance_local(
+            logger.clone(),
+            store_dir.clone(),
+            timestamp,
+        )),
+        (None, Some(host)) => new_advance_remote(logger.clone(), host.clone(), port, timestamp),
+        (None, None) => unreachable!("Store dir is set without a host"),
+    };
+    let mut advance = new_advance(timestamp)?;
 
     // Fill the last_sample for forward iteration. If no previous sample exists,
     // this should have no effect.
@@ -984,10 +1848,27 @@ fn replay(
 
     cliutil::check_initial_sample_time_with_requested_time(model.timestamp, timestamp);
 
-    let mut view = view::View::new_with_advance(
+    let mut view = view::View::new_with_viewrc(
         model,
         view::ViewMode::Replay(Rc::new(RefCell::new(advance))),
+        viewrc,
+        viewrc_error,
     );
+    // A second cursor into the same store, following the replayed one at
+    // the compare offset
+    view.set_compare(new_advance(timestamp)?, compare);
+    view.set_snapshot(view_snapshot(
+        logger.clone(),
+        store_dir.clone(),
+        host.clone(),
+        port,
+    ));
+    if let Some(speed) = speed {
+        view.set_playback(speed);
+    }
+    if let Some(path) = record_session {
+        view.record_session(&path)?;
+    }
     logutil::set_current_log_target(logutil::TargetLog::File);
 
     let sink = view.cb_sink().clone();
@@ -1010,41 +1891,195 @@ fn replay(
     view.run()
 }
 
+/// Play back a recorded session against the store, or a snapshot, printing
+/// the screens to stdout
+#[cfg(feature = "view")]
+fn view_replay(
+    logger: slog::Logger,
+    below_config: &BelowConfig,
+    session: PathBuf,
+    snapshot: Option<String>,
+) -> Result<()> {
+    let mut unpacked = None;
+    let store_dir = match snapshot {
+        Some(snapshot) => unpacked
+            .insert(dump::snapshot::open_snapshot(&logger, &snapshot)?)
+            .store_dir()
+            .to_owned(),
+        None => below_config.store_dir.clone(),
+    };
+    let timestamp =
+        SystemTime::UNIX_EPOCH + Duration::from_secs(view::View::session_timestamp(&session)?);
+    let mut advance = new_advance_local(logger, store_dir, timestamp);
+    advance.initialize();
+    let model = match advance.jump_sample_to(timestamp) {
+        Some(m) => m,
+        None => bail!("No sample found for the start of the session"),
+    };
+
+    // View settings of belowrc are left out so that the session plays back the
+    // same on any host
+    let mut view = view::View::new_with_viewrc(
+        model,
+        view::ViewMode::Replay(Rc::new(RefCell::new(advance))),
+        view::viewrc::ViewRc::default(),
+        None,
+    );
+    view.replay_session(&session, Box::new(std::io::stdout()))
+}
+
+/// Statistics of `below record` itself, reported over the control socket
+struct RecordStats {
+    started: Instant,
+    samples: u64,
+    collection_errors: u64,
+    slow_collections: u64,
+    last_collection: Duration,
+    /// Health of the BPF collectors, by collector name
+    bpf_stats: Arc<Mutex<model::BpfProgramStats>>,
+}
+
 fn record(
     init: init::InitToken,
     logger: slog::Logger,
     errs: Receiver<Error>,
-    interval: Duration,
+    mut interval: Duration,
     below_config: &BelowConfig,
     retention: Option<Duration>,
     store_size_limit: Option<u64>,
+    downsample: Option<(Duration, Duration)>,
+    shard_duration_s: Option<u64>,
     collect_io_stat: bool,
     skew_detection_threshold: Duration,
     debug: bool,
     disable_disk_stat: bool,
     disable_exitstats: bool,
+    omit_sections: BTreeSet<model::SampleSection>,
     compress_opts: &CompressOpts,
+    forward_opts: &ForwardOpts,
 ) -> Result<()> {
     debug!(logger, "Starting up!");
 
-    if !disable_exitstats {
+    if !disable_exitstats
+        || below_config.enable_file_io_stats
+        || below_config.enable_process_net_stats
+    {
         bump_memlock_rlimit()?;
     }
 
-    let mut store = store::StoreWriter::new(
-        logger.clone(),
-        &below_config.store_dir,
-        compress_opts.to_compression_mode()?,
-        store::Format::Cbor,
-    )?;
+    let mut store = if forward_opts.no_local_store {
+        None
+    } else {
+        if let Some(shard_duration_s) = shard_duration_s {
+            store::StoreMetadata::new(shard_duration_s)?.init(&below_config.store_dir)?;
+        }
+        let mut store = store::StoreWriter::new(
+            logger.clone(),
+            &below_config.store_dir,
+            compress_opts.to_compression_mode()?,
+            compress_opts.to_format(),
+        )?;
+        store.set_process_delta(compress_opts.process_delta)?;
+        Some(store)
+    };
+    if let Some((downsample_interval, downsample_retention)) = downsample {
+        if store.is_none() {
+            bail!("Downsampling requires a local store");
+        }
+        start_downsampling(
+            logger.clone(),
+            below_config.store_dir.clone(),
+            downsample_interval,
+            downsample_retention,
+            compress_opts,
+        )?;
+    }
+    let mut forwarder = match (&forward_opts.forward_to, &forward_opts.forward_token_file) {
+        (Some(addr), Some(token_file)) => Some(store::forward::ForwardWriter::new(
+            logger.clone(),
+            addr.clone(),
+            store::forward::read_token(token_file)?,
+        )),
+        _ => None,
+    };
     let mut stats = statistics::Statistics::new(init.clone());
+    let mut record_stats = RecordStats {
+        started: Instant::now(),
+        samples: 0,
+        collection_errors: 0,
+        slow_collections: 0,
+        last_collection: Duration::ZERO,
+        bpf_stats: Arc::new(Mutex::new(model::BpfProgramStats::new())),
+    };
+    let control_calls = if below_config.control_socket.as_os_str().is_empty() {
+        None
+    } else {
+        match control::listen(logger.clone(), &below_config.control_socket) {
+            Ok(calls) => Some(calls),
+            // Recording carries on, it just cannot be managed
+            Err(e) => {
+                warn!(logger, "Control socket disabled: {:#}", e);
+                None
+            }
+        }
+    };
 
+    let bpf_stats = record_stats.bpf_stats.clone();
     let (exit_buffer, bpf_errs) = if disable_exitstats {
         (Arc::new(Mutex::new(procfs::PidMap::new())), None)
     } else {
-        start_exitstat(logger.clone(), debug)
+        start_exitstat(logger.clone(), debug, bpf_stats.clone())
     };
     let mut bpf_err_warned = false;
+    let file_io_data = if below_config.enable_file_io_stats {
+        Some(start_file_io(
+            logger.clone(),
+            debug,
+            interval,
+            bpf_stats.clone(),
+        ))
+    } else {
+        None
+    };
+    let process_net_data = if below_config.enable_process_net_stats {
+        Some(start_process_net(
+            logger.clone(),
+            debug,
+            interval,
+            bpf_stats.clone(),
+        ))
+    } else {
+        None
+    };
+    let perf_counter_data = if below_config.enable_perf_counter_stats {
+        Some(start_perf_counters(
+            logger.clone(),
+            below_config.cgroup_root.clone(),
+            interval,
+        ))
+    } else {
+        None
+    };
+    let psi_trigger_data = if below_config.psi_trigger_cgroups.is_empty() {
+        None
+    } else {
+        Some(start_psi_triggers(logger.clone(), below_config))
+    };
+    let oom_kill_data = if below_config.enable_oom_killer_events {
+        Some(start_oom_kill_events(logger.clone()))
+    } else {
+        None
+    };
+    let journal_error_data = if below_config.enable_journal_errors {
+        Some(start_journal_errors(logger.clone(), below_config))
+    } else {
+        None
+    };
+    let stack_sampling = if below_config.enable_stack_sampling {
+        Some(start_stack_sampling(logger.clone(), below_config))
+    } else {
+        None
+    };
 
     // Handle cgroup filter from conf and generate Regex
     let cgroup_re = if !below_config.cgroup_filter_out.is_empty() {
@@ -1056,6 +2091,22 @@ fn record(
         None
     };
 
+    let cmdline_redaction =
+        if below_config.cmdline_argv0_only || !below_config.cmdline_redact_regex.is_empty() {
+            Some(model::CmdlineRedaction {
+                argv0_only: below_config.cmdline_argv0_only,
+                regex: if below_config.cmdline_redact_regex.is_empty() {
+                    None
+                } else {
+                    Some(Regex::new(&below_config.cmdline_redact_regex).context(
+                        "Failed to generate regex from cmdline_redact_regex in below.conf",
+                    )?)
+                },
+            })
+        } else {
+            None
+        };
+
     let gpu_stats_receiver = if below_config.enable_gpu_stats {
         Some(start_gpu_stats_thread_and_get_stats_receiver(
             init,
@@ -1079,7 +2130,15 @@ fn record(
         logger.clone(),
         model::CollectorOptions {
             cgroup_root: below_config.cgroup_root.clone(),
+            extra_cgroup_roots: below_config.extra_cgroup_roots.clone(),
             exit_data: exit_buffer,
+            file_io_data,
+            psi_trigger_data,
+            oom_kill_data,
+            journal_error_data,
+            process_net_data,
+            perf_counter_data,
+            bpf_stats: Some(bpf_stats),
             collect_io_stat,
             disable_disk_stat,
             enable_btrfs_stats: below_config.enable_btrfs_stats,
@@ -1087,14 +2146,29 @@ fn record(
             enable_ksm_stats: below_config.enable_ksm_stats,
             enable_resctrl_stats: below_config.enable_resctrl_stats,
             enable_tc_stats: below_config.enable_tc_stats,
+            enable_smaps_rollup_stats: below_config.enable_smaps_rollup_stats,
+            proc_collection_workers: below_config.proc_collection_workers,
+            proc_batched_reads: below_config.proc_batched_reads,
+            cgroup_limit_refresh_samples: below_config.cgroup_limit_refresh_samples,
             btrfs_samples: below_config.btrfs_samples,
             btrfs_min_pct: below_config.btrfs_min_pct,
             cgroup_re,
             gpu_stats_receiver,
             tc_stats_receiver,
+            omit_sections,
+            interval: Some(interval),
+            stack_sampling,
+            cmdline_redaction,
+            lite: None,
         },
     );
 
+    let mut clock_steps = model::ClockStepDetector::default();
+    // Set from a wall clock step until a sample is stored after it
+    let mut discontinuity = false;
+    // Set while samples are dropped for a wall clock stepped backwards
+    let mut behind_store = false;
+
     loop {
         if !disable_exitstats {
             // Anything that comes over the error channel is an error
@@ -1116,31 +2190,79 @@ fn record(
 
         let collect_instant = Instant::now();
 
+        // May have been changed through the control socket
+        collector.set_interval(interval);
         let collected_sample = collector.collect_sample();
         let post_collect_sys_time = SystemTime::now();
         let post_collect_instant = Instant::now();
 
+        if let Some(step) = clock_steps.check(post_collect_sys_time, post_collect_instant) {
+            warn!(logger, "Wall clock stepped {} since the last sample", step);
+            discontinuity = true;
+        }
+
         let collection_skew = post_collect_instant.duration_since(collect_instant);
+        record_stats.last_collection = collection_skew;
         if collection_skew >= skew_detection_threshold {
+            let breakdown = collected_sample
+                .as_ref()
+                .ok()
+                .and_then(|s| s.collection_timing.as_ref())
+                .map_or_else(String::new, |timing| format!(": {}", timing));
             warn!(
                 logger,
-                "data collection took {} ms (>= {} ms)",
+                "data collection took {} ms (>= {} ms){}",
                 collection_skew.as_millis(),
-                skew_detection_threshold.as_millis()
+                skew_detection_threshold.as_millis(),
+                breakdown
             );
 
             stats.report_collection_skew();
+            record_stats.slow_collections += 1;
         }
 
         match collected_sample {
-            Ok(s) => {
-                let frame = DataFrame { sample: s };
-                match store.put(post_collect_sys_time, &frame) {
-                    Ok(/* new shard */ true) => {
-                        cleanup_store(&store, &logger, store_size_limit, /* retention */ None)?
+            Ok(mut s) => {
+                record_stats.samples += 1;
+                s.discontinuity = discontinuity;
+                let frame = DataFrame {
+                    sample: s,
+                    aggregate: None,
+                };
+                // Samples can only be appended after the stored ones, so after
+                // the clock stepped backwards they are dropped until it passes
+                // the last stored one again.
+                let stored_ahead = store
+                    .as_ref()
+                    .map_or(false, |store| !store.is_after_last(post_collect_sys_time));
+                if stored_ahead && !behind_store {
+                    warn!(
+                        logger,
+                        "Wall clock is behind the last stored sample, dropping samples until it catches up"
+                    );
+                }
+                behind_store = stored_ahead;
+                if !stored_ahead {
+                    discontinuity = false;
+                }
+                if let Some(store) = store.as_mut().filter(|_| !stored_ahead) {
+                    match store.put(post_collect_sys_time, &frame) {
+                        Ok(/* new shard */ true) => {
+                            cleanup_store(
+                                store,
+                                &logger,
+                                store_size_limit,
+                                /* retention */ None,
+                            )?
+                        }
+                        Ok(/* new shard */ false) => {}
+                        Err(e) => error!(logger, "{:#}", e),
+                    }
+                }
+                if let Some(forwarder) = forwarder.as_mut() {
+                    if let Err(e) = forwarder.put(post_collect_sys_time, &frame) {
+                        error!(logger, "{:#}", e);
                     }
-                    Ok(/* new shard */ false) => {}
-                    Err(e) => error!(logger, "{:#}", e),
                 }
                 if below_config.enable_gpu_stats {
                     stats.report_nr_accelerators(&frame.sample);
@@ -1155,34 +2277,338 @@ fn record(
                 };
 
                 error!(logger, "{:#}", e);
+                record_stats.collection_errors += 1;
             }
         };
 
         // Only check against retention and not size limit. Size limit is only
         // checked on creation of successful write to a new shard.
-        cleanup_store(&store, &logger, /* store_size_limit */ None, retention)?;
+        if let Some(store) = store.as_ref() {
+            cleanup_store(store, &logger, /* store_size_limit */ None, retention)?;
 
-        stats.report_store_size(below_config.store_dir.as_path());
+            stats.report_store_size(below_config.store_dir.as_path());
+        }
 
-        let collect_duration = Instant::now().duration_since(collect_instant);
-        // Sleep for at least 1s to avoid sample collision
-        let sleep_duration = if interval > collect_duration {
-            std::cmp::max(Duration::from_secs(1), interval - collect_duration)
-        } else {
-            Duration::from_secs(1)
-        };
-        std::thread::sleep(sleep_duration);
+        let collect_end = Instant::now();
+        let collect_duration = collect_end.duration_since(collect_instant);
+        // Serve control requests until the next collection. The interval may
+        // change meanwhile, so the wake up time is recomputed every time.
+        loop {
+            // Sleep for at least 1s to avoid sample collision
+            let sleep_duration = if interval > collect_duration {
+                std::cmp::max(Duration::from_secs(1), interval - collect_duration)
+            } else {
+                Duration::from_secs(1)
+            };
+            let timeout =
+                match (collect_end + sleep_duration).checked_duration_since(Instant::now()) {
+                    Some(timeout) => timeout,
+                    None => break,
+                };
+            match control_calls
+                .as_ref()
+                .map(|calls| calls.recv_timeout(timeout))
+            {
+                Some(Ok((request, response_send))) => handle_control_request(
+                    &logger,
+                    below_config,
+                    request,
+                    response_send,
+                    store.as_mut(),
+                    &mut interval,
+                    &record_stats,
+                ),
+                Some(Err(RecvTimeoutError::Timeout)) => break,
+                None | Some(Err(RecvTimeoutError::Disconnected)) => {
+                    std::thread::sleep(timeout);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Handle a request from the control socket of `below record`. Snapshots are
+/// created on their own thread so that recording carries on meanwhile.
+fn handle_control_request(
+    logger: &slog::Logger,
+    below_config: &BelowConfig,
+    request: control::Request,
+    response_send: Sender<control::Response>,
+    store: Option<&mut store::StoreWriter>,
+    interval: &mut Duration,
+    record_stats: &RecordStats,
+) {
+    let res = match request {
+        control::Request::Flush => match store {
+            Some(store) => store.sync().map(|_| serde_json::json!({})),
+            None => Err(anyhow!("No local store to flush")),
+        },
+        control::Request::Rotate => match store {
+            Some(store) => store.reopen().map(|_| serde_json::json!({})),
+            None => Err(anyhow!("No local store to rotate")),
+        },
+        control::Request::SetInterval { interval_s } => {
+            if interval_s == 0 {
+                Err(anyhow!("Interval must be at least 1 second"))
+            } else {
+                info!(
+                    logger,
+                    "Changing interval from {}s to {}s",
+                    interval.as_secs(),
+                    interval_s
+                );
+                *interval = Duration::from_secs(interval_s);
+                Ok(serde_json::json!({ "interval_s": interval_s }))
+            }
+        }
+        control::Request::Stats => Ok(serde_json::json!({
+            "pid": std::process::id(),
+            "uptime_s": record_stats.started.elapsed().as_secs(),
+            "interval_s": interval.as_secs(),
+            "samples": record_stats.samples,
+            "collection_errors": record_stats.collection_errors,
+            "slow_collections": record_stats.slow_collections,
+            "last_collection_ms": record_stats.last_collection.as_millis() as u64,
+            "bpf": *record_stats.bpf_stats.lock().unwrap(),
+            "store_dir": below_config.store_dir,
+            "store_size_bytes": store
+                .map(|_| common::fileutil::get_dir_size(&below_config.store_dir)),
+        })),
+        control::Request::Snapshot {
+            begin,
+            end,
+            duration,
+            output,
+        } => {
+            let logger = logger.clone();
+            thread::Builder::new()
+                .name("control_snapshot".to_owned())
+                .spawn(move || {
+                    let below_config = config::BELOW_CONFIG
+                        .get()
+                        .expect("BELOW_CONFIG empty after set");
+                    let res = create_snapshot(
+                        logger,
+                        below_config,
+                        begin,
+                        end,
+                        duration,
+                        /* from_store_dir */ None,
+                        Some(output),
+                        /* host */ None,
+                        /* port */ None,
+                        ProgressBar::no_length(),
+                    )
+                    .map(|tarball| serde_json::json!({ "output": tarball }));
+                    // The client may have gone away meanwhile
+                    let _ = response_send.send(res.into());
+                })
+                .expect("Failed to spawn thread");
+            return;
+        }
+    };
+    // The client may have gone away meanwhile
+    let _ = response_send.send(res.into());
+}
+
+/// Accept connections from forwarding `below record` instances and write
+/// their samples to a store per host under `store_dir`. Each connection is
+/// served by its own thread, of which there are at most `max_connections`.
+fn collect(
+    logger: slog::Logger,
+    errs: Receiver<Error>,
+    listen: &str,
+    token_file: &Path,
+    max_connections: usize,
+    store_dir: PathBuf,
+    retention: Option<Duration>,
+    store_size_limit: Option<u64>,
+    compress_opts: &CompressOpts,
+) -> Result<()> {
+    let compression_mode = compress_opts.to_compression_mode()?;
+    let format = compress_opts.to_format();
+    let process_delta = compress_opts.process_delta;
+    let token = Arc::new(store::forward::read_token(token_file)?);
+    fs::create_dir_all(&store_dir)
+        .with_context(|| format!("Failed to create {}", store_dir.display()))?;
+    let listener = std::net::TcpListener::bind(listen)
+        .with_context(|| format!("Failed to listen on {}", listen))?;
+    info!(logger, "Collecting samples on {}", listen);
+
+    // Hosts with an open connection. A second connection from the same host
+    // is rejected so that two writers never share a store.
+    let active_hosts = Arc::new(Mutex::new(std::collections::HashSet::new()));
+    let connections = Arc::new(AtomicUsize::new(0));
+    thread::Builder::new()
+        .name("collect_listener".to_owned())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        error!(logger, "Failed to accept connection: {:#}", e);
+                        continue;
+                    }
+                };
+                let peer = stream
+                    .peer_addr()
+                    .map_or_else(|_| "unknown".to_owned(), |addr| addr.to_string());
+                if connections.fetch_add(1, Ordering::SeqCst) >= max_connections {
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                    warn!(
+                        logger,
+                        "Rejected connection from {}: {} connections are open already",
+                        peer,
+                        max_connections
+                    );
+                    continue;
+                }
+                let logger = logger.clone();
+                let store_dir = store_dir.clone();
+                let active_hosts = active_hosts.clone();
+                let token = token.clone();
+                let connections = connections.clone();
+                let spawned = thread::Builder::new()
+                    .name("collect_conn".to_owned())
+                    .spawn(move || {
+                        collect_connection(
+                            &logger,
+                            stream,
+                            &peer,
+                            &token,
+                            &store_dir,
+                            &active_hosts,
+                            compression_mode,
+                            format,
+                            process_delta,
+                            retention,
+                            store_size_limit,
+                        );
+                        connections.fetch_sub(1, Ordering::SeqCst);
+                    });
+                if let Err(e) = spawned {
+                    error!(logger, "Failed to spawn connection thread: {:#}", e);
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                }
+            }
+        })
+        .expect("Failed to spawn thread");
+
+    loop {
+        match errs.recv_timeout(Duration::from_secs(1)) {
+            Ok(e) => bail!(e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => bail!("error channel disconnected"),
+        }
+    }
+}
+
+/// Serve a connection of `below collect` until the peer goes away
+fn collect_connection(
+    logger: &slog::Logger,
+    stream: std::net::TcpStream,
+    peer: &str,
+    token: &[u8],
+    store_dir: &Path,
+    active_hosts: &Mutex<std::collections::HashSet<String>>,
+    compression_mode: store::CompressionMode,
+    format: store::Format,
+    process_delta: bool,
+    retention: Option<Duration>,
+    store_size_limit: Option<u64>,
+) {
+    // Peers that stall before authenticating are dropped early
+    if let Err(e) = stream.set_read_timeout(Some(store::forward::HANDSHAKE_TIMEOUT)) {
+        error!(logger, "Failed to set read timeout for {}: {:#}", peer, e);
+        return;
+    }
+    let mut reader = match store::forward::ForwardReader::new(&stream, token) {
+        Ok(reader) => reader,
+        Err(e) => {
+            warn!(logger, "Rejected connection from {}: {:#}", peer, e);
+            return;
+        }
+    };
+    // A host that went away without closing its connection would otherwise
+    // keep its hostname and have reconnects rejected
+    if let Err(e) = stream.set_read_timeout(Some(store::forward::READ_TIMEOUT)) {
+        error!(logger, "Failed to set read timeout for {}: {:#}", peer, e);
+        return;
+    }
+    let hostname = reader.hostname().to_owned();
+    if !active_hosts.lock().unwrap().insert(hostname.clone()) {
+        warn!(
+            logger,
+            "Rejected connection from {}: {} is already connected", peer, hostname
+        );
+        return;
+    }
+    info!(logger, "Collecting samples of {} from {}", hostname, peer);
+    let res = (|| -> Result<()> {
+        let mut store = store::StoreWriter::new(
+            logger.clone(),
+            store_dir.join(&hostname),
+            compression_mode,
+            format,
+        )?;
+        store.set_process_delta(process_delta)?;
+        // Whether samples are dropped for not being after the last stored
+        // one, to only log when it starts
+        let mut behind_store = false;
+        loop {
+            let (timestamp, frame) = match reader.next_frame() {
+                Ok(Some(sample)) => sample,
+                Ok(None) => {
+                    info!(logger, "{} disconnected", hostname);
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!(logger, "Dropped connection of {}: {:#}", hostname, e);
+                    return Ok(());
+                }
+            };
+            // E.g. the clock of the host stepped backwards
+            if !store.is_after_last(timestamp) {
+                if !behind_store {
+                    warn!(
+                        logger,
+                        "Dropping samples of {} until they are after its last stored sample",
+                        hostname
+                    );
+                }
+                behind_store = true;
+                continue;
+            }
+            behind_store = false;
+            if store.put(timestamp, &frame)? {
+                cleanup_store(&store, logger, store_size_limit, None)?;
+            }
+            cleanup_store(&store, logger, None, retention)?;
+        }
+    })();
+    active_hosts.lock().unwrap().remove(&hostname);
+    // Only the connection of the host is dropped, so that one host cannot
+    // stop collection for all others
+    if let Err(e) = res {
+        error!(
+            logger,
+            "Dropped connection of {}, failed to write its store: {:#}", hostname, e
+        );
     }
 }
 
-fn live_local(
+/// Start the collectors of live mode, leaving out processes and the optional
+/// collectors with `lite`. Returns the receiver of exitstat errors if it was
+/// started.
+fn start_live_collector(
     init: init::InitToken,
-    logger: slog::Logger,
-    errs: Receiver<Error>,
-    interval: Duration,
+    logger: &slog::Logger,
     debug: bool,
     below_config: &BelowConfig,
-) -> Result<()> {
+    interval: Duration,
+    lite: Option<model::LiteOptions>,
+) -> Result<(model::Collector, Option<Receiver<Error>>)> {
     match bump_memlock_rlimit() {
         Err(e) => {
             warn!(
@@ -1196,10 +2622,65 @@ fn live_local(
         _ => {}
     };
 
-    let (exit_buffer, bpf_errs) = start_exitstat(logger.clone(), debug);
-    let mut bpf_err_warned = false;
+    // Lite mode leaves out processes and the optional collectors
+    let full = lite.is_none();
+    let bpf_stats = Arc::new(Mutex::new(model::BpfProgramStats::new()));
+    let (exit_buffer, bpf_errs) = if full {
+        start_exitstat(logger.clone(), debug, bpf_stats.clone())
+    } else {
+        (Arc::new(Mutex::new(procfs::PidMap::new())), None)
+    };
+    let file_io_data = if full && below_config.enable_file_io_stats {
+        Some(start_file_io(
+            logger.clone(),
+            debug,
+            interval,
+            bpf_stats.clone(),
+        ))
+    } else {
+        None
+    };
+    let process_net_data = if full && below_config.enable_process_net_stats {
+        Some(start_process_net(
+            logger.clone(),
+            debug,
+            interval,
+            bpf_stats.clone(),
+        ))
+    } else {
+        None
+    };
+    let perf_counter_data = if full && below_config.enable_perf_counter_stats {
+        Some(start_perf_counters(
+            logger.clone(),
+            below_config.cgroup_root.clone(),
+            interval,
+        ))
+    } else {
+        None
+    };
+    let psi_trigger_data = if !full || below_config.psi_trigger_cgroups.is_empty() {
+        None
+    } else {
+        Some(start_psi_triggers(logger.clone(), below_config))
+    };
+    let oom_kill_data = if full && below_config.enable_oom_killer_events {
+        Some(start_oom_kill_events(logger.clone()))
+    } else {
+        None
+    };
+    let journal_error_data = if full && below_config.enable_journal_errors {
+        Some(start_journal_errors(logger.clone(), below_config))
+    } else {
+        None
+    };
+    let stack_sampling = if full && below_config.enable_stack_sampling {
+        Some(start_stack_sampling(logger.clone(), below_config))
+    } else {
+        None
+    };
 
-    let gpu_stats_receiver = if below_config.enable_gpu_stats {
+    let gpu_stats_receiver = if full && below_config.enable_gpu_stats {
         Some(start_gpu_stats_thread_and_get_stats_receiver(
             init,
             logger.clone(),
@@ -1209,21 +2690,60 @@ fn live_local(
         None
     };
 
-    let mut collector = model::Collector::new(
+    let collector = model::Collector::new(
         logger.clone(),
         model::CollectorOptions {
             cgroup_root: below_config.cgroup_root.clone(),
+            extra_cgroup_roots: below_config.extra_cgroup_roots.clone(),
             exit_data: exit_buffer,
-            enable_btrfs_stats: below_config.enable_btrfs_stats,
-            enable_ethtool_stats: below_config.enable_ethtool_stats,
-            enable_ksm_stats: below_config.enable_ksm_stats,
-            enable_resctrl_stats: below_config.enable_resctrl_stats,
+            file_io_data,
+            psi_trigger_data,
+            oom_kill_data,
+            journal_error_data,
+            process_net_data,
+            perf_counter_data,
+            bpf_stats: Some(bpf_stats),
+            enable_btrfs_stats: full && below_config.enable_btrfs_stats,
+            enable_ethtool_stats: full && below_config.enable_ethtool_stats,
+            enable_ksm_stats: full && below_config.enable_ksm_stats,
+            enable_resctrl_stats: full && below_config.enable_resctrl_stats,
+            enable_smaps_rollup_stats: full && below_config.enable_smaps_rollup_stats,
+            proc_collection_workers: below_config.proc_collection_workers,
+            proc_batched_reads: below_config.proc_batched_reads,
+            cgroup_limit_refresh_samples: below_config.cgroup_limit_refresh_samples,
             btrfs_samples: below_config.btrfs_samples,
             btrfs_min_pct: below_config.btrfs_min_pct,
             gpu_stats_receiver,
+            interval: Some(interval),
+            stack_sampling,
+            lite,
             ..Default::default()
         },
     );
+    Ok((collector, bpf_errs))
+}
+
+#[cfg(feature = "view")]
+fn live_local(
+    init: init::InitToken,
+    logger: slog::Logger,
+    errs: Receiver<Error>,
+    interval_s: u64,
+    debug: bool,
+    below_config: &BelowConfig,
+    viewrc: view::viewrc::ViewRc,
+    viewrc_error: Option<String>,
+    lite: Option<model::LiteOptions>,
+    record_session: Option<PathBuf>,
+) -> Result<()> {
+    let interval = Duration::from_secs(interval_s);
+    let lite_label = lite
+        .as_ref()
+        .map(|lite| format!("top {} cgroups by {}", lite.top_cgroups, lite.metric));
+    let (mut collector, bpf_errs) =
+        start_live_collector(init, &logger, debug, below_config, interval, lite)?;
+    // Without exitstat there are no errors to check for
+    let mut bpf_err_warned = bpf_errs.is_none();
     logutil::set_current_log_target(logutil::TargetLog::File);
     // Prepare advance obj for pause mode
     let mut adv = new_advance_local(
@@ -1232,10 +2752,28 @@ fn live_local(
         SystemTime::now(),
     );
     adv.initialize();
-    let mut view = view::View::new_with_advance(
+    let mut view = view::View::new_with_viewrc(
         collector.collect_and_update_model()?,
         view::ViewMode::Live(Rc::new(RefCell::new(adv))),
+        viewrc,
+        viewrc_error,
     );
+    // Switching to a view profile can change the interval
+    let shared_interval_s = Arc::new(AtomicU64::new(interval_s));
+    view.set_refresh_interval(shared_interval_s.clone(), false);
+    // Samples of the recorder writing to the local store
+    view.set_snapshot(view_snapshot(
+        logger.clone(),
+        Some(below_config.store_dir.clone()),
+        /* host */ None,
+        /* port */ None,
+    ));
+    if let Some(lite_label) = lite_label {
+        view.set_lite(lite_label);
+    }
+    if let Some(path) = record_session {
+        view.record_session(&path)?;
+    }
 
     let sink = view.cb_sink().clone();
 
@@ -1253,6 +2791,7 @@ fn live_local(
                 }
 
                 // Rely on timeout to guarantee interval between samples
+                let interval = Duration::from_secs(shared_interval_s.load(Ordering::Relaxed));
                 match errs.recv_timeout(interval) {
                     Ok(e) => {
                         error!(logger, "{:#}", e);
@@ -1269,6 +2808,7 @@ fn live_local(
                     Err(RecvTimeoutError::Timeout) => {}
                 };
 
+                collector.set_interval(interval);
                 match collector.collect_and_update_model() {
                     Ok(model) => {
                         // Error only happens if the other side disconnected - just terminate the thread
@@ -1295,26 +2835,142 @@ fn live_local(
     view.run()
 }
 
+/// Output formats of `below live --headless`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HeadlessFormat {
+    /// A JSON object per line and sample
+    Jsonl,
+}
+
+impl std::str::FromStr for HeadlessFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "jsonl" => Ok(Self::Jsonl),
+            _ => Err(anyhow!("Unknown format {:?}, expected jsonl", s)),
+        }
+    }
+}
+
+/// Print each sample collected to stdout in `format`, or only `fields` of it
+/// if any, until stdout is closed
+fn live_headless(
+    init: init::InitToken,
+    logger: slog::Logger,
+    errs: Receiver<Error>,
+    interval_s: u64,
+    debug: bool,
+    below_config: &BelowConfig,
+    lite: Option<model::LiteOptions>,
+    format: HeadlessFormat,
+    fields: Vec<String>,
+) -> Result<()> {
+    let fields = fields
+        .iter()
+        .map(|field| {
+            field
+                .parse::<model::ModelFieldId>()
+                .with_context(|| format!("Invalid field {:?}", field))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let interval = Duration::from_secs(interval_s);
+    let (mut collector, bpf_errs) =
+        start_live_collector(init, &logger, debug, below_config, interval, lite)?;
+    // Without exitstat there are no errors to check for
+    let mut bpf_err_warned = bpf_errs.is_none();
+    let mut stdout = io::stdout().lock();
+    loop {
+        if !bpf_err_warned {
+            bpf_err_warned = check_for_exitstat_errors(
+                &logger,
+                bpf_errs
+                    .as_ref()
+                    .expect("Failed to unwrap bpf_errs receiver"),
+            );
+        }
+
+        match collector.collect_and_update_model() {
+            Ok(model) => {
+                let line = match format {
+                    HeadlessFormat::Jsonl => headless_json(&model, &fields)?,
+                };
+                match writeln!(stdout, "{}", line).and_then(|_| stdout.flush()) {
+                    // Whatever read the samples went away
+                    Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+                    res => res?,
+                }
+            }
+            Err(e) => {
+                error!(logger, "{:#}", e);
+            }
+        }
+
+        // Rely on timeout to guarantee interval between samples
+        match errs.recv_timeout(interval) {
+            Ok(e) => bail!(e),
+            Err(RecvTimeoutError::Disconnected) => bail!("error channel disconnected"),
+            Err(RecvTimeoutError::Timeout) => {}
+        };
+    }
+}
+
+/// `model` as JSON, or only its `fields` along with the timestamp if any
+fn headless_json(model: &model::Model, fields: &[model::ModelFieldId]) -> Result<String> {
+    if fields.is_empty() {
+        return Ok(serde_json::to_string(model)?);
+    }
+    let mut object = serde_json::Map::new();
+    let timestamp = model
+        .timestamp
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs());
+    object.insert("timestamp".to_owned(), timestamp.into());
+    for field in fields {
+        let value = model::Queriable::query(model, field)
+            .map_or(serde_json::Value::Null, serde_json::Value::from);
+        object.insert(field.to_string(), value);
+    }
+    Ok(serde_json::Value::Object(object).to_string())
+}
+
+#[cfg(feature = "view")]
 fn live_remote(
     logger: slog::Logger,
     errs: Receiver<Error>,
     interval: Duration,
     host: String,
     port: Option<u16>,
+    viewrc: view::viewrc::ViewRc,
+    viewrc_error: Option<String>,
+    record_session: Option<PathBuf>,
 ) -> Result<()> {
     let timestamp = SystemTime::now()
         .checked_sub(Duration::from_secs(LIVE_REMOTE_MAX_LATENCY_SEC))
         .expect("Fail to construct timestamp with latency allowance in live remote.");
-    let mut advance = new_advance_remote(logger.clone(), host, port, timestamp)?;
+    let mut advance = new_advance_remote(logger.clone(), host.clone(), port, timestamp)?;
 
     advance.initialize();
     let mut view = match advance.get_latest_sample() {
-        Some(model) => view::View::new_with_advance(
+        Some(model) => view::View::new_with_viewrc(
             model,
             view::ViewMode::Live(Rc::new(RefCell::new(advance))),
+            viewrc,
+            viewrc_error,
         ),
         None => return Err(anyhow!("No data could be found!")),
     };
+    // The refresh commands can change the interval
+    let shared_interval_s = Arc::new(AtomicU64::new(interval.as_secs()));
+    view.set_refresh_interval(shared_interval_s.clone(), true);
+    view.set_snapshot(view_snapshot(
+        logger.clone(),
+        /* store_dir */ None,
+        Some(host),
+        port,
+    ));
+    if let Some(path) = record_session {
+        view.record_session(&path)?;
+    }
 
     let sink = view.cb_sink().clone();
 
@@ -1323,6 +2979,7 @@ fn live_remote(
         .spawn(move || {
             loop {
                 // Rely on timeout to guarantee interval between samples
+                let interval = Duration::from_secs(shared_interval_s.load(Ordering::Relaxed));
                 match errs.recv_timeout(interval) {
                     Ok(e) => {
                         error!(logger, "{:#}", e);
@@ -1360,20 +3017,106 @@ fn live_remote(
     view.run()
 }
 
+/// Load the view configuration from belowrc with `profile` active
+#[cfg(feature = "view")]
+fn load_viewrc(profile: Option<&str>) -> Result<(view::viewrc::ViewRc, Option<String>)> {
+    let (mut viewrc, viewrc_error) = view::viewrc::ViewRc::new();
+    if profile.is_some() {
+        if let Some(e) = viewrc_error {
+            bail!("{}", e);
+        }
+        viewrc.set_active_profile(profile)?;
+    }
+    Ok((viewrc, viewrc_error))
+}
+
+#[cfg(feature = "view")]
 fn live(
     init: init::InitToken,
     logger: slog::Logger,
     errs: Receiver<Error>,
-    interval: Duration,
+    interval_s: Option<u64>,
     debug: bool,
     below_config: &BelowConfig,
     host: Option<String>,
     port: Option<u16>,
+    profile: Option<String>,
+    lite: Option<model::LiteOptions>,
+    record_session: Option<PathBuf>,
+    headless: Option<(HeadlessFormat, Vec<String>)>,
 ) -> Result<()> {
-    if let Some(host) = host {
-        live_remote(logger, errs, interval, host, port)
+    let (viewrc, viewrc_error) = load_viewrc(profile.as_deref())?;
+    let interval_s = interval_s
+        .or(viewrc.settings().refresh_interval_s)
+        .unwrap_or(DEFAULT_LIVE_INTERVAL_S);
+    if let Some((format, fields)) = headless {
+        live_headless(
+            init,
+            logger,
+            errs,
+            interval_s,
+            debug,
+            below_config,
+            lite,
+            format,
+            fields,
+        )
+    } else if let Some(host) = host {
+        live_remote(
+            logger,
+            errs,
+            Duration::from_secs(interval_s),
+            host,
+            port,
+            viewrc,
+            viewrc_error,
+            record_session,
+        )
     } else {
-        live_local(init, logger, errs, interval, debug, below_config)
+        live_local(
+            init,
+            logger,
+            errs,
+            interval_s,
+            debug,
+            below_config,
+            viewrc,
+            viewrc_error,
+            lite,
+            record_session,
+        )
+    }
+}
+
+/// Without the view, live mode can only print samples with --headless
+#[cfg(not(feature = "view"))]
+fn live(
+    init: init::InitToken,
+    logger: slog::Logger,
+    errs: Receiver<Error>,
+    interval_s: Option<u64>,
+    debug: bool,
+    below_config: &BelowConfig,
+    _host: Option<String>,
+    _port: Option<u16>,
+    _profile: Option<String>,
+    lite: Option<model::LiteOptions>,
+    _record_session: Option<PathBuf>,
+    headless: Option<(HeadlessFormat, Vec<String>)>,
+) -> Result<()> {
+    match headless {
+        Some((format, fields)) => live_headless(
+            init,
+            logger,
+            errs,
+            interval_s.unwrap_or(DEFAULT_LIVE_INTERVAL_S),
+            debug,
+            below_config,
+            lite,
+            format,
+            fields,
+        ),
+        None => bail!("The interactive view is not part of this build, use --headless"),
     }
 }
 
@@ -1409,6 +3152,172 @@ fn dump_store(
     Ok(())
 }
 
+/// Send a request to the control socket of a running `below record` and
+/// print its result
+fn ctl(socket: PathBuf, cmd: &CtlCommand) -> Result<()> {
+    let request = match cmd {
+        CtlCommand::Flush => control::Request::Flush,
+        CtlCommand::Rotate => control::Request::Rotate,
+        CtlCommand::SetInterval { interval_s } => control::Request::SetInterval {
+            interval_s: *interval_s,
+        },
+        CtlCommand::Stats => control::Request::Stats,
+        CtlCommand::Snapshot {
+            begin,
+            end,
+            duration,
+            output,
+        } => control::Request::Snapshot {
+            begin: begin.clone(),
+            end: end.clone(),
+            duration: duration.clone(),
+            // The recorder would resolve it against its own working directory
+            output: std::env::current_dir()
+                .context("Failed to get current directory")?
+                .join(output),
+        },
+    };
+    match control::call(&socket, &request)? {
+        control::Response::Result(value) => {
+            println!("{}", serde_json::to_string_pretty(&value)?);
+            Ok(())
+        }
+        control::Response::Error(e) => bail!(e),
+    }
+}
+
+fn capabilities(logger: slog::Logger, below_config: &BelowConfig) -> Result<()> {
+    let sample = model::Collector::new(
+        logger,
+        model::CollectorOptions {
+            cgroup_root: below_config.cgroup_root.clone(),
+            ..Default::default()
+        },
+    )
+    .collect_sample()?;
+    println!("{}", serde_json::to_string_pretty(&sample.capabilities())?);
+    Ok(())
+}
+
+fn verify_store(below_config: &BelowConfig, store_dir: Option<PathBuf>) -> Result<()> {
+    let store_dir = store_dir.unwrap_or_else(|| below_config.store_dir.clone());
+    let statuses = store::verify_store(&store_dir)?;
+    let mut failed = 0;
+    for (shard, status) in &statuses {
+        println!("{}: {}", timestamp_to_datetime(&(*shard as i64)), status);
+        if matches!(
+            status,
+            store::ShardStatus::Truncated(_)
+                | store::ShardStatus::Missing(_)
+                | store::ShardStatus::Corrupt(_)
+        ) {
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        bail!(
+            "{} of {} shards failed verification",
+            failed,
+            statuses.len()
+        );
+    }
+    Ok(())
+}
+
+fn analyze(
+    logger: slog::Logger,
+    below_config: &BelowConfig,
+    begin: String,
+    end: Option<String>,
+    duration: Option<String>,
+    top: usize,
+    aggregate: Vec<String>,
+    host: Option<String>,
+    port: Option<u16>,
+) -> Result<()> {
+    let field_ids = aggregate
+        .iter()
+        .map(|field| {
+            field
+                .parse::<model::ModelFieldId>()
+                .with_context(|| format!("Invalid field {:?}", field))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let (time_begin, time_end) = cliutil::system_time_range_from_date_and_adjuster(
+        begin.as_str(),
+        end.as_deref(),
+        duration.as_deref(),
+        /* days_adjuster */ None,
+    )?;
+
+    let mut advance = match host {
+        None => new_advance_local(logger.clone(), below_config.store_dir.clone(), time_begin),
+        Some(host) => new_advance_remote(logger.clone(), host, port, time_begin)?,
+    };
+    advance.set_model_parts(model::ModelParts {
+        system: true,
+        cgroup: true,
+        ..model::ModelParts::NONE
+    });
+    advance.initialize();
+
+    let mut model = match advance.jump_sample_to(time_begin) {
+        Some(m) => m,
+        None => bail!(
+            "No initial sample could be found!\n\
+            You may have provided a time in the future or no data was recorded during the provided time. \
+            Please check your input and timezone.\n\
+            If you are using remote, please make sure the below service on target host is running."
+        ),
+    };
+    cliutil::check_initial_sample_time_in_time_range(model.timestamp, time_begin, time_end)?;
+
+    let mut analyzer = analyze::Analyzer::new();
+    let mut last = None;
+    loop {
+        analyzer.observe(&model, last.as_ref());
+        if advance.get_next_ts() > time_end {
+            break;
+        }
+        last = Some(model);
+        model = match advance.advance(store::Direction::Forward) {
+            Some(m) => m,
+            None => break,
+        };
+    }
+
+    let mut out = io::stdout().lock();
+    analyzer.report(&mut out, top)?;
+    if !field_ids.is_empty() {
+        // Another pass over the range, keeping no more than a model at a time
+        let aggregates = advance.aggregate(time_begin, time_end, &field_ids);
+        analyze::write_aggregates(&mut out, &field_ids, &aggregates)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "fuse")]
+fn fuse_mount(
+    logger: slog::Logger,
+    below_config: &BelowConfig,
+    mountpoint: PathBuf,
+    begin: String,
+    end: Option<String>,
+    duration: Option<String>,
+    store_dir: Option<PathBuf>,
+) -> Result<()> {
+    let (time_begin, time_end) = cliutil::system_time_range_from_date_and_adjuster(
+        begin.as_str(),
+        end.as_deref(),
+        duration.as_deref(),
+        /* days_adjuster */ None,
+    )?;
+    let store_dir = store_dir.unwrap_or_else(|| below_config.store_dir.clone());
+    let fs = fuse::StoreFs::new(logger, store_dir, time_begin, time_end)?;
+    fuse::mount(fs, &mountpoint)
+        .with_context(|| format!("Failed to mount store at {}", mountpoint.display()))
+}
+
 fn generate_completions(shell: Shell, output: Option<PathBuf>) -> Result<()> {
     let mut file: Box<dyn io::Write> = match output {
         Some(path) => Box::new(fs::File::create(path)?),
@@ -1431,6 +3340,7 @@ fn convert_store(
     host: Option<String>,
     port: Option<u16>,
     compress_opts: &CompressOpts,
+    pb: ProgressBar,
 ) -> Result<()> {
     let (time_begin, time_end) = cliutil::system_time_range_from_date_and_adjuster(
         begin.as_str(),
@@ -1442,7 +3352,7 @@ fn convert_store(
         common::util::get_unix_timestamp(time_begin),
         common::util::get_unix_timestamp(time_end),
     );
-    let pb = ProgressBar::new(timestamp_end - timestamp_begin);
+    pb.set_length(timestamp_end - timestamp_begin);
 
     let mut store: Box<dyn Store<SampleType = DataFrame>> = match (from_store_dir, host) {
         (Some(_from_store_dir), Some(_host)) => {
@@ -1472,8 +3382,9 @@ fn convert_store(
         logger.clone(),
         &to_store_dir,
         compress_opts.to_compression_mode()?,
-        store::Format::Cbor,
+        compress_opts.to_format(),
     )?;
+    dest_store.set_process_delta(compress_opts.process_delta)?;
 
     pb.set_message(format!("Writing to local store at {:?}", to_store_dir));
 
@@ -1512,6 +3423,36 @@ fn snapshot(
     host: Option<String>,
     port: Option<u16>,
 ) -> Result<()> {
+    let tarball = create_snapshot(
+        logger,
+        below_config,
+        begin,
+        end,
+        duration,
+        /* from_store_dir */ None,
+        output,
+        host,
+        port,
+        ProgressBar::no_length(),
+    )?;
+    println!("Snapshot has been created at {}", tarball.display());
+    Ok(())
+}
+
+/// Create a snapshot file and return its path. Samples are read from
+/// `from_store_dir` or `host`, or the configured store if neither is given.
+fn create_snapshot(
+    logger: slog::Logger,
+    below_config: &BelowConfig,
+    begin: String,
+    end: Option<String>,
+    duration: Option<String>,
+    from_store_dir: Option<PathBuf>,
+    output: Option<PathBuf>,
+    host: Option<String>,
+    port: Option<u16>,
+    pb: ProgressBar,
+) -> Result<PathBuf> {
     let (time_begin, time_end) = cliutil::system_time_range_from_date_and_adjuster(
         begin.as_str(),
         end.as_deref(),
@@ -1535,7 +3476,9 @@ fn snapshot(
     // Build compression options to ensure snapshot is compressed before tarball
     let compress_opts = CompressOpts {
         compress: true,
-        dict_compress_chunk_size: Some(16),
+        dict_compress_chunk_size: Some(DictChunkSize::Fixed(16)),
+        indexed_format: false,
+        process_delta: false,
     };
     convert_store(
         logger,
@@ -1543,21 +3486,56 @@ fn snapshot(
         begin,
         end,
         duration,
-        None,
+        from_store_dir,
         snapshot_store_path.clone(),
         host,
         port,
         &compress_opts,
+        pb,
     )
     .context("Failed to convert store for snapshot")?;
 
     // The temp dir path will be something like "/tmp/snapshot_<timestamp_begin>_<timestamp_end>.XXXX".
     // We will use the dir name as name of the tarball.
+    write_snapshot_tarball(&snapshot_store_path, output)
+}
+
+/// Snapshot creation for the snapshot command of the view, reading samples
+/// from `store_dir` or `host` like `create_snapshot`. Progress is not
+/// shown, as it would draw over the view.
+#[cfg(feature = "view")]
+fn view_snapshot(
+    logger: slog::Logger,
+    store_dir: Option<PathBuf>,
+    host: Option<String>,
+    port: Option<u16>,
+) -> view::SnapshotFn {
+    Arc::new(move |begin: SystemTime, end: SystemTime, output: PathBuf| {
+        let below_config = config::BELOW_CONFIG
+            .get()
+            .expect("BELOW_CONFIG empty after set");
+        create_snapshot(
+            logger.clone(),
+            below_config,
+            common::util::get_unix_timestamp(begin).to_string(),
+            Some(common::util::get_unix_timestamp(end).to_string()),
+            /* duration */ None,
+            store_dir.clone(),
+            Some(output),
+            host.clone(),
+            port,
+            ProgressBar::hidden(),
+        )
+    })
+}
+
+/// Pack the store at `store_path` into a snapshot file at `output`, or named
+/// after the store directory if unspecified, and return its path
+fn write_snapshot_tarball(store_path: &Path, output: Option<PathBuf>) -> Result<PathBuf> {
     let tarball = if let Some(output) = output {
         output
     } else {
-        snapshot_store_path
-            .as_path()
+        store_path
             .file_name()
             .with_context(|| "path has no filename")?
             .into()
@@ -1566,12 +3544,118 @@ fn snapshot(
         .with_context(|| format!("Failed to create snapshot file {}", tarball.display()))?;
     // Create a new tarball with the snapshot dir name
     let mut tar = TarBuilder::new(file);
-    tar.append_dir_all("store", snapshot_store_path.as_path())
+    tar.append_dir_all("store", store_path)
         .context("Failed to add snapshot store to tar builder")?;
     tar.finish()
         .context("Failed to build compressed snapshot file.")?;
+    Ok(tarball)
+}
 
-    println!("Snapshot has been created at {}", tarball.display());
+/// Run `command` in a cgroup of its own, sampling it every `interval` until
+/// it exits. Prints a summary of the run and writes its samples to a
+/// snapshot file.
+fn profile(
+    logger: slog::Logger,
+    below_config: &BelowConfig,
+    interval: Duration,
+    output: Option<PathBuf>,
+    command: Vec<String>,
+) -> Result<()> {
+    // Sample at most once a second, as the store keys samples by second
+    let interval = std::cmp::max(interval, Duration::from_secs(1));
+    let cgroup = profile::ProfileCgroup::create(logger.clone(), &below_config.cgroup_root)?;
+
+    // Format: profile_<timestamp>.XXXX, also used as the snapshot name
+    let store_dir = TempDir::with_prefix(format!(
+        "profile_{:011}.",
+        common::util::get_unix_timestamp(SystemTime::now())
+    ))
+    .context("Failed to create temporary folder for profile")?;
+    let mut store = store::StoreWriter::new(
+        logger.clone(),
+        store_dir.path(),
+        CompressionMode::Zstd,
+        store::Format::Cbor,
+    )?;
+    // The cgroup of the command is the root of the recorded hierarchy, so
+    // the snapshot does not carry every other cgroup on the host
+    let mut collector = model::Collector::new(
+        logger.clone(),
+        model::CollectorOptions {
+            cgroup_root: cgroup.path().to_owned(),
+            interval: Some(interval),
+            ..Default::default()
+        },
+    );
+    let mut summary = profile::ProfileSummary::new();
+
+    let started = Instant::now();
+    let mut child = cgroup.spawn(&command)?;
+    // Ctrl-C reaches the command as well, and the stop signal handler of
+    // `run` keeps below alive, so an interrupted run is still reported.
+    let status = loop {
+        // Samples are stored by second, so the one taken right after the
+        // command exited waits for the second after the last stored sample
+        let now = SystemTime::now();
+        if !store.is_after_last(now) {
+            let subsec = now
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .subsec_nanos();
+            std::thread::sleep(Duration::from_nanos(1_000_000_000 - subsec as u64));
+        }
+        // Stamped with the time collection started, which samples are
+        // spaced from
+        let timestamp = SystemTime::now();
+        let collect_instant = Instant::now();
+        // Check before collecting so that the last sample is taken after
+        // the command exited and holds its totals
+        let status = child.try_wait().context("Failed to wait for command")?;
+        match collector.collect_sample() {
+            Ok(sample) => {
+                summary.observe(&sample.cgroup);
+                if store.is_after_last(timestamp) {
+                    store.put(
+                        timestamp,
+                        &DataFrame {
+                            sample,
+                            aggregate: None,
+                        },
+                    )?;
+                } else {
+                    warn!(logger, "Dropped a sample as the clock went backwards");
+                }
+            }
+            Err(e) => error!(logger, "{:#}", e),
+        }
+        if let Some(status) = status {
+            break status;
+        }
+        // Wake up early when the command exits, but no sooner than a
+        // second after the last sample
+        let next = collect_instant + interval;
+        let min_next = collect_instant + Duration::from_secs(1);
+        loop {
+            let now = Instant::now();
+            if now >= next {
+                break;
+            }
+            if now >= min_next && child.try_wait()?.is_some() {
+                break;
+            }
+            std::thread::sleep(std::cmp::min(Duration::from_millis(100), next - now));
+        }
+    };
+    let wall_time = started.elapsed();
+    summary.set_memory_peak(cgroup.memory_peak());
+    drop(cgroup);
+
+    summary.report(&mut io::stdout().lock(), &command, status, wall_time)?;
+    let tarball = write_snapshot_tarball(store_dir.path(), output)?;
+    println!(
+        "Snapshot of the run has been created at {}",
+        tarball.display()
+    );
     Ok(())
 }
 
@@ -0,0 +1,125 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Kills of userspace OOM killers.
+//!
+//! oomd and systemd-oomd kill whole cgroups on memory pressure or swap
+//! exhaustion, before the kernel OOM killer steps in, and log each kill to
+//! the journal. The journal is followed with journalctl, which keeps below
+//! free of a libsystemd dependency, and kills are kept until the next
+//! collection.
+
+use std::io::BufRead;
+use std::io::BufReader;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use model::OomKillEvent;
+use serde_json::Value;
+
+/// Syslog identifiers of the userspace OOM killers
+pub const OOM_KILLERS: &[&str] = &["systemd-oomd", "oomd"];
+
+/// Kills are dropped beyond this many between two collections, e.g. while
+/// the collector is stuck
+const MAX_PENDING_KILLS: usize = 1000;
+
+/// Killed cgroup of a kill message, None if the message is not about a kill.
+///
+/// systemd-oomd logs e.g. "Killed /system.slice/foo.service due to memory
+/// pressure for /system.slice being 80.00% > 50.00% for > 20s with reclaim
+/// activity", and oomd e.g. "oomd kill: 30.43 25.92 12.10
+/// /workload.slice/foo.service 1073741824 ruleset:[...] ...".
+pub fn parse_kill_message(message: &str) -> Option<&str> {
+    if let Some(rest) = message.strip_prefix("Killed ") {
+        let (cgroup, _) = rest.split_once(" due to ")?;
+        return cgroup.starts_with('/').then_some(cgroup);
+    }
+    let (_, rest) = message.split_once("oomd kill: ")?;
+    rest.split_ascii_whitespace()
+        .find(|item| item.starts_with('/'))
+}
+
+/// Kill of a journal entry as printed by `journalctl --output=json`, None
+/// if the entry is not about a kill
+pub fn parse_journal_entry(line: &str) -> Option<OomKillEvent> {
+    let entry = serde_json::from_str::<Value>(line).ok()?;
+    // Non UTF-8 messages are arrays of bytes, kill messages never are
+    let message = entry.get("MESSAGE")?.as_str()?;
+    let cgroup = parse_kill_message(message)?;
+    let timestamp_us = entry
+        .get("__REALTIME_TIMESTAMP")?
+        .as_str()?
+        .parse::<u64>()
+        .ok()?;
+    Some(OomKillEvent {
+        timestamp: timestamp_us / 1_000_000,
+        killer: entry
+            .get("SYSLOG_IDENTIFIER")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned(),
+        cgroup: cgroup.to_owned(),
+        message: message.to_owned(),
+    })
+}
+
+pub struct OomKillWatcher {
+    data: Arc<Mutex<Vec<OomKillEvent>>>,
+}
+
+impl OomKillWatcher {
+    pub fn new() -> Self {
+        Self {
+            data: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn get_buffer(&self) -> Arc<Mutex<Vec<OomKillEvent>>> {
+        self.data.clone()
+    }
+
+    /// Follow the journal and record kills from now on. Only returns on
+    /// failure.
+    pub fn drive(&mut self) -> Result<()> {
+        let mut journalctl = Command::new("journalctl");
+        journalctl.args(["--follow", "--lines=0", "--output=json"]);
+        for killer in OOM_KILLERS {
+            journalctl.arg(format!("--identifier={}", killer));
+        }
+        let mut child = journalctl
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to run journalctl")?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        for line in BufReader::new(stdout).lines() {
+            let line = line.context("Failed to read journalctl output")?;
+            if let Some(kill) = parse_journal_entry(&line) {
+                let mut data = self.data.lock().expect("tried to acquire poisoned lock");
+                if data.len() < MAX_PENDING_KILLS {
+                    data.push(kill);
+                }
+            }
+        }
+        let status = child.wait().context("Failed to wait for journalctl")?;
+        bail!("journalctl exited with {}", status);
+    }
+}
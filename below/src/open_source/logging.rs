@@ -13,6 +13,9 @@
 // limitations under the License.
 
 use std::fs::OpenOptions;
+use std::io::IsTerminal;
+use std::panic::RefUnwindSafe;
+use std::panic::UnwindSafe;
 use std::os::unix::fs::PermissionsExt;
 use std::path::PathBuf;
 
@@ -23,19 +26,39 @@ use slog::Drain;
 use crate::init::InitToken;
 use crate::logutil::CommandPaletteDrain;
 use crate::logutil::CompoundDecorator;
+use crate::logutil::JsonDrain;
+use crate::logutil::LevelSpecDrain;
+use crate::logutil::LogFormat;
+use crate::logutil::LogOptions;
 use crate::RedirectLogOnFail;
 
+fn root_logger<D>(drain: D, options: &LogOptions) -> slog::Logger
+where
+    D: 'static + Drain<Err = slog::Never> + Send + Sync + RefUnwindSafe + UnwindSafe,
+{
+    // Messages for the command palette are shown regardless of level
+    let drain = LevelSpecDrain::new(drain, options.level.clone());
+    let drain = CommandPaletteDrain::new(drain).fuse();
+    slog::Logger::root(drain, o!())
+}
+
 fn setup_log<T: 'static + std::io::Write + std::marker::Send>(
     _init: InitToken,
     file: T,
-    _debug: bool,
+    options: &LogOptions,
     error: Option<std::io::Error>,
 ) -> slog::Logger {
     let decorator = CompoundDecorator::new(file, std::io::stderr());
-    let drain = slog_term::FullFormat::new(decorator).build().fuse();
-    let drain = CommandPaletteDrain::new(drain).fuse();
-
-    let logger = slog::Logger::root(drain, o!());
+    let logger = match options.format {
+        LogFormat::Text => {
+            let decorator = decorator.with_color(std::io::stderr().is_terminal());
+            root_logger(
+                slog_term::FullFormat::new(decorator).build().fuse(),
+                options,
+            )
+        }
+        LogFormat::Json => root_logger(JsonDrain::new(decorator).fuse(), options),
+    };
 
     // When we want to redirect the log, also log the open file err to stderr
     if let Some(e) = error {
@@ -51,7 +74,7 @@ fn setup_log<T: 'static + std::io::Write + std::marker::Send>(
 pub fn setup(
     init: InitToken,
     path: PathBuf,
-    debug: bool,
+    options: &LogOptions,
     redirect: RedirectLogOnFail,
 ) -> slog::Logger {
     let file_maybe = OpenOptions::new().create(true).append(true).open(path);
@@ -76,7 +99,7 @@ pub fn setup(
     }
 
     match file_maybe {
-        Ok(f) => setup_log(init, f, debug, None),
-        Err(e) => setup_log(init, std::io::stderr(), debug, Some(e)),
+        Ok(f) => setup_log(init, f, options, None),
+        Err(e) => setup_log(init, std::io::stderr(), options, Some(e)),
     }
 }
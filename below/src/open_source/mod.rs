@@ -15,6 +15,13 @@
 mod bpf {
     include!(concat!(env!("OUT_DIR"), "/exitstat.skel.rs"));
 }
+// Generated skeletons each bring their own imports, so keep them apart
+mod fileio_bpf {
+    include!(concat!(env!("OUT_DIR"), "/fileio.skel.rs"));
+}
+mod procnet_bpf {
+    include!(concat!(env!("OUT_DIR"), "/procnet.skel.rs"));
+}
 pub mod commands;
 pub mod gpu_stats;
 pub mod init;
@@ -22,3 +29,5 @@ pub mod logging;
 pub mod statistics;
 
 pub use bpf::ExitstatSkelBuilder;
+pub use fileio_bpf::FileioSkelBuilder;
+pub use procnet_bpf::ProcnetSkelBuilder;
@@ -0,0 +1,90 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bits of perf_event_open(2) shared by stack sampling and perf counters,
+//! from linux/perf_event.h, which libc does not cover.
+
+use std::io;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+
+pub const PERF_TYPE_HARDWARE: u32 = 0;
+pub const PERF_TYPE_SOFTWARE: u32 = 1;
+/// Target pid is the fd of a cgroup directory
+pub const PERF_FLAG_PID_CGROUP: libc::c_ulong = 1 << 2;
+const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+
+/// perf_event_attr up to PERF_ATTR_SIZE_VER5, supported since Linux 4.1
+#[repr(C)]
+#[derive(Default)]
+pub struct PerfEventAttr {
+    pub type_: u32,
+    pub size: u32,
+    pub config: u64,
+    pub sample_freq: u64,
+    pub sample_type: u64,
+    pub read_format: u64,
+    pub flags: u64,
+    pub wakeup_events: u32,
+    pub bp_type: u32,
+    pub config1: u64,
+    pub config2: u64,
+    pub branch_sample_type: u64,
+    pub sample_regs_user: u64,
+    pub sample_stack_user: u32,
+    pub clockid: i32,
+    pub sample_regs_intr: u64,
+    pub aux_watermark: u32,
+    pub sample_max_stack: u16,
+    pub reserved: u16,
+}
+
+impl PerfEventAttr {
+    /// Attributes of an event of the given type and config, to be filled in
+    /// further
+    pub fn new(type_: u32, config: u64) -> Self {
+        Self {
+            type_,
+            size: std::mem::size_of::<PerfEventAttr>() as u32,
+            config,
+            ..Default::default()
+        }
+    }
+}
+
+/// Open a perf event for `pid` on `cpu`, either of which may be -1 for any.
+/// The returned fd is closed on exec.
+pub fn perf_event_open(
+    attr: &PerfEventAttr,
+    pid: i32,
+    cpu: i32,
+    flags: libc::c_ulong,
+) -> io::Result<OwnedFd> {
+    // SAFETY: attr is a valid perf_event_attr that outlives the call
+    let fd = unsafe {
+        libc::syscall(
+            libc::SYS_perf_event_open,
+            attr as *const PerfEventAttr,
+            pid,
+            cpu,
+            -1,
+            flags | PERF_FLAG_FD_CLOEXEC,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: fd was just returned by perf_event_open and is owned here
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as i32) })
+}
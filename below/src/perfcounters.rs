@@ -0,0 +1,250 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Last level cache counters of the whole system and of top level cgroups.
+//!
+//! Cache references and misses are counted on every online CPU, system wide
+//! and in perf cgroup mode for each child of the cgroup root. The model
+//! estimates memory bandwidth from the misses, which needs neither uncore
+//! memory controller counters nor RDT.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use model::PerfCounterSample;
+use model::PerfCounters;
+use slog::warn;
+
+use crate::perf::perf_event_open;
+use crate::perf::PerfEventAttr;
+use crate::perf::PERF_FLAG_PID_CGROUP;
+use crate::perf::PERF_TYPE_HARDWARE;
+
+// From linux/perf_event.h, which libc does not cover
+const PERF_COUNT_HW_CACHE_REFERENCES: u64 = 2;
+const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+const PERF_FORMAT_TOTAL_TIME_ENABLED: u64 = 1 << 0;
+const PERF_FORMAT_TOTAL_TIME_RUNNING: u64 = 1 << 1;
+
+/// Each counted cgroup takes two events per CPU
+const MAX_CGROUPS: usize = 64;
+
+/// Scale `value` up to the whole time the event was enabled, as the PMU
+/// multiplexes events when more are open than it has counters.
+pub fn scale_count(value: u64, time_enabled: u64, time_running: u64) -> u64 {
+    if time_running == 0 {
+        return 0;
+    }
+    (value as u128 * time_enabled as u128 / time_running as u128) as u64
+}
+
+/// A counting hardware event on a single CPU
+struct Counter(File);
+
+impl Counter {
+    fn open(config: u64, pid: i32, cpu: i32, flags: libc::c_ulong) -> io::Result<Self> {
+        let attr = PerfEventAttr {
+            read_format: PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_TOTAL_TIME_RUNNING,
+            ..PerfEventAttr::new(PERF_TYPE_HARDWARE, config)
+        };
+        Ok(Self(File::from(perf_event_open(&attr, pid, cpu, flags)?)))
+    }
+
+    /// Count since the event was opened, scaled for multiplexing
+    fn read(&mut self) -> io::Result<u64> {
+        // value, time_enabled and time_running
+        let mut buf = [0u8; 24];
+        self.0.read_exact(&mut buf)?;
+        let field = |idx: usize| {
+            u64::from_ne_bytes(buf[idx * 8..(idx + 1) * 8].try_into().expect("8 bytes"))
+        };
+        Ok(scale_count(field(0), field(1), field(2)))
+    }
+}
+
+/// Cache references and misses of one target on every online CPU
+struct CacheCounters(Vec<(Counter, Counter)>);
+
+impl CacheCounters {
+    fn open(cpus: &[i32], pid: i32, flags: libc::c_ulong) -> io::Result<Self> {
+        cpus.iter()
+            .map(|&cpu| {
+                Ok((
+                    Counter::open(PERF_COUNT_HW_CACHE_REFERENCES, pid, cpu, flags)?,
+                    Counter::open(PERF_COUNT_HW_CACHE_MISSES, pid, cpu, flags)?,
+                ))
+            })
+            .collect::<io::Result<_>>()
+            .map(Self)
+    }
+
+    fn read(&mut self) -> io::Result<PerfCounterSample> {
+        let mut sample = PerfCounterSample::default();
+        for (references, misses) in &mut self.0 {
+            sample.llc_references += references.read()?;
+            sample.llc_misses += misses.read()?;
+        }
+        Ok(sample)
+    }
+}
+
+/// CPUs in /sys/devices/system/cpu/online. CPUs brought online later are not
+/// counted.
+fn online_cpus() -> Result<Vec<i32>> {
+    let path = "/sys/devices/system/cpu/online";
+    let online = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+    let cpuset = cgroupfs::Cpuset::from_str(online.trim())
+        .map_err(|e| anyhow!("Failed to parse {}: {}", path, e))?;
+    Ok(cpuset.cpus.into_iter().map(|cpu| cpu as i32).collect())
+}
+
+/// Counts cache events and periodically publishes the cumulative counts.
+pub struct PerfCounterDriver {
+    logger: slog::Logger,
+    cgroup_root: PathBuf,
+    interval: Duration,
+    buffer: Arc<Mutex<PerfCounters>>,
+}
+
+impl PerfCounterDriver {
+    pub fn new(logger: slog::Logger, cgroup_root: PathBuf, interval: Duration) -> Self {
+        Self {
+            logger,
+            cgroup_root,
+            interval,
+            buffer: Arc::new(Mutex::new(PerfCounters::default())),
+        }
+    }
+
+    pub fn get_buffer(&self) -> Arc<Mutex<PerfCounters>> {
+        self.buffer.clone()
+    }
+
+    /// Loops forever unless the system wide counters fail
+    pub fn drive(&mut self) -> Result<()> {
+        let cpus = online_cpus()?;
+        let mut system = CacheCounters::open(&cpus, -1, 0).context(
+            "Failed to open cache counters, hardware events may be unavailable or not permitted \
+             by kernel.perf_event_paranoid",
+        )?;
+        // Keyed by cgroup id, i.e. the inode number of the cgroup directory
+        let mut cgroups: BTreeMap<u64, CacheCounters> = BTreeMap::new();
+        // Cgroups that could not be counted, not retried until recreated
+        let mut failed = BTreeSet::new();
+
+        loop {
+            self.update_cgroups(&cpus, &mut cgroups, &mut failed);
+            let mut counters = PerfCounters {
+                system: Some(system.read().context("Failed to read cache counters")?),
+                cgroups: BTreeMap::new(),
+            };
+            cgroups.retain(|id, cgroup| match cgroup.read() {
+                Ok(sample) => {
+                    counters.cgroups.insert(*id, sample);
+                    true
+                }
+                Err(e) => {
+                    warn!(
+                        self.logger,
+                        "Failed to read cache counters of cgroup: {}", e
+                    );
+                    failed.insert(*id);
+                    false
+                }
+            });
+            *self.buffer.lock().unwrap() = counters;
+            std::thread::sleep(self.interval);
+        }
+    }
+
+    /// Start counting new children of the cgroup root and stop counting
+    /// removed ones.
+    fn update_cgroups(
+        &self,
+        cpus: &[i32],
+        cgroups: &mut BTreeMap<u64, CacheCounters>,
+        failed: &mut BTreeSet<u64>,
+    ) {
+        let entries = match fs::read_dir(&self.cgroup_root) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!(
+                    self.logger,
+                    "Failed to list cgroups in {}: {}",
+                    self.cgroup_root.display(),
+                    e
+                );
+                return;
+            }
+        };
+        let mut present = BTreeSet::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            // perf identifies the cgroup by an fd of its directory, which is
+            // only needed while opening the events
+            let dir = match File::open(&path) {
+                Ok(dir) => dir,
+                Err(_) => continue,
+            };
+            let id = match dir.metadata() {
+                Ok(metadata) if metadata.is_dir() => metadata.ino(),
+                _ => continue,
+            };
+            present.insert(id);
+            if cgroups.contains_key(&id) || failed.contains(&id) {
+                continue;
+            }
+            if cgroups.len() >= MAX_CGROUPS {
+                warn!(
+                    self.logger,
+                    "Not counting cache events of {}, already counting {} cgroups",
+                    path.display(),
+                    MAX_CGROUPS
+                );
+                failed.insert(id);
+                continue;
+            }
+            match CacheCounters::open(cpus, dir.as_raw_fd(), PERF_FLAG_PID_CGROUP) {
+                Ok(counters) => {
+                    cgroups.insert(id, counters);
+                }
+                Err(e) => {
+                    warn!(
+                        self.logger,
+                        "Failed to count cache events of {}: {}",
+                        path.display(),
+                        e
+                    );
+                    failed.insert(id);
+                }
+            }
+        }
+        cgroups.retain(|id, _| present.contains(id));
+        failed.retain(|id| present.contains(id));
+    }
+}
@@ -0,0 +1,160 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Context;
+use anyhow::Result;
+use libbpf_rs::skel::OpenSkel as _;
+use libbpf_rs::skel::Skel as _;
+use libbpf_rs::skel::SkelBuilder as _;
+use libbpf_rs::MapFlags;
+use model::ProcessNetIfaceStat;
+use model::ProcessNetMap;
+use model::ProcessNetStat;
+use plain::Plain;
+
+use crate::bpfstats::BpfHealth;
+use crate::ProcnetSkelBuilder;
+
+// See bpf prog for comments on what each field is
+#[repr(C)]
+#[derive(Default)]
+struct NetKey {
+    tgid: u32,
+    ifindex: u32,
+}
+
+unsafe impl Plain for NetKey {}
+
+#[repr(C)]
+#[derive(Default)]
+struct NetStats {
+    rx_bytes: u64,
+    tx_bytes: u64,
+}
+
+unsafe impl Plain for NetStats {}
+
+/// Interface names by ifindex in the network namespace of below, which is
+/// the one of the Iface view
+fn interface_names() -> BTreeMap<u32, String> {
+    let entries = match std::fs::read_dir("/sys/class/net") {
+        Ok(entries) => entries,
+        Err(_) => return BTreeMap::new(),
+    };
+    entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let ifindex = std::fs::read_to_string(path.join("ifindex"))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()?;
+            Some((ifindex, path.file_name()?.to_str()?.to_owned()))
+        })
+        .collect()
+}
+
+/// Traces socket sends and receives with BPF and periodically publishes the
+/// cumulative bytes of each process, in total and by interface.
+pub struct ProcNetDriver {
+    debug: bool,
+    interval: Duration,
+    buffer: Arc<Mutex<ProcessNetMap>>,
+    health: BpfHealth,
+}
+
+impl ProcNetDriver {
+    pub fn new(debug: bool, interval: Duration, health: BpfHealth) -> Self {
+        Self {
+            debug,
+            interval,
+            buffer: Arc::new(Mutex::new(ProcessNetMap::default())),
+            health,
+        }
+    }
+
+    pub fn get_buffer(&self) -> Arc<Mutex<ProcessNetMap>> {
+        self.buffer.clone()
+    }
+
+    /// Loops forever unless an error is hit
+    pub fn drive(&mut self) -> Result<()> {
+        let mut skel_builder = ProcnetSkelBuilder::default();
+        skel_builder.obj_builder.debug(self.debug);
+        let mut skel = skel_builder
+            .open()
+            .context("Failed to open BPF program")?
+            .load()
+            .context("Failed to load BPF program")?;
+        skel.attach()
+            .map_err(|e| {
+                self.health.attach_failed();
+                e
+            })
+            .context("Failed to attach BPF program?")?;
+
+        loop {
+            std::thread::sleep(self.interval);
+            self.health.update_run_time(skel.object());
+
+            let maps = skel.maps();
+            let proc_net = maps.proc_net();
+            let interface_names = interface_names();
+            let mut processes = ProcessNetMap::new();
+            let mut exited = Vec::new();
+            for key_bytes in proc_net.keys() {
+                let mut key = NetKey::default();
+                plain::copy_from_bytes(&mut key, &key_bytes).expect("Key buffer was too short");
+                // Entries of exited processes would otherwise linger until
+                // evicted, and be attributed to a new process reusing the pid
+                if !Path::new("/proc").join(key.tgid.to_string()).exists() {
+                    exited.push(key_bytes);
+                    continue;
+                }
+                // The entry may have been evicted since we got its key
+                let value_bytes = match proc_net
+                    .lookup(&key_bytes, MapFlags::ANY)
+                    .context("Failed to look up process network entry")?
+                {
+                    Some(value_bytes) => value_bytes,
+                    None => continue,
+                };
+                let mut stats = NetStats::default();
+                plain::copy_from_bytes(&mut stats, &value_bytes)
+                    .expect("Value buffer was too short");
+                let process: &mut ProcessNetStat = processes.entry(key.tgid as i32).or_default();
+                process.rx_bytes += stats.rx_bytes;
+                process.tx_bytes += stats.tx_bytes;
+                // Interfaces may be gone by now
+                if let Some(name) = interface_names.get(&key.ifindex) {
+                    let interface: &mut ProcessNetIfaceStat =
+                        process.interfaces.entry(name.clone()).or_default();
+                    interface.rx_bytes += stats.rx_bytes;
+                    interface.tx_bytes += stats.tx_bytes;
+                }
+            }
+            for key_bytes in exited {
+                // Already gone if evicted in the meantime
+                let _ = proc_net.delete(&key_bytes);
+            }
+            *self.buffer.lock().unwrap() = processes;
+        }
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Resource usage report of a single command run by `below profile`.
+//!
+//! The command runs in a cgroup created for it, so the cumulative counters
+//! of that cgroup start at zero and the last sample holds the totals of the
+//! whole run. Only memory usage needs tracking over time, for its peak.
+
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Child;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use anyhow::Context;
+use anyhow::Result;
+use common::util::convert_bytes;
+use model::CgroupSample;
+use slog::warn;
+
+/// Cgroup the profiled command runs in, removed on drop.
+pub struct ProfileCgroup {
+    logger: slog::Logger,
+    path: PathBuf,
+}
+
+impl ProfileCgroup {
+    /// Create a cgroup for the profiled command directly under `cgroup_root`
+    pub fn create(logger: slog::Logger, cgroup_root: &Path) -> Result<Self> {
+        let path = cgroup_root.join(format!("below_profile.{}", std::process::id()));
+        fs::create_dir(&path).with_context(|| {
+            format!(
+                "Failed to create cgroup {}. below profile needs write access to cgroup2.",
+                path.display()
+            )
+        })?;
+        Ok(Self { logger, path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Spawn `command` inside the cgroup. The child moves itself in before
+    /// exec so that none of its memory or forks are accounted elsewhere.
+    pub fn spawn(&self, command: &[String]) -> Result<Child> {
+        let (program, args) = command.split_first().context("No command to profile")?;
+        let procs_path = self.path.join("cgroup.procs");
+        let procs = fs::OpenOptions::new()
+            .write(true)
+            .open(&procs_path)
+            .with_context(|| format!("Failed to open {}", procs_path.display()))?;
+        let procs_fd = procs.as_raw_fd();
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        // SAFETY: only calls write(2), which is async-signal-safe, and
+        // `procs` outlives the spawn.
+        unsafe {
+            cmd.pre_exec(move || {
+                // "0" moves the writing process
+                if libc::write(procs_fd, b"0".as_ptr() as *const libc::c_void, 1) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to run {}", program))?;
+        drop(procs);
+        Ok(child)
+    }
+
+    /// Highest memory usage of the cgroup as tracked by the kernel, which
+    /// unlike sampling catches short spikes. None on kernels without
+    /// memory.peak.
+    pub fn memory_peak(&self) -> Option<u64> {
+        fs::read_to_string(self.path.join("memory.peak"))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+}
+
+impl Drop for ProfileCgroup {
+    fn drop(&mut self) {
+        // Fails if the command left processes behind
+        if let Err(e) = fs::remove_dir(&self.path) {
+            warn!(
+                self.logger,
+                "Failed to remove cgroup {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Accumulates samples of the profiled cgroup and prints a summary of its
+/// resource usage.
+#[derive(Default)]
+pub struct ProfileSummary {
+    samples: usize,
+    sampled_memory_peak: Option<u64>,
+    memory_peak: Option<u64>,
+    last: Option<CgroupSample>,
+}
+
+fn secs(usec: Option<u64>) -> String {
+    match usec {
+        Some(usec) => format!("{:.2}s", usec as f64 / 1_000_000.0),
+        None => "?".to_owned(),
+    }
+}
+
+fn bytes(bytes: Option<u64>) -> String {
+    match bytes {
+        Some(bytes) => convert_bytes(bytes as f64),
+        None => "?".to_owned(),
+    }
+}
+
+impl ProfileSummary {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Feed the next sample of the profiled cgroup
+    pub fn observe(&mut self, cgroup: &CgroupSample) {
+        self.samples += 1;
+        if let Some(current) = cgroup.memory_current {
+            let current = current.max(0) as u64;
+            self.sampled_memory_peak = Some(
+                self.sampled_memory_peak
+                    .map_or(current, |peak| std::cmp::max(peak, current)),
+            );
+        }
+        self.last = Some(cgroup.clone());
+    }
+
+    /// Set the peak memory usage tracked by the kernel, preferred over the
+    /// sampled one
+    pub fn set_memory_peak(&mut self, peak: Option<u64>) {
+        self.memory_peak = peak;
+    }
+
+    /// Write the summary of a run of `command` that took `wall_time`
+    pub fn report<W: Write>(
+        &self,
+        out: &mut W,
+        command: &[String],
+        status: ExitStatus,
+        wall_time: Duration,
+    ) -> io::Result<()> {
+        writeln!(out, "Profile of: {}", command.join(" "))?;
+        writeln!(out, "  Exit status:  {}", status)?;
+        writeln!(
+            out,
+            "  Wall time:    {:.2}s ({} samples)",
+            wall_time.as_secs_f64(),
+            self.samples
+        )?;
+        let last = match &self.last {
+            Some(last) => last,
+            None => return Ok(()),
+        };
+
+        let cpu = last.cpu_stat.as_ref();
+        writeln!(
+            out,
+            "  CPU time:     {} (user {}, system {})",
+            secs(cpu.and_then(|cpu| cpu.usage_usec)),
+            secs(cpu.and_then(|cpu| cpu.user_usec)),
+            secs(cpu.and_then(|cpu| cpu.system_usec)),
+        )?;
+
+        writeln!(
+            out,
+            "  Peak memory:  {}",
+            bytes(self.memory_peak.or(self.sampled_memory_peak))
+        )?;
+
+        let io_total = |get: fn(&cgroupfs::IoStat) -> Option<u64>| {
+            last.io_stat
+                .as_ref()
+                .map(|io_stat| io_stat.values().filter_map(get).sum::<u64>())
+        };
+        writeln!(
+            out,
+            "  IO:           read {}, written {}",
+            bytes(io_total(|io| io.rbytes)),
+            bytes(io_total(|io| io.wbytes)),
+        )?;
+
+        // Total stall time, which unlike the averages covers the whole run
+        if let Some(pressure) = &last.pressure {
+            writeln!(
+                out,
+                "  Pressure:     cpu some {}, io some {} full {}, memory some {} full {}",
+                secs(pressure.cpu.some.total),
+                secs(pressure.io.some.total),
+                secs(pressure.io.full.total),
+                secs(pressure.memory.some.total),
+                secs(pressure.memory.full.total),
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Memory PSI triggers on selected cgroups.
+//!
+//! A trigger written to a cgroup's memory.pressure makes the kernel signal
+//! POLLPRI on the file whenever tasks of the cgroup stalled on memory for
+//! longer than a threshold within a time window, at most once per window.
+//! Firings are timestamped and kept per cgroup until the next collection.
+//! Unlike the pressure averages read every interval they catch stall bursts
+//! shorter than the interval.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use model::CgroupPsiTriggerMap;
+use slog::info;
+use slog::warn;
+
+/// How often cgroups without a trigger, e.g. not created yet or recreated,
+/// are retried
+const REGISTER_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Trigger description as written to memory.pressure. The kernel replaces
+/// the last byte written with a NUL, so the terminator is part of it.
+pub fn trigger_string(threshold: Duration, window: Duration) -> String {
+    format!("some {} {}\0", threshold.as_micros(), window.as_micros())
+}
+
+struct Trigger {
+    cgroup: String,
+    /// Cgroup id the firings are recorded under
+    inode_number: u64,
+    file: File,
+}
+
+pub struct PsiTriggerWatcher {
+    logger: slog::Logger,
+    cgroup_root: PathBuf,
+    cgroups: Vec<String>,
+    trigger: String,
+    triggers: Vec<Trigger>,
+    /// Cgroups whose registration failed, so that failures are only logged
+    /// once until it succeeds
+    failed: BTreeSet<String>,
+    data: Arc<Mutex<CgroupPsiTriggerMap>>,
+}
+
+impl PsiTriggerWatcher {
+    /// Watch `cgroups`, given relative to `cgroup_root`, for stalls on
+    /// memory longer than `threshold` within `window`
+    pub fn new(
+        logger: slog::Logger,
+        cgroup_root: PathBuf,
+        cgroups: Vec<String>,
+        threshold: Duration,
+        window: Duration,
+    ) -> Self {
+        Self {
+            logger,
+            cgroup_root,
+            cgroups,
+            trigger: trigger_string(threshold, window),
+            triggers: Vec::new(),
+            failed: BTreeSet::new(),
+            data: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    pub fn get_buffer(&self) -> Arc<Mutex<CgroupPsiTriggerMap>> {
+        self.data.clone()
+    }
+
+    fn register(&self, cgroup: &str) -> Result<Trigger> {
+        let path = self.cgroup_root.join(cgroup.trim_start_matches('/'));
+        let reader = cgroupfs::CgroupReader::new_with_relative_path(
+            self.cgroup_root.clone(),
+            PathBuf::from(cgroup.trim_start_matches('/')),
+        )?;
+        // Not present on kernels before 6.1, where PSI is always on
+        if let Ok(false) = reader.read_cgroup_pressure() {
+            bail!("PSI is disabled for the cgroup in cgroup.pressure");
+        }
+        let inode_number = fs::metadata(&path)
+            .with_context(|| format!("Failed to stat {}", path.display()))?
+            .ino();
+        let pressure_path = path.join("memory.pressure");
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&pressure_path)
+            .with_context(|| format!("Failed to open {}", pressure_path.display()))?;
+        file.write_all(self.trigger.as_bytes()).with_context(|| {
+            format!("Failed to write PSI trigger to {}", pressure_path.display())
+        })?;
+        Ok(Trigger {
+            cgroup: cgroup.to_owned(),
+            inode_number,
+            file,
+        })
+    }
+
+    /// Register triggers for cgroups that do not have one
+    fn register_missing(&mut self) {
+        for cgroup in &self.cgroups {
+            if self.triggers.iter().any(|t| &t.cgroup == cgroup) {
+                continue;
+            }
+            match self.register(cgroup) {
+                Ok(trigger) => {
+                    if self.failed.remove(cgroup) {
+                        info!(self.logger, "Watching PSI trigger of cgroup {}", cgroup);
+                    }
+                    self.data
+                        .lock()
+                        .expect("tried to acquire poisoned lock")
+                        .entry(trigger.inode_number)
+                        .or_default();
+                    self.triggers.push(trigger);
+                }
+                Err(e) => {
+                    if self.failed.insert(cgroup.clone()) {
+                        warn!(
+                            self.logger,
+                            "Failed to register PSI trigger for cgroup {}, retrying: {:#}",
+                            cgroup,
+                            e
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Wait for triggers to fire and record them. Only returns on failure.
+    pub fn drive(&mut self) -> Result<()> {
+        loop {
+            self.register_missing();
+            let mut fds = self
+                .triggers
+                .iter()
+                .map(|trigger| libc::pollfd {
+                    fd: trigger.file.as_raw_fd(),
+                    events: libc::POLLPRI,
+                    revents: 0,
+                })
+                .collect::<Vec<_>>();
+            // SAFETY: fds is a valid array of fds.len() pollfds
+            let ret = unsafe {
+                libc::poll(
+                    fds.as_mut_ptr(),
+                    fds.len() as libc::nfds_t,
+                    REGISTER_RETRY_INTERVAL.as_millis() as libc::c_int,
+                )
+            };
+            if ret < 0 {
+                let err = std::io::Error::last_os_error();
+                if err.kind() == std::io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err).context("Failed to poll PSI triggers");
+            }
+            let now_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64);
+
+            let mut data = self.data.lock().expect("tried to acquire poisoned lock");
+            let mut removed = Vec::new();
+            for (idx, fd) in fds.iter().enumerate() {
+                let trigger = &self.triggers[idx];
+                if fd.revents & libc::POLLERR != 0 {
+                    // The cgroup went away
+                    removed.push(idx);
+                    data.remove(&trigger.inode_number);
+                } else if fd.revents & libc::POLLPRI != 0 {
+                    data.entry(trigger.inode_number).or_default().push(now_ms);
+                }
+            }
+            drop(data);
+            for idx in removed.into_iter().rev() {
+                let trigger = self.triggers.remove(idx);
+                info!(
+                    self.logger,
+                    "Cgroup {} with PSI trigger went away", trigger.cgroup
+                );
+            }
+        }
+    }
+}
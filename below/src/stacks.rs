@@ -0,0 +1,356 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stack sampling of processes the collector found hot.
+//!
+//! Each requested process is sampled for a short while with a perf cpu clock
+//! event per thread, recording only callchains. Kernel frames are resolved
+//! with /proc/kallsyms. User frames are not symbolized, as that needs the
+//! debug info of every binary; they are reported as `<file>+0x<offset>`,
+//! which addr2line and similar tools resolve offline.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::os::fd::AsRawFd;
+use std::os::fd::OwnedFd;
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::fence;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Context;
+use anyhow::Result;
+use model::StackCount;
+use model::StackSample;
+use model::StackSampleMap;
+
+use crate::perf::perf_event_open;
+use crate::perf::PerfEventAttr;
+use crate::perf::PERF_TYPE_SOFTWARE;
+
+/// How long each hot process is sampled for
+const SAMPLE_DURATION: Duration = Duration::from_secs(1);
+/// Off the usual timer frequencies so that periodic work is not sampled in
+/// lockstep
+const SAMPLE_FREQ: u64 = 99;
+/// Each sampled thread needs its own event and ring buffer
+const MAX_THREADS: usize = 32;
+/// Pages of each ring buffer after its header page, must be a power of 2
+const DATA_PAGES: usize = 32;
+/// Number of stacks kept per process
+const TOP_STACKS: usize = 5;
+/// Frames kept per stack, innermost first
+const MAX_FRAMES: usize = 32;
+
+// From linux/perf_event.h, which libc does not cover
+const PERF_COUNT_SW_CPU_CLOCK: u64 = 0;
+const PERF_SAMPLE_CALLCHAIN: u64 = 1 << 5;
+const PERF_RECORD_SAMPLE: u32 = 9;
+const PERF_ATTR_FLAG_EXCLUDE_HV: u64 = 1 << 6;
+const PERF_ATTR_FLAG_FREQ: u64 = 1 << 10;
+/// Callchain entries from here on mark the context of the frames that follow
+/// rather than being addresses
+const PERF_CONTEXT_MAX: u64 = -4095i64 as u64;
+const PERF_CONTEXT_KERNEL: u64 = -128i64 as u64;
+/// Offset of data_head in the header page of a ring buffer
+const PERF_DATA_HEAD_OFFSET: usize = 1024;
+
+/// Callchains sampled from a process, addresses innermost first with
+/// context markers kept, mapped to their number of samples
+type Callchains = HashMap<Vec<u64>, u64>;
+
+/// A perf event sampling the callchains of a single thread into its ring
+/// buffer
+struct ThreadSampler {
+    _fd: OwnedFd,
+    buf: *mut u8,
+    len: usize,
+    page_size: usize,
+}
+
+impl ThreadSampler {
+    fn open(tid: i32) -> io::Result<Self> {
+        let attr = PerfEventAttr {
+            sample_freq: SAMPLE_FREQ,
+            sample_type: PERF_SAMPLE_CALLCHAIN,
+            flags: PERF_ATTR_FLAG_EXCLUDE_HV | PERF_ATTR_FLAG_FREQ,
+            ..PerfEventAttr::new(PERF_TYPE_SOFTWARE, PERF_COUNT_SW_CPU_CLOCK)
+        };
+        let fd = perf_event_open(&attr, tid, -1, 0)?;
+        // SAFETY: sysconf has no preconditions
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let len = page_size * (DATA_PAGES + 1);
+        // SAFETY: maps a new region that only this struct refers to
+        let buf = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd.as_raw_fd(),
+                0,
+            )
+        };
+        if buf == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            _fd: fd,
+            buf: buf as *mut u8,
+            len,
+            page_size,
+        })
+    }
+
+    /// Add the callchains sampled so far to `callchains`, returning the
+    /// number of samples. data_tail is never advanced, so the kernel stops
+    /// writing once the buffer is full rather than wrapping around.
+    fn read_callchains(&self, callchains: &mut Callchains) -> u64 {
+        // SAFETY: data_head is within the header page, which stays mapped
+        // for the lifetime of self
+        let head = unsafe { ptr::read_volatile(self.buf.add(PERF_DATA_HEAD_OFFSET) as *const u64) };
+        // Pairs with the kernel's barrier between writing records and head
+        fence(Ordering::Acquire);
+        let data_len = self.page_size * DATA_PAGES;
+        // SAFETY: the data pages follow the header page within the mapping
+        let data = unsafe { std::slice::from_raw_parts(self.buf.add(self.page_size), data_len) };
+        parse_records(&data[..std::cmp::min(head as usize, data_len)], callchains)
+    }
+}
+
+impl Drop for ThreadSampler {
+    fn drop(&mut self) {
+        // SAFETY: buf and len are the region mapped in open
+        unsafe {
+            libc::munmap(self.buf as *mut libc::c_void, self.len);
+        }
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 8)?;
+    Some(u64::from_ne_bytes(bytes.try_into().ok()?))
+}
+
+/// Parse the records of a ring buffer that has not wrapped around, adding
+/// the callchains of its samples to `callchains`. Returns the number of
+/// samples.
+fn parse_records(data: &[u8], callchains: &mut Callchains) -> u64 {
+    let mut samples = 0;
+    let mut offset = 0;
+    // perf_event_header is u32 type, u16 misc, u16 size
+    while offset + 8 <= data.len() {
+        let type_ = u32::from_ne_bytes(data[offset..offset + 4].try_into().unwrap());
+        let size = u16::from_ne_bytes(data[offset + 6..offset + 8].try_into().unwrap()) as usize;
+        if size < 8 || offset + size > data.len() {
+            break;
+        }
+        if type_ == PERF_RECORD_SAMPLE {
+            let record = &data[offset + 8..offset + size];
+            if let Some(nr) = read_u64(record, 0) {
+                let nr = std::cmp::min(nr as usize, (record.len() - 8) / 8);
+                let mut callchain = Vec::new();
+                let mut frames = 0;
+                for i in 0..nr {
+                    let ip = read_u64(record, 8 + i * 8).unwrap_or_default();
+                    if ip < PERF_CONTEXT_MAX {
+                        if frames == MAX_FRAMES {
+                            break;
+                        }
+                        frames += 1;
+                    }
+                    callchain.push(ip);
+                }
+                *callchains.entry(callchain).or_default() += 1;
+                samples += 1;
+            }
+        }
+        offset += size;
+    }
+    samples
+}
+
+/// Function symbols of the kernel sorted by address. Empty when addresses
+/// are hidden by kptr_restrict.
+pub fn parse_kallsyms(kallsyms: &str) -> Vec<(u64, String)> {
+    let mut symbols: Vec<_> = kallsyms
+        .lines()
+        .filter_map(|line| {
+            let mut items = line.split_whitespace();
+            let addr = u64::from_str_radix(items.next()?, 16).ok()?;
+            let kind = items.next()?;
+            let name = items.next()?;
+            if addr == 0 || !matches!(kind, "t" | "T" | "w" | "W") {
+                return None;
+            }
+            Some((addr, name.to_owned()))
+        })
+        .collect();
+    symbols.sort_unstable();
+    symbols
+}
+
+/// A file backed executable mapping of a process
+#[derive(Debug, PartialEq)]
+pub struct Mapping {
+    start: u64,
+    end: u64,
+    offset: u64,
+    file: String,
+}
+
+/// Executable file mappings from the contents of /proc/<pid>/maps
+pub fn parse_maps(maps: &str) -> Vec<Mapping> {
+    maps.lines()
+        .filter_map(|line| {
+            let mut items = line.split_whitespace();
+            let (start, end) = items.next()?.split_once('-')?;
+            let perms = items.next()?;
+            let offset = items.next()?;
+            let path = items.nth(2)?;
+            if !perms.contains('x') || !path.starts_with('/') {
+                return None;
+            }
+            Some(Mapping {
+                start: u64::from_str_radix(start, 16).ok()?,
+                end: u64::from_str_radix(end, 16).ok()?,
+                offset: u64::from_str_radix(offset, 16).ok()?,
+                file: Path::new(path).file_name()?.to_string_lossy().into_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Resolve the addresses of `callchain` to frame names, innermost first
+pub fn symbolize(callchain: &[u64], kallsyms: &[(u64, String)], maps: &[Mapping]) -> Vec<String> {
+    let mut kernel = false;
+    let mut frames = Vec::new();
+    for &ip in callchain {
+        if ip >= PERF_CONTEXT_MAX {
+            kernel = ip == PERF_CONTEXT_KERNEL;
+            continue;
+        }
+        let frame = if kernel {
+            match kallsyms.partition_point(|(addr, _)| *addr <= ip) {
+                0 => "[kernel]".to_owned(),
+                idx => kallsyms[idx - 1].1.clone(),
+            }
+        } else {
+            match maps.iter().find(|m| m.start <= ip && ip < m.end) {
+                Some(m) => format!("{}+{:#x}", m.file, ip - m.start + m.offset),
+                None => format!("{:#x}", ip),
+            }
+        };
+        frames.push(frame);
+    }
+    frames
+}
+
+/// Samples the stacks of the processes it is sent and buffers the results
+/// for the collector to take.
+pub struct StackSampler {
+    requests: Receiver<i32>,
+    buffer: Arc<Mutex<StackSampleMap>>,
+    kallsyms: Option<Vec<(u64, String)>>,
+}
+
+impl StackSampler {
+    pub fn new(requests: Receiver<i32>) -> Self {
+        Self {
+            requests,
+            buffer: Default::default(),
+            kallsyms: None,
+        }
+    }
+
+    pub fn get_buffer(&self) -> Arc<Mutex<StackSampleMap>> {
+        self.buffer.clone()
+    }
+
+    /// Sample requested processes until the collector goes away. Fails if
+    /// perf events cannot be opened at all, e.g. for lack of privileges.
+    pub fn drive(&mut self) -> Result<()> {
+        while let Ok(pid) = self.requests.recv() {
+            match self.sample(pid) {
+                Ok(Some(sample)) => {
+                    self.buffer.lock().unwrap().insert(pid, sample);
+                }
+                Ok(None) => {}
+                Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                    return Err(e).context("Failed to open perf event");
+                }
+                // The process most likely exited in the meantime
+                Err(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn sample(&mut self, pid: i32) -> io::Result<Option<StackSample>> {
+        let mut samplers = Vec::new();
+        for entry in fs::read_dir(format!("/proc/{}/task", pid))?.take(MAX_THREADS) {
+            let tid = match entry?.file_name().to_str().and_then(|s| s.parse().ok()) {
+                Some(tid) => tid,
+                None => continue,
+            };
+            match ThreadSampler::open(tid) {
+                Ok(sampler) => samplers.push(sampler),
+                // Thread exited since listing
+                Err(e) if e.raw_os_error() == Some(libc::ESRCH) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if samplers.is_empty() {
+            return Ok(None);
+        }
+
+        let start = Instant::now();
+        thread::sleep(SAMPLE_DURATION);
+        let duration = start.elapsed();
+        let mut callchains = Callchains::new();
+        let samples = samplers
+            .iter()
+            .map(|sampler| sampler.read_callchains(&mut callchains))
+            .sum();
+        drop(samplers);
+
+        let maps =
+            parse_maps(&fs::read_to_string(format!("/proc/{}/maps", pid)).unwrap_or_default());
+        let kallsyms = self.kallsyms.get_or_insert_with(|| {
+            parse_kallsyms(&fs::read_to_string("/proc/kallsyms").unwrap_or_default())
+        });
+        let mut callchains: Vec<_> = callchains.into_iter().collect();
+        callchains.sort_unstable_by_key(|(_, count)| Reverse(*count));
+        callchains.truncate(TOP_STACKS);
+        Ok(Some(StackSample {
+            duration_ms: duration.as_millis() as u64,
+            samples,
+            stacks: callchains
+                .into_iter()
+                .map(|(callchain, count)| StackCount {
+                    frames: symbolize(&callchain, kallsyms, &maps),
+                    count,
+                })
+                .collect(),
+        }))
+    }
+}
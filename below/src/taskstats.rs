@@ -0,0 +1,396 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Exited processes from the taskstats netlink interface.
+//!
+//! The kernel sends the accounting of every exiting task to the listeners
+//! registered for the CPU it exits on. It is the fallback of the exitstat
+//! collector where its BPF program cannot run, e.g. kernels without BTF or
+//! architectures the program was not built for. Unlike the BPF program it
+//! tells neither the process group, session, CPU, thread count nor RSS of
+//! the task.
+
+use std::io;
+use std::mem::size_of;
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use slog::warn;
+
+use crate::bpfstats::BpfHealth;
+
+const GENL_ID_CTRL: u16 = 0x10;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+
+const TASKSTATS_GENL_NAME: &[u8] = b"TASKSTATS\0";
+const TASKSTATS_GENL_VERSION: u8 = 1;
+const TASKSTATS_CMD_GET: u8 = 1;
+const TASKSTATS_CMD_ATTR_REGISTER_CPUMASK: u16 = 3;
+const TASKSTATS_TYPE_STATS: u16 = 3;
+const TASKSTATS_TYPE_AGGR_PID: u16 = 4;
+
+const NLMSG_HDR_LEN: usize = 16;
+const GENL_HDR_LEN: usize = 4;
+const NLA_HDR_LEN: usize = 4;
+/// Attribute types without the nested and byte order flags
+const NLA_TYPE_MASK: u16 = 0x3fff;
+
+/// Receive buffer requested for the socket. Exits come in bursts, e.g. when
+/// a build finishes, and are lost once the buffer is full.
+const RECEIVE_BUFFER_BYTES: libc::c_int = 4 << 20;
+
+/// Offsets of the fields of struct taskstats used, which are all in
+/// version 4 and later
+const TS_COMM: usize = 80;
+const TS_COMM_LEN: usize = 32;
+const TS_PID: usize = 128;
+const TS_PPID: usize = 132;
+const TS_ETIME_US: usize = 144;
+const TS_UTIME_US: usize = 152;
+const TS_STIME_US: usize = 160;
+const TS_MINFLT: usize = 168;
+const TS_MAJFLT: usize = 176;
+const TS_READ_BYTES: usize = 248;
+const TS_WRITE_BYTES: usize = 256;
+const TS_MIN_LEN: usize = 264;
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_ne_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_ne_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_ne_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// Netlink attributes in `data` as (type, payload)
+fn parse_attributes(mut data: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        let len = read_u16(data, 0)? as usize;
+        let ty = read_u16(data, 2)? & NLA_TYPE_MASK;
+        let payload = data.get(NLA_HDR_LEN..len)?;
+        data = data.get(align(len)..).unwrap_or_default();
+        Some((ty, payload))
+    })
+}
+
+fn push_attribute(buf: &mut Vec<u8>, ty: u16, payload: &[u8]) {
+    let len = NLA_HDR_LEN + payload.len();
+    buf.extend_from_slice(&(len as u16).to_ne_bytes());
+    buf.extend_from_slice(&ty.to_ne_bytes());
+    buf.extend_from_slice(payload);
+    buf.resize(align(buf.len()), 0);
+}
+
+/// Generic netlink request of `cmd` to the family `ty`
+fn request(ty: u16, flags: u16, seq: u32, cmd: u8, version: u8, attributes: &[u8]) -> Vec<u8> {
+    let len = NLMSG_HDR_LEN + GENL_HDR_LEN + attributes.len();
+    let mut buf = Vec::with_capacity(len);
+    buf.extend_from_slice(&(len as u32).to_ne_bytes());
+    buf.extend_from_slice(&ty.to_ne_bytes());
+    buf.extend_from_slice(&(libc::NLM_F_REQUEST as u16 | flags).to_ne_bytes());
+    buf.extend_from_slice(&seq.to_ne_bytes());
+    // Port id, filled in by the kernel
+    buf.extend_from_slice(&0u32.to_ne_bytes());
+    buf.extend_from_slice(&[cmd, version, 0, 0]);
+    buf.extend_from_slice(attributes);
+    buf
+}
+
+/// Netlink messages in `data` as (type, payload)
+fn parse_messages(mut data: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        let len = read_u32(data, 0)? as usize;
+        let ty = read_u16(data, 4)?;
+        let payload = data.get(NLMSG_HDR_LEN..len)?;
+        data = data.get(align(len)..).unwrap_or_default();
+        Some((ty, payload))
+    })
+}
+
+/// Error of an NLMSG_ERROR message, None for an acknowledgement
+fn message_error(payload: &[u8]) -> Option<io::Error> {
+    match read_u32(payload, 0).map(|errno| errno as i32) {
+        Some(0) => None,
+        Some(errno) => Some(io::Error::from_raw_os_error(-errno)),
+        None => Some(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Truncated netlink error",
+        )),
+    }
+}
+
+/// Exited task of a struct taskstats. None if it is too short, i.e. from a
+/// kernel older than 2.6.19.
+pub fn parse_taskstats(data: &[u8]) -> Option<procfs::PidInfo> {
+    if data.len() < TS_MIN_LEN {
+        return None;
+    }
+    let comm = &data[TS_COMM..TS_COMM + TS_COMM_LEN];
+    let comm = &comm[..comm.iter().position(|b| *b == 0).unwrap_or(comm.len())];
+    Some(procfs::PidInfo {
+        stat: procfs::PidStat {
+            pid: Some(read_u32(data, TS_PID)? as i32),
+            comm: std::str::from_utf8(comm).ok().map(str::to_owned),
+            state: Some(procfs::PidState::Dead),
+            ppid: Some(read_u32(data, TS_PPID)? as i32),
+            minflt: Some(read_u64(data, TS_MINFLT)?),
+            majflt: Some(read_u64(data, TS_MAJFLT)?),
+            user_usecs: Some(read_u64(data, TS_UTIME_US)?),
+            system_usecs: Some(read_u64(data, TS_STIME_US)?),
+            running_secs: Some(read_u64(data, TS_ETIME_US)? / 1000000),
+            ..Default::default()
+        },
+        io: procfs::PidIo {
+            rbytes: Some(read_u64(data, TS_READ_BYTES)?),
+            wbytes: Some(read_u64(data, TS_WRITE_BYTES)?),
+        },
+        // Same as for exitstat
+        cgroup: "?".to_string(),
+        ..Default::default()
+    })
+}
+
+/// Exited tasks of a taskstats message payload, i.e. after the netlink
+/// header
+pub fn parse_message(payload: &[u8]) -> Vec<procfs::PidInfo> {
+    parse_attributes(payload.get(GENL_HDR_LEN..).unwrap_or_default())
+        .filter(|(ty, _)| *ty == TASKSTATS_TYPE_AGGR_PID)
+        .flat_map(|(_, nested)| parse_attributes(nested))
+        .filter(|(ty, _)| *ty == TASKSTATS_TYPE_STATS)
+        .filter_map(|(_, stats)| parse_taskstats(stats))
+        .collect()
+}
+
+struct Socket(OwnedFd);
+
+impl Socket {
+    fn open() -> io::Result<Self> {
+        // SAFETY: plain syscall without pointers
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_GENERIC,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: fd was just returned by socket and is owned here
+        let socket = Self(unsafe { OwnedFd::from_raw_fd(fd) });
+        // SAFETY: all zeros is a valid sockaddr_nl
+        let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        // SAFETY: addr is a valid sockaddr_nl of the given size
+        if unsafe {
+            libc::bind(
+                fd,
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        } != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(socket)
+    }
+
+    /// Ask for a receive buffer of `bytes`, beyond net.core.rmem_max if
+    /// allowed to. Smaller buffers only risk losing exits.
+    fn set_receive_buffer(&self, bytes: libc::c_int) {
+        for option in [libc::SO_RCVBUFFORCE, libc::SO_RCVBUF] {
+            // SAFETY: bytes is a valid c_int of the given size
+            if unsafe {
+                libc::setsockopt(
+                    self.0.as_raw_fd(),
+                    libc::SOL_SOCKET,
+                    option,
+                    &bytes as *const libc::c_int as *const libc::c_void,
+                    size_of::<libc::c_int>() as libc::socklen_t,
+                )
+            } == 0
+            {
+                return;
+            }
+        }
+    }
+
+    fn send(&self, buf: &[u8]) -> io::Result<()> {
+        // SAFETY: buf is valid for reads of its length
+        let sent = unsafe {
+            libc::send(
+                self.0.as_raw_fd(),
+                buf.as_ptr() as *const libc::c_void,
+                buf.len(),
+                0,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        // SAFETY: buf is valid for writes of its length
+        let received = unsafe {
+            libc::recv(
+                self.0.as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                0,
+            )
+        };
+        if received < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(received as usize)
+    }
+}
+
+pub struct TaskstatsDriver {
+    logger: slog::Logger,
+    buffer: Arc<Mutex<procfs::PidMap>>,
+    health: BpfHealth,
+}
+
+impl TaskstatsDriver {
+    /// Exited tasks are added to `buffer` and lost ones counted in `health`,
+    /// both shared with the BPF driver this one stands in for
+    pub fn new(
+        logger: slog::Logger,
+        buffer: Arc<Mutex<procfs::PidMap>>,
+        health: BpfHealth,
+    ) -> Self {
+        Self {
+            logger,
+            buffer,
+            health,
+        }
+    }
+
+    fn resolve_family(socket: &Socket, buf: &mut [u8]) -> Result<u16> {
+        let mut attributes = Vec::new();
+        push_attribute(&mut attributes, CTRL_ATTR_FAMILY_NAME, TASKSTATS_GENL_NAME);
+        socket
+            .send(&request(
+                GENL_ID_CTRL,
+                0,
+                1,
+                CTRL_CMD_GETFAMILY,
+                1,
+                &attributes,
+            ))
+            .context("Failed to query taskstats family")?;
+        let len = socket
+            .recv(buf)
+            .context("Failed to receive taskstats family")?;
+        for (ty, payload) in parse_messages(&buf[..len]) {
+            if ty == libc::NLMSG_ERROR as u16 {
+                if let Some(e) = message_error(payload) {
+                    return Err(e).context("Failed to query taskstats family");
+                }
+            } else if ty == GENL_ID_CTRL {
+                let family = parse_attributes(payload.get(GENL_HDR_LEN..).unwrap_or_default())
+                    .find(|(ty, _)| *ty == CTRL_ATTR_FAMILY_ID)
+                    .and_then(|(_, id)| read_u16(id, 0));
+                if let Some(family) = family {
+                    return Ok(family);
+                }
+            }
+        }
+        bail!("No taskstats family id in reply, is CONFIG_TASKSTATS enabled?")
+    }
+
+    /// Loops forever unless an error is hit
+    pub fn drive(&mut self) -> Result<()> {
+        let socket = Socket::open().context("Failed to open generic netlink socket")?;
+        socket.set_receive_buffer(RECEIVE_BUFFER_BYTES);
+        let mut buf = vec![0u8; 64 << 10];
+        let family = Self::resolve_family(&socket, &mut buf)?;
+
+        // Listen on all CPUs tasks may exit on
+        let cpus = std::fs::read_to_string("/sys/devices/system/cpu/possible")
+            .context("Failed to read possible CPUs")?;
+        let mut cpumask = cpus.trim().as_bytes().to_vec();
+        cpumask.push(0);
+        let mut attributes = Vec::new();
+        push_attribute(
+            &mut attributes,
+            TASKSTATS_CMD_ATTR_REGISTER_CPUMASK,
+            &cpumask,
+        );
+        socket
+            .send(&request(
+                family,
+                libc::NLM_F_ACK as u16,
+                2,
+                TASKSTATS_CMD_GET,
+                TASKSTATS_GENL_VERSION,
+                &attributes,
+            ))
+            .context("Failed to register taskstats listener")?;
+
+        loop {
+            let len = match socket.recv(&mut buf) {
+                Ok(len) => len,
+                Err(e) if e.raw_os_error() == Some(libc::ENOBUFS) => {
+                    // The kernel does not tell how many, but at least one
+                    warn!(self.logger, "Lost taskstats events");
+                    self.health.lost_events(1);
+                    continue;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e).context("Failed to receive taskstats"),
+            };
+            for (ty, payload) in parse_messages(&buf[..len]) {
+                if ty == libc::NLMSG_ERROR as u16 {
+                    // Only the registration is acknowledged
+                    if let Some(e) = message_error(payload) {
+                        return Err(e).context("Failed to register taskstats listener");
+                    }
+                } else if ty == family {
+                    let mut buffer = self.buffer.lock().unwrap();
+                    for pidinfo in parse_message(payload) {
+                        if let Some(pid) = pidinfo.stat.pid {
+                            buffer.insert(pid, pidinfo);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::time::Duration;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
@@ -22,6 +23,7 @@ use common::util::fold_string;
 use model::CgroupPressureModel;
 use model::Collector;
 use model::CollectorOptions;
+use model::DataSource;
 use model::Model;
 use model::Sample;
 use store::advance::new_advance_local;
@@ -30,6 +32,17 @@ use store::CompressionMode;
 use store::DataFrame;
 use tempfile::TempDir;
 
+use crate::analyze;
+use crate::analyze::Analyzer;
+use crate::control;
+use crate::journal_errors;
+use crate::oomd;
+use crate::perfcounters;
+use crate::profile::ProfileSummary;
+use crate::psi_triggers;
+use crate::stacks;
+use crate::taskstats;
+
 #[cfg_attr(not(feature = "vmtest"), test)]
 fn record_replay_integration() {
     let logger = get_logger();
@@ -80,7 +93,10 @@ fn record_replay_integration() {
     // what the deltas will be (0).
     let timestamp = 554433;
     let unix_ts = UNIX_EPOCH + Duration::from_secs(timestamp);
-    let df = DataFrame { sample };
+    let df = DataFrame {
+        sample,
+        aggregate: None,
+    };
     store.put(unix_ts, &df).expect("failed to store sample");
     store
         .put(unix_ts + Duration::from_secs(1), &df)
@@ -144,6 +160,7 @@ fn advance_forward_and_reverse() {
     for i in 0..3 {
         let df = DataFrame {
             sample: sample.clone(),
+            aggregate: None,
         };
         store
             .put(unix_ts + Duration::from_secs(i), &df)
@@ -183,6 +200,62 @@ fn advance_forward_and_reverse() {
     );
 }
 
+#[test]
+fn aggregate_fields_over_range() {
+    let logger = get_logger();
+    let dir = TempDir::with_prefix("below_aggregate_test.").expect("tempdir failed");
+    let mut store = store::StoreWriter::new(
+        logger.clone(),
+        &dir,
+        CompressionMode::None,
+        store::Format::Cbor,
+    )
+    .expect("Failed to create store");
+
+    let unix_ts = UNIX_EPOCH + Duration::from_secs(554433);
+    for (i, running_processes) in [2, 4, 6, 8].into_iter().enumerate() {
+        let mut sample: Sample = Default::default();
+        sample.system.stat.running_processes = Some(running_processes);
+        store
+            .put(
+                unix_ts + Duration::from_secs(i as u64),
+                &DataFrame {
+                    sample,
+                    aggregate: None,
+                },
+            )
+            .expect("failed to store sample");
+    }
+
+    let field_ids = ["system.stat.running_processes", "system.hostname"]
+        .map(|field| field.parse::<model::ModelFieldId>().unwrap());
+    let mut advance = new_advance_local(logger, dir.as_ref().to_path_buf(), unix_ts);
+    advance.set_model_parts(model::ModelParts::NONE);
+    advance.initialize();
+    // The last sample is out of range
+    let aggregates = advance.aggregate(unix_ts, unix_ts + Duration::from_secs(2), &field_ids);
+    assert_eq!(aggregates[0].samples, 3);
+    assert_eq!(aggregates[0].avg(), Some(4.0));
+    assert_eq!(aggregates[0].min, Some(2.0));
+    assert_eq!(aggregates[0].max, Some(6.0));
+    assert_eq!(aggregates[0].sum, 12.0);
+    // Not numeric
+    assert_eq!(aggregates[1].samples, 0);
+
+    let mut report = Vec::new();
+    analyze::write_aggregates(&mut report, &field_ids, &aggregates)
+        .expect("Failed to write aggregates");
+    let report = String::from_utf8(report).expect("Report is not UTF-8");
+    assert!(
+        report.contains(
+            "avg 4.00  min 2.00  max 6.00  sum 12.00  (3 samples)  system.stat.running_processes"
+        ),
+        "{}",
+        report
+    );
+    assert!(report.contains("no values  system.hostname"), "{}", report);
+}
+
 #[cfg_attr(not(feature = "vmtest"), test)]
 fn disable_io_stat() {
     let logger = get_logger();
@@ -216,6 +289,26 @@ fn disable_disk_stat() {
     assert!(sample.system.disks.is_empty());
 }
 
+#[cfg_attr(not(feature = "vmtest"), test)]
+fn missing_cgroup_root() {
+    let logger = get_logger();
+    let sample = Collector::new(
+        logger.clone(),
+        CollectorOptions {
+            cgroup_root: PathBuf::from("/nonexistent"),
+            ..Default::default()
+        },
+    )
+    .collect_sample()
+    .expect("failed to collect sample");
+    assert_eq!(sample.cgroup, Default::default());
+    assert_eq!(
+        sample.unavailable_sources.get(&DataSource::Cgroup),
+        Some(&"/nonexistent not found".to_owned())
+    );
+    assert!(!sample.processes.is_empty());
+}
+
 #[cfg_attr(not(feature = "vmtest"), test)]
 /// For cgroup io stat that's empty, make sure we report zero's instead of None
 fn default_cgroup_io_model() {
@@ -326,8 +419,9 @@ fn calculate_pressure() {
         },
         memory: cgroupfs::MemoryPressure {
             some: pressure.clone(),
-            full: pressure,
+            full: pressure.clone(),
         },
+        irq: Some(cgroupfs::IrqPressure { full: pressure }),
     });
     last_sample.cgroup.pressure = Some(cgroupfs::Pressure {
         cpu: cgroupfs::CpuPressure {
@@ -342,6 +436,7 @@ fn calculate_pressure() {
             some: last_pressure.clone(),
             full: last_pressure,
         },
+        irq: None,
     });
     // Measure as 5s, which could happen if last sample took too long to record
     let model = Model::new(
@@ -359,6 +454,8 @@ fn calculate_pressure() {
             io_full_pct: Some(90.0),
             memory_some_pct: Some(90.0),
             memory_full_pct: Some(90.0),
+            irq_full_pct: Some(90.0),
+            memory_trigger_events: None,
         })
     );
 }
@@ -376,3 +473,363 @@ fn testing_fold_string() {
         "d/...aaaaa"
     );
 }
+
+#[cfg_attr(not(feature = "vmtest"), test)]
+fn analyze_notable_events() {
+    let pressure = |avg10| {
+        let metrics = cgroupfs::PressureMetrics {
+            avg10: Some(avg10),
+            avg60: Some(avg10),
+            avg300: Some(avg10),
+            total: Some(0),
+        };
+        Some(cgroupfs::Pressure {
+            cpu: cgroupfs::CpuPressure {
+                some: metrics.clone(),
+                full: Some(metrics.clone()),
+            },
+            io: cgroupfs::IoPressure {
+                some: metrics.clone(),
+                full: metrics.clone(),
+            },
+            memory: cgroupfs::MemoryPressure {
+                some: metrics.clone(),
+                full: metrics,
+            },
+            irq: None,
+        })
+    };
+    // (memory GiB, local oom kills, throttled seconds, cgroup pressure, root io pressure)
+    let points = [
+        (1, 0, 0, 0.0, 0.0),
+        (1, 0, 0, 5.0, 30.0),
+        (2, 1, 2_500_000, 40.0, 0.0),
+        (3, 1, 5_000_000, 5.0, 0.0),
+    ];
+    let begin = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    let interval = Duration::from_secs(5);
+    let mut analyzer = Analyzer::new();
+    let mut last: Option<(Sample, Model)> = None;
+    for (i, (memory_gb, oom_kill, throttled_usec, cgroup_pressure, io_pressure)) in
+        points.into_iter().enumerate()
+    {
+        let mut sample: Sample = Default::default();
+        sample.system.hostname = "host1".to_owned();
+        sample.system.vmstat.oom_kill = Some(5 + oom_kill);
+        sample.cgroup.pressure = pressure(io_pressure);
+        let child = model::CgroupSample {
+            memory_current: Some(memory_gb << 30),
+            memory_events_local: Some(cgroupfs::MemoryEventsLocal {
+                oom_kill: Some(oom_kill),
+                ..Default::default()
+            }),
+            cpu_stat: Some(cgroupfs::CpuStat {
+                throttled_usec: Some(throttled_usec),
+                ..Default::default()
+            }),
+            pressure: pressure(cgroup_pressure),
+            ..Default::default()
+        };
+        sample.cgroup.children = Some(BTreeMap::from([("workload.slice".to_owned(), child)]));
+
+        let model = Model::new(
+            begin + interval * i as u32,
+            &sample,
+            last.as_ref()
+                .map(|(last_sample, _)| (last_sample, interval)),
+        );
+        analyzer.observe(&model, last.as_ref().map(|(_, last_model)| last_model));
+        last = Some((sample, model));
+    }
+
+    let mut report = Vec::new();
+    analyzer
+        .report(&mut report, 5)
+        .expect("Failed to write report");
+    let report = String::from_utf8(report).expect("Report is not UTF-8");
+    assert!(report.contains("Analysis of host1"), "{}", report);
+    assert!(report.contains("(4 samples)"), "{}", report);
+    assert!(report.contains(" 40.00%  at "), "{}", report);
+    assert!(report.contains("  1 system wide"), "{}", report);
+    assert!(report.contains("  1 killed at "), "{}", report);
+    assert!(report.contains("+2 GB"), "{}", report);
+    assert!(
+        report.contains("(5s), peak throttled 50.00%  /workload.slice"),
+        "{}",
+        report
+    );
+    assert!(report.contains("(0s), peak pressure 30.00%"), "{}", report);
+}
+
+#[test]
+fn profile_summary() {
+    let sample = |memory_mb: i64, usage_usec| model::CgroupSample {
+        memory_current: Some(memory_mb << 20),
+        cpu_stat: Some(cgroupfs::CpuStat {
+            usage_usec: Some(usage_usec),
+            user_usec: Some(usage_usec * 3 / 4),
+            system_usec: Some(usage_usec / 4),
+            ..Default::default()
+        }),
+        io_stat: Some(BTreeMap::from([
+            (
+                "8:0".to_owned(),
+                cgroupfs::IoStat {
+                    rbytes: Some(1 << 20),
+                    wbytes: Some(2 << 20),
+                    ..Default::default()
+                },
+            ),
+            (
+                "8:16".to_owned(),
+                cgroupfs::IoStat {
+                    rbytes: Some(1 << 20),
+                    ..Default::default()
+                },
+            ),
+        ])),
+        ..Default::default()
+    };
+    let mut summary = ProfileSummary::new();
+    summary.observe(&sample(10, 0));
+    summary.observe(&sample(300, 1_000_000));
+    summary.observe(&sample(20, 2_000_000));
+
+    let command = vec!["make".to_owned(), "-j8".to_owned()];
+    let status = std::process::Command::new("true")
+        .status()
+        .expect("Failed to run true");
+    let mut report = Vec::new();
+    summary
+        .report(&mut report, &command, status, Duration::from_secs(3))
+        .expect("Failed to write report");
+    let report = String::from_utf8(report).expect("Report is not UTF-8");
+    assert!(report.contains("Profile of: make -j8"), "{}", report);
+    assert!(report.contains("3.00s (3 samples)"), "{}", report);
+    assert!(
+        report.contains("2.00s (user 1.50s, system 0.50s)"),
+        "{}",
+        report
+    );
+    assert!(report.contains("Peak memory:  300 MB"), "{}", report);
+    assert!(report.contains("read 2 MB, written 2 MB"), "{}", report);
+
+    // The kernel tracked peak wins over the sampled one
+    summary.set_memory_peak(Some(400 << 20));
+    let mut report = Vec::new();
+    summary
+        .report(&mut report, &command, status, Duration::from_secs(3))
+        .expect("Failed to write report");
+    let report = String::from_utf8(report).expect("Report is not UTF-8");
+    assert!(report.contains("Peak memory:  400 MB"), "{}", report);
+}
+
+#[test]
+fn control_socket_roundtrip() {
+    let dir = TempDir::with_prefix("below_control_test.").expect("tempdir failed");
+    let path = dir.path().join("control.sock");
+    let calls = control::listen(get_logger(), &path).expect("Failed to listen");
+    let recorder = std::thread::spawn(move || {
+        let (request, response_send) = calls.recv().expect("Failed to receive request");
+        assert_eq!(request, control::Request::SetInterval { interval_s: 10 });
+        response_send
+            .send(control::Response::Result(
+                serde_json::json!({"interval_s": 10}),
+            ))
+            .expect("Failed to send response");
+    });
+
+    let response = control::call(&path, &control::Request::SetInterval { interval_s: 10 })
+        .expect("Failed to call recorder");
+    assert_eq!(
+        response,
+        control::Response::Result(serde_json::json!({"interval_s": 10}))
+    );
+    recorder.join().expect("Recorder panicked");
+
+    // Methods without params can leave them out
+    assert_eq!(
+        serde_json::from_str::<control::Request>(r#"{"method":"flush"}"#)
+            .expect("Failed to parse request"),
+        control::Request::Flush
+    );
+    // The socket of a running recorder is not taken over
+    assert!(control::listen(get_logger(), &path).is_err());
+}
+
+#[test]
+fn stack_symbolization() {
+    let kallsyms = stacks::parse_kallsyms(
+        "ffffffff81000000 T _stext\n\
+         ffffffff81001000 t do_syscall_64\n\
+         ffffffff81002000 D some_data\n\
+         ffffffff81003000 T entry_SYSCALL_64\n",
+    );
+    let maps = stacks::parse_maps(
+        "55d0c0a00000-55d0c0a20000 r--p 00000000 fd:01 1234 /usr/bin/app\n\
+         55d0c0a20000-55d0c0b00000 r-xp 00020000 fd:01 1234 /usr/bin/app\n\
+         7f0000000000-7f0000100000 r-xp 00028000 fd:01 5678 /usr/lib64/libc.so.6\n\
+         7ffd00000000-7ffd00021000 rw-p 00000000 00:00 0 [stack]\n",
+    );
+    // Context markers from linux/perf_event.h
+    let kernel = -128i64 as u64;
+    let user = -512i64 as u64;
+    let frames = stacks::symbolize(
+        &[
+            kernel,
+            0xffffffff81001010,
+            0xffffffff81003020,
+            user,
+            0x7f0000000100,
+            0x55d0c0a20010,
+            0x1000,
+        ],
+        &kallsyms,
+        &maps,
+    );
+    assert_eq!(
+        frames,
+        vec![
+            "do_syscall_64",
+            "entry_SYSCALL_64",
+            "libc.so.6+0x28100",
+            "app+0x20010",
+            "0x1000",
+        ]
+    );
+}
+
+#[test]
+fn psi_trigger_string() {
+    // The kernel overwrites the last byte written with a NUL
+    assert_eq!(
+        psi_triggers::trigger_string(Duration::from_millis(150), Duration::from_secs(1)),
+        "some 150000 1000000\0"
+    );
+}
+
+#[test]
+fn oomd_kill_messages() {
+    assert_eq!(
+        oomd::parse_kill_message(
+            "Killed /system.slice/foo.service due to memory pressure for /system.slice being 80.00% > 50.00% for > 20s with reclaim activity"
+        ),
+        Some("/system.slice/foo.service")
+    );
+    assert_eq!(
+        oomd::parse_kill_message(
+            "oomd kill: 30.43 25.92 12.10 /workload.slice/foo.service 1073741824 ruleset:[protection against heavy workload thrashing] detectorgroup:[sustained high pressure] killer:kill_by_memory_size_or_growth v2"
+        ),
+        Some("/workload.slice/foo.service")
+    );
+    assert_eq!(
+        oomd::parse_kill_message("Considered 12 cgroups for killing, top candidates were:"),
+        None
+    );
+
+    let kill = oomd::parse_journal_entry(
+        r#"{"__REALTIME_TIMESTAMP":"1700000000123456","SYSLOG_IDENTIFIER":"systemd-oomd","MESSAGE":"Killed /user.slice/app.scope due to memory used (15000000000) / total (16000000000) and swap used (900000000) / total (1000000000) being more than 90.00%"}"#,
+    )
+    .expect("Failed to parse kill");
+    assert_eq!(kill.timestamp, 1700000000);
+    assert_eq!(kill.killer, "systemd-oomd");
+    assert_eq!(kill.cgroup, "/user.slice/app.scope");
+    assert_eq!(
+        oomd::parse_journal_entry(
+            r#"{"__REALTIME_TIMESTAMP":"1700000000123456","MESSAGE":[75,105]}"#
+        ),
+        None
+    );
+}
+
+#[test]
+fn journal_error_counts() {
+    assert_eq!(
+        journal_errors::parse_journal_entry(
+            r#"{"__CURSOR":"s=1","_SYSTEMD_CGROUP":"/system.slice/foo.service"}"#
+        ),
+        Some("/system.slice/foo.service".to_owned())
+    );
+    // Kernel messages have no cgroup
+    assert_eq!(
+        journal_errors::parse_journal_entry(r#"{"__CURSOR":"s=2"}"#),
+        None
+    );
+
+    let mut counts = model::CgroupJournalErrorMap::new();
+    journal_errors::count_message(&mut counts, "/system.slice/foo.service");
+    journal_errors::count_message(&mut counts, "/system.slice/bar.service");
+    journal_errors::count_message(&mut counts, "/");
+    assert_eq!(
+        counts,
+        BTreeMap::from([
+            ("".to_owned(), 3),
+            ("/system.slice".to_owned(), 2),
+            ("/system.slice/bar.service".to_owned(), 1),
+            ("/system.slice/foo.service".to_owned(), 1),
+        ])
+    );
+}
+
+#[test]
+fn perf_count_scaling() {
+    // Counted the whole time
+    assert_eq!(perfcounters::scale_count(1000, 50, 50), 1000);
+    // Multiplexed in for a quarter of the time
+    assert_eq!(perfcounters::scale_count(1000, 200, 50), 4000);
+    // Never scheduled on the PMU
+    assert_eq!(perfcounters::scale_count(0, 200, 0), 0);
+    // No overflow on long running counters
+    assert_eq!(
+        perfcounters::scale_count(u64::MAX / 2, u64::MAX, u64::MAX),
+        u64::MAX / 2
+    );
+}
+
+#[test]
+fn taskstats_exited_tasks() {
+    let mut stats = vec![0u8; 352];
+    stats[0..2].copy_from_slice(&10u16.to_ne_bytes());
+    stats[80..84].copy_from_slice(b"make");
+    stats[128..132].copy_from_slice(&4242u32.to_ne_bytes());
+    stats[132..136].copy_from_slice(&1u32.to_ne_bytes());
+    stats[144..152].copy_from_slice(&3_500_000u64.to_ne_bytes());
+    stats[152..160].copy_from_slice(&2000u64.to_ne_bytes());
+    stats[160..168].copy_from_slice(&1000u64.to_ne_bytes());
+    stats[168..176].copy_from_slice(&300u64.to_ne_bytes());
+    stats[176..184].copy_from_slice(&3u64.to_ne_bytes());
+    stats[248..256].copy_from_slice(&4096u64.to_ne_bytes());
+    stats[256..264].copy_from_slice(&8192u64.to_ne_bytes());
+
+    let attribute = |ty: u16, payload: &[u8]| {
+        let mut attribute = ((4 + payload.len()) as u16).to_ne_bytes().to_vec();
+        attribute.extend_from_slice(&ty.to_ne_bytes());
+        attribute.extend_from_slice(payload);
+        attribute.resize((attribute.len() + 3) & !3, 0);
+        attribute
+    };
+    // TASKSTATS_TYPE_AGGR_PID of TASKSTATS_TYPE_PID and TASKSTATS_TYPE_STATS
+    let mut nested = attribute(1, &4242u32.to_ne_bytes());
+    nested.extend(attribute(3, &stats));
+    // Generic netlink header of TASKSTATS_CMD_NEW
+    let mut payload = vec![2, 1, 0, 0];
+    payload.extend(attribute(4 | 0x8000, &nested));
+
+    let exited = taskstats::parse_message(&payload);
+    assert_eq!(exited.len(), 1);
+    let pidinfo = &exited[0];
+    assert_eq!(pidinfo.stat.pid, Some(4242));
+    assert_eq!(pidinfo.stat.ppid, Some(1));
+    assert_eq!(pidinfo.stat.comm.as_deref(), Some("make"));
+    assert_eq!(pidinfo.stat.state, Some(procfs::PidState::Dead));
+    assert_eq!(pidinfo.stat.running_secs, Some(3));
+    assert_eq!(pidinfo.stat.user_usecs, Some(2000));
+    assert_eq!(pidinfo.stat.system_usecs, Some(1000));
+    assert_eq!(pidinfo.stat.minflt, Some(300));
+    assert_eq!(pidinfo.stat.majflt, Some(3));
+    assert_eq!(pidinfo.io.rbytes, Some(4096));
+    assert_eq!(pidinfo.io.wbytes, Some(8192));
+
+    // Too old a kernel
+    assert!(taskstats::parse_taskstats(&stats[..200]).is_none());
+}
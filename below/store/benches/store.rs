@@ -0,0 +1,125 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Store throughput with samples of synthetic hosts of a configurable
+//! number of processes, see `model::synthetic::bench_sizes`.
+
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use below_store::cursor::Cursor;
+use below_store::ChunkSizePo2;
+use below_store::CompressionMode;
+use below_store::DataFrame;
+use below_store::Direction;
+use below_store::StoreWriter;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::Throughput;
+use model::synthetic;
+
+/// Number of samples read back in each iteration of the read benchmark
+const READ_SAMPLES: u64 = 16;
+
+fn compression_modes() -> Vec<(&'static str, CompressionMode)> {
+    vec![
+        ("zstd", CompressionMode::Zstd),
+        (
+            "zstd_dict16",
+            CompressionMode::ZstdDictionary(ChunkSizePo2(4)),
+        ),
+        ("zstd_dict_auto", CompressionMode::ZstdDictionaryAuto),
+    ]
+}
+
+fn frame(size: usize, seq: u64) -> DataFrame {
+    DataFrame {
+        sample: synthetic::synthetic_sample(size, std::cmp::max(size / 10, 1), seq),
+        aggregate: None,
+    }
+}
+
+fn logger() -> slog::Logger {
+    slog::Logger::root(slog::Discard, slog::o!())
+}
+
+fn bench_write(c: &mut Criterion) {
+    let mut group = c.benchmark_group("store_write");
+    for (mode_name, mode) in compression_modes() {
+        for size in synthetic::bench_sizes() {
+            let frame = frame(size, 1);
+            group.throughput(Throughput::Elements(1));
+            group.bench_function(BenchmarkId::new(mode_name, size), |b| {
+                // A new store for every batch, so that the store does not
+                // grow without bounds
+                b.iter_custom(|iters| {
+                    let dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+                    let mut writer =
+                        StoreWriter::new(logger(), dir.path(), mode, below_store::Format::Cbor)
+                            .expect("Failed to create store");
+                    let begin = SystemTime::now();
+                    let started = Instant::now();
+                    for i in 0..iters {
+                        writer
+                            .put(begin + Duration::from_secs(i), &frame)
+                            .expect("Failed to store sample");
+                    }
+                    started.elapsed()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("store_read");
+    for (mode_name, mode) in compression_modes() {
+        for size in synthetic::bench_sizes() {
+            let dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+            let mut writer =
+                StoreWriter::new(logger(), dir.path(), mode, below_store::Format::Cbor)
+                    .expect("Failed to create store");
+            let begin = SystemTime::now();
+            for i in 0..READ_SAMPLES {
+                writer
+                    .put(begin + Duration::from_secs(i), &frame(size, i))
+                    .expect("Failed to store sample");
+            }
+            group.throughput(Throughput::Elements(READ_SAMPLES));
+            group.bench_function(BenchmarkId::new(mode_name, size), |b| {
+                b.iter(|| {
+                    let mut cursor =
+                        below_store::cursor::StoreCursor::new(logger(), dir.path().to_owned());
+                    let mut samples = 0;
+                    while cursor
+                        .next(Direction::Forward)
+                        .expect("Failed to read sample")
+                        .is_some()
+                    {
+                        samples += 1;
+                    }
+                    assert_eq!(samples, READ_SAMPLES);
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_write, bench_read);
+criterion_main!(benches);
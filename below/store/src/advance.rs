@@ -18,7 +18,11 @@ use std::time::SystemTime;
 
 use anyhow::Result;
 use common::util;
+use model::Field;
 use model::Model;
+use model::ModelFieldId;
+use model::ModelParts;
+use model::Queriable;
 use slog::error;
 
 use crate::DataFrame;
@@ -49,28 +53,37 @@ impl<SampleType> SamplePackage<SampleType> {
         newer_sample: SampleType,
         newer_timestamp: SystemTime,
     ) -> Self {
-        Self {
-            older_sample,
-            newer_sample,
-            timestamp: newer_timestamp,
-            duration: newer_timestamp
-                .duration_since(older_timestamp)
-                .expect("time went backwards"),
+        // Stores recorded before clock steps were detected may go backwards,
+        // in which case there is nothing to compute rates against
+        match newer_timestamp.duration_since(older_timestamp) {
+            Ok(duration) => Self {
+                older_sample,
+                newer_sample,
+                timestamp: newer_timestamp,
+                duration,
+            },
+            Err(_) => Self {
+                older_sample: None,
+                newer_sample,
+                timestamp: newer_timestamp,
+                duration: Duration::from_secs(0),
+            },
         }
     }
 }
 
 impl SamplePackage<DataFrame> {
-    pub fn to_model(&self) -> Model {
+    pub fn to_model(&self, parts: ModelParts) -> Model {
         // When older_sample is None, we don't provide older_sample to the model
         if let Some(older_sample) = self.older_sample.as_ref() {
-            Model::new(
+            Model::new_with_parts(
                 self.timestamp,
                 &self.newer_sample.sample,
                 Some((&older_sample.sample, self.duration)),
+                parts,
             )
         } else {
-            Model::new(self.timestamp, &self.newer_sample.sample, None)
+            Model::new_with_parts(self.timestamp, &self.newer_sample.sample, None, parts)
         }
     }
 }
@@ -82,8 +95,16 @@ trait ModelStore: Store {
     type ModelType;
 
     /// Defines how should we generate a ModelType from a SamplePackage.
-    fn to_model(&self, sample_package: &SamplePackage<Self::SampleType>)
-    -> Option<Self::ModelType>;
+    /// Only the sub-models selected by parts need to be materialized.
+    fn to_model(
+        &self,
+        sample_package: &SamplePackage<Self::SampleType>,
+        parts: ModelParts,
+    ) -> Option<Self::ModelType>;
+
+    /// Hint that only the sub-models in parts will be materialized, so the
+    /// store may skip decoding sample fields that are not needed for them.
+    fn set_model_parts(&mut self, _parts: ModelParts) {}
 
     /// Syntactic sugar to extract the value from the store return and log on error
     fn extract_sample_and_log(
@@ -128,11 +149,10 @@ trait ModelStore: Store {
             Direction::Reverse,
             logger,
         ) {
-            res_package.older_sample = Some(older_sample);
-            res_package.duration = res_package
-                .timestamp
-                .duration_since(older_ts)
-                .expect("time went backwards");
+            if let Ok(duration) = res_package.timestamp.duration_since(older_ts) {
+                res_package.older_sample = Some(older_sample);
+                res_package.duration = duration;
+            }
         }
 
         Some(res_package)
@@ -142,16 +162,76 @@ trait ModelStore: Store {
 impl ModelStore for LocalStore {
     type ModelType = Model;
 
-    fn to_model(&self, sample_package: &SamplePackage<DataFrame>) -> Option<Model> {
-        Some(sample_package.to_model())
+    fn to_model(
+        &self,
+        sample_package: &SamplePackage<DataFrame>,
+        parts: ModelParts,
+    ) -> Option<Model> {
+        Some(sample_package.to_model(parts))
+    }
+
+    fn set_model_parts(&mut self, parts: ModelParts) {
+        LocalStore::set_model_parts(self, parts);
     }
 }
 
 impl ModelStore for RemoteStore {
     type ModelType = Model;
 
-    fn to_model(&self, sample_package: &SamplePackage<DataFrame>) -> Option<Model> {
-        Some(sample_package.to_model())
+    fn to_model(
+        &self,
+        sample_package: &SamplePackage<DataFrame>,
+        parts: ModelParts,
+    ) -> Option<Model> {
+        Some(sample_package.to_model(parts))
+    }
+}
+
+/// Sum, average, min and max of a field over the samples of a time range.
+/// Samples in which the field is missing or not numeric are not counted.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct FieldAggregate {
+    pub samples: u64,
+    pub sum: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl FieldAggregate {
+    pub fn observe(&mut self, value: f64) {
+        self.samples += 1;
+        self.sum += value;
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    pub fn avg(&self) -> Option<f64> {
+        (self.samples > 0).then(|| self.sum / self.samples as f64)
+    }
+}
+
+fn numeric(field: Field) -> Option<f64> {
+    match field {
+        Field::U32(v) => Some(v as f64),
+        Field::U64(v) => Some(v as f64),
+        Field::I32(v) => Some(v as f64),
+        Field::I64(v) => Some(v as f64),
+        Field::F32(v) => Some(v as f64),
+        Field::F64(v) => Some(v),
+        _ => None,
+    }
+}
+
+/// Add the sub-model `field_id` is part of to `parts`
+fn add_model_part(parts: &mut ModelParts, field_id: &ModelFieldId) {
+    match field_id {
+        ModelFieldId::System(_) => parts.system = true,
+        ModelFieldId::Cgroup(_) => parts.cgroup = true,
+        ModelFieldId::Process(_) => parts.process = true,
+        ModelFieldId::Network(_) => parts.network = true,
+        ModelFieldId::Gpu(_) => parts.gpu = true,
+        ModelFieldId::Resctrl(_) => parts.resctrl = true,
+        ModelFieldId::Tc(_) => parts.tc = true,
     }
 }
 
@@ -172,9 +252,24 @@ pub struct Advance<FrameType, MType> {
     // the timestamp we want to move.
     target_timestamp: SystemTime,
     current_direction: Direction,
+    // Sub-models to materialize for each generated model
+    model_parts: ModelParts,
+    // Timestamp of the last sample shown before live mode was paused.
+    // Samples after it were skipped and can be stepped through on resume.
+    skipped_since: Option<SystemTime>,
 }
 
 impl<FrameType, ModelType> Advance<FrameType, ModelType> {
+    /// Only materialize the given sub-models from now on. Useful when the
+    /// consumer only looks at part of the model, e.g. dumping cgroups does
+    /// not need the process map. Stores that support partial reads also
+    /// skip decoding the unneeded sample fields, so this should be called
+    /// before any sample is cached.
+    pub fn set_model_parts(&mut self, parts: ModelParts) {
+        self.store.set_model_parts(parts);
+        self.model_parts = parts;
+    }
+
     /// Initialize the current advance module.
     // Base on the target_timestamp, we will go forward to find the first
     // available sample. Once we find a sample, we will update the
@@ -261,7 +356,7 @@ impl<FrameType, ModelType> Advance<FrameType, ModelType> {
                     next_sample,
                     next_timestamp,
                 );
-                let model = self.store.to_model(&sample_package);
+                let model = self.store.to_model(&sample_package, self.model_parts);
                 self.cached_sample = Some(sample_package.newer_sample);
                 self.target_timestamp = next_timestamp;
                 model
@@ -275,7 +370,7 @@ impl<FrameType, ModelType> Advance<FrameType, ModelType> {
                     ),
                     self.target_timestamp,
                 );
-                let model = self.store.to_model(&sample_package);
+                let model = self.store.to_model(&sample_package, self.model_parts);
                 self.cached_sample = sample_package.older_sample;
                 self.target_timestamp = next_timestamp;
                 model
@@ -304,7 +399,7 @@ impl<FrameType, ModelType> Advance<FrameType, ModelType> {
         }
 
         let sample_package = sample_package?;
-        let model = self.store.to_model(&sample_package);
+        let model = self.store.to_model(&sample_package, self.model_parts);
         // We will always set direction to Forward after jump to ease of caching
         self.current_direction = Direction::Forward;
         self.cached_sample = Some(sample_package.newer_sample);
@@ -333,6 +428,65 @@ impl<FrameType, ModelType> Advance<FrameType, ModelType> {
         self.jump_sample_to(self.target_timestamp - gap)
     }
 
+    /// Mark the current sample as the start of a skipped range, e.g. when
+    /// live mode is paused. Samples after it can later be stepped through
+    /// with `catch_up`.
+    pub fn mark_skipped_since(&mut self) {
+        self.skipped_since = Some(self.target_timestamp);
+    }
+
+    pub fn skipped_since(&self) -> Option<SystemTime> {
+        self.skipped_since
+    }
+
+    /// Forget the skipped range, e.g. when jumping straight to the latest
+    /// sample instead of catching up.
+    pub fn clear_skipped(&mut self) {
+        self.skipped_since = None;
+    }
+
+    /// Jump back to the start of the skipped range. Returns None if there is
+    /// no skipped range or its start is no longer available.
+    pub fn start_catch_up(&mut self) -> Option<ModelType> {
+        let skipped_since = self.skipped_since?;
+        self.jump_sample_to(skipped_since)
+    }
+
+    /// Step forward through the skipped range by up to `max_samples`
+    /// samples. Returns the model of the last sample stepped to and whether
+    /// the latest sample was reached, in which case the skipped range is
+    /// cleared.
+    pub fn catch_up(&mut self, max_samples: usize) -> (Option<ModelType>, bool) {
+        let mut model = None;
+        for _ in 0..max_samples {
+            match self.advance(Direction::Forward) {
+                Some(next) => model = Some(next),
+                None => {
+                    self.skipped_since = None;
+                    return (model, true);
+                }
+            }
+        }
+        (model, false)
+    }
+
+    /// Fraction of the skipped range caught up on so far, from 0 to 1. The
+    /// range keeps growing until caught up, as new samples are recorded.
+    pub fn catch_up_progress(&self) -> Option<f64> {
+        let skipped_since = self.skipped_since?;
+        let total = SystemTime::now()
+            .duration_since(skipped_since)
+            .unwrap_or_default();
+        let done = self
+            .target_timestamp
+            .duration_since(skipped_since)
+            .unwrap_or_default();
+        if total.is_zero() {
+            return Some(1.0);
+        }
+        Some((done.as_secs_f64() / total.as_secs_f64()).min(1.0))
+    }
+
     // Convenience function will be used by dump and scuba dump
     pub fn get_next_ts(&self) -> SystemTime {
         // timestamp for initial advance if initialize didn't setup cached_sample
@@ -345,6 +499,66 @@ impl<FrameType, ModelType> Advance<FrameType, ModelType> {
             Direction::Reverse => self.target_timestamp - Duration::from_secs(1),
         }
     }
+
+    /// Aggregate the values `values` takes from each model from `time_begin`
+    /// to `time_end`, one per field in the same order for every model.
+    /// Models are dropped as soon as their values are taken, so long ranges
+    /// take no more memory than short ones. Leaves the advance at the last
+    /// model of the range.
+    pub fn aggregate_by<F>(
+        &mut self,
+        time_begin: SystemTime,
+        time_end: SystemTime,
+        mut values: F,
+    ) -> Vec<FieldAggregate>
+    where
+        F: FnMut(&ModelType) -> Vec<Option<f64>>,
+    {
+        let mut aggregates: Vec<FieldAggregate> = Vec::new();
+        // Falls back to the latest sample if the range is in the future
+        let mut model = self
+            .jump_sample_to(time_begin)
+            .filter(|_| self.target_timestamp >= time_begin);
+        while let Some(current) = model.filter(|_| self.target_timestamp <= time_end) {
+            let values = values(&current);
+            if aggregates.len() < values.len() {
+                aggregates.resize_with(values.len(), Default::default);
+            }
+            for (aggregate, value) in aggregates.iter_mut().zip(values) {
+                if let Some(value) = value {
+                    aggregate.observe(value);
+                }
+            }
+            model = self.advance(Direction::Forward);
+        }
+        aggregates
+    }
+}
+
+impl<FrameType> Advance<FrameType, Model> {
+    /// Aggregates of `field_ids` over the samples from `time_begin` to
+    /// `time_end`, in the same order. Only the sub-models of the fields are
+    /// materialized on top of those already set with `set_model_parts`.
+    pub fn aggregate(
+        &mut self,
+        time_begin: SystemTime,
+        time_end: SystemTime,
+        field_ids: &[ModelFieldId],
+    ) -> Vec<FieldAggregate> {
+        let mut parts = self.model_parts;
+        for field_id in field_ids {
+            add_model_part(&mut parts, field_id);
+        }
+        self.set_model_parts(parts);
+        let mut aggregates = self.aggregate_by(time_begin, time_end, |model| {
+            field_ids
+                .iter()
+                .map(|field_id| model.query(field_id).and_then(numeric))
+                .collect()
+        });
+        aggregates.resize_with(field_ids.len(), Default::default);
+        aggregates
+    }
 }
 
 /// Construct a new Advance object with local store
@@ -360,6 +574,8 @@ pub fn new_advance_local(
         cached_sample: None,
         target_timestamp: timestamp,
         current_direction: Direction::Forward,
+        model_parts: ModelParts::ALL,
+        skipped_since: None,
     }
 }
 
@@ -380,6 +596,8 @@ pub fn new_advance_remote(
         cached_sample: None,
         target_timestamp: timestamp,
         current_direction: Direction::Forward,
+        model_parts: ModelParts::ALL,
+        skipped_since: None,
     })
 }
 
@@ -453,7 +671,11 @@ mod tests {
     impl ModelStore for FakeStore {
         type ModelType = String;
 
-        fn to_model(&self, sample_package: &SamplePackage<u64>) -> Option<String> {
+        fn to_model(
+            &self,
+            sample_package: &SamplePackage<u64>,
+            _parts: ModelParts,
+        ) -> Option<String> {
             // When duration is 0, we don't provide older_sample to the model
             if let Some(older_sample) = sample_package.older_sample.as_ref() {
                 Some(format!(
@@ -480,6 +702,8 @@ mod tests {
             cached_sample: None,
             target_timestamp: util::get_system_time(timestamp),
             current_direction: Direction::Forward,
+            model_parts: ModelParts::ALL,
+            skipped_since: None,
         }
     }
 
@@ -541,7 +765,7 @@ mod tests {
                     store.get_adjacent_sample_at_timestamp(timestamp, $direction, &get_logger());
                 assert_eq!(
                     store
-                        .to_model(&res.expect("Failed to get sample"))
+                        .to_model(&res.expect("Failed to get sample"), ModelParts::ALL)
                         .expect("Failed to convert sample to model"),
                     $expected_sample
                 );
@@ -812,4 +1036,79 @@ mod tests {
         advance.advance(Direction::Reverse);
         assert_eq!(advance.get_next_ts(), util::get_system_time(2));
     }
+
+    #[test]
+    fn advance_test_catch_up() {
+        let mut advance = get_advance_with_fake_store(0);
+        advance.initialize();
+        // Nothing was skipped
+        assert_eq!(advance.start_catch_up(), None);
+        assert_eq!(advance.catch_up_progress(), None);
+
+        // Paused while showing 10
+        advance.jump_sample_to(util::get_system_time(10));
+        advance.mark_skipped_since();
+        advance.jump_sample_to(util::get_system_time(3));
+        assert_eq!(advance.skipped_since(), Some(util::get_system_time(10)));
+
+        assert_eq!(advance.start_catch_up(), Some("3_10_10_7".to_string()));
+        assert_eq!(
+            advance.catch_up(1),
+            (Some("10_20_20_10".to_string()), false)
+        );
+        assert_eq!(advance.catch_up(5), (Some("20_50_50_30".to_string()), true));
+        assert_eq!(advance.skipped_since(), None);
+
+        advance.mark_skipped_since();
+        advance.clear_skipped();
+        assert_eq!(advance.start_catch_up(), None);
+    }
+
+    #[test]
+    fn advance_test_aggregate_by() {
+        // Samples: [3, 10, 20, 50], aggregating the duration of each model
+        let duration = |model: &String| {
+            vec![
+                model.rsplit('_').next().and_then(|d| d.parse().ok()),
+                // Never there
+                None,
+            ]
+        };
+        let mut advance = get_advance_with_fake_store(0);
+        advance.initialize();
+
+        let aggregates = advance.aggregate_by(
+            util::get_system_time(10),
+            util::get_system_time(50),
+            duration,
+        );
+        assert_eq!(
+            aggregates[0],
+            FieldAggregate {
+                samples: 3,
+                sum: 47.0,
+                min: Some(7.0),
+                max: Some(30.0),
+            }
+        );
+        assert_eq!(aggregates[1], FieldAggregate::default());
+        assert_eq!(aggregates[1].avg(), None);
+
+        // Samples after the end are left out
+        let aggregates = advance.aggregate_by(
+            util::get_system_time(5),
+            util::get_system_time(30),
+            duration,
+        );
+        assert_eq!(aggregates[0].samples, 2);
+        assert_eq!(aggregates[0].avg(), Some(8.5));
+
+        // Nothing in the future
+        let aggregates = advance.aggregate_by(
+            util::get_system_time(60),
+            util::get_system_time(100),
+            duration,
+        );
+        assert!(aggregates.is_empty());
+    }
 }
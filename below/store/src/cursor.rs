@@ -16,18 +16,23 @@ use std::cell::RefCell;
 use std::fs::File;
 use std::io::ErrorKind;
 use std::path::PathBuf;
+use std::rc::Rc;
 use std::time::SystemTime;
 
 use anyhow::anyhow;
 use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
+use common::util::timestamp_to_datetime;
 use memmap2::Mmap;
 use memmap2::MmapOptions;
+use model::ModelParts;
+use procfs::PidMap;
 use slog::warn;
 use slog::Logger;
 
 use crate::compression::Decompressor;
+use crate::decode_committed_len;
 use crate::deserialize_frame;
 use crate::get_index_files;
 use crate::Crc32;
@@ -37,8 +42,9 @@ use crate::Format;
 use crate::IndexEntry;
 use crate::IndexEntryFlags;
 use crate::SerializedFrame;
-use crate::INDEX_ENTRY_SIZE;
+use crate::StoreMetadata;
 use crate::SHARD_TIME;
+use crate::INDEX_ENTRY_SIZE;
 
 /// A read-only Iterator that can move back and forth.
 pub trait Cursor {
@@ -142,6 +148,13 @@ pub trait KeyedCursor<Key: std::cmp::Ord>: Cursor {
     }
 }
 
+/// Shard and dict_index_offset of a chunk
+type ChunkKey = (u64, usize);
+
+/// Process map of the key frame of a chunk, which process deltas in the
+/// chunk are relative to
+type ProcessDeltaBase = (ChunkKey, Rc<PidMap>);
+
 /// For read-only access to a store. Similar to an iterator, but support moving
 /// back and forth.
 pub struct StoreCursor {
@@ -150,6 +163,8 @@ pub struct StoreCursor {
     path: PathBuf,
     // Current shard this cursor points to.
     shard: Option<u64>,
+    // Seconds covered by each shard, from the store metadata.
+    shard_time: u64,
     // Mmap of the index and data files of the current shard. Could be None if
     // the current shard does not exist.
     index_mmap: Option<Mmap>,
@@ -160,7 +175,13 @@ pub struct StoreCursor {
     index_offset: Option<usize>,
     // Used for extracting compressed frames. If dictionary is used, it's also
     // cached, along with the shard and dict_index_offset that identify it.
-    decompressor: RefCell<Option<Decompressor<(u64, usize)>>>,
+    decompressor: RefCell<Option<Decompressor<ChunkKey>>>,
+    // Sub-models the returned frames are used for. Formats that support
+    // partial reads skip decoding the other fields.
+    model_parts: ModelParts,
+    // Process map of the last key frame used as a process delta base, along
+    // with the shard and index offset that identify it.
+    process_delta_base: RefCell<Option<ProcessDeltaBase>>,
 }
 
 enum StoreFile {
@@ -168,20 +189,45 @@ enum StoreFile {
     Data,
 }
 
+/// Whether `e` was caused by a file or directory that does not exist.
+fn is_not_found(e: &anyhow::Error) -> bool {
+    e.chain().any(|cause| {
+        cause
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == ErrorKind::NotFound)
+    })
+}
+
 impl StoreCursor {
     /// Create a new cursor with uninitialized shard.
     pub fn new(logger: Logger, path: PathBuf) -> Self {
+        let shard_time = match StoreMetadata::load(&path) {
+            Ok(metadata) => metadata.shard_time,
+            Err(e) => {
+                warn!(logger, "{:#}, assuming daily shards", e);
+                SHARD_TIME
+            }
+        };
         Self {
             logger,
             path,
             shard: None,
+            shard_time,
             index_mmap: None,
             data_mmap: None,
             index_offset: None,
             decompressor: RefCell::new(None),
+            model_parts: ModelParts::ALL,
+            process_delta_base: RefCell::new(None),
         }
     }
 
+    /// Only decode the fields needed to build the given sub-models. Frames
+    /// stored in formats without partial read support are fully decoded.
+    pub fn set_model_parts(&mut self, parts: ModelParts) {
+        self.model_parts = parts;
+    }
+
     /// Get the mmap of a related store file based on the given shard. If the
     /// file is not found or empty, None will be returned.
     fn get_mmap(&self, file_type: StoreFile, shard: u64) -> Result<Option<Mmap>> {
@@ -192,11 +238,14 @@ impl StoreCursor {
         let path = self.path.join(format!("{}_{:011}", prefix, shard));
         let file = match File::open(&path) {
             Ok(f) => f,
+            // Retention may remove the shard at any time, including right
+            // after the store directory was listed. Treat it as exhausted.
             Err(e) if e.kind() == ErrorKind::NotFound => {
                 warn!(
                     self.logger,
-                    "Expected file does not exist: {}",
-                    path.display()
+                    "Expected file does not exist: {}, skipping {}",
+                    path.display(),
+                    self.shard_range(shard),
                 );
                 return Ok(None);
             }
@@ -210,6 +259,10 @@ impl StoreCursor {
             .with_context(|| format!("Failed to get metadata of file: {}", path.display()))?
             .len() as usize;
         if let StoreFile::Index = file_type {
+            // Entries past the committed length may still be being written
+            if let Some(committed_len) = self.get_committed_len(shard) {
+                len = len.min(committed_len as usize);
+            }
             len = len - len % INDEX_ENTRY_SIZE;
         }
         if len == 0 {
@@ -221,7 +274,8 @@ impl StoreCursor {
         // our case, we only have one background process (below record) doing
         // append-only writes to both index and data files. We also use CRC to
         // verify file content. As long as we do read-only operations here, this
-        // should be Ok.
+        // should be Ok. Retention only unlinks files, which keeps existing
+        // mappings valid until they are dropped.
         unsafe {
             Some(
                 MmapOptions::new()
@@ -233,6 +287,35 @@ impl StoreCursor {
         }
     }
 
+    /// Human readable time range covered by the given shard, for warnings.
+    fn shard_range(&self, shard: u64) -> String {
+        format!(
+            "samples from {} to {}",
+            timestamp_to_datetime(&(shard as i64)),
+            timestamp_to_datetime(&(shard.saturating_add(self.shard_time) as i64)),
+        )
+    }
+
+    /// Get the index length published by the writer of the given shard. None
+    /// if it is not available, in which case the whole index file is used.
+    fn get_committed_len(&self, shard: u64) -> Option<u64> {
+        let path = self.path.join(format!("committed_{:011}", shard));
+        match std::fs::read(&path) {
+            Ok(buf) => {
+                let len = decode_committed_len(&buf);
+                if len.is_none() && !buf.is_empty() {
+                    warn!(
+                        self.logger,
+                        "Invalid committed index length: {}",
+                        path.display()
+                    );
+                }
+                len
+            }
+            Err(_) => None,
+        }
+    }
+
     /// Update the cursor to use the given shard and initialize the mmaps. If
     /// the current shard's index mmap has grown in length, update to the new
     /// index mmap so data appended since last update will show up.
@@ -272,7 +355,24 @@ impl StoreCursor {
     /// more shard in the given direction. Retrying may succeed as the store
     /// directory is scanned on every call.
     fn update_or_advance_shard(&mut self, direction: Direction) -> Result<bool> {
-        let entries = get_index_files(&self.path)?;
+        let entries = match get_index_files(&self.path) {
+            Ok(entries) => entries,
+            // The store directory was removed or rotated away while reading.
+            // Nothing more can be read from it.
+            Err(e) if is_not_found(&e) => {
+                warn!(
+                    self.logger,
+                    "Store directory {} no longer exists, skipping samples after {}",
+                    self.path.display(),
+                    self.get_key().map_or_else(
+                        || "the current position".to_owned(),
+                        |key| timestamp_to_datetime(&(key as i64))
+                    ),
+                );
+                return Ok(false);
+            }
+            Err(e) => return Err(e),
+        };
 
         let entries_iter: Box<dyn Iterator<Item = &String>> = match direction {
             Direction::Forward => Box::new(entries.iter()),
@@ -388,7 +488,7 @@ impl StoreCursor {
     fn get_serialized_single_frame<'a>(
         data_slice: &'a [u8],
         compressed: bool,
-        decompressor: &mut Option<Decompressor<(u64, usize)>>,
+        decompressor: &mut Option<Decompressor<ChunkKey>>,
     ) -> Result<SerializedFrame<'a>> {
         let serialized_frame = if compressed {
             SerializedFrame::Owned(
@@ -416,7 +516,7 @@ impl StoreCursor {
         data_slice: &[u8],
         index_offset: usize,
         chunk_compress_size_po2: u32,
-        decompressor: &mut Option<Decompressor<(u64, usize)>>,
+        decompressor: &mut Option<Decompressor<ChunkKey>>,
     ) -> Result<SerializedFrame> {
         // Calculate offset into the chunk. If this is 0, then this
         // is the first frame and hence key frame of the chunk.
@@ -455,6 +555,45 @@ impl StoreCursor {
         Ok(SerializedFrame::Owned(bytes))
     }
 
+    /// Get the process map of the key frame of the chunk containing the frame
+    /// at `index_offset`, which process deltas are relative to.
+    fn get_process_delta_base(&self, index_offset: usize) -> Result<Rc<PidMap>> {
+        let index_entry = self
+            .get_index_entry_at(index_offset)
+            .ok_or_else(|| anyhow!("Failed to get index entry at offset {}", index_offset))?;
+        let chunk_compress_size_po2 = index_entry.flags.get_chunk_compress_size_po2();
+        if chunk_compress_size_po2 == 0 {
+            bail!("Process delta found in frame outside of a chunk");
+        }
+        let chunk_mask = (INDEX_ENTRY_SIZE << chunk_compress_size_po2) - 1;
+        let dict_index_offset = index_offset & !chunk_mask;
+        let base_key = (self.shard.expect("shard should be set"), dict_index_offset);
+
+        if let Some((key, base)) = self.process_delta_base.borrow().as_ref() {
+            if *key == base_key {
+                return Ok(base.clone());
+            }
+        }
+        let (key_frame_entry, serialized_key_frame) = self
+            .get_index_and_serialized_frame_at(dict_index_offset)
+            .context("Failed to get key frame")?;
+        if !key_frame_entry.flags.contains(IndexEntryFlags::INDEXED) {
+            bail!("Key frame of process delta is not in indexed format");
+        }
+        let key_frame = deserialize_frame(
+            serialized_key_frame.as_ref(),
+            Format::Indexed,
+            ModelParts {
+                process: true,
+                ..ModelParts::NONE
+            },
+            || bail!("Key frame cannot have a process delta"),
+        )?;
+        let base = Rc::new(key_frame.sample.processes);
+        *self.process_delta_base.borrow_mut() = Some((base_key, base.clone()));
+        Ok(base)
+    }
+
     /// Get index entry at offset and it's corresponding data slice.
     fn get_index_and_data_at(&self, index_offset: usize) -> Result<(&IndexEntry, &[u8])> {
         let index_entry = self
@@ -527,8 +666,18 @@ impl StoreOffset {
     /// Shard and index offset are trimmed to multiples of SHARD_TIME and
     /// INDEX_ENTRY_SIZE respectively. Index offset is ignored if shard is None.
     pub fn new(shard: Option<u64>, index_offset: Option<usize>) -> Self {
+        Self::with_shard_time(shard, index_offset, SHARD_TIME)
+    }
+
+    /// Same as `new` but trims shard to a multiple of the given shard time,
+    /// for stores created with a non-default shard time.
+    pub fn with_shard_time(
+        shard: Option<u64>,
+        index_offset: Option<usize>,
+        shard_time: u64,
+    ) -> Self {
         StoreOffset {
-            shard: shard.as_ref().map(|s| s - s % SHARD_TIME),
+            shard: shard.as_ref().map(|s| s - s % shard_time),
             index_offset: shard.and(index_offset.map(|o| o - o % INDEX_ENTRY_SIZE)),
         }
     }
@@ -547,7 +696,7 @@ impl Cursor for StoreCursor {
     type Item = (SystemTime, DataFrame);
 
     fn get_offset(&self) -> StoreOffset {
-        StoreOffset::new(self.shard, self.index_offset)
+        StoreOffset::with_shard_time(self.shard, self.index_offset, self.shard_time)
     }
 
     fn set_offset(&mut self, offset: StoreOffset) {
@@ -586,16 +735,21 @@ impl Cursor for StoreCursor {
     /// This does not mean samples are depleted. More could be retrieved by
     /// advancing further to skip the holes.
     fn get(&self) -> Option<(SystemTime, DataFrame)> {
-        match self.get_index_and_serialized_frame_at(self.index_offset?) {
+        let index_offset = self.index_offset?;
+        match self.get_index_and_serialized_frame_at(index_offset) {
             Ok((index_entry, serialized_data)) => {
                 let format = if index_entry.flags.contains(IndexEntryFlags::CBOR) {
                     Format::Cbor
+                } else if index_entry.flags.contains(IndexEntryFlags::INDEXED) {
+                    Format::Indexed
                 } else {
                     panic!("Unexpected format");
                 };
                 let ts =
                     std::time::UNIX_EPOCH + std::time::Duration::from_secs(index_entry.timestamp);
-                match deserialize_frame(serialized_data.as_ref(), format) {
+                match deserialize_frame(serialized_data.as_ref(), format, self.model_parts, || {
+                    self.get_process_delta_base(index_offset)
+                }) {
                     Ok(df) => Some((ts, df)),
                     Err(e) => {
                         warn!(self.logger, "Failed to deserialize data frame: {}", e);
@@ -625,9 +779,13 @@ impl KeyedCursor<u64> for StoreCursor {
     /// Set the cursor offset near the given timestamp by inferring shard and
     /// index offset.
     fn jump_near_key(&mut self, key: &u64, _direction: Direction) {
-        let time_offset = key % SHARD_TIME;
+        let time_offset = key % self.shard_time;
         let shard = key - time_offset;
-        self.set_offset(StoreOffset::new(Some(shard), None));
+        self.set_offset(StoreOffset::with_shard_time(
+            Some(shard),
+            None,
+            self.shard_time,
+        ));
         // Move to the end of the shard.
         if self.advance_index(Direction::Reverse) {
             if let Some(last_entry) = self.get_index_entry() {
@@ -635,14 +793,18 @@ impl KeyedCursor<u64> for StoreCursor {
                     .get_offset()
                     .get_index_offset()
                     .expect("get_index_offset should return Some if get_index_entry returns Some");
-                let last_entry_time_offset = last_entry.timestamp % SHARD_TIME;
+                let last_entry_time_offset = last_entry.timestamp % self.shard_time;
                 if last_entry_time_offset != 0 {
                     // Assume samples are recorded in constant interval and
                     // scale index offset by time offset
                     let index_offset_hint = (last_entry_index_offset as f64
                         / last_entry_time_offset as f64
                         * time_offset as f64) as usize;
-                    self.set_offset(StoreOffset::new(Some(shard), Some(index_offset_hint)));
+                    self.set_offset(StoreOffset::with_shard_time(
+                        Some(shard),
+                        Some(index_offset_hint),
+                        self.shard_time,
+                    ));
                 }
             }
         }
@@ -868,7 +1030,7 @@ mod tests {
             let shard = timestamp - timestamp % SHARD_TIME;
             let open_options = OpenOptions::new().create(true).append(true).clone();
 
-            let data_bytes = serialize_frame(&DataFrame::default(), Format::Cbor)
+            let data_bytes = serialize_frame(&DataFrame::default(), Format::Cbor, None)
                 .context("Failed to serialize data frame")?;
             let data_crc = if corrupt_data { 0 } else { data_bytes.crc32() };
             let mut data_file = open_options
@@ -996,6 +1158,94 @@ mod tests {
         assert!(!cursor.advance(Forward).unwrap());
     }
 
+    /// Remove the files of the shard containing timestamp, as retention does.
+    fn remove_shard(dir: &TempDir, timestamp: u64, data_only: bool) {
+        let shard = timestamp - timestamp % SHARD_TIME;
+        let prefixes: &[&str] = if data_only {
+            &["data"]
+        } else {
+            &["index", "data"]
+        };
+        for prefix in prefixes {
+            std::fs::remove_file(dir.path().join(format!("{}_{:011}", prefix, shard)))
+                .expect("Failed to remove shard file");
+        }
+    }
+
+    /// Shards removed while reading are skipped in both directions.
+    #[test]
+    fn skip_removed_shard() {
+        let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        let ts = get_unix_timestamp(SystemTime::now());
+        let writer = TestWriter::new(&dir);
+        let mut cursor = StoreCursor::new(get_logger(), dir.path().to_path_buf());
+
+        writer.put(ts).unwrap();
+        writer.put(ts + SHARD_TIME).unwrap();
+        writer.put(ts + SHARD_TIME * 2).unwrap();
+        writer.put(ts + SHARD_TIME * 3).unwrap();
+
+        assert!(cursor.advance(Forward).unwrap());
+        assert_eq!(cursor.get_key(), Some(ts));
+        remove_shard(&dir, ts + SHARD_TIME, false);
+        // Data file removed between listing and opening the shard
+        remove_shard(&dir, ts + SHARD_TIME * 2, true);
+        assert!(cursor.advance(Forward).unwrap());
+        assert_eq!(cursor.get_key(), Some(ts + SHARD_TIME * 3));
+        assert!(cursor.advance(Reverse).unwrap());
+        assert_eq!(cursor.get_key(), Some(ts));
+    }
+
+    /// Samples of the current shard remain readable after it is removed.
+    #[test]
+    fn read_removed_current_shard() {
+        let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        let ts = get_unix_timestamp(SystemTime::now());
+        let writer = TestWriter::new(&dir);
+        let mut cursor = StoreCursor::new(get_logger(), dir.path().to_path_buf());
+
+        writer.put(ts).unwrap();
+        writer.put(ts + 5).unwrap();
+        writer.put(ts + SHARD_TIME).unwrap();
+
+        assert!(cursor.advance(Forward).unwrap());
+        assert_eq!(cursor.get_key(), Some(ts));
+        remove_shard(&dir, ts, false);
+        assert_eq!(
+            get_unix_timestamp(cursor.next(Forward).unwrap().unwrap().0),
+            ts + 5
+        );
+        assert_eq!(
+            get_unix_timestamp(cursor.next(Forward).unwrap().unwrap().0),
+            ts + SHARD_TIME
+        );
+        // The removed shard cannot be reopened
+        assert!(!cursor.advance(Reverse).unwrap());
+        assert_eq!(cursor.get_key(), Some(ts + SHARD_TIME));
+    }
+
+    /// A removed store directory is treated as having no more samples.
+    #[test]
+    fn advance_in_removed_dir() {
+        let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        let ts = get_unix_timestamp(SystemTime::now());
+        let writer = TestWriter::new(&dir);
+        let mut cursor = StoreCursor::new(get_logger(), dir.path().to_path_buf());
+
+        writer.put(ts).unwrap();
+        writer.put(ts + 5).unwrap();
+        writer.put(ts + SHARD_TIME).unwrap();
+
+        assert!(cursor.advance(Forward).unwrap());
+        std::fs::remove_dir_all(dir.path()).expect("Failed to remove store directory");
+        // Still mapped
+        assert!(cursor.advance(Forward).unwrap());
+        assert_eq!(cursor.get_key(), Some(ts + 5));
+        assert!(!cursor.advance(Forward).unwrap());
+        assert!(cursor.next(Forward).unwrap().is_none());
+        assert_eq!(cursor.get_key(), Some(ts + 5));
+    }
+
     /// Get corrupt index/data should return None.
     #[test]
     fn get_corrupt() {
@@ -0,0 +1,519 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::bail;
+use anyhow::Result;
+use model::Sample;
+use serde::Deserialize;
+use serde::Serialize;
+use slog::warn;
+
+use crate::cursor::KeyedCursor;
+use crate::cursor::StoreCursor;
+use crate::CompressionMode;
+use crate::DataFrame;
+use crate::Direction;
+use crate::Format;
+use crate::StoreWriter;
+
+// This file implements downsampling of a store, so that a coarser copy of
+// the data can be retained for longer than the full samples.
+//
+// The downsampled store lives in the `DOWNSAMPLED_DIR` subdirectory of the
+// store and is a regular store holding an aggregate frame per interval: a
+// `DataFrame` with the last sample of the interval and an `Aggregate` of all
+// its samples. Samples mostly hold cumulative counters and models derive
+// rates from the difference of consecutive samples, so rates computed
+// between aggregate frames are the averages over the interval. The
+// `Aggregate` additionally holds the minimum, maximum and average of the
+// main gauges, and the main counters summed over the interval.
+//
+// An interval is aggregated once a sample of a later interval was stored,
+// so the last interval is only downsampled by a later run.
+//
+// `LocalStore` reads the downsampled store where the full samples were
+// already discarded.
+
+/// Subdirectory of a store holding its downsampled samples
+pub const DOWNSAMPLED_DIR: &str = "downsampled";
+
+/// Default seconds between downsampled samples
+pub const DEFAULT_DOWNSAMPLE_INTERVAL_S: u64 = 60;
+
+pub fn downsampled_dir<P: AsRef<Path>>(dir: P) -> PathBuf {
+    dir.as_ref().join(DOWNSAMPLED_DIR)
+}
+
+/// Sample path of a value, e.g. "cgroup.memory_current", and its getter
+type SampleValue<T> = (&'static str, fn(&Sample) -> Option<T>);
+
+/// Gauges aggregated by their minimum, maximum and average
+const GAUGES: &[SampleValue<f64>] = &[
+    ("cgroup.memory_current", |s| {
+        s.cgroup.memory_current.map(|v| v as f64)
+    }),
+    ("cgroup.memory_swap_current", |s| {
+        s.cgroup.memory_swap_current.map(|v| v as f64)
+    }),
+    ("cgroup.tids_current", |s| {
+        s.cgroup.tids_current.map(|v| v as f64)
+    }),
+    ("system.meminfo.free", |s| {
+        s.system.meminfo.free.map(|v| v as f64)
+    }),
+    ("system.meminfo.available", |s| {
+        s.system.meminfo.available.map(|v| v as f64)
+    }),
+    ("system.meminfo.cached", |s| {
+        s.system.meminfo.cached.map(|v| v as f64)
+    }),
+    ("system.stat.running_processes", |s| {
+        s.system.stat.running_processes.map(|v| v as f64)
+    }),
+    ("system.stat.blocked_processes", |s| {
+        s.system.stat.blocked_processes.map(|v| v as f64)
+    }),
+    ("system.loadavg.load1", |s| s.system.loadavg.as_ref()?.load1),
+];
+
+/// Cumulative counters aggregated by their increase
+const COUNTERS: &[SampleValue<u64>] = &[
+    ("cgroup.cpu_stat.usage_usec", |s| {
+        s.cgroup.cpu_stat.as_ref()?.usage_usec
+    }),
+    ("cgroup.cpu_stat.user_usec", |s| {
+        s.cgroup.cpu_stat.as_ref()?.user_usec
+    }),
+    ("cgroup.cpu_stat.system_usec", |s| {
+        s.cgroup.cpu_stat.as_ref()?.system_usec
+    }),
+    ("cgroup.cpu_stat.throttled_usec", |s| {
+        s.cgroup.cpu_stat.as_ref()?.throttled_usec
+    }),
+    ("system.stat.total_cpu.user_usec", |s| {
+        s.system.stat.total_cpu.as_ref()?.user_usec
+    }),
+    ("system.stat.total_cpu.system_usec", |s| {
+        s.system.stat.total_cpu.as_ref()?.system_usec
+    }),
+    ("system.stat.total_cpu.idle_usec", |s| {
+        s.system.stat.total_cpu.as_ref()?.idle_usec
+    }),
+    ("system.stat.total_cpu.iowait_usec", |s| {
+        s.system.stat.total_cpu.as_ref()?.iowait_usec
+    }),
+    ("system.stat.context_switches", |s| {
+        s.system.stat.context_switches
+    }),
+    ("system.stat.total_interrupt_count", |s| {
+        s.system.stat.total_interrupt_count
+    }),
+    ("system.vmstat.pgpgin", |s| s.system.vmstat.pgpgin),
+    ("system.vmstat.pgpgout", |s| s.system.vmstat.pgpgout),
+    ("system.vmstat.pswpin", |s| s.system.vmstat.pswpin),
+    ("system.vmstat.pswpout", |s| s.system.vmstat.pswpout),
+    ("system.vmstat.oom_kill", |s| s.system.vmstat.oom_kill),
+];
+
+/// Statistics of the full samples of a downsample interval, set on the
+/// frames of the downsampled store.
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Aggregate {
+    /// Start of the interval, in seconds since the epoch
+    pub start: u64,
+    /// Number of full samples in the interval
+    pub samples: u64,
+    /// Gauges of the samples, by sample path, e.g. "cgroup.memory_current"
+    pub gauges: BTreeMap<String, GaugeAggregate>,
+    /// Increase of counters since the last sample of the previous interval,
+    /// by sample path, e.g. "cgroup.cpu_stat.usage_usec". Resets, e.g. by a
+    /// reboot, are accounted for.
+    pub counters: BTreeMap<String, u64>,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GaugeAggregate {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+}
+
+/// Aggregates the samples of an interval, in order
+struct Aggregator {
+    aggregate: Aggregate,
+    /// Sums and counts of the gauges, averaged when the interval is complete
+    gauge_sums: BTreeMap<&'static str, (f64, u64)>,
+    /// Last sample added, or the last one of the previous interval
+    last: Option<(SystemTime, Sample)>,
+}
+
+impl Aggregator {
+    /// `base` is the last sample of the previous interval, if known
+    fn new(start: u64, base: Option<(SystemTime, Sample)>) -> Self {
+        Self {
+            aggregate: Aggregate {
+                start,
+                ..Default::default()
+            },
+            gauge_sums: BTreeMap::new(),
+            last: base,
+        }
+    }
+
+    fn add(&mut self, timestamp: SystemTime, sample: Sample) {
+        self.aggregate.samples += 1;
+        for (name, get) in GAUGES {
+            if let Some(value) = get(&sample) {
+                let gauge =
+                    self.aggregate
+                        .gauges
+                        .entry(name.to_string())
+                        .or_insert(GaugeAggregate {
+                            min: value,
+                            max: value,
+                            avg: 0.0,
+                        });
+                gauge.min = gauge.min.min(value);
+                gauge.max = gauge.max.max(value);
+                let (sum, count) = self.gauge_sums.entry(name).or_default();
+                *sum += value;
+                *count += 1;
+            }
+        }
+        if let Some((_, last)) = self.last.as_ref() {
+            for (name, get) in COUNTERS {
+                if let (Some(prev), Some(value)) = (get(last), get(&sample)) {
+                    // The counter was reset, and counted up from zero since
+                    let increase = value.checked_sub(prev).unwrap_or(value);
+                    *self.aggregate.counters.entry(name.to_string()).or_default() += increase;
+                }
+            }
+        }
+        self.last = Some((timestamp, sample));
+    }
+
+    /// The aggregate frame of the interval, at the timestamp of its last
+    /// sample. None if no sample was added.
+    fn finish(mut self) -> Option<(SystemTime, DataFrame)> {
+        if self.aggregate.samples == 0 {
+            return None;
+        }
+        // Gauges can be missing from some samples, e.g. after a collection
+        // error, so average over the samples that had them
+        for (name, (sum, count)) in self.gauge_sums {
+            if let Some(gauge) = self.aggregate.gauges.get_mut(name) {
+                gauge.avg = sum / count as f64;
+            }
+        }
+        let (timestamp, sample) = self.last?;
+        Some((
+            timestamp,
+            DataFrame {
+                sample,
+                aggregate: Some(self.aggregate),
+            },
+        ))
+    }
+}
+/// Writes an aggregate frame per interval of a store to its downsampled
+/// store.
+pub struct Downsampler {
+    logger: slog::Logger,
+    cursor: StoreCursor,
+    writer: StoreWriter,
+    interval: u64,
+    /// Start of the next interval without a downsampled frame
+    next: u64,
+    /// Last sample before `next`, the base of the counters of its interval
+    base: Option<(SystemTime, Sample)>,
+}
+
+impl Downsampler {
+    /// Create a `Downsampler` of the store at `dir`. Downsampling resumes
+    /// after the last downsampled frame, or starts from the first sample of
+    /// the store.
+    pub fn new<P: AsRef<Path>>(
+        logger: slog::Logger,
+        dir: P,
+        interval: Duration,
+        compression_mode: CompressionMode,
+        format: Format,
+    ) -> Result<Self> {
+        let interval = interval.as_secs();
+        if interval == 0 {
+            bail!("Downsample interval must be at least a second");
+        }
+        let dir = dir.as_ref();
+        let downsampled_dir = downsampled_dir(dir);
+        // Creates the downsampled store if it does not exist yet
+        let writer = StoreWriter::new(logger.clone(), &downsampled_dir, compression_mode, format)?;
+        let mut downsampled = StoreCursor::new(logger.clone(), downsampled_dir);
+        // The sample of the last aggregate frame is the last sample of its
+        // interval
+        let (next, base) = match downsampled.get_next(
+            &common::util::get_unix_timestamp(SystemTime::now()),
+            Direction::Reverse,
+        )? {
+            Some((timestamp, frame)) => {
+                let last = common::util::get_unix_timestamp(timestamp);
+                (
+                    last - last % interval + interval,
+                    Some((timestamp, frame.sample)),
+                )
+            }
+            None => (0, None),
+        };
+        Ok(Self {
+            cursor: StoreCursor::new(logger.clone(), dir.to_path_buf()),
+            writer,
+            logger,
+            interval,
+            next,
+            base,
+        })
+    }
+
+    /// Downsample the intervals completed since the last call. Returns the
+    /// number of aggregate frames written.
+    pub fn run(&mut self) -> Result<usize> {
+        let mut written = 0;
+        let mut key = self.next;
+        let mut aggregator: Option<Aggregator> = None;
+        while let Some((timestamp, frame)) = self.cursor.get_next(&key, Direction::Forward)? {
+            key = common::util::get_unix_timestamp(timestamp);
+            let start = key - key % self.interval;
+            if aggregator
+                .as_ref()
+                .is_some_and(|a| a.aggregate.start != start)
+            {
+                let complete = aggregator.take().expect("aggregator is set");
+                if let Some((timestamp, frame)) = complete.finish() {
+                    if let Err(e) = self.writer.put(timestamp, &frame) {
+                        // Carry on with the next interval rather than
+                        // retrying the same one forever
+                        warn!(self.logger, "Failed to write downsampled frame: {:#}", e);
+                    } else {
+                        written += 1;
+                    }
+                    self.next = start;
+                    self.base = Some((timestamp, frame.sample));
+                }
+            }
+            aggregator
+                .get_or_insert_with(|| Aggregator::new(start, self.base.clone()))
+                .add(timestamp, frame.sample);
+            key += 1;
+        }
+        // The interval of the last sample may still get samples, it is
+        // aggregated again from `next` by a later run
+        Ok(written)
+    }
+
+    /// Discard downsampled data earlier than `timestamp`, see
+    /// `StoreWriter::discard_earlier`
+    pub fn discard_earlier(&self, timestamp: SystemTime) -> Result<()> {
+        self.writer.discard_earlier(timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use slog::Drain;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::DataFrame;
+    use crate::LocalStore;
+    use crate::Store;
+
+    fn get_logger() -> slog::Logger {
+        let plain = slog_term::PlainSyncDecorator::new(std::io::stderr());
+        slog::Logger::root(slog_term::FullFormat::new(plain).build().fuse(), slog::o!())
+    }
+
+    fn time(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    /// Frame with a gauge and a counter of `secs`, collected every 10
+    /// seconds
+    fn frame(secs: u64) -> DataFrame {
+        let mut frame = DataFrame::default();
+        frame.sample.interval_s = Some(10);
+        frame.sample.cgroup.memory_current = Some(secs as i64);
+        frame
+            .sample
+            .cgroup
+            .cpu_stat
+            .get_or_insert_with(Default::default)
+            .usage_usec = Some(secs * 1000);
+        frame
+    }
+
+    fn new_downsampler(dir: &Path) -> Downsampler {
+        Downsampler::new(
+            get_logger(),
+            dir,
+            Duration::from_secs(60),
+            CompressionMode::Zstd,
+            Format::Cbor,
+        )
+        .expect("Failed to create downsampler")
+    }
+
+    fn get(store: &mut LocalStore, secs: u64, direction: Direction) -> Option<u64> {
+        store
+            .get_sample_at_timestamp(time(secs), direction)
+            .expect("Failed to read sample")
+            .map(|(timestamp, frame)| {
+                assert_eq!(
+                    frame.sample.cgroup.memory_current,
+                    Some(common::util::get_unix_timestamp(timestamp) as i64)
+                );
+                common::util::get_unix_timestamp(timestamp)
+            })
+    }
+
+    fn get_aggregate(store: &mut LocalStore, secs: u64) -> Aggregate {
+        store
+            .get_sample_at_timestamp(time(secs), Direction::Forward)
+            .expect("Failed to read sample")
+            .and_then(|(_, frame)| frame.aggregate)
+            .expect("Not an aggregate frame")
+    }
+
+    #[test]
+    fn downsample() {
+        let dir = TempDir::with_prefix("below_downsample_test.").expect("tempdir failed");
+        let mut writer = StoreWriter::new_with_timestamp(
+            get_logger(),
+            &dir,
+            time(0),
+            CompressionMode::Zstd,
+            Format::Cbor,
+        )
+        .expect("Failed to create store");
+        let start = 86400;
+        for secs in (start..start + 300).step_by(10) {
+            writer.put(time(secs), &frame(secs)).expect("put failed");
+        }
+
+        // The interval of the last sample may still get samples
+        let mut downsampler = new_downsampler(dir.path());
+        assert_eq!(downsampler.run().expect("run failed"), 4);
+        assert_eq!(downsampler.run().expect("run failed"), 0);
+
+        // A sample of the next interval completes it
+        for secs in (start + 300..start + 330).step_by(10) {
+            writer.put(time(secs), &frame(secs)).expect("put failed");
+        }
+        assert_eq!(downsampler.run().expect("run failed"), 1);
+        drop(downsampler);
+
+        // A restarted downsampler resumes after the last aggregate frame
+        writer
+            .put(time(start + 360), &frame(start + 360))
+            .expect("put failed");
+        assert_eq!(new_downsampler(dir.path()).run().expect("run failed"), 1);
+
+        // With the full samples of the first shard gone, reads fall back to
+        // the aggregate frames, at the last sample of their interval, and use
+        // full samples again where they exist
+        for secs in (2 * start..2 * start + 60).step_by(10) {
+            writer.put(time(secs), &frame(secs)).expect("put failed");
+        }
+        writer
+            .discard_earlier(time(2 * start))
+            .expect("discard failed");
+        let mut store = LocalStore::new(get_logger(), dir.path().to_path_buf());
+        assert_eq!(
+            get(&mut store, start + 5, Direction::Forward),
+            Some(start + 50)
+        );
+        assert_eq!(
+            get(&mut store, start + 65, Direction::Reverse),
+            Some(start + 50)
+        );
+        assert_eq!(
+            get(&mut store, start + 361, Direction::Forward),
+            Some(2 * start)
+        );
+        assert_eq!(
+            get(&mut store, 2 * start + 15, Direction::Forward),
+            Some(2 * start + 20)
+        );
+        assert_eq!(
+            get(&mut store, 2 * start + 15, Direction::Reverse),
+            Some(2 * start + 10)
+        );
+        assert_eq!(
+            get(&mut store, 2 * start - 1, Direction::Reverse),
+            Some(start + 320)
+        );
+
+        // Counters are summed from the last sample of the previous interval,
+        // unknown for the first one
+        let first = get_aggregate(&mut store, start);
+        assert_eq!(first.start, start);
+        assert_eq!(first.samples, 6);
+        assert_eq!(
+            first.gauges.get("cgroup.memory_current"),
+            Some(&GaugeAggregate {
+                min: start as f64,
+                max: (start + 50) as f64,
+                avg: (start + 25) as f64,
+            })
+        );
+        assert_eq!(
+            first.counters.get("cgroup.cpu_stat.usage_usec"),
+            Some(&50_000)
+        );
+        let second = get_aggregate(&mut store, start + 60);
+        assert_eq!(second.start, start + 60);
+        assert_eq!(
+            second.counters.get("cgroup.cpu_stat.usage_usec"),
+            Some(&60_000)
+        );
+        // Including across a restart of the downsampler, which only got
+        // three samples of its first interval
+        let restarted = get_aggregate(&mut store, start + 300);
+        assert_eq!(restarted.start, start + 300);
+        assert_eq!(restarted.samples, 3);
+        assert_eq!(
+            restarted.counters.get("cgroup.cpu_stat.usage_usec"),
+            Some(&30_000)
+        );
+    }
+
+    #[test]
+    fn counter_reset() {
+        let mut aggregator = Aggregator::new(0, Some((time(0), frame(100).sample)));
+        aggregator.add(time(10), frame(110).sample);
+        // e.g. a reboot
+        aggregator.add(time(20), frame(5).sample);
+        let (timestamp, frame) = aggregator.finish().expect("No aggregate frame");
+        assert_eq!(timestamp, time(20));
+        let aggregate = frame.aggregate.expect("Not an aggregate frame");
+        assert_eq!(
+            aggregate.counters.get("cgroup.cpu_stat.usage_usec"),
+            Some(&15_000)
+        );
+    }
+}
@@ -0,0 +1,390 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpStream;
+use std::net::ToSocketAddrs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use common::util::get_unix_timestamp;
+use slog::info;
+use slog::warn;
+
+use crate::DataFrame;
+
+// This file implements forwarding of recorded samples to a collector, i.e.
+// another below instance running `below collect`, which writes a store per
+// host. The protocol is a plain TCP stream:
+//
+//   handshake: magic (4 bytes), version: u8, hostname_len: u16 LE, hostname,
+//              token_len: u16 LE, token
+//   frames:    timestamp: u64 LE, len: u32 LE, CBOR serialized DataFrame
+//
+// The token is a secret shared by the forwarders and the collector, which
+// drops connections presenting another one. It is sent in the clear, so
+// the forwarders and the collector are expected to share a trusted network
+// or a tunnel.
+//
+// There is no acknowledgement. Samples recorded while the collector is
+// unreachable are dropped, and the forwarder reconnects on the next sample.
+
+const MAGIC: &[u8; 4] = b"BLWF";
+const VERSION: u8 = 2;
+/// Upper bound on a single serialized frame, to reject garbage early. Frames
+/// are read as they arrive rather than allocated upfront, so a peer has to
+/// send that much to make the collector hold it.
+const MAX_FRAME_SIZE: u32 = 256 << 20;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time the collector waits for the handshake of a new connection, so that
+/// peers that do not authenticate cannot hold connections for long
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time the collector waits for the next frame of a sender before dropping
+/// its connection, e.g. as the host went away without closing it. Longer
+/// than any sensible collection interval.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Whether `hostname` is safe to use as a directory name on the collector.
+pub fn is_valid_hostname(hostname: &str) -> bool {
+    !hostname.is_empty()
+        && hostname != "."
+        && hostname != ".."
+        && hostname.len() <= u16::MAX as usize
+        && !hostname.contains(['/', '\0'])
+}
+
+/// Read the token shared by forwarders and the collector from `path`, without
+/// trailing whitespace. The file must not be accessible by group or others.
+pub fn read_token(path: &Path) -> Result<Vec<u8>> {
+    let mode = std::fs::metadata(path)
+        .with_context(|| format!("Failed to stat token file {}", path.display()))?
+        .permissions()
+        .mode();
+    if mode & 0o077 != 0 {
+        bail!(
+            "Token file {} must not be accessible by group or others (mode {:o})",
+            path.display(),
+            mode & 0o777
+        );
+    }
+    let mut token = std::fs::read(path)
+        .with_context(|| format!("Failed to read token file {}", path.display()))?;
+    token.truncate(token.trim_ascii_end().len());
+    if token.is_empty() {
+        bail!("Token file {} is empty", path.display());
+    }
+    if token.len() > u16::MAX as usize {
+        bail!("Token in {} is too long", path.display());
+    }
+    Ok(token)
+}
+
+/// Compare tokens in time independent of where they differ
+fn tokens_match(lhs: &[u8], rhs: &[u8]) -> bool {
+    lhs.len() == rhs.len() && lhs.iter().zip(rhs).fold(0, |diff, (l, r)| diff | (l ^ r)) == 0
+}
+
+/// Connect to the first address `addr` resolves to that accepts the
+/// connection within `CONNECT_TIMEOUT`, so that an unreachable collector does
+/// not hold up recording for long.
+fn connect_any(addr: &str) -> std::io::Result<TcpStream> {
+    let mut last_err = std::io::Error::new(ErrorKind::NotFound, "No address resolved");
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_err = e,
+        }
+    }
+    Err(last_err)
+}
+
+/// Sends samples to a collector.
+pub struct ForwardWriter {
+    logger: slog::Logger,
+    addr: String,
+    token: Vec<u8>,
+    stream: Option<TcpStream>,
+}
+
+impl ForwardWriter {
+    /// Create a `ForwardWriter` sending to `addr` ("host:port") and
+    /// authenticating with `token`, see `read_token`. The connection is only
+    /// made on the first `put`.
+    pub fn new(logger: slog::Logger, addr: String, token: Vec<u8>) -> Self {
+        Self {
+            logger,
+            addr,
+            token,
+            stream: None,
+        }
+    }
+
+    fn connect(&self, hostname: &str) -> Result<TcpStream> {
+        if !is_valid_hostname(hostname) {
+            bail!(
+                "Cannot forward samples with invalid hostname {:?}",
+                hostname
+            );
+        }
+        let mut stream = connect_any(&self.addr)
+            .with_context(|| format!("Failed to connect to collector {}", self.addr))?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        stream.set_nodelay(true)?;
+
+        let mut handshake = Vec::with_capacity(MAGIC.len() + 5 + hostname.len() + self.token.len());
+        handshake.extend_from_slice(MAGIC);
+        handshake.push(VERSION);
+        handshake.extend_from_slice(&(hostname.len() as u16).to_le_bytes());
+        handshake.extend_from_slice(hostname.as_bytes());
+        handshake.extend_from_slice(&(self.token.len() as u16).to_le_bytes());
+        handshake.extend_from_slice(&self.token);
+        stream
+            .write_all(&handshake)
+            .context("Failed to send handshake to collector")?;
+        info!(self.logger, "Forwarding samples to {}", self.addr);
+        Ok(stream)
+    }
+
+    /// Send a sample to the collector, connecting first if needed. On
+    /// failure the connection is dropped and retried on the next call.
+    pub fn put(&mut self, timestamp: SystemTime, data: &DataFrame) -> Result<()> {
+        let payload = serde_cbor::to_vec(data).context("Failed to serialize data frame")?;
+        let len: u32 = payload
+            .len()
+            .try_into()
+            .ok()
+            .filter(|len| *len <= MAX_FRAME_SIZE)
+            .context("Serialized data frame too large to forward")?;
+        let mut message = Vec::with_capacity(12 + payload.len());
+        message.extend_from_slice(&get_unix_timestamp(timestamp).to_le_bytes());
+        message.extend_from_slice(&len.to_le_bytes());
+        message.extend_from_slice(&payload);
+
+        let stream = match &mut self.stream {
+            Some(stream) => stream,
+            None => self
+                .stream
+                .insert(self.connect(&data.sample.system.hostname)?),
+        };
+        if let Err(e) = stream.write_all(&message) {
+            warn!(self.logger, "Lost connection to collector {}", self.addr);
+            self.stream = None;
+            return Err(e).context("Failed to forward sample");
+        }
+        Ok(())
+    }
+}
+
+/// Receives samples sent by a `ForwardWriter`.
+pub struct ForwardReader<R> {
+    reader: R,
+    hostname: String,
+}
+
+/// Read a u16 LE length and that many bytes
+fn read_with_len<R: Read>(reader: &mut R) -> std::io::Result<Vec<u8>> {
+    let mut len = [0; 2];
+    reader.read_exact(&mut len)?;
+    let mut bytes = vec![0; u16::from_le_bytes(len) as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+impl<R: Read> ForwardReader<R> {
+    /// Read and validate the handshake from `reader`, which must present
+    /// `token`.
+    pub fn new(mut reader: R, token: &[u8]) -> Result<Self> {
+        let mut header = [0; MAGIC.len() + 1];
+        reader
+            .read_exact(&mut header)
+            .context("Failed to read handshake")?;
+        if &header[..MAGIC.len()] != MAGIC {
+            bail!("Bad handshake magic");
+        }
+        let version = header[MAGIC.len()];
+        if version != VERSION {
+            bail!("Unsupported forwarding protocol version: {}", version);
+        }
+        let hostname = read_with_len(&mut reader).context("Failed to read hostname")?;
+        let presented = read_with_len(&mut reader).context("Failed to read token")?;
+        if !tokens_match(&presented, token) {
+            bail!("Invalid token");
+        }
+        let hostname = String::from_utf8(hostname).context("Hostname is not valid UTF-8")?;
+        if !is_valid_hostname(&hostname) {
+            bail!("Invalid hostname {:?}", hostname);
+        }
+        Ok(Self { reader, hostname })
+    }
+
+    /// Hostname the sender announced in the handshake.
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    /// Read the next sample. Returns None if the sender closed the
+    /// connection between samples.
+    pub fn next_frame(&mut self) -> Result<Option<(SystemTime, DataFrame)>> {
+        let mut header = [0; 12];
+        match self.reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e).context("Failed to read frame header"),
+        }
+        let timestamp = u64::from_le_bytes(header[..8].try_into().expect("bug: bad header"));
+        let len = u32::from_le_bytes(header[8..].try_into().expect("bug: bad header"));
+        if len > MAX_FRAME_SIZE {
+            bail!("Frame too large: {} bytes", len);
+        }
+        let mut payload = Vec::new();
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut payload)
+            .context("Failed to read frame")?;
+        if payload.len() != len as usize {
+            bail!("Truncated frame");
+        }
+        let frame = serde_cbor::from_slice(&payload).context("Failed to deserialize frame")?;
+        Ok(Some((
+            SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp),
+            frame,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use slog::Drain;
+
+    use super::*;
+
+    fn get_logger() -> slog::Logger {
+        let plain = slog_term::PlainSyncDecorator::new(std::io::stderr());
+        slog::Logger::root(slog_term::FullFormat::new(plain).build().fuse(), slog::o!())
+    }
+
+    const TOKEN: &[u8] = b"secret";
+
+    fn handshake(hostname: &[u8], token: &[u8]) -> Vec<u8> {
+        let mut handshake = MAGIC.to_vec();
+        handshake.push(VERSION);
+        handshake.extend_from_slice(&(hostname.len() as u16).to_le_bytes());
+        handshake.extend_from_slice(hostname);
+        handshake.extend_from_slice(&(token.len() as u16).to_le_bytes());
+        handshake.extend_from_slice(token);
+        handshake
+    }
+
+    #[test]
+    fn forward_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+        let addr = listener.local_addr().expect("Failed to get address");
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("Failed to accept");
+            let mut reader = ForwardReader::new(stream, TOKEN).expect("Bad handshake");
+            let mut frames = Vec::new();
+            while let Some(frame) = reader.next_frame().expect("Failed to read frame") {
+                frames.push(frame);
+            }
+            (reader.hostname().to_owned(), frames)
+        });
+
+        let mut writer = ForwardWriter::new(get_logger(), addr.to_string(), TOKEN.to_vec());
+        let mut frame = DataFrame::default();
+        frame.sample.system.hostname = "host1".to_owned();
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        for i in 0..3 {
+            frame.sample.cgroup.memory_current = Some(i);
+            writer
+                .put(timestamp + Duration::from_secs(i as u64), &frame)
+                .expect("Failed to forward");
+        }
+        drop(writer);
+
+        let (hostname, frames) = server.join().expect("Server panicked");
+        assert_eq!(hostname, "host1");
+        assert_eq!(frames.len(), 3);
+        for (i, (ts, received)) in frames.into_iter().enumerate() {
+            assert_eq!(ts, timestamp + Duration::from_secs(i as u64));
+            assert_eq!(received.sample.cgroup.memory_current, Some(i as i64));
+            assert_eq!(received.sample.system.hostname, "host1");
+        }
+    }
+
+    #[test]
+    fn bad_handshake() {
+        assert!(ForwardReader::new(handshake(b"host1", TOKEN).as_slice(), TOKEN).is_ok());
+        assert!(ForwardReader::new(handshake(b"..", TOKEN).as_slice(), TOKEN).is_err());
+        assert!(ForwardReader::new(handshake(b"host1", b"secreT").as_slice(), TOKEN).is_err());
+        assert!(ForwardReader::new(handshake(b"host1", b"").as_slice(), TOKEN).is_err());
+
+        let mut bad_magic = handshake(b"host1", TOKEN);
+        bad_magic[0] = b'X';
+        assert!(ForwardReader::new(bad_magic.as_slice(), TOKEN).is_err());
+        assert!(ForwardReader::new(&MAGIC[..2], TOKEN).is_err());
+    }
+
+    #[test]
+    fn bad_frame() {
+        let handshake = handshake(b"host1", TOKEN);
+
+        let mut too_large = handshake.clone();
+        too_large.extend_from_slice(&1000u64.to_le_bytes());
+        too_large.extend_from_slice(&(MAX_FRAME_SIZE + 1).to_le_bytes());
+        let mut reader = ForwardReader::new(too_large.as_slice(), TOKEN).expect("Bad handshake");
+        assert!(reader.next_frame().is_err());
+
+        let mut truncated = handshake;
+        truncated.extend_from_slice(&1000u64.to_le_bytes());
+        truncated.extend_from_slice(&100u32.to_le_bytes());
+        truncated.extend_from_slice(&[0; 10]);
+        let mut reader = ForwardReader::new(truncated.as_slice(), TOKEN).expect("Bad handshake");
+        assert!(reader.next_frame().is_err());
+    }
+
+    #[test]
+    fn token_file() {
+        let dir = tempfile::TempDir::with_prefix("below_forward_test.").expect("tempdir failed");
+        let path = dir.path().join("token");
+        std::fs::write(&path, "secret\n").expect("Failed to write token");
+        let set_mode = |mode| {
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode))
+                .expect("Failed to set mode")
+        };
+        set_mode(0o644);
+        assert!(read_token(&path).is_err());
+        set_mode(0o600);
+        assert_eq!(read_token(&path).expect("Failed to read token"), TOKEN);
+        std::fs::write(&path, "\n").expect("Failed to write token");
+        assert!(read_token(&path).is_err());
+    }
+
+    #[test]
+    fn hostname_validation() {
+        assert!(is_valid_hostname("host1.example.com"));
+        assert!(!is_valid_hostname(""));
+        assert!(!is_valid_hostname(".."));
+        assert!(!is_valid_hostname("../etc"));
+    }
+}
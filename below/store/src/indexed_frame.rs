@@ -0,0 +1,431 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use model::ModelParts;
+use model::Sample;
+use procfs::PidMap;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::DataFrame;
+
+// This file defines the field-indexed frame container used by
+// `Format::Indexed`. Each top-level field of a `Sample` is CBOR
+// serialized on its own, preceded by a table of contents:
+//
+//   version: u8
+//   nr_fields: u8
+//   nr_fields * (field_id: u8, len: u32 little endian)
+//   concatenated field payloads, in table order
+//
+// Readers walk the table and only decode the payloads they need, so e.g.
+// dumping cgroups does not pay for decoding a large process map. Unknown
+// field ids are skipped and missing fields are left at their default
+// value, which allows adding fields without bumping the version. The
+// aggregate of a downsampled frame (`FIELD_AGGREGATE`) is stored alongside
+// the sample fields, and left out of other frames.
+//
+// Non-key frames of a dictionary compressed chunk may store the process map
+// as a delta against the key frame of the chunk (`FIELD_PROCESSES_DELTA`
+// instead of `FIELD_PROCESSES`). Deltas are always against the key frame
+// rather than the previous frame so that any frame can be reconstructed
+// from itself and the key frame alone.
+
+const VERSION: u8 = 1;
+const TOC_ENTRY_SIZE: usize = 5;
+
+const FIELD_CGROUP: u8 = 0;
+const FIELD_PROCESSES: u8 = 1;
+const FIELD_SYSTEM: u8 = 2;
+const FIELD_NETSTATS: u8 = 3;
+const FIELD_GPUS: u8 = 4;
+const FIELD_ETHTOOL: u8 = 5;
+const FIELD_RESCTRL: u8 = 6;
+const FIELD_TC: u8 = 7;
+const FIELD_PROCESSES_DELTA: u8 = 8;
+const FIELD_INTERVAL_S: u8 = 9;
+const FIELD_STACKS: u8 = 10;
+const FIELD_CMDLINE_REDACTED: u8 = 11;
+const FIELD_DISCONTINUITY: u8 = 12;
+const FIELD_COLLECTION_TIMING: u8 = 13;
+const FIELD_PROCESS_NET: u8 = 14;
+const FIELD_EXTRA_CGROUPS: u8 = 15;
+const FIELD_BPF_STATS: u8 = 16;
+const FIELD_UNAVAILABLE_SOURCES: u8 = 17;
+const FIELD_OOM_KILLS: u8 = 18;
+const FIELD_OMITTED_SECTIONS: u8 = 19;
+const FIELD_AGGREGATE: u8 = 20;
+
+/// Process map relative to a base map. Pids in the base map that are absent
+/// from both `removed` and `changed` are unchanged.
+#[derive(Serialize, Deserialize)]
+struct PidMapDelta<V> {
+    removed: Vec<i32>,
+    changed: BTreeMap<i32, V>,
+}
+
+impl<'a> PidMapDelta<&'a procfs::PidInfo> {
+    fn new(base: &PidMap, processes: &'a PidMap) -> Self {
+        PidMapDelta {
+            removed: base
+                .keys()
+                .filter(|pid| !processes.contains_key(pid))
+                .copied()
+                .collect(),
+            changed: processes
+                .iter()
+                .filter(|(pid, info)| base.get(pid) != Some(*info))
+                .map(|(pid, info)| (*pid, info))
+                .collect(),
+        }
+    }
+}
+
+impl PidMapDelta<procfs::PidInfo> {
+    fn apply(self, base: &PidMap) -> PidMap {
+        let mut processes = base.clone();
+        for pid in &self.removed {
+            processes.remove(pid);
+        }
+        processes.extend(self.changed);
+        processes
+    }
+}
+
+/// Whether the field is needed to build the selected sub-models.
+fn is_field_needed(field_id: u8, parts: ModelParts) -> bool {
+    match field_id {
+        FIELD_CGROUP | FIELD_EXTRA_CGROUPS => parts.cgroup,
+        FIELD_PROCESSES | FIELD_PROCESSES_DELTA => parts.process || parts.dstate,
+        FIELD_STACKS | FIELD_PROCESS_NET => parts.process,
+        FIELD_SYSTEM | FIELD_COLLECTION_TIMING | FIELD_BPF_STATS => parts.system,
+        FIELD_NETSTATS | FIELD_ETHTOOL => parts.network,
+        FIELD_GPUS => parts.gpu,
+        FIELD_RESCTRL => parts.resctrl,
+        // Root qdisc drops are joined into the network model
+        FIELD_TC => parts.tc || parts.network,
+        // Metadata of the sample, needed whatever the parts
+        FIELD_INTERVAL_S
+        | FIELD_CMDLINE_REDACTED
+        | FIELD_DISCONTINUITY
+        | FIELD_UNAVAILABLE_SOURCES
+        | FIELD_OOM_KILLS
+        | FIELD_OMITTED_SECTIONS
+        | FIELD_AGGREGATE => true,
+        _ => false,
+    }
+}
+
+/// Serialize the data frame. If `process_base` is given, the process map is
+/// stored as a delta against it.
+pub fn serialize(data: &DataFrame, process_base: Option<&PidMap>) -> Result<Vec<u8>> {
+    let sample = &data.sample;
+    let processes = match process_base {
+        Some(base) => (
+            FIELD_PROCESSES_DELTA,
+            serde_cbor::to_vec(&PidMapDelta::new(base, &sample.processes))?,
+        ),
+        None => (FIELD_PROCESSES, serde_cbor::to_vec(&sample.processes)?),
+    };
+    let mut payloads = vec![
+        (FIELD_CGROUP, serde_cbor::to_vec(&sample.cgroup)?),
+        processes,
+        (FIELD_SYSTEM, serde_cbor::to_vec(&sample.system)?),
+        (FIELD_NETSTATS, serde_cbor::to_vec(&sample.netstats)?),
+        (FIELD_GPUS, serde_cbor::to_vec(&sample.gpus)?),
+        (FIELD_ETHTOOL, serde_cbor::to_vec(&sample.ethtool)?),
+        (FIELD_RESCTRL, serde_cbor::to_vec(&sample.resctrl)?),
+        (FIELD_TC, serde_cbor::to_vec(&sample.tc)?),
+        (FIELD_INTERVAL_S, serde_cbor::to_vec(&sample.interval_s)?),
+        (FIELD_STACKS, serde_cbor::to_vec(&sample.stacks)?),
+        (
+            FIELD_CMDLINE_REDACTED,
+            serde_cbor::to_vec(&sample.cmdline_redacted)?,
+        ),
+        (
+            FIELD_DISCONTINUITY,
+            serde_cbor::to_vec(&sample.discontinuity)?,
+        ),
+        (
+            FIELD_COLLECTION_TIMING,
+            serde_cbor::to_vec(&sample.collection_timing)?,
+        ),
+        (FIELD_PROCESS_NET, serde_cbor::to_vec(&sample.process_net)?),
+        (
+            FIELD_EXTRA_CGROUPS,
+            serde_cbor::to_vec(&sample.extra_cgroups)?,
+        ),
+        (FIELD_BPF_STATS, serde_cbor::to_vec(&sample.bpf_stats)?),
+        (
+            FIELD_UNAVAILABLE_SOURCES,
+            serde_cbor::to_vec(&sample.unavailable_sources)?,
+        ),
+        (FIELD_OOM_KILLS, serde_cbor::to_vec(&sample.oom_kills)?),
+        (
+            FIELD_OMITTED_SECTIONS,
+            serde_cbor::to_vec(&sample.omitted_sections)?,
+        ),
+    ];
+    // Only frames of downsampled stores have an aggregate
+    if let Some(aggregate) = data.aggregate.as_ref() {
+        payloads.push((FIELD_AGGREGATE, serde_cbor::to_vec(aggregate)?));
+    }
+
+    let toc_len = 2 + payloads.len() * TOC_ENTRY_SIZE;
+    let payload_len: usize = payloads.iter().map(|(_, p)| p.len()).sum();
+    let mut bytes = Vec::with_capacity(toc_len + payload_len);
+    bytes.push(VERSION);
+    bytes.push(payloads.len() as u8);
+    for (field_id, payload) in &payloads {
+        let len: u32 = payload
+            .len()
+            .try_into()
+            .context("Serialized field too large")?;
+        bytes.push(*field_id);
+        bytes.extend_from_slice(&len.to_le_bytes());
+    }
+    for (_, payload) in &payloads {
+        bytes.extend_from_slice(payload);
+    }
+    Ok(bytes)
+}
+
+/// Walk the table of contents and return each (field id, payload) pair.
+fn fields(bytes: &[u8]) -> Result<Vec<(u8, &[u8])>> {
+    let (version, nr_fields) = match bytes {
+        [version, nr_fields, ..] => (*version, *nr_fields as usize),
+        _ => bail!("Indexed frame too short: {} bytes", bytes.len()),
+    };
+    if version != VERSION {
+        bail!("Unsupported indexed frame version: {}", version);
+    }
+    let toc_end = 2 + nr_fields * TOC_ENTRY_SIZE;
+    if bytes.len() < toc_end {
+        bail!("Truncated indexed frame table of contents");
+    }
+
+    let mut fields = Vec::with_capacity(nr_fields);
+    let mut offset = toc_end;
+    for entry in bytes[2..toc_end].chunks_exact(TOC_ENTRY_SIZE) {
+        let field_id = entry[0];
+        let len = u32::from_le_bytes(entry[1..].try_into().expect("bug: bad toc entry")) as usize;
+        let payload = bytes
+            .get(offset..offset + len)
+            .with_context(|| format!("Truncated payload of field {}", field_id))?;
+        offset += len;
+        fields.push((field_id, payload));
+    }
+    Ok(fields)
+}
+
+/// Deserialize only the fields needed by `parts`. Other fields are left at
+/// their default value. `process_base` is called to get the key frame's
+/// process map if the process map is needed and stored as a delta.
+pub fn deserialize<F>(bytes: &[u8], parts: ModelParts, process_base: F) -> Result<DataFrame>
+where
+    F: FnOnce() -> Result<Rc<PidMap>>,
+{
+    let mut sample = Sample::default();
+    let mut aggregate = None;
+    let mut process_base = Some(process_base);
+    for (field_id, payload) in fields(bytes)? {
+        if !is_field_needed(field_id, parts) {
+            continue;
+        }
+        match field_id {
+            FIELD_CGROUP => sample.cgroup = serde_cbor::from_slice(payload)?,
+            FIELD_PROCESSES => sample.processes = serde_cbor::from_slice(payload)?,
+            FIELD_SYSTEM => sample.system = serde_cbor::from_slice(payload)?,
+            FIELD_NETSTATS => sample.netstats = serde_cbor::from_slice(payload)?,
+            FIELD_GPUS => sample.gpus = serde_cbor::from_slice(payload)?,
+            FIELD_ETHTOOL => sample.ethtool = serde_cbor::from_slice(payload)?,
+            FIELD_RESCTRL => sample.resctrl = serde_cbor::from_slice(payload)?,
+            FIELD_TC => sample.tc = serde_cbor::from_slice(payload)?,
+            FIELD_INTERVAL_S => sample.interval_s = serde_cbor::from_slice(payload)?,
+            FIELD_STACKS => sample.stacks = serde_cbor::from_slice(payload)?,
+            FIELD_CMDLINE_REDACTED => sample.cmdline_redacted = serde_cbor::from_slice(payload)?,
+            FIELD_DISCONTINUITY => sample.discontinuity = serde_cbor::from_slice(payload)?,
+            FIELD_COLLECTION_TIMING => sample.collection_timing = serde_cbor::from_slice(payload)?,
+            FIELD_PROCESS_NET => sample.process_net = serde_cbor::from_slice(payload)?,
+            FIELD_EXTRA_CGROUPS => sample.extra_cgroups = serde_cbor::from_slice(payload)?,
+            FIELD_BPF_STATS => sample.bpf_stats = serde_cbor::from_slice(payload)?,
+            FIELD_UNAVAILABLE_SOURCES => {
+                sample.unavailable_sources = serde_cbor::from_slice(payload)?
+            }
+            FIELD_OOM_KILLS => sample.oom_kills = serde_cbor::from_slice(payload)?,
+            FIELD_OMITTED_SECTIONS => sample.omitted_sections = serde_cbor::from_slice(payload)?,
+            FIELD_AGGREGATE => aggregate = Some(serde_cbor::from_slice(payload)?),
+            FIELD_PROCESSES_DELTA => {
+                let delta: PidMapDelta<procfs::PidInfo> = serde_cbor::from_slice(payload)?;
+                let base = process_base.take().context("Duplicate process delta")?()
+                    .context("Failed to get base process map of delta frame")?;
+                sample.processes = delta.apply(&base);
+            }
+            _ => unreachable!("unknown fields are never needed"),
+        }
+    }
+    Ok(DataFrame { sample, aggregate })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frame with every field of the sample set. Fields are listed without
+    /// `..Default::default()`, so that a new sample field does not build
+    /// until it is added here and to the indexed format.
+    fn get_data_frame() -> DataFrame {
+        let sample = Sample {
+            cgroup: model::CgroupSample {
+                memory_current: Some(42),
+                ..Default::default()
+            },
+            processes: BTreeMap::from([(1, Default::default())]),
+            system: model::SystemSample {
+                hostname: "host".to_string(),
+                ..Default::default()
+            },
+            netstats: procfs::NetStat {
+                tcp: Some(Default::default()),
+                ..Default::default()
+            },
+            gpus: Some(Default::default()),
+            ethtool: Some(Default::default()),
+            resctrl: Some(Default::default()),
+            tc: Some(Default::default()),
+            omitted_sections: [model::SampleSection::Slabinfo].into(),
+            interval_s: Some(5),
+            stacks: BTreeMap::from([(
+                1,
+                model::StackSample {
+                    samples: 10,
+                    ..Default::default()
+                },
+            )]),
+            process_net: BTreeMap::from([(
+                1,
+                model::ProcessNetStat {
+                    rx_bytes: 100,
+                    ..Default::default()
+                },
+            )]),
+            extra_cgroups: BTreeMap::from([("nested".to_owned(), Default::default())]),
+            cmdline_redacted: true,
+            discontinuity: true,
+            collection_timing: Some(model::CollectionTiming {
+                total_usecs: 1000,
+                ..Default::default()
+            }),
+            bpf_stats: BTreeMap::from([(
+                "exitstat".to_owned(),
+                model::BpfProgramStat {
+                    lost_events: 1,
+                    ..Default::default()
+                },
+            )]),
+            unavailable_sources: BTreeMap::from([(
+                model::DataSource::Disks,
+                "not found".to_owned(),
+            )]),
+            oom_kills: vec![model::OomKillEvent {
+                timestamp: 1,
+                ..Default::default()
+            }],
+        };
+        DataFrame {
+            sample,
+            aggregate: None,
+        }
+    }
+
+    fn no_base() -> Result<Rc<PidMap>> {
+        bail!("Not a delta frame")
+    }
+
+    #[test]
+    fn round_trip() {
+        let frame = get_data_frame();
+        let bytes = serialize(&frame, None).expect("Failed to serialize");
+        let deserialized =
+            deserialize(&bytes, ModelParts::ALL, no_base).expect("Failed to deserialize");
+        assert_eq!(deserialized, frame);
+    }
+
+    #[test]
+    fn aggregate_round_trip() {
+        let mut frame = get_data_frame();
+        frame.aggregate = Some(crate::downsample::Aggregate {
+            start: 60,
+            samples: 2,
+            ..Default::default()
+        });
+        let bytes = serialize(&frame, None).expect("Failed to serialize");
+        let deserialized =
+            deserialize(&bytes, ModelParts::NONE, no_base).expect("Failed to deserialize");
+        assert_eq!(deserialized.aggregate, frame.aggregate);
+    }
+
+    #[test]
+    fn partial_read() {
+        let frame = get_data_frame();
+        let bytes = serialize(&frame, None).expect("Failed to serialize");
+        let parts = ModelParts {
+            cgroup: true,
+            ..ModelParts::NONE
+        };
+        let deserialized = deserialize(&bytes, parts, no_base).expect("Failed to deserialize");
+        assert_eq!(deserialized.sample.cgroup, frame.sample.cgroup);
+        assert!(deserialized.sample.processes.is_empty());
+        assert_eq!(deserialized.sample.system.hostname, "");
+    }
+
+    #[test]
+    fn process_delta() {
+        let base = get_data_frame().sample.processes;
+        let mut frame = get_data_frame();
+        frame.sample.processes.remove(&1);
+        frame.sample.processes.insert(2, Default::default());
+        let bytes = serialize(&frame, Some(&base)).expect("Failed to serialize");
+
+        let deserialized = deserialize(&bytes, ModelParts::ALL, || Ok(Rc::new(base.clone())))
+            .expect("Failed to deserialize");
+        assert_eq!(deserialized, frame);
+
+        // The base is only needed when the process map is read
+        let parts = ModelParts {
+            cgroup: true,
+            ..ModelParts::NONE
+        };
+        let deserialized = deserialize(&bytes, parts, no_base).expect("Failed to deserialize");
+        assert_eq!(deserialized.sample.cgroup, frame.sample.cgroup);
+        assert!(deserialize(&bytes, ModelParts::ALL, no_base).is_err());
+    }
+
+    #[test]
+    fn corrupt_frame() {
+        let frame = get_data_frame();
+        let bytes = serialize(&frame, None).expect("Failed to serialize");
+        assert!(deserialize(&bytes[..bytes.len() - 1], ModelParts::ALL, no_base).is_err());
+        assert!(deserialize(&bytes[..1], ModelParts::ALL, no_base).is_err());
+        let mut bad_version = bytes;
+        bad_version[0] = VERSION + 1;
+        assert!(deserialize(&bad_version, ModelParts::ALL, no_base).is_err());
+    }
+}
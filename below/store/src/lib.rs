@@ -16,11 +16,18 @@ use std::fs;
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::io::ErrorKind;
+use std::io::Read;
 use std::io::Write;
+use std::os::unix::fs::FileExt;
 use std::os::unix::io::AsRawFd;
 use std::path::Path;
 use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::Duration;
 use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
 use anyhow::bail;
 use anyhow::Context;
@@ -30,6 +37,8 @@ use common::fileutil::get_dir_size;
 use common::open_source_shim;
 use common::util::get_unix_timestamp;
 use model::Model;
+use model::ModelParts;
+use procfs::PidMap;
 use serde::Deserialize;
 use serde::Serialize;
 use slog::info;
@@ -43,6 +52,9 @@ use crate::cursor::StoreCursor;
 pub mod advance;
 pub mod compression;
 pub mod cursor;
+pub mod downsample;
+pub mod forward;
+mod indexed_frame;
 #[cfg(test)]
 mod test;
 
@@ -73,17 +85,134 @@ open_source_shim!();
 /// Data and Index files are append-only and never modified (only ever
 /// removed).
 ///
-/// Data and Index files are sharded by SHARD_TIME - e.g. any one file
-/// only contains data or index entries whose timestamps are congruent
-/// modulo SHARD_TIME. This allows data and index files to be cleaned
-/// up by just unlinking the files.
+/// After each complete write, the writer publishes the index length
+/// covered by complete entries in a per-shard committed file. Readers
+/// only look at the index up to that length, so a sample is visible
+/// once both its data and index entry are fully written, without any
+/// locking between reader and writer. The committed length is stored
+/// twice, the second time bitwise inverted, so a torn read is detected.
+/// Readers fall back to the index file length if the committed file is
+/// missing or invalid, e.g. for stores written by older versions.
+///
+/// Data and Index files are sharded by the shard time of the store -
+/// e.g. any one file only contains data or index entries whose
+/// timestamps are congruent modulo the shard time. This allows data and
+/// index files to be cleaned up by just unlinking the files. The shard
+/// time is fixed when the store is created and kept in its metadata file
+/// (see `StoreMetadata`), defaulting to SHARD_TIME.
+///
+/// When the writer rolls over to a new shard, it writes a manifest
+/// file for the shard it just closed on a separate thread, holding the
+/// size and crc32 of its data and index files. Closed shards are never written to
+/// again, so a copied or snapshotted store can be checked against
+/// the manifests (see `verify_store`). A shard without a manifest was
+/// either still active or its recorder stopped before rolling over.
 
 #[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct DataFrame {
     pub sample: model::Sample,
+    /// Set on the aggregate frames of a downsampled store, see
+    /// `downsample::Aggregate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregate: Option<downsample::Aggregate>,
+}
+
+/// Shard time in seconds of stores without a metadata file, a day
+pub const SHARD_TIME: u64 = 24 * 60 * 60;
+
+const METADATA_FILE: &str = "metadata";
+
+/// Settings fixed when a store is created, kept in its metadata file as
+/// "<key> <value>" lines. Stores written by older versions have no
+/// metadata file and use the defaults.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StoreMetadata {
+    /// Seconds covered by each shard. Evenly divides a day so that shards
+    /// stay aligned to days.
+    pub shard_time: u64,
 }
 
-const SHARD_TIME: u64 = 24 * 60 * 60;
+impl Default for StoreMetadata {
+    fn default() -> Self {
+        Self {
+            shard_time: SHARD_TIME,
+        }
+    }
+}
+
+impl StoreMetadata {
+    pub fn new(shard_time: u64) -> Result<Self> {
+        if shard_time == 0 || !SHARD_TIME.is_multiple_of(shard_time) {
+            bail!(
+                "Invalid shard time {}s, it must evenly divide a day, e.g. 3600 for hourly shards",
+                shard_time
+            );
+        }
+        Ok(Self { shard_time })
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        let mut metadata = Self::default();
+        for line in content.lines() {
+            match line.split_once(' ') {
+                Some(("shard_time", value)) => {
+                    let shard_time = value
+                        .parse()
+                        .with_context(|| format!("Invalid shard time: {}", value))?;
+                    metadata = Self::new(shard_time)?;
+                }
+                // Keys added by newer versions
+                Some(_) => {}
+                None => bail!("Invalid metadata line: {}", line),
+            }
+        }
+        Ok(metadata)
+    }
+
+    /// Read the metadata of the store at `dir`, the defaults if it has no
+    /// metadata file or does not exist yet
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let path = dir.as_ref().join(METADATA_FILE);
+        match fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content)
+                .with_context(|| format!("Failed to parse store metadata {}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Create the store at `dir` with this metadata, or check that the
+    /// existing store there was created with it. The shard time of a store
+    /// cannot change, as readers locate samples by it.
+    pub fn init<P: AsRef<Path>>(&self, dir: P) -> Result<()> {
+        let dir = dir.as_ref();
+        let path = dir.join(METADATA_FILE);
+        let existing = if path.exists() || !get_index_files(dir).unwrap_or_default().is_empty() {
+            Some(Self::load(dir)?)
+        } else {
+            None
+        };
+        match existing {
+            Some(existing) if existing != *self => bail!(
+                "Store {} was created with {}s shards, cannot change them to {}s",
+                dir.display(),
+                existing.shard_time,
+                self.shard_time
+            ),
+            Some(_) => Ok(()),
+            None => {
+                fs::create_dir_all(dir)
+                    .with_context(|| format!("Failed to create store path: {}", dir.display()))?;
+                let tmp_path = dir.join(format!("{}.tmp", METADATA_FILE));
+                fs::write(&tmp_path, format!("shard_time {}\n", self.shard_time)).with_context(
+                    || format!("Failed to write metadata file: {}", tmp_path.display()),
+                )?;
+                fs::rename(&tmp_path, &path)
+                    .with_context(|| format!("Failed to rename metadata file: {}", path.display()))
+            }
+        }
+    }
+}
 
 // Number of bits used by other bit flags in IndexEntry before the
 // chunk compress flags.
@@ -118,6 +247,10 @@ bitflags! {
         /// can also occur if possible data corruption has been
         /// detected.
         const CHUNK_COMPRESS_SIZE_PO2 = MAX_CHUNK_COMPRESS_SIZE_PO2 << CHUNK_COMPRESS_SHIFT;
+        /// If set, data item is serialized in the field-indexed
+        /// container (see `Format::Indexed`). Mutually exclusive with
+        /// `CBOR`.
+        const INDEXED = 0x40;
     }
 }
 
@@ -158,6 +291,23 @@ const INDEX_ENTRY_SIZE: usize = std::mem::size_of::<IndexEntry>();
 const INDEX_ENTRY_SIZE_PO2: u32 = INDEX_ENTRY_SIZE.trailing_zeros();
 const_assert_eq!(INDEX_ENTRY_SIZE, 32);
 
+const COMMITTED_LEN_SIZE: usize = 16;
+
+fn encode_committed_len(len: u64) -> [u8; COMMITTED_LEN_SIZE] {
+    let mut buf = [0; COMMITTED_LEN_SIZE];
+    buf[..8].copy_from_slice(&len.to_le_bytes());
+    buf[8..].copy_from_slice(&(!len).to_le_bytes());
+    buf
+}
+
+/// Returns None if `buf` is not a complete and consistent committed length.
+fn decode_committed_len(buf: &[u8]) -> Option<u64> {
+    let buf: &[u8; COMMITTED_LEN_SIZE] = buf.try_into().ok()?;
+    let len = u64::from_le_bytes(buf[..8].try_into().ok()?);
+    let check = u64::from_le_bytes(buf[8..].try_into().ok()?);
+    (len == !check).then_some(len)
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct ChunkSizePo2(pub u32);
 
@@ -166,6 +316,29 @@ pub enum CompressionMode {
     None,
     Zstd,
     ZstdDictionary(ChunkSizePo2),
+    /// Dictionary compression with the chunk size of each shard picked by
+    /// `auto_chunk_size_po2` from the frames written so far. The chunk size
+    /// is recorded in the index entries like for a fixed chunk size.
+    ZstdDictionaryAuto,
+}
+
+/// Uncompressed bytes a dictionary compression chunk aims to cover in
+/// `CompressionMode::ZstdDictionaryAuto`. Longer chunks spread the cost of
+/// the poorly compressed key frame over more frames, but frames further
+/// from the key frame share less with it, and readers decompress the key
+/// frame of every chunk they touch.
+const AUTO_CHUNK_BYTES: u64 = 16 << 20;
+const AUTO_MIN_CHUNK_SIZE_PO2: u32 = 1;
+const AUTO_MAX_CHUNK_SIZE_PO2: u32 = 8;
+
+/// Chunk size po2 for frames of `frame_size` serialized bytes, such that a
+/// chunk covers about AUTO_CHUNK_BYTES. Rounded down to a power of 2 and
+/// kept between 2 and 256 frames.
+pub fn auto_chunk_size_po2(frame_size: u64) -> u32 {
+    (AUTO_CHUNK_BYTES / frame_size.max(1))
+        .checked_ilog2()
+        .unwrap_or(0)
+        .clamp(AUTO_MIN_CHUNK_SIZE_PO2, AUTO_MAX_CHUNK_SIZE_PO2)
 }
 
 /// The StoreWriter struct maintains state to put more data in the
@@ -183,11 +356,16 @@ pub struct StoreWriter {
     /// Currently active data file. Append only so cursor always
     /// point to end of file.
     data: File,
+    /// Committed file of the active shard, holding the index length
+    /// readers may look at.
+    committed: File,
     /// Current length of the data file (needed to record offsets in
     /// the index)
     data_len: u64,
     /// Active shard
     shard: u64,
+    /// Seconds covered by each shard, from the store metadata
+    shard_time: u64,
     /// Cached compressor for memory efficiency. Compressor also stores key
     /// frame for dict compression.
     compressor: Option<Compressor>,
@@ -196,6 +374,24 @@ pub struct StoreWriter {
     compression_mode: CompressionMode,
     /// Serialization format of data frames
     format: Format,
+    /// If set, the process map of non-key frames in a dictionary
+    /// compressed chunk is stored as a delta against the key frame.
+    process_delta: bool,
+    /// Process map of the key frame of the current chunk. Only kept if
+    /// `process_delta` is set.
+    process_delta_base: Option<PidMap>,
+    /// Timestamp of the last frame written by this writer. Frames are only
+    /// written in increasing timestamp order, which readers rely on.
+    last_timestamp: Option<u64>,
+    /// Chunk size po2 of the active shard in `ZstdDictionaryAuto` mode,
+    /// chosen on its first write
+    auto_chunk_size_po2: Option<u32>,
+    /// Moving average of the serialized frame size, from which
+    /// `ZstdDictionaryAuto` mode chooses the chunk size of new shards
+    mean_frame_size: Option<u64>,
+    /// Thread writing the manifest of the last closed shard, if any. Shared
+    /// with discarding, which must not race it.
+    manifest_writer: Mutex<Option<JoinHandle<()>>>,
 }
 
 // Given path to the store dir, get a Vec<String> of the index file
@@ -220,6 +416,248 @@ fn get_index_files(path: &Path) -> Result<Vec<String>> {
     Ok(entries)
 }
 
+/// Size and crc32 of the file at `path`. The file is read in chunks so
+/// large shards need not fit in memory.
+fn get_file_checksum(path: &Path) -> Result<(u64, u32)> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut buf = vec![0; 1 << 16];
+    let mut size = 0;
+    let mut crc = CRC32_INIT;
+    loop {
+        let len = file
+            .read(&mut buf)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if len == 0 {
+            return Ok((size, crc));
+        }
+        size += len as u64;
+        crc = crc32_update(crc, &buf[..len]);
+    }
+}
+
+/// Write the manifest of a closed shard. The manifest is written to a
+/// temporary file first and renamed, so a crash never leaves a partial
+/// manifest behind.
+fn write_manifest(dir: &Path, shard: u64) -> Result<()> {
+    let mut content = String::new();
+    for name in [
+        format!("data_{:011}", shard),
+        format!("index_{:011}", shard),
+    ] {
+        let (size, crc) = get_file_checksum(&dir.join(&name))?;
+        content.push_str(&format!("{} {} {:08x}\n", name, size, crc));
+    }
+
+    let manifest_path = dir.join(format!("manifest_{:011}", shard));
+    let tmp_path = dir.join(format!("manifest_{:011}.tmp", shard));
+    fs::write(&tmp_path, content)
+        .with_context(|| format!("Failed to write manifest file: {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, &manifest_path).with_context(|| {
+        format!(
+            "Failed to rename manifest file: {}",
+            manifest_path.display()
+        )
+    })
+}
+
+/// Write the manifest of a closed shard on its own thread, as checksumming
+/// the shard reads all of it. `previous` is the thread writing the manifest
+/// of the shard closed before, which is waited for so that no more than one
+/// manifest is written at a time.
+fn spawn_manifest_writer(
+    logger: slog::Logger,
+    dir: PathBuf,
+    shard: u64,
+    previous: Option<JoinHandle<()>>,
+) -> Option<JoinHandle<()>> {
+    let spawned = std::thread::Builder::new()
+        .name("below_manifest".to_owned())
+        .spawn({
+            let logger = logger.clone();
+            move || {
+                if let Some(previous) = previous {
+                    let _ = previous.join();
+                }
+                // The manifest only helps verifying copies, so failing to
+                // write it must not stop recording
+                if let Err(e) = write_manifest(&dir, shard) {
+                    warn!(logger, "Failed to write shard manifest: {:#}", e);
+                }
+            }
+        });
+    match spawned {
+        Ok(handle) => Some(handle),
+        Err(e) => {
+            warn!(logger, "Failed to spawn shard manifest writer: {:#}", e);
+            None
+        }
+    }
+}
+
+/// Parse a manifest into (file name, size, crc32) entries
+fn parse_manifest(content: &str) -> Result<Vec<(String, u64, u32)>> {
+    content
+        .lines()
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [name, size, crc] => Ok((
+                    name.to_string(),
+                    size.parse::<u64>()
+                        .with_context(|| format!("Invalid size in manifest line: {}", line))?,
+                    u32::from_str_radix(crc, 16)
+                        .with_context(|| format!("Invalid crc in manifest line: {}", line))?,
+                )),
+                _ => bail!("Invalid manifest line: {}", line),
+            }
+        })
+        .collect()
+}
+
+/// Integrity of a single shard as reported by `verify_store`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShardStatus {
+    /// Data and index files match the manifest
+    Ok,
+    /// Newest shard without a manifest. It is likely still being written.
+    Active,
+    /// Older shard without a manifest, but with data for all committed
+    /// index entries. The recorder stopped or crashed before rolling over.
+    Unclosed,
+    /// A file is shorter than recorded in the manifest or needed by the
+    /// committed index entries, e.g. an incomplete copy.
+    Truncated(String),
+    /// A file recorded in the manifest is missing
+    Missing(String),
+    /// A file has a different size or checksum than recorded in the
+    /// manifest
+    Corrupt(String),
+}
+
+impl std::fmt::Display for ShardStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShardStatus::Ok => write!(f, "ok"),
+            ShardStatus::Active => write!(f, "active"),
+            ShardStatus::Unclosed => write!(f, "unclosed (recorder stopped before rollover)"),
+            ShardStatus::Truncated(name) => write!(f, "truncated: {}", name),
+            ShardStatus::Missing(name) => write!(f, "missing: {}", name),
+            ShardStatus::Corrupt(name) => write!(f, "corrupt: {}", name),
+        }
+    }
+}
+
+/// Check a shard against its manifest
+fn verify_manifest(dir: &Path, manifest: &str) -> Result<ShardStatus> {
+    for (name, size, crc) in parse_manifest(manifest)? {
+        let path = dir.join(&name);
+        let actual_size = match fs::metadata(&path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(ShardStatus::Missing(name)),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to stat {}", path.display()));
+            }
+        };
+        if actual_size < size {
+            return Ok(ShardStatus::Truncated(name));
+        }
+        if actual_size > size || get_file_checksum(&path)?.1 != crc {
+            return Ok(ShardStatus::Corrupt(name));
+        }
+    }
+    Ok(ShardStatus::Ok)
+}
+
+/// Check a shard without a manifest. We can't checksum it, but we can
+/// tell whether its files cover everything the committed length says
+/// was written.
+fn verify_unclosed(dir: &Path, shard: u64, newest: bool) -> Result<ShardStatus> {
+    let index_name = format!("index_{:011}", shard);
+    let data_name = format!("data_{:011}", shard);
+    let index_path = dir.join(&index_name);
+    let index = fs::read(&index_path)
+        .with_context(|| format!("Failed to read {}", index_path.display()))?;
+
+    let committed_path = dir.join(format!("committed_{:011}", shard));
+    let committed_len = match fs::read(&committed_path) {
+        Ok(buf) => decode_committed_len(&buf),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", committed_path.display()));
+        }
+    };
+    let index_len = match committed_len {
+        Some(committed_len) if (index.len() as u64) < committed_len => {
+            return Ok(ShardStatus::Truncated(index_name));
+        }
+        Some(committed_len) => committed_len as usize,
+        None => index.len(),
+    };
+
+    // Index entries are appended in data file order, so the last valid
+    // entry tells how long the data file must be
+    let data_needed = index[..index_len - index_len % INDEX_ENTRY_SIZE]
+        .chunks_exact(INDEX_ENTRY_SIZE)
+        .rev()
+        .find_map(|entry| {
+            let field = |range: std::ops::Range<usize>| {
+                let mut buf = [0; 8];
+                buf[..range.len()].copy_from_slice(&entry[range]);
+                u64::from_ne_bytes(buf)
+            };
+            let index_crc = field(INDEX_ENTRY_SIZE - 4..INDEX_ENTRY_SIZE) as u32;
+            let valid = entry[..INDEX_ENTRY_SIZE - 4].crc32() == index_crc;
+            valid.then(|| field(8..16) + field(16..20))
+        })
+        .unwrap_or(0);
+    let data_path = dir.join(&data_name);
+    let data_len = match fs::metadata(&data_path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(ShardStatus::Missing(data_name)),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to stat {}", data_path.display()));
+        }
+    };
+    if data_len < data_needed {
+        return Ok(ShardStatus::Truncated(data_name));
+    }
+
+    if newest {
+        Ok(ShardStatus::Active)
+    } else {
+        Ok(ShardStatus::Unclosed)
+    }
+}
+
+/// Verify the integrity of every shard in the store at `dir`, oldest
+/// first. Closed shards are checked against their manifest. This is
+/// cheap enough to run after copying a store between hosts.
+pub fn verify_store<P: AsRef<Path>>(dir: P) -> Result<Vec<(u64, ShardStatus)>> {
+    let dir = dir.as_ref();
+    let shards = get_index_files(dir)?
+        .iter()
+        .filter_map(|entry| entry.strip_prefix("index_")?.parse::<u64>().ok())
+        .collect::<Vec<_>>();
+
+    let mut statuses = Vec::with_capacity(shards.len());
+    for (i, shard) in shards.iter().enumerate() {
+        let manifest_path = dir.join(format!("manifest_{:011}", shard));
+        let status = match fs::read_to_string(&manifest_path) {
+            Ok(manifest) => verify_manifest(dir, &manifest)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                verify_unclosed(dir, *shard, i + 1 == shards.len())?
+            }
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read {}", manifest_path.display()));
+            }
+        };
+        statuses.push((*shard, status));
+    }
+    Ok(statuses)
+}
+
 enum SerializedFrame<'a> {
     Owned(bytes::Bytes),
     Borrowed(&'a [u8]),
@@ -243,29 +681,58 @@ impl SerializedFrame<'_> {
     }
 }
 
-/// Serialization format. Currently only Cbor is supported.
+/// Serialization format.
 #[derive(Copy, Clone, Debug)]
 pub enum Format {
+    /// The whole data frame as a single CBOR item.
     Cbor,
+    /// Each top-level sample field as a separate CBOR item behind a table
+    /// of contents, so readers can skip decoding fields they do not need.
+    Indexed,
 }
 
-/// Serialize a single data frame with `format` format.
-fn serialize_frame(data: &DataFrame, format: Format) -> Result<bytes::Bytes> {
+/// Serialize a single data frame with `format` format. If `process_base`
+/// is given, the process map is stored as a delta against it.
+fn serialize_frame(
+    data: &DataFrame,
+    format: Format,
+    process_base: Option<&PidMap>,
+) -> Result<bytes::Bytes> {
     match format {
         Format::Cbor => {
+            if process_base.is_some() {
+                bail!("Process delta is not supported by the CBOR format");
+            }
             let bytes = serde_cbor::to_vec(data)?;
             Ok(bytes::Bytes::from(bytes))
         }
+        Format::Indexed => Ok(bytes::Bytes::from(indexed_frame::serialize(
+            data,
+            process_base,
+        )?)),
     }
 }
 
-/// Deserialize a single data frame with `format` format.
-fn deserialize_frame(bytes: &[u8], format: Format) -> Result<DataFrame> {
+/// Deserialize a single data frame with `format` format. Formats that
+/// support partial reads only decode the fields needed to build the
+/// sub-models in `parts`; the other fields are left at their default value.
+/// `process_base` is called to get the process map of the chunk's key frame
+/// if the process map is stored as a delta.
+fn deserialize_frame<F>(
+    bytes: &[u8],
+    format: Format,
+    parts: ModelParts,
+    process_base: F,
+) -> Result<DataFrame>
+where
+    F: FnOnce() -> Result<Rc<PidMap>>,
+{
     match format {
         Format::Cbor => {
             let data_frame = serde_cbor::from_slice(bytes)?;
             Ok(data_frame)
         }
+        Format::Indexed => indexed_frame::deserialize(bytes, parts, process_base),
     }
 }
 
@@ -291,14 +758,16 @@ impl StoreWriter {
         compression_mode: CompressionMode,
         format: Format,
     ) -> Result<Self> {
-        let shard = calculate_shard(timestamp);
-        Self::new_with_shard(logger, path, shard, compression_mode, format)
+        let shard_time = StoreMetadata::load(&path)?.shard_time;
+        let shard = calculate_shard(timestamp, shard_time);
+        Self::new_with_shard(logger, path, shard, shard_time, compression_mode, format)
     }
 
     fn new_with_shard<P: AsRef<Path>>(
         logger: slog::Logger,
         path: P,
         shard: u64,
+        shard_time: u64,
         compression_mode: CompressionMode,
         format: Format,
     ) -> Result<Self> {
@@ -308,12 +777,14 @@ impl StoreWriter {
             })?;
         }
 
-        let (data_path, index_path) = {
+        let (data_path, index_path, committed_path) = {
             let mut data_path = path.as_ref().to_path_buf();
             let mut index_path = data_path.clone();
+            let mut committed_path = data_path.clone();
             data_path.push(format!("data_{:011}", shard));
             index_path.push(format!("index_{:011}", shard));
-            (data_path, index_path)
+            committed_path.push(format!("committed_{:011}", shard));
+            (data_path, index_path, committed_path)
         };
 
         let index = OpenOptions::new()
@@ -358,20 +829,107 @@ impl StoreWriter {
             })?
             .len();
 
+        // Protected by the index file lock
+        let committed = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(committed_path.as_path())
+            .with_context(|| {
+                format!(
+                    "Failed to open committed file: {}",
+                    committed_path.display()
+                )
+            })?;
+
+        // A closed shard may be reopened if the clock went backwards. Its
+        // manifest would no longer match once we append to it.
+        let manifest_path = path.as_ref().join(format!("manifest_{:011}", shard));
+        match std::fs::remove_file(&manifest_path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                return Err(e).context(format!(
+                    "Failed to remove manifest file: {}",
+                    manifest_path.display()
+                ));
+            }
+            _ => {}
+        };
+
         Ok(StoreWriter {
             logger,
             dir: path.as_ref().to_path_buf(),
             index,
             data,
+            committed,
             data_len,
             shard,
+            shard_time,
             // First compressed write initializes the compressor
             compressor: None,
             compression_mode,
             format,
+            process_delta: false,
+            process_delta_base: None,
+            last_timestamp: None,
+            auto_chunk_size_po2: None,
+            mean_frame_size: None,
+            manifest_writer: Mutex::new(None),
         })
     }
 
+    /// Store the process map of non-key frames as a delta against the key
+    /// frame of their chunk. Consecutive samples mostly repeat the same
+    /// processes, so this considerably shrinks stores on hosts with many
+    /// processes. Requires the indexed format and dictionary compression.
+    pub fn set_process_delta(&mut self, process_delta: bool) -> Result<()> {
+        if process_delta {
+            if !matches!(self.format, Format::Indexed) {
+                bail!("Process delta requires the indexed format");
+            }
+            if !matches!(
+                self.compression_mode,
+                CompressionMode::ZstdDictionary(_) | CompressionMode::ZstdDictionaryAuto
+            ) {
+                bail!("Process delta requires dictionary compression");
+            }
+        }
+        self.process_delta = process_delta;
+        self.process_delta_base = None;
+        Ok(())
+    }
+
+    /// Chunk size po2 if dictionary compression is used. In auto mode, this
+    /// is only set once the first frame of the shard is written.
+    fn dict_chunk_size_po2(&self) -> Option<u32> {
+        match self.compression_mode {
+            CompressionMode::None | CompressionMode::Zstd => None,
+            CompressionMode::ZstdDictionary(ChunkSizePo2(chunk_size_po2)) => Some(chunk_size_po2),
+            CompressionMode::ZstdDictionaryAuto => self.auto_chunk_size_po2,
+        }
+    }
+
+    /// Choose the chunk size of the active shard in auto mode, from the
+    /// frames written so far, or from `data` for the first frame of this
+    /// writer.
+    fn choose_auto_chunk_size(&mut self, data: &DataFrame) -> Result<()> {
+        let frame_size = match self.mean_frame_size {
+            Some(mean_frame_size) => mean_frame_size,
+            None => serialize_frame(data, self.format, None)
+                .context("Failed to serialize data frame")?
+                .len() as u64,
+        };
+        let chunk_size_po2 = auto_chunk_size_po2(frame_size);
+        info!(
+            self.logger,
+            "Using dictionary compression chunk size {} for shard {} (frame size {} bytes)",
+            1 << chunk_size_po2,
+            self.shard,
+            frame_size,
+        );
+        self.auto_chunk_size_po2 = Some(chunk_size_po2);
+        Ok(())
+    }
+
     /// The index file is padded to the next (1 << alignment_po2) aligned
     /// boundary. Both the original and aligned lengths are then returned.
     /// Mostly used to align index file with INDEX_ENTRY_SIZE or chunk size if
@@ -399,21 +957,35 @@ impl StoreWriter {
     /// tuple consisting of:
     ///   1) Raw bytes to write to the data file
     ///   2) Flags to write to the index entry
+    ///   3) Size of the serialized frame before compression
     /// For compressed write, the Compressor will be initialized if None, and
     /// potentially updated. is_key_frame is used to indicate the start of a new
-    /// chunk if dictionary compression is enabled.
+    /// chunk if dictionary compression is enabled. If process delta is
+    /// enabled, process_delta_base is updated on key frames and used as the
+    /// delta base otherwise.
     fn get_bytes_and_flags_for_frame(
         &self,
         data_frame: &DataFrame,
         compressor: &mut Option<Compressor>,
+        process_delta_base: &mut Option<PidMap>,
         is_key_frame: bool,
-    ) -> Result<(bytes::Bytes, IndexEntryFlags)> {
+    ) -> Result<(bytes::Bytes, IndexEntryFlags, usize)> {
         let mut flags = match self.format {
             Format::Cbor => IndexEntryFlags::CBOR,
+            Format::Indexed => IndexEntryFlags::INDEXED,
+        };
+        let process_base = if !self.process_delta {
+            None
+        } else if is_key_frame {
+            *process_delta_base = Some(data_frame.sample.processes.clone());
+            None
+        } else {
+            process_delta_base.as_ref()
         };
         // Get serialized data frame
-        let frame_bytes =
-            serialize_frame(data_frame, self.format).context("Failed to serialize data frame")?;
+        let frame_bytes = serialize_frame(data_frame, self.format, process_base)
+            .context("Failed to serialize data frame")?;
+        let frame_size = frame_bytes.len();
         let serialized = match self.compression_mode {
             CompressionMode::None => frame_bytes,
             CompressionMode::Zstd => {
@@ -423,7 +995,10 @@ impl StoreWriter {
                     .compress_with_dict_reset(&frame_bytes)
                     .context("Failed to compress data")?
             }
-            CompressionMode::ZstdDictionary(ChunkSizePo2(chunk_size_po2)) => {
+            CompressionMode::ZstdDictionary(_) | CompressionMode::ZstdDictionaryAuto => {
+                let chunk_size_po2 = self
+                    .dict_chunk_size_po2()
+                    .expect("bug: chunk size not chosen");
                 flags |= IndexEntryFlags::COMPRESSED;
                 flags
                     .set_chunk_compress_size_po2(chunk_size_po2)
@@ -444,29 +1019,29 @@ impl StoreWriter {
                 }
             }
         };
-        Ok((serialized, flags))
+        Ok((serialized, flags, frame_size))
     }
 
     /// Store data with corresponding timestamp in current shard.
     /// Fails if data does not belong to current shard. Errors may be
     /// returned if file operations fail.
     fn put_in_current_shard(&mut self, timestamp: SystemTime, data: &DataFrame) -> Result<()> {
-        let shard = calculate_shard(timestamp);
+        let shard = calculate_shard(timestamp, self.shard_time);
         if shard != self.shard {
             panic!("Can't write data to shard as it belongs to different shard")
         }
 
+        if matches!(self.compression_mode, CompressionMode::ZstdDictionaryAuto)
+            && self.auto_chunk_size_po2.is_none()
+        {
+            self.choose_auto_chunk_size(data)?;
+        }
         // PO2 chunk size in bytes if dict compression is used, otherwise 0.
-        let chunk_alignment_po2 =
-            if let CompressionMode::ZstdDictionary(ChunkSizePo2(chunk_size_po2)) =
-                self.compression_mode
-            {
-                // chunk_size_po2 is in number of entries. Add with entry size
-                // po2 to get size in bytes po2.
-                chunk_size_po2 + INDEX_ENTRY_SIZE_PO2
-            } else {
-                0
-            };
+        // chunk_size_po2 is in number of entries. Add with entry size po2 to
+        // get size in bytes po2.
+        let chunk_alignment_po2 = self
+            .dict_chunk_size_po2()
+            .map_or(0, |chunk_size_po2| chunk_size_po2 + INDEX_ENTRY_SIZE_PO2);
         // If dict compression is used but Compressor uninitialized, e.g. new
         // shard, previous write failed, then pad index to start a new chunk.
         // Otherwise pad to ensure index file is aligned with INDEX_ENTRY_SIZE.
@@ -508,12 +1083,20 @@ impl StoreWriter {
         // discarded and a new one be created in the next write. No-op if
         // compression is not used.
         let mut compressor = self.compressor.take();
+        // Same for the process delta base, which belongs to the chunk the
+        // compressor dictionary was loaded from.
+        let mut process_delta_base = self.process_delta_base.take();
         // If dict compression is used and the index file is chunk aligned, the
         // current frame is the key frame.
         let is_key_frame =
             chunk_alignment_po2 != 0 && aligned_len.trailing_zeros() >= chunk_alignment_po2;
-        let (serialized, flags) = self
-            .get_bytes_and_flags_for_frame(data, &mut compressor, is_key_frame)
+        let (serialized, flags, frame_size) = self
+            .get_bytes_and_flags_for_frame(
+                data,
+                &mut compressor,
+                &mut process_delta_base,
+                is_key_frame,
+            )
             .context("Failed to get serialized frame and flags")?;
 
         // Appends to data file are large and cannot be atomic. We
@@ -575,32 +1158,100 @@ impl StoreWriter {
                 .write_all(entry_slice)
                 .context("Failed to write entry to index file")?;
         }
+        // The sample is stored at this point. Failing to publish it only
+        // delays when readers see it, until the next successful write.
+        let committed_len = aligned_len + INDEX_ENTRY_SIZE as u64;
+        if let Err(e) = self
+            .committed
+            .write_all_at(&encode_committed_len(committed_len), 0)
+        {
+            warn!(
+                self.logger,
+                "Failed to publish committed index length: committed_{:011}: {}", self.shard, e
+            );
+        }
 
         // Set compressor only after successful writes. No-op if not in
         // compression mode
         self.compressor = compressor;
+        self.process_delta_base = process_delta_base;
+        self.last_timestamp = Some(index_entry.timestamp);
+        let frame_size = frame_size as u64;
+        self.mean_frame_size = Some(match self.mean_frame_size {
+            Some(mean) => mean - mean / 8 + frame_size / 8,
+            None => frame_size,
+        });
         Ok(())
     }
 
+    /// Timestamp of the last frame written by this writer, which the next
+    /// one has to be after
+    pub fn last_timestamp(&self) -> Option<SystemTime> {
+        self.last_timestamp
+            .map(|timestamp| UNIX_EPOCH + Duration::from_secs(timestamp))
+    }
+
+    /// Whether `put` takes a frame at `timestamp`, i.e. it is in a later
+    /// second than the last frame, as frames are stored by the second
+    pub fn is_after_last(&self, timestamp: SystemTime) -> bool {
+        self.last_timestamp
+            .is_none_or(|last| get_unix_timestamp(timestamp) > last)
+    }
+
     /// Store data with corresponding timestamp. Returns true if a new shard
     /// is created and data is written successfully. Errors may be returned if
     /// file operations fail.
     pub fn put(&mut self, timestamp: SystemTime, data: &DataFrame) -> Result<bool> {
-        let shard = calculate_shard(timestamp);
+        if let Some(last) = self.last_timestamp() {
+            if !self.is_after_last(timestamp) {
+                bail!(
+                    "Timestamp {} is not after the last stored one {}, the clock may have gone backwards",
+                    get_unix_timestamp(timestamp),
+                    get_unix_timestamp(last)
+                );
+            }
+        }
+        let shard = calculate_shard(timestamp, self.shard_time);
         if shard != self.shard {
+            if shard < self.shard {
+                // Opening a closed shard removes its manifest, which must
+                // not be written again afterwards
+                self.wait_for_manifest();
+            }
             // We just recreate the StoreWriter since this is a new shard
             let mut writer = Self::new_with_shard(
                 self.logger.clone(),
                 self.dir.as_path(),
                 shard,
+                self.shard_time,
                 self.compression_mode,
                 self.format,
             )?;
+            writer.process_delta = self.process_delta;
+            writer.last_timestamp = self.last_timestamp;
+            // The new shard chooses its chunk size from the frames so far
+            writer.mean_frame_size = self.mean_frame_size;
             // Set self to new shard only if we succeed in writing the first
             // frame. If we don't do this, we may "forget" returning a true
             // for a new shard where the first write fails.
             writer.put_in_current_shard(timestamp, data)?;
+            let closed_shard = self.shard;
+            let previous_manifest_writer = self
+                .manifest_writer
+                .lock()
+                .expect("Manifest writer lock poisoned")
+                .take();
             *self = writer;
+            self.manifest_writer = Mutex::new(if closed_shard < shard {
+                spawn_manifest_writer(
+                    self.logger.clone(),
+                    self.dir.clone(),
+                    closed_shard,
+                    previous_manifest_writer,
+                )
+            } else {
+                previous_manifest_writer
+            });
             Ok(true)
         } else {
             self.put_in_current_shard(timestamp, data)?;
@@ -608,6 +1259,62 @@ impl StoreWriter {
         }
     }
 
+    /// Flush the frames written to the active shard to disk. Data is synced
+    /// before the index so that a synced index never points past the data.
+    pub fn sync(&self) -> Result<()> {
+        self.data
+            .sync_data()
+            .with_context(|| format!("Failed to sync data file: data_{:011}", self.shard))?;
+        self.index
+            .sync_data()
+            .with_context(|| format!("Failed to sync index file: index_{:011}", self.shard))?;
+        self.committed.sync_data().with_context(|| {
+            format!(
+                "Failed to sync committed file: committed_{:011}",
+                self.shard
+            )
+        })
+    }
+
+    /// Close and reopen the files of the active shard, e.g. after an
+    /// external tool moved or removed them. The next frame starts a new
+    /// compression chunk. Shards stay aligned to the shard time, so this
+    /// does not start a new shard early.
+    pub fn reopen(&mut self) -> Result<()> {
+        // The new file handles can only take the locks once the old ones
+        // released them
+        for file in [&self.index, &self.data] {
+            nix::fcntl::flock(file.as_raw_fd(), nix::fcntl::FlockArg::Unlock)
+                .context("Failed to release store file lock")?;
+        }
+        let mut writer = Self::new_with_shard(
+            self.logger.clone(),
+            self.dir.as_path(),
+            self.shard,
+            self.shard_time,
+            self.compression_mode,
+            self.format,
+        )?;
+        writer.process_delta = self.process_delta;
+        writer.last_timestamp = self.last_timestamp;
+        writer.auto_chunk_size_po2 = self.auto_chunk_size_po2;
+        writer.mean_frame_size = self.mean_frame_size;
+        *self = writer;
+        Ok(())
+    }
+
+    /// Wait for the manifest of the last closed shard to be written
+    fn wait_for_manifest(&self) {
+        let manifest_writer = self
+            .manifest_writer
+            .lock()
+            .expect("Manifest writer lock poisoned")
+            .take();
+        if let Some(manifest_writer) = manifest_writer {
+            let _ = manifest_writer.join();
+        }
+    }
+
     /// Discard shards from the oldest first until f(shard_timestamp) is true
     /// or we've reached the current shard. Returns true if f(shard_timestamp)
     /// is true for the last shard visited or false otherwise.
@@ -640,6 +1347,9 @@ impl StoreWriter {
                 return Ok(false);
             }
 
+            // A manifest written after the removal would be left behind
+            self.wait_for_manifest();
+
             // Removal order doesn't matter at all, it's the
             // responsibility of the read side to handle missing files
             let mut index_path = self.dir.clone();
@@ -667,6 +1377,32 @@ impl StoreWriter {
                 }
                 _ => {}
             };
+
+            let mut committed_path = self.dir.clone();
+            committed_path.push(format!("committed_{:011}", entry_shard));
+
+            match std::fs::remove_file(&committed_path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    return Err(e).context(format!(
+                        "Failed to remove committed file: {}",
+                        committed_path.display()
+                    ));
+                }
+                _ => {}
+            };
+
+            let mut manifest_path = self.dir.clone();
+            manifest_path.push(format!("manifest_{:011}", entry_shard));
+
+            match std::fs::remove_file(&manifest_path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    return Err(e).context(format!(
+                        "Failed to remove manifest file: {}",
+                        manifest_path.display()
+                    ));
+                }
+                _ => {}
+            };
         }
         Ok(false)
     }
@@ -676,7 +1412,7 @@ impl StoreWriter {
     /// We do not modify index and data files. We just look for files
     /// which can only contain earlier data and remove them.
     pub fn discard_earlier(&self, timestamp: SystemTime) -> Result<()> {
-        let shard = calculate_shard(timestamp);
+        let shard = calculate_shard(timestamp, self.shard_time);
         self.discard_until(|shard_timestamp| shard_timestamp >= shard)?;
         Ok(())
     }
@@ -693,6 +1429,14 @@ impl StoreWriter {
     }
 }
 
+impl Drop for StoreWriter {
+    /// Finish writing the manifest of the last closed shard, so it is not
+    /// left out when recording stops right after rolling over.
+    fn drop(&mut self) {
+        self.wait_for_manifest();
+    }
+}
+
 /// Direction to scan for next sample
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Direction {
@@ -756,6 +1500,8 @@ pub trait Store {
 
 pub struct LocalStore {
     store_cursor: StoreCursor,
+    // Cursor of the downsampled store, if the store has one
+    downsampled_cursor: Option<StoreCursor>,
 }
 
 pub struct RemoteStore {
@@ -764,10 +1510,23 @@ pub struct RemoteStore {
 
 impl LocalStore {
     pub fn new(logger: slog::Logger, dir: PathBuf) -> Self {
+        let downsampled_dir = downsample::downsampled_dir(&dir);
         Self {
+            downsampled_cursor: downsampled_dir
+                .is_dir()
+                .then(|| StoreCursor::new(logger.clone(), downsampled_dir)),
             store_cursor: StoreCursor::new(logger, dir),
         }
     }
+
+    /// Only decode the sample fields needed to build the given sub-models,
+    /// if the stored format allows it.
+    pub fn set_model_parts(&mut self, parts: ModelParts) {
+        self.store_cursor.set_model_parts(parts);
+        if let Some(cursor) = self.downsampled_cursor.as_mut() {
+            cursor.set_model_parts(parts);
+        }
+    }
 }
 
 impl RemoteStore {
@@ -786,8 +1545,36 @@ impl Store for LocalStore {
         timestamp: SystemTime,
         direction: Direction,
     ) -> Result<Option<(SystemTime, Self::SampleType)>> {
-        self.store_cursor
-            .get_next(&get_unix_timestamp(timestamp), direction)
+        let key = get_unix_timestamp(timestamp);
+        let full = self.store_cursor.get_next(&key, direction)?;
+        let downsampled_cursor = match self.downsampled_cursor.as_mut() {
+            Some(cursor) => cursor,
+            None => return Ok(full),
+        };
+        // Consecutive full samples are a collection interval apart, so a
+        // sample further away than that means the full samples around the
+        // key are missing, e.g. discarded. Only then may the downsampled
+        // store have a closer frame.
+        let distance =
+            |(timestamp, _): &(SystemTime, DataFrame)| get_unix_timestamp(*timestamp).abs_diff(key);
+        let full_missing = full.as_ref().is_none_or(|full| {
+            full.1
+                .sample
+                .interval_s
+                .is_some_and(|interval_s| distance(full) > interval_s)
+        });
+        if !full_missing {
+            return Ok(full);
+        }
+        let downsampled = downsampled_cursor.get_next(&key, direction)?;
+        // Ties go to the full store
+        match (full, downsampled) {
+            (Some(full), Some(downsampled)) if distance(&downsampled) < distance(&full) => {
+                Ok(Some(downsampled))
+            }
+            (None, downsampled) => Ok(downsampled),
+            (full, _) => Ok(full),
+        }
     }
 }
 
@@ -844,13 +1631,19 @@ const CRC32_TABLE: [u32; 256] = [
     0xB3667A2E, 0xC4614AB8, 0x5D681B02, 0x2A6F2B94, 0xB40BBE37, 0xC30C8EA1, 0x5A05DF1B, 0x2D02EF8D,
 ];
 
+const CRC32_INIT: u32 = 0xFFFF_FFFF;
+
+/// Continue a crc32 computation over `bytes`, starting from `CRC32_INIT`
+fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    for byte in bytes {
+        crc = (crc >> 8) ^ CRC32_TABLE[((crc & 0xFF) as u8 ^ *byte) as usize];
+    }
+    crc
+}
+
 impl Crc32 for [u8] {
     fn crc32(&self) -> u32 {
-        let mut crc: u32 = 0xFFFF_FFFF;
-        for byte in self {
-            crc = (crc >> 8) ^ CRC32_TABLE[((crc & 0xFF) as u8 ^ *byte) as usize];
-        }
-        crc
+        crc32_update(CRC32_INIT, self)
     }
 }
 
@@ -868,10 +1661,10 @@ impl Crc32 for IndexEntry {
 }
 
 // This is the timestamp rounded down to the nearest
-// multiple of SHARD_TIME
-fn calculate_shard(timestamp: SystemTime) -> u64 {
+// multiple of shard_time
+fn calculate_shard(timestamp: SystemTime, shard_time: u64) -> u64 {
     let timestamp_secs = get_unix_timestamp(timestamp);
-    let shard_rem = timestamp_secs % SHARD_TIME;
+    let shard_rem = timestamp_secs % shard_time;
     timestamp_secs - shard_rem
 }
 
@@ -885,6 +1678,11 @@ mod tests {
 
     use super::*;
 
+    /// Shard of the timestamp in a store with the default shard time
+    fn calculate_shard(timestamp: SystemTime) -> u64 {
+        super::calculate_shard(timestamp, SHARD_TIME)
+    }
+
     fn get_logger() -> slog::Logger {
         let plain = slog_term::PlainSyncDecorator::new(std::io::stderr());
         slog::Logger::root(slog_term::FullFormat::new(plain).build().fuse(), slog::o!())
@@ -925,6 +1723,41 @@ mod tests {
                     $func(CompressionMode::ZstdDictionary(ChunkSizePo2(2)), Format::Cbor);
                 }
             }
+
+            paste! {
+                #[test]
+                fn [<$name _auto_dict_compressed_cbor>]() {
+                    $func(CompressionMode::ZstdDictionaryAuto, Format::Cbor);
+                }
+            }
+
+            paste! {
+                #[test]
+                fn [<$name _uncompressed_indexed>]() {
+                    $func(CompressionMode::None, Format::Indexed);
+                }
+            }
+
+            paste! {
+                #[test]
+                fn [<$name _compressed_indexed>]() {
+                    $func(CompressionMode::Zstd, Format::Indexed);
+                }
+            }
+
+            paste! {
+                #[test]
+                fn [<$name _dict_compressed_indexed>]() {
+                    $func(CompressionMode::ZstdDictionary(ChunkSizePo2(2)), Format::Indexed);
+                }
+            }
+
+            paste! {
+                #[test]
+                fn [<$name _auto_dict_compressed_indexed>]() {
+                    $func(CompressionMode::ZstdDictionaryAuto, Format::Indexed);
+                }
+            }
         };
     }
 
@@ -956,6 +1789,12 @@ mod tests {
                 CompressionMode::ZstdDictionary(ChunkSizePo2(3)),
                 Format::Cbor,
             ),
+            (CompressionMode::None, Format::Indexed),
+            (CompressionMode::Zstd, Format::Indexed),
+            (
+                CompressionMode::ZstdDictionary(ChunkSizePo2(2)),
+                Format::Indexed,
+            ),
         ];
         // State sequence that contains all possible transitions
         let state_sequence = states
@@ -990,6 +1829,82 @@ mod tests {
         }
     }
 
+    #[test]
+    fn writer_reopen() {
+        let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        let ts = std::time::UNIX_EPOCH + Duration::from_secs(SHARD_TIME);
+        let mut writer = StoreWriter::new_with_timestamp(
+            get_logger(),
+            &dir,
+            ts,
+            CompressionMode::ZstdDictionary(ChunkSizePo2(2)),
+            Format::Cbor,
+        )
+        .expect("Failed to create store");
+        for i in 0..3 {
+            let mut frame = DataFrame::default();
+            frame.sample.cgroup.memory_current = Some(i);
+            writer
+                .put(ts + Duration::from_secs(i as u64), &frame)
+                .expect("Failed to store data");
+            writer.sync().expect("Failed to sync store");
+            writer.reopen().expect("Failed to reopen store");
+        }
+        // The reopened writer holds the locks again
+        assert!(
+            StoreWriter::new_with_timestamp(
+                get_logger(),
+                &dir,
+                ts,
+                CompressionMode::None,
+                Format::Cbor,
+            )
+            .is_err()
+        );
+
+        let mut store_cursor = StoreCursor::new(get_logger(), dir.path().to_path_buf());
+        for i in 0..3 {
+            let frame = store_cursor
+                .get_next(
+                    &get_unix_timestamp(ts + Duration::from_secs(i as u64)),
+                    Direction::Forward,
+                )
+                .expect("Failed to read sample")
+                .expect("Did not find stored sample");
+            assert_ts!(frame.0, ts + Duration::from_secs(i as u64));
+            assert_eq!(frame.1.sample.cgroup.memory_current, Some(i));
+        }
+    }
+
+    #[test]
+    fn writer_rejects_non_increasing_timestamps() {
+        let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        let ts = std::time::UNIX_EPOCH + Duration::from_secs(SHARD_TIME);
+        let mut writer = StoreWriter::new(get_logger(), &dir, CompressionMode::None, Format::Cbor)
+            .expect("Failed to create store");
+        assert_eq!(writer.last_timestamp(), None);
+        let frame = DataFrame::default();
+        writer
+            .put(ts + Duration::from_secs(5), &frame)
+            .expect("Failed to store data");
+        assert_eq!(writer.last_timestamp(), Some(ts + Duration::from_secs(5)));
+        // Same second and a clock stepped backwards
+        assert!(!writer.is_after_last(ts + Duration::from_millis(5500)));
+        assert!(writer.put(ts + Duration::from_secs(5), &frame).is_err());
+        assert!(
+            writer
+                .put(ts + Duration::from_millis(5500), &frame)
+                .is_err()
+        );
+        assert!(writer.put(ts, &frame).is_err());
+        // Also across shards
+        assert!(writer.put(ts - Duration::from_secs(1), &frame).is_err());
+        writer
+            .put(ts + Duration::from_secs(6), &frame)
+            .expect("Failed to store data");
+        assert_eq!(writer.last_timestamp(), Some(ts + Duration::from_secs(6)));
+    }
+
     #[test]
     fn write_index_padding() {
         let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
@@ -1138,6 +2053,72 @@ mod tests {
         assert_eq!(frame.1.sample.cgroup.memory_current, Some(333));
     }
 
+    store_test!(partial_read, _partial_read);
+    fn _partial_read(compression_mode: CompressionMode, format: Format) {
+        let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        let ts = SystemTime::now();
+        {
+            let mut writer = StoreWriter::new(get_logger(), &dir, compression_mode, format)
+                .expect("Failed to create store");
+            let mut frame = DataFrame::default();
+            frame.sample.cgroup.memory_current = Some(333);
+            frame.sample.processes.insert(1, Default::default());
+
+            writer.put(ts, &frame).expect("Failed to store data");
+        }
+
+        let mut store_cursor = StoreCursor::new(get_logger(), dir.path().to_path_buf());
+        store_cursor.set_model_parts(ModelParts {
+            cgroup: true,
+            ..ModelParts::NONE
+        });
+        let frame = store_cursor
+            .get_next(&get_unix_timestamp(ts), Direction::Forward)
+            .expect("Failed to read sample")
+            .expect("Did not find stored sample");
+        assert_ts!(frame.0, ts);
+        assert_eq!(frame.1.sample.cgroup.memory_current, Some(333));
+        // Only the indexed format can skip decoding the process map
+        match format {
+            Format::Cbor => assert_eq!(frame.1.sample.processes.len(), 1),
+            Format::Indexed => assert!(frame.1.sample.processes.is_empty()),
+        }
+    }
+
+    store_test!(dstate_read, _dstate_read);
+    fn _dstate_read(compression_mode: CompressionMode, format: Format) {
+        let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        let ts = SystemTime::now();
+        {
+            let mut writer = StoreWriter::new(get_logger(), &dir, compression_mode, format)
+                .expect("Failed to create store");
+            let mut frame = DataFrame::default();
+            let mut pidinfo = procfs::PidInfo::default();
+            pidinfo.stat.state = Some(procfs::PidState::UninterruptibleSleep);
+            pidinfo.cgroup = "/a.slice".to_owned();
+            frame.sample.processes.insert(1, pidinfo);
+
+            writer.put(ts, &frame).expect("Failed to store data");
+        }
+
+        // The parts dumping system and cgroup models request
+        let mut advance =
+            crate::advance::new_advance_local(get_logger(), dir.path().to_path_buf(), ts);
+        advance.set_model_parts(ModelParts {
+            system: true,
+            cgroup: true,
+            dstate: true,
+            ..ModelParts::NONE
+        });
+        advance.initialize();
+        let model = advance
+            .jump_sample_to(ts)
+            .expect("Did not find stored sample");
+        assert_eq!(model.system.dstate.and_then(|d| d.count), Some(1));
+        assert_eq!(model.cgroup.data.dstate.and_then(|d| d.count), Some(1));
+        assert!(model.process.processes.is_empty());
+    }
+
     store_test!(simple_put_read_10, _simple_put_read_10);
     fn _simple_put_read_10(compression_mode: CompressionMode, format: Format) {
         let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
@@ -1318,6 +2299,52 @@ mod tests {
         assert_eq!(frame.1.sample.cgroup.memory_current, Some(222));
     }
 
+    store_test!(read_up_to_committed_len, _read_up_to_committed_len);
+    fn _read_up_to_committed_len(compression_mode: CompressionMode, format: Format) {
+        let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        // Keep test on one shard
+        let ts = std::time::UNIX_EPOCH + Duration::from_secs(SHARD_TIME);
+        let committed_path = dir
+            .path()
+            .join(format!("committed_{:011}", calculate_shard(ts)));
+        let mut writer = StoreWriter::new(get_logger(), &dir, compression_mode, format)
+            .expect("Failed to create store");
+        let mut frame = DataFrame::default();
+        for i in 0..3 {
+            frame.sample.cgroup.memory_current = Some(i);
+            writer
+                .put(ts + Duration::from_secs(i as u64), &frame)
+                .expect("Failed to store data");
+        }
+
+        // Pretend the last entry is still being written
+        fs::write(
+            &committed_path,
+            encode_committed_len(2 * INDEX_ENTRY_SIZE as u64),
+        )
+        .expect("Failed to write committed file");
+        let last = StoreCursor::new(get_logger(), dir.path().to_path_buf())
+            .get_next(
+                &get_unix_timestamp(ts + Duration::from_secs(2)),
+                Direction::Reverse,
+            )
+            .expect("Failed to read sample")
+            .expect("Did not find stored sample");
+        assert_eq!(last.1.sample.cgroup.memory_current, Some(1));
+
+        // Falls back to the index length without a valid committed length
+        fs::write(&committed_path, [1; COMMITTED_LEN_SIZE])
+            .expect("Failed to write committed file");
+        let last = StoreCursor::new(get_logger(), dir.path().to_path_buf())
+            .get_next(
+                &get_unix_timestamp(ts + Duration::from_secs(2)),
+                Direction::Reverse,
+            )
+            .expect("Failed to read sample")
+            .expect("Did not find stored sample");
+        assert_eq!(last.1.sample.cgroup.memory_current, Some(2));
+    }
+
     store_test!(
         read_past_the_end_returns_none,
         _read_past_the_end_returns_none
@@ -1520,6 +2547,8 @@ mod tests {
                     .put(timestamp + Duration::from_secs(i as u64), &frame)
                     .expect("Failed to store data");
             }
+            // Include the manifest of the shard closed by the writes
+            writer.wait_for_manifest();
             let dir_size_after = get_dir_size(dir_path_buf.clone());
             assert!(
                 dir_size_after > dir_size,
@@ -1765,4 +2794,323 @@ mod tests {
         assert_ts!(frame.0, ts);
         assert_eq!(frame.1.sample.cgroup.memory_current, Some(333));
     }
+
+    #[test]
+    fn process_delta_requires_indexed_dict_compression() {
+        let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        let mut writer = StoreWriter::new(
+            get_logger(),
+            &dir,
+            CompressionMode::ZstdDictionary(ChunkSizePo2(2)),
+            Format::Cbor,
+        )
+        .expect("Failed to create store");
+        assert!(writer.set_process_delta(true).is_err());
+        drop(writer);
+
+        let mut writer =
+            StoreWriter::new(get_logger(), &dir, CompressionMode::Zstd, Format::Indexed)
+                .expect("Failed to create store");
+        assert!(writer.set_process_delta(true).is_err());
+        assert!(writer.set_process_delta(false).is_ok());
+    }
+
+    #[test]
+    fn process_delta_put_read() {
+        let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        // Keep test on one shard
+        let ts = std::time::UNIX_EPOCH + Duration::from_secs(SHARD_TIME);
+        let get_frame = |i: i32| {
+            let mut frame = DataFrame::default();
+            frame.sample.cgroup.memory_current = Some(i as i64);
+            // Pid 1 never changes, pid 2 changes every frame and pids
+            // 3.. come and go
+            frame.sample.processes.insert(1, Default::default());
+            let pid2 = frame.sample.processes.entry(2).or_default();
+            pid2.stat.comm = Some(format!("comm_{}", i));
+            frame.sample.processes.insert(3 + i, Default::default());
+            frame
+        };
+        {
+            let mut writer = StoreWriter::new(
+                get_logger(),
+                &dir,
+                CompressionMode::ZstdDictionary(ChunkSizePo2(2)),
+                Format::Indexed,
+            )
+            .expect("Failed to create store");
+            writer
+                .set_process_delta(true)
+                .expect("Failed to enable process delta");
+            for i in 0..10 {
+                writer
+                    .put(ts + Duration::from_secs(i as u64), &get_frame(i))
+                    .expect("Failed to store data");
+            }
+        }
+
+        // Read in reverse so that chunks are entered at a non-key frame
+        let mut store_cursor = StoreCursor::new(get_logger(), dir.path().to_path_buf());
+        for i in (0..10).rev() {
+            let frame = store_cursor
+                .get_next(
+                    &get_unix_timestamp(ts + Duration::from_secs(i as u64)),
+                    Direction::Reverse,
+                )
+                .expect("Failed to read sample")
+                .expect("Did not find stored sample");
+            assert_eq!(frame.1, get_frame(i));
+        }
+
+        // Reading without the process map does not need the key frame
+        store_cursor.set_model_parts(ModelParts {
+            cgroup: true,
+            ..ModelParts::NONE
+        });
+        let frame = store_cursor
+            .get_next(
+                &get_unix_timestamp(ts + Duration::from_secs(5)),
+                Direction::Forward,
+            )
+            .expect("Failed to read sample")
+            .expect("Did not find stored sample");
+        assert_eq!(frame.1.sample.cgroup.memory_current, Some(5));
+        assert!(frame.1.sample.processes.is_empty());
+    }
+
+    #[test]
+    fn auto_chunk_size() {
+        assert_eq!(auto_chunk_size_po2(1 << 20), 4);
+        assert_eq!(auto_chunk_size_po2(3 << 20), 2);
+        assert_eq!(auto_chunk_size_po2(64 << 20), AUTO_MIN_CHUNK_SIZE_PO2);
+        assert_eq!(auto_chunk_size_po2(1024), AUTO_MAX_CHUNK_SIZE_PO2);
+        assert_eq!(auto_chunk_size_po2(0), AUTO_MAX_CHUNK_SIZE_PO2);
+    }
+
+    #[test]
+    fn auto_chunk_size_per_shard() {
+        let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        let ts = std::time::UNIX_EPOCH + Duration::from_secs(SHARD_TIME);
+        let mut writer = StoreWriter::new_with_timestamp(
+            get_logger(),
+            &dir,
+            ts,
+            CompressionMode::ZstdDictionaryAuto,
+            Format::Cbor,
+        )
+        .expect("Failed to create store");
+        for i in 0..3 {
+            writer
+                .put(ts + Duration::from_secs(i), &DataFrame::default())
+                .expect("Failed to store data");
+        }
+        // Empty frames are small, so chunks are as long as allowed
+        assert_eq!(writer.auto_chunk_size_po2, Some(AUTO_MAX_CHUNK_SIZE_PO2));
+        let index = fs::read(dir.path().join(format!("index_{:011}", SHARD_TIME)))
+            .expect("Failed to read index");
+        let flags = IndexEntryFlags::from_bits_retain(u32::from_ne_bytes(
+            index[20..24].try_into().unwrap(),
+        ));
+        assert_eq!(flags.get_chunk_compress_size_po2(), AUTO_MAX_CHUNK_SIZE_PO2);
+
+        // Pretend frames grew, the next shard picks a shorter chunk
+        writer.mean_frame_size = Some(4 << 20);
+        assert!(
+            writer
+                .put(ts + Duration::from_secs(SHARD_TIME), &DataFrame::default())
+                .expect("Failed to store data")
+        );
+        assert_eq!(writer.auto_chunk_size_po2, Some(2));
+
+        let mut store_cursor = StoreCursor::new(get_logger(), dir.path().to_path_buf());
+        for i in [0, 1, 2, SHARD_TIME] {
+            let frame = store_cursor
+                .get_next(
+                    &get_unix_timestamp(ts + Duration::from_secs(i)),
+                    Direction::Forward,
+                )
+                .expect("Failed to read sample")
+                .expect("Did not find stored sample");
+            assert_ts!(frame.0, ts + Duration::from_secs(i));
+        }
+    }
+
+    #[test]
+    fn verify_store_manifest() {
+        let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        let ts = std::time::UNIX_EPOCH + Duration::from_secs(SHARD_TIME);
+        {
+            let mut writer = StoreWriter::new_with_shard(
+                get_logger(),
+                &dir,
+                SHARD_TIME,
+                SHARD_TIME,
+                CompressionMode::None,
+                Format::Cbor,
+            )
+            .expect("Failed to create store");
+            for i in 0..3 {
+                writer
+                    .put(ts + Duration::from_secs(i), &DataFrame::default())
+                    .expect("Failed to store data");
+            }
+            // Rolling over closes the first shard
+            writer
+                .put(ts + Duration::from_secs(SHARD_TIME), &DataFrame::default())
+                .expect("Failed to store data");
+        }
+        let path = |name: &str, shard: u64| dir.path().join(format!("{}_{:011}", name, shard));
+        let closed = SHARD_TIME;
+        let active = 2 * SHARD_TIME;
+        assert!(path("manifest", closed).exists());
+        assert!(!path("manifest", active).exists());
+        assert_eq!(
+            verify_store(&dir).expect("Failed to verify store"),
+            vec![(closed, ShardStatus::Ok), (active, ShardStatus::Active)]
+        );
+
+        // Flip a byte in the closed shard
+        let mut data = fs::read(path("data", closed)).expect("Failed to read data");
+        data[0] ^= 0xFF;
+        fs::write(path("data", closed), &data).expect("Failed to write data");
+        assert_eq!(
+            verify_store(&dir).expect("Failed to verify store")[0].1,
+            ShardStatus::Corrupt(format!("data_{:011}", closed))
+        );
+
+        // Incomplete copy of the closed shard
+        data.truncate(data.len() - 1);
+        fs::write(path("data", closed), &data).expect("Failed to write data");
+        assert_eq!(
+            verify_store(&dir).expect("Failed to verify store")[0].1,
+            ShardStatus::Truncated(format!("data_{:011}", closed))
+        );
+
+        // Without a manifest we can still tell the data file is short
+        fs::remove_file(path("manifest", closed)).expect("Failed to remove manifest");
+        assert_eq!(
+            verify_store(&dir).expect("Failed to verify store")[0].1,
+            ShardStatus::Truncated(format!("data_{:011}", closed))
+        );
+
+        // Index entries beyond the data are not committed as if the
+        // recorder crashed mid write
+        let index = fs::read(path("index", closed)).expect("Failed to read index");
+        fs::write(
+            path("index", closed),
+            &index[..index.len() - INDEX_ENTRY_SIZE],
+        )
+        .expect("Failed to write index");
+        fs::write(
+            path("committed", closed),
+            encode_committed_len((index.len() - INDEX_ENTRY_SIZE) as u64),
+        )
+        .expect("Failed to write committed");
+        assert_eq!(
+            verify_store(&dir).expect("Failed to verify store")[0].1,
+            ShardStatus::Unclosed
+        );
+
+        // Index shorter than committed
+        let index = fs::read(path("index", active)).expect("Failed to read index");
+        fs::write(path("index", active), &index[..index.len() - 1]).expect("Failed to write index");
+        assert_eq!(
+            verify_store(&dir).expect("Failed to verify store")[1].1,
+            ShardStatus::Truncated(format!("index_{:011}", active))
+        );
+    }
+
+    #[test]
+    fn store_metadata() {
+        assert!(StoreMetadata::new(0).is_err());
+        assert!(StoreMetadata::new(7).is_err());
+        assert!(StoreMetadata::new(2 * SHARD_TIME).is_err());
+        assert_eq!(StoreMetadata::new(3600).unwrap().shard_time, 3600);
+
+        let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        let store_dir = dir.path().join("store");
+        // Missing store defaults to daily shards
+        assert_eq!(
+            StoreMetadata::load(&store_dir).expect("Failed to load metadata"),
+            StoreMetadata::default()
+        );
+        let hourly = StoreMetadata::new(3600).unwrap();
+        hourly.init(&store_dir).expect("Failed to init store");
+        assert_eq!(
+            StoreMetadata::load(&store_dir).expect("Failed to load metadata"),
+            hourly
+        );
+        // Same shard time is accepted, a different one is not
+        hourly.init(&store_dir).expect("Failed to init store");
+        assert!(StoreMetadata::default().init(&store_dir).is_err());
+
+        // Stores written before metadata files have daily shards
+        let old_dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        StoreWriter::new(get_logger(), &old_dir, CompressionMode::None, Format::Cbor)
+            .expect("Failed to create store")
+            .put(SystemTime::now(), &DataFrame::default())
+            .expect("Failed to store data");
+        assert!(hourly.init(&old_dir).is_err());
+        StoreMetadata::default()
+            .init(&old_dir)
+            .expect("Failed to init store");
+
+        // Unknown keys are ignored
+        assert_eq!(
+            StoreMetadata::parse("shard_time 900\nfoo bar\n").unwrap(),
+            StoreMetadata::new(900).unwrap()
+        );
+        assert!(StoreMetadata::parse("shard_time 7\n").is_err());
+    }
+
+    #[test]
+    fn hourly_shards() {
+        let dir = TempDir::with_prefix("below_store_test.").expect("tempdir failed");
+        let shard_time = 3600;
+        StoreMetadata::new(shard_time)
+            .unwrap()
+            .init(&dir)
+            .expect("Failed to init store");
+        let ts = std::time::UNIX_EPOCH + Duration::from_secs(SHARD_TIME);
+        {
+            let mut writer =
+                StoreWriter::new(get_logger(), &dir, CompressionMode::None, Format::Cbor)
+                    .expect("Failed to create store");
+            let mut frame = DataFrame::default();
+            for i in 0..3 {
+                frame.sample.cgroup.memory_current = Some(i as i64);
+                writer
+                    .put(ts + Duration::from_secs(i * shard_time), &frame)
+                    .expect("Failed to store data");
+            }
+        }
+        for i in 0..3 {
+            let shard = SHARD_TIME + i * shard_time;
+            assert!(dir.path().join(format!("index_{:011}", shard)).exists());
+        }
+
+        let mut store_cursor = StoreCursor::new(get_logger(), dir.path().to_path_buf());
+        let frame = store_cursor
+            .get_next(
+                &get_unix_timestamp(ts + Duration::from_secs(1)),
+                Direction::Forward,
+            )
+            .expect("Failed to read sample")
+            .expect("Did not find stored sample");
+        assert_ts!(frame.0, ts + Duration::from_secs(shard_time));
+        assert_eq!(frame.1.sample.cgroup.memory_current, Some(1));
+
+        // Retention drops whole hours rather than whole days
+        let writer = StoreWriter::new(get_logger(), &dir, CompressionMode::None, Format::Cbor)
+            .expect("Failed to create store");
+        writer
+            .discard_earlier(ts + Duration::from_secs(shard_time + 1))
+            .expect("Failed to discard data");
+        let mut store_cursor = StoreCursor::new(get_logger(), dir.path().to_path_buf());
+        let frame = store_cursor
+            .get_next(&get_unix_timestamp(ts), Direction::Forward)
+            .expect("Failed to read sample")
+            .expect("Did not find stored sample");
+        assert_ts!(frame.0, ts + Duration::from_secs(shard_time));
+    }
 }
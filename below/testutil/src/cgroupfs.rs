@@ -0,0 +1,154 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+
+const PRESSURE: &str = "some avg10=1.00 avg60=0.50 avg300=0.25 total=1000\n\
+    full avg10=1.00 avg60=0.50 avg300=0.25 total=1000\n";
+
+/// The interface files of a cgroup
+#[derive(Clone, Debug, Default)]
+pub struct CgroupFixture {
+    files: Files,
+}
+
+impl CgroupFixture {
+    /// A cgroup with CPU, IO, memory, pids and pressure files
+    pub fn new() -> CgroupFixture {
+        CgroupFixture::empty()
+            .cpu_usage_usec(2000, 1500, 500)
+            .file(
+                "io.stat",
+                "8:0 rbytes=4096 wbytes=8192 rios=1 wios=2 dbytes=0 dios=0\n",
+            )
+            .memory_current(64 << 20)
+            .file(
+                "memory.stat",
+                "anon 50331648\nfile 16777216\nkernel_stack 16384\nslab 1048576\n",
+            )
+            .pids_current(4)
+            .file("cpu.pressure", PRESSURE)
+            .file("io.pressure", PRESSURE)
+            .file("memory.pressure", PRESSURE)
+    }
+
+    /// A cgroup without interface files, as readers see a cgroup removed
+    /// while being read
+    pub fn empty() -> CgroupFixture {
+        Default::default()
+    }
+
+    pub fn cpu_usage_usec(self, usage_usec: u64, user_usec: u64, system_usec: u64) -> Self {
+        self.file(
+            "cpu.stat",
+            format!(
+                "usage_usec {}\nuser_usec {}\nsystem_usec {}\nnr_periods 1\n\
+                nr_throttled 0\nthrottled_usec 0\n",
+                usage_usec, user_usec, system_usec
+            ),
+        )
+    }
+
+    pub fn memory_current(self, bytes: u64) -> Self {
+        self.file("memory.current", format!("{}\n", bytes))
+    }
+
+    pub fn pids_current(self, pids: u64) -> Self {
+        self.file("pids.current", format!("{}\n", pids))
+    }
+
+    /// Set the interface file `name` to raw `contents`, e.g. "max\n" for
+    /// memory.max or a malformed file
+    pub fn file(mut self, name: &str, contents: impl AsRef<[u8]>) -> Self {
+        self.files
+            .insert(name.to_owned(), Some(contents.as_ref().to_vec()));
+        self
+    }
+
+    pub fn without(mut self, name: &str) -> Self {
+        self.files.remove(name);
+        self
+    }
+}
+
+/// A cgroup2 tree. The root cgroup always exists, and parents of added
+/// cgroups are created without interface files unless added themselves.
+#[derive(Clone, Debug)]
+pub struct CgroupfsBuilder {
+    /// Cgroups by path relative to the root, "" for the root
+    cgroups: BTreeMap<String, CgroupFixture>,
+}
+
+impl Default for CgroupfsBuilder {
+    fn default() -> Self {
+        CgroupfsBuilder {
+            cgroups: BTreeMap::from([(String::new(), CgroupFixture::new())]),
+        }
+    }
+}
+
+impl CgroupfsBuilder {
+    pub fn new() -> CgroupfsBuilder {
+        Default::default()
+    }
+
+    /// The units of `synthetic_units(nr_cgroups)` and the slices grouping
+    /// them
+    pub fn synthetic(nr_cgroups: usize) -> CgroupfsBuilder {
+        let mut builder = CgroupfsBuilder::new();
+        for unit in synthetic_units(nr_cgroups) {
+            let (slice, _) = unit.split_once('/').expect("bug: unit without slice");
+            builder = builder
+                .cgroup(slice, CgroupFixture::new())
+                .cgroup(&unit, CgroupFixture::new());
+        }
+        builder
+    }
+
+    /// Add the cgroup at `path`, relative to the root and without leading
+    /// slash, replacing any cgroup at the same path
+    pub fn cgroup(mut self, path: &str, cgroup: CgroupFixture) -> Self {
+        self.cgroups
+            .insert(path.trim_matches('/').to_owned(), cgroup);
+        self
+    }
+
+    /// Replace the root cgroup
+    pub fn root(self, cgroup: CgroupFixture) -> Self {
+        self.cgroup("", cgroup)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cgroups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cgroups.is_empty()
+    }
+
+    /// Write the tree under `path`, which is created if needed. Readers
+    /// checking for cgroup2 have to be skipped, e.g. with
+    /// `CgroupReader::new_unchecked`.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        for (cgroup_path, cgroup) in &self.cgroups {
+            write_files(&path.join(cgroup_path), &cgroup.files)?;
+        }
+        Ok(())
+    }
+
+    /// Write the tree to a new temporary directory, removed on drop
+    pub fn build(&self) -> io::Result<TempDir> {
+        build_tempdir(|path| self.write(path))
+    }
+}
@@ -0,0 +1,84 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Builders of synthetic /proc and cgroupfs trees, for tests that read a
+//! host end to end, from collection through dump output, without root.
+//!
+//! Builders only describe the trees. Nothing touches the filesystem until
+//! `write` or `build` is called, so a tree can be built up in a loop with
+//! tens of thousands of entries and tweaked per entry to hit edge cases:
+//!
+//! ```
+//! use below_testutil::ProcessFixture;
+//! use below_testutil::ProcfsBuilder;
+//!
+//! let procfs = ProcfsBuilder::synthetic(1_000, 100)
+//!     // Comms can contain spaces and parentheses
+//!     .process(ProcessFixture::new(2_000).comm("a) (b"))
+//!     // Exited between listing /proc and reading its files
+//!     .process(ProcessFixture::new(2_001).without("status"))
+//!     .build()
+//!     .expect("Failed to write procfs");
+//! assert!(procfs.path().join("2000/stat").exists());
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use tempfile::TempDir;
+
+mod cgroupfs;
+mod procfs;
+
+pub use cgroupfs::*;
+pub use procfs::*;
+
+/// Number of units in each slice of a synthetic cgroup tree
+pub const UNITS_PER_SLICE: usize = 8;
+
+/// Paths of the units of a synthetic cgroup tree of `nr_cgroups` cgroups,
+/// relative to the cgroup root and without leading slash. Units are grouped
+/// into slices of up to `UNITS_PER_SLICE` units under the root.
+pub fn synthetic_units(nr_cgroups: usize) -> Vec<String> {
+    (0..nr_cgroups.max(1))
+        .map(|i| {
+            format!(
+                "slice{}.slice/unit{}.service",
+                i / UNITS_PER_SLICE,
+                i % UNITS_PER_SLICE
+            )
+        })
+        .collect()
+}
+
+/// Files of a directory by name, None for files left out of the directory
+type Files = BTreeMap<String, Option<Vec<u8>>>;
+
+fn write_files(dir: &Path, files: &Files) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (name, contents) in files {
+        if let Some(contents) = contents {
+            fs::write(dir.join(name), contents)?;
+        }
+    }
+    Ok(())
+}
+
+fn build_tempdir(write: impl FnOnce(&Path) -> io::Result<()>) -> io::Result<TempDir> {
+    let dir = TempDir::new()?;
+    write(dir.path())?;
+    Ok(dir)
+}
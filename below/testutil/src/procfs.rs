@@ -0,0 +1,345 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::os::unix::fs::symlink;
+use std::path::PathBuf;
+
+use super::*;
+
+/// A /proc/[pid] directory with the files `ProcReader::read_all_pids` reads.
+/// Counters are given in the units of the files, e.g. clock ticks and pages.
+#[derive(Clone, Debug)]
+pub struct ProcessFixture {
+    pid: u32,
+    comm: String,
+    state: char,
+    ppid: u32,
+    minflt: u64,
+    majflt: u64,
+    user_ticks: u64,
+    system_ticks: u64,
+    num_threads: u64,
+    start_ticks: u64,
+    rss_pages: u64,
+    processor: u32,
+    read_bytes: u64,
+    write_bytes: u64,
+    cgroup: String,
+    cmdline: Vec<String>,
+    exe: Option<String>,
+    /// Raw contents replacing or removing the generated files
+    overrides: Files,
+}
+
+impl ProcessFixture {
+    /// A running process named "proc<pid>" in the root cgroup
+    pub fn new(pid: u32) -> ProcessFixture {
+        ProcessFixture {
+            pid,
+            comm: format!("proc{}", pid),
+            state: 'R',
+            ppid: 1,
+            minflt: 100,
+            majflt: 10,
+            user_ticks: 150,
+            system_ticks: 50,
+            num_threads: 1,
+            start_ticks: 0,
+            rss_pages: 4096,
+            processor: 0,
+            read_bytes: 4096,
+            write_bytes: 8192,
+            cgroup: "/".to_owned(),
+            cmdline: vec![format!("/usr/bin/proc{}", pid)],
+            exe: Some(format!("/usr/bin/proc{}", pid)),
+            overrides: Files::new(),
+        }
+    }
+
+    /// A kernel thread, which has no command line or executable
+    pub fn kernel_thread(pid: u32) -> ProcessFixture {
+        ProcessFixture::new(pid)
+            .comm(&format!("kworker/{}", pid))
+            .state('I')
+            .ppid(2)
+            .cmdline(&[])
+            .no_exe()
+            .rss_pages(0)
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn comm(mut self, comm: &str) -> Self {
+        self.comm = comm.to_owned();
+        self
+    }
+
+    /// State as the letter of /proc/[pid]/stat, e.g. 'D'
+    pub fn state(mut self, state: char) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn ppid(mut self, ppid: u32) -> Self {
+        self.ppid = ppid;
+        self
+    }
+
+    pub fn faults(mut self, minflt: u64, majflt: u64) -> Self {
+        self.minflt = minflt;
+        self.majflt = majflt;
+        self
+    }
+
+    pub fn cpu_ticks(mut self, user_ticks: u64, system_ticks: u64) -> Self {
+        self.user_ticks = user_ticks;
+        self.system_ticks = system_ticks;
+        self
+    }
+
+    pub fn num_threads(mut self, num_threads: u64) -> Self {
+        self.num_threads = num_threads;
+        self
+    }
+
+    /// Start time in clock ticks since boot. Readers compute the running
+    /// time from the real uptime, so keep it below the uptime of the host
+    /// running the test.
+    pub fn start_ticks(mut self, start_ticks: u64) -> Self {
+        self.start_ticks = start_ticks;
+        self
+    }
+
+    pub fn rss_pages(mut self, rss_pages: u64) -> Self {
+        self.rss_pages = rss_pages;
+        self
+    }
+
+    pub fn processor(mut self, processor: u32) -> Self {
+        self.processor = processor;
+        self
+    }
+
+    pub fn io_bytes(mut self, read_bytes: u64, write_bytes: u64) -> Self {
+        self.read_bytes = read_bytes;
+        self.write_bytes = write_bytes;
+        self
+    }
+
+    /// Cgroup path relative to the cgroup root, with leading slash
+    pub fn cgroup(mut self, cgroup: &str) -> Self {
+        self.cgroup = cgroup.to_owned();
+        self
+    }
+
+    /// Arguments of the command line, empty for kernel threads
+    pub fn cmdline(mut self, args: &[&str]) -> Self {
+        self.cmdline = args.iter().map(|arg| (*arg).to_owned()).collect();
+        self
+    }
+
+    /// Target of the exe symlink
+    pub fn exe(mut self, exe: &str) -> Self {
+        self.exe = Some(exe.to_owned());
+        self
+    }
+
+    /// Leave out the exe symlink, as for kernel threads and for readers
+    /// without permission to follow it
+    pub fn no_exe(mut self) -> Self {
+        self.exe = None;
+        self
+    }
+
+    /// Replace the generated file `name` with raw `contents`, e.g. to test
+    /// malformed or truncated files
+    pub fn file(mut self, name: &str, contents: impl AsRef<[u8]>) -> Self {
+        self.overrides
+            .insert(name.to_owned(), Some(contents.as_ref().to_vec()));
+        self
+    }
+
+    /// Leave out the file `name`, as if the process exited while being read
+    pub fn without(mut self, name: &str) -> Self {
+        self.overrides.insert(name.to_owned(), None);
+        self
+    }
+
+    fn files(&self) -> Files {
+        let mut files = Files::new();
+        files.insert(
+            "stat".to_owned(),
+            Some(
+                format!(
+                    "{pid} ({comm}) {state} {ppid} {pid} {pid} 0 -1 4194560 {minflt} 0 {majflt} \
+                    0 {utime} {stime} 0 0 20 0 {threads} 0 {start} 268435456 {rss} \
+                    18446744073709551615 1 1 0 0 0 0 0 0 0 0 0 0 17 {cpu} 0 0 0 0 0\n",
+                    pid = self.pid,
+                    comm = self.comm,
+                    state = self.state,
+                    ppid = self.ppid,
+                    minflt = self.minflt,
+                    majflt = self.majflt,
+                    utime = self.user_ticks,
+                    stime = self.system_ticks,
+                    threads = self.num_threads,
+                    start = self.start_ticks,
+                    rss = self.rss_pages,
+                    cpu = self.processor,
+                )
+                .into_bytes(),
+            ),
+        );
+        files.insert(
+            "status".to_owned(),
+            Some(
+                format!(
+                    "Name:\t{comm}\nState:\t{state}\nPid:\t{pid}\nPPid:\t{ppid}\n\
+                    VmSize:\t  262144 kB\nVmLck:\t       0 kB\nVmRSS:\t{rss:>8} kB\n\
+                    RssAnon:\t{rss:>8} kB\nRssFile:\t       0 kB\nRssShmem:\t       0 kB\n\
+                    VmPTE:\t     128 kB\nVmSwap:\t       0 kB\nThreads:\t{threads}\n",
+                    comm = self.comm,
+                    state = self.state,
+                    pid = self.pid,
+                    ppid = self.ppid,
+                    rss = self.rss_pages * 4,
+                    threads = self.num_threads,
+                )
+                .into_bytes(),
+            ),
+        );
+        files.insert(
+            "io".to_owned(),
+            Some(
+                format!(
+                    "rchar: {read}\nwchar: {write}\nsyscr: 10\nsyscw: 10\nread_bytes: {read}\n\
+                    write_bytes: {write}\ncancelled_write_bytes: 0\n",
+                    read = self.read_bytes,
+                    write = self.write_bytes,
+                )
+                .into_bytes(),
+            ),
+        );
+        files.insert(
+            "cgroup".to_owned(),
+            Some(format!("0::{}\n", self.cgroup).into_bytes()),
+        );
+        files.insert(
+            "cmdline".to_owned(),
+            Some(
+                self.cmdline
+                    .iter()
+                    .flat_map(|arg| arg.bytes().chain(std::iter::once(b'\0')))
+                    .collect(),
+            ),
+        );
+        files.extend(self.overrides.clone());
+        files
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        let dir = path.join(self.pid.to_string());
+        write_files(&dir, &self.files())?;
+        if let Some(exe) = &self.exe {
+            if !self.overrides.contains_key("exe") {
+                symlink(exe, dir.join("exe"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A /proc tree of processes and system wide files
+#[derive(Clone, Debug, Default)]
+pub struct ProcfsBuilder {
+    processes: BTreeMap<u32, ProcessFixture>,
+    /// Files outside of the pid directories, by path relative to the root
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl ProcfsBuilder {
+    pub fn new() -> ProcfsBuilder {
+        Default::default()
+    }
+
+    /// Processes 1 to `nr_processes`, spread evenly over the units of
+    /// `synthetic_units(nr_cgroups)`
+    pub fn synthetic(nr_processes: usize, nr_cgroups: usize) -> ProcfsBuilder {
+        let units = synthetic_units(nr_cgroups);
+        ProcfsBuilder::new().processes((0..nr_processes).map(|i| {
+            let pid = i as u32 + 1;
+            ProcessFixture::new(pid)
+                .num_threads(4)
+                .processor(i as u32 % 8)
+                .cgroup(&format!("/{}", units[i % units.len()]))
+                .cmdline(&[&format!("/usr/bin/proc{}", pid), "--flag"])
+        }))
+    }
+
+    /// Add `process`, replacing any process with the same pid
+    pub fn process(mut self, process: ProcessFixture) -> Self {
+        self.processes.insert(process.pid, process);
+        self
+    }
+
+    pub fn processes(mut self, processes: impl IntoIterator<Item = ProcessFixture>) -> Self {
+        self.processes
+            .extend(processes.into_iter().map(|process| (process.pid, process)));
+        self
+    }
+
+    pub fn remove_process(mut self, pid: u32) -> Self {
+        self.processes.remove(&pid);
+        self
+    }
+
+    /// Add a file outside of the pid directories, e.g. "stat" or
+    /// "sys/kernel/hung_task_detect_count"
+    pub fn file(mut self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Self {
+        self.files
+            .insert(path.as_ref().to_owned(), contents.as_ref().to_vec());
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.processes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.processes.is_empty()
+    }
+
+    /// Write the tree under `path`, which is created if needed
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)?;
+        for (file, contents) in &self.files {
+            let file = path.join(file);
+            if let Some(parent) = file.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(file, contents)?;
+        }
+        for process in self.processes.values() {
+            process.write(path)?;
+        }
+        Ok(())
+    }
+
+    /// Write the tree to a new temporary directory, removed on drop
+    pub fn build(&self) -> io::Result<TempDir> {
+        build_tempdir(|path| self.write(path))
+    }
+}
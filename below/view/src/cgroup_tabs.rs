@@ -79,10 +79,7 @@ impl CgroupTab {
         }
 
         if recreated {
-            line = StyledString::styled(
-                line.source(),
-                cursive::theme::Color::Light(cursive::theme::BaseColor::Green),
-            );
+            line = StyledString::styled(line.source(), crate::theme::current().info);
         }
 
         line
@@ -141,8 +138,8 @@ impl CgroupTab {
                 sort_queriables(&mut children, &field_id, state.reverse);
             }
 
-            // Stop at next level (one below <root>)
-            if state.collapse_all_top_level_cgroup {
+            // Stop at the fold depth
+            if state.fold_depth == Some(cgroup.data.depth + 1) {
                 for child_cgroup in &children {
                     state
                         .collapsed_cgroups
@@ -241,12 +238,18 @@ pub mod default_tabs {
     use model::CgroupCpuModelFieldId::ThrottledPct;
     use model::CgroupCpuModelFieldId::UsagePct;
     use model::CgroupCpuModelFieldId::UserPct;
+    use model::CgroupFileIoModelFieldId::ReadBytesPerSec;
+    use model::CgroupFileIoModelFieldId::TopFile;
+    use model::CgroupFileIoModelFieldId::TopFileRwBytesPerSec;
+    use model::CgroupFileIoModelFieldId::WriteBytesPerSec;
     use model::CgroupIoModelFieldId::CostIndebtPct;
     use model::CgroupIoModelFieldId::CostIndelayPct;
     use model::CgroupIoModelFieldId::CostUsagePct;
     use model::CgroupIoModelFieldId::CostWaitPct;
     use model::CgroupIoModelFieldId::DbytesPerSec;
     use model::CgroupIoModelFieldId::DiosPerSec;
+    use model::CgroupIoModelFieldId::LatencyAvgUsec;
+    use model::CgroupIoModelFieldId::LatencyTargetUsec;
     use model::CgroupIoModelFieldId::RbytesPerSec;
     use model::CgroupIoModelFieldId::RiosPerSec;
     use model::CgroupIoModelFieldId::RwbytesPerSec;
@@ -257,14 +260,21 @@ pub mod default_tabs {
     use model::CgroupMemoryModelFieldId::Anon;
     use model::CgroupMemoryModelFieldId::AnonThp;
     use model::CgroupMemoryModelFieldId::EventsHigh;
+    use model::CgroupMemoryModelFieldId::EventsHighPerSec;
     use model::CgroupMemoryModelFieldId::EventsLow;
+    use model::CgroupMemoryModelFieldId::EventsLowPerSec;
     use model::CgroupMemoryModelFieldId::EventsMax;
+    use model::CgroupMemoryModelFieldId::EventsMaxPerSec;
     use model::CgroupMemoryModelFieldId::EventsOom;
     use model::CgroupMemoryModelFieldId::EventsOomKill;
+    use model::CgroupMemoryModelFieldId::EventsOomKillDelta;
     use model::CgroupMemoryModelFieldId::File;
     use model::CgroupMemoryModelFieldId::FileDirty;
+    use model::CgroupMemoryModelFieldId::FileDirtyGrowthPerSec;
     use model::CgroupMemoryModelFieldId::FileMapped;
+    use model::CgroupMemoryModelFieldId::FileThp;
     use model::CgroupMemoryModelFieldId::FileWriteback;
+    use model::CgroupMemoryModelFieldId::Hugetlb;
     use model::CgroupMemoryModelFieldId::InactiveAnon;
     use model::CgroupMemoryModelFieldId::InactiveFile;
     use model::CgroupMemoryModelFieldId::Kernel;
@@ -279,6 +289,7 @@ pub mod default_tabs {
     use model::CgroupMemoryModelFieldId::Pgscan;
     use model::CgroupMemoryModelFieldId::Pgsteal;
     use model::CgroupMemoryModelFieldId::Shmem;
+    use model::CgroupMemoryModelFieldId::ShmemThp;
     use model::CgroupMemoryModelFieldId::Slab;
     use model::CgroupMemoryModelFieldId::SlabReclaimable;
     use model::CgroupMemoryModelFieldId::SlabUnreclaimable;
@@ -302,14 +313,18 @@ pub mod default_tabs {
     use model::CgroupPressureModelFieldId::CpuSomePct;
     use model::CgroupPressureModelFieldId::IoFullPct;
     use model::CgroupPressureModelFieldId::IoSomePct;
+    use model::CgroupPressureModelFieldId::IrqFullPct;
     use model::CgroupPressureModelFieldId::MemoryFullPct;
     use model::CgroupPressureModelFieldId::MemorySomePct;
+    use model::CgroupPressureModelFieldId::MemoryTriggerEvents;
     use model::CgroupPropertiesFieldId::CgroupControllers;
     use model::CgroupPropertiesFieldId::CpuMaxPeriodUsec;
     use model::CgroupPropertiesFieldId::CpuMaxUsec;
     use model::CgroupPropertiesFieldId::CpuWeight;
+    use model::CgroupPropertiesFieldId::CpusAllowedCount;
     use model::CgroupPropertiesFieldId::CpusetCpus;
     use model::CgroupPropertiesFieldId::CpusetCpusEffective;
+    use model::CgroupPropertiesFieldId::CpusetMemsEffective;
     use model::CgroupPropertiesFieldId::MemoryHigh;
     use model::CgroupPropertiesFieldId::MemoryLow;
     use model::CgroupPropertiesFieldId::MemoryMax;
@@ -317,15 +332,32 @@ pub mod default_tabs {
     use model::CgroupPropertiesFieldId::MemorySwapMax;
     use model::CgroupPropertiesFieldId::MemoryZswapMax;
     use model::CgroupPropertiesFieldId::TidsMax;
+    use model::CgroupShareModelFieldId::CpuPctOfParent;
+    use model::CgroupShareModelFieldId::CpuPctOfRoot;
+    use model::CgroupShareModelFieldId::MemPctOfParent;
+    use model::CgroupShareModelFieldId::MemPctOfRoot;
     use model::CgroupStatModelFieldId::NrDescendants;
+    use model::DStateModelFieldId::Count;
+    use model::PerfCounterModelFieldId::LlcMissPct;
+    use model::PerfCounterModelFieldId::LlcMissesPerSec;
+    use model::PerfCounterModelFieldId::LlcReferencesPerSec;
+    use model::PerfCounterModelFieldId::MemBwBytesPerSec;
     use model::CgroupStatModelFieldId::NrDyingDescendants;
+    use model::CgroupChurnModelFieldId::CreatedPerSec;
+    use model::CgroupChurnModelFieldId::RemovedPerSec;
     use model::SingleCgroupModelFieldId::CgroupStat;
+    use model::SingleCgroupModelFieldId::Churn;
     use model::SingleCgroupModelFieldId::Cpu;
+    use model::SingleCgroupModelFieldId::Dstate;
+    use model::SingleCgroupModelFieldId::FileIo;
     use model::SingleCgroupModelFieldId::Io;
+    use model::SingleCgroupModelFieldId::JournalErrorsPerSec;
     use model::SingleCgroupModelFieldId::Mem;
+    use model::SingleCgroupModelFieldId::Perf;
     use model::SingleCgroupModelFieldId::Pids;
     use model::SingleCgroupModelFieldId::Pressure;
     use model::SingleCgroupModelFieldId::Props;
+    use model::SingleCgroupModelFieldId::Share;
 
     use super::*;
 
@@ -341,7 +373,11 @@ pub mod default_tabs {
             ViewItem::from_default(Io(RwbytesPerSec)),
             ViewItem::from_default(CgroupStat(NrDescendants)),
             ViewItem::from_default(CgroupStat(NrDyingDescendants)),
+            ViewItem::from_default(Churn(CreatedPerSec)),
+            ViewItem::from_default(Churn(RemovedPerSec)),
             ViewItem::from_default(Pids(TidsCurrent)),
+            ViewItem::from_default(Dstate(Count)),
+            ViewItem::from_default(JournalErrorsPerSec),
         ]
     }
 
@@ -373,6 +409,8 @@ pub mod default_tabs {
             ViewItem::from_default(Mem(FileDirty)),
             ViewItem::from_default(Mem(FileWriteback)),
             ViewItem::from_default(Mem(AnonThp)),
+            ViewItem::from_default(Mem(FileThp)),
+            ViewItem::from_default(Mem(ShmemThp)),
             ViewItem::from_default(Mem(InactiveAnon)),
             ViewItem::from_default(Mem(ActiveAnon)),
             ViewItem::from_default(Mem(InactiveFile)),
@@ -398,11 +436,16 @@ pub mod default_tabs {
             ViewItem::from_default(Mem(Pglazyfreed)),
             ViewItem::from_default(Mem(ThpFaultAlloc)),
             ViewItem::from_default(Mem(ThpCollapseAlloc)),
+            ViewItem::from_default(Mem(Hugetlb)),
             ViewItem::from_default(Mem(EventsLow)),
             ViewItem::from_default(Mem(EventsHigh)),
             ViewItem::from_default(Mem(EventsMax)),
             ViewItem::from_default(Mem(EventsOom)),
             ViewItem::from_default(Mem(EventsOomKill)),
+            ViewItem::from_default(Mem(EventsOomKillDelta)),
+            ViewItem::from_default(Mem(EventsLowPerSec)),
+            ViewItem::from_default(Mem(EventsHighPerSec)),
+            ViewItem::from_default(Mem(EventsMaxPerSec)),
         ]
     }
 
@@ -419,6 +462,54 @@ pub mod default_tabs {
             ViewItem::from_default(Io(CostWaitPct)),
             ViewItem::from_default(Io(CostIndebtPct)),
             ViewItem::from_default(Io(CostIndelayPct)),
+            ViewItem::from_default(Io(LatencyTargetUsec)),
+            ViewItem::from_default(Io(LatencyAvgUsec)),
+        ]
+    }
+
+    /// Dirty page cache and the write IO cleaning it, to tell writers
+    /// throttled in balance_dirty_pages from slow devices
+    pub fn get_writeback_items() -> Vec<ViewItem<SingleCgroupModelFieldId>> {
+        vec![
+            ViewItem::from_default(Mem(FileDirty)),
+            ViewItem::from_default(Mem(FileWriteback)),
+            ViewItem::from_default(Mem(FileDirtyGrowthPerSec)),
+            ViewItem::from_default(Io(WbytesPerSec)),
+            ViewItem::from_default(Io(WiosPerSec)),
+            ViewItem::from_default(Io(CostUsagePct)),
+            ViewItem::from_default(Io(CostWaitPct)),
+            ViewItem::from_default(Io(CostIndelayPct)),
+            ViewItem::from_default(Pressure(IoSomePct)),
+            ViewItem::from_default(Pressure(IoFullPct)),
+        ]
+    }
+
+    pub fn get_file_io_items() -> Vec<ViewItem<SingleCgroupModelFieldId>> {
+        vec![
+            ViewItem::from_default(FileIo(ReadBytesPerSec)),
+            ViewItem::from_default(FileIo(WriteBytesPerSec)),
+            ViewItem::from_default(FileIo(TopFileRwBytesPerSec)),
+            ViewItem::from_default(FileIo(TopFile)),
+        ]
+    }
+
+    /// Only top level cgroups have perf counters
+    pub fn get_perf_items() -> Vec<ViewItem<SingleCgroupModelFieldId>> {
+        vec![
+            ViewItem::from_default(Perf(MemBwBytesPerSec)),
+            ViewItem::from_default(Perf(LlcMissesPerSec)),
+            ViewItem::from_default(Perf(LlcReferencesPerSec)),
+            ViewItem::from_default(Perf(LlcMissPct)),
+        ]
+    }
+
+    /// CPU and memory of each cgroup relative to its parent and the root
+    pub fn get_share_items() -> Vec<ViewItem<SingleCgroupModelFieldId>> {
+        vec![
+            ViewItem::from_default(Share(CpuPctOfParent)),
+            ViewItem::from_default(Share(CpuPctOfRoot)),
+            ViewItem::from_default(Share(MemPctOfParent)),
+            ViewItem::from_default(Share(MemPctOfRoot)),
         ]
     }
 
@@ -430,6 +521,8 @@ pub mod default_tabs {
             ViewItem::from_default(Pressure(MemoryFullPct)),
             ViewItem::from_default(Pressure(IoSomePct)),
             ViewItem::from_default(Pressure(IoFullPct)),
+            ViewItem::from_default(Pressure(IrqFullPct)),
+            ViewItem::from_default(Pressure(MemoryTriggerEvents)),
         ]
     }
 
@@ -446,6 +539,8 @@ pub mod default_tabs {
             ViewItem::from_default(Props(CpuWeight)),
             ViewItem::from_default(Props(CpusetCpus)),
             ViewItem::from_default(Props(CpusetCpusEffective)),
+            ViewItem::from_default(Props(CpusAllowedCount)),
+            ViewItem::from_default(Props(CpusetMemsEffective)),
             ViewItem::from_default(Props(TidsMax)),
             ViewItem::from_default(Props(CgroupControllers)),
         ]
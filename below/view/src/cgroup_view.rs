@@ -15,10 +15,16 @@
 use std::cell::Ref;
 use std::cell::RefCell;
 use std::cell::RefMut;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::path::PathBuf;
 use std::rc::Rc;
 
+use anyhow::Context;
+use anyhow::Result;
+use common::util::get_cgroup_folds_filename;
+use common::util::timestamp_to_datetime;
 use cursive::utils::markup::StyledString;
 use cursive::view::Nameable;
 use cursive::views::NamedView;
@@ -29,11 +35,15 @@ use model::CgroupCpuModelFieldId;
 use model::CgroupIoModelFieldId;
 use model::CgroupMemoryModelFieldId;
 use model::CgroupModel;
+use model::OomKillEvent;
 use model::Queriable;
 use model::SingleCgroupModelFieldId;
+use serde::Deserialize;
+use serde::Serialize;
 
 use crate::cgroup_tabs::default_tabs;
 use crate::cgroup_tabs::CgroupTab;
+use crate::inspect_popup;
 use crate::render::ViewItem;
 use crate::stats_view::ColumnTitles;
 use crate::stats_view::StateCommon;
@@ -58,7 +68,22 @@ pub struct CgroupState {
     pub sort_tags: HashMap<String, Vec<ViewItem<SingleCgroupModelFieldId>>>,
     pub reverse: bool,
     pub model: Rc<RefCell<CgroupModel>>,
-    pub collapse_all_top_level_cgroup: bool,
+    // Kills by userspace OOM killers since the previous sample, shown for
+    // the selected cgroup
+    pub oom_kills: Rc<RefCell<Vec<OomKillEvent>>>,
+    // If set, cgroups at this depth are collapsed as they show up, so that
+    // the tree stays folded across refreshes. Depth 1 collapses all top
+    // level cgroups.
+    pub fold_depth: Option<u32>,
+    // If set, fold state is saved to this file whenever it changes
+    pub fold_state_file: Option<PathBuf>,
+}
+
+/// Fold state of the cgroup tree as saved across sessions.
+#[derive(Default, Serialize, Deserialize)]
+struct CgroupFoldState {
+    fold_depth: Option<u32>,
+    collapsed: BTreeSet<String>,
 }
 
 impl StateCommon for CgroupState {
@@ -130,6 +155,12 @@ impl StateCommon for CgroupState {
         }
     }
 
+    fn sort_strings() -> Vec<String> {
+        enum_iterator::all::<Self::TagType>()
+            .map(|field_id| field_id.to_string())
+            .collect()
+    }
+
     fn get_model(&self) -> Ref<Self::ModelType> {
         self.model.borrow()
     }
@@ -144,6 +175,10 @@ impl StateCommon for CgroupState {
         sort_tags.insert("CPU".into(), default_tabs::get_cpu_items());
         sort_tags.insert("Mem".into(), default_tabs::get_mem_items());
         sort_tags.insert("I/O".into(), default_tabs::get_io_items());
+        sort_tags.insert("Writeback".into(), default_tabs::get_writeback_items());
+        sort_tags.insert("Files".into(), default_tabs::get_file_io_items());
+        sort_tags.insert("Perf".into(), default_tabs::get_perf_items());
+        sort_tags.insert("Share".into(), default_tabs::get_share_items());
         sort_tags.insert("Pressure".into(), default_tabs::get_pressure_items());
         sort_tags.insert("Properties".into(), default_tabs::get_properties_items());
         Self {
@@ -155,7 +190,9 @@ impl StateCommon for CgroupState {
             sort_tags,
             reverse: false,
             model,
-            collapse_all_top_level_cgroup: false,
+            oom_kills: Default::default(),
+            fold_depth: None,
+            fold_state_file: None,
         }
     }
 }
@@ -170,7 +207,67 @@ impl CgroupState {
     }
 
     fn toggle_collapse_root_flag(&mut self) {
-        self.collapse_all_top_level_cgroup = !self.collapse_all_top_level_cgroup;
+        self.fold_depth = match self.fold_depth {
+            Some(1) => None,
+            _ => Some(1),
+        };
+    }
+
+    /// Collapse all cgroups at `depth` and expand everything above.
+    pub fn fold_to_depth(&mut self, depth: u32) {
+        self.fold_depth = Some(depth);
+        self.collapsed_cgroups.borrow_mut().clear();
+    }
+
+    /// Expand the tree one level further than it is currently folded to.
+    /// Returns false if the tree is not folded to a depth.
+    pub fn expand_one_level(&mut self) -> bool {
+        match self.fold_depth {
+            Some(depth) => {
+                self.fold_to_depth(depth + 1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn expand_all(&mut self) {
+        self.fold_depth = None;
+        self.collapsed_cgroups.borrow_mut().clear();
+    }
+
+    /// Restore fold state saved by a previous session, and keep saving it
+    /// to `path` from now on.
+    pub fn load_fold_state(&mut self, path: PathBuf) -> Result<()> {
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                let saved = toml::from_str::<CgroupFoldState>(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+                self.fold_depth = saved.fold_depth;
+                *self.collapsed_cgroups.borrow_mut() = saved.collapsed.into_iter().collect();
+            }
+            // Nothing saved yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        }
+        self.fold_state_file = Some(path);
+        Ok(())
+    }
+
+    /// Save fold state if persistence is enabled.
+    pub fn save_fold_state(&self) -> Result<()> {
+        let path = match &self.fold_state_file {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let saved = CgroupFoldState {
+            fold_depth: self.fold_depth,
+            collapsed: self.collapsed_cgroups.borrow().iter().cloned().collect(),
+        };
+        let content = toml::to_string(&saved).context("Failed to serialize fold state")?;
+        std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
     }
 
     // Recursively fold open to given cgroup
@@ -179,7 +276,7 @@ impl CgroupState {
         if cgroup.is_empty() {
             return;
         }
-        self.collapse_all_top_level_cgroup = false;
+        self.fold_depth = None;
         let mut sub_cgroup = Some(cgroup);
         while let Some(c) = sub_cgroup {
             self.collapsed_cgroups.borrow_mut().remove(c);
@@ -212,9 +309,11 @@ impl CgroupView {
                     .collapsed_cgroups
                     .borrow_mut()
                     .clear();
+                Self::save_fold_state(&mut view);
                 return view.refresh(c);
-            } else if view.state.borrow().collapse_all_top_level_cgroup {
-                view.state.borrow_mut().toggle_collapse_root_flag();
+            } else {
+                // Folding by hand stops folding new cgroups to a depth
+                view.state.borrow_mut().fold_depth = None;
             }
 
             if view
@@ -237,6 +336,7 @@ impl CgroupView {
                     .insert(cgroup.to_string());
             }
 
+            Self::save_fold_state(&mut view);
             view.refresh(c);
         });
 
@@ -252,6 +352,10 @@ impl CgroupView {
             "CPU".into(),
             "Mem".into(),
             "I/O".into(),
+            "Writeback".into(),
+            "Files".into(),
+            "Perf".into(),
+            "Share".into(),
             "Pressure".into(),
             "Properties".into(),
         ];
@@ -280,6 +384,30 @@ impl CgroupView {
                 tab: CgroupTab::new(default_tabs::get_io_items(), &cgroup_name_config),
             },
         );
+        tabs_map.insert(
+            "Writeback".into(),
+            CgroupView {
+                tab: CgroupTab::new(default_tabs::get_writeback_items(), &cgroup_name_config),
+            },
+        );
+        tabs_map.insert(
+            "Files".into(),
+            CgroupView {
+                tab: CgroupTab::new(default_tabs::get_file_io_items(), &cgroup_name_config),
+            },
+        );
+        tabs_map.insert(
+            "Perf".into(),
+            CgroupView {
+                tab: CgroupTab::new(default_tabs::get_perf_items(), &cgroup_name_config),
+            },
+        );
+        tabs_map.insert(
+            "Share".into(),
+            CgroupView {
+                tab: CgroupTab::new(default_tabs::get_share_items(), &cgroup_name_config),
+            },
+        );
         tabs_map.insert(
             "Pressure".into(),
             CgroupView {
@@ -293,9 +421,21 @@ impl CgroupView {
             },
         );
         let mut cgroup_state = CgroupState::new(user_data.cgroup.clone());
+        cgroup_state.oom_kills = user_data.oom_kills.clone();
         if user_data.viewrc.collapse_cgroups == Some(true) {
-            cgroup_state.collapse_all_top_level_cgroup = true;
+            cgroup_state.fold_depth = Some(1);
         }
+        if let Some(depth) = user_data.viewrc.cgroup_fold_depth {
+            cgroup_state.fold_depth = Some(depth);
+        }
+        // Saved state takes precedence over the defaults above
+        let fold_state_error = if user_data.viewrc.persist_cgroup_folds == Some(true) {
+            cgroup_state
+                .load_fold_state(get_cgroup_folds_filename().into())
+                .err()
+        } else {
+            None
+        };
         StatsView::new(
             "Cgroup",
             tabs,
@@ -305,6 +445,7 @@ impl CgroupView {
             user_data.event_controllers.clone(),
             user_data.cmd_controllers.clone(),
         )
+        .with_alert(fold_state_error.map(|e| format!("{:#}", e)))
         .feed_data(c)
         .on_event('C', |c| {
             let mut view = Self::get_cgroup_view(c);
@@ -343,21 +484,37 @@ impl CgroupView {
         ViewType::get_view(c)
     }
 
+    /// Save fold state if persistence is enabled, alerting on failure.
+    pub fn save_fold_state(view: &mut ViewType) {
+        let res = view.state.borrow().save_fold_state();
+        if let Err(e) = res {
+            view.set_alert(&format!("{:#}", e));
+        }
+    }
+
     pub fn refresh(c: &mut Cursive) {
         let mut view = Self::get_cgroup_view(c);
         let cgroup_to_focus = view.state.borrow_mut().cgroup_to_focus.take();
-        if let Some(cgroup) = &cgroup_to_focus {
-            // Refresh before getting position to ensure cgroup is expanded
-            view.refresh(c);
-            let pos = view
-                .get_detail_view()
-                .iter()
-                .position(|(_row, key)| key == cgroup);
-            if let Some(pos) = pos {
-                view.get_detail_view().set_selection(pos)(c);
+        let follow_selection = cgroup_to_focus.is_none();
+        // Without an explicit focus, keep the selection on the same cgroup
+        // as cgroups come and go above it.
+        let cgroup =
+            cgroup_to_focus.unwrap_or_else(|| view.state.borrow().current_selected_cgroup.clone());
+        // Refresh before getting position to ensure cgroup is expanded
+        view.refresh(c);
+        let pos = view
+            .get_detail_view()
+            .iter()
+            .position(|(_row, key)| key == &cgroup);
+        if let Some(pos) = pos {
+            if follow_selection && view.get_detail_view().selected_id() == Some(pos) {
+                return;
             }
+            view.get_detail_view().set_selection(pos)(c);
+            view.refresh(c);
+        } else if !follow_selection {
+            view.refresh(c);
         }
-        view.refresh(c);
     }
 }
 
@@ -404,6 +561,48 @@ impl ViewBridge for CgroupView {
             })
             .and_then(|model| model.data.query(&tag))
             .map_or("?".to_string(), |field| field.to_string());
-        format!(" {} : {} ", tag.to_string(), field_str)
+        let kills = view
+            .oom_kills
+            .borrow()
+            .iter()
+            .filter(|kill| kill.targets(selected_key))
+            .map(|kill| {
+                format!(
+                    "{} killed {} at {}",
+                    kill.killer,
+                    kill.cgroup,
+                    timestamp_to_datetime(&(kill.timestamp as i64))
+                )
+            })
+            .collect::<Vec<_>>();
+        if kills.is_empty() {
+            format!(" {} : {} ", tag.to_string(), field_str)
+        } else {
+            format!(
+                " {} : {} | {} ",
+                tag.to_string(),
+                field_str,
+                kills.join("; ")
+            )
+        }
+    }
+
+    fn inspect(
+        view: &Self::StateType,
+        selected_key: &String,
+        _current_tab: &str,
+    ) -> Option<(String, String)> {
+        selected_key
+            .split('/')
+            .skip(1)
+            .try_fold(view.model.borrow(), |model, cgroup_name| {
+                Ref::filter_map(model, |model| model.children.get(cgroup_name)).ok()
+            })
+            .map(|model| {
+                (
+                    model.data.full_path.clone(),
+                    inspect_popup::to_json(&model.data),
+                )
+            })
     }
 }
@@ -15,8 +15,11 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::VecDeque;
+use std::path::Path;
+use std::path::PathBuf;
 use std::rc::Rc;
 
+use common::util::get_cmd_history_filename;
 use cursive::event::Event;
 use cursive::event::EventResult;
 use cursive::event::Key;
@@ -29,16 +32,82 @@ use cursive::Printer;
 use cursive::View;
 
 use crate::controllers::Controllers;
+use crate::stats_view::StateCommon;
 use crate::stats_view::StatsView;
 use crate::stats_view::ViewBridge;
 
-const MAX_CMD_HISTORY: usize = 10;
+const MAX_CMD_HISTORY: usize = 100;
+
+/// Score how well `pattern` fuzzy matches `candidate`.
+///
+/// Every character of `pattern` must appear in `candidate` in order (case
+/// insensitive), otherwise None is returned. Consecutive matches and matches
+/// at the start of a word, or a field id component, score higher.
+pub fn fuzzy_match(pattern: &str, candidate: &str) -> Option<usize> {
+    let candidate = candidate.to_lowercase().chars().collect::<Vec<char>>();
+    let mut score = 0;
+    let mut idx = 0;
+    let mut last_match: Option<usize> = None;
+    for p in pattern.to_lowercase().chars() {
+        let pos = idx + candidate[idx..].iter().position(|c| *c == p)?;
+        score += 1;
+        if pos == 0 || matches!(candidate[pos - 1], '_' | '.') {
+            score += 2;
+        }
+        if last_match.map_or(false, |last| last + 1 == pos) {
+            score += 3;
+        }
+        last_match = Some(pos);
+        idx = pos + 1;
+    }
+    Some(score)
+}
+
+/// Candidates fuzzy matching `pattern`, best match first. Equally good
+/// matches keep their order.
+pub fn rank_matches<'a>(pattern: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut matches = candidates
+        .filter_map(|candidate| fuzzy_match(pattern, candidate).map(|score| (candidate, score)))
+        .collect::<Vec<(&str, usize)>>();
+    matches.sort_by(|(_, score_a), (_, score_b)| score_b.cmp(score_a));
+    matches
+        .into_iter()
+        .map(|(candidate, _)| candidate)
+        .collect()
+}
+
+/// Read saved command history, oldest first. Missing or unreadable history
+/// is empty.
+pub fn load_cmd_history(path: &Path) -> VecDeque<String> {
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    let mut history = content
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned)
+        .collect::<VecDeque<String>>();
+    while history.len() > MAX_CMD_HISTORY {
+        history.pop_front();
+    }
+    history
+}
+
+/// Save command history, one command per line
+pub fn save_cmd_history(path: &Path, history: &VecDeque<String>) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut content = String::new();
+    for cmd in history {
+        content.push_str(cmd);
+        content.push('\n');
+    }
+    std::fs::write(path, content)
+}
 
 /// Command palette will have different mode:
 /// Info is used to show info like full cgroup path.
 /// Alert is used to show error messages.
 /// Command is used to turn command palette in Command mode.
-// TODO: command mode for command palette.
 #[derive(PartialEq)]
 enum CPMode {
     Info,
@@ -59,7 +128,10 @@ pub struct CommandPalette {
     cmd_view: RefCell<EditView>,
     cmd_controllers: Rc<RefCell<HashMap<&'static str, Controllers>>>,
     cmd_history: VecDeque<String>,
+    cmd_history_file: PathBuf,
     cur_cmd_idx: usize,
+    /// Field ids completed for the argument of the sort command
+    sort_strings: Vec<String>,
 }
 
 impl View for CommandPalette {
@@ -89,6 +161,13 @@ impl View for CommandPalette {
                 let inner_printer = printer.offset((1, 1));
                 self.cmd_view.borrow_mut().layout(inner_printer.size);
                 self.cmd_view.borrow().draw(&inner_printer);
+                let hints = match self.matching_sort_strings() {
+                    Some(fields) => fields.join(" "),
+                    None => self.matching_cmds().join(" "),
+                };
+                printer.with_color(ColorStyle::secondary(), |printer| {
+                    printer.print((0, 2), &hints);
+                });
             }
             _ => {
                 // Message should adapt the screen size
@@ -116,12 +195,20 @@ impl View for CommandPalette {
                 self.next_cmd();
                 EventResult::Consumed(None)
             }
+            Event::Key(Key::Tab) => {
+                self.complete_cmd();
+                EventResult::Consumed(None)
+            }
             _ => self.cmd_view.borrow_mut().on_event(event),
         }
     }
 
     fn required_size(&mut self, constraint: Vec2) -> Vec2 {
-        Vec2::new(1, self.content.len() / constraint.x + 2)
+        match self.mode {
+            // Leave a line for the matching commands or field ids
+            CPMode::Command => Vec2::new(1, 3),
+            _ => Vec2::new(1, self.content.len() / constraint.x + 2),
+        }
     }
 }
 
@@ -132,6 +219,8 @@ impl CommandPalette {
         content: &str,
         cmd_controllers: Rc<RefCell<HashMap<&'static str, Controllers>>>,
     ) -> Self {
+        let cmd_history_file = PathBuf::from(get_cmd_history_filename());
+        let cmd_history = load_cmd_history(&cmd_history_file);
         Self {
             content: content.into(),
             filter_info: None,
@@ -146,8 +235,10 @@ impl CommandPalette {
                     .style(ColorStyle::terminal_default()),
             ),
             cmd_controllers,
-            cmd_history: VecDeque::new(),
-            cur_cmd_idx: 0,
+            cur_cmd_idx: cmd_history.len().saturating_sub(1),
+            cmd_history,
+            cmd_history_file,
+            sort_strings: <V::StateType as StateCommon>::sort_strings(),
         }
     }
 
@@ -161,6 +252,9 @@ impl CommandPalette {
                     cmd_palette.cmd_history.pop_front();
                 }
                 cmd_palette.cur_cmd_idx = cmd_palette.cmd_history.len() - 1;
+                // History is only a convenience, so failing to save it must
+                // not get in the way of the command
+                let _ = save_cmd_history(&cmd_palette.cmd_history_file, &cmd_palette.cmd_history);
             },
         );
     }
@@ -191,6 +285,64 @@ impl CommandPalette {
         }
     }
 
+    /// Commands matching what has been typed so far, best match first.
+    /// Shortcuts are left out since they are listed with their commands in
+    /// the help menu.
+    fn matching_cmds(&self) -> Vec<&'static str> {
+        let input = self.cmd_view.borrow().get_content();
+        let pattern = input.trim().split(' ').next().unwrap_or("");
+        let mut cmds = self
+            .cmd_controllers
+            .borrow()
+            .iter()
+            .filter(|(cmd, controller)| **cmd == controller.command() && !cmd.starts_with("__"))
+            .map(|(cmd, _)| *cmd)
+            .collect::<Vec<&'static str>>();
+        cmds.sort();
+        rank_matches(pattern, cmds.into_iter())
+    }
+
+    /// Field ids matching the argument typed so far, best match first. None
+    /// if no sort command with an argument is being typed.
+    fn matching_sort_strings(&self) -> Option<Vec<&str>> {
+        let input = self.cmd_view.borrow().get_content();
+        let (cmd, arg) = input.trim_start().split_once(' ')?;
+        if !matches!(
+            self.cmd_controllers.borrow().get(cmd),
+            Some(Controllers::SortCol)
+        ) {
+            return None;
+        }
+        Some(rank_matches(
+            arg.trim(),
+            self.sort_strings.iter().map(String::as_str),
+        ))
+    }
+
+    /// Replace the typed command with the best match, keeping the arguments,
+    /// or the typed field id if the command is sort
+    fn complete_cmd(&mut self) {
+        if let Some(fields) = self.matching_sort_strings() {
+            if let Some(best) = fields.first() {
+                let input = self.cmd_view.borrow().get_content();
+                let cmd = input.trim_start().split(' ').next().unwrap_or("");
+                let completed = format!("{} {}", cmd, best);
+                self.cmd_view.borrow_mut().set_content(completed);
+            }
+            return;
+        }
+        let best = match self.matching_cmds().first() {
+            Some(cmd) => *cmd,
+            None => return,
+        };
+        let input = self.cmd_view.borrow().get_content();
+        let completed = match input.trim_start().split_once(' ') {
+            Some((_, args)) => format!("{} {}", best, args),
+            None => format!("{} ", best),
+        };
+        self.cmd_view.borrow_mut().set_content(completed);
+    }
+
     /// Run the captured command
     // In this function, we should avoid borrowing command palette object, since
     // it will cause a double mut borrow in the handler.
@@ -210,8 +362,11 @@ impl CommandPalette {
                 let mut cp = c
                     .find_name::<Self>(&format!("{}_cmd_palette", name))
                     .expect("Fail to get cmd_palette");
+                cp.content = match cp.matching_cmds().first() {
+                    Some(best) => format!("Unknown Command, did you mean: {}", best),
+                    None => "Unknown Command".into(),
+                };
                 cp.mode = CPMode::Alert;
-                cp.content = "Unknown Command".into();
                 cp.cmd_view.borrow_mut().set_content("");
             }
             _ => {
@@ -0,0 +1,88 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+use std::time::SystemTime;
+
+use model::Model;
+use store::Advance;
+
+/// Offset used when comparison is turned on without one, to compare with the
+/// same time yesterday.
+pub const DEFAULT_COMPARE_OFFSET: Duration = Duration::from_secs(24 * 60 * 60);
+/// A sample further than this from the compared time is not shown, as the
+/// store has a gap there.
+const MAX_COMPARE_SKEW: Duration = Duration::from_secs(5 * 60);
+
+/// Second cursor into the store of a replay. It follows the displayed sample
+/// at a fixed offset, so that e.g. today can be compared with yesterday at
+/// the same time.
+pub struct Compare {
+    advance: Advance,
+    offset: Option<Duration>,
+    model: Option<Model>,
+}
+
+impl Compare {
+    pub fn new(advance: Advance, offset: Option<Duration>) -> Self {
+        Self {
+            advance,
+            offset,
+            model: None,
+        }
+    }
+
+    pub fn offset(&self) -> Option<Duration> {
+        self.offset
+    }
+
+    /// Model of the compared sample, None if comparison is off or there is
+    /// no sample close enough to the compared time.
+    pub fn model(&self) -> Option<&Model> {
+        self.model.as_ref()
+    }
+
+    /// Row label of the compared values, e.g. "  1day ago"
+    pub fn label(&self) -> String {
+        match self.offset {
+            Some(offset) => format!("  {} ago", humantime::format_duration(offset)),
+            None => String::new(),
+        }
+    }
+
+    /// Change the offset, or turn comparison off with None, and fetch the
+    /// compared sample for the displayed one at `timestamp`.
+    pub fn set_offset(&mut self, offset: Option<Duration>, timestamp: SystemTime) {
+        self.offset = offset;
+        self.update(timestamp);
+    }
+
+    /// Fetch the compared sample for the displayed one at `timestamp`.
+    pub fn update(&mut self, timestamp: SystemTime) {
+        let target = match self.offset.and_then(|offset| timestamp.checked_sub(offset)) {
+            Some(target) => target,
+            None => {
+                self.model = None;
+                return;
+            }
+        };
+        self.model = self.advance.jump_sample_to(target).filter(|model| {
+            let skew = match model.timestamp.duration_since(target) {
+                Ok(skew) => skew,
+                Err(e) => e.duration(),
+            };
+            skew <= MAX_COMPARE_SKEW
+        });
+    }
+}
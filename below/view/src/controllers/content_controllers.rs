@@ -14,6 +14,8 @@
 
 use super::*;
 use crate::filter_popup;
+use crate::inspect_popup;
+use crate::search_popup;
 
 // Sort by selected column
 make_event_controller!(
@@ -56,7 +58,7 @@ make_event_controller!(
     FilterPopup,
     "filter",
     "f",
-    vec![Event::Char('/')],
+    vec![Event::Char('F')],
     |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
     |c: &mut Cursive, cmd_vec: &[&str]| {
         let (state, title_idx, title_name, tab) = {
@@ -95,6 +97,87 @@ make_event_controller!(
     }
 );
 
+// Search all visible columns and jump to the first match
+make_event_controller!(
+    SearchPopup,
+    "search",
+    "se",
+    vec![Event::Char('/')],
+    |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
+    |c: &mut Cursive, cmd_vec: &[&str]| {
+        if cmd_vec.len() > 1 {
+            StatsView::<T>::search_myself(c, cmd_vec[1..].join(" "));
+        } else {
+            let current = StatsView::<T>::get_view(c).search.clone();
+            c.add_layer(search_popup::new(
+                current.unwrap_or_default(),
+                StatsView::<T>::search_myself,
+            ));
+        }
+    }
+);
+
+// Jump to next search match
+make_event_controller!(
+    NextMatchImpl,
+    "next_match",
+    "nm",
+    vec![Event::Char('n')],
+    |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
+    |c: &mut Cursive, _cmd_vec: &[&str]| {
+        jump_to_match::<T>(c, true);
+    }
+);
+
+// Jump to previous search match
+make_event_controller!(
+    PrevMatchImpl,
+    "prev_match",
+    "pm",
+    vec![Event::Char('N')],
+    |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
+    |c: &mut Cursive, _cmd_vec: &[&str]| {
+        jump_to_match::<T>(c, false);
+    }
+);
+
+fn jump_to_match<T: 'static + ViewBridge>(c: &mut Cursive, forward: bool) {
+    {
+        let mut view = StatsView::<T>::get_view(c);
+        match (view.search.clone(), view.jump_to_match(forward)) {
+            (_, Some(cb)) => cb(c),
+            (Some(search), None) => view.set_alert(&format!("Pattern not found: {}", search)),
+            (None, None) => view.set_alert("No search, start one with search"),
+        }
+    }
+    StatsView::<T>::refresh_myself(c);
+}
+
+// Show every field of the selected row
+make_event_controller!(
+    InspectPopup,
+    "inspect",
+    "in",
+    vec![Event::Char('i')],
+    |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
+    |c: &mut Cursive, _cmd_vec: &[&str]| {
+        let inspected = {
+            let mut view = StatsView::<T>::get_view(c);
+            let selection = view.get_detail_view().selection();
+            let tab = view.get_tab_view().get_cur_selected().to_string();
+            let state = view.state.borrow();
+            selection.and_then(|key| T::inspect(&state, &key, &tab))
+        };
+        match inspected {
+            Some((title, json)) => c.add_layer(inspect_popup::new(&title, json)),
+            None => {
+                StatsView::<T>::get_view(c).set_alert("Nothing to inspect");
+                StatsView::<T>::refresh_myself(c);
+            }
+        }
+    }
+);
+
 // Clear filter
 make_event_controller!(
     ClearFilter,
@@ -116,11 +116,22 @@ make_controllers!(
     SortCol: SortByColumn,
     Filter: FilterPopup,
     CFilter: ClearFilter,
+    Search: SearchPopup,
+    Inspect: InspectPopup,
+    NextMatch: NextMatchImpl,
+    PrevMatch: PrevMatchImpl,
     JForward: JumpForward,
     JBackward: JumpBackward,
     NSample: NextSample,
     PSample: PrevSample,
     Pause: PauseImpl,
+    Compare: CompareImpl,
+    Playback: PlaybackImpl,
+    RefreshFaster: RefreshFasterImpl,
+    RefreshSlower: RefreshSlowerImpl,
+    RefreshInterval: RefreshIntervalImpl,
+    Snapshot: SnapshotImpl,
+    Profile: ProfileImpl,
     Quit: QuitImpl,
     Help: HelpMenu,
     Process: ProcessView,
@@ -131,6 +142,8 @@ make_controllers!(
     GpuZoom: GpuZoomView,
     Zoom: ZoomView,
     Fold: FoldProcessView,
+    CollapseAll: CollapseAllCgroups,
+    ExpandToDepth: ExpandCgroupsToDepth,
     NextPage: NextPageImpl,
     PrevPage: PrevPageImpl,
     NextSelection: NextSelectionImpl,
@@ -12,10 +12,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::path::PathBuf;
+use std::time::Duration;
+
+use common::cliutil;
 use store::Direction;
 
 use super::*;
+use crate::compare::DEFAULT_COMPARE_OFFSET;
 use crate::jump_popup;
+use crate::playback;
+use crate::resume_popup;
 use crate::ViewMode;
 
 // Jump forward
@@ -127,24 +134,263 @@ make_event_controller!(
     "pr",
     vec![Event::Char(' ')],
     |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
-    |c: &mut Cursive, _cmd_vec: &[&str]| {
-        {
-            let view_state = c.user_data::<ViewState>().expect("user data not set");
+    |c: &mut Cursive, cmd_vec: &[&str]| {
+        let mode = c
+            .user_data::<ViewState>()
+            .expect("user data not set")
+            .mode
+            .clone();
+        match mode {
+            // On resume, either catch up on the samples skipped while paused
+            // or jump back to the latest sample
+            ViewMode::Pause(adv) => match cmd_vec.get(1) {
+                Some(&"catch_up") => resume_popup::resume(c, adv, true),
+                Some(&"now") => resume_popup::resume(c, adv, false),
+                Some(option) => {
+                    view_warn!(c, "Unknown resume option: {}", option);
+                }
+                None if adv.borrow().skipped_since().is_some() => {
+                    c.add_layer(resume_popup::new(adv));
+                }
+                None => resume_popup::resume(c, adv, false),
+            },
+            ViewMode::Live(adv) => {
+                // If it's live local, we need to jump to the lastest sample
+                let mut advance = adv.borrow_mut();
+                advance.get_latest_sample();
+                advance.mark_skipped_since();
+                drop(advance);
+                c.user_data::<ViewState>().expect("user data not set").mode = ViewMode::Pause(adv);
+            }
+            // Keep the skipped range so catching up can be resumed later
+            ViewMode::CatchUp(adv) => {
+                c.user_data::<ViewState>().expect("user data not set").mode = ViewMode::Pause(adv);
+            }
+            ViewMode::Replay(_) => {
+                if let Some(playback) = c
+                    .user_data::<ViewState>()
+                    .expect("user data not set")
+                    .playback
+                    .as_mut()
+                {
+                    playback.toggle_pause();
+                }
+            }
+        };
+        crate::refresh(c);
+    }
+);
 
-            match &view_state.mode {
-                ViewMode::Pause(adv) => {
-                    // On resume, we need to jump back to latest sample
-                    adv.borrow_mut().get_latest_sample();
-                    view_state.mode = ViewMode::Live(adv.clone());
+// Compare with the values at an offset earlier
+make_event_controller!(
+    CompareImpl,
+    "compare",
+    "cmp",
+    vec![Event::Char('C')],
+    |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
+    |c: &mut Cursive, cmd_vec: &[&str]| {
+        let current_offset = match c
+            .user_data::<ViewState>()
+            .expect("user data not set")
+            .compare
+            .as_ref()
+        {
+            Some(compare) => compare.offset(),
+            None => {
+                view_warn!(c, "Comparison is only available in replay mode");
+                return;
+            }
+        };
+        let offset = match cmd_vec.get(1) {
+            // Without an offset, toggle comparing with the same time yesterday
+            None if current_offset.is_some() => None,
+            None => Some(DEFAULT_COMPARE_OFFSET),
+            Some(&"off") => None,
+            Some(_) => match cliutil::duration_from_str(&cmd_vec[1..].join(" ")) {
+                Ok(offset) => Some(offset),
+                Err(e) => {
+                    view_warn!(c, "{:#}", e);
+                    return;
                 }
-                ViewMode::Live(adv) => {
-                    // If it's live local, we need to jump to the lastest sample
-                    adv.borrow_mut().get_latest_sample();
-                    view_state.mode = ViewMode::Pause(adv.clone());
+            },
+        };
+        let view_state = c.user_data::<ViewState>().expect("user data not set");
+        let timestamp = view_state.timestamp;
+        if let Some(compare) = view_state.compare.as_mut() {
+            compare.set_offset(offset, timestamp);
+        }
+        crate::refresh(c);
+    }
+);
+
+// Time-lapse playback of a replay
+make_event_controller!(
+    PlaybackImpl,
+    "playback",
+    "pb",
+    vec![Event::Char('P')],
+    |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
+    |c: &mut Cursive, cmd_vec: &[&str]| {
+        let view_state = c.user_data::<ViewState>().expect("user data not set");
+        if !matches!(view_state.mode, ViewMode::Replay(_)) {
+            view_warn!(c, "Playback is only available in replay mode");
+            return;
+        }
+        let speed = match cmd_vec.get(1) {
+            // Without a speed, toggle playback at the default speed
+            None if view_state.playback.is_some() => None,
+            None => Some(playback::DEFAULT_PLAYBACK_SPEED),
+            Some(&"off") => None,
+            Some(speed) => match playback::parse_speed(speed) {
+                Ok(speed) => Some(speed),
+                Err(e) => {
+                    view_warn!(c, "{:#}", e);
+                    return;
                 }
-                _ => {}
-            };
+            },
+        };
+        let timestamp = view_state.timestamp;
+        match (speed, view_state.playback.as_mut()) {
+            (Some(speed), Some(playback)) => playback.set_speed(speed),
+            (Some(speed), None) => {
+                view_state.playback = Some(playback::Playback::new(speed, timestamp))
+            }
+            (None, _) => view_state.playback = None,
         }
         crate::status_bar::refresh(c);
     }
 );
+
+// Write the samples up to the one shown to a snapshot file, e.g.
+// "snapshot 10m /tmp/incident.snapshot"
+make_event_controller!(
+    SnapshotImpl,
+    "snapshot",
+    "ss",
+    vec![],
+    |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
+    |c: &mut Cursive, cmd_vec: &[&str]| {
+        let view_state = c.user_data::<ViewState>().expect("user data not set");
+        let snapshot = match view_state.snapshot.clone() {
+            Some(snapshot) => snapshot,
+            None => {
+                view_warn!(c, "Snapshots are not available for the samples shown");
+                return;
+            }
+        };
+        let timestamp = view_state.timestamp;
+        let (duration, output) = match cmd_vec {
+            [_, duration @ .., output] if !duration.is_empty() => {
+                match cliutil::duration_from_str(&duration.join(" ")) {
+                    Ok(duration) => (duration, PathBuf::from(*output)),
+                    Err(e) => {
+                        view_warn!(c, "{:#}", e);
+                        return;
+                    }
+                }
+            }
+            _ => {
+                view_warn!(
+                    c,
+                    "Expected a duration and a file, e.g. snapshot 10m /tmp/incident.snapshot"
+                );
+                return;
+            }
+        };
+        let begin = match timestamp.checked_sub(duration) {
+            Some(begin) => begin,
+            None => {
+                view_warn!(c, "Duration reaches before the epoch");
+                return;
+            }
+        };
+        // Snapshots end before their end time, so end after the sample shown
+        let end = timestamp + Duration::from_secs(1);
+        view_warn!(c, "Creating snapshot at {}", output.display());
+        let sink = c.cb_sink().clone();
+        let res = std::thread::Builder::new()
+            .name("view_snapshot".to_owned())
+            .spawn(move || {
+                let res = snapshot(begin, end, output);
+                // The view may have quit meanwhile
+                let _ = sink.send(Box::new(move |c| match res {
+                    Ok(tarball) => view_warn!(c, "Snapshot created at {}", tarball.display()),
+                    Err(e) => view_warn!(c, "Failed to create snapshot: {:#}", e),
+                }));
+            });
+        if let Err(e) = res {
+            view_warn!(c, "Failed to create snapshot: {:#}", e);
+        }
+    }
+);
+
+/// Show the refresh interval set by a refresh command in the status bar, or
+/// why it could not be changed
+fn refresh_interval_changed(c: &mut Cursive, res: anyhow::Result<u64>) {
+    if let Err(e) = res {
+        view_warn!(c, "{:#}", e);
+    }
+    crate::status_bar::refresh(c);
+}
+
+// Refresh live mode more often
+make_event_controller!(
+    RefreshFasterImpl,
+    "refresh_faster",
+    "rf",
+    vec![Event::Char(']')],
+    |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
+    |c: &mut Cursive, _cmd_vec: &[&str]| {
+        let res = c
+            .user_data::<ViewState>()
+            .expect("user data not set")
+            .step_refresh_interval(true);
+        refresh_interval_changed(c, res);
+    }
+);
+
+// Refresh live mode less often
+make_event_controller!(
+    RefreshSlowerImpl,
+    "refresh_slower",
+    "rs",
+    vec![Event::Char('[')],
+    |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
+    |c: &mut Cursive, _cmd_vec: &[&str]| {
+        let res = c
+            .user_data::<ViewState>()
+            .expect("user data not set")
+            .step_refresh_interval(false);
+        refresh_interval_changed(c, res);
+    }
+);
+
+// Set the refresh interval of live mode
+make_event_controller!(
+    RefreshIntervalImpl,
+    "refresh_interval",
+    "ri",
+    vec![],
+    |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
+    |c: &mut Cursive, cmd_vec: &[&str]| {
+        // Seconds, optionally suffixed with "s"
+        let interval_s = match cmd_vec
+            .get(1)
+            .map(|interval| interval.trim_end_matches('s').parse::<u64>())
+        {
+            Some(Ok(interval_s)) => interval_s,
+            _ => {
+                view_warn!(
+                    c,
+                    "Expected a refresh interval in seconds, e.g. refresh_interval 2"
+                );
+                return;
+            }
+        };
+        let res = c
+            .user_data::<ViewState>()
+            .expect("user data not set")
+            .set_refresh_interval(interval_s);
+        refresh_interval_changed(c, res);
+    }
+);
@@ -338,3 +338,275 @@ next_col = 'd'
         Some(&Controllers::Cgroup)
     );
 }
+
+#[test]
+fn test_fuzzy_match() {
+    use crate::command_palette::fuzzy_match;
+
+    assert_eq!(fuzzy_match("", "cgroup"), Some(0));
+    assert_eq!(fuzzy_match("xyz", "cgroup"), None);
+    // Order matters
+    assert_eq!(fuzzy_match("pc", "cgroup"), None);
+    assert!(fuzzy_match("CG", "cgroup").is_some());
+    // Consecutive and word start matches rank higher
+    assert!(fuzzy_match("nt", "next_tab") > fuzzy_match("nt", "invoke_cmd_palette"));
+    assert!(fuzzy_match("jf", "jump_forward") > fuzzy_match("jf", "jump_backward"));
+}
+
+#[test]
+fn test_sort_string_completion() {
+    use crate::command_palette::rank_matches;
+    use crate::process_view::ProcessState;
+    use crate::stats_view::StateCommon;
+
+    let fields = ProcessState::sort_strings();
+    assert!(fields.contains(&"cpu.usage_pct".to_owned()));
+    // Components of field ids count as word starts
+    let matches = rank_matches("cpu.usa", fields.iter().map(String::as_str));
+    assert_eq!(matches.first(), Some(&"cpu.usage_pct"));
+    assert!(rank_matches("xyzzy", fields.iter().map(String::as_str)).is_empty());
+}
+
+#[test]
+fn test_cmd_history_persistence() {
+    use std::collections::VecDeque;
+
+    use crate::command_palette::load_cmd_history;
+    use crate::command_palette::save_cmd_history;
+
+    let tempdir = TempDir::with_prefix("below_history_test.").expect("Failed to create temp dir");
+    // The directory is created on save
+    let path = tempdir.path().join("below").join("cmd_history");
+    assert!(load_cmd_history(&path).is_empty());
+
+    let history = (0..150)
+        .map(|i| format!("sort cpu.usage_pct {}", i))
+        .collect::<VecDeque<String>>();
+    save_cmd_history(&path, &history).expect("Failed to save history");
+    // Only the most recent commands are kept
+    let restored = load_cmd_history(&path);
+    assert_eq!(restored.len(), 100);
+    assert_eq!(restored.front(), history.get(50));
+    assert_eq!(restored.back(), history.back());
+}
+
+#[test]
+fn test_help_menu_remapped_keys() {
+    use crate::help_menu::gen_controller_lines;
+    use crate::help_menu::gen_reserved_lines;
+
+    let mut fake_view = FakeView::new();
+    fake_view.add_cgroup_view();
+
+    // help loses its default keys to other commands and is left unbound.
+    let cmdrc_str = "help = []
+process = ['h', 'P']
+system = '?'
+";
+    let cmdrc_val = cmdrc_str
+        .parse::<Value>()
+        .expect("Failed to parse test cmdrc");
+    let event_controllers = make_event_controller_map(&mut fake_view.inner, &Some(cmdrc_val));
+
+    let lines = gen_controller_lines(&event_controllers);
+    let line_for = |cmd: &str| {
+        lines
+            .iter()
+            .find(|line| line.split_ascii_whitespace().next() == Some(cmd))
+            .unwrap_or_else(|| panic!("No help line for {}", cmd))
+            .clone()
+    };
+    assert!(line_for("help").contains(" - "));
+    assert!(line_for("process").contains("'P','h','p'"));
+    assert!(line_for("system").contains("'?','s'"));
+    assert!(line_for("next_selection").contains("<Ctrl> 'n'"));
+
+    // 'P' now goes to the process command instead of sorting by pid
+    let reserved = gen_reserved_lines(&event_controllers);
+    assert!(!reserved.iter().any(|line| line.contains("sort by pid")));
+    assert!(reserved.iter().any(|line| line.contains("sort by name")));
+}
+
+#[test]
+fn test_cgroup_fold_state_persistence() {
+    use crate::cgroup_view::CgroupState;
+
+    let tempdir = TempDir::with_prefix("below_fold_test.").expect("Failed to create temp dir");
+    let path = tempdir.path().join("cgroup_folds");
+
+    let mut state = CgroupState::new(Default::default());
+    state
+        .load_fold_state(path.clone())
+        .expect("Missing fold state should not be an error");
+    state.fold_to_depth(2);
+    state
+        .collapsed_cgroups
+        .borrow_mut()
+        .insert("/system.slice".into());
+    state.save_fold_state().expect("Failed to save fold state");
+
+    let mut restored = CgroupState::new(Default::default());
+    restored
+        .load_fold_state(path)
+        .expect("Failed to load fold state");
+    assert_eq!(restored.fold_depth, Some(2));
+    assert!(
+        restored
+            .collapsed_cgroups
+            .borrow()
+            .contains("/system.slice")
+    );
+
+    assert!(restored.expand_one_level());
+    assert_eq!(restored.fold_depth, Some(3));
+    assert!(restored.collapsed_cgroups.borrow().is_empty());
+    restored.expand_all();
+    assert!(!restored.expand_one_level());
+}
+
+#[test]
+fn test_view_profiles() {
+    use crate::viewrc::DefaultFrontView;
+    use crate::viewrc::ViewRc;
+
+    let belowrc_str = r#"
+[view]
+default_view = "cgroup"
+highlight_color = "cyan"
+collapse_cgroups = true
+
+[view.profile.incident]
+default_view = "process"
+process_columns = ["cpu.usage_pct", "mem.rss_bytes"]
+refresh_interval_s = 1
+"#;
+    let (mut viewrc, err) = ViewRc::from_belowrc_str(belowrc_str);
+    assert!(err.is_none(), "{:?}", err);
+    assert_eq!(viewrc.collapse_cgroups, Some(true));
+    assert!(matches!(
+        viewrc.settings().default_view,
+        Some(DefaultFrontView::Cgroup)
+    ));
+
+    viewrc
+        .set_active_profile(Some("incident"))
+        .expect("Failed to switch profile");
+    let settings = viewrc.settings();
+    assert!(matches!(
+        settings.default_view,
+        Some(DefaultFrontView::Process)
+    ));
+    assert_eq!(settings.refresh_interval_s, Some(1));
+    // Falls back to the [view] settings
+    assert_eq!(settings.highlight_color.as_deref(), Some("cyan"));
+    assert!(
+        crate::process_tabs::ProcessTab::from_field_ids(
+            settings.process_columns.as_deref().unwrap_or_default()
+        )
+        .is_ok()
+    );
+
+    assert!(viewrc.set_active_profile(Some("work")).is_err());
+    viewrc
+        .set_active_profile(None)
+        .expect("Failed to reset profile");
+    assert_eq!(viewrc.settings().refresh_interval_s, None);
+    assert!(
+        crate::process_tabs::ProcessTab::from_field_ids(&["cpu.nonexistent".to_owned()]).is_err()
+    );
+}
+
+#[test]
+fn test_view_themes() {
+    use cursive::theme::BaseColor;
+    use cursive::theme::Color;
+
+    use crate::theme::ViewTheme;
+    use crate::viewrc::ViewRc;
+
+    let belowrc_str = r##"
+[view]
+theme = "solarized-light"
+critical_color = "color160"
+
+[view.profile.light]
+theme = "light"
+highlight_color = "#005f87"
+tab_color = "#12"
+"##;
+    let (mut viewrc, err) = ViewRc::from_belowrc_str(belowrc_str);
+    assert!(err.is_none(), "{:?}", err);
+
+    let (theme, errors) = ViewTheme::from_profile(&viewrc.settings(), true);
+    assert!(errors.is_empty(), "{:?}", errors);
+    let solarized = ViewTheme::builtin("solarized-light").expect("Missing built-in theme");
+    assert_eq!(theme.highlight, solarized.highlight);
+    assert_eq!(theme.critical, Color::from_256colors(160));
+
+    // 24 bit colors are approximated without truecolor support
+    let (theme, _) = ViewTheme::from_profile(&viewrc.settings(), false);
+    assert_eq!(theme.highlight, Color::RgbLowRes(1, 3, 4));
+
+    viewrc
+        .set_active_profile(Some("light"))
+        .expect("Failed to switch profile");
+    let (theme, errors) = ViewTheme::from_profile(&viewrc.settings(), true);
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(theme.highlight, Color::Rgb(0x00, 0x5f, 0x87));
+    assert_eq!(theme.warning, Color::Dark(BaseColor::Yellow));
+    // Invalid colors are left at the theme's
+    assert_eq!(theme.tab, Color::Dark(BaseColor::Blue));
+    // Falls back to the [view] settings
+    assert_eq!(theme.critical, Color::from_256colors(160));
+
+    let (theme, errors) = ViewTheme::from_profile(
+        &crate::viewrc::ViewProfile {
+            theme: Some("neon".to_owned()),
+            ..Default::default()
+        },
+        true,
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(Some(theme), ViewTheme::builtin("default"));
+}
+
+#[test]
+fn test_playback() {
+    use std::time::Duration;
+    use std::time::Instant;
+    use std::time::SystemTime;
+
+    use crate::playback::parse_speed;
+    use crate::playback::Playback;
+
+    assert_eq!(parse_speed("60x").expect("Failed to parse speed"), 60.0);
+    assert_eq!(parse_speed("0.5").expect("Failed to parse speed"), 0.5);
+    assert!(parse_speed("0").is_err());
+    assert!(parse_speed("fast").is_err());
+
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+    let now = Instant::now();
+    let mut playback = Playback::new(60.0, start);
+    assert_eq!(playback.label(), "60x");
+    assert_eq!(playback.advance_playhead(start, now), Some(start));
+    // One second of wall time plays a minute of samples
+    let playhead = playback
+        .advance_playhead(start, now + Duration::from_secs(1))
+        .expect("Playback is paused");
+    assert_eq!(playhead, start + Duration::from_secs(60));
+
+    // Moving the replay manually restarts playback from there
+    let jumped = start + Duration::from_secs(3600);
+    let playhead = playback
+        .advance_playhead(jumped, now + Duration::from_secs(2))
+        .expect("Playback is paused");
+    assert_eq!(playhead, jumped + Duration::from_secs(60));
+
+    playback.toggle_pause();
+    assert!(playback.is_paused());
+    assert_eq!(playback.label(), "60x paused");
+    assert_eq!(
+        playback.advance_playhead(jumped, now + Duration::from_secs(3)),
+        None
+    );
+}
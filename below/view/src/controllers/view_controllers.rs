@@ -243,6 +243,31 @@ make_event_controller!(
     }
 );
 
+// Switch view profile
+make_event_controller!(
+    ProfileImpl,
+    "profile",
+    "pf",
+    vec![],
+    |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
+    |c: &mut Cursive, cmd_vec: &[&str]| {
+        // Without a name, go back to the [view] settings
+        let name = cmd_vec.get(1).copied().filter(|name| *name != "off");
+        let res = c
+            .user_data::<ViewState>()
+            .expect("No data stored in Cursive object!")
+            .viewrc
+            .set_active_profile(name);
+        match res {
+            Ok(()) => {
+                crate::apply_profile(c, true);
+                refresh(c)
+            }
+            Err(e) => view_warn!(c, "{:#}", e),
+        }
+    }
+);
+
 // Zoom in View
 make_event_controller!(
     ZoomView,
@@ -295,7 +320,42 @@ make_event_controller!(
                     .handle_state_for_entering_zoom(current_selection);
                 MainViewState::Process(ProcessZoomState::Cgroup)
             }
-            _ => return,
+            // Pressing 'z' on an interface in the Iface tab shows the
+            // processes with traffic on it, if per-process network
+            // accounting is enabled
+            MainViewState::System => {
+                let iface = {
+                    let mut view = crate::system_view::SystemView::get_system_view(c);
+                    if view.get_tab_view().get_cur_selected() != "Iface" {
+                        return;
+                    }
+                    match view.get_detail_view().selection() {
+                        Some(iface) => iface.as_ref().clone(),
+                        None => return,
+                    }
+                };
+                let pids = c
+                    .user_data::<ViewState>()
+                    .expect("No data stored in Cursive object!")
+                    .process
+                    .borrow()
+                    .processes
+                    .iter()
+                    .filter(|(_, spm)| {
+                        spm.net
+                            .as_ref()
+                            .is_some_and(|net| net.uses_interface(&iface))
+                    })
+                    .map(|(pid, _)| *pid)
+                    .collect();
+                crate::process_view::ProcessView::get_process_view(c)
+                    .state
+                    .borrow_mut()
+                    .handle_state_for_entering_pids_zoom(pids);
+                MainViewState::Process(ProcessZoomState::Pids)
+            }
+            #[cfg(fbcode_build)]
+            MainViewState::Gpu => return,
         };
 
         match &next_state {
@@ -348,6 +408,75 @@ make_event_controller!(
     }
 );
 
+// Collapse cgroup tree to top level cgroups
+make_event_controller!(
+    CollapseAllCgroups,
+    "collapse_all",
+    "ca",
+    vec![Event::Char('-')],
+    |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
+    |c: &mut Cursive, _cmd_vec: &[&str]| {
+        let current_state = c
+            .user_data::<ViewState>()
+            .expect("No data stored in Cursive object!")
+            .main_view_state
+            .clone();
+
+        if current_state == MainViewState::Cgroup {
+            let mut cgroup_view = crate::cgroup_view::CgroupView::get_cgroup_view(c);
+            cgroup_view.state.borrow_mut().fold_to_depth(1);
+            crate::cgroup_view::CgroupView::save_fold_state(&mut cgroup_view);
+        }
+
+        // Redraw screen now so we don't have to wait until next tick
+        refresh(c)
+    }
+);
+
+// Expand cgroup tree to a depth
+make_event_controller!(
+    ExpandCgroupsToDepth,
+    "expand_to_depth",
+    "ed",
+    vec![Event::Char('+')],
+    |_view: &mut StatsView<T>, _cmd_vec: &[&str]| {},
+    |c: &mut Cursive, cmd_vec: &[&str]| {
+        let current_state = c
+            .user_data::<ViewState>()
+            .expect("No data stored in Cursive object!")
+            .main_view_state
+            .clone();
+
+        if current_state == MainViewState::Cgroup {
+            let mut cgroup_view = crate::cgroup_view::CgroupView::get_cgroup_view(c);
+            // Without a depth, expand one more level, or everything if the
+            // tree is not folded to a depth
+            let res = match cmd_vec.get(1).map(|d| d.parse::<u32>()) {
+                Some(Ok(0)) => Err("Depth must be at least 1".to_owned()),
+                Some(Ok(depth)) => {
+                    cgroup_view.state.borrow_mut().fold_to_depth(depth);
+                    Ok(())
+                }
+                Some(Err(e)) => Err(format!("Fail to parse argument: {}, {}", cmd_vec[1], e)),
+                None => {
+                    let mut state = cgroup_view.state.borrow_mut();
+                    if !state.expand_one_level() {
+                        state.expand_all();
+                    }
+                    Ok(())
+                }
+            };
+            match res {
+                Ok(()) => crate::cgroup_view::CgroupView::save_fold_state(&mut cgroup_view),
+                Err(e) => cgroup_view.get_cmd_palette().set_alert(e),
+            }
+        }
+
+        // Redraw screen now so we don't have to wait until next tick
+        refresh(c)
+    }
+);
+
 // utl function to parse page length
 fn parse_page_length(cmd_vec: &[&str]) -> Result<usize, String> {
     static DEFAULT_PAGE_LENGTH: usize = 15;
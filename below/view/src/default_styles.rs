@@ -14,16 +14,29 @@
 
 use crate::render::HasViewStyle;
 use crate::render::ViewStyle;
+use crate::render::CGROUP_CHURN_HIGHLIGHT;
+use crate::render::CORRUPTION_HIGHLIGHT;
 use crate::render::CPU_HIGHLIGHT;
+use crate::render::DSTATE_HIGHLIGHT;
+use crate::render::HUNG_TASK_HIGHLIGHT;
+use crate::render::LINK_FLAP_HIGHLIGHT;
 use crate::render::MEM_HIGHLIGHT;
+use crate::render::OOM_KILL_HIGHLIGHT;
 use crate::render::PRESSURE_HIGHLIGHT;
+use crate::render::THROTTLE_HIGHLIGHT;
 
 impl HasViewStyle for model::SingleCgroupModel {
     fn get_view_style(field_id: &Self::FieldId) -> Option<ViewStyle> {
+        use model::SingleCgroupModelFieldId::Churn;
         use model::SingleCgroupModelFieldId::Cpu;
+        use model::SingleCgroupModelFieldId::Dstate;
+        use model::SingleCgroupModelFieldId::Mem;
         use model::SingleCgroupModelFieldId::Pressure;
         match field_id {
+            Churn(field_id) => model::CgroupChurnModel::get_view_style(field_id),
             Cpu(field_id) => model::CgroupCpuModel::get_view_style(field_id),
+            Dstate(field_id) => model::DStateModel::get_view_style(field_id),
+            Mem(field_id) => model::CgroupMemoryModel::get_view_style(field_id),
             Pressure(field_id) => model::CgroupPressureModel::get_view_style(field_id),
             _ => None,
         }
@@ -42,13 +55,37 @@ impl HasViewStyle for model::CgroupCpuModel {
     }
 }
 
+impl HasViewStyle for model::CgroupChurnModel {
+    fn get_view_style(_field_id: &Self::FieldId) -> Option<ViewStyle> {
+        Some(CGROUP_CHURN_HIGHLIGHT.clone())
+    }
+}
+
+impl HasViewStyle for model::CgroupMemoryModel {
+    fn get_view_style(field_id: &Self::FieldId) -> Option<ViewStyle> {
+        use model::CgroupMemoryModelFieldId::EventsOomKillDelta;
+        match field_id {
+            EventsOomKillDelta => Some(OOM_KILL_HIGHLIGHT.clone()),
+            _ => None,
+        }
+    }
+}
+
 impl HasViewStyle for model::CgroupPressureModel {
     fn get_view_style(_field_id: &Self::FieldId) -> Option<ViewStyle> {
         Some(PRESSURE_HIGHLIGHT.clone())
     }
 }
 
-impl HasViewStyle for model::SingleNetModel {}
+impl HasViewStyle for model::SingleNetModel {
+    fn get_view_style(field_id: &Self::FieldId) -> Option<ViewStyle> {
+        use model::SingleNetModelFieldId::CarrierChangesDelta;
+        match field_id {
+            CarrierChangesDelta => Some(LINK_FLAP_HIGHLIGHT.clone()),
+            _ => None,
+        }
+    }
+}
 
 impl HasViewStyle for model::SingleProcessModel {
     fn get_view_style(field_id: &Self::FieldId) -> Option<ViewStyle> {
@@ -74,9 +111,23 @@ impl HasViewStyle for model::ProcessCpuModel {
 
 impl HasViewStyle for model::SystemModel {
     fn get_view_style(field_id: &Self::FieldId) -> Option<ViewStyle> {
+        use model::SystemModelFieldId::Dstate;
+        use model::SystemModelFieldId::HungTaskDetectDelta;
         use model::SystemModelFieldId::Mem;
         match field_id {
             Mem(field_id) => model::MemoryModel::get_view_style(field_id),
+            Dstate(field_id) => model::DStateModel::get_view_style(field_id),
+            HungTaskDetectDelta => Some(HUNG_TASK_HIGHLIGHT.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl HasViewStyle for model::DStateModel {
+    fn get_view_style(field_id: &Self::FieldId) -> Option<ViewStyle> {
+        use model::DStateModelFieldId::Count;
+        match field_id {
+            Count => Some(DSTATE_HIGHLIGHT.clone()),
             _ => None,
         }
     }
@@ -94,7 +145,21 @@ impl HasViewStyle for model::MemoryModel {
 
 impl HasViewStyle for model::Model {}
 
-impl HasViewStyle for model::SingleCpuModel {}
+impl HasViewStyle for model::SingleCpuModel {
+    fn get_view_style(field_id: &Self::FieldId) -> Option<ViewStyle> {
+        use model::SingleCpuModelFieldId::CoreThrottles;
+        use model::SingleCpuModelFieldId::PackageThrottles;
+        match field_id {
+            // Frequency capping skews the CPU percentages of the interval
+            CoreThrottles | PackageThrottles => Some(THROTTLE_HIGHLIGHT.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl HasViewStyle for model::SingleSoftirqModel {}
+
+impl HasViewStyle for model::SingleSchedModel {}
 
 impl HasViewStyle for model::VmModel {}
 
@@ -102,6 +167,29 @@ impl HasViewStyle for model::SingleSlabModel {}
 
 impl HasViewStyle for model::KsmModel {}
 
+impl HasViewStyle for model::LoadAvgModel {}
+
+impl HasViewStyle for model::SockStatModel {}
+
+impl HasViewStyle for model::SockStat6Model {}
+
+impl HasViewStyle for model::CollectionModel {}
+
 impl HasViewStyle for model::SingleDiskModel {}
 
 impl HasViewStyle for model::BtrfsModel {}
+
+impl HasViewStyle for model::BtrfsDeviceModel {
+    fn get_view_style(field_id: &Self::FieldId) -> Option<ViewStyle> {
+        use model::BtrfsDeviceModelFieldId::CorruptionErrs;
+        use model::BtrfsDeviceModelFieldId::ScrubUncorrectableErrors;
+        match field_id {
+            CorruptionErrs | ScrubUncorrectableErrors => Some(CORRUPTION_HIGHLIGHT.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl HasViewStyle for model::ResctrlMonGroupModel {}
+
+impl HasViewStyle for model::SingleTcModel {}
@@ -17,6 +17,7 @@ use std::collections::HashMap;
 use std::rc::Rc;
 
 use cursive::event::Event;
+use cursive::event::Key;
 use cursive::view::Nameable;
 use cursive::view::Scrollable;
 use cursive::view::View;
@@ -37,6 +38,18 @@ pub struct ControllerHelper {
     args: &'static str,
 }
 
+impl ControllerHelper {
+    fn new(controller: &Controllers) -> Self {
+        ControllerHelper {
+            events: vec![],
+            cmd: controller.command(),
+            cmd_short: controller.cmd_shortcut(),
+            description: get_description(controller),
+            args: get_args(controller),
+        }
+    }
+}
+
 impl std::fmt::Display for ControllerHelper {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
@@ -56,16 +69,20 @@ impl std::fmt::Display for ControllerHelper {
 }
 
 fn gen_hotkey_string(events: &[Event]) -> String {
-    return events
-        .iter()
-        .map(event_to_string)
-        .collect::<Vec<String>>()
-        .join(",");
+    // Unbound commands are still reachable from the command palette
+    if events.is_empty() {
+        return "-".into();
+    }
+    let mut hotkeys = events.iter().map(event_to_string).collect::<Vec<String>>();
+    hotkeys.sort();
+    hotkeys.join(",")
 }
 
 fn get_description(controller: &Controllers) -> &'static str {
     match controller {
-        Controllers::CmdPalette => "Invoking command palette.",
+        Controllers::CmdPalette => {
+            "Invoking command palette. Matching commands are listed while typing, <Tab> completes the best match."
+        }
         Controllers::NextTab => "Cycle topic tabs.",
         Controllers::PrevTab => "Reverse cycle topic tabs.",
         Controllers::NextCol => "Cycle columns.",
@@ -73,10 +90,18 @@ fn get_description(controller: &Controllers) -> &'static str {
         Controllers::Right => "Scroll right primary display.",
         Controllers::Left => "Scroll left primary display.",
         Controllers::SortCol => {
-            "Sort by the selected title, reverse the result by hitting 'S' again(cgroup view and process view only)."
+            "Sort by the selected title, reverse the result by sorting again (cgroup view and process view only)."
         }
         Controllers::Filter => "Filter by selected column.",
         Controllers::CFilter => "Clear the current filter.",
+        Controllers::Search => {
+            "Search all visible columns, highlight the matches and jump to the first one. Without text, clear the search."
+        }
+        Controllers::NextMatch => "Jump to the next row matching the search.",
+        Controllers::PrevMatch => "Jump to the previous row matching the search.",
+        Controllers::Inspect => {
+            "Show every field of the selected cgroup, process or interface as JSON."
+        }
         Controllers::JForward => {
             "Jump time by a specific amount forward or to a specific timestamp (replay and live-paused mode)."
         }
@@ -86,7 +111,16 @@ fn get_description(controller: &Controllers) -> &'static str {
         Controllers::NSample => "Show next sample (replay and live-paused mode).",
         Controllers::PSample => "Show previous sample (replay and live-paused mode).",
         Controllers::Pause => {
-            "pause/resume the live mode. While pausing, use the above commands to go forwards or backwards in time"
+            "pause/resume the live mode, or the playback of a replay. While pausing, use the above commands to go forwards or backwards in time. On resume, choose to catch up on the skipped samples or jump to now (\"pr catch_up\" or \"pr now\" in the command palette)"
+        }
+        Controllers::Compare => {
+            "Show the values at a given offset earlier next to the current ones, or toggle comparing with the same time yesterday without an offset. \"off\" turns it off (replay mode)."
+        }
+        Controllers::Playback => {
+            "Play the replay back automatically at a speed multiplier, e.g. \"60x\" plays an hour in a minute, or toggle playback at 60x without one. Pause/resume with pause_resume and seek with the jump and sample commands. \"off\" stops it (replay mode)."
+        }
+        Controllers::Profile => {
+            "Switch to a view profile from belowrc, or back to the [view] settings without a name or with \"off\"."
         }
         Controllers::Quit => "Quit.",
         Controllers::Help => "Toggle help menu.",
@@ -97,11 +131,17 @@ fn get_description(controller: &Controllers) -> &'static str {
         Controllers::GpuZoom => "Zoom into process view filtered by selected GPU.",
         Controllers::GpuProcess => "Zoom into process view for all GPU processes.",
         Controllers::Zoom => {
-            "If in cgroup view, zoom into process view filtered by cgroup. If in process view, zoom into cgroup view, selected on cgroup of process."
+            "If in cgroup view, zoom into process view filtered by cgroup. If in process view, zoom into cgroup view, selected on cgroup of process. If in the Iface tab of system view, zoom into process view filtered by processes with traffic on the interface (requires enable_process_net_stats)."
         }
         Controllers::Fold => "Fold processes (post filter) and display aggregated values.",
+        Controllers::CollapseAll => "Collapse cgroup tree to top level cgroups (cgroup view only).",
+        Controllers::ExpandToDepth => {
+            "Expand cgroup tree to the given depth, or one level further without a depth (cgroup view only)."
+        }
         Controllers::NextPage => "Scroll down 15 lines primary display.",
         Controllers::PrevPage => "Scroll up 15 lines primary display.",
+        Controllers::NextSelection => "Select next line of primary display.",
+        Controllers::PrevSelection => "Select previous line of primary display.",
         _ => "Unknown",
     }
 }
@@ -110,8 +150,13 @@ fn get_args(controller: &Controllers) -> &'static str {
     match controller {
         Controllers::SortCol => "SortKey",
         Controllers::Filter => "Substring",
+        Controllers::Search => "Substring",
         Controllers::JForward => "Time",
         Controllers::JBackward => "Time",
+        Controllers::ExpandToDepth => "Depth",
+        Controllers::Compare => "Duration",
+        Controllers::Playback => "Speed",
+        Controllers::Profile => "Name",
         _ => "-",
     }
 }
@@ -126,91 +171,158 @@ fn get_title() -> Vec<String> {
     ]
 }
 
+// Display order of the commands in help menu
+fn ordered_controllers() -> Vec<Controllers> {
+    vec![
+        Controllers::Help,
+        Controllers::CmdPalette,
+        Controllers::Quit,
+        Controllers::Left,
+        Controllers::Right,
+        Controllers::NextTab,
+        Controllers::PrevTab,
+        Controllers::NextCol,
+        Controllers::PrevCol,
+        Controllers::JForward,
+        Controllers::JBackward,
+        Controllers::NSample,
+        Controllers::PSample,
+        Controllers::Pause,
+        Controllers::Compare,
+        Controllers::Playback,
+        Controllers::Profile,
+        Controllers::SortCol,
+        Controllers::Filter,
+        Controllers::CFilter,
+        Controllers::Search,
+        Controllers::NextMatch,
+        Controllers::PrevMatch,
+        Controllers::Inspect,
+        Controllers::Zoom,
+        Controllers::Fold,
+        Controllers::CollapseAll,
+        Controllers::ExpandToDepth,
+        Controllers::Process,
+        Controllers::Cgroup,
+        Controllers::System,
+        Controllers::NextPage,
+        Controllers::PrevPage,
+        Controllers::NextSelection,
+        Controllers::PrevSelection,
+    ]
+}
+
 // Grab the user customized keymaps and generate helper message
-fn fill_controllers(
-    v: &mut SelectView<String>,
-    event_controllers: Rc<RefCell<HashMap<Event, Controllers>>>,
-) {
+pub fn gen_controller_lines(event_controllers: &HashMap<Event, Controllers>) -> Vec<String> {
     // event_controllers can generate helper messages in completely random order base on
     // user's customization. Instead of using it directly, we will generate a cmd-msg map
-    // to ensure the order.
-    //
-    let mut cmd_map: HashMap<Controllers, ControllerHelper> = HashMap::new();
-    for (event, controller) in event_controllers.borrow().iter() {
-        match cmd_map.get_mut(controller) {
-            Some(ref mut item) => item.events.push(event.clone()),
-            None => drop(cmd_map.insert(
-                controller.clone(),
-                ControllerHelper {
-                    events: vec![event.clone()],
-                    cmd: controller.command(),
-                    cmd_short: controller.cmd_shortcut(),
-                    description: get_description(controller),
-                    args: get_args(controller),
-                },
-            )),
-        }
+    // to ensure the order. Commands without any hot key are kept so the help menu
+    // always lists every command.
+    let ordered = ordered_controllers();
+    let mut cmd_map: HashMap<Controllers, ControllerHelper> = ordered
+        .iter()
+        .map(|controller| (controller.clone(), ControllerHelper::new(controller)))
+        .collect();
+    for (event, controller) in event_controllers.iter() {
+        cmd_map
+            .entry(controller.clone())
+            .or_insert_with(|| ControllerHelper::new(controller))
+            .events
+            .push(event.clone());
     }
 
-    // Unwrap in this vec! must be success, otherwise we may have lost
-    // controller(s) and should be detected by unit test.
-    let mut controllers = vec![
-        cmd_map.get(&Controllers::Help).unwrap().to_string(),
-        cmd_map.get(&Controllers::CmdPalette).unwrap().to_string(),
-        cmd_map.get(&Controllers::Quit).unwrap().to_string(),
-        cmd_map.get(&Controllers::Left).unwrap().to_string(),
-        cmd_map.get(&Controllers::Right).unwrap().to_string(),
-        cmd_map.get(&Controllers::NextTab).unwrap().to_string(),
-        cmd_map.get(&Controllers::PrevTab).unwrap().to_string(),
-        cmd_map.get(&Controllers::NextCol).unwrap().to_string(),
-        cmd_map.get(&Controllers::PrevCol).unwrap().to_string(),
-        cmd_map.get(&Controllers::JForward).unwrap().to_string(),
-        cmd_map.get(&Controllers::JBackward).unwrap().to_string(),
-        cmd_map.get(&Controllers::NSample).unwrap().to_string(),
-        cmd_map.get(&Controllers::PSample).unwrap().to_string(),
-        cmd_map.get(&Controllers::Pause).unwrap().to_string(),
-        cmd_map.get(&Controllers::SortCol).unwrap().to_string(),
-        cmd_map.get(&Controllers::Filter).unwrap().to_string(),
-        cmd_map.get(&Controllers::CFilter).unwrap().to_string(),
-        cmd_map.get(&Controllers::Zoom).unwrap().to_string(),
-        cmd_map.get(&Controllers::Fold).unwrap().to_string(),
-        cmd_map.get(&Controllers::Process).unwrap().to_string(),
-        cmd_map.get(&Controllers::Cgroup).unwrap().to_string(),
-        cmd_map.get(&Controllers::System).unwrap().to_string(),
-        cmd_map.get(&Controllers::NextPage).unwrap().to_string(),
-        cmd_map.get(&Controllers::PrevPage).unwrap().to_string(),
-    ];
+    let mut controllers = ordered
+        .iter()
+        .map(|controller| cmd_map[controller].to_string())
+        .collect::<Vec<String>>();
 
     controllers.extend(crate::get_extra_controller_str(&cmd_map));
+    controllers
+}
 
-    v.add_all_str(controllers);
+fn fill_controllers(
+    v: &mut SelectView<String>,
+    event_controllers: Rc<RefCell<HashMap<Event, Controllers>>>,
+) {
+    v.add_all_str(gen_controller_lines(&event_controllers.borrow()));
+}
+
+// Keys handled by the views themselves. A key bound to a command in belowrc
+// takes precedence, so it is left out here.
+pub fn gen_reserved_lines(event_controllers: &HashMap<Event, Controllers>) -> Vec<&'static str> {
+    vec![
+        (
+            Event::Key(Key::Down),
+            " <DOWN>         - scroll down primary display, next command if command palette activated\n",
+        ),
+        (
+            Event::Key(Key::Up),
+            " <UP>           - scroll up primary display, last command if command palette activated\n",
+        ),
+        (
+            Event::Key(Key::PageDown),
+            " <PgDn>         - scroll down 15 lines primary display\n",
+        ),
+        (
+            Event::Key(Key::PageUp),
+            " <PgUp>         - scroll up 15 lines primary display\n",
+        ),
+        (
+            Event::Key(Key::Home),
+            " <Home>         - scroll to top of primary display\n",
+        ),
+        (
+            Event::Key(Key::End),
+            " <End>          - scroll to end of primary display\n",
+        ),
+        (
+            Event::Key(Key::Enter),
+            " <Enter>        - collapse/expand cgroup tree, submit command if command palette activated\n",
+        ),
+        (
+            Event::CtrlChar('r'),
+            " <Ctrl>-r       - refresh the screen\n",
+        ),
+        (
+            Event::Char('P'),
+            " 'P'            - sort by pid (process view only)\n",
+        ),
+        (
+            Event::Char('N'),
+            " 'N'            - sort by name (process view only)\n",
+        ),
+        (
+            Event::Char('C'),
+            " 'C'            - sort by cpu (cgroup view and process view only)\n",
+        ),
+        (
+            Event::Char('M'),
+            " 'M'            - sort by memory (cgroup view and process view only)\n",
+        ),
+        (
+            Event::Char('D'),
+            " 'D'            - sort by total disk activity(cgroup view and process view only)\n",
+        ),
+        (
+            Event::Char('W'),
+            " 'W'            - sort by swap usage (process view only)\n",
+        ),
+    ]
+    .into_iter()
+    .filter(|(event, _)| !event_controllers.contains_key(event))
+    .map(|(_, line)| line)
+    .collect()
 }
 
-fn fill_reserved(v: &mut LinearLayout) {
-    let lines = vec![
-        " <DOWN>         - scroll down primary display, next command if command palette activated\n",
-        " <UP>           - scroll up primary display, last command if command palette activated\n",
-        " <PgDn>         - scroll down 15 lines primary display\n",
-        " <PgUp>         - scroll up 15 lines primary display\n",
-        " <Home>         - scroll to top of primary display\n",
-        " <End>          - scroll to end of primary display\n",
-        " <Enter>        - collapse/expand cgroup tree, submit command if command palette activated\n",
-        " <Ctrl>-r       - refresh the screen",
-        " 'P'            - sort by pid (process view only)\n",
-        " 'N'            - sort by name (process view only)\n",
-        " 'C'            - sort by cpu (cgroup view and process view only)\n",
-        " 'M'            - sort by memory (cgroup view and process view only)\n",
-        " 'D'            - sort by total disk activity(cgroup view and process view only)\n",
-    ];
-
-    for line in lines {
+fn fill_reserved(v: &mut LinearLayout, event_controllers: &HashMap<Event, Controllers>) {
+    for line in gen_reserved_lines(event_controllers) {
         v.add_child(TextView::new(line));
     }
 }
 
 pub fn new(event_controllers: Rc<RefCell<HashMap<Event, Controllers>>>) -> impl View {
     let mut reserved = LinearLayout::vertical();
-    fill_reserved(&mut reserved);
+    fill_reserved(&mut reserved, &event_controllers.borrow());
     let mut controllers = SelectView::<String>::new();
     fill_controllers(&mut controllers, event_controllers);
     LinearLayout::vertical()
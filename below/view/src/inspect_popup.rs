@@ -0,0 +1,83 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use cursive::event::Event;
+use cursive::event::Key;
+use cursive::view::Scrollable;
+use cursive::view::View;
+use cursive::views::Dialog;
+use cursive::views::OnEventView;
+use cursive::views::TextView;
+use enum_iterator::Sequence;
+use model::Queriable;
+use serde_json::Value;
+
+/// Every field of `model` as a pretty printed JSON object, keyed by field id
+/// in the order of the field ids. Fields of containers, e.g. the per NUMA
+/// node memory of a cgroup, are left out as their ids need an index.
+pub fn to_json<T>(model: &T) -> String
+where
+    T: Queriable,
+    T::FieldId: Sequence + ToString,
+{
+    let lines = enum_iterator::all::<T::FieldId>()
+        .map(|field_id| (field_id.to_string(), field_id))
+        .filter(|(name, _)| !name.contains('<'))
+        .map(|(name, field_id)| {
+            format!(
+                "  {}: {}",
+                Value::from(name),
+                model.query(&field_id).map_or(Value::Null, Value::from)
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("{{\n{}\n}}", lines.join(",\n"))
+}
+
+/// Scrollable popup showing `json`, the fields of the selected row
+pub fn new(title: &str, json: String) -> impl View {
+    OnEventView::new(
+        Dialog::new()
+            .title(title)
+            .padding_lrtb(1, 1, 1, 0)
+            .content(TextView::new(json).scrollable())
+            .dismiss_button("Close"),
+    )
+    .on_event(Key::Esc, |s| {
+        s.pop_layer();
+    })
+    .on_event(Event::Char('q'), |s| {
+        s.pop_layer();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let model = model::SingleNetModel {
+            interface: "eth0".to_owned(),
+            rx_bytes_per_sec: Some(1.5),
+            tx_packets: Some(10),
+            ..Default::default()
+        };
+        let json: Value = serde_json::from_str(&to_json(&model)).expect("Invalid JSON");
+        assert_eq!(json["interface"], "eth0");
+        assert_eq!(json["rx_bytes_per_sec"], 1.5);
+        assert_eq!(json["tx_packets"], 10);
+        assert_eq!(json["rx_errors"], Value::Null);
+    }
+}
@@ -52,7 +52,13 @@
 ///   or `S` to sort in ascending or descending order.
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
 use std::rc::Rc;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::SystemTime;
 
@@ -65,7 +71,6 @@ use common::util::get_belowrc_view_section_key;
 use crossterm::event::DisableMouseCapture;
 use crossterm::execute;
 use cursive::event::Event;
-use cursive::theme::BaseColor;
 use cursive::theme::Color;
 use cursive::theme::PaletteColor;
 use cursive::view::Nameable;
@@ -84,8 +89,11 @@ use model::CgroupModel;
 use model::GpuModel;
 use model::Model;
 use model::NetworkModel;
+use model::OomKillEvent;
 use model::ProcessModel;
+use model::ResctrlModel;
 use model::SystemModel;
+use model::TcModel;
 use store::Advance;
 use toml::value::Value;
 use viewrc::ViewRc;
@@ -96,23 +104,34 @@ open_source_shim!();
 mod cgroup_tabs;
 pub mod cgroup_view;
 pub mod command_palette;
+pub mod compare;
 mod default_styles;
 mod filter_popup;
 mod help_menu;
+mod inspect_popup;
+pub mod panic_report;
 mod process_tabs;
 mod process_view;
 mod render;
+mod search_popup;
+mod session;
 pub mod stats_view;
 mod status_bar;
 mod summary_view;
 mod system_tabs;
 mod system_view;
 mod tab_view;
+mod theme;
 
 pub struct View {
     inner: CursiveRunnable,
+    session: Option<session::SessionRecorder>,
 }
 
+/// Refresh intervals in seconds stepped through by the refresh_faster and
+/// refresh_slower commands
+const REFRESH_INTERVALS_S: [u64; 9] = [1, 2, 5, 10, 15, 30, 60, 120, 300];
+
 macro_rules! advance {
     ($c:ident, $adv:ident, $dir:expr) => {
         match $adv.advance($dir) {
@@ -159,6 +178,9 @@ pub mod controllers;
 pub mod viewrc;
 // Jump popup depends on view_warn
 mod jump_popup;
+mod notify;
+pub mod playback;
+mod resume_popup;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum ProcessZoomState {
@@ -185,16 +207,23 @@ impl MainViewState {
     }
 }
 
+/// Create a snapshot file at the given path of the samples between the given
+/// times, returning the path of the file
+pub type SnapshotFn = Arc<dyn Fn(SystemTime, SystemTime, PathBuf) -> Result<PathBuf> + Send + Sync>;
+
 #[derive(Clone)]
 pub enum ViewMode {
     Live(Rc<RefCell<Advance>>),
     Pause(Rc<RefCell<Advance>>),
+    /// Stepping through the samples skipped while live mode was paused
+    CatchUp(Rc<RefCell<Advance>>),
     Replay(Rc<RefCell<Advance>>),
 }
 
 // Invoked either when the data view was explicitly advanced, or
 // periodically (during live mode)
 fn refresh(c: &mut Cursive) {
+    panic_report::update_context(c);
     status_bar::refresh(c);
     summary_view::refresh(c);
     let current_state = c
@@ -233,6 +262,65 @@ pub fn set_active_screen(c: &mut Cursive, name: &str) {
     .expect("failed to find main_view_screens");
 }
 
+/// Apply the settings of the active view profile: its default view,
+/// process columns and colors, and with `set_refresh_interval` its refresh
+/// interval. Invalid settings are reported and left at their defaults.
+pub fn apply_profile(c: &mut Cursive, set_refresh_interval: bool) {
+    let view_state = c
+        .user_data::<ViewState>()
+        .expect("No data stored in Cursive object!");
+    let settings = view_state.viewrc.settings();
+    if set_refresh_interval && view_state.refresh_interval_s.is_some() {
+        if let Some(interval_s) = settings.refresh_interval_s {
+            // Only fails outside of live mode
+            view_state.set_refresh_interval(interval_s).ok();
+        }
+    }
+
+    let (view_theme, errors) =
+        theme::ViewTheme::from_profile(&settings, theme::truecolor_supported());
+    if !errors.is_empty() {
+        view_warn!(c, "{}", errors.join(", "));
+    }
+    theme::set_current(view_theme);
+    let mut theme = c.current_theme().clone();
+    theme.palette[PaletteColor::Highlight] = view_theme.highlight;
+    theme.palette[PaletteColor::HighlightText] = view_theme.highlight_text;
+    c.set_theme(theme);
+
+    if let Err(e) =
+        process_view::ProcessView::set_general_columns(c, settings.process_columns.as_deref())
+    {
+        view_warn!(c, "{}", e);
+    }
+
+    if let Some(view) = settings.default_view {
+        let current_state = c
+            .user_data::<ViewState>()
+            .expect("No data stored in Cursive object!")
+            .main_view_state
+            .clone();
+        if current_state.is_process_zoom_state() {
+            process_view::ProcessView::get_process_view(c)
+                .state
+                .borrow_mut()
+                .reset_state_for_quiting_zoom();
+        }
+        let (main_view_state, screen) = match view {
+            viewrc::DefaultFrontView::Cgroup => (MainViewState::Cgroup, "cgroup_view_panel"),
+            viewrc::DefaultFrontView::Process => (
+                MainViewState::Process(ProcessZoomState::NoZoom),
+                "process_view_panel",
+            ),
+            viewrc::DefaultFrontView::System => (MainViewState::System, "system_view_panel"),
+        };
+        c.user_data::<ViewState>()
+            .expect("No data stored in Cursive object!")
+            .main_view_state = main_view_state;
+        set_active_screen(c, screen);
+    }
+}
+
 pub struct ViewState {
     pub time_elapsed: Duration,
     /// Keep track of the lowest seen `time_elapsed` so that view can highlight abnormal
@@ -246,31 +334,61 @@ pub struct ViewState {
     pub cgroup: Rc<RefCell<CgroupModel>>,
     pub process: Rc<RefCell<ProcessModel>>,
     pub network: Rc<RefCell<NetworkModel>>,
+    pub resctrl: Rc<RefCell<Option<ResctrlModel>>>,
+    pub tc: Rc<RefCell<Option<TcModel>>>,
     #[cfg(fbcode_build)]
     pub gpu: Rc<RefCell<Option<GpuModel>>>,
+    pub oom_kills: Rc<RefCell<Vec<OomKillEvent>>>,
+    /// Set in replay mode to show the values at an offset earlier
+    pub compare: Option<compare::Compare>,
+    /// Set in replay mode while samples are played back automatically
+    pub playback: Option<playback::Playback>,
+    /// Set in live mode when belowrc has notification rules
+    pub notifier: Option<notify::Notifier>,
+    /// Set if the samples shown can be written to a snapshot file
+    pub snapshot: Option<SnapshotFn>,
     pub main_view_state: MainViewState,
     pub main_view_screens: HashMap<String, ScreenId>,
     pub mode: ViewMode,
     pub viewrc: ViewRc,
     pub viewrc_error: Option<String>,
+    /// Seconds between samples of the live collector, changed by switching
+    /// to a profile with a refresh interval or the refresh commands
+    pub refresh_interval_s: Option<Arc<AtomicU64>>,
+    /// Whether live samples are fetched from a remote recorder, which has
+    /// no new samples more often than it records them
+    pub refresh_interval_remote: bool,
+    /// Set in lite live mode to what is collected, e.g. "top 10 cgroups by
+    /// cpu"
+    pub lite: Option<String>,
     pub event_controllers: Rc<RefCell<HashMap<Event, controllers::Controllers>>>,
     pub cmd_controllers: Rc<RefCell<HashMap<&'static str, controllers::Controllers>>>,
 }
 
 impl ViewState {
-    pub fn update(&mut self, model: Model) {
+    pub fn update(&mut self, mut model: Model) {
+        // Growth of slab caches is tracked over the samples shown in order
+        if model.timestamp > self.timestamp {
+            model.system.track_slab_growth(&self.system.borrow());
+        }
         self.time_elapsed = model.time_elapsed;
         if model.time_elapsed.as_secs() != 0 && model.time_elapsed < self.lowest_time_elapsed {
             self.lowest_time_elapsed = model.time_elapsed;
         }
         self.timestamp = model.timestamp;
+        if let Some(compare) = self.compare.as_mut() {
+            compare.update(model.timestamp);
+        }
         self.model.replace(model.clone());
         self.system.replace(model.system);
         self.cgroup.replace(model.cgroup);
         self.process.replace(model.process);
         self.network.replace(model.network);
+        self.resctrl.replace(model.resctrl);
+        self.tc.replace(model.tc);
         #[cfg(fbcode_build)]
         self.gpu.replace(model.gpu);
+        self.oom_kills.replace(model.oom_kills);
     }
 
     pub fn new_with_advance(
@@ -289,13 +407,23 @@ impl ViewState {
             cgroup: Rc::new(RefCell::new(model.cgroup)),
             process: Rc::new(RefCell::new(model.process)),
             network: Rc::new(RefCell::new(model.network)),
+            resctrl: Rc::new(RefCell::new(model.resctrl)),
+            tc: Rc::new(RefCell::new(model.tc)),
             #[cfg(fbcode_build)]
             gpu: Rc::new(RefCell::new(model.gpu)),
+            oom_kills: Rc::new(RefCell::new(model.oom_kills)),
+            compare: None,
+            playback: None,
+            notifier: None,
+            snapshot: None,
             main_view_state,
             main_view_screens: HashMap::new(),
             mode,
             viewrc,
             viewrc_error,
+            refresh_interval_s: None,
+            refresh_interval_remote: false,
+            lite: None,
             event_controllers: Rc::new(RefCell::new(HashMap::new())),
             cmd_controllers: Rc::new(RefCell::new(controllers::make_cmd_controller_map())),
         }
@@ -305,13 +433,57 @@ impl ViewState {
         match self.mode {
             ViewMode::Live(_) => "live",
             ViewMode::Pause(_) => "live-paused",
+            ViewMode::CatchUp(_) => "live-catching-up",
             ViewMode::Replay(_) => "replay",
         }
     }
 
+    /// Refresh interval of live mode in seconds, if it can be changed
+    pub fn refresh_interval(&self) -> Option<u64> {
+        self.refresh_interval_s
+            .as_ref()
+            .map(|interval_s| interval_s.load(Ordering::Relaxed))
+    }
+
+    /// Change the refresh interval of live mode, independent of the interval
+    /// samples are recorded at. Intervals shorter than new samples are
+    /// available at are clamped. Returns the interval set.
+    pub fn set_refresh_interval(&mut self, interval_s: u64) -> Result<u64> {
+        let shared = match self.refresh_interval_s.as_ref() {
+            Some(shared) => shared,
+            None => anyhow::bail!("Refresh interval can only be changed in live mode"),
+        };
+        let min_interval_s = if self.refresh_interval_remote {
+            self.lowest_time_elapsed.as_secs().max(1)
+        } else {
+            1
+        };
+        let interval_s = interval_s.max(min_interval_s);
+        shared.store(interval_s, Ordering::Relaxed);
+        Ok(interval_s)
+    }
+
+    /// Step the refresh interval of live mode to the next shorter or longer
+    /// one in `REFRESH_INTERVALS_S`. Returns the interval set.
+    pub fn step_refresh_interval(&mut self, faster: bool) -> Result<u64> {
+        let current = self.refresh_interval().unwrap_or_default();
+        let next = if faster {
+            REFRESH_INTERVALS_S
+                .iter()
+                .rev()
+                .find(|interval_s| **interval_s < current)
+        } else {
+            REFRESH_INTERVALS_S
+                .iter()
+                .find(|interval_s| **interval_s > current)
+        };
+        self.set_refresh_interval(next.copied().unwrap_or(current))
+    }
+
+    /// Whether newly collected samples should be held back from the view
     pub fn is_paused(&self) -> bool {
         match self.mode {
-            ViewMode::Pause(_) => true,
+            ViewMode::Pause(_) | ViewMode::CatchUp(_) => true,
             _ => false,
         }
     }
@@ -319,15 +491,19 @@ impl ViewState {
 
 impl View {
     pub fn new_with_advance(model: model::Model, mode: ViewMode) -> View {
-        let mut inner = cursive::CursiveRunnable::new(|| {
-            let backend = cursive::backends::crossterm::Backend::init().map(|backend| {
-                Box::new(cursive_buffered_backend::BufferedBackend::new(backend))
-                    as Box<(dyn cursive::backend::Backend)>
-            });
-            execute!(std::io::stdout(), DisableMouseCapture).expect("Failed to disable mouse.");
-            backend
-        });
         let (viewrc, viewrc_error) = viewrc::ViewRc::new();
+        Self::new_with_viewrc(model, mode, viewrc, viewrc_error)
+    }
+
+    /// Same as `new_with_advance`, with the view configuration loaded by the
+    /// caller, e.g. to pick a profile first
+    pub fn new_with_viewrc(
+        model: model::Model,
+        mode: ViewMode,
+        viewrc: ViewRc,
+        viewrc_error: Option<String>,
+    ) -> View {
+        let mut inner = cursive::CursiveRunnable::new(init_backend);
         inner.set_user_data(ViewState::new_with_advance(
             MainViewState::Cgroup,
             model,
@@ -335,7 +511,119 @@ impl View {
             viewrc,
             viewrc_error,
         ));
-        View { inner }
+        View {
+            inner,
+            session: None,
+        }
+    }
+
+    /// Show the values at `offset` earlier next to the current ones, read
+    /// through `advance`, a second cursor into the replayed store. The offset
+    /// can be changed later with the compare command.
+    pub fn set_compare(&mut self, advance: Advance, offset: Option<Duration>) {
+        let view_state = self
+            .inner
+            .user_data::<ViewState>()
+            .expect("No data stored in Cursive object!");
+        let mut compare = compare::Compare::new(advance, offset);
+        compare.update(view_state.timestamp);
+        view_state.compare = Some(compare);
+    }
+
+    /// Start playing the replay back at `speed` times the recorded speed.
+    /// It can be paused or stopped later with the pause_resume and playback
+    /// commands.
+    pub fn set_playback(&mut self, speed: f64) {
+        let view_state = self
+            .inner
+            .user_data::<ViewState>()
+            .expect("No data stored in Cursive object!");
+        view_state.playback = Some(playback::Playback::new(speed, view_state.timestamp));
+    }
+
+    /// Let the snapshot command write the samples shown to a snapshot file
+    /// with `snapshot`, which is run on a thread of its own
+    pub fn set_snapshot(&mut self, snapshot: SnapshotFn) {
+        let view_state = self
+            .inner
+            .user_data::<ViewState>()
+            .expect("No data stored in Cursive object!");
+        view_state.snapshot = Some(snapshot);
+    }
+
+    /// Let profiles with a refresh interval and the refresh commands change
+    /// the interval of the live collector, which reads it before every
+    /// sample. With `remote`, samples come from a remote recorder and the
+    /// interval is kept at or above the interval it records at.
+    pub fn set_refresh_interval(&mut self, interval_s: Arc<AtomicU64>, remote: bool) {
+        let view_state = self
+            .inner
+            .user_data::<ViewState>()
+            .expect("No data stored in Cursive object!");
+        view_state.refresh_interval_s = Some(interval_s);
+        view_state.refresh_interval_remote = remote;
+    }
+
+    /// Show in the status bar that the live collector runs in lite mode and
+    /// collects what `label` describes
+    pub fn set_lite(&mut self, label: String) {
+        let view_state = self
+            .inner
+            .user_data::<ViewState>()
+            .expect("No data stored in Cursive object!");
+        view_state.lite = Some(label);
+    }
+
+    /// Record the input events and rendered frames of the session to `path`,
+    /// to be played back with `replay_session`
+    pub fn record_session(&mut self, path: &Path) -> Result<()> {
+        let view_state = self
+            .inner
+            .user_data::<ViewState>()
+            .expect("No data stored in Cursive object!");
+        let mode = match view_state.mode {
+            ViewMode::Live(_) | ViewMode::Pause(_) | ViewMode::CatchUp(_) => "live",
+            ViewMode::Replay(_) => "replay",
+        };
+        let timestamp = view_state
+            .timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        self.session = Some(session::SessionRecorder::new(path, mode, timestamp)?);
+        Ok(())
+    }
+
+    /// Unix timestamp of the sample a recorded session started at
+    pub fn session_timestamp(path: &Path) -> Result<u64> {
+        Ok(session::Session::read(path)?.timestamp())
+    }
+
+    /// Feed the events recorded in the session at `path` to the view without
+    /// a terminal, writing the frames rendered to `out`
+    pub fn replay_session(&mut self, path: &Path, out: Box<dyn Write>) -> Result<()> {
+        let session = session::Session::read(path)?;
+        self.setup();
+        self.inner.run_with(|| session.into_backend(out));
+        Ok(())
+    }
+
+    /// Watch the notification rules of belowrc in live mode. Invalid rules
+    /// are reported and none are watched.
+    fn set_notifier(c: &mut Cursive) {
+        let view_state = c
+            .user_data::<ViewState>()
+            .expect("No data stored in Cursive object!");
+        if !matches!(view_state.mode, ViewMode::Live(_)) {
+            return;
+        }
+        let rules = match view_state.viewrc.notify.as_ref() {
+            Some(rules) if !rules.is_empty() => rules,
+            _ => return,
+        };
+        match notify::Notifier::new(rules) {
+            Ok(notifier) => view_state.notifier = Some(notifier),
+            Err(e) => view_warn!(c, "Notifications disabled: {:#}", e),
+        }
     }
 
     pub fn cb_sink(&mut self) -> &::cursive::CbSink {
@@ -370,12 +658,28 @@ impl View {
     }
 
     pub fn run(&mut self) -> Result<()> {
+        self.setup();
+        panic_report::install();
+        let res = match self.session.take() {
+            Some(recorder) => self
+                .inner
+                .try_run_with(|| init_backend().map(|backend| recorder.wrap(backend))),
+            None => {
+                self.inner.run();
+                Ok(())
+            }
+        };
+        panic_report::uninstall();
+        res?;
+
+        Ok(())
+    }
+
+    fn setup(&mut self) {
         let mut theme = self.inner.current_theme().clone();
         theme.palette[PaletteColor::Background] = Color::TerminalDefault;
         theme.palette[PaletteColor::View] = Color::TerminalDefault;
         theme.palette[PaletteColor::Primary] = Color::TerminalDefault;
-        theme.palette[PaletteColor::Highlight] = Color::Dark(BaseColor::Cyan);
-        theme.palette[PaletteColor::HighlightText] = Color::Dark(BaseColor::Black);
         theme.shadow = false;
 
         self.inner.set_theme(theme);
@@ -408,6 +712,9 @@ impl View {
                 c.on_event(Event::WindowResize);
             });
         self.inner.add_global_callback(Event::Refresh, |c| {
+            resume_popup::catch_up_step(c);
+            playback::step(c);
+            notify::check(c);
             refresh(c);
         });
         self.inner.add_global_callback(Event::CtrlChar('r'), |c| {
@@ -467,35 +774,9 @@ impl View {
             .focus_name("dynamic_view")
             .expect("Could not set focus at initialization!");
 
-        // Set default view from viewrc
-        if let Some(view) = self
-            .inner
-            .user_data::<ViewState>()
-            .expect("No data stored in Cursive object!")
-            .viewrc
-            .default_view
-            .clone()
-        {
-            let main_view_state = &mut self
-                .inner
-                .user_data::<ViewState>()
-                .expect("No data stored in Cursive object!")
-                .main_view_state;
-            match view {
-                viewrc::DefaultFrontView::Cgroup => {
-                    *main_view_state = MainViewState::Cgroup;
-                    set_active_screen(&mut self.inner, "cgroup_view_panel")
-                }
-                viewrc::DefaultFrontView::Process => {
-                    *main_view_state = MainViewState::Process(ProcessZoomState::NoZoom);
-                    set_active_screen(&mut self.inner, "process_view_panel")
-                }
-                viewrc::DefaultFrontView::System => {
-                    *main_view_state = MainViewState::System;
-                    set_active_screen(&mut self.inner, "system_view_panel")
-                }
-            }
-        }
+        // Apply the view profile, starting on its default view. The refresh
+        // interval was already picked with the command line one.
+        apply_profile(&mut self.inner, false);
 
         // Raise warning message if failed to map the customized command.
         Self::generate_event_controller_map(&mut self.inner, get_belowrc_filename());
@@ -509,16 +790,23 @@ impl View {
             let c = &mut self.inner;
             view_warn!(c, "{}", msg);
         }
+        Self::set_notifier(&mut self.inner);
         if let Some(msg) = init_warnings {
             let c = &mut self.inner;
             view_warn!(c, "{}", msg);
         }
-        self.inner.run();
-
-        Ok(())
     }
 }
 
+fn init_backend() -> std::io::Result<Box<dyn cursive::backend::Backend>> {
+    let backend = cursive::backends::crossterm::Backend::init().map(|backend| {
+        Box::new(cursive_buffered_backend::BufferedBackend::new(backend))
+            as Box<(dyn cursive::backend::Backend)>
+    });
+    execute!(std::io::stdout(), DisableMouseCapture).expect("Failed to disable mouse.");
+    backend
+}
+
 #[cfg(test)]
 pub mod fake_view {
     use std::cell::RefCell;
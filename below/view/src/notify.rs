@@ -0,0 +1,230 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Error;
+use anyhow::Result;
+use common::logutil::get_logger;
+use crossterm::execute;
+use crossterm::style::Print;
+use cursive::Cursive;
+use model::Field;
+use model::Model;
+use model::ModelFieldId;
+use model::Queriable;
+use slog::warn;
+
+use crate::viewrc::NotifyRule;
+use crate::ViewMode;
+use crate::ViewState;
+
+/// How long the status bar flashes after a rule fires
+const FLASH_DURATION: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+}
+
+impl Op {
+    // Longer operators first so that ">=" is not taken for ">"
+    const ALL: [(&'static str, Op); 6] = [
+        (">=", Op::Ge),
+        ("<=", Op::Le),
+        ("==", Op::Eq),
+        ("!=", Op::Ne),
+        (">", Op::Gt),
+        ("<", Op::Lt),
+    ];
+
+    fn apply(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Op::Gt => value > threshold,
+            Op::Ge => value >= threshold,
+            Op::Lt => value < threshold,
+            Op::Le => value <= threshold,
+            Op::Eq => value == threshold,
+            Op::Ne => value != threshold,
+        }
+    }
+}
+
+/// Condition on a numeric model field, e.g.
+/// "system.cpu.usage_pct > 90" or
+/// "cgroup.path:/system.slice/.mem.total_bytes >= 1073741824".
+#[derive(Clone, Debug)]
+pub struct Rule {
+    field_id: ModelFieldId,
+    op: Op,
+    threshold: f64,
+}
+
+impl FromStr for Rule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (pos, op_str, op) = Op::ALL
+            .iter()
+            .filter_map(|(op_str, op)| s.find(op_str).map(|pos| (pos, *op_str, *op)))
+            .min_by_key(|(pos, _, _)| *pos)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Missing comparison in rule \"{}\", expected e.g. \"system.cpu.usage_pct > 90\"",
+                    s
+                )
+            })?;
+        let field = s[..pos].trim();
+        let threshold = s[pos + op_str.len()..].trim();
+        if field.is_empty() {
+            bail!("Missing field id in rule \"{}\"", s);
+        }
+        Ok(Rule {
+            field_id: ModelFieldId::from_str(field)
+                .map_err(|e| anyhow!("Invalid field id in rule \"{}\": {}", s, e))?,
+            op,
+            threshold: threshold
+                .parse()
+                .with_context(|| format!("Invalid threshold in rule \"{}\"", s))?,
+        })
+    }
+}
+
+impl Rule {
+    /// Whether the rule holds for `model`. Missing and non-numeric values
+    /// never match.
+    pub fn matches(&self, model: &Model) -> bool {
+        let value = match model.query(&self.field_id) {
+            Some(
+                field @ (Field::U32(_)
+                | Field::U64(_)
+                | Field::I32(_)
+                | Field::I64(_)
+                | Field::F32(_)
+                | Field::F64(_)),
+            ) => f64::from(field),
+            _ => return false,
+        };
+        self.op.apply(value, self.threshold)
+    }
+}
+
+struct Watch {
+    name: String,
+    rule: Rule,
+    bell: bool,
+    firing: bool,
+    fired_at: Option<Instant>,
+}
+
+/// Watches the rules of the [[view.notify]] sections of belowrc in live
+/// mode. A rule fires when it starts to hold, which flashes the status bar,
+/// optionally rings the terminal bell and is logged. It fires again only
+/// after it stopped holding for a sample.
+pub struct Notifier {
+    watches: Vec<Watch>,
+    /// Timestamp of the last sample checked
+    checked: Option<SystemTime>,
+}
+
+impl Notifier {
+    pub fn new(rules: &[NotifyRule]) -> Result<Self> {
+        let watches = rules
+            .iter()
+            .map(|rule| {
+                Ok(Watch {
+                    name: rule.name.clone().unwrap_or_else(|| rule.rule.clone()),
+                    rule: rule.rule.parse()?,
+                    bell: rule.bell.unwrap_or(false),
+                    firing: false,
+                    fired_at: None,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            watches,
+            checked: None,
+        })
+    }
+
+    /// Check the rules against a new sample. Returns the watches that fired.
+    fn observe(&mut self, model: &Model, now: Instant) -> Vec<&Watch> {
+        if self.checked == Some(model.timestamp) {
+            return vec![];
+        }
+        self.checked = Some(model.timestamp);
+        self.watches
+            .iter_mut()
+            .filter_map(|watch| {
+                let was_firing = watch.firing;
+                watch.firing = watch.rule.matches(model);
+                if watch.firing && !was_firing {
+                    watch.fired_at = Some(now);
+                    Some(&*watch)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Names of the rules that currently hold, each with whether it should
+    /// be highlighted at `now`. Rules that fired recently blink.
+    pub fn firing(&self, now: Instant) -> Vec<(&str, bool)> {
+        self.watches
+            .iter()
+            .filter(|watch| watch.firing)
+            .map(|watch| {
+                let since = watch.fired_at.map_or(FLASH_DURATION, |fired_at| {
+                    now.saturating_duration_since(fired_at)
+                });
+                let highlight = since >= FLASH_DURATION || since.as_millis() / 500 % 2 == 0;
+                (watch.name.as_str(), highlight)
+            })
+            .collect()
+    }
+}
+
+/// Check the notification rules against the displayed sample in live mode
+pub fn check(c: &mut Cursive) {
+    let view_state = c.user_data::<ViewState>().expect("user data not set");
+    if !matches!(view_state.mode, ViewMode::Live(_)) {
+        return;
+    }
+    let notifier = match view_state.notifier.as_mut() {
+        Some(notifier) => notifier,
+        None => return,
+    };
+    let model = view_state.model.borrow();
+    let mut bell = false;
+    for watch in notifier.observe(&model, Instant::now()) {
+        warn!(get_logger(), "Notification \"{}\" fired", watch.name);
+        bell |= watch.bell;
+    }
+    if bell {
+        // Cursive has no bell, write it to the terminal directly
+        execute!(std::io::stdout(), Print('\x07')).ok();
+    }
+}
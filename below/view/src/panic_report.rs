@@ -0,0 +1,131 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Panic hook of the view. A panic while the view runs would otherwise leave
+//! the terminal in raw mode on the alternate screen, and the panic message
+//! printed to it is lost as soon as anything else is drawn.
+
+use std::fmt::Display;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::thread;
+use std::thread::ThreadId;
+use std::time::SystemTime;
+
+use chrono::DateTime;
+use chrono::Local;
+use crossterm::cursor::Show;
+use crossterm::event::DisableMouseCapture;
+use crossterm::execute;
+use crossterm::terminal::LeaveAlternateScreen;
+use cursive::Cursive;
+use once_cell::sync::OnceCell;
+
+use crate::ViewState;
+
+static REPORT_DIR: OnceCell<PathBuf> = OnceCell::new();
+/// The thread running the view while it runs
+static VIEW_THREAD: Mutex<Option<ThreadId>> = Mutex::new(None);
+static CONTEXT: Mutex<Option<String>> = Mutex::new(None);
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Write panic reports to `dir`, the temp dir by default. Only the first call
+/// has an effect.
+pub fn set_panic_report_dir(dir: PathBuf) {
+    let _ = REPORT_DIR.set(dir);
+}
+
+/// Install the panic hook for the view run by the current thread. The hook
+/// installed before keeps being called after the report is written.
+pub(crate) fn install() {
+    *VIEW_THREAD.lock().unwrap() = Some(thread::current().id());
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        report(info);
+        previous(info);
+    }));
+}
+
+/// Stop handling panics as the view stopped running
+pub(crate) fn uninstall() {
+    *VIEW_THREAD.lock().unwrap() = None;
+}
+
+/// Remember what the view shows for the report of a later panic
+pub(crate) fn update_context(c: &mut Cursive) {
+    let view_state = c
+        .user_data::<ViewState>()
+        .expect("No data stored in Cursive object!");
+    let context = format!(
+        "mode: {}\ntimestamp: {}\nview: {:?}\n",
+        view_state.view_mode_str(),
+        DateTime::<Local>::from(view_state.timestamp).format("%m/%d/%Y %H:%M:%S UTC%:z"),
+        view_state.main_view_state,
+    );
+    *CONTEXT.lock().unwrap() = Some(context);
+}
+
+fn report(info: &dyn Display) {
+    // Only the view thread owns the terminal, panics of other threads are
+    // left to the previous hook
+    let is_view_thread = VIEW_THREAD.try_lock().map_or(false, |view_thread| {
+        *view_thread == Some(thread::current().id())
+    });
+    if !is_view_thread || REPORTED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    let _ = execute!(
+        std::io::stdout(),
+        DisableMouseCapture,
+        LeaveAlternateScreen,
+        Show
+    );
+    let _ = crossterm::terminal::disable_raw_mode();
+
+    let context = CONTEXT
+        .try_lock()
+        .ok()
+        .and_then(|context| context.clone())
+        .unwrap_or_default();
+    let report = format!(
+        "below {} panicked: {}\n{}",
+        crate::get_version_str(),
+        info,
+        context
+    );
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs());
+    let path = REPORT_DIR
+        .get()
+        .cloned()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(format!("view_panic_{}.log", now));
+    match fs::write(&path, report) {
+        Ok(()) => eprintln!(
+            "below crashed, please attach {} to a bug report",
+            path.display()
+        ),
+        Err(e) => eprintln!(
+            "below crashed, failed to write the report to {}: {}\n{}",
+            path.display(),
+            e,
+            context
+        ),
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Instant;
+use std::time::SystemTime;
+
+use anyhow::bail;
+use anyhow::Result;
+use cursive::Cursive;
+use store::Direction;
+
+use crate::ViewMode;
+use crate::ViewState;
+
+/// Speed used when playback is started without one, an hour per minute
+pub const DEFAULT_PLAYBACK_SPEED: f64 = 60.0;
+/// Most samples stepped through on one refresh, so that a high speed over a
+/// dense store does not stall the view. Refreshes happen 4 times a second.
+const MAX_SAMPLES_PER_REFRESH: usize = 32;
+
+/// Parse a playback speed, e.g. "60" or "60x"
+pub fn parse_speed(speed: &str) -> Result<f64> {
+    match speed.trim_end_matches('x').parse::<f64>() {
+        Ok(speed) if speed.is_finite() && speed > 0.0 => Ok(speed),
+        _ => bail!("Invalid playback speed \"{}\", expected e.g. 60x", speed),
+    }
+}
+
+/// Time-lapse of a replay: steps through the samples automatically at a
+/// multiple of the speed they were recorded at, e.g. 60x plays an hour of
+/// samples in a minute.
+pub struct Playback {
+    speed: f64,
+    paused: bool,
+    /// Recorded time playback reached. Samples up to it are shown.
+    playhead: SystemTime,
+    /// Timestamp of the sample playback last showed. If the displayed
+    /// sample differs, the replay was moved manually, e.g. with jump or
+    /// next_sample, and playback continues from there.
+    shown: SystemTime,
+    last_step: Instant,
+}
+
+impl Playback {
+    pub fn new(speed: f64, timestamp: SystemTime) -> Self {
+        Self {
+            speed,
+            paused: false,
+            playhead: timestamp,
+            shown: timestamp,
+            last_step: Instant::now(),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed;
+        self.paused = false;
+        self.playhead = self.shown;
+        self.last_step = Instant::now();
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.playhead = self.shown;
+        self.last_step = Instant::now();
+    }
+
+    /// Status bar label, e.g. "60x" or "60x paused"
+    pub fn label(&self) -> String {
+        format!(
+            "{}x{}",
+            self.speed,
+            if self.paused { " paused" } else { "" }
+        )
+    }
+
+    /// Move the playhead by the time passed since the last call, scaled by
+    /// the speed, from the displayed sample at `timestamp`. Returns the new
+    /// playhead, or None when paused.
+    pub fn advance_playhead(&mut self, timestamp: SystemTime, now: Instant) -> Option<SystemTime> {
+        let elapsed = now.saturating_duration_since(self.last_step);
+        self.last_step = now;
+        if self.paused {
+            return None;
+        }
+        if timestamp != self.shown {
+            self.playhead = timestamp;
+        }
+        self.playhead += elapsed.mul_f64(self.speed);
+        Some(self.playhead)
+    }
+}
+
+/// Step the replay forward to the playhead if playing. Pauses playback at
+/// the end of the recorded samples.
+pub fn step(c: &mut Cursive) {
+    let view_state = c.user_data::<ViewState>().expect("user data not set");
+    let adv = match &view_state.mode {
+        ViewMode::Replay(adv) => adv.clone(),
+        _ => return,
+    };
+    let mut playback = match view_state.playback.take() {
+        Some(playback) => playback,
+        None => return,
+    };
+    let mut reached_end = false;
+    if let Some(playhead) = playback.advance_playhead(view_state.timestamp, Instant::now()) {
+        let mut adv = adv.borrow_mut();
+        let mut stepped = 0;
+        while view_state.timestamp < playhead {
+            if stepped == MAX_SAMPLES_PER_REFRESH {
+                // Too far behind, play on from here rather than skip ahead
+                playback.playhead = view_state.timestamp;
+                break;
+            }
+            match adv.advance(Direction::Forward) {
+                Some(model) => view_state.update(model),
+                None => {
+                    reached_end = true;
+                    playback.paused = true;
+                    break;
+                }
+            }
+            stepped += 1;
+        }
+        playback.shown = view_state.timestamp;
+    }
+    view_state.playback = Some(playback);
+    if reached_end {
+        view_warn!(c, "Playback reached the end of the recorded samples.");
+    }
+}
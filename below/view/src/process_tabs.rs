@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::str::FromStr;
+
 use cursive::utils::markup::StyledString;
 use itertools::Itertools;
 use model::Queriable;
@@ -37,6 +39,19 @@ impl ProcessTab {
         Self { view_items }
     }
 
+    /// Tab of the given process field ids, e.g. "cpu.usage_pct"
+    pub fn from_field_ids(field_ids: &[String]) -> Result<Self, String> {
+        field_ids
+            .iter()
+            .map(|field_id| {
+                model::SingleProcessModelFieldId::from_str(field_id)
+                    .map(ViewItem::from_default)
+                    .map_err(|e| format!("Invalid process column {}: {:#}", field_id, e))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self::new)
+    }
+
     fn get_process_field_line(
         &self,
         model: &SingleProcessModel,
@@ -141,32 +156,46 @@ impl ProcessTab {
 pub mod default_tabs {
     use model::ProcessCpuModelFieldId::NumThreads;
     use model::ProcessCpuModelFieldId::SystemPct;
+    use model::ProcessCpuModelFieldId::TotalUsecs;
     use model::ProcessCpuModelFieldId::UsagePct;
     use model::ProcessCpuModelFieldId::UserPct;
     use model::ProcessIoModelFieldId::RbytesPerSec;
     use model::ProcessIoModelFieldId::RwbytesPerSec;
     use model::ProcessIoModelFieldId::WbytesPerSec;
     use model::ProcessMemoryModelFieldId::Anon;
+    use model::ProcessMemoryModelFieldId::AnonThp;
     use model::ProcessMemoryModelFieldId::File;
     use model::ProcessMemoryModelFieldId::HugeTlb;
     use model::ProcessMemoryModelFieldId::Lock;
     use model::ProcessMemoryModelFieldId::MajorfaultsPerSec;
     use model::ProcessMemoryModelFieldId::MinorfaultsPerSec;
     use model::ProcessMemoryModelFieldId::Pin;
+    use model::ProcessMemoryModelFieldId::Private;
+    use model::ProcessMemoryModelFieldId::Pss;
     use model::ProcessMemoryModelFieldId::Pte;
     use model::ProcessMemoryModelFieldId::RssBytes;
+    use model::ProcessMemoryModelFieldId::Shared;
     use model::ProcessMemoryModelFieldId::Shmem;
     use model::ProcessMemoryModelFieldId::Swap;
+    use model::ProcessMemoryModelFieldId::SwapGrowthPerSec;
+    use model::ProcessMemoryModelFieldId::SwapPss;
     use model::ProcessMemoryModelFieldId::VmSize;
+    use model::ProcessNetModelFieldId::RxBytesPerSec;
+    use model::ProcessNetModelFieldId::TxBytesPerSec;
+    use model::ProcessStackModelFieldId::TopFrame;
+    use model::ProcessStackModelFieldId::TopFramePct;
     use model::SingleProcessModelFieldId::Cgroup;
     use model::SingleProcessModelFieldId::Cmdline;
     use model::SingleProcessModelFieldId::Comm;
     use model::SingleProcessModelFieldId::Cpu;
     use model::SingleProcessModelFieldId::Io;
     use model::SingleProcessModelFieldId::Mem;
+    use model::SingleProcessModelFieldId::Net;
     use model::SingleProcessModelFieldId::NsTgid;
     use model::SingleProcessModelFieldId::Pid;
     use model::SingleProcessModelFieldId::Ppid;
+    use model::SingleProcessModelFieldId::Stack;
+    use model::SingleProcessModelFieldId::StartTimeEpochSecs;
     use model::SingleProcessModelFieldId::State;
     use model::SingleProcessModelFieldId::UptimeSecs;
     use once_cell::sync::Lazy;
@@ -192,6 +221,8 @@ pub mod default_tabs {
             ViewItem::from_default(Io(RbytesPerSec)),
             ViewItem::from_default(Io(WbytesPerSec)),
             ViewItem::from_default(UptimeSecs),
+            ViewItem::from_default(StartTimeEpochSecs),
+            ViewItem::from_default(Cpu(TotalUsecs)),
             ViewItem::from_default(Cpu(NumThreads)),
             ViewItem::from_default(Io(RwbytesPerSec)),
             ViewItem::from_default(Cmdline),
@@ -204,14 +235,20 @@ pub mod default_tabs {
             ViewItem::from_default(Cpu(SystemPct)),
             ViewItem::from_default(Cpu(NumThreads)),
             ViewItem::from_default(Cpu(UsagePct)),
+            // Only set with enable_stack_sampling, in samples with stacks
+            ViewItem::from_default(Stack(TopFrame)),
+            ViewItem::from_default(Stack(TopFramePct)),
         ])
     });
 
     pub static PROCESS_MEM_TAB: Lazy<ProcessTab> = Lazy::new(|| {
         ProcessTab::new(vec![
             ViewItem::from_default(Mem(RssBytes)),
-            ViewItem::from_default(Mem(VmSize)),
             ViewItem::from_default(Mem(Swap)),
+            ViewItem::from_default(Mem(SwapGrowthPerSec)),
+            ViewItem::from_default(Mem(MinorfaultsPerSec)),
+            ViewItem::from_default(Mem(MajorfaultsPerSec)),
+            ViewItem::from_default(Mem(VmSize)),
             ViewItem::from_default(Mem(Anon)),
             ViewItem::from_default(Mem(File)),
             ViewItem::from_default(Mem(Shmem)),
@@ -219,8 +256,12 @@ pub mod default_tabs {
             ViewItem::from_default(Mem(Lock)),
             ViewItem::from_default(Mem(Pin)),
             ViewItem::from_default(Mem(HugeTlb)),
-            ViewItem::from_default(Mem(MinorfaultsPerSec)),
-            ViewItem::from_default(Mem(MajorfaultsPerSec)),
+            // Only set with enable_smaps_rollup_stats
+            ViewItem::from_default(Mem(Pss)),
+            ViewItem::from_default(Mem(Shared)),
+            ViewItem::from_default(Mem(Private)),
+            ViewItem::from_default(Mem(AnonThp)),
+            ViewItem::from_default(Mem(SwapPss)),
         ])
     });
 
@@ -231,4 +272,12 @@ pub mod default_tabs {
             ViewItem::from_default(Io(RwbytesPerSec)),
         ])
     });
+
+    // Only set with enable_process_net_stats
+    pub static PROCESS_NET_TAB: Lazy<ProcessTab> = Lazy::new(|| {
+        ProcessTab::new(vec![
+            ViewItem::from_default(Net(RxBytesPerSec)),
+            ViewItem::from_default(Net(TxBytesPerSec)),
+        ])
+    });
 }
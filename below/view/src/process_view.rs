@@ -35,6 +35,8 @@ use crate::process_tabs::default_tabs::PROCESS_CPU_TAB;
 use crate::process_tabs::default_tabs::PROCESS_GENERAL_TAB;
 use crate::process_tabs::default_tabs::PROCESS_IO_TAB;
 use crate::process_tabs::default_tabs::PROCESS_MEM_TAB;
+use crate::process_tabs::default_tabs::PROCESS_NET_TAB;
+use crate::inspect_popup;
 use crate::process_tabs::ProcessTab;
 use crate::stats_view::ColumnTitles;
 use crate::stats_view::StateCommon;
@@ -54,7 +56,7 @@ pub struct ProcessState {
     pub filter_cache_for_zoom: Option<(SingleProcessModelFieldId, String)>,
     pub current_selected_pid: Option<i32>,
     pub sort_order: Option<SingleProcessModelFieldId>,
-    pub sort_tags: HashMap<String, &'static ProcessTab>,
+    pub sort_tags: HashMap<String, ProcessTab>,
     pub reverse: bool,
     pub fold: bool,
     pub model: Rc<RefCell<ProcessModel>>,
@@ -134,6 +136,12 @@ impl StateCommon for ProcessState {
         }
     }
 
+    fn sort_strings() -> Vec<String> {
+        enum_iterator::all::<Self::TagType>()
+            .map(|field_id| field_id.to_string())
+            .collect()
+    }
+
     fn get_model(&self) -> Ref<Self::ModelType> {
         self.model.borrow()
     }
@@ -144,10 +152,11 @@ impl StateCommon for ProcessState {
 
     fn new(model: Rc<RefCell<Self::ModelType>>) -> Self {
         let mut sort_tags = HashMap::new();
-        sort_tags.insert("General".into(), &*PROCESS_GENERAL_TAB);
-        sort_tags.insert("CPU".into(), &*PROCESS_CPU_TAB);
-        sort_tags.insert("Mem".into(), &*PROCESS_MEM_TAB);
-        sort_tags.insert("I/O".into(), &*PROCESS_IO_TAB);
+        sort_tags.insert("General".into(), PROCESS_GENERAL_TAB.clone());
+        sort_tags.insert("CPU".into(), PROCESS_CPU_TAB.clone());
+        sort_tags.insert("Mem".into(), PROCESS_MEM_TAB.clone());
+        sort_tags.insert("I/O".into(), PROCESS_IO_TAB.clone());
+        sort_tags.insert("Net".into(), PROCESS_NET_TAB.clone());
         Self {
             filter_info: None,
             cgroup_filter: None,
@@ -190,7 +199,6 @@ impl ProcessState {
         self.pids_filter = None;
     }
 
-    #[allow(dead_code)]
     pub fn handle_state_for_entering_pids_zoom(&mut self, current_selection: Vec<i32>) {
         self.pids_filter = Some(current_selection);
         std::mem::swap(&mut self.filter_cache_for_zoom, &mut self.filter_info);
@@ -207,36 +215,48 @@ impl ProcessState {
 }
 
 pub struct ProcessView {
-    tab: &'static ProcessTab,
+    tab: ProcessTab,
 }
 
 impl ProcessView {
     pub fn new(c: &mut Cursive) -> NamedView<ViewType> {
         let list = SelectView::<i32>::new();
-        let tabs = vec!["General".into(), "CPU".into(), "Mem".into(), "I/O".into()];
+        let tabs = vec![
+            "General".into(),
+            "CPU".into(),
+            "Mem".into(),
+            "I/O".into(),
+            "Net".into(),
+        ];
         let mut tabs_map: HashMap<String, ProcessView> = HashMap::new();
         tabs_map.insert(
             "General".into(),
             Self {
-                tab: &*PROCESS_GENERAL_TAB,
+                tab: PROCESS_GENERAL_TAB.clone(),
             },
         );
         tabs_map.insert(
             "CPU".into(),
             Self {
-                tab: &*PROCESS_CPU_TAB,
+                tab: PROCESS_CPU_TAB.clone(),
             },
         );
         tabs_map.insert(
             "Mem".into(),
             Self {
-                tab: &*PROCESS_MEM_TAB,
+                tab: PROCESS_MEM_TAB.clone(),
             },
         );
         tabs_map.insert(
             "I/O".into(),
             Self {
-                tab: &*PROCESS_IO_TAB,
+                tab: PROCESS_IO_TAB.clone(),
+            },
+        );
+        tabs_map.insert(
+            "Net".into(),
+            Self {
+                tab: PROCESS_NET_TAB.clone(),
             },
         );
         let user_data = c
@@ -298,6 +318,16 @@ impl ProcessView {
             view.state.borrow_mut().set_reverse(true);
             view.refresh(c)
         })
+        .on_event('W', |c| {
+            let mut view = Self::get_process_view(c);
+            view.state
+                .borrow_mut()
+                .set_sort_order(SingleProcessModelFieldId::Mem(
+                    ProcessMemoryModelFieldId::Swap,
+                ));
+            view.state.borrow_mut().set_reverse(true);
+            view.refresh(c)
+        })
         .with_name(Self::get_view_name())
     }
 
@@ -309,6 +339,23 @@ impl ProcessView {
         let mut view = Self::get_process_view(c);
         view.refresh(c);
     }
+
+    /// Show `columns`, given as field ids, in the General tab instead of the
+    /// default ones
+    pub fn set_general_columns(c: &mut Cursive, columns: Option<&[String]>) -> Result<(), String> {
+        let (tab, res) = match columns.map(ProcessTab::from_field_ids) {
+            Some(Ok(tab)) => (tab, Ok(())),
+            Some(Err(e)) => (PROCESS_GENERAL_TAB.clone(), Err(e)),
+            None => (PROCESS_GENERAL_TAB.clone(), Ok(())),
+        };
+        let mut view = Self::get_process_view(c);
+        view.state
+            .borrow_mut()
+            .sort_tags
+            .insert("General".into(), tab.clone());
+        view.set_tab_view("General", Self { tab });
+        res
+    }
 }
 
 impl ViewBridge for ProcessView {
@@ -351,4 +398,16 @@ impl ViewBridge for ProcessView {
             .map_or("?".to_string(), |field| field.to_string());
         format!(" {} : {} ", tag.to_string(), field_str)
     }
+
+    fn inspect(
+        state: &Self::StateType,
+        selected_key: &i32,
+        _current_tab: &str,
+    ) -> Option<(String, String)> {
+        state
+            .get_model()
+            .processes
+            .get(selected_key /* pid */)
+            .map(|spm| (format!("pid {}", selected_key), inspect_popup::to_json(spm)))
+    }
 }
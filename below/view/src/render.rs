@@ -32,6 +32,23 @@ pub enum ViewStyle {
 pub const PRESSURE_HIGHLIGHT: ViewStyle = ViewStyle::HighlightAbove(Field::F64(40.0));
 pub const CPU_HIGHLIGHT: ViewStyle = ViewStyle::HighlightAbove(Field::F64(100.0));
 pub const MEM_HIGHLIGHT: ViewStyle = ViewStyle::HighlightBelow(Field::U64(2 << 20));
+pub const OOM_KILL_HIGHLIGHT: ViewStyle = ViewStyle::HighlightAbove(Field::U64(0));
+pub const LINK_FLAP_HIGHLIGHT: ViewStyle = ViewStyle::HighlightAbove(Field::U64(0));
+pub const THROTTLE_HIGHLIGHT: ViewStyle = ViewStyle::HighlightAbove(Field::U64(0));
+pub const CORRUPTION_HIGHLIGHT: ViewStyle = ViewStyle::HighlightAbove(Field::U64(0));
+pub const DSTATE_HIGHLIGHT: ViewStyle = ViewStyle::HighlightAbove(Field::U64(0));
+pub const HUNG_TASK_HIGHLIGHT: ViewStyle = ViewStyle::HighlightAbove(Field::U64(0));
+// Sustained creation or removal of a child cgroup a second keeps the kernel
+// busy setting up and tearing down cgroups
+pub const CGROUP_CHURN_HIGHLIGHT: ViewStyle = ViewStyle::HighlightAbove(Field::F64(1.0));
+
+pub fn is_cpu_significant(v: f64) -> Option<cursive::theme::BaseColor> {
+    if v > 100.0 {
+        Some(cursive::theme::BaseColor::Red)
+    } else {
+        None
+    }
+}
 
 #[derive(Clone, Default)]
 pub struct ViewConfig {
@@ -55,20 +72,14 @@ impl ViewConfig {
             Some(view_style) => match view_style {
                 ViewStyle::HighlightAbove(threshold) => {
                     if field.as_ref().map_or(false, |field| field > threshold) {
-                        StyledString::styled(
-                            rendered,
-                            cursive::theme::Color::Light(cursive::theme::BaseColor::Red),
-                        )
+                        StyledString::styled(rendered, crate::theme::current().critical)
                     } else {
                         StyledString::plain(rendered)
                     }
                 }
                 ViewStyle::HighlightBelow(threshold) => {
                     if field.as_ref().map_or(false, |field| field < threshold) {
-                        StyledString::styled(
-                            rendered,
-                            cursive::theme::Color::Light(cursive::theme::BaseColor::Red),
-                        )
+                        StyledString::styled(rendered, crate::theme::current().critical)
                     } else {
                         StyledString::plain(rendered)
                     }
@@ -0,0 +1,103 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use chrono::DateTime;
+use chrono::Local;
+use cursive::event::Key;
+use cursive::view::View;
+use cursive::views::Dialog;
+use cursive::views::OnEventView;
+use cursive::Cursive;
+use store::Advance;
+
+use crate::ViewMode;
+use crate::ViewState;
+
+/// Number of skipped samples stepped through on each refresh while
+/// catching up. Refreshes happen 4 times a second in live mode.
+const CATCH_UP_SAMPLES_PER_REFRESH: usize = 8;
+
+/// Resume live mode, either by stepping through the samples skipped while
+/// paused or by jumping straight to the latest sample.
+pub fn resume(c: &mut Cursive, adv: Rc<RefCell<Advance>>, catch_up: bool) {
+    let view_state = c.user_data::<ViewState>().expect("user data not set");
+    if catch_up {
+        let model = adv.borrow_mut().start_catch_up();
+        // Fall back to jumping to now if the skipped samples are gone
+        if let Some(model) = model {
+            view_state.update(model);
+            view_state.mode = ViewMode::CatchUp(adv);
+            return;
+        }
+    }
+
+    let mut advance = adv.borrow_mut();
+    advance.clear_skipped();
+    advance.get_latest_sample();
+    drop(advance);
+    view_state.mode = ViewMode::Live(adv);
+}
+
+/// Step through the next batch of skipped samples if catching up. Switches
+/// to live mode once the latest sample is reached.
+pub fn catch_up_step(c: &mut Cursive) {
+    let view_state = c.user_data::<ViewState>().expect("user data not set");
+    if let ViewMode::CatchUp(adv) = view_state.mode.clone() {
+        let (model, done) = adv.borrow_mut().catch_up(CATCH_UP_SAMPLES_PER_REFRESH);
+        if let Some(model) = model {
+            view_state.update(model);
+        }
+        if done {
+            view_state.mode = ViewMode::Live(adv);
+        }
+    }
+}
+
+pub fn new(adv: Rc<RefCell<Advance>>) -> impl View {
+    let skipped_since = adv
+        .borrow()
+        .skipped_since()
+        .map(|timestamp| {
+            DateTime::<Local>::from(timestamp)
+                .format("%m/%d/%Y %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| "?".to_owned());
+    let catch_up_adv = adv.clone();
+    OnEventView::new(
+        Dialog::text(format!(
+            "Samples since {} were skipped while paused.",
+            skipped_since
+        ))
+        .title("Resume live mode")
+        .padding_lrtb(1, 1, 1, 0)
+        .button("Catch up", move |c| {
+            c.pop_layer();
+            resume(c, catch_up_adv.clone(), true);
+            crate::refresh(c);
+        })
+        .button("Jump to now", move |c| {
+            c.pop_layer();
+            resume(c, adv.clone(), false);
+            crate::refresh(c);
+        })
+        .dismiss_button("Cancel"),
+    )
+    .on_event(Key::Esc, |s| {
+        s.pop_layer();
+    })
+}
@@ -0,0 +1,54 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use cursive::event::Key;
+use cursive::view::Nameable;
+use cursive::view::View;
+use cursive::views::Dialog;
+use cursive::views::EditView;
+use cursive::views::OnEventView;
+use cursive::Cursive;
+
+/// Popup to enter the text to search the rows for, prefilled with `current`.
+/// `search` is called with the entered text, which is empty to clear the
+/// search.
+pub fn new<F>(current: String, search: F) -> impl View
+where
+    F: 'static + Copy + Fn(&mut Cursive, String),
+{
+    let mut editview = EditView::new()
+        // Run cb and close popup when user presses "Enter"
+        .on_submit(move |c, text| {
+            c.pop_layer();
+            search(c, text.to_owned());
+        });
+    editview.set_content(current);
+
+    OnEventView::new(
+        Dialog::new()
+            .title("Search")
+            .padding_lrtb(1, 1, 1, 0)
+            .content(editview.with_name("search_popup"))
+            .dismiss_button("Close")
+            .button("Search", move |c| {
+                let text = c
+                    .call_on_name("search_popup", |view: &mut EditView| view.get_content())
+                    .expect("Unable to find search_popup");
+                c.pop_layer();
+                search(c, text.to_string());
+            }),
+    )
+    .on_event(Key::Esc, |s| {
+        s.pop_layer();
+    })
+}
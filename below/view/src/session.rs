@@ -0,0 +1,488 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recording of view sessions for bug reports.
+//!
+//! A session file holds one JSON object per line: a header with the
+//! terminal size and the sample the view started at, followed by the input
+//! events and the frames rendered, as plain text, whenever a frame differs
+//! from the previous one. `below debug view-replay` feeds the events of a
+//! session to a view without a terminal and prints the frames it renders.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use cursive::backend::Backend;
+use cursive::event::Event;
+use cursive::event::Key;
+use cursive::theme;
+use cursive::Vec2;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Keys that can be recorded. Others, e.g. mouse events, are left out.
+const KEYS: [Key; 29] = [
+    Key::Enter,
+    Key::Tab,
+    Key::Backspace,
+    Key::Esc,
+    Key::Left,
+    Key::Right,
+    Key::Up,
+    Key::Down,
+    Key::Ins,
+    Key::Del,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+    Key::PageDown,
+    Key::PauseBreak,
+    Key::NumpadCenter,
+    Key::F0,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+];
+
+/// An input event, as cursive events cannot be serialized. Keys are stored
+/// by name, e.g. "PageDown".
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEvent {
+    Char(char),
+    CtrlChar(char),
+    AltChar(char),
+    Key(String),
+    Shift(String),
+    Alt(String),
+    AltShift(String),
+    Ctrl(String),
+    CtrlShift(String),
+    CtrlAlt(String),
+    WindowResize,
+}
+
+fn key_name(key: Key) -> Option<String> {
+    KEYS.contains(&key).then(|| format!("{:?}", key))
+}
+
+fn key_from_name(name: &str) -> Option<Key> {
+    KEYS.iter()
+        .copied()
+        .find(|key| format!("{:?}", key) == name)
+}
+
+impl SessionEvent {
+    pub fn new(event: &Event) -> Option<Self> {
+        Some(match *event {
+            Event::Char(c) => Self::Char(c),
+            Event::CtrlChar(c) => Self::CtrlChar(c),
+            Event::AltChar(c) => Self::AltChar(c),
+            Event::Key(key) => Self::Key(key_name(key)?),
+            Event::Shift(key) => Self::Shift(key_name(key)?),
+            Event::Alt(key) => Self::Alt(key_name(key)?),
+            Event::AltShift(key) => Self::AltShift(key_name(key)?),
+            Event::Ctrl(key) => Self::Ctrl(key_name(key)?),
+            Event::CtrlShift(key) => Self::CtrlShift(key_name(key)?),
+            Event::CtrlAlt(key) => Self::CtrlAlt(key_name(key)?),
+            Event::WindowResize => Self::WindowResize,
+            _ => return None,
+        })
+    }
+
+    pub fn to_event(&self) -> Option<Event> {
+        Some(match self {
+            Self::Char(c) => Event::Char(*c),
+            Self::CtrlChar(c) => Event::CtrlChar(*c),
+            Self::AltChar(c) => Event::AltChar(*c),
+            Self::Key(name) => Event::Key(key_from_name(name)?),
+            Self::Shift(name) => Event::Shift(key_from_name(name)?),
+            Self::Alt(name) => Event::Alt(key_from_name(name)?),
+            Self::AltShift(name) => Event::AltShift(key_from_name(name)?),
+            Self::Ctrl(name) => Event::Ctrl(key_from_name(name)?),
+            Self::CtrlShift(name) => Event::CtrlShift(key_from_name(name)?),
+            Self::CtrlAlt(name) => Event::CtrlAlt(key_from_name(name)?),
+            Self::WindowResize => Event::WindowResize,
+        })
+    }
+}
+
+/// A line of a session file
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEntry {
+    /// Always the first entry
+    Header {
+        version: String,
+        cols: usize,
+        rows: usize,
+        /// View mode the session started in, e.g. "live"
+        mode: String,
+        /// Unix timestamp of the sample the view started at
+        timestamp: u64,
+    },
+    Event {
+        elapsed_ms: u64,
+        event: SessionEvent,
+    },
+    Frame {
+        elapsed_ms: u64,
+        lines: Vec<String>,
+    },
+}
+
+/// Plain text contents of the screen
+struct Screen {
+    size: Vec2,
+    cells: Vec<Vec<char>>,
+}
+
+impl Screen {
+    fn new(size: Vec2) -> Self {
+        Self {
+            size,
+            cells: vec![vec![' '; size.x]; size.y],
+        }
+    }
+
+    fn print_at(&mut self, pos: Vec2, text: &str) {
+        if let Some(row) = self.cells.get_mut(pos.y) {
+            for (cell, c) in row.iter_mut().skip(pos.x).zip(text.chars()) {
+                *cell = c;
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        *self = Self::new(self.size);
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.cells
+            .iter()
+            .map(|row| row.iter().collect::<String>().trim_end().to_owned())
+            .collect()
+    }
+}
+
+/// Writes the entries of a session to its file
+pub struct SessionRecorder {
+    out: BufWriter<File>,
+    started: Instant,
+    mode: String,
+    timestamp: u64,
+}
+
+impl SessionRecorder {
+    pub fn new(path: &Path, mode: &str, timestamp: u64) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create session file {}", path.display()))?;
+        Ok(Self {
+            out: BufWriter::new(file),
+            started: Instant::now(),
+            mode: mode.to_owned(),
+            timestamp,
+        })
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    /// Entries are flushed right away so that a session is complete up to a
+    /// crash. Failures are ignored rather than breaking the view.
+    fn write(&mut self, entry: &SessionEntry) {
+        if let Ok(line) = serde_json::to_string(entry) {
+            let _ = writeln!(self.out, "{}", line).and_then(|_| self.out.flush());
+        }
+    }
+
+    /// Record the session through `backend`
+    pub fn wrap(mut self, backend: Box<dyn Backend>) -> Box<dyn Backend> {
+        let size = backend.screen_size();
+        self.write(&SessionEntry::Header {
+            version: crate::get_version_str(),
+            cols: size.x,
+            rows: size.y,
+            mode: self.mode.clone(),
+            timestamp: self.timestamp,
+        });
+        Box::new(RecordingBackend {
+            inner: backend,
+            recorder: self,
+            screen: RefCell::new(Screen::new(size)),
+            last_frame: Vec::new(),
+        })
+    }
+}
+
+/// Passes everything through to the terminal backend, recording input
+/// events and changed frames on the way
+struct RecordingBackend {
+    inner: Box<dyn Backend>,
+    recorder: SessionRecorder,
+    screen: RefCell<Screen>,
+    last_frame: Vec<String>,
+}
+
+impl Backend for RecordingBackend {
+    fn poll_event(&mut self) -> Option<Event> {
+        let event = self.inner.poll_event()?;
+        if event == Event::WindowResize {
+            self.screen.replace(Screen::new(self.inner.screen_size()));
+        }
+        if let Some(session_event) = SessionEvent::new(&event) {
+            let elapsed_ms = self.recorder.elapsed_ms();
+            self.recorder.write(&SessionEntry::Event {
+                elapsed_ms,
+                event: session_event,
+            });
+        }
+        Some(event)
+    }
+
+    fn set_title(&mut self, title: String) {
+        self.inner.set_title(title)
+    }
+
+    fn refresh(&mut self) {
+        self.inner.refresh();
+        let lines = self.screen.borrow().lines();
+        if lines != self.last_frame {
+            let elapsed_ms = self.recorder.elapsed_ms();
+            self.recorder.write(&SessionEntry::Frame {
+                elapsed_ms,
+                lines: lines.clone(),
+            });
+            self.last_frame = lines;
+        }
+    }
+
+    fn has_colors(&self) -> bool {
+        self.inner.has_colors()
+    }
+
+    fn screen_size(&self) -> Vec2 {
+        self.inner.screen_size()
+    }
+
+    fn print_at(&self, pos: Vec2, text: &str) {
+        self.inner.print_at(pos, text);
+        self.screen.borrow_mut().print_at(pos, text);
+    }
+
+    fn clear(&self, color: theme::Color) {
+        self.inner.clear(color);
+        self.screen.borrow_mut().clear();
+    }
+
+    fn set_color(&self, colors: theme::ColorPair) -> theme::ColorPair {
+        self.inner.set_color(colors)
+    }
+
+    fn set_effect(&self, effect: theme::Effect) {
+        self.inner.set_effect(effect)
+    }
+
+    fn unset_effect(&self, effect: theme::Effect) {
+        self.inner.unset_effect(effect)
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+}
+
+/// A recorded session
+pub struct Session {
+    pub header: SessionEntry,
+    pub events: Vec<SessionEvent>,
+}
+
+impl Session {
+    pub fn read(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open session file {}", path.display()))?;
+        let mut entries = BufReader::new(file).lines().enumerate().map(|(idx, line)| {
+            let line = line.context("Failed to read session file")?;
+            serde_json::from_str::<SessionEntry>(&line)
+                .with_context(|| format!("Invalid entry on line {} of session file", idx + 1))
+        });
+        let header = match entries.next() {
+            Some(entry @ Ok(SessionEntry::Header { .. })) => entry?,
+            Some(Err(e)) => return Err(e),
+            _ => bail!("Session file does not start with a header"),
+        };
+        let mut events = Vec::new();
+        for entry in entries {
+            if let SessionEntry::Event { event, .. } = entry? {
+                events.push(event);
+            }
+        }
+        Ok(Self { header, events })
+    }
+
+    /// Unix timestamp of the sample the view started at
+    pub fn timestamp(&self) -> u64 {
+        match self.header {
+            SessionEntry::Header { timestamp, .. } => timestamp,
+            _ => unreachable!("Session header is checked on read"),
+        }
+    }
+
+    /// A backend without a terminal that feeds the events of the session to
+    /// the view, one per frame, and writes the frames rendered to `out`.
+    /// The view exits after the last event.
+    pub fn into_backend(self, out: Box<dyn Write>) -> Box<dyn Backend> {
+        let size = match self.header {
+            SessionEntry::Header { cols, rows, .. } => Vec2::new(cols, rows),
+            _ => unreachable!("Session header is checked on read"),
+        };
+        Box::new(ReplayBackend {
+            events: self.events.into(),
+            last_event: None,
+            polled: false,
+            screen: RefCell::new(Screen::new(size)),
+            last_frame: Vec::new(),
+            out,
+        })
+    }
+}
+
+struct ReplayBackend {
+    events: VecDeque<SessionEvent>,
+    last_event: Option<SessionEvent>,
+    /// Whether an event was returned since the last frame, so that the view
+    /// renders a frame after every event
+    polled: bool,
+    screen: RefCell<Screen>,
+    last_frame: Vec<String>,
+    out: Box<dyn Write>,
+}
+
+impl Backend for ReplayBackend {
+    fn poll_event(&mut self) -> Option<Event> {
+        if self.polled {
+            return None;
+        }
+        self.polled = true;
+        match self.events.pop_front() {
+            Some(session_event) => {
+                let event = session_event.to_event();
+                self.last_event = Some(session_event);
+                // Unknown keys still take a frame to keep the output aligned
+                // with the session
+                Some(event.unwrap_or(Event::Refresh))
+            }
+            None => Some(Event::Exit),
+        }
+    }
+
+    fn set_title(&mut self, _title: String) {}
+
+    fn refresh(&mut self) {
+        self.polled = false;
+        let lines = self.screen.borrow().lines();
+        if lines == self.last_frame {
+            return;
+        }
+        let header = match &self.last_event {
+            Some(event) => format!("--- after {:?} ---", event),
+            None => "--- initial ---".to_owned(),
+        };
+        let _ = writeln!(self.out, "{}\n{}", header, lines.join("\n"));
+        self.last_frame = lines;
+    }
+
+    fn has_colors(&self) -> bool {
+        true
+    }
+
+    fn screen_size(&self) -> Vec2 {
+        self.screen.borrow().size
+    }
+
+    fn print_at(&self, pos: Vec2, text: &str) {
+        self.screen.borrow_mut().print_at(pos, text);
+    }
+
+    fn clear(&self, _color: theme::Color) {
+        self.screen.borrow_mut().clear();
+    }
+
+    fn set_color(&self, colors: theme::ColorPair) -> theme::ColorPair {
+        colors
+    }
+
+    fn set_effect(&self, _effect: theme::Effect) {}
+
+    fn unset_effect(&self, _effect: theme::Effect) {}
+
+    fn name(&self) -> &str {
+        "session-replay"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_round_trip() {
+        for event in [
+            Event::Char('j'),
+            Event::CtrlChar('r'),
+            Event::AltChar('x'),
+            Event::Key(Key::PageDown),
+            Event::Shift(Key::Tab),
+            Event::Ctrl(Key::F5),
+            Event::WindowResize,
+        ] {
+            let session_event = SessionEvent::new(&event).expect("Event not recorded");
+            let json = serde_json::to_string(&session_event).unwrap();
+            let parsed: SessionEvent = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.to_event(), Some(event));
+        }
+        assert_eq!(SessionEvent::new(&Event::Refresh), None);
+    }
+
+    #[test]
+    fn screen_lines() {
+        let mut screen = Screen::new(Vec2::new(8, 2));
+        screen.print_at(Vec2::new(2, 0), "below");
+        screen.print_at(Vec2::new(6, 1), "long text");
+        assert_eq!(screen.lines(), vec!["  below", "      lo"]);
+        screen.clear();
+        assert_eq!(screen.lines(), vec!["", ""]);
+    }
+}
@@ -20,9 +20,11 @@ use std::rc::Rc;
 
 use common::logutil::get_last_log_to_display;
 use common::logutil::CPMsgRecord;
+use cursive::event::Callback;
 use cursive::event::Event;
 use cursive::event::EventResult;
 use cursive::event::EventTrigger;
+use cursive::theme::Effect;
 use cursive::utils::markup::StyledString;
 use cursive::view::Nameable;
 use cursive::view::Scrollable;
@@ -41,6 +43,19 @@ use cursive::Cursive;
 use crate::command_palette::CommandPalette;
 use crate::controllers::Controllers;
 use crate::tab_view::TabView;
+use crate::ViewState;
+
+/// Warning for samples in which the BPF collectors lost events, as the
+/// processes that exited meanwhile are then missing
+fn bpf_lost_events_alert(model: &model::Model) -> Option<String> {
+    let lost_events = model.system.collection.as_ref()?.bpf_lost_events?;
+    (lost_events > 0).then(|| {
+        format!(
+            "BPF collectors lost {} events, exited processes may be missing",
+            lost_events
+        )
+    })
+}
 
 pub struct ColumnTitles {
     pub titles: Vec<String>,
@@ -86,6 +101,11 @@ pub trait StateCommon {
     fn set_sort_string(&mut self, _selection: &str, _reverse: &mut bool) -> bool {
         false
     }
+    /// Field ids accepted by set_sort_string, completed in the command
+    /// palette
+    fn sort_strings() -> Vec<String> {
+        Vec::new()
+    }
     fn set_sort_tag_from_tab_idx(&mut self, _tab: &str, _idx: usize, _reverse: &mut bool) -> bool {
         false
     }
@@ -138,6 +158,17 @@ pub trait ViewBridge {
     ) -> String {
         "".to_owned()
     }
+
+    /// Optional callback for the inspect popup. Returns the title of the
+    /// popup and every field of the selected row as JSON, or None if the
+    /// selected row cannot be inspected.
+    fn inspect(
+        _state: &Self::StateType,
+        _selected_key: &<<Self as ViewBridge>::StateType as StateCommon>::KeyType,
+        _current_tab: &str,
+    ) -> Option<(String, String)> {
+        None
+    }
 }
 
 /// StatsView is a view wrapper that wraps tabs, titles, and list of stats.
@@ -179,6 +210,8 @@ pub struct StatsView<V: 'static + ViewBridge> {
     pub state: Rc<RefCell<V::StateType>>,
     pub reverse_sort: bool,
     pub event_controllers: Rc<RefCell<HashMap<Event, Controllers>>>,
+    /// Text searched for in the rows, whose matches are highlighted
+    pub search: Option<String>,
 }
 
 impl<V: 'static + ViewBridge> ViewWrapper for StatsView<V> {
@@ -295,6 +328,16 @@ impl<V: 'static + ViewBridge> StatsView<V> {
             state: Rc::new(RefCell::new(state)),
             reverse_sort: true,
             event_controllers,
+            search: None,
+        }
+    }
+
+    /// Replace the content of `tab`, e.g. to change its columns
+    pub fn set_tab_view(&mut self, tab: &str, bridge: V) {
+        self.tab_titles_map.insert(tab.into(), bridge.get_titles());
+        self.tab_view_map.insert(tab.into(), bridge);
+        if self.get_tab_view().get_cur_selected() == tab {
+            self.update_title();
         }
     }
 
@@ -435,7 +478,14 @@ impl<V: 'static + ViewBridge> StatsView<V> {
                 .tab_view_map
                 .get_mut(&cur_tab)
                 .unwrap_or_else(|| panic!("Fail to query data from tab {}", cur_tab));
-            select_view.add_all(tab_detail.get_rows(&self.state.borrow(), Some(horizontal_offset)));
+            let rows = tab_detail.get_rows(&self.state.borrow(), Some(horizontal_offset));
+            match self.search.as_deref() {
+                Some(search) => select_view.add_all(
+                    rows.into_iter()
+                        .map(|(line, key)| (highlight_matches(line, search), key)),
+                ),
+                None => select_view.add_all(rows),
+            }
 
             // This will trigger on_select handler, but handler will not be able to
             // find the current StatsView from cursive, presumably because we are
@@ -443,6 +493,15 @@ impl<V: 'static + ViewBridge> StatsView<V> {
             select_view.select_down(pos)(c);
 
             let mut cmd_palette = self.get_cmd_palette();
+            // Not repeated while the alert of an earlier sample is shown
+            if !cmd_palette.is_alerting() {
+                if let Some(msg) = c
+                    .user_data::<ViewState>()
+                    .and_then(|view_state| bpf_lost_events_alert(&view_state.model.borrow()))
+                {
+                    cmd_palette.set_alert(CPMsgRecord::construct_msg(slog::Level::Warning, &msg));
+                }
+            }
             if let Some(msg) = get_last_log_to_display() {
                 cmd_palette.set_alert(msg);
             }
@@ -474,6 +533,58 @@ impl<V: 'static + ViewBridge> StatsView<V> {
         self
     }
 
+    // Chaining call. Use for construction to raise a warning found while
+    // setting up the view.
+    pub fn with_alert(mut self, msg: Option<String>) -> Self {
+        if let Some(msg) = msg {
+            self.set_alert(&msg);
+        }
+        self
+    }
+
+    /// Select the next row after the selected one, or the previous one if
+    /// not `forward`, that matches the search, wrapping around at the end.
+    /// Returns the callback of the selection change, or None if no row
+    /// matches.
+    pub fn jump_to_match(&mut self, forward: bool) -> Option<Callback> {
+        let search = self.search.clone()?;
+        let mut select_view = self.get_detail_view();
+        let len = select_view.len();
+        let selected = select_view.selected_id().unwrap_or(0);
+        let matching: Vec<bool> = select_view
+            .iter()
+            .map(|(label, _)| label.contains(&search))
+            .collect();
+        (1..=len)
+            .map(|step| {
+                if forward {
+                    (selected + step) % len
+                } else {
+                    (selected + len - step % len) % len
+                }
+            })
+            .find(|idx| matching[*idx])
+            .map(|idx| select_view.set_selection(idx))
+    }
+
+    /// Search the rows for `search`, or clear the search if empty, and jump
+    /// to the first match.
+    pub fn search_myself(c: &mut Cursive, search: String) {
+        {
+            let mut view = Self::get_view(c);
+            view.search = Some(search).filter(|search| !search.is_empty());
+            // Highlight the matches before looking for them
+            view.refresh(c);
+            if let Some(search) = view.search.clone() {
+                match view.jump_to_match(true) {
+                    Some(cb) => cb(c),
+                    None => view.set_alert(&format!("Pattern not found: {}", search)),
+                }
+            }
+        }
+        Self::refresh_myself(c);
+    }
+
     /// Convenience function to get StatsView
     pub fn get_view(c: &mut Cursive) -> ViewRef<Self> {
         c.find_name::<Self>(V::get_view_name())
@@ -502,3 +613,41 @@ impl<V: 'static + ViewBridge> StatsView<V> {
         Self::get_view(c).get_cmd_palette().set_filter(filter_info);
     }
 }
+
+/// Reverse the colors of the matches of `search` in `line`, keeping their
+/// style otherwise. Matches may span several columns.
+fn highlight_matches(line: StyledString, search: &str) -> StyledString {
+    if search.is_empty() {
+        return line;
+    }
+    let matches: Vec<(usize, usize)> = line
+        .source()
+        .match_indices(search)
+        .map(|(start, m)| (start, start + m.len()))
+        .collect();
+    if matches.is_empty() {
+        return line;
+    }
+    let mut highlighted = StyledString::new();
+    // Rows are built by appending, so the spans cover the source in order
+    let mut offset = 0;
+    for span in line.spans() {
+        let end = offset + span.content.len();
+        let mut pos = offset;
+        for &(start, stop) in &matches {
+            let (start, stop) = (start.max(pos), stop.min(end));
+            if start >= stop {
+                continue;
+            }
+            highlighted.append_styled(&span.content[pos - offset..start - offset], *span.attr);
+            highlighted.append_styled(
+                &span.content[start - offset..stop - offset],
+                span.attr.combine(Effect::Reverse),
+            );
+            pos = stop;
+        }
+        highlighted.append_styled(&span.content[pos - offset..], *span.attr);
+        offset = end;
+    }
+    highlighted
+}
@@ -12,14 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::time::Instant;
+
 use chrono::DateTime;
 use chrono::Local;
+use common::util::render_bar;
 use cursive::utils::markup::StyledString;
 use cursive::view::Nameable;
 use cursive::view::View;
 use cursive::views::TextView;
 use cursive::Cursive;
 
+use crate::ViewMode;
 use crate::ViewState;
 
 fn get_spacing() -> &'static str {
@@ -43,13 +47,18 @@ fn get_content(c: &mut Cursive) -> impl Into<StyledString> {
     let this = view_state.time_elapsed.as_secs();
     // 1 second jitter happens pretty often due to integer rounding
     if lowest != 0 && this >= (lowest + 2) {
-        header_str.append_styled(
-            elapsed_rendered,
-            cursive::theme::Color::Light(cursive::theme::BaseColor::Red),
-        );
+        header_str.append_styled(elapsed_rendered, crate::theme::current().critical);
     } else {
         header_str.append_plain(elapsed_rendered);
     }
+    // Rates are averaged over the whole gap, so flag it explicitly
+    let gaps = view_state.model.borrow().gaps;
+    if gaps > 0 {
+        header_str.append_styled(
+            format!(" GAP: {} missed", gaps),
+            crate::theme::current().critical,
+        );
+    }
 
     header_str.append_plain(format!(
         "{}{}{}",
@@ -61,6 +70,94 @@ fn get_content(c: &mut Cursive) -> impl Into<StyledString> {
     header_str.append_plain(crate::get_version_str());
     header_str.append_plain(get_spacing());
     header_str.append_plain(view_state.view_mode_str());
+    if let (ViewMode::Live(_), Some(interval_s)) = (&view_state.mode, view_state.refresh_interval())
+    {
+        header_str.append_plain(format!(" every {}s", interval_s));
+    }
+    if let (ViewMode::Live(_), Some(lite)) = (&view_state.mode, &view_state.lite) {
+        header_str.append_styled(format!(" LITE: {}", lite), crate::theme::current().warning);
+    }
+    if let Some(playback) = &view_state.playback {
+        header_str.append_plain(format!(" {}", playback.label()));
+    }
+    if let ViewMode::CatchUp(adv) = &view_state.mode {
+        if let Some(progress) = adv.borrow().catch_up_progress() {
+            header_str.append_plain(format!(
+                " {} {:.0}%",
+                render_bar(progress * 100.0, 10),
+                progress * 100.0
+            ));
+        }
+    }
+
+    if let Some(compare) = view_state.compare.as_ref() {
+        if let Some(offset) = compare.offset() {
+            let compared = match compare.model() {
+                Some(model) => DateTime::<Local>::from(model.timestamp)
+                    .format("%m/%d/%Y %H:%M:%S")
+                    .to_string(),
+                None => "no data".to_owned(),
+            };
+            header_str.append_plain(format!(
+                "{}Compare -{}: {}",
+                get_spacing(),
+                humantime::format_duration(offset),
+                compared
+            ));
+        }
+    }
+
+    let omitted = &view_state.model.borrow().omitted_sections;
+    if !omitted.is_empty() {
+        header_str.append_plain(format!(
+            "{}Not collected: {}",
+            get_spacing(),
+            omitted
+                .iter()
+                .map(|section| section.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    let unavailable = &view_state.model.borrow().unavailable_sources;
+    if !unavailable.is_empty() {
+        header_str.append_plain(format!(
+            "{}Unavailable: {}",
+            get_spacing(),
+            unavailable
+                .keys()
+                .map(|source| source.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    if view_state.model.borrow().cmdline_redacted {
+        header_str.append_plain(format!("{}Cmdlines redacted", get_spacing()));
+    }
+    if view_state.model.borrow().discontinuity {
+        header_str.append_plain(format!("{}Clock stepped", get_spacing()));
+    }
+    // Rates restart from this sample, which would otherwise look like the
+    // host was idle
+    if view_state.model.borrow().rebooted {
+        header_str.append_plain(get_spacing());
+        header_str.append_styled("REBOOTED", crate::theme::current().warning);
+    }
+    if let Some(notifier) = &view_state.notifier {
+        for (name, highlight) in notifier.firing(Instant::now()) {
+            header_str.append_plain(get_spacing());
+            // Blinks between reversed and plain after firing
+            let style = if highlight {
+                cursive::theme::Style::from(cursive::theme::ColorStyle::new(
+                    cursive::theme::Color::Dark(cursive::theme::BaseColor::Black),
+                    crate::theme::current().critical,
+                ))
+            } else {
+                cursive::theme::Style::from(crate::theme::current().critical)
+            };
+            header_str.append_styled(format!("NOTIFY: {}", name), style);
+        }
+    }
 
     header_str
 }
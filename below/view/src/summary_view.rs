@@ -11,26 +11,30 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+use cursive::utils::markup::StyledString;
 use cursive::view::Nameable;
 use cursive::view::View;
 use cursive::views::LinearLayout;
 use cursive::views::TextView;
 use cursive::Cursive;
+use model::Model;
 
 use crate::ViewState;
 
 mod render_impl {
-    use std::collections::BTreeMap;
     use std::str::FromStr;
 
     use base_render::RenderConfig;
     use base_render::RenderConfigBuilder as Rc;
+    use common::util::convert_bytes;
+    use common::util::render_bar;
+    use common::util::render_level;
+    use cursive::theme::Color;
     use cursive::utils::markup::StyledString;
+    use model::CgroupPressureModel;
     use model::Model;
     use model::ModelFieldId;
     use model::Queriable;
-    use model::SingleDiskModel;
-    use model::SingleNetModel;
     use model::SystemModel;
     use once_cell::sync::Lazy;
 
@@ -52,6 +56,20 @@ mod render_impl {
         ]
     });
 
+    static SYS_LOAD_ITEMS: Lazy<Vec<SummaryViewItem>> = Lazy::new(|| {
+        use model::LoadAvgModelFieldId::Load1;
+        use model::LoadAvgModelFieldId::Load15;
+        use model::LoadAvgModelFieldId::Load5;
+        use model::SystemModelFieldId::Loadavg;
+        use model::SystemModelFieldId::UptimeSecs;
+        vec![
+            ViewItem::from_default(Loadavg(Load1)).update(Rc::new().title("1m")),
+            ViewItem::from_default(Loadavg(Load5)).update(Rc::new().title("5m")),
+            ViewItem::from_default(Loadavg(Load15)).update(Rc::new().title("15m")),
+            ViewItem::from_default(UptimeSecs).update(Rc::new().title("Uptime")),
+        ]
+    });
+
     static SYS_MEM_ITEMS: Lazy<Vec<SummaryViewItem>> = Lazy::new(|| {
         use model::MemoryModelFieldId::Anon;
         use model::MemoryModelFieldId::File;
@@ -80,6 +98,18 @@ mod render_impl {
         ]
     });
 
+    static SYS_TASK_ITEMS: Lazy<Vec<SummaryViewItem>> = Lazy::new(|| {
+        use model::DStateModelFieldId::Count;
+        use model::DStateModelFieldId::MaxUptimeSecs;
+        use model::SystemModelFieldId::Dstate;
+        use model::SystemModelFieldId::HungTaskDetectDelta;
+        vec![
+            ViewItem::from_default(Dstate(Count)).update(Rc::new().title("D State")),
+            ViewItem::from_default(Dstate(MaxUptimeSecs)).update(Rc::new().title("D Uptime")),
+            ViewItem::from_default(HungTaskDetectDelta).update(Rc::new().title("Hung Task")),
+        ]
+    });
+
     const ROW_NAME_WIDTH: usize = 15;
     const ROW_FIELD_NAME_WIDTH: usize = 9;
     const ROW_FIELD_WIDTH: usize = 21;
@@ -90,9 +120,17 @@ mod render_impl {
     const ROW_FIELD_WIDTH_HALVED: usize = 10;
     /// Maximum number of I/O devices to display
     const MAX_IO_DEVICES: usize = 5;
+    /// Number of cells in the memory and swap meters
+    const METER_WIDTH: usize = 30;
+    /// Number of cells in each of the PSI meters
+    const PSI_METER_WIDTH: usize = 10;
+    /// Meters at or above this percentage are highlighted
+    const METER_HIGHLIGHT_PCT: f64 = 80.0;
+    /// Number of per core bars to group together, for easier counting
+    const CORES_PER_GROUP: usize = 8;
 
     pub fn render_row<T: Queriable>(
-        name: &'static str,
+        name: &str,
         model: &T,
         items: impl Iterator<Item = ViewItem<T::FieldId>>,
     ) -> StyledString {
@@ -106,8 +144,17 @@ mod render_impl {
         row
     }
 
-    pub fn render_extra_row(extra_row: &SummaryViewExtraRow, model: &Model) -> StyledString {
+    /// Render a row configured in viewrc. Rows of the compared sample are
+    /// prefixed with its `label`.
+    pub fn render_extra_row(
+        extra_row: &SummaryViewExtraRow,
+        model: &Model,
+        label: Option<&str>,
+    ) -> StyledString {
         let mut row = StyledString::new();
+        if let Some(label) = label {
+            row.append_plain(format!("{} ", label));
+        }
         if let Some(title) = &extra_row.title {
             row.append(title.clone());
         }
@@ -122,7 +169,7 @@ mod render_impl {
     }
 
     pub fn render_read_write_models_row<'a, T: 'a + Queriable>(
-        name: &'static str,
+        name: &str,
         models: impl Iterator<Item = (&'a String, &'a T)>,
         read_item: ViewItem<T::FieldId>,
         write_item: ViewItem<T::FieldId>,
@@ -149,40 +196,159 @@ mod render_impl {
         row
     }
 
-    pub fn render_cpu_row(model: &SystemModel) -> StyledString {
-        render_row("CPU", model, SYS_CPU_ITEMS.iter().cloned())
+    pub fn render_load_row(name: &str, model: &Model) -> StyledString {
+        render_row(name, &model.system, SYS_LOAD_ITEMS.iter().cloned())
+    }
+
+    pub fn render_cpu_row(name: &str, model: &Model) -> StyledString {
+        render_row(name, &model.system, SYS_CPU_ITEMS.iter().cloned())
+    }
+
+    pub fn render_mem_row(name: &str, model: &Model) -> StyledString {
+        render_row(name, &model.system, SYS_MEM_ITEMS.iter().cloned())
     }
 
-    pub fn render_mem_row(model: &SystemModel) -> StyledString {
-        render_row("Mem", model, SYS_MEM_ITEMS.iter().cloned())
+    pub fn render_vm_row(name: &str, model: &Model) -> StyledString {
+        render_row(name, &model.system, SYS_VM_ITEMS.iter().cloned())
     }
 
-    pub fn render_vm_row(model: &SystemModel) -> StyledString {
-        render_row("VM", model, SYS_VM_ITEMS.iter().cloned())
+    /// Processes in D state and new hung tasks, a pileup of which is a sign
+    /// of stuck I/O or lock contention
+    pub fn render_task_row(name: &str, model: &Model) -> StyledString {
+        render_row(name, &model.system, SYS_TASK_ITEMS.iter().cloned())
     }
 
-    pub fn render_io_row(disks: &BTreeMap<String, SingleDiskModel>) -> StyledString {
+    pub fn render_io_row(name: &str, model: &Model) -> StyledString {
         use model::SingleDiskModelFieldId::ReadBytesPerSec;
         use model::SingleDiskModelFieldId::WriteBytesPerSec;
         render_read_write_models_row(
-            "I/O   (Rd|Wr)", // Line up () with Iface's below
-            disks.iter().filter(|(_, sdm)| sdm.minor == Some(0)),
+            name,
+            model
+                .system
+                .disks
+                .iter()
+                .filter(|(_, sdm)| sdm.minor == Some(0)),
             ViewItem::from_default(ReadBytesPerSec),
             ViewItem::from_default(WriteBytesPerSec),
         )
     }
 
-    pub fn render_iface_row(ifaces: &BTreeMap<String, SingleNetModel>) -> StyledString {
+    pub fn render_iface_row(name: &str, model: &Model) -> StyledString {
         use model::SingleNetModelFieldId::RxBytesPerSec;
         use model::SingleNetModelFieldId::TxBytesPerSec;
         render_read_write_models_row(
-            "Iface (Rx|Tx)",
-            ifaces.iter(),
+            name,
+            model.network.interfaces.iter(),
             ViewItem::from_default(RxBytesPerSec),
             ViewItem::from_default(TxBytesPerSec),
         )
     }
 
+    fn meter_style(pct: f64) -> Color {
+        if pct >= METER_HIGHLIGHT_PCT {
+            crate::theme::current().critical
+        } else {
+            Color::TerminalDefault
+        }
+    }
+
+    /// Render a "[████▌     ] label" meter, or an empty meter if `pct` is
+    /// not available.
+    fn render_meter(pct: Option<f64>, width: usize, label: String) -> StyledString {
+        let mut meter = StyledString::plain("[");
+        match pct {
+            Some(pct) => meter.append_styled(render_bar(pct, width), meter_style(pct)),
+            None => meter.append_plain(" ".repeat(width)),
+        }
+        meter.append_plain(format!("] {}", label));
+        meter
+    }
+
+    fn format_pct(pct: Option<f64>) -> String {
+        match pct {
+            Some(pct) => format!("{:.1}%", pct),
+            None => "?".to_owned(),
+        }
+    }
+
+    /// Render a used/total meter, e.g. for memory or swap.
+    fn render_usage_meter_row(
+        name: &'static str,
+        used: Option<u64>,
+        total: Option<u64>,
+    ) -> StyledString {
+        let pct = match (used, total) {
+            (Some(used), Some(total)) if total > 0 => Some(used as f64 * 100.0 / total as f64),
+            _ => None,
+        };
+        let label = match (used, total) {
+            (Some(used), Some(total)) => format!(
+                "{} {}/{}",
+                format_pct(pct),
+                convert_bytes(used as f64),
+                convert_bytes(total as f64)
+            ),
+            _ => "?".to_owned(),
+        };
+        let mut row = StyledString::new();
+        row.append(base_render::get_fixed_width(name, ROW_NAME_WIDTH));
+        row.append(render_meter(pct, METER_WIDTH, label));
+        row
+    }
+
+    /// One vertical bar per core showing its utilization.
+    pub fn render_cores_meter_row(model: &SystemModel) -> StyledString {
+        let mut row = StyledString::new();
+        row.append(base_render::get_fixed_width("Cores", ROW_NAME_WIDTH));
+        for (idx, cpu) in model.cpus.values().enumerate() {
+            if idx > 0 && idx % CORES_PER_GROUP == 0 {
+                row.append_plain(" ");
+            }
+            let pct = cpu.usage_pct.unwrap_or_default();
+            row.append_styled(render_level(pct).to_string(), meter_style(pct));
+        }
+        row
+    }
+
+    pub fn render_mem_meter_row(model: &SystemModel) -> StyledString {
+        let mem = &model.mem;
+        let used = match (mem.total, mem.available) {
+            (Some(total), Some(available)) => Some(total.saturating_sub(available)),
+            _ => None,
+        };
+        render_usage_meter_row("Mem Used", used, mem.total)
+    }
+
+    pub fn render_swap_meter_row(model: &SystemModel) -> StyledString {
+        let mem = &model.mem;
+        let used = match (mem.swap_total, mem.swap_free) {
+            (Some(total), Some(free)) => Some(total.saturating_sub(free)),
+            _ => None,
+        };
+        render_usage_meter_row("Swap Used", used, mem.swap_total)
+    }
+
+    /// System wide pressure, taken from the root cgroup. IRQ pressure is
+    /// only shown on kernels that have it.
+    pub fn render_psi_meter_row(pressure: Option<&CgroupPressureModel>) -> StyledString {
+        let mut row = StyledString::new();
+        row.append(base_render::get_fixed_width("Pressure", ROW_NAME_WIDTH));
+        let mut meters = vec![
+            ("CPU Some", pressure.and_then(|p| p.cpu_some_pct)),
+            ("Mem Full", pressure.and_then(|p| p.memory_full_pct)),
+            ("I/O Full", pressure.and_then(|p| p.io_full_pct)),
+        ];
+        if let Some(irq) = pressure.and_then(|p| p.irq_full_pct) {
+            meters.push(("IRQ Full", Some(irq)));
+        }
+        for (title, pct) in meters {
+            row.append(base_render::get_fixed_width(title, ROW_FIELD_NAME_WIDTH));
+            row.append(render_meter(pct, PSI_METER_WIDTH, format_pct(pct)));
+            row.append_plain("  ");
+        }
+        row
+    }
+
     pub struct SummaryViewExtraRow {
         pub title: Option<String>,
         pub items: Vec<ViewItem<model::ModelFieldId>>,
@@ -223,27 +389,60 @@ fn fill_content(c: &mut Cursive, v: &mut LinearLayout) {
         .expect("No data stored in Cursive object!");
 
     let system_model = view_state.system.borrow();
-    let network_model = view_state.network.borrow();
-    let cpu_row = render_impl::render_cpu_row(&system_model);
-    let mem_row = render_impl::render_mem_row(&system_model);
-    let vm_row = render_impl::render_vm_row(&system_model);
-    let io_row = render_impl::render_io_row(&system_model.disks);
-    let iface_row = render_impl::render_iface_row(&network_model.interfaces);
+    let model = view_state.model.borrow();
+    // Label and model of the compared sample in replay, if any
+    let compare = view_state
+        .compare
+        .as_ref()
+        .and_then(|compare| Some((compare.label(), compare.model()?)));
 
     let mut view = LinearLayout::vertical();
-    view.add_child(TextView::new(cpu_row));
-    view.add_child(TextView::new(mem_row));
-    view.add_child(TextView::new(vm_row));
-    view.add_child(TextView::new(io_row));
-    view.add_child(TextView::new(iface_row));
+    if view_state.viewrc.summary_meters.unwrap_or(false) {
+        let cgroup_model = view_state.cgroup.borrow();
+        view.add_child(TextView::new(render_impl::render_cores_meter_row(
+            &system_model,
+        )));
+        view.add_child(TextView::new(render_impl::render_mem_meter_row(
+            &system_model,
+        )));
+        view.add_child(TextView::new(render_impl::render_swap_meter_row(
+            &system_model,
+        )));
+        view.add_child(TextView::new(render_impl::render_psi_meter_row(
+            cgroup_model.data.pressure.as_ref(),
+        )));
+    }
+    let rows: [(&str, fn(&str, &Model) -> StyledString); 7] = [
+        ("Load", render_impl::render_load_row),
+        ("CPU", render_impl::render_cpu_row),
+        ("Mem", render_impl::render_mem_row),
+        ("VM", render_impl::render_vm_row),
+        ("Tasks", render_impl::render_task_row),
+        // Line up () with Iface's below
+        ("I/O   (Rd|Wr)", render_impl::render_io_row),
+        ("Iface (Rx|Tx)", render_impl::render_iface_row),
+    ];
+    // Each row is followed by the same row of the compared sample
+    for (name, render) in rows {
+        view.add_child(TextView::new(render(name, &model)));
+        if let Some((label, compare_model)) = &compare {
+            view.add_child(TextView::new(render(label, compare_model)));
+        }
+    }
 
-    let model = view_state.model.borrow();
     // TODO: Save the parsed extra rows in a struct and reuse
     let extra_rows = render_impl::get_summary_view_extra_rows(&view_state.viewrc);
     for extra_row in extra_rows {
         view.add_child(TextView::new(render_impl::render_extra_row(
-            &extra_row, &model,
+            &extra_row, &model, None,
         )));
+        if let Some((label, compare_model)) = &compare {
+            view.add_child(TextView::new(render_impl::render_extra_row(
+                &extra_row,
+                compare_model,
+                Some(label.as_str()),
+            )));
+        }
     }
 
     *v = view;
@@ -16,16 +16,29 @@ use base_render::get_fixed_width;
 use base_render::RenderConfigBuilder as Rc;
 use common::util::get_prefix;
 use cursive::utils::markup::StyledString;
+use model::system::BtrfsDeviceModelFieldId;
 use model::system::BtrfsModelFieldId;
+use model::system::CollectionModelFieldId;
 use model::system::KsmModelFieldId;
+use model::system::LoadAvgModelFieldId;
 use model::system::MemoryModelFieldId;
+use model::system::PerfCounterModelFieldId;
 use model::system::SingleCpuModelFieldId;
 use model::system::SingleDiskModelFieldId;
+use model::system::SingleSchedModelFieldId;
 use model::system::SingleSlabModelFieldId;
+use model::system::SingleSoftirqModelFieldId;
 use model::system::VmModelFieldId;
 use model::BtrfsModel;
 use model::Queriable;
+use model::ResctrlL3MonModelFieldId;
+use model::ResctrlMonGroupModelFieldId;
+use model::ResctrlMonModelFieldId;
+use model::SingleNetModelFieldId;
 use model::SingleSlabModel;
+use model::SingleTcModelFieldId;
+use model::SockStat6ModelFieldId;
+use model::SockStatModelFieldId;
 
 use crate::render::ViewItem;
 use crate::stats_view::ColumnTitles;
@@ -105,6 +118,116 @@ impl SystemTab for SystemCpu {
     }
 }
 
+#[derive(Default, Clone)]
+pub struct SystemSoftirq;
+
+impl SystemTab for SystemSoftirq {
+    fn get_titles(&self) -> ColumnTitles {
+        ColumnTitles {
+            titles: enum_iterator::all::<SingleSoftirqModelFieldId>()
+                .map(|field_id| ViewItem::from_default(field_id).config.render_title())
+                .collect(),
+            pinned_titles: 1,
+        }
+    }
+
+    fn get_rows(&self, state: &SystemState, offset: Option<usize>) -> Vec<(StyledString, String)> {
+        let model = state.get_model();
+        model
+            .softirqs
+            .values()
+            .filter(|ssm| {
+                if let Some((SystemStateFieldId::Softirq(field), filter)) = &state.filter_info {
+                    match ssm.query(field) {
+                        None => true,
+                        Some(value) => value.to_string().starts_with(filter),
+                    }
+                } else {
+                    true
+                }
+            })
+            .chain(std::iter::once(&model.total_softirq))
+            .map(|ssm| {
+                (
+                    std::iter::once(SingleSoftirqModelFieldId::Idx)
+                        .chain(
+                            enum_iterator::all::<SingleSoftirqModelFieldId>()
+                                .skip(offset.unwrap_or(0) + 1),
+                        )
+                        .fold(StyledString::new(), |mut line, field_id| {
+                            let view_item = ViewItem::from_default(field_id.clone());
+                            let rendered =
+                                if field_id == SingleSoftirqModelFieldId::Idx && ssm.idx == -1 {
+                                    view_item.config.render(Some("total".to_owned().into()))
+                                } else {
+                                    view_item.render(ssm)
+                                };
+                            line.append(rendered);
+                            line.append_plain(" ");
+                            line
+                        }),
+                    "".to_owned(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct SystemSched;
+
+impl SystemTab for SystemSched {
+    fn get_titles(&self) -> ColumnTitles {
+        ColumnTitles {
+            titles: enum_iterator::all::<SingleSchedModelFieldId>()
+                .map(|field_id| ViewItem::from_default(field_id).config.render_title())
+                .collect(),
+            pinned_titles: 1,
+        }
+    }
+
+    fn get_rows(&self, state: &SystemState, offset: Option<usize>) -> Vec<(StyledString, String)> {
+        let model = state.get_model();
+        model
+            .scheds
+            .values()
+            .filter(|scm| {
+                if let Some((SystemStateFieldId::Sched(field), filter)) = &state.filter_info {
+                    match scm.query(field) {
+                        None => true,
+                        Some(value) => value.to_string().starts_with(filter),
+                    }
+                } else {
+                    true
+                }
+            })
+            .chain(std::iter::once(&model.total_sched))
+            .map(|scm| {
+                (
+                    std::iter::once(SingleSchedModelFieldId::Idx)
+                        .chain(
+                            enum_iterator::all::<SingleSchedModelFieldId>()
+                                .skip(offset.unwrap_or(0) + 1),
+                        )
+                        .fold(StyledString::new(), |mut line, field_id| {
+                            let view_item = ViewItem::from_default(field_id.clone());
+                            let rendered =
+                                if field_id == SingleSchedModelFieldId::Idx && scm.idx == -1 {
+                                    view_item.config.render(Some("total".to_owned().into()))
+                                } else {
+                                    view_item.render(scm)
+                                };
+                            line.append(rendered);
+                            line.append_plain(" ");
+                            line
+                        }),
+                    "".to_owned(),
+                )
+            })
+            .collect()
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct SystemMem;
 
@@ -163,6 +286,11 @@ impl SystemTab for SystemVm {
     }
 }
 
+/// Caches that grew in this many consecutive samples are highlighted as
+/// kernel memory leak suspects
+const SLAB_LEAK_SUSPECT_STREAK: u64 = 5;
+
+/// Slab caches, by default sorted by growth since the last sample
 #[derive(Default, Clone)]
 pub struct SystemSlab;
 
@@ -182,11 +310,15 @@ impl SystemTab for SystemSlab {
 
         if let Some(SystemStateFieldId::Slab(sort_order)) = state.sort_order.as_ref() {
             model::sort_queriables(&mut slab, sort_order, state.reverse);
+        } else {
+            // Fastest growing caches first, below the total
+            model::sort_queriables(&mut slab, &SingleSlabModelFieldId::TotalSizeDelta, true);
+            slab.sort_by_key(|ssm| ssm.name.as_deref() != Some("TOTAL"));
         }
 
         slab.into_iter()
             .map(|ssm| {
-                enum_iterator::all::<SingleSlabModelFieldId>().fold(
+                let line = enum_iterator::all::<SingleSlabModelFieldId>().fold(
                     StyledString::new(),
                     |mut line, field_id| {
                         let view_item = ViewItem::from_default(field_id.clone());
@@ -194,7 +326,48 @@ impl SystemTab for SystemSlab {
                         line.append_plain(" ");
                         line
                     },
-                )
+                );
+                if ssm.growth_streak.unwrap_or(0) >= SLAB_LEAK_SUSPECT_STREAK {
+                    StyledString::styled(line.source(), crate::theme::current().critical)
+                } else {
+                    line
+                }
+            })
+            .filter(|s| {
+                if let Some((_, filter)) = &state.filter_info {
+                    s.source().contains(filter)
+                } else {
+                    true
+                }
+            })
+            .map(|s| (s.clone(), "".into()))
+            .collect()
+    }
+}
+
+/// Load averages alongside uptime and available entropy
+#[derive(Default, Clone)]
+pub struct SystemLoad;
+
+impl SystemTab for SystemLoad {
+    fn get_rows(&self, state: &SystemState, _offset: Option<usize>) -> Vec<(StyledString, String)> {
+        use model::SystemModelFieldId;
+
+        let model = state.get_model();
+        enum_iterator::all::<LoadAvgModelFieldId>()
+            .map(SystemModelFieldId::Loadavg)
+            .chain([
+                SystemModelFieldId::UptimeSecs,
+                SystemModelFieldId::EntropyAvail,
+            ])
+            .map(|field_id| {
+                let mut line = StyledString::new();
+                let item =
+                    ViewItem::from_default(field_id).update(Rc::new().width(FIELD_NAME_WIDTH));
+                line.append_plain(item.config.render_title());
+                line.append_plain(" ");
+                line.append(item.update(Rc::new().width(FIELD_WIDTH)).render(model));
+                line
             })
             .filter(|s| {
                 if let Some((_, filter)) = &state.filter_info {
@@ -239,6 +412,75 @@ impl SystemTab for SystemKsm {
     }
 }
 
+/// Last level cache activity and the memory bandwidth estimated from it
+#[derive(Default, Clone)]
+pub struct SystemPerf;
+
+impl SystemTab for SystemPerf {
+    fn get_rows(&self, state: &SystemState, _offset: Option<usize>) -> Vec<(StyledString, String)> {
+        if let Some(perf_model) = state.get_model().perf.as_ref() {
+            enum_iterator::all::<PerfCounterModelFieldId>()
+                .map(|field_id| {
+                    let mut line = StyledString::new();
+                    let item =
+                        ViewItem::from_default(field_id).update(Rc::new().width(FIELD_NAME_WIDTH));
+                    line.append_plain(item.config.render_title());
+                    line.append_plain(" ");
+                    line.append(item.update(Rc::new().width(FIELD_WIDTH)).render(perf_model));
+                    line
+                })
+                .filter(|s| {
+                    if let Some((_, filter)) = &state.filter_info {
+                        s.source().contains(filter)
+                    } else {
+                        true
+                    }
+                })
+                .map(|s| (s.clone(), "".into()))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Time below took to collect each part of the sample, to tell which one
+/// is responsible for a long collection, and the health of its BPF
+/// collectors
+#[derive(Default, Clone)]
+pub struct SystemBelow;
+
+impl SystemTab for SystemBelow {
+    fn get_rows(&self, state: &SystemState, _offset: Option<usize>) -> Vec<(StyledString, String)> {
+        if let Some(collection_model) = state.get_model().collection.as_ref() {
+            enum_iterator::all::<CollectionModelFieldId>()
+                .map(|field_id| {
+                    let mut line = StyledString::new();
+                    let item =
+                        ViewItem::from_default(field_id).update(Rc::new().width(FIELD_NAME_WIDTH));
+                    line.append_plain(item.config.render_title());
+                    line.append_plain(" ");
+                    line.append(
+                        item.update(Rc::new().width(FIELD_WIDTH))
+                            .render(collection_model),
+                    );
+                    line
+                })
+                .filter(|s| {
+                    if let Some((_, filter)) = &state.filter_info {
+                        s.source().contains(filter)
+                    } else {
+                        true
+                    }
+                })
+                .map(|s| (s.clone(), "".into()))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct SystemDisk;
 
@@ -296,6 +538,237 @@ impl SystemTab for SystemDisk {
     }
 }
 
+/// Link state and the main rate and error counters of an interface, so
+/// that rates can be read against link speed.
+const IFACE_FIELDS: &[SingleNetModelFieldId] = &[
+    SingleNetModelFieldId::Interface,
+    SingleNetModelFieldId::Operstate,
+    SingleNetModelFieldId::SpeedMbps,
+    SingleNetModelFieldId::Duplex,
+    SingleNetModelFieldId::CarrierChanges,
+    SingleNetModelFieldId::CarrierChangesDelta,
+    SingleNetModelFieldId::RxBytesPerSec,
+    SingleNetModelFieldId::TxBytesPerSec,
+    SingleNetModelFieldId::RxPacketsPerSec,
+    SingleNetModelFieldId::TxPacketsPerSec,
+    SingleNetModelFieldId::RxErrors,
+    SingleNetModelFieldId::TxErrors,
+    SingleNetModelFieldId::RxDropped,
+    SingleNetModelFieldId::TxDropped,
+    SingleNetModelFieldId::QdiscDropsPerSec,
+];
+
+#[derive(Default, Clone)]
+pub struct SystemIface;
+
+impl SystemTab for SystemIface {
+    fn get_titles(&self) -> ColumnTitles {
+        ColumnTitles {
+            titles: IFACE_FIELDS
+                .iter()
+                .map(|field_id| {
+                    ViewItem::from_default(field_id.clone())
+                        .config
+                        .render_title()
+                })
+                .collect(),
+            pinned_titles: 1,
+        }
+    }
+
+    fn get_rows(&self, state: &SystemState, offset: Option<usize>) -> Vec<(StyledString, String)> {
+        state
+            .network
+            .borrow()
+            .interfaces
+            .iter()
+            .filter(|(name, _)| match &state.filter_info {
+                Some((_, filter)) => name.starts_with(filter),
+                None => true,
+            })
+            .map(|(name, snm)| {
+                (
+                    IFACE_FIELDS[..1]
+                        .iter()
+                        .chain(IFACE_FIELDS.iter().skip(offset.unwrap_or(0) + 1))
+                        .fold(StyledString::new(), |mut line, field_id| {
+                            line.append(ViewItem::from_default(field_id.clone()).render(snm));
+                            line.append_plain(" ");
+                            line
+                        }),
+                    name.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Sockets in use per protocol, IPv4 first and then IPv6
+#[derive(Default, Clone)]
+pub struct SystemSockets;
+
+impl SystemTab for SystemSockets {
+    fn get_rows(&self, state: &SystemState, _offset: Option<usize>) -> Vec<(StyledString, String)> {
+        let network = state.network.borrow();
+        let sockstat_lines = enum_iterator::all::<SockStatModelFieldId>().map(|field_id| {
+            let mut line = StyledString::new();
+            let item = ViewItem::from_default(field_id).update(Rc::new().width(FIELD_NAME_WIDTH));
+            line.append_plain(item.config.render_title());
+            line.append_plain(" ");
+            line.append(
+                item.update(Rc::new().width(FIELD_WIDTH))
+                    .render(&network.sockstat),
+            );
+            line
+        });
+        let sockstat6_lines = enum_iterator::all::<SockStat6ModelFieldId>().map(|field_id| {
+            let mut line = StyledString::new();
+            let item = ViewItem::from_default(field_id).update(Rc::new().width(FIELD_NAME_WIDTH));
+            line.append_plain(item.config.render_title());
+            line.append_plain(" ");
+            line.append(
+                item.update(Rc::new().width(FIELD_WIDTH))
+                    .render(&network.sockstat6),
+            );
+            line
+        });
+        sockstat_lines
+            .chain(sockstat6_lines)
+            .filter(|s| {
+                if let Some((_, filter)) = &state.filter_info {
+                    s.source().contains(filter)
+                } else {
+                    true
+                }
+            })
+            .map(|s| (s.clone(), "".into()))
+            .collect()
+    }
+}
+
+/// Monitoring totals of a resctrl group across all L3 domains
+const RESCTRL_FIELDS: &[ResctrlMonGroupModelFieldId] = &[
+    ResctrlMonGroupModelFieldId::FullPath,
+    ResctrlMonGroupModelFieldId::Mon(ResctrlMonModelFieldId::Total(
+        ResctrlL3MonModelFieldId::MbmTotalBytesPerSec,
+    )),
+    ResctrlMonGroupModelFieldId::Mon(ResctrlMonModelFieldId::Total(
+        ResctrlL3MonModelFieldId::MbmLocalBytesPerSec,
+    )),
+    ResctrlMonGroupModelFieldId::Mon(ResctrlMonModelFieldId::Total(
+        ResctrlL3MonModelFieldId::LlcOccupancyBytes,
+    )),
+    ResctrlMonGroupModelFieldId::Mon(ResctrlMonModelFieldId::Total(
+        ResctrlL3MonModelFieldId::LlcOccupancyPct,
+    )),
+];
+
+#[derive(Default, Clone)]
+pub struct SystemResctrl;
+
+impl SystemTab for SystemResctrl {
+    fn get_titles(&self) -> ColumnTitles {
+        ColumnTitles {
+            titles: RESCTRL_FIELDS
+                .iter()
+                .map(|field_id| {
+                    ViewItem::from_default(field_id.clone())
+                        .config
+                        .render_title()
+                })
+                .collect(),
+            pinned_titles: 1,
+        }
+    }
+
+    fn get_rows(&self, state: &SystemState, offset: Option<usize>) -> Vec<(StyledString, String)> {
+        let groups = match state.resctrl.borrow().as_ref() {
+            Some(resctrl_model) => resctrl_model.all_groups(),
+            None => return Vec::new(),
+        };
+        groups
+            .into_iter()
+            .filter(|group| match &state.filter_info {
+                Some((_, filter)) => group.full_path.contains(filter),
+                None => true,
+            })
+            .map(|group| {
+                (
+                    RESCTRL_FIELDS[..1]
+                        .iter()
+                        .chain(RESCTRL_FIELDS.iter().skip(offset.unwrap_or(0) + 1))
+                        .fold(StyledString::new(), |mut line, field_id| {
+                            line.append(ViewItem::from_default(field_id.clone()).render(&group));
+                            line.append_plain(" ");
+                            line
+                        }),
+                    group.full_path,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Fields of a qdisc needed to tell where it is buffering or dropping
+const TC_FIELDS: &[SingleTcModelFieldId] = &[
+    SingleTcModelFieldId::Path,
+    SingleTcModelFieldId::Kind,
+    SingleTcModelFieldId::Qlen,
+    SingleTcModelFieldId::BacklogBytes,
+    SingleTcModelFieldId::BytesPerSec,
+    SingleTcModelFieldId::PacketsPerSec,
+    SingleTcModelFieldId::DropsPerSec,
+    SingleTcModelFieldId::RequeuesPerSec,
+    SingleTcModelFieldId::OverlimitsPerSec,
+];
+
+#[derive(Default, Clone)]
+pub struct SystemTc;
+
+impl SystemTab for SystemTc {
+    fn get_titles(&self) -> ColumnTitles {
+        ColumnTitles {
+            titles: TC_FIELDS
+                .iter()
+                .map(|field_id| {
+                    ViewItem::from_default(field_id.clone())
+                        .config
+                        .render_title()
+                })
+                .collect(),
+            pinned_titles: 1,
+        }
+    }
+
+    fn get_rows(&self, state: &SystemState, offset: Option<usize>) -> Vec<(StyledString, String)> {
+        let tc_model = state.tc.borrow();
+        let qdiscs = match tc_model.as_ref() {
+            Some(tc_model) => &tc_model.tc,
+            None => return Vec::new(),
+        };
+        qdiscs
+            .iter()
+            .filter(|qdisc| match &state.filter_info {
+                Some((_, filter)) => qdisc.path.contains(filter),
+                None => true,
+            })
+            .map(|qdisc| {
+                (
+                    TC_FIELDS[..1]
+                        .iter()
+                        .chain(TC_FIELDS.iter().skip(offset.unwrap_or(0) + 1))
+                        .fold(StyledString::new(), |mut line, field_id| {
+                            line.append(ViewItem::from_default(field_id.clone()).render(qdisc));
+                            line.append_plain(" ");
+                            line
+                        }),
+                    qdisc.path.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
 /// Renders corresponding Fields From BtrfsModel.
 type BtrfsViewItem = ViewItem<model::BtrfsModelFieldId>;
 
@@ -304,12 +777,6 @@ pub struct SystemBtrfs {
     pub view_items: Vec<BtrfsViewItem>,
 }
 
-impl SystemBtrfs {
-    fn new(view_items: Vec<BtrfsViewItem>) -> Self {
-        Self { view_items }
-    }
-}
-
 impl SystemTab for SystemBtrfs {
     fn get_titles(&self) -> ColumnTitles {
         ColumnTitles {
@@ -321,6 +788,18 @@ impl SystemTab for SystemBtrfs {
     }
 
     fn get_rows(&self, state: &SystemState, _offset: Option<usize>) -> Vec<(StyledString, String)> {
+        let mut rows = self.get_subvolume_rows(state);
+        rows.extend(get_btrfs_device_rows(state));
+        rows
+    }
+}
+
+impl SystemBtrfs {
+    fn new(view_items: Vec<BtrfsViewItem>) -> Self {
+        Self { view_items }
+    }
+
+    fn get_subvolume_rows(&self, state: &SystemState) -> Vec<(StyledString, String)> {
         if let Some(btrfs_model) = state.get_model().btrfs.as_ref() {
             let mut subvolumes: Vec<&BtrfsModel> = btrfs_model.values().collect();
 
@@ -362,6 +841,43 @@ impl SystemTab for SystemBtrfs {
     }
 }
 
+/// Devices of the mounted btrfs filesystems, below the subvolumes under
+/// their own header. Non-zero corruption counters are highlighted.
+fn get_btrfs_device_rows(state: &SystemState) -> Vec<(StyledString, String)> {
+    let model = state.get_model();
+    let devices = match model.btrfs_devices.as_ref() {
+        Some(devices) if !devices.is_empty() => devices,
+        _ => return Vec::new(),
+    };
+    let header = enum_iterator::all::<BtrfsDeviceModelFieldId>().fold(
+        StyledString::new(),
+        |mut line, field_id| {
+            line.append_styled(
+                ViewItem::from_default(field_id).config.render_title(),
+                cursive::theme::Effect::Bold,
+            );
+            line.append_plain(" ");
+            line
+        },
+    );
+    std::iter::once((StyledString::new(), String::new()))
+        .chain(std::iter::once((header, String::new())))
+        .chain(devices.iter().map(|(path, device)| {
+            (
+                enum_iterator::all::<BtrfsDeviceModelFieldId>().fold(
+                    StyledString::new(),
+                    |mut line, field_id| {
+                        line.append(ViewItem::from_default(field_id).render(device));
+                        line.append_plain(" ");
+                        line
+                    },
+                ),
+                path.clone(),
+            )
+        }))
+        .collect()
+}
+
 pub mod default_tabs {
     use model::BtrfsModelFieldId::DiskBytes;
     use model::BtrfsModelFieldId::DiskFraction;
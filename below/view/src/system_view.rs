@@ -27,13 +27,26 @@ use cursive::views::ViewRef;
 use cursive::Cursive;
 use model::system::SystemModel;
 use model::BtrfsModelFieldId;
+use model::CollectionModelFieldId;
 use model::KsmModelFieldId;
+use model::LoadAvgModelFieldId;
 use model::MemoryModelFieldId;
+use model::NetworkModel;
+use model::PerfCounterModelFieldId;
+use model::ResctrlModel;
+use model::ResctrlMonGroupModelFieldId;
 use model::SingleCpuModelFieldId;
 use model::SingleDiskModelFieldId;
+use model::SingleNetModelFieldId;
+use model::SingleSchedModelFieldId;
 use model::SingleSlabModelFieldId;
+use model::SingleSoftirqModelFieldId;
+use model::SingleTcModelFieldId;
+use model::SockStatModelFieldId;
+use model::TcModel;
 use model::VmModelFieldId;
 
+use crate::inspect_popup;
 use crate::stats_view::ColumnTitles;
 use crate::stats_view::StateCommon;
 use crate::stats_view::StatsView;
@@ -52,6 +65,13 @@ pub struct SystemState {
     pub filter_info: Option<(SystemStateFieldId, String)>,
     pub collapsed_disk: HashSet<String>,
     pub model: Rc<RefCell<SystemModel>>,
+    /// Interfaces live in the network model, shared for the Iface and
+    /// Sockets tabs
+    pub network: Rc<RefCell<NetworkModel>>,
+    /// Resctrl groups, shared for the Resctrl tab
+    pub resctrl: Rc<RefCell<Option<ResctrlModel>>>,
+    /// Qdiscs, shared for the Tc tab
+    pub tc: Rc<RefCell<Option<TcModel>>>,
     pub sort_order: Option<SystemStateFieldId>,
     pub sort_tags: HashMap<String, default_tabs::SystemTabs>,
     pub reverse: bool,
@@ -60,24 +80,42 @@ pub struct SystemState {
 #[derive(PartialEq)]
 pub enum SystemStateFieldId {
     Disk(SingleDiskModelFieldId),
+    Iface(SingleNetModelFieldId),
+    Sockets(SockStatModelFieldId),
     Btrfs(BtrfsModelFieldId),
     Cpu(SingleCpuModelFieldId),
+    Softirq(SingleSoftirqModelFieldId),
+    Sched(SingleSchedModelFieldId),
     Mem(MemoryModelFieldId),
     Vm(VmModelFieldId),
+    Load(LoadAvgModelFieldId),
     Slab(SingleSlabModelFieldId),
     Ksm(KsmModelFieldId),
+    Perf(PerfCounterModelFieldId),
+    Resctrl(ResctrlMonGroupModelFieldId),
+    Tc(SingleTcModelFieldId),
+    Below(CollectionModelFieldId),
 }
 
 impl std::string::ToString for SystemStateFieldId {
     fn to_string(&self) -> String {
         match self {
             Self::Disk(field) => field.to_string(),
+            Self::Iface(field) => field.to_string(),
+            Self::Sockets(field) => field.to_string(),
             Self::Btrfs(field) => field.to_string(),
             Self::Cpu(field) => field.to_string(),
+            Self::Softirq(field) => field.to_string(),
+            Self::Sched(field) => field.to_string(),
             Self::Mem(field) => field.to_string(),
             Self::Vm(field) => field.to_string(),
+            Self::Load(field) => field.to_string(),
             Self::Slab(field) => field.to_string(),
             Self::Ksm(field) => field.to_string(),
+            Self::Perf(field) => field.to_string(),
+            Self::Resctrl(field) => field.to_string(),
+            Self::Tc(field) => field.to_string(),
+            Self::Below(field) => field.to_string(),
         }
     }
 }
@@ -117,19 +155,28 @@ impl StateCommon for SystemState {
                 )
             }
             "CPU" => SystemStateFieldId::Cpu(SingleCpuModelFieldId::Idx),
+            "Softirq" => SystemStateFieldId::Softirq(SingleSoftirqModelFieldId::Idx),
+            "Sched" => SystemStateFieldId::Sched(SingleSchedModelFieldId::Idx),
             "Disk" => SystemStateFieldId::Disk(SingleDiskModelFieldId::Name),
+            "Iface" => SystemStateFieldId::Iface(SingleNetModelFieldId::Interface),
+            "Sockets" => SystemStateFieldId::Sockets(SockStatModelFieldId::SocketsUsed),
             // tabs Mem and Vm have two columns 'Field' and 'Value'. 'Field' contains
             // a list of all the FieldIds in MemoryModel and VmModel respectively.
             // the field given to filter_info don't matter for these tabs because
             // they don't use FieldId as column titles/selected col (it isn't used to filter)
             "Mem" => SystemStateFieldId::Mem(MemoryModelFieldId::Total),
             "Vm" => SystemStateFieldId::Vm(VmModelFieldId::PgpginPerSec),
+            "Load" => SystemStateFieldId::Load(LoadAvgModelFieldId::Load1),
             "Slab" => SystemStateFieldId::Slab(
                 enum_iterator::all::<SingleSlabModelFieldId>()
                     .nth(idx)
                     .expect("Tag out of range"),
             ),
             "Ksm" => SystemStateFieldId::Ksm(KsmModelFieldId::FullScans),
+            "Perf" => SystemStateFieldId::Perf(PerfCounterModelFieldId::MemBwBytesPerSec),
+            "Resctrl" => SystemStateFieldId::Resctrl(ResctrlMonGroupModelFieldId::FullPath),
+            "Tc" => SystemStateFieldId::Tc(SingleTcModelFieldId::Path),
+            "Below" => SystemStateFieldId::Below(CollectionModelFieldId::TotalUsecs),
             _ => panic!("bug: got unsupported tab {}", tab),
         }
     }
@@ -179,6 +226,12 @@ impl StateCommon for SystemState {
         }
     }
 
+    fn sort_strings() -> Vec<String> {
+        enum_iterator::all::<BtrfsModelFieldId>()
+            .map(|field_id| field_id.to_string())
+            .collect()
+    }
+
     fn get_model(&self) -> Ref<Self::ModelType> {
         self.model.borrow()
     }
@@ -205,12 +258,21 @@ impl StateCommon for SystemState {
 
 pub enum SystemView {
     Cpu(SystemCpu),
+    Softirq(SystemSoftirq),
+    Sched(SystemSched),
     Mem(SystemMem),
     Vm(SystemVm),
+    Load(SystemLoad),
     Slab(SystemSlab),
     Ksm(SystemKsm),
+    Perf(SystemPerf),
     Disk(SystemDisk),
+    Iface(SystemIface),
+    Sockets(SystemSockets),
     Btrfs(SystemBtrfs),
+    Resctrl(SystemResctrl),
+    Tc(SystemTc),
+    Below(SystemBelow),
 }
 
 impl SystemView {
@@ -235,30 +297,52 @@ impl SystemView {
 
         let tabs = vec![
             "CPU".into(),
+            "Softirq".into(),
+            "Sched".into(),
             "Mem".into(),
             "Vm".into(),
+            "Load".into(),
             "Slab".into(),
             "Ksm".into(),
+            "Perf".into(),
             "Disk".into(),
+            "Iface".into(),
+            "Sockets".into(),
             "Btrfs".into(),
+            "Resctrl".into(),
+            "Tc".into(),
+            "Below".into(),
         ];
         let mut tabs_map: HashMap<String, SystemView> = HashMap::new();
         tabs_map.insert("CPU".into(), SystemView::Cpu(Default::default()));
+        tabs_map.insert("Softirq".into(), SystemView::Softirq(Default::default()));
+        tabs_map.insert("Sched".into(), SystemView::Sched(Default::default()));
         tabs_map.insert("Mem".into(), SystemView::Mem(Default::default()));
         tabs_map.insert("Vm".into(), SystemView::Vm(Default::default()));
+        tabs_map.insert("Load".into(), SystemView::Load(Default::default()));
         tabs_map.insert("Slab".into(), SystemView::Slab(Default::default()));
         tabs_map.insert("Ksm".into(), SystemView::Ksm(Default::default()));
+        tabs_map.insert("Perf".into(), SystemView::Perf(Default::default()));
         tabs_map.insert("Disk".into(), SystemView::Disk(Default::default()));
+        tabs_map.insert("Iface".into(), SystemView::Iface(Default::default()));
+        tabs_map.insert("Sockets".into(), SystemView::Sockets(Default::default()));
         tabs_map.insert("Btrfs".into(), SystemView::Btrfs(Default::default()));
+        tabs_map.insert("Resctrl".into(), SystemView::Resctrl(Default::default()));
+        tabs_map.insert("Tc".into(), SystemView::Tc(Default::default()));
+        tabs_map.insert("Below".into(), SystemView::Below(Default::default()));
         let user_data = c
             .user_data::<ViewState>()
             .expect("No data stored in Cursive Object!");
+        let mut state = SystemState::new(user_data.system.clone());
+        state.network = user_data.network.clone();
+        state.resctrl = user_data.resctrl.clone();
+        state.tc = user_data.tc.clone();
         StatsView::new(
             "system",
             tabs,
             tabs_map,
             list,
-            SystemState::new(user_data.system.clone()),
+            state,
             user_data.event_controllers.clone(),
             user_data.cmd_controllers.clone(),
         )
@@ -277,12 +361,21 @@ impl SystemView {
     fn get_inner(&self) -> Box<dyn SystemTab> {
         match self {
             Self::Cpu(inner) => Box::new(inner.clone()),
+            Self::Softirq(inner) => Box::new(inner.clone()),
+            Self::Sched(inner) => Box::new(inner.clone()),
             Self::Mem(inner) => Box::new(inner.clone()),
             Self::Vm(inner) => Box::new(inner.clone()),
+            Self::Load(inner) => Box::new(inner.clone()),
             Self::Slab(inner) => Box::new(inner.clone()),
             Self::Ksm(inner) => Box::new(inner.clone()),
+            Self::Perf(inner) => Box::new(inner.clone()),
             Self::Disk(inner) => Box::new(inner.clone()),
+            Self::Iface(inner) => Box::new(inner.clone()),
+            Self::Sockets(inner) => Box::new(inner.clone()),
             Self::Btrfs(inner) => Box::new(inner.clone()),
+            Self::Resctrl(inner) => Box::new(inner.clone()),
+            Self::Tc(inner) => Box::new(inner.clone()),
+            Self::Below(inner) => Box::new(inner.clone()),
         }
     }
 }
@@ -312,4 +405,20 @@ impl ViewBridge for SystemView {
     ) -> String {
         selected_key.clone()
     }
+
+    fn inspect(
+        state: &Self::StateType,
+        selected_key: &String,
+        current_tab: &str,
+    ) -> Option<(String, String)> {
+        match current_tab {
+            "Iface" => state
+                .network
+                .borrow()
+                .interfaces
+                .get(selected_key)
+                .map(|snm| (selected_key.clone(), inspect_popup::to_json(snm))),
+            _ => None,
+        }
+    }
 }
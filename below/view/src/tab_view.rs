@@ -14,6 +14,7 @@
 
 use anyhow::bail;
 use anyhow::Result;
+use cursive::theme::ColorStyle;
 use cursive::theme::Effect;
 use cursive::vec::Vec2;
 use cursive::Printer;
@@ -51,8 +52,10 @@ impl View for TabView {
 
             if idx == self.current_selected {
                 let trimed = &content.trim_end();
-                printer.with_effects(Effect::Bold | Effect::Underline, |printer| {
-                    printer.print((current_offset, 0), trimed);
+                printer.with_color(ColorStyle::front(crate::theme::current().tab), |printer| {
+                    printer.with_effects(Effect::Bold | Effect::Underline, |printer| {
+                        printer.print((current_offset, 0), trimed);
+                    });
                 });
                 printer.print_hline(
                     (current_offset + trimed.len(), 0),
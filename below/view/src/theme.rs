@@ -0,0 +1,193 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Colors of the view. The theme is picked by the active view profile (see
+//! `apply_profile`) and kept here, as most of the view renders styled
+//! strings without access to the Cursive object.
+
+use std::sync::RwLock;
+
+use cursive::theme::BaseColor;
+use cursive::theme::Color;
+
+use crate::viewrc::ViewProfile;
+
+/// Colors the view uses on top of the cursive palette
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewTheme {
+    /// Background of the selected line
+    pub highlight: Color,
+    /// Text of the selected line
+    pub highlight_text: Color,
+    /// Conditions worth a look, e.g. the LITE marker
+    pub warning: Color,
+    /// Values above their threshold and firing notifications
+    pub critical: Color,
+    /// Lines worth telling apart, e.g. recreated cgroups
+    pub info: Color,
+    /// Text of the selected tab
+    pub tab: Color,
+}
+
+const DEFAULT_THEME: ViewTheme = ViewTheme {
+    highlight: Color::Dark(BaseColor::Cyan),
+    highlight_text: Color::Dark(BaseColor::Black),
+    warning: Color::Light(BaseColor::Yellow),
+    critical: Color::Light(BaseColor::Red),
+    info: Color::Light(BaseColor::Green),
+    tab: Color::TerminalDefault,
+};
+
+/// Themes selected by name with the `theme` setting
+pub const BUILTIN_THEMES: &[(&str, ViewTheme)] = &[
+    ("default", DEFAULT_THEME),
+    // Light colors are barely readable on a light background
+    (
+        "light",
+        ViewTheme {
+            highlight: Color::Dark(BaseColor::Blue),
+            highlight_text: Color::Light(BaseColor::White),
+            warning: Color::Dark(BaseColor::Yellow),
+            critical: Color::Dark(BaseColor::Red),
+            info: Color::Dark(BaseColor::Green),
+            tab: Color::Dark(BaseColor::Blue),
+        },
+    ),
+    (
+        "solarized-dark",
+        ViewTheme {
+            highlight: Color::Rgb(0x2a, 0xa1, 0x98),
+            highlight_text: Color::Rgb(0x00, 0x2b, 0x36),
+            warning: Color::Rgb(0xb5, 0x89, 0x00),
+            critical: Color::Rgb(0xdc, 0x32, 0x2f),
+            info: Color::Rgb(0x85, 0x99, 0x00),
+            tab: Color::Rgb(0x26, 0x8b, 0xd2),
+        },
+    ),
+    (
+        "solarized-light",
+        ViewTheme {
+            highlight: Color::Rgb(0x26, 0x8b, 0xd2),
+            highlight_text: Color::Rgb(0xfd, 0xf6, 0xe3),
+            warning: Color::Rgb(0xcb, 0x4b, 0x16),
+            critical: Color::Rgb(0xdc, 0x32, 0x2f),
+            info: Color::Rgb(0x85, 0x99, 0x00),
+            tab: Color::Rgb(0x6c, 0x71, 0xc4),
+        },
+    ),
+];
+
+static CURRENT: RwLock<ViewTheme> = RwLock::new(DEFAULT_THEME);
+
+/// The theme of the active view profile
+pub fn current() -> ViewTheme {
+    *CURRENT.read().unwrap()
+}
+
+pub(crate) fn set_current(theme: ViewTheme) {
+    *CURRENT.write().unwrap() = theme;
+}
+
+/// Whether the terminal announces 24 bit color support in $COLORTERM, as
+/// most terminals that have it do
+pub fn truecolor_supported() -> bool {
+    std::env::var("COLORTERM").map_or(false, |colorterm| {
+        colorterm == "truecolor" || colorterm == "24bit"
+    })
+}
+
+/// Parse a color of belowrc: a name, e.g. "cyan" or "light red", "#rrggbb"
+/// or "color<n>" for color n of the 256 color palette
+pub fn parse_color(color: &str) -> Option<Color> {
+    if let Some(n) = color.strip_prefix("color") {
+        return n.parse().ok().map(Color::from_256colors);
+    }
+    if let Some(hex) = color.strip_prefix('#') {
+        if !matches!(hex.len(), 3 | 6) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+    }
+    Color::parse(color)
+}
+
+/// The closest color of the 256 color palette to a 24 bit one
+fn to_256_colors(color: Color) -> Color {
+    match color {
+        Color::Rgb(r, g, b) => {
+            let scale = |v: u8| ((v as u16 * 5 + 127) / 255) as u8;
+            Color::RgbLowRes(scale(r), scale(g), scale(b))
+        }
+        color => color,
+    }
+}
+
+impl ViewTheme {
+    pub fn builtin(name: &str) -> Option<Self> {
+        BUILTIN_THEMES
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, theme)| *theme)
+    }
+
+    /// Theme of the view profile `settings`: its built-in theme with the
+    /// colors it sets on top. 24 bit colors are approximated unless
+    /// `truecolor` is set. Invalid settings are left at the theme's colors
+    /// and returned as errors.
+    pub fn from_profile(settings: &ViewProfile, truecolor: bool) -> (Self, Vec<String>) {
+        let mut errors = Vec::new();
+        let mut theme = match settings.theme.as_deref() {
+            Some(name) => Self::builtin(name).unwrap_or_else(|| {
+                errors.push(format!(
+                    "Unknown theme \"{}\", available themes: [{}]",
+                    name,
+                    BUILTIN_THEMES
+                        .iter()
+                        .map(|(builtin, _)| *builtin)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+                DEFAULT_THEME
+            }),
+            None => DEFAULT_THEME,
+        };
+        for (color, setting) in [
+            (&mut theme.highlight, &settings.highlight_color),
+            (&mut theme.highlight_text, &settings.highlight_text_color),
+            (&mut theme.warning, &settings.warning_color),
+            (&mut theme.critical, &settings.critical_color),
+            (&mut theme.info, &settings.info_color),
+            (&mut theme.tab, &settings.tab_color),
+        ] {
+            if let Some(setting) = setting {
+                match parse_color(setting) {
+                    Some(parsed) => *color = parsed,
+                    None => errors.push(format!("Invalid color in view profile: {}", setting)),
+                }
+            }
+        }
+        if !truecolor {
+            for color in [
+                &mut theme.highlight,
+                &mut theme.highlight_text,
+                &mut theme.warning,
+                &mut theme.critical,
+                &mut theme.info,
+                &mut theme.tab,
+            ] {
+                *color = to_256_colors(*color);
+            }
+        }
+        (theme, errors)
+    }
+}
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
+
 use serde::Deserialize;
 
 use super::get_belowrc_filename;
@@ -34,25 +36,110 @@ pub struct SummaryViewExtraRowItem {
     pub field_id: String,
 }
 
+/// Condition watched in live mode, see `notify::Notifier`
+#[derive(Default, Deserialize)]
+pub struct NotifyRule {
+    // Shown in the status bar and log, defaults to the rule itself
+    pub name: Option<String>,
+    // Field id, comparison and threshold, e.g. "system.cpu.usage_pct > 90"
+    pub rule: String,
+    // If we want to ring the terminal bell when the rule fires. If this
+    // field is not set, it will be treated as false
+    pub bell: Option<bool>,
+}
+
 #[derive(Default, Deserialize)]
 pub struct SummaryViewExtraRow {
     pub title: Option<String>,
     pub items: Vec<SummaryViewExtraRowItem>,
 }
 
-/// Runtime configuration on the below view.
-#[derive(Default, Deserialize)]
-pub struct ViewRc {
+/// Settings that can differ between profiles. Set in [view] they apply
+/// unless the active profile overrides them.
+#[derive(Clone, Default, Deserialize)]
+pub struct ViewProfile {
     // The default front view. If this field is not set, we will use cgroup
     // view as front view
     pub default_view: Option<DefaultFrontView>,
+    // Field ids of the columns of the process General tab, e.g.
+    // "cpu.usage_pct". If this field is not set, the default columns are shown
+    pub process_columns: Option<Vec<String>>,
+    // Built-in color theme, e.g. "light" for terminals with a light
+    // background. If this field is not set, the default theme is used
+    pub theme: Option<String>,
+    // Background color of the selected line, e.g. "cyan" or "#005f87"
+    pub highlight_color: Option<String>,
+    // Text color of the selected line
+    pub highlight_text_color: Option<String>,
+    // Color of conditions worth a look
+    pub warning_color: Option<String>,
+    // Color of values above their threshold and firing notifications
+    pub critical_color: Option<String>,
+    // Color of recreated cgroups
+    pub info_color: Option<String>,
+    // Text color of the selected tab
+    pub tab_color: Option<String>,
+    // Seconds between samples in live mode, unless set on the command line
+    pub refresh_interval_s: Option<u64>,
+}
+
+impl ViewProfile {
+    /// Settings of this profile, falling back to `base` for unset ones
+    fn or(&self, base: &ViewProfile) -> ViewProfile {
+        ViewProfile {
+            default_view: self.default_view.clone().or(base.default_view.clone()),
+            process_columns: self
+                .process_columns
+                .clone()
+                .or(base.process_columns.clone()),
+            theme: self.theme.clone().or(base.theme.clone()),
+            highlight_color: self
+                .highlight_color
+                .clone()
+                .or(base.highlight_color.clone()),
+            highlight_text_color: self
+                .highlight_text_color
+                .clone()
+                .or(base.highlight_text_color.clone()),
+            warning_color: self.warning_color.clone().or(base.warning_color.clone()),
+            critical_color: self.critical_color.clone().or(base.critical_color.clone()),
+            info_color: self.info_color.clone().or(base.info_color.clone()),
+            tab_color: self.tab_color.clone().or(base.tab_color.clone()),
+            refresh_interval_s: self.refresh_interval_s.or(base.refresh_interval_s),
+        }
+    }
+}
+
+/// Runtime configuration on the below view.
+#[derive(Default, Deserialize)]
+pub struct ViewRc {
+    #[serde(flatten)]
+    pub base: ViewProfile,
+    // Named profiles in [view.profile.<name>] sections, selected with
+    // --profile or the profile command
+    #[serde(default)]
+    pub profile: BTreeMap<String, ViewProfile>,
+    #[serde(skip)]
+    pub active_profile: Option<String>,
     // If we want to collapse all top level cgroups. If this field is not set,
     // it will be treated as false
     pub collapse_cgroups: Option<bool>,
+    // Collapse all cgroups at this depth by default. Takes precedence over
+    // collapse_cgroups, which is the same as a depth of 1.
+    pub cgroup_fold_depth: Option<u32>,
+    // If we want to save cgroup fold state and restore it in the next
+    // session. If this field is not set, it will be treated as false
+    pub persist_cgroup_folds: Option<bool>,
     // Overrides cgroup name column width.
     pub cgroup_name_width: Option<usize>,
+    // If we want to show bar meters for per core CPU, memory, swap and
+    // pressure at the top of the summary view. If this field is not set, it
+    // will be treated as false
+    pub summary_meters: Option<bool>,
     // Extra rows to add in the summary view.
     pub summary_view_extra_rows: Option<Vec<SummaryViewExtraRow>>,
+    // Rules in [[view.notify]] sections to watch in live mode
+    pub notify: Option<Vec<NotifyRule>>,
 }
 
 impl ViewRc {
@@ -62,32 +149,65 @@ impl ViewRc {
     /// parse error string.
     pub fn new() -> (ViewRc, Option<String>) {
         match std::fs::read_to_string(get_belowrc_filename()) {
-            Ok(belowrc_str) => match belowrc_str.parse::<toml::value::Value>() {
-                // We get the belowrc file, parsing the [view] section
-                Ok(belowrc_val) => {
-                    if let Some(viewrc_val) = belowrc_val.get(get_belowrc_view_section_key()) {
-                        // Got the [view] section, let's see if we can deserialize it to ViewRc
-                        match viewrc_val.to_owned().try_into::<ViewRc>() {
-                            Ok(viewrc) => (viewrc, None),
-                            Err(e) => (
-                                Default::default(),
-                                Some(format!(
-                                    "Failed to parse belowrc::{}: {}",
-                                    get_belowrc_view_section_key(),
-                                    e
-                                )),
-                            ),
-                        }
-                    } else {
-                        Default::default()
+            Ok(belowrc_str) => Self::from_belowrc_str(&belowrc_str),
+            _ => (Default::default(), None),
+        }
+    }
+
+    /// Same as `new`, with the belowrc content given
+    pub fn from_belowrc_str(belowrc_str: &str) -> (ViewRc, Option<String>) {
+        match belowrc_str.parse::<toml::value::Value>() {
+            // We get the belowrc file, parsing the [view] section
+            Ok(belowrc_val) => {
+                if let Some(viewrc_val) = belowrc_val.get(get_belowrc_view_section_key()) {
+                    // Got the [view] section, let's see if we can deserialize it to ViewRc
+                    match viewrc_val.to_owned().try_into::<ViewRc>() {
+                        Ok(viewrc) => (viewrc, None),
+                        Err(e) => (
+                            Default::default(),
+                            Some(format!(
+                                "Failed to parse belowrc::{}: {}",
+                                get_belowrc_view_section_key(),
+                                e
+                            )),
+                        ),
                     }
+                } else {
+                    Default::default()
                 }
-                Err(e) => (
-                    Default::default(),
-                    Some(format!("Failed to parse belowrc: {}", e)),
-                ),
-            },
-            _ => (Default::default(), None),
+            }
+            Err(e) => (
+                Default::default(),
+                Some(format!("Failed to parse belowrc: {}", e)),
+            ),
+        }
+    }
+
+    /// Switch to the profile called `name`, or back to the [view] settings
+    /// with None
+    pub fn set_active_profile(&mut self, name: Option<&str>) -> anyhow::Result<()> {
+        if let Some(name) = name {
+            if !self.profile.contains_key(name) {
+                anyhow::bail!(
+                    "Unknown view profile \"{}\", available profiles: [{}]",
+                    name,
+                    self.profile.keys().cloned().collect::<Vec<_>>().join(", ")
+                );
+            }
+        }
+        self.active_profile = name.map(str::to_owned);
+        Ok(())
+    }
+
+    /// Settings of the active profile
+    pub fn settings(&self) -> ViewProfile {
+        match self
+            .active_profile
+            .as_ref()
+            .and_then(|name| self.profile.get(name))
+        {
+            Some(profile) => profile.or(&self.base),
+            None => self.base.clone(),
         }
     }
 }